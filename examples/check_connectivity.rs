@@ -0,0 +1,26 @@
+// Checks credentials and the network path against Alpaca's test feed, e.g. before the open.
+//
+//     APCA_API_KEY_ID=... APCA_API_SECRET_KEY=... cargo run --example check_connectivity
+
+use std::{env, error::Error};
+use trading_client::{
+    alpaca::AlpacaClient,
+    connectivity::{check_test_feed, ConnectivityConfig},
+    datastructures::config::Config,
+};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let config = Config::builder()
+        .alpaca_api_key(env::var("APCA_API_KEY_ID")?)
+        .alpaca_secret_key(env::var("APCA_API_SECRET_KEY")?)
+        .build()?;
+    let client = AlpacaClient::new(&config)?;
+
+    let report = check_test_feed(&client, &ConnectivityConfig::default()).await;
+    println!("{}", report);
+    if !report.is_ok() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,182 @@
+// Skeleton of a crypto market maker: quotes both sides around the mid and leans against its inventory.
+// The spread and sizes are placeholders, not a profitable configuration.
+//
+//     APCA_API_KEY_ID=... APCA_API_SECRET_KEY=... cargo run --example crypto_market_maker
+
+use std::{env, error::Error, time::Duration};
+use trading_client::{
+    alpaca::AlpacaClient,
+    datastructures::{
+        client::{FeedType, SubscriptionParamsBuilder},
+        config::Config,
+        event::EventType,
+        order::{Order, OrderResponse, OrderSide, OrderType, TimeInForce},
+    },
+    middleware::{ClientBuilder, RiskManager},
+    risk::RiskLimits,
+    strategy::{Fill, RunnerConfig, Strategy, StrategyContext, StrategyRunner},
+    stream::{EventStream, StreamConfig},
+    CancellationToken,
+};
+
+const SYMBOL: &str = "BTC/USD";
+
+/// Keeps one bid and one ask working, `half_spread` either side of the mid, and replaces them when the mid
+/// moves by more than `requote`. Quotes shift against inventory so fills pull the position back towards flat.
+/// Crypto cannot be sold short, so no ask is shown without inventory.
+struct MarketMaker {
+    half_spread: f64,
+    requote: f64,
+    size: f64,
+    max_inventory: f64,
+    /// Shift of both quotes per unit of inventory, as a fraction of the mid.
+    skew: f64,
+    quoted_mid: Option<f64>,
+    /// Client order ids of the working bid and ask.
+    working: Vec<String>,
+    next_id: u64,
+}
+
+impl MarketMaker {
+    fn quote(&mut self, side: OrderSide, price: f64, quantity: f64, context: &mut StrategyContext) {
+        self.next_id += 1;
+        let client_order_id = format!("mm-{}-{}", std::process::id(), self.next_id);
+        let order = Order::builder()
+            .symbol(SYMBOL)
+            .quantity(quantity)
+            .side(side)
+            .order_type(OrderType::Limit)
+            .limit_price((price * 100.0).round() / 100.0)
+            .time_in_force(TimeInForce::Gtc)
+            .client_order_id(client_order_id.clone())
+            .strategy("market_maker")
+            .build();
+        match order {
+            Ok(order) => {
+                context.submit(order);
+                self.working.push(client_order_id);
+            }
+            Err(e) => eprintln!("Invalid order: {}", e),
+        }
+    }
+}
+
+impl Strategy for MarketMaker {
+    fn on_event(&mut self, event: &EventType, context: &mut StrategyContext) {
+        let EventType::Quote {
+            symbol,
+            bid_price,
+            ask_price,
+            ..
+        } = event
+        else {
+            return;
+        };
+        if symbol != SYMBOL || *bid_price <= 0.0 || *ask_price <= *bid_price {
+            return;
+        }
+        let mid = (bid_price + ask_price) / 2.0;
+        if self
+            .quoted_mid
+            .is_some_and(|quoted| (mid - quoted).abs() / quoted < self.requote)
+        {
+            return;
+        }
+
+        for client_order_id in self.working.drain(..) {
+            context.cancel(client_order_id);
+        }
+        self.quoted_mid = Some(mid);
+
+        let inventory = context.position(SYMBOL);
+        let center = mid * (1.0 - self.skew * inventory);
+        if inventory + self.size <= self.max_inventory {
+            self.quote(
+                OrderSide::Buy,
+                center * (1.0 - self.half_spread),
+                self.size,
+                context,
+            );
+        }
+        if inventory > 0.0 {
+            let quantity = self.size.min(inventory);
+            self.quote(
+                OrderSide::Sell,
+                center * (1.0 + self.half_spread),
+                quantity,
+                context,
+            );
+        }
+    }
+
+    fn on_fill(&mut self, fill: &Fill, context: &mut StrategyContext) {
+        println!(
+            "Filled {:?} {} at {}, inventory {}",
+            fill.side,
+            fill.quantity,
+            fill.price,
+            context.position(SYMBOL)
+        );
+        // Requote on the next quote with the new inventory.
+        self.quoted_mid = None;
+    }
+
+    fn on_order_update(&mut self, order: &OrderResponse, _context: &mut StrategyContext) {
+        if order.status.is_terminal() {
+            self.working.retain(|id| *id != order.client_order_id);
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let config = Config::builder()
+        .alpaca_api_key(env::var("APCA_API_KEY_ID")?)
+        .alpaca_secret_key(env::var("APCA_API_SECRET_KEY")?)
+        .build()?;
+    let client = ClientBuilder::new(AlpacaClient::new(&config)?)
+        .with(RiskManager::new(RiskLimits {
+            max_order_notional: Some(5_000.0),
+            max_position: Some(0.1),
+            ..RiskLimits::default()
+        }))
+        .build();
+
+    // Ctrl-C stops the stream and the runner, which cancels both quotes on the way out.
+    let cancellation = CancellationToken::new();
+    tokio::spawn({
+        let cancellation = cancellation.clone();
+        async move {
+            let _ = tokio::signal::ctrl_c().await;
+            cancellation.cancel();
+        }
+    });
+
+    let params = SubscriptionParamsBuilder::new()
+        .feed_type(FeedType::Crypto)
+        .quotes(&[SYMBOL])
+        .build();
+    let stream_config = StreamConfig::builder()
+        .cancellation_token(cancellation.clone())
+        .build()?;
+    let events = EventStream::connect(client.clone(), params, stream_config).await?;
+    let runner_config = RunnerConfig {
+        order_poll_interval: Duration::from_millis(500),
+        cancellation,
+        ..RunnerConfig::default()
+    };
+
+    let mut strategy = MarketMaker {
+        half_spread: 0.001,
+        requote: 0.0005,
+        size: 0.001,
+        max_inventory: 0.01,
+        skew: 0.5,
+        quoted_mid: None,
+        working: vec![],
+        next_id: 0,
+    };
+    StrategyRunner::new(client, runner_config)
+        .run(&mut strategy, events)
+        .await
+}
@@ -0,0 +1,185 @@
+// Buys stocks that spike right after a news headline and sells them a few minutes later.
+//
+//     APCA_API_KEY_ID=... APCA_API_SECRET_KEY=... cargo run --example news_scalper
+
+use futures_util::stream;
+use std::{collections::HashMap, env, error::Error, time::Duration};
+use trading_client::{
+    alpaca::AlpacaClient,
+    datastructures::{
+        client::{FeedType, SubscriptionParamsBuilder},
+        config::Config,
+        event::EventType,
+        order::{Order, OrderSide, OrderType, TimeInForce},
+    },
+    middleware::{ClientBuilder, RiskManager},
+    risk::RiskLimits,
+    strategy::{Fill, RunnerConfig, Strategy, StrategyContext, StrategyRunner},
+    stream::{EventStream, StreamConfig},
+    time, CancellationToken,
+};
+
+const SYMBOLS: &[&str] = &["AAPL", "AMZN", "META", "MSFT", "NVDA", "TSLA"];
+
+/// A headline waiting for the price to react, or a position entered on one.
+enum Setup {
+    Watching { reference: f64, since: i64 },
+    Holding { since: i64 },
+}
+
+/// After a headline mentioning a watched symbol, buys if the price rises `spike` above the last trade before
+/// the news within `reaction`, then exits after `hold`.
+struct NewsScalper {
+    spike: f64,
+    reaction: Duration,
+    hold: Duration,
+    notional: f64,
+    last_price: HashMap<String, f64>,
+    setups: HashMap<String, Setup>,
+}
+
+impl NewsScalper {
+    fn order(symbol: &str, side: OrderSide, quantity: f64) -> Option<Order> {
+        Order::builder()
+            .symbol(symbol)
+            .quantity(quantity)
+            .side(side)
+            .order_type(OrderType::Market)
+            .time_in_force(TimeInForce::Day)
+            .strategy("news_scalper")
+            .build()
+            .ok()
+    }
+}
+
+impl Strategy for NewsScalper {
+    fn on_event(&mut self, event: &EventType, context: &mut StrategyContext) {
+        let now = event
+            .timestamp()
+            .and_then(time::parse_rfc3339)
+            .unwrap_or_else(time::now_nanos);
+        match event {
+            EventType::News {
+                headline, symbols, ..
+            } => {
+                for symbol in symbols {
+                    let Some(&reference) = self.last_price.get(symbol) else {
+                        continue;
+                    };
+                    if self.setups.contains_key(symbol) {
+                        continue;
+                    }
+                    println!("{}: {}", symbol, headline);
+                    let setup = Setup::Watching {
+                        reference,
+                        since: now,
+                    };
+                    self.setups.insert(symbol.clone(), setup);
+                }
+            }
+            EventType::Trade { symbol, price, .. } => {
+                self.last_price.insert(symbol.clone(), *price);
+                let Some(Setup::Watching { reference, since }) = self.setups.get(symbol) else {
+                    return;
+                };
+                if now - since > self.reaction.as_nanos() as i64 {
+                    self.setups.remove(symbol);
+                } else if *price >= reference * (1.0 + self.spike) {
+                    let quantity = (self.notional / price).floor();
+                    if let Some(order) = Self::order(symbol, OrderSide::Buy, quantity) {
+                        println!("{} spiked to {}, buying {}", symbol, price, quantity);
+                        context.submit(order);
+                        self.setups
+                            .insert(symbol.clone(), Setup::Holding { since: now });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn on_timer(&mut self, context: &mut StrategyContext) {
+        let now = time::now_nanos();
+        let hold = self.hold.as_nanos() as i64;
+        let expired: Vec<String> = self
+            .setups
+            .iter()
+            .filter(|(_, setup)| matches!(setup, Setup::Holding { since } if now - since > hold))
+            .map(|(symbol, _)| symbol.clone())
+            .collect();
+        for symbol in expired {
+            self.setups.remove(&symbol);
+            let position = context.position(&symbol);
+            if let Some(order) = Self::order(&symbol, OrderSide::Sell, position) {
+                println!("Closing {} {}", position, symbol);
+                context.submit(order);
+            }
+        }
+    }
+
+    fn on_fill(&mut self, fill: &Fill, _context: &mut StrategyContext) {
+        println!(
+            "Filled {:?} {} {} at {}",
+            fill.side, fill.quantity, fill.symbol, fill.price
+        );
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let config = Config::builder()
+        .alpaca_api_key(env::var("APCA_API_KEY_ID")?)
+        .alpaca_secret_key(env::var("APCA_API_SECRET_KEY")?)
+        .build()?;
+    let client = ClientBuilder::new(AlpacaClient::new(&config)?)
+        .with(RiskManager::new(RiskLimits {
+            max_order_notional: Some(10_000.0),
+            max_gross_notional: Some(30_000.0),
+            max_daily_loss: Some(1_000.0),
+            ..RiskLimits::default()
+        }))
+        .build();
+
+    // Ctrl-C stops the streams and the runner, which cancels any working order on the way out.
+    let cancellation = CancellationToken::new();
+    tokio::spawn({
+        let cancellation = cancellation.clone();
+        async move {
+            let _ = tokio::signal::ctrl_c().await;
+            cancellation.cancel();
+        }
+    });
+
+    let stream_config = StreamConfig::builder()
+        .cancellation_token(cancellation.clone())
+        .build()?;
+    let news = SubscriptionParamsBuilder::new()
+        .feed_type(FeedType::News)
+        .news(SYMBOLS)
+        .build();
+    let trades = SubscriptionParamsBuilder::new()
+        .feed_type(FeedType::Stocks)
+        .trades(SYMBOLS)
+        .build();
+    let events = stream::select(
+        EventStream::connect(client.clone(), news, stream_config.clone()).await?,
+        EventStream::connect(client.clone(), trades, stream_config).await?,
+    );
+    let runner_config = RunnerConfig {
+        timer_interval: Some(Duration::from_secs(5)),
+        cancellation,
+        ..RunnerConfig::default()
+    };
+
+    let mut strategy = NewsScalper {
+        spike: 0.005,
+        reaction: Duration::from_secs(60),
+        hold: Duration::from_secs(5 * 60),
+        notional: 5_000.0,
+        last_price: HashMap::new(),
+        setups: HashMap::new(),
+    };
+    StrategyRunner::new(client, runner_config)
+        .run(&mut strategy, events)
+        .await
+}
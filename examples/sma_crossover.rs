@@ -0,0 +1,137 @@
+// Moving-average crossover on minute bars, trading a paper account.
+//
+//     APCA_API_KEY_ID=... APCA_API_SECRET_KEY=... cargo run --example sma_crossover
+
+use std::{env, error::Error};
+use trading_client::{
+    alpaca::AlpacaClient,
+    datastructures::{
+        client::{FeedType, SubscriptionParamsBuilder},
+        config::Config,
+        event::EventType,
+        order::{Order, OrderSide, OrderType, TimeInForce},
+    },
+    indicators::Sma,
+    middleware::{ClientBuilder, RiskManager},
+    risk::RiskLimits,
+    strategy::{Fill, RunnerConfig, Strategy, StrategyContext, StrategyRunner},
+    stream::{EventStream, StreamConfig},
+    CancellationToken,
+};
+
+const SYMBOL: &str = "SPY";
+
+/// Holds `quantity` shares while the fast average is above the slow one, and is flat otherwise.
+struct SmaCrossover {
+    fast: Sma,
+    slow: Sma,
+    quantity: f64,
+    /// Whether the fast average was above the slow one on the last bar.
+    above: Option<bool>,
+}
+
+impl Strategy for SmaCrossover {
+    fn on_event(&mut self, event: &EventType, context: &mut StrategyContext) {
+        let EventType::Bar { symbol, close, .. } = event else {
+            return;
+        };
+        if symbol != SYMBOL {
+            return;
+        }
+        let (Some(fast), Some(slow)) = (self.fast.update(*close), self.slow.update(*close)) else {
+            return;
+        };
+
+        let above = fast > slow;
+        let crossed = self.above.is_some_and(|was_above| was_above != above);
+        self.above = Some(above);
+        if !crossed {
+            return;
+        }
+
+        let position = context.position(SYMBOL);
+        let (side, quantity) = if above {
+            (OrderSide::Buy, self.quantity - position)
+        } else {
+            (OrderSide::Sell, position)
+        };
+        if quantity <= 0.0 {
+            return;
+        }
+        println!(
+            "{} crossed {}: {:?} {}",
+            SYMBOL,
+            if above { "up" } else { "down" },
+            side,
+            quantity
+        );
+        let order = Order::builder()
+            .symbol(SYMBOL)
+            .quantity(quantity)
+            .side(side)
+            .order_type(OrderType::Market)
+            .time_in_force(TimeInForce::Day)
+            .strategy("sma_crossover")
+            .build();
+        match order {
+            Ok(order) => context.submit(order),
+            Err(e) => eprintln!("Invalid order: {}", e),
+        }
+    }
+
+    fn on_fill(&mut self, fill: &Fill, _context: &mut StrategyContext) {
+        println!(
+            "Filled {:?} {} {} at {}",
+            fill.side, fill.quantity, fill.symbol, fill.price
+        );
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let config = Config::builder()
+        .alpaca_api_key(env::var("APCA_API_KEY_ID")?)
+        .alpaca_secret_key(env::var("APCA_API_SECRET_KEY")?)
+        .build()?;
+    let client = ClientBuilder::new(AlpacaClient::new(&config)?)
+        .with(RiskManager::new(RiskLimits {
+            max_order_quantity: Some(100.0),
+            max_position: Some(100.0),
+            max_daily_loss: Some(500.0),
+            ..RiskLimits::default()
+        }))
+        .build();
+
+    // Ctrl-C stops the stream and the runner, which cancels any working order on the way out.
+    let cancellation = CancellationToken::new();
+    tokio::spawn({
+        let cancellation = cancellation.clone();
+        async move {
+            let _ = tokio::signal::ctrl_c().await;
+            cancellation.cancel();
+        }
+    });
+
+    let params = SubscriptionParamsBuilder::new()
+        .feed_type(FeedType::Stocks)
+        .bars(&[SYMBOL])
+        .build();
+    let stream_config = StreamConfig::builder()
+        .cancellation_token(cancellation.clone())
+        .build()?;
+    let events = EventStream::connect(client.clone(), params, stream_config).await?;
+    let runner_config = RunnerConfig {
+        cancellation,
+        ..RunnerConfig::default()
+    };
+
+    let mut strategy = SmaCrossover {
+        fast: Sma::new(10),
+        slow: Sma::new(30),
+        quantity: 10.0,
+        above: None,
+    };
+    StrategyRunner::new(client, runner_config)
+        .run(&mut strategy, events)
+        .await
+}
@@ -0,0 +1,107 @@
+use crate::{
+    datastructures::{account::AccountActivity, client::TradingClient},
+    journal::{JournalEvent, OrderJournal},
+    time,
+};
+use std::{collections::HashMap, time::Duration};
+use tokio::{sync::broadcast, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
+
+#[derive(Clone)]
+pub struct ActivityConfig {
+    /// How often the broker is asked for new activity.
+    pub poll_interval: Duration,
+    /// First date to report activity from, as YYYY-MM-DD. `None` starts from today's UTC date, so history
+    /// is not replayed on every start.
+    pub since: Option<String>,
+    /// Publishes each new activity as a `JournalEvent::AccountActivity`, e.g. for webhooks.
+    pub journal: Option<OrderJournal>,
+}
+
+impl Default for ActivityConfig {
+    fn default() -> Self {
+        ActivityConfig {
+            poll_interval: Duration::from_secs(60),
+            since: None,
+            journal: None,
+        }
+    }
+}
+
+/// Polls the broker for deposits, withdrawals, dividends and other non-trading account activity, and passes
+/// each entry on once, to subscribers and to the journal's. Feed them to `PnlTracker::on_activity` to account
+/// for equity changes that are not trading P/L. Stops when dropped.
+pub struct ActivityMonitor {
+    activities: broadcast::Sender<AccountActivity>,
+    task: JoinHandle<()>,
+}
+
+impl ActivityMonitor {
+    pub fn spawn<C>(client: C, config: ActivityConfig, cancel: CancellationToken) -> ActivityMonitor
+    where
+        C: TradingClient + Clone + Send + Sync + 'static,
+    {
+        let activities = broadcast::channel(256).0;
+        let sender = activities.clone();
+        let task = tokio::spawn(async move {
+            let mut since = config.since.clone().unwrap_or_else(|| {
+                time::date(&time::format_rfc3339(time::now_nanos())).unwrap_or_default()
+            });
+            // Ids already passed on, with their dates.
+            let mut seen: HashMap<String, String> = HashMap::new();
+            let mut ticker = tokio::time::interval(config.poll_interval);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    _ = cancel.cancelled() => return,
+                }
+                let polled = client
+                    .get_account_activities(&since)
+                    .await
+                    .map_err(|e| e.to_string());
+                let polled = match polled {
+                    Ok(polled) => polled,
+                    Err(e) => {
+                        tracing::error!(error = %e, "Failed to read account activity");
+                        continue;
+                    }
+                };
+
+                for activity in polled {
+                    if seen.contains_key(&activity.id) {
+                        continue;
+                    }
+                    seen.insert(activity.id.clone(), activity.date.clone());
+                    tracing::info!(
+                        id = %activity.id,
+                        kind = ?activity.kind,
+                        amount = activity.net_amount,
+                        "Account activity"
+                    );
+                    if let Some(journal) = &config.journal {
+                        journal.publish(JournalEvent::AccountActivity(activity.clone()));
+                    }
+                    // Only fails when nobody is subscribed.
+                    let _ = sender.send(activity);
+                }
+                // Later polls start from the newest date seen, which may still get more entries.
+                if let Some(latest) = seen.values().max().cloned() {
+                    since = since.max(latest);
+                    seen.retain(|_, date| *date >= since);
+                }
+            }
+        });
+        ActivityMonitor { activities, task }
+    }
+
+    /// Activities passed on from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<AccountActivity> {
+        self.activities.subscribe()
+    }
+}
+
+impl Drop for ActivityMonitor {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -0,0 +1,419 @@
+use crate::{
+    datastructures::{
+        event::{EventType, MarketSession},
+        market::Bar,
+    },
+    time,
+};
+use futures_util::{stream, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+
+const NANOS_PER_DAY: i64 = 86_400 * 1_000_000_000;
+
+/// Trading session bars are confined to, as an offset from midnight UTC. Must not cross midnight UTC.
+/// E.g. `start` 13:30 and `length` 6.5h is the US regular session during daylight saving time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Session {
+    pub start: Duration,
+    pub length: Duration,
+}
+
+/// Bar built from trades, with the extra fields only trade data can give.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AggregatedBar {
+    #[serde(flatten)]
+    pub bar: Bar,
+    /// Volume-weighted average trade price. The close if every trade had zero volume.
+    pub vwap: f64,
+    pub trade_count: u64,
+    /// End of the bar's interval, which is earlier than `start + interval` for a bar cut off by the session close.
+    pub end: String,
+}
+
+struct Building {
+    start: i64,
+    end: i64,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    volume: f64,
+    notional: f64,
+    trade_count: u64,
+}
+
+impl Building {
+    fn finish(self, symbol: String) -> AggregatedBar {
+        AggregatedBar {
+            bar: Bar {
+                symbol,
+                open: self.open,
+                high: self.high,
+                low: self.low,
+                close: self.close,
+                volume: self.volume,
+                timestamp: time::format_rfc3339(self.start),
+            },
+            vwap: if self.volume > 0.0 {
+                self.notional / self.volume
+            } else {
+                self.close
+            },
+            trade_count: self.trade_count,
+            end: time::format_rfc3339(self.end),
+        }
+    }
+}
+
+/// Builds OHLCV bars of any interval, e.g. 1s, 1m or 5m, from trade events.
+/// Bars are aligned to the session open, or to midnight UTC without a session, and never span two sessions:
+/// the last bar of a session ends at the close even if the interval is cut short, and trades outside the session
+/// are ignored. Only intervals with trades produce a bar.
+///
+/// A bar is emitted once a trade for the same symbol lands in a later interval. Call `flush` periodically so quiet
+/// symbols' bars are not held back, and `finish` at the end of the feed.
+pub struct BarAggregator {
+    interval: i64,
+    session: Option<Session>,
+    extended_hours: bool,
+    building: HashMap<String, Building>,
+    late_trades: u64,
+}
+
+impl BarAggregator {
+    pub fn new(interval: Duration) -> Self {
+        assert!(!interval.is_zero(), "Interval must be greater than zero");
+        BarAggregator {
+            interval: interval.as_nanos() as i64,
+            session: None,
+            extended_hours: true,
+            building: HashMap::new(),
+            late_trades: 0,
+        }
+    }
+
+    pub fn session(mut self, session: Session) -> Self {
+        self.session = Some(session);
+        self
+    }
+
+    /// Whether pre-market and after-hours trades of US equities go into bars. On by default.
+    pub fn extended_hours(mut self, include: bool) -> Self {
+        self.extended_hours = include;
+        self
+    }
+
+    /// Adds a trade. Returns the symbol's previous bar if this trade closed it. Other events are ignored.
+    pub fn update(&mut self, event: &EventType) -> Option<AggregatedBar> {
+        let EventType::Trade {
+            symbol,
+            price,
+            volume,
+            timestamp,
+        } = event
+        else {
+            return None;
+        };
+        if !self.extended_hours && event.is_extended_hours() {
+            return None;
+        }
+        let timestamp = time::parse_rfc3339(timestamp)?;
+        let (start, end) = self.interval_of(timestamp)?;
+
+        let mut completed = None;
+        if let Some(bar) = self.building.get_mut(symbol.as_str()) {
+            if start < bar.start {
+                // The bar it belongs to has already been emitted.
+                self.late_trades += 1;
+                return None;
+            }
+            if start == bar.start {
+                bar.high = bar.high.max(*price);
+                bar.low = bar.low.min(*price);
+                bar.close = *price;
+                bar.volume += volume;
+                bar.notional += price * volume;
+                bar.trade_count += 1;
+                return None;
+            }
+            completed = self
+                .building
+                .remove(symbol.as_str())
+                .map(|bar| bar.finish(symbol.clone()));
+        }
+
+        self.building.insert(
+            symbol.clone(),
+            Building {
+                start,
+                end,
+                open: *price,
+                high: *price,
+                low: *price,
+                close: *price,
+                volume: *volume,
+                notional: price * volume,
+                trade_count: 1,
+            },
+        );
+        completed
+    }
+
+    /// Emits every bar whose interval ended at or before `now`, an RFC 3339 timestamp. Use the feed's latest event
+    /// time in backtests and the wall clock live.
+    pub fn flush(&mut self, now: &str) -> Vec<AggregatedBar> {
+        let Some(now) = time::parse_rfc3339(now) else {
+            return vec![];
+        };
+        let closed: Vec<String> = self
+            .building
+            .iter()
+            .filter(|(_, bar)| bar.end <= now)
+            .map(|(symbol, _)| symbol.clone())
+            .collect();
+        let mut bars: Vec<AggregatedBar> = closed
+            .into_iter()
+            .filter_map(|symbol| {
+                let bar = self.building.remove(&symbol)?;
+                Some(bar.finish(symbol))
+            })
+            .collect();
+        sort(&mut bars);
+        bars
+    }
+
+    /// Emits every bar still being built, complete or not.
+    pub fn finish(&mut self) -> Vec<AggregatedBar> {
+        let mut bars: Vec<AggregatedBar> = self
+            .building
+            .drain()
+            .map(|(symbol, bar)| bar.finish(symbol))
+            .collect();
+        sort(&mut bars);
+        bars
+    }
+
+    /// Trades dropped because they arrived after their bar was emitted.
+    pub fn late_trades(&self) -> u64 {
+        self.late_trades
+    }
+
+    /// Start and end of the interval containing `timestamp`, or `None` outside the session.
+    fn interval_of(&self, timestamp: i64) -> Option<(i64, i64)> {
+        let Some(session) = self.session else {
+            let start = timestamp.div_euclid(self.interval) * self.interval;
+            return Some((start, start + self.interval));
+        };
+
+        let day = timestamp.div_euclid(NANOS_PER_DAY) * NANOS_PER_DAY;
+        let open = day + session.start.as_nanos() as i64;
+        let close = open + session.length.as_nanos() as i64;
+        if timestamp < open || timestamp >= close {
+            return None;
+        }
+        let start = open + (timestamp - open) / self.interval * self.interval;
+        Some((start, (start + self.interval).min(close)))
+    }
+}
+
+fn sort(bars: &mut [AggregatedBar]) {
+    bars.sort_by(|a, b| (&a.bar.timestamp, &a.bar.symbol).cmp(&(&b.bar.timestamp, &b.bar.symbol)));
+}
+
+/// Target timeframe of a `Resampler`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timeframe {
+    /// Intraday bars of a fixed length, e.g. 15 minutes, aligned to the start of the day. Periods never cross a day
+    /// boundary.
+    Interval(Duration),
+    /// One bar per trading day.
+    Day,
+}
+
+/// Where one day ends and the next begins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DayBoundary {
+    /// Midnight UTC, e.g. for crypto.
+    #[default]
+    Utc,
+    /// Midnight in New York, following daylight saving time. Matches how Alpaca labels US equity daily bars.
+    UsEastern,
+}
+
+impl DayBoundary {
+    fn offset(&self, nanos: i64) -> i64 {
+        match self {
+            DayBoundary::Utc => 0,
+            DayBoundary::UsEastern => time::us_eastern_offset(nanos) * 1_000_000_000,
+        }
+    }
+
+    /// Start and end of the day containing `nanos`, as UTC instants.
+    fn day(&self, nanos: i64) -> (i64, i64) {
+        let local_midnight = (nanos + self.offset(nanos)).div_euclid(NANOS_PER_DAY) * NANOS_PER_DAY;
+        // Clocks change at 2am, so the offset at midnight can differ from the one at `nanos`.
+        let start = local_midnight - self.offset(local_midnight - self.offset(nanos));
+        let next = local_midnight + NANOS_PER_DAY;
+        let end = next - self.offset(next - self.offset(nanos));
+        (start, end)
+    }
+}
+
+/// Converts bars to a longer timeframe, e.g. 1m to 15m or 15m to daily. Opens and closes come from the first and last
+/// bar of each period, highs and lows are the extremes and volumes are summed. Intraday periods start at the day
+/// boundary, so 4h bars under `DayBoundary::UsEastern` start at midnight New York time. Each output bar is stamped with
+/// the start of its period.
+///
+/// Feed each symbol's bars in order; a bar is emitted once a bar from a later period arrives, and `flush` and `finish`
+/// release the rest.
+pub struct Resampler {
+    timeframe: Timeframe,
+    boundary: DayBoundary,
+    extended_hours: bool,
+    /// Bar being built per symbol, with the end of its period.
+    building: HashMap<String, (Bar, i64)>,
+}
+
+impl Resampler {
+    pub fn new(timeframe: Timeframe) -> Self {
+        if let Timeframe::Interval(interval) = timeframe {
+            assert!(!interval.is_zero(), "Interval must be greater than zero");
+        }
+        Resampler {
+            timeframe,
+            boundary: DayBoundary::Utc,
+            extended_hours: true,
+            building: HashMap::new(),
+        }
+    }
+
+    pub fn day_boundary(mut self, boundary: DayBoundary) -> Self {
+        self.boundary = boundary;
+        self
+    }
+
+    /// Whether pre-market and after-hours bars of US equities are resampled, judged by each bar's start. On by
+    /// default; turn it off for daily bars of the regular session alone.
+    pub fn extended_hours(mut self, include: bool) -> Self {
+        self.extended_hours = include;
+        self
+    }
+
+    /// Resamples a slice of bars in one go, returning the bars sorted by time and symbol.
+    pub fn resample(mut self, bars: &[Bar]) -> Vec<Bar> {
+        let mut resampled: Vec<Bar> = bars.iter().filter_map(|bar| self.update(bar)).collect();
+        resampled.extend(self.finish());
+        sort_bars(&mut resampled);
+        resampled
+    }
+
+    /// Resamples a stream of bars, emitting each bar as soon as it is complete and the rest once the stream ends.
+    pub fn resample_stream<S>(self, bars: S) -> impl Stream<Item = Bar>
+    where
+        S: Stream<Item = Bar> + Unpin,
+    {
+        stream::unfold(
+            (self, bars, VecDeque::new(), false),
+            |(mut resampler, mut bars, mut pending, mut ended)| async move {
+                loop {
+                    if let Some(bar) = pending.pop_front() {
+                        return Some((bar, (resampler, bars, pending, ended)));
+                    }
+                    if ended {
+                        return None;
+                    }
+                    match bars.next().await {
+                        Some(bar) => pending.extend(resampler.update(&bar)),
+                        None => {
+                            pending.extend(resampler.finish());
+                            ended = true;
+                        }
+                    }
+                }
+            },
+        )
+    }
+
+    /// Adds a bar. Returns the symbol's previous resampled bar if this one starts a new period.
+    pub fn update(&mut self, bar: &Bar) -> Option<Bar> {
+        if !self.extended_hours
+            && MarketSession::of(&bar.symbol, &bar.timestamp)
+                .is_some_and(|session| !session.is_regular())
+        {
+            return None;
+        }
+        let (start, end) = self.period_of(time::parse_rfc3339(&bar.timestamp)?);
+
+        if let Some((building, building_end)) = self.building.get_mut(&bar.symbol) {
+            if *building_end == end {
+                building.high = building.high.max(bar.high);
+                building.low = building.low.min(bar.low);
+                building.close = bar.close;
+                building.volume += bar.volume;
+                return None;
+            }
+        }
+
+        let period = Bar {
+            timestamp: time::format_rfc3339(start),
+            ..bar.clone()
+        };
+        self.building
+            .insert(bar.symbol.clone(), (period, end))
+            .map(|(completed, _)| completed)
+    }
+
+    /// Adds the bar from a minute bar event. Other events, including `UpdatedBar` corrections, are ignored.
+    pub fn update_event(&mut self, event: &EventType) -> Option<Bar> {
+        match event {
+            EventType::Bar { .. } => self.update(&Bar::from_event(event)?),
+            _ => None,
+        }
+    }
+
+    /// Emits every bar whose period ended at or before `now`, an RFC 3339 timestamp.
+    pub fn flush(&mut self, now: &str) -> Vec<Bar> {
+        let Some(now) = time::parse_rfc3339(now) else {
+            return vec![];
+        };
+        let closed: Vec<String> = self
+            .building
+            .iter()
+            .filter(|(_, (_, end))| *end <= now)
+            .map(|(symbol, _)| symbol.clone())
+            .collect();
+        let mut bars: Vec<Bar> = closed
+            .into_iter()
+            .filter_map(|symbol| self.building.remove(&symbol).map(|(bar, _)| bar))
+            .collect();
+        sort_bars(&mut bars);
+        bars
+    }
+
+    /// Emits every bar still being built, complete or not.
+    pub fn finish(&mut self) -> Vec<Bar> {
+        let mut bars: Vec<Bar> = self.building.drain().map(|(_, (bar, _))| bar).collect();
+        sort_bars(&mut bars);
+        bars
+    }
+
+    fn period_of(&self, timestamp: i64) -> (i64, i64) {
+        let (day_start, day_end) = self.boundary.day(timestamp);
+        match self.timeframe {
+            Timeframe::Day => (day_start, day_end),
+            Timeframe::Interval(interval) => {
+                let interval = interval.as_nanos() as i64;
+                let start = day_start + (timestamp - day_start) / interval * interval;
+                (start, (start + interval).min(day_end))
+            }
+        }
+    }
+}
+
+fn sort_bars(bars: &mut [Bar]) {
+    bars.sort_by(|a, b| (&a.timestamp, &a.symbol).cmp(&(&b.timestamp, &b.symbol)));
+}
@@ -1,20 +1,101 @@
 use crate::datastructures::{
+    account::{Account, AccountActivity, Position, RawActivity},
     asset::Asset,
-    client::{FeedType, SubscriptionParams, TradingClient},
+    client::{FeedType, MarketDataClient, SubscriptionParams, TradingClient},
     config::Config,
-    order::Order,
+    corporate_action::{CorporateAction, CorporateActionsPage},
+    market::{Bar, BarAdjustment, Quote},
+    news::{NewsArticle, NewsPage},
+    order::{CancelOutcome, Order, OrderResponse},
+};
+use crate::{
+    http::{CircuitBreaker, CircuitBreakerMetrics, CircuitState, RateLimiter, RetryPolicy},
+    journal::OrderJournal,
+    store::{self, OrderStore},
+    time,
 };
 use async_trait::async_trait;
 use futures_util::{SinkExt, StreamExt};
-use reqwest::{header::HeaderMap, Client as HttpClient};
+use reqwest::{
+    header::HeaderMap, Client as HttpClient, Request, RequestBuilder, Response, StatusCode,
+};
+use serde::Deserialize;
 use serde_json::json;
-use std::error::Error;
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use tokio::net::TcpStream;
 use tokio_tungstenite::{
     connect_async, tungstenite::protocol::Message, MaybeTlsStream, WebSocketStream,
 };
+use tracing::Instrument;
 use url::Url;
 
+/// Market data REST API. Shared by live and paper accounts.
+const DATA_URL: &str = "https://data.alpaca.markets";
+
+/// How long `cancel_order` keeps polling for the order to reach a terminal state.
+const CANCEL_CONFIRM_TIMEOUT: Duration = Duration::from_secs(10);
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+const CANCEL_MAX_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Most orders Alpaca returns per request.
+const ORDERS_PAGE_SIZE: usize = 500;
+
+/// Activity types other than fills, for `get_account_activities`.
+const NON_TRADE_ACTIVITIES: &str =
+    "CSD,CSW,DIV,DIVCGL,DIVCGS,DIVFEE,DIVFT,DIVNRA,DIVROC,DIVTW,DIVTXEX,INT,INTNRA,INTTW,\
+FEE,PTC,JNLC,JNLS,MA,NC,REORG,SC,SSO,SSP,SPIN,SPLIT";
+
+/// Error response of the Alpaca REST API, e.g. an order rejected for insufficient buying power.
+#[derive(Debug, Clone)]
+pub struct AlpacaError {
+    pub status: u16,
+    /// Alpaca's error code, e.g. 40310000 for insufficient buying power. Zero if the response had none.
+    pub code: i64,
+    pub message: String,
+}
+
+impl fmt::Display for AlpacaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Alpaca error {} ({}): {}",
+            self.code, self.status, self.message
+        )
+    }
+}
+
+impl Error for AlpacaError {}
+
+impl AlpacaError {
+    /// Reads the error out of a failed response's body, keeping the raw body as the message if it is not
+    /// Alpaca's error JSON.
+    fn from_response(status: StatusCode, body: String) -> Self {
+        let error: RawError = serde_json::from_str(&body).unwrap_or(RawError {
+            code: 0,
+            message: body,
+        });
+        AlpacaError {
+            status: status.as_u16(),
+            code: error.code,
+            message: error.message,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RawError {
+    #[serde(default)]
+    code: i64,
+    #[serde(default)]
+    message: String,
+}
+
 // Alpaca uses the same WebSocket API for both live and paper trading accounts when it comes to market data (IEX or SIP).
 // The WebSocket endpoints for real-time market data do not differentiate between paper and live trading environments.
 // The distinction between paper and live trading applies to order placement, not data streaming.
@@ -23,27 +104,15 @@ fn get_ws_url(feed_type: FeedType, enable_real_trading: bool) -> String {
 
     match feed_type {
         // Docs: https://docs.alpaca.markets/docs/real-time-stock-pricing-data
-        FeedType::Stocks => {
-            if enable_real_trading {
-                format!("wss://stream.data.alpaca.markets/v2/{src}")
-            } else {
-                format!("wss://stream.data.alpaca.markets/v2/{src}")
-            }
-        }
+        FeedType::Stocks => format!("wss://stream.data.alpaca.markets/v2/{src}"),
         // Docs: https://docs.alpaca.markets/docs/real-time-crypto-pricing-data
-        FeedType::Crypto => {
-            if enable_real_trading {
-                format!("wss://stream.data.alpaca.markets/v1beta3/crypto/us")
-            } else {
-                format!("wss://stream.data.alpaca.markets/v1beta3/crypto/us")
-            }
-        }
+        FeedType::Crypto => "wss://stream.data.alpaca.markets/v1beta3/crypto/us".to_string(),
         // Docs: https://docs.alpaca.markets/docs/streaming-real-time-news
         FeedType::News => {
             if enable_real_trading {
-                format!("wss://stream.data.alpaca.markets/v1beta1/news")
+                "wss://stream.data.alpaca.markets/v1beta1/news".to_string()
             } else {
-                format!("wss://stream.data.sandbox.alpaca.markets/v1beta1/news")
+                "wss://stream.data.sandbox.alpaca.markets/v1beta1/news".to_string()
             }
         }
         // Docs: https://docs.alpaca.markets/docs/real-time-option-data
@@ -54,16 +123,62 @@ fn get_ws_url(feed_type: FeedType, enable_real_trading: bool) -> String {
                 format!("wss://stream.data.sandbox.alpaca.markets/v1beta1/{src}")
             }
         }
-        FeedType::Test => {
-            if enable_real_trading {
-                format!("wss://stream.data.alpaca.markets/v2/test")
-            } else {
-                format!("wss://stream.data.alpaca.markets/v2/test")
-            }
+        FeedType::Test => "wss://stream.data.alpaca.markets/v2/test".to_string(),
+    }
+}
+
+#[derive(Deserialize)]
+struct RawQuote {
+    t: String,
+    bp: f64,
+    bs: f64,
+    ap: f64,
+    #[serde(rename = "as")]
+    ask_size: f64,
+}
+
+#[derive(Deserialize)]
+struct LatestQuote {
+    quote: RawQuote,
+}
+
+#[derive(Deserialize)]
+struct LatestQuotes {
+    quotes: HashMap<String, RawQuote>,
+}
+
+#[derive(Deserialize)]
+struct RawBar {
+    t: String,
+    o: f64,
+    h: f64,
+    l: f64,
+    c: f64,
+    v: f64,
+}
+
+impl RawBar {
+    fn into_bar(self, symbol: &str) -> Bar {
+        Bar {
+            symbol: symbol.to_string(),
+            open: self.o,
+            high: self.h,
+            low: self.l,
+            close: self.c,
+            volume: self.v,
+            timestamp: self.t,
         }
     }
 }
 
+/// One page of `/v2/stocks/bars` or `/v1beta3/crypto/us/bars`, both keyed by symbol.
+#[derive(Deserialize)]
+struct BarsPage {
+    #[serde(default)]
+    bars: HashMap<String, Vec<RawBar>>,
+    next_page_token: Option<String>,
+}
+
 #[derive(Clone)]
 pub struct AlpacaClient {
     http_client: HttpClient,
@@ -71,76 +186,716 @@ pub struct AlpacaClient {
     api_key: String,
     secret_key: String,
     enable_real_trading: bool,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    retry: Option<RetryPolicy>,
+    circuit_breaker: Option<Arc<CircuitBreaker>>,
+    journal: Option<OrderJournal>,
+    order_store: Option<Arc<dyn OrderStore>>,
+    /// Broker API keys are sent as HTTP basic auth rather than in the APCA headers.
+    basic_auth: bool,
+    #[cfg(feature = "testing")]
+    cassette: Option<crate::testing::Cassette>,
+    #[cfg(feature = "testing")]
+    stream_url: Option<String>,
     // cfg: Config, TODO: possibly cleaner to put the entire config object on the client instead of manually adding each property.
 }
 
-#[async_trait]
-impl TradingClient for AlpacaClient {
-    fn new(config: &Config) -> Self {
+impl AlpacaClient {
+    /// Client for the paper account, or the live one if real trading is enabled.
+    pub fn new(config: &Config) -> Result<Self, reqwest::Error> {
         let base_url = if config.enable_real_trading {
             "https://api.alpaca.markets"
         } else {
             "https://paper-api.alpaca.markets"
         };
 
-        AlpacaClient {
-            http_client: HttpClient::new(),
+        Ok(AlpacaClient {
+            http_client: config.http.build_client()?,
             base_url,
             api_key: config.alpaca_api_key.clone(),
             secret_key: config.alpaca_secret_key.clone(),
             enable_real_trading: config.enable_real_trading,
+            rate_limiter: config
+                .rate_limit
+                .map(|rate_limit| Arc::new(RateLimiter::new(rate_limit))),
+            retry: config.retry,
+            circuit_breaker: config
+                .circuit_breaker
+                .map(|circuit_breaker| Arc::new(CircuitBreaker::new(circuit_breaker))),
+            journal: config.journal.clone(),
+            order_store: config.order_store.clone(),
+            basic_auth: false,
+            #[cfg(feature = "testing")]
+            cassette: None,
+            #[cfg(feature = "testing")]
+            stream_url: None,
+        })
+    }
+
+    /// State of the REST circuit breaker, if one is configured.
+    pub fn circuit_state(&self) -> Option<CircuitState> {
+        self.circuit_breaker
+            .as_ref()
+            .map(|circuit_breaker| circuit_breaker.state())
+    }
+
+    pub fn circuit_metrics(&self) -> Option<CircuitBreakerMetrics> {
+        self.circuit_breaker
+            .as_ref()
+            .map(|circuit_breaker| circuit_breaker.metrics())
+    }
+
+    /// Records REST calls to, or replays them from, the cassette instead of only calling Alpaca. The market
+    /// data stream is not affected.
+    #[cfg(feature = "testing")]
+    pub fn with_cassette(mut self, cassette: crate::testing::Cassette) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
+    /// Connects market data streams of every feed type to `url`, e.g. a `MockFeedServer`.
+    #[cfg(feature = "testing")]
+    pub fn with_stream_url(mut self, url: impl Into<String>) -> Self {
+        self.stream_url = Some(url.into());
+        self
+    }
+
+    /// Splits and cash dividends with an ex-date from `start` to `end` inclusive, as YYYY-MM-DD dates, sorted by
+    /// ex-date.
+    pub async fn get_corporate_actions(
+        &self,
+        symbols: &[&str],
+        start: &str,
+        end: &str,
+    ) -> Result<Vec<CorporateAction>, Box<dyn Error>> {
+        let url = format!("{}/v1/corporate-actions", DATA_URL);
+        let symbols = symbols.join(",");
+        let mut actions = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut query = vec![
+                ("symbols", symbols.as_str()),
+                ("types", "forward_split,reverse_split,cash_dividend"),
+                ("start", start),
+                ("end", end),
+                ("limit", "1000"),
+            ];
+            if let Some(page_token) = &page_token {
+                query.push(("page_token", page_token));
+            }
+            let response = self
+                .send(self.http_client.get(&url).query(&query), true)
+                .await?;
+            if !response.status().is_success() {
+                return Err(
+                    format!("Failed to fetch corporate actions: {}", response.status()).into(),
+                );
+            }
+
+            let page: CorporateActionsPage = serde_json::from_str(&response.text().await?)?;
+            actions.extend(page.corporate_actions.into_actions());
+            match page.next_page_token {
+                Some(next) => page_token = Some(next),
+                None => break,
+            }
         }
+        actions.sort_by(|a, b| a.ex_date().cmp(b.ex_date()));
+        Ok(actions)
     }
 
-    // TODO: what if order was its own struct that had adjust_for_confidence and adjust_for_kelly_criteron
-    async fn create_order(&self, order: &Order) -> Result<(), Box<dyn std::error::Error>> {
-        let url = format!("{}/v2/orders", self.base_url);
+    /// News articles mentioning any of `symbols` published between `start` and `end` (RFC 3339 or
+    /// YYYY-MM-DD), oldest first, e.g. to replay alongside prices in a backtest.
+    /// Docs: https://docs.alpaca.markets/reference/news-3
+    pub async fn get_news(
+        &self,
+        symbols: &[&str],
+        start: &str,
+        end: &str,
+    ) -> Result<Vec<NewsArticle>, Box<dyn Error>> {
+        let url = format!("{}/v1beta1/news", DATA_URL);
+        let symbols = symbols.join(",");
+        let mut articles = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut query = vec![
+                ("symbols", symbols.as_str()),
+                ("start", start),
+                ("end", end),
+                ("sort", "asc"),
+                ("limit", "50"),
+            ];
+            if let Some(page_token) = &page_token {
+                query.push(("page_token", page_token));
+            }
+            let response = self
+                .send(self.http_client.get(&url).query(&query), true)
+                .await?;
+            if !response.status().is_success() {
+                return Err(format!("Failed to fetch news: {}", response.status()).into());
+            }
+
+            let page: NewsPage = serde_json::from_str(&response.text().await?)?;
+            articles.extend(page.news);
+            match page.next_page_token {
+                Some(next) => page_token = Some(next),
+                None => break,
+            }
+        }
+        articles.sort_by_key(|article| time::parse_rfc3339(&article.created_at));
+        Ok(articles)
+    }
+
+    async fn fetch_daily_bars(
+        &self,
+        symbol: &str,
+        start: &str,
+        end: &str,
+        adjustment: BarAdjustment,
+    ) -> Result<Vec<Bar>, Box<dyn Error>> {
+        let crypto = symbol.contains('/');
+        let url = if crypto {
+            format!("{}/v1beta3/crypto/us/bars", DATA_URL)
+        } else {
+            format!("{}/v2/stocks/bars", DATA_URL)
+        };
+
+        let mut bars = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut query = vec![
+                ("symbols", symbol),
+                ("timeframe", "1Day"),
+                ("start", start),
+                ("end", end),
+                ("limit", "10000"),
+            ];
+            if !crypto {
+                query.extend([("adjustment", adjustment.as_alpaca()), ("feed", "iex")]);
+            }
+            if let Some(page_token) = &page_token {
+                query.push(("page_token", page_token));
+            }
+            let response = self
+                .send(self.http_client.get(&url).query(&query), true)
+                .await?;
+            if !response.status().is_success() {
+                return Err(format!("Failed to fetch bars: {}", response.status()).into());
+            }
+            let body = response.text().await?;
+
+            let mut page: BarsPage = serde_json::from_str(&body)?;
+            bars.extend(
+                page.bars
+                    .remove(symbol)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|raw| raw.into_bar(symbol)),
+            );
+            match page.next_page_token {
+                Some(next) => page_token = Some(next),
+                None => return Ok(bars),
+            }
+        }
+    }
+
+    /// Client for Alpaca's Broker API, sharing the REST policies configured for trading. Uses the sandbox unless
+    /// real trading is enabled.
+    #[cfg(feature = "broker-api")]
+    pub(crate) fn broker_api(config: &Config) -> Result<Self, reqwest::Error> {
+        let mut client = AlpacaClient::new(config)?;
+        client.base_url = if config.enable_real_trading {
+            "https://broker-api.alpaca.markets"
+        } else {
+            "https://broker-api.sandbox.alpaca.markets"
+        };
+        client.basic_auth = true;
+        Ok(client)
+    }
+
+    #[cfg(feature = "broker-api")]
+    pub(crate) fn base_url(&self) -> &'static str {
+        self.base_url
+    }
+
+    #[cfg(feature = "broker-api")]
+    pub(crate) fn http_client(&self) -> &HttpClient {
+        &self.http_client
+    }
+
+    #[cfg(feature = "broker-api")]
+    pub(crate) fn journal(&self) -> Option<&OrderJournal> {
+        self.journal.as_ref()
+    }
+
+    fn headers(&self) -> Result<HeaderMap, Box<dyn Error>> {
         let mut headers = HeaderMap::new();
-        headers.insert("APCA-API-KEY-ID", self.api_key.parse()?);
-        headers.insert("APCA-API-SECRET-KEY", self.secret_key.parse()?);
+        if !self.basic_auth {
+            headers.insert("APCA-API-KEY-ID", self.api_key.parse()?);
+            headers.insert("APCA-API-SECRET-KEY", self.secret_key.parse()?);
+        }
         headers.insert("accept", "application/json".parse()?);
+        Ok(headers)
+    }
+
+    /// Every REST call goes through here so client-wide policies apply uniformly.
+    /// Only `idempotent` requests are retried.
+    pub(crate) async fn send(
+        &self,
+        request: RequestBuilder,
+        idempotent: bool,
+    ) -> Result<Response, Box<dyn Error>> {
+        let mut request = request.headers(self.headers()?);
+        if self.basic_auth {
+            request = request.basic_auth(&self.api_key, Some(&self.secret_key));
+        }
+        let mut request = request.build()?;
+        let max_attempts = match self.retry {
+            Some(retry) if idempotent => retry.max_attempts.max(1),
+            _ => 1,
+        };
+
+        let mut attempt = 1;
+        loop {
+            let next_request = if attempt < max_attempts {
+                request.try_clone()
+            } else {
+                None
+            };
+
+            let permit = self
+                .circuit_breaker
+                .as_ref()
+                .map(|circuit_breaker| circuit_breaker.try_acquire())
+                .transpose()?;
+
+            let result = self.execute(request).await;
+
+            if let Some(permit) = permit {
+                permit.record(result.as_ref().ok().map(Response::status));
+            }
+
+            let retryable = match &result {
+                Ok(response) => RetryPolicy::is_retryable(response.status()),
+                Err(_) => true,
+            };
+
+            match (next_request, self.retry) {
+                (Some(next_request), Some(retry)) if retryable => {
+                    tokio::time::sleep(retry.backoff(attempt)).await;
+                    request = next_request;
+                    attempt += 1;
+                }
+                _ => return result.map_err(|e| e as Box<dyn Error>),
+            }
+        }
+    }
+
+    async fn execute(&self, request: Request) -> Result<Response, Box<dyn Error + Send + Sync>> {
+        let span = tracing::info_span!(
+            "http_request",
+            method = %request.method(),
+            url = %request.url(),
+            status = tracing::field::Empty,
+            latency_ms = tracing::field::Empty,
+        );
+
+        async {
+            if let Some(rate_limiter) = &self.rate_limiter {
+                rate_limiter.acquire().await;
+            }
+
+            let started = Instant::now();
+            let result = self.transport(request).await;
+            let span = tracing::Span::current();
+            span.record("latency_ms", started.elapsed().as_millis() as u64);
+            #[cfg(feature = "metrics")]
+            crate::metrics::registry()
+                .rest_latency
+                .observe(started.elapsed());
+
+            match &result {
+                Ok(response) => {
+                    span.record("status", response.status().as_u16());
+                    if let Some(rate_limiter) = &self.rate_limiter {
+                        rate_limiter.observe(response.status(), response.headers());
+                    }
+                    if response.status().is_success() {
+                        tracing::debug!("Request completed");
+                    } else {
+                        tracing::warn!("Request failed");
+                    }
+                }
+                Err(e) => tracing::warn!(error = %e, "Request error"),
+            }
+
+            result
+        }
+        .instrument(span)
+        .await
+    }
 
+    async fn transport(&self, request: Request) -> Result<Response, Box<dyn Error + Send + Sync>> {
+        #[cfg(feature = "testing")]
+        if let Some(cassette) = &self.cassette {
+            return cassette.execute(&self.http_client, request).await;
+        }
+        Ok(self.http_client.execute(request).await?)
+    }
+
+    /// Masks the credentials in text that is about to be logged.
+    fn redact(&self, text: &str) -> String {
+        let mut redacted = text.to_string();
+        for secret in [&self.api_key, &self.secret_key] {
+            if !secret.is_empty() {
+                redacted = redacted.replace(secret.as_str(), "[REDACTED]");
+            }
+        }
+        redacted
+    }
+}
+
+#[async_trait]
+impl TradingClient for AlpacaClient {
+    async fn create_order(&self, order: &Order) -> Result<(), Box<dyn std::error::Error>> {
+        let url = format!("{}/v2/orders", self.base_url);
+        tracing::debug!(?order, "Submitting order");
+        if let Some(journal) = &self.journal {
+            journal.record(order)?;
+        }
         let response = self
-            .http_client
-            .post(&url)
-            .headers(headers)
-            .json(&order)
-            .send()
-            .await?
-            .text()
+            .send(
+                self.http_client.post(&url).json(&order),
+                order.client_order_id.is_some(),
+            )
             .await?;
+        #[cfg(feature = "metrics")]
+        {
+            let metrics = crate::metrics::registry();
+            metrics.orders_submitted.inc();
+            if !response.status().is_success() {
+                metrics.orders_rejected.inc();
+            }
+        }
+        let status = response.status();
+        let body = response.text().await?;
 
-        println!("Create Order Response: {}", response);
-
-        // Insert order details into the postgres database. Consider using SQLite instead.
-        // let (client, connection) =
-        //     tokio_postgres::connect("host=localhost user=postgres password=secret dbname=orders", tokio_postgres::NoTls).await?;
+        tracing::debug!(body = %self.redact(&body), "Create order response");
 
-        // // The connection object performs the actual communication with the database,
-        // // so spawn it off to run on its own.
-        // tokio::spawn(async move {
-        //     if let Err(e) = connection.await {
-        //         eprintln!("connection error: {}", e);
-        //     }
-        // });
+        if !status.is_success() {
+            return Err(AlpacaError::from_response(status, body).into());
+        }
 
-        // client.execute(
-        //     "INSERT INTO orders (symbol, quantity, order_type, time_in_force) VALUES ($1, $2, $3, $4)",
-        //     &[&order.symbol, &order.quantity, &format!("{:?}", order.order_type), &order.time_in_force],
-        // ).await?;
+        if let Some(store) = &self.order_store {
+            match serde_json::from_str::<OrderResponse>(&body) {
+                Ok(mut accepted) => {
+                    accepted.metadata = order.metadata.clone();
+                    if let Err(e) = store.record_submission(&accepted).await {
+                        tracing::error!(error = %e, "Failed to store submitted order");
+                    }
+                }
+                Err(e) => tracing::warn!(error = %e, "Unexpected create order response"),
+            }
+        }
 
         Ok(())
     }
 
-    // async fn close_order();
+    /// Pages through every open order, oldest first. Docs: https://docs.alpaca.markets/reference/getallorders
+    async fn get_open_orders(&self) -> Result<Vec<OrderResponse>, Box<dyn Error>> {
+        let url = format!("{}/v2/orders", self.base_url);
+        let limit = ORDERS_PAGE_SIZE.to_string();
+        let mut orders: Vec<OrderResponse> = Vec::new();
+        let mut seen = HashSet::new();
+        let mut after: Option<String> = None;
+        loop {
+            let mut query = vec![
+                ("status", "open"),
+                ("direction", "asc"),
+                ("limit", limit.as_str()),
+            ];
+            if let Some(after) = &after {
+                query.push(("after", after));
+            }
+            let response = self
+                .send(self.http_client.get(&url).query(&query), true)
+                .await?;
+            let body = response.text().await?;
+
+            let page: Vec<OrderResponse> = serde_json::from_str(&body)?;
+            let full = page.len() >= ORDERS_PAGE_SIZE;
+            // `after` is exclusive, so the next page starts just before the last order seen in case others share
+            // its timestamp. The overlap is skipped by id.
+            after = page
+                .last()
+                .and_then(|last| time::parse_rfc3339(&last.created_at))
+                .map(|created_at| time::format_rfc3339(created_at - 1_000));
+            let known = orders.len();
+            for order in page {
+                if seen.insert(order.id.clone()) {
+                    orders.push(order);
+                }
+            }
+            if !full || orders.len() == known || after.is_none() {
+                break;
+            }
+        }
+
+        for order in &mut orders {
+            if let Some(journal) = &self.journal {
+                journal.annotate(order);
+            }
+            store::record_logged(&self.order_store, order).await;
+        }
+        Ok(orders)
+    }
+
+    /// Docs: https://docs.alpaca.markets/reference/getorderbyorderid
+    async fn get_order(&self, order_id: &str) -> Result<OrderResponse, Box<dyn Error>> {
+        let url = format!("{}/v2/orders/{}", self.base_url, order_id);
+        let response = self.send(self.http_client.get(&url), true).await?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(AlpacaError::from_response(status, body).into());
+        }
+
+        let mut order: OrderResponse = serde_json::from_str(&body)?;
+        if let Some(journal) = &self.journal {
+            journal.annotate(&mut order);
+        }
+        store::record_logged(&self.order_store, &order).await;
+        Ok(order)
+    }
+
+    /// Docs: https://docs.alpaca.markets/reference/getorderbyclientorderid
+    async fn get_order_by_client_id(
+        &self,
+        client_order_id: &str,
+    ) -> Result<OrderResponse, Box<dyn Error>> {
+        let url = format!("{}/v2/orders:by_client_order_id", self.base_url);
+        let request = self
+            .http_client
+            .get(&url)
+            .query(&[("client_order_id", client_order_id)]);
+        let response = self.send(request, true).await?;
+        let body = response.text().await?;
+
+        let mut order: OrderResponse = serde_json::from_str(&body)?;
+        if let Some(journal) = &self.journal {
+            journal.annotate(&mut order);
+        }
+        store::record_logged(&self.order_store, &order).await;
+        Ok(order)
+    }
+
+    /// Docs: https://docs.alpaca.markets/reference/deleteorderbyorderid
+    async fn cancel_order(&self, order_id: &str) -> Result<CancelOutcome, Box<dyn Error>> {
+        let url = format!("{}/v2/orders/{}", self.base_url, order_id);
+        let response = self.send(self.http_client.delete(&url), true).await?;
+
+        // 422 means the order is no longer cancelable, usually because it already filled or was
+        // canceled by an earlier attempt. Its actual state is checked below like any other cancel.
+        let status = response.status();
+        if !status.is_success() && status != StatusCode::UNPROCESSABLE_ENTITY {
+            return Err(format!("Cancel order failed with status code: {}", status).into());
+        }
+
+        let deadline = Instant::now() + CANCEL_CONFIRM_TIMEOUT;
+        let mut delay = CANCEL_POLL_INTERVAL;
+        loop {
+            let outcome = CancelOutcome::from_order(self.get_order(order_id).await?);
+            if !matches!(outcome, CancelOutcome::Unconfirmed(_)) || Instant::now() >= deadline {
+                return Ok(outcome);
+            }
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(CANCEL_MAX_POLL_INTERVAL);
+        }
+    }
+
     // async fn close_all_orders();
 
+    async fn get_asset(&self, symbol: &str) -> Result<Asset, Box<dyn std::error::Error>> {
+        let url = format!("{}/v2/assets/{}", self.base_url, symbol);
+        let response = self.send(self.http_client.get(&url), true).await?;
+        let body = response.text().await?;
+
+        tracing::debug!(body = %self.redact(&body), "Get asset response");
+
+        let asset: Asset = serde_json::from_str(&body)?;
+        Ok(asset)
+    }
+
+    async fn list_assets(&self) -> Result<Vec<Asset>, Box<dyn std::error::Error>> {
+        let url = format!("{}/v2/assets", self.base_url);
+        let mut assets = Vec::new();
+        for asset_class in ["us_equity", "crypto"] {
+            let query = [("status", "active"), ("asset_class", asset_class)];
+            let response = self
+                .send(self.http_client.get(&url).query(&query), true)
+                .await?;
+            if !response.status().is_success() {
+                return Err(format!("Failed to list assets: {}", response.status()).into());
+            }
+            let page: Vec<Asset> = serde_json::from_str(&response.text().await?)?;
+            assets.extend(page);
+        }
+        Ok(assets)
+    }
+
+    /// Docs: https://docs.alpaca.markets/reference/getaccount-1
+    async fn get_account(&self) -> Result<Account, Box<dyn Error>> {
+        let url = format!("{}/v2/account", self.base_url);
+        let response = self.send(self.http_client.get(&url), true).await?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(AlpacaError::from_response(status, body).into());
+        }
+
+        let account: Account = serde_json::from_str(&body)?;
+        Ok(account)
+    }
+
+    /// Docs: https://docs.alpaca.markets/reference/getallopenpositions
+    async fn get_positions(&self) -> Result<Vec<Position>, Box<dyn Error>> {
+        let url = format!("{}/v2/positions", self.base_url);
+        let response = self.send(self.http_client.get(&url), true).await?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(AlpacaError::from_response(status, body).into());
+        }
+
+        let positions: Vec<Position> = serde_json::from_str(&body)?;
+        Ok(positions)
+    }
+
+    /// Docs: https://docs.alpaca.markets/reference/getaccountactivities-1
+    async fn get_account_activities(
+        &self,
+        since: &str,
+    ) -> Result<Vec<AccountActivity>, Box<dyn Error>> {
+        let url = format!("{}/v2/account/activities", self.base_url);
+        // `after` is exclusive.
+        let after = time::parse_rfc3339(&format!("{}T00:00:00Z", since))
+            .ok_or_else(|| format!("Not a YYYY-MM-DD date: {}", since))?;
+        let after = time::format_rfc3339(after - 1);
+        let mut activities = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut query = vec![
+                ("activity_types", NON_TRADE_ACTIVITIES),
+                ("after", after.as_str()),
+                ("direction", "asc"),
+                ("page_size", "100"),
+            ];
+            if let Some(page_token) = &page_token {
+                query.push(("page_token", page_token));
+            }
+            let response = self
+                .send(self.http_client.get(&url).query(&query), true)
+                .await?;
+            if !response.status().is_success() {
+                return Err(
+                    format!("Failed to fetch account activities: {}", response.status()).into(),
+                );
+            }
+
+            let page: Vec<RawActivity> = serde_json::from_str(&response.text().await?)?;
+            let full = page.len() == 100;
+            page_token = page.last().map(|activity| activity.id.clone());
+            activities.extend(page.into_iter().map(RawActivity::into_activity));
+            if !full {
+                break;
+            }
+        }
+        Ok(activities)
+    }
+}
+
+#[async_trait]
+impl MarketDataClient for AlpacaClient {
+    /// Docs: https://docs.alpaca.markets/reference/stocklatestquotesingle
+    /// and https://docs.alpaca.markets/reference/cryptolatestquotes. Crypto pairs are recognized by their slash.
+    async fn get_latest_quote(&self, symbol: &str) -> Result<Quote, Box<dyn Error>> {
+        let crypto = symbol.contains('/');
+        let url = if crypto {
+            format!("{}/v1beta3/crypto/us/latest/quotes", DATA_URL)
+        } else {
+            format!("{}/v2/stocks/{}/quotes/latest", DATA_URL, symbol)
+        };
+        let mut request = self.http_client.get(&url);
+        if crypto {
+            request = request.query(&[("symbols", symbol)]);
+        }
+        let response = self.send(request, true).await?;
+        let body = response.text().await?;
+
+        let raw = if crypto {
+            let mut latest: LatestQuotes = serde_json::from_str(&body)?;
+            latest
+                .quotes
+                .remove(symbol)
+                .ok_or_else(|| format!("No quote for {}", symbol))?
+        } else {
+            serde_json::from_str::<LatestQuote>(&body)?.quote
+        };
+        Ok(Quote {
+            symbol: symbol.to_string(),
+            bid_price: raw.bp,
+            ask_price: raw.ap,
+            bid_size: raw.bs,
+            ask_size: raw.ask_size,
+            timestamp: raw.t,
+        })
+    }
+
+    /// Docs: https://docs.alpaca.markets/reference/stockbars
+    /// and https://docs.alpaca.markets/reference/cryptobars. Stock bars come from the IEX feed, like the stream.
+    /// Adjusted stock bars the data plan does not serve are adjusted locally.
+    async fn get_daily_bars(
+        &self,
+        symbol: &str,
+        start: &str,
+        end: &str,
+        adjustment: BarAdjustment,
+    ) -> Result<Vec<Bar>, Box<dyn Error>> {
+        // Crypto has no corporate actions.
+        if symbol.contains('/') || adjustment == BarAdjustment::Raw {
+            return self
+                .fetch_daily_bars(symbol, start, end, BarAdjustment::Raw)
+                .await;
+        }
+        let adjusted = self
+            .fetch_daily_bars(symbol, start, end, adjustment)
+            .await
+            .map_err(|e| e.to_string());
+        match adjusted {
+            Ok(bars) => Ok(bars),
+            Err(e) => {
+                tracing::warn!(symbol, error = %e, "Adjusted bars unavailable, adjusting locally");
+                let mut bars = self
+                    .fetch_daily_bars(symbol, start, end, BarAdjustment::Raw)
+                    .await?;
+                // Splits after `end` restate the bars too.
+                let today = time::date(&time::format_rfc3339(time::now_nanos()))
+                    .unwrap_or_else(|| end.to_string());
+                let actions = self.get_corporate_actions(&[symbol], start, &today).await?;
+                adjustment.apply(&mut bars, &actions);
+                Ok(bars)
+            }
+        }
+    }
+
     /// Docs: https://docs.alpaca.markets/docs/streaming-market-data
     async fn subscribe(
         &self,
         params: SubscriptionParams,
     ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, Box<dyn Error>> {
-        let url = Url::parse(&get_ws_url(params.feed_type, self.enable_real_trading))?;
+        let url = get_ws_url(params.feed_type, self.enable_real_trading);
+        #[cfg(feature = "testing")]
+        let url = self.stream_url.clone().unwrap_or(url);
+        let url = Url::parse(&url)?;
 
         let (mut socket, response) = connect_async(url).await?;
 
@@ -158,10 +913,17 @@ impl TradingClient for AlpacaClient {
 
         socket.send(Message::Text(auth_message.to_string())).await?;
 
-        if let Some(message) = socket.next().await {
+        // The greeting sent on connect can arrive before the authentication response.
+        let mut response = socket.next().await;
+        if let Some(Ok(Message::Text(text))) = &response {
+            if text.contains("\"connected\"") {
+                response = socket.next().await;
+            }
+        }
+        if let Some(message) = response {
             match message? {
                 Message::Text(text) => {
-                    println!("Authentication Response: {}", text);
+                    tracing::debug!(response = %self.redact(&text), "Stream authentication response");
                     if text.contains("unauthorized") || text.contains("error") {
                         return Err("Authentication failed".into());
                     } else if !text.contains("success") {
@@ -184,26 +946,120 @@ impl TradingClient for AlpacaClient {
 
         Ok(socket)
     }
+}
 
-    async fn get_asset(&self, symbol: &str) -> Result<Asset, Box<dyn std::error::Error>> {
-        let url = format!("{}/v2/assets/{}", self.base_url, symbol);
-        let mut headers = HeaderMap::new();
-        headers.insert("APCA-API-KEY-ID", self.api_key.parse()?);
-        headers.insert("APCA-API-SECRET-KEY", self.secret_key.parse()?);
-        headers.insert("accept", "application/json".parse()?);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Cassette;
+    use serde_json::Value;
 
-        let response = self
-            .http_client
-            .get(&url)
-            .headers(headers)
-            .send()
-            .await?
-            .text()
-            .await?;
+    fn order(index: usize, created_at: &str) -> Value {
+        json!({
+            "id": format!("order-{}", index),
+            "client_order_id": format!("client-{}", index),
+            "symbol": "AAPL",
+            "status": "new",
+            "created_at": created_at,
+            "side": "buy",
+            "type": "limit",
+            "qty": "1",
+            "filled_qty": "0",
+            "limit_price": "100"
+        })
+    }
 
-        println!("Get Asset Response: {}", response);
+    fn orders_url(after: Option<&str>) -> String {
+        let mut query = vec![("status", "open"), ("direction", "asc"), ("limit", "500")];
+        query.extend(after.map(|after| ("after", after)));
+        Url::parse_with_params("https://paper-api.alpaca.markets/v2/orders", &query)
+            .unwrap()
+            .to_string()
+    }
 
-        let asset: Asset = serde_json::from_str(&response)?;
-        Ok(asset)
+    #[tokio::test]
+    async fn pages_through_every_open_order() {
+        // The last two orders of the first page share a timestamp with the first of the second.
+        let first: Vec<Value> = (0..ORDERS_PAGE_SIZE)
+            .map(|index| {
+                let second = if index + 2 >= ORDERS_PAGE_SIZE { 59 } else { 0 };
+                order(index, &format!("2024-05-01T14:30:{:02}Z", second))
+            })
+            .collect();
+        let second: Vec<Value> = [ORDERS_PAGE_SIZE - 2, ORDERS_PAGE_SIZE - 1, ORDERS_PAGE_SIZE]
+            .into_iter()
+            .map(|index| order(index, "2024-05-01T14:30:59Z"))
+            .collect();
+        let path =
+            std::env::temp_dir().join(format!("alpaca-open-orders-{}.json", std::process::id()));
+        let interactions = json!([
+            {
+                "method": "GET",
+                "url": orders_url(None),
+                "status": 200,
+                "response": Value::from(first).to_string()
+            },
+            {
+                "method": "GET",
+                "url": orders_url(Some("2024-05-01T14:30:58.999999Z")),
+                "status": 200,
+                "response": Value::from(second).to_string()
+            }
+        ]);
+        std::fs::write(&path, interactions.to_string()).unwrap();
+        let config = Config::builder()
+            .alpaca_api_key("key".to_string())
+            .alpaca_secret_key("secret".to_string())
+            .build()
+            .unwrap();
+        let cassette = Cassette::replay(&path).unwrap();
+        let client = AlpacaClient::new(&config)
+            .unwrap()
+            .with_cassette(cassette.clone());
+
+        let orders = client.get_open_orders().await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(orders.len(), ORDERS_PAGE_SIZE + 1);
+        assert_eq!(orders[ORDERS_PAGE_SIZE].id, "order-500");
+        assert_eq!(cassette.unplayed(), 0);
+    }
+
+    #[tokio::test]
+    async fn reports_failed_reads_as_alpaca_errors() {
+        let path =
+            std::env::temp_dir().join(format!("alpaca-failed-reads-{}.json", std::process::id()));
+        let interactions = json!([
+            {
+                "method": "GET",
+                "url": "https://paper-api.alpaca.markets/v2/orders/missing",
+                "status": 404,
+                "response": r#"{"code":40410000,"message":"order not found"}"#
+            },
+            {
+                "method": "GET",
+                "url": "https://paper-api.alpaca.markets/v2/account",
+                "status": 403,
+                "response": "forbidden"
+            }
+        ]);
+        std::fs::write(&path, interactions.to_string()).unwrap();
+        let config = Config::builder()
+            .alpaca_api_key("key".to_string())
+            .alpaca_secret_key("secret".to_string())
+            .build()
+            .unwrap();
+        let client = AlpacaClient::new(&config)
+            .unwrap()
+            .with_cassette(Cassette::replay(&path).unwrap());
+
+        let order = client.get_order("missing").await.unwrap_err();
+        let account = client.get_account().await.unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        let order = order.downcast_ref::<AlpacaError>().unwrap();
+        assert_eq!((order.status, order.code), (404, 40410000));
+        assert_eq!(order.message, "order not found");
+        let account = account.downcast_ref::<AlpacaError>().unwrap();
+        assert_eq!((account.status, account.code), (403, 0));
+        assert_eq!(account.message, "forbidden");
     }
 }
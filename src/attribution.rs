@@ -0,0 +1,296 @@
+use crate::{
+    datastructures::order::OrderSide,
+    journal::OrderJournal,
+    lots::{LotMethod, LotTracker},
+    strategy::Fill,
+    time,
+};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+/// Order metadata key holding the price the order was decided at, e.g. the quote midpoint when the signal
+/// fired. Fills are charged slippage against it.
+pub const REFERENCE_PRICE_TAG: &str = "reference_price";
+
+/// Performance of one symbol over a date range.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SymbolAttribution {
+    pub symbol: String,
+    /// Realized on quantity closed in the range, before fees.
+    pub gross_pnl: f64,
+    /// Commissions of fills in the range.
+    pub fees: f64,
+    /// `gross_pnl` less `fees`.
+    pub net_pnl: f64,
+    /// What fills in the range cost against their order's reference price; negative when they did better.
+    /// Fills of orders without one count as none.
+    pub slippage: f64,
+    /// Fills in the range.
+    pub trades: u64,
+    /// Fills in the range that closed quantity, and how many of those made money before fees.
+    pub closing_trades: u64,
+    pub winning_trades: u64,
+    /// Average time quantity closed in the range was held, in seconds, weighted by quantity.
+    pub average_hold_secs: Option<f64>,
+}
+
+impl SymbolAttribution {
+    /// Fraction of closing trades that made money. `None` without closing trades.
+    pub fn hit_rate(&self) -> Option<f64> {
+        (self.closing_trades > 0).then(|| self.winning_trades as f64 / self.closing_trades as f64)
+    }
+}
+
+/// Per-symbol attribution over a date range, as built by `attribute`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AttributionReport {
+    /// YYYY-MM-DD, inclusive.
+    pub start: String,
+    pub end: String,
+    /// Sorted by symbol.
+    pub symbols: Vec<SymbolAttribution>,
+}
+
+impl AttributionReport {
+    /// Sum over every symbol, under the symbol "TOTAL".
+    pub fn total(&self) -> SymbolAttribution {
+        let mut total = SymbolAttribution {
+            symbol: "TOTAL".to_string(),
+            ..SymbolAttribution::default()
+        };
+        let (mut held, mut weight) = (0.0, 0.0);
+        for symbol in &self.symbols {
+            total.gross_pnl += symbol.gross_pnl;
+            total.fees += symbol.fees;
+            total.net_pnl += symbol.net_pnl;
+            total.slippage += symbol.slippage;
+            total.trades += symbol.trades;
+            total.closing_trades += symbol.closing_trades;
+            total.winning_trades += symbol.winning_trades;
+            if let Some(average) = symbol.average_hold_secs {
+                held += average * symbol.closing_trades as f64;
+                weight += symbol.closing_trades as f64;
+            }
+        }
+        total.average_hold_secs = (weight > 0.0).then(|| held / weight);
+        total
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    /// One row per symbol and a final TOTAL row, with a header.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "symbol,gross_pnl,fees,net_pnl,slippage,trades,closing_trades,winning_trades,hit_rate,average_hold_secs\n",
+        );
+        let optional =
+            |value: Option<f64>| value.map(|value| value.to_string()).unwrap_or_default();
+        for symbol in self.symbols.iter().chain([&self.total()]) {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{},{},{}\n",
+                symbol.symbol,
+                symbol.gross_pnl,
+                symbol.fees,
+                symbol.net_pnl,
+                symbol.slippage,
+                symbol.trades,
+                symbol.closing_trades,
+                symbol.winning_trades,
+                optional(symbol.hit_rate()),
+                optional(symbol.average_hold_secs),
+            ));
+        }
+        csv
+    }
+}
+
+impl fmt::Display for AttributionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Attribution {} to {}", self.start, self.end)?;
+        for symbol in self.symbols.iter().chain([&self.total()]) {
+            writeln!(
+                f,
+                "{}: net {:.2} (gross {:.2}, fees {:.2}), slippage {:.2}, {} trades, hit rate {}",
+                symbol.symbol,
+                symbol.net_pnl,
+                symbol.gross_pnl,
+                symbol.fees,
+                symbol.slippage,
+                symbol.trades,
+                symbol
+                    .hit_rate()
+                    .map_or("n/a".to_string(), |rate| format!("{:.1}%", rate * 100.0)),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Attributes P&L, fees and slippage per symbol to fills dated `start` to `end` inclusive (YYYY-MM-DD).
+/// Every fill is replayed so positions opened before the range are closed against their real cost, matched
+/// first in, first out. Reference prices for slippage are read from the journal's order metadata.
+pub fn attribute(
+    fills: &[Fill],
+    journal: Option<&OrderJournal>,
+    start: &str,
+    end: &str,
+) -> AttributionReport {
+    let mut fills: Vec<&Fill> = fills.iter().collect();
+    fills.sort_by_key(|fill| time::parse_rfc3339(&fill.timestamp));
+
+    let mut lots = LotTracker::new(LotMethod::Fifo);
+    let mut symbols: BTreeMap<String, SymbolAttribution> = BTreeMap::new();
+    let mut held: BTreeMap<String, (f64, f64)> = BTreeMap::new();
+    for fill in fills {
+        // Fees are reported apart, so lots are matched on price alone.
+        let realized = lots.on_fill(&Fill {
+            commission: 0.0,
+            ..fill.clone()
+        });
+        let in_range = time::date(&fill.timestamp)
+            .is_some_and(|date| date.as_str() >= start && date.as_str() <= end);
+        if !in_range {
+            continue;
+        }
+
+        let attribution = symbols
+            .entry(fill.symbol.clone())
+            .or_insert_with(|| SymbolAttribution {
+                symbol: fill.symbol.clone(),
+                ..SymbolAttribution::default()
+            });
+        attribution.trades += 1;
+        attribution.fees += fill.commission;
+        let reference = journal
+            .and_then(|journal| journal.metadata(&fill.order_id))
+            .and_then(|metadata| metadata.get(REFERENCE_PRICE_TAG)?.parse::<f64>().ok());
+        if let Some(reference) = reference {
+            let direction = match fill.side {
+                OrderSide::Buy => 1.0,
+                OrderSide::Sell => -1.0,
+            };
+            attribution.slippage += (fill.price - reference) * fill.quantity * direction;
+        }
+        if !realized.is_empty() {
+            let gain: f64 = realized.iter().map(|lot| lot.gain).sum();
+            attribution.gross_pnl += gain;
+            attribution.closing_trades += 1;
+            if gain > 0.0 {
+                attribution.winning_trades += 1;
+            }
+            let (seconds, quantity) = held.entry(fill.symbol.clone()).or_default();
+            for lot in &realized {
+                *seconds += lot.holding_period.as_secs_f64() * lot.quantity.abs();
+                *quantity += lot.quantity.abs();
+            }
+        }
+    }
+
+    for (symbol, attribution) in symbols.iter_mut() {
+        attribution.net_pnl = attribution.gross_pnl - attribution.fees;
+        attribution.average_hold_secs = held
+            .get(symbol)
+            .filter(|(_, quantity)| *quantity > 0.0)
+            .map(|(seconds, quantity)| seconds / quantity);
+    }
+    AttributionReport {
+        start: start.to_string(),
+        end: end.to_string(),
+        symbols: symbols.into_values().collect(),
+    }
+}
+
+struct BlotterState {
+    writer: Option<BufWriter<File>>,
+    fills: Vec<Fill>,
+}
+
+/// Record of every fill, for attribution. Optionally kept in a JSONL file so it survives restarts, and paired
+/// with the order journal that holds reference prices.
+#[derive(Clone)]
+pub struct Blotter {
+    state: Arc<Mutex<BlotterState>>,
+    journal: Option<OrderJournal>,
+}
+
+impl Blotter {
+    /// Blotter that only lives as long as the process.
+    pub fn in_memory() -> Self {
+        Blotter {
+            state: Arc::new(Mutex::new(BlotterState {
+                writer: None,
+                fills: vec![],
+            })),
+            journal: None,
+        }
+    }
+
+    /// Appends to the JSONL file at `path`, creating it if needed. Fills already in the file are loaded.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut fills = vec![];
+        if path.exists() {
+            for line in BufReader::new(File::open(path)?).lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                fills.push(serde_json::from_str(&line)?);
+            }
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Blotter {
+            state: Arc::new(Mutex::new(BlotterState {
+                writer: Some(BufWriter::new(file)),
+                fills,
+            })),
+            journal: None,
+        })
+    }
+
+    /// Reads reference prices for slippage from the journal's order metadata.
+    pub fn with_journal(mut self, journal: OrderJournal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    pub fn record(&self, fill: &Fill) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(writer) = &mut state.writer {
+            serde_json::to_writer(&mut *writer, fill)?;
+            writer.write_all(b"\n")?;
+            writer.flush()?;
+        }
+        state.fills.push(fill.clone());
+        Ok(())
+    }
+
+    /// Every fill recorded, in the order recorded.
+    pub fn fills(&self) -> Vec<Fill> {
+        self.state.lock().unwrap().fills.clone()
+    }
+
+    /// Per-symbol attribution of fills dated `start` to `end` inclusive (YYYY-MM-DD).
+    pub fn attribute(&self, start: &str, end: &str) -> AttributionReport {
+        let state = self.state.lock().unwrap();
+        attribute(&state.fills, self.journal.as_ref(), start, end)
+    }
+}
+
+impl fmt::Debug for Blotter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Blotter")
+            .field("fills", &self.state.lock().unwrap().fills.len())
+            .field("journal", &self.journal.is_some())
+            .finish()
+    }
+}
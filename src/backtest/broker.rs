@@ -0,0 +1,487 @@
+use crate::{
+    datastructures::{
+        account::Position,
+        event::EventType,
+        order::{Order, OrderResponse, OrderSide, OrderStatus, OrderType, TimeInForce},
+    },
+    strategy::Fill,
+    time,
+};
+
+use super::costs::{ExecutionCosts, FillContext};
+use std::collections::HashMap;
+
+const NANOS_PER_DAY: i64 = 86_400 * 1_000_000_000;
+
+/// Prices an order can be matched against, taken from a single event.
+#[derive(Debug, Clone, Copy)]
+enum Market {
+    Quote { bid: f64, ask: f64 },
+    Bar { open: f64, high: f64, low: f64 },
+}
+
+impl Market {
+    fn from_event(event: &EventType) -> Option<Market> {
+        match *event {
+            EventType::Quote {
+                bid_price,
+                ask_price,
+                ..
+            } if bid_price > 0.0 && ask_price > 0.0 => Some(Market::Quote {
+                bid: bid_price,
+                ask: ask_price,
+            }),
+            EventType::Trade { price, .. } if price > 0.0 => Some(Market::Quote {
+                bid: price,
+                ask: price,
+            }),
+            EventType::Bar {
+                open, high, low, ..
+            }
+            | EventType::DailyBar {
+                open, high, low, ..
+            } => Some(Market::Bar { open, high, low }),
+            _ => None,
+        }
+    }
+
+    /// Price a resting order executes at against this market, if it executes at all.
+    fn fill_price(&self, order: &Order) -> Option<f64> {
+        let buy = order.side == OrderSide::Buy;
+        let stop_triggered = |stop: f64| match *self {
+            Market::Quote { bid, ask } => (buy && ask >= stop) || (!buy && bid <= stop),
+            Market::Bar { high, low, .. } => (buy && high >= stop) || (!buy && low <= stop),
+        };
+        let limit_price = |limit: f64| match *self {
+            Market::Quote { bid, ask } => match buy {
+                true if ask <= limit => Some(ask),
+                false if bid >= limit => Some(bid),
+                _ => None,
+            },
+            Market::Bar { open, high, low } => match buy {
+                true if low <= limit => Some(open.min(limit)),
+                false if high >= limit => Some(open.max(limit)),
+                _ => None,
+            },
+        };
+        let market_price = match *self {
+            Market::Quote { bid, ask } => {
+                if buy {
+                    ask
+                } else {
+                    bid
+                }
+            }
+            Market::Bar { open, .. } => open,
+        };
+
+        match (order.order_type, order.stop_price, order.limit_price) {
+            (OrderType::Market, _, _) => Some(market_price),
+            (OrderType::Limit, _, Some(limit)) => limit_price(limit),
+            (OrderType::Stop, Some(stop), _) if stop_triggered(stop) => Some(match *self {
+                Market::Bar { open, .. } if buy => open.max(stop),
+                Market::Bar { open, .. } => open.min(stop),
+                Market::Quote { .. } => market_price,
+            }),
+            (OrderType::StopLimit, Some(stop), Some(limit)) if stop_triggered(stop) => {
+                limit_price(limit)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct SimPosition {
+    quantity: f64,
+    average_price: f64,
+}
+
+#[derive(Debug)]
+struct WorkingOrder {
+    id: String,
+    order: Order,
+    /// Day (since the epoch) the order was submitted on, for day orders.
+    submitted_on: Option<i64>,
+}
+
+/// Simulated account that matches orders against market events and keeps the books.
+/// Orders fill in full on the first event for their symbol after submission that satisfies their price;
+/// market orders never fill on the event that triggered them, which avoids look-ahead.
+pub(crate) struct SimBroker {
+    costs: ExecutionCosts,
+    cash: f64,
+    realized_pnl: f64,
+    commissions: f64,
+    dividends: f64,
+    positions: HashMap<String, SimPosition>,
+    marks: HashMap<String, f64>,
+    /// Latest bid and ask per symbol, for slippage models.
+    quotes: HashMap<String, (f64, f64)>,
+    working: Vec<WorkingOrder>,
+    /// Every order submitted, as the broker would report it.
+    orders: HashMap<String, OrderResponse>,
+    /// Timestamp of the latest event.
+    clock: Option<String>,
+    next_id: u64,
+}
+
+impl SimBroker {
+    pub(crate) fn new(cash: f64, costs: ExecutionCosts) -> Self {
+        SimBroker {
+            costs,
+            cash,
+            realized_pnl: 0.0,
+            commissions: 0.0,
+            dividends: 0.0,
+            positions: HashMap::new(),
+            marks: HashMap::new(),
+            quotes: HashMap::new(),
+            working: Vec::new(),
+            orders: HashMap::new(),
+            clock: None,
+            next_id: 0,
+        }
+    }
+
+    /// Queues an order and returns its id: the client order id if set, otherwise a generated one.
+    /// `timestamp` defaults to that of the latest event, or the current time before any event.
+    pub(crate) fn submit(&mut self, order: Order, timestamp: Option<&str>) -> String {
+        let id = order.client_order_id.clone().unwrap_or_else(|| {
+            self.next_id += 1;
+            format!("sim-{}", self.next_id)
+        });
+        let created_at = timestamp
+            .map(str::to_string)
+            .or_else(|| self.clock.clone())
+            .unwrap_or_else(|| time::format_rfc3339(time::now_nanos()));
+
+        let mut status = OrderStatus::New;
+        if order.order_type == OrderType::TrailingStop {
+            tracing::warn!(order_id = %id, "Trailing stops are not simulated; order rejected");
+            status = OrderStatus::Rejected;
+        }
+        self.orders.insert(
+            id.clone(),
+            OrderResponse {
+                id: id.clone(),
+                client_order_id: id.clone(),
+                symbol: order.symbol.clone(),
+                status,
+                created_at: created_at.clone(),
+                side: order.side,
+                order_type: order.order_type,
+                qty: Some(order.quantity),
+                filled_qty: 0.0,
+                filled_avg_price: None,
+                limit_price: order.limit_price,
+                stop_price: order.stop_price,
+                metadata: order.metadata.clone(),
+            },
+        );
+        if status == OrderStatus::New {
+            self.working.push(WorkingOrder {
+                id: id.clone(),
+                order,
+                submitted_on: day(&created_at),
+            });
+        }
+        id
+    }
+
+    pub(crate) fn cancel(&mut self, order_id: &str) -> bool {
+        let before = self.working.len();
+        self.working.retain(|working| working.id != order_id);
+        let canceled = self.working.len() != before;
+        if canceled {
+            self.set_status(order_id, OrderStatus::Canceled);
+        }
+        canceled
+    }
+
+    pub(crate) fn order(&self, order_id: &str) -> Option<&OrderResponse> {
+        self.orders.get(order_id)
+    }
+
+    /// Working orders, oldest first.
+    pub(crate) fn open_orders(&self) -> Vec<OrderResponse> {
+        self.working
+            .iter()
+            .filter_map(|working| self.orders.get(&working.id))
+            .cloned()
+            .collect()
+    }
+
+    fn set_status(&mut self, order_id: &str, status: OrderStatus) {
+        if let Some(order) = self.orders.get_mut(order_id) {
+            order.status = status;
+        }
+    }
+
+    /// Updates marks and fills whatever working orders the event allows. Expires day orders once the event's
+    /// date moves past the submission date, and IOC/FOK orders that could not fill on their first chance.
+    pub(crate) fn on_event(&mut self, event: &EventType) -> Vec<Fill> {
+        let Some(symbol) = event.symbol() else {
+            return Vec::new();
+        };
+        if let Some(timestamp) = event.timestamp() {
+            self.clock = Some(timestamp.to_string());
+        }
+        if let EventType::Quote {
+            bid_price,
+            ask_price,
+            ..
+        } = *event
+        {
+            if bid_price > 0.0 && ask_price > 0.0 {
+                self.quotes
+                    .insert(symbol.to_string(), (bid_price, ask_price));
+            }
+        }
+        let market = Market::from_event(event);
+        if let Some(mark) = mark(event) {
+            self.marks.insert(symbol.to_string(), mark);
+        }
+
+        let today = event.timestamp().and_then(day);
+        let timestamp = event.timestamp().unwrap_or_default();
+        let mut fills = Vec::new();
+        let mut index = 0;
+        while index < self.working.len() {
+            let working = &self.working[index];
+            if working.order.time_in_force == TimeInForce::Day
+                && matches!((working.submitted_on, today), (Some(submitted), Some(today)) if today > submitted)
+            {
+                let expired = self.working.remove(index);
+                self.set_status(&expired.id, OrderStatus::Expired);
+                continue;
+            }
+            if working.order.symbol != symbol {
+                index += 1;
+                continue;
+            }
+            let Some(market) = market else {
+                index += 1;
+                continue;
+            };
+
+            match market.fill_price(&working.order) {
+                Some(price) => {
+                    let working = self.working.remove(index);
+                    fills.push(self.fill(working, price, timestamp));
+                }
+                None if matches!(
+                    working.order.time_in_force,
+                    TimeInForce::Ioc | TimeInForce::Fok
+                ) =>
+                {
+                    let canceled = self.working.remove(index);
+                    self.set_status(&canceled.id, OrderStatus::Canceled);
+                }
+                None => index += 1,
+            }
+        }
+        fills
+    }
+
+    fn fill(&mut self, working: WorkingOrder, price: f64, timestamp: &str) -> Fill {
+        let order = working.order;
+        let quote = self.quotes.get(&order.symbol);
+        let price = self.costs.fill_price(
+            &order,
+            &FillContext {
+                price,
+                bid: quote.map(|quote| quote.0),
+                ask: quote.map(|quote| quote.1),
+            },
+        );
+        let commission = self.costs.commission.commission(&order, price);
+        let signed = match order.side {
+            OrderSide::Buy => order.quantity,
+            OrderSide::Sell => -order.quantity,
+        };
+        self.cash -= signed * price + commission;
+        self.commissions += commission;
+
+        let position = self.positions.entry(order.symbol.clone()).or_default();
+        if position.quantity == 0.0 || position.quantity.signum() == signed.signum() {
+            let held = position.quantity.abs();
+            position.average_price =
+                (position.average_price * held + price * signed.abs()) / (held + signed.abs());
+        } else {
+            let closed = signed.abs().min(position.quantity.abs());
+            self.realized_pnl +=
+                closed * (price - position.average_price) * position.quantity.signum();
+            if signed.abs() > position.quantity.abs() {
+                position.average_price = price;
+            }
+        }
+        position.quantity += signed;
+        if position.quantity == 0.0 {
+            position.average_price = 0.0;
+        }
+
+        if let Some(record) = self.orders.get_mut(&working.id) {
+            record.status = OrderStatus::Filled;
+            record.filled_qty = order.quantity;
+            record.filled_avg_price = Some(price);
+        }
+
+        Fill {
+            order_id: working.id,
+            symbol: order.symbol,
+            side: order.side,
+            quantity: order.quantity,
+            price,
+            commission,
+            timestamp: timestamp.to_string(),
+        }
+    }
+
+    /// Applies a split of `ratio` new shares per old share to the position, its marks and the working orders
+    /// for the symbol. A whole-share position left with a fraction is paid cash in lieu at the adjusted price.
+    pub(crate) fn apply_split(&mut self, symbol: &str, ratio: f64) {
+        if ratio <= 0.0 {
+            return;
+        }
+        if let Some(mark) = self.marks.get_mut(symbol) {
+            *mark /= ratio;
+        }
+        if let Some((bid, ask)) = self.quotes.get_mut(symbol) {
+            *bid /= ratio;
+            *ask /= ratio;
+        }
+        if let Some(position) = self.positions.get_mut(symbol) {
+            let whole = position.quantity.fract() == 0.0;
+            position.quantity *= ratio;
+            position.average_price /= ratio;
+            if whole && position.quantity.fract() != 0.0 {
+                let fraction = position.quantity.fract();
+                let price = self
+                    .marks
+                    .get(symbol)
+                    .copied()
+                    .unwrap_or(position.average_price);
+                self.cash += fraction * price;
+                self.realized_pnl += fraction * (price - position.average_price);
+                position.quantity = position.quantity.trunc();
+            }
+        }
+        for working in self.working.iter_mut() {
+            if working.order.symbol != symbol {
+                continue;
+            }
+            let order = &mut working.order;
+            order.quantity *= ratio;
+            order.limit_price = order.limit_price.map(|price| price / ratio);
+            order.stop_price = order.stop_price.map(|price| price / ratio);
+            if let Some(response) = self.orders.get_mut(&working.id) {
+                response.qty = Some(order.quantity);
+                response.limit_price = order.limit_price;
+                response.stop_price = order.stop_price;
+            }
+        }
+    }
+
+    /// Pays a cash dividend of `amount` per share on the position. Short positions pay it instead.
+    /// Returns the cash credited.
+    pub(crate) fn apply_dividend(&mut self, symbol: &str, amount: f64) -> f64 {
+        let paid = self.position(symbol) * amount;
+        self.cash += paid;
+        self.dividends += paid;
+        paid
+    }
+
+    pub(crate) fn position(&self, symbol: &str) -> f64 {
+        self.positions
+            .get(symbol)
+            .map(|position| position.quantity)
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn cash(&self) -> f64 {
+        self.cash
+    }
+
+    /// Realized profit and loss before commissions.
+    pub(crate) fn realized_pnl(&self) -> f64 {
+        self.realized_pnl
+    }
+
+    pub(crate) fn commissions(&self) -> f64 {
+        self.commissions
+    }
+
+    /// Dividends received, net of those paid on short positions.
+    pub(crate) fn dividends(&self) -> f64 {
+        self.dividends
+    }
+
+    pub(crate) fn mark(&self, symbol: &str) -> Option<f64> {
+        self.marks.get(symbol).copied()
+    }
+
+    /// Open positions, marked at their latest price.
+    pub(crate) fn positions(&self) -> Vec<Position> {
+        let mut positions: Vec<Position> = self
+            .positions
+            .iter()
+            .filter(|(_, position)| position.quantity != 0.0)
+            .map(|(symbol, position)| {
+                let current_price = self.mark(symbol);
+                let price = current_price.unwrap_or(position.average_price);
+                Position {
+                    symbol: symbol.clone(),
+                    exchange: "SIM".to_string(),
+                    asset_class: if symbol.contains('/') {
+                        "crypto".to_string()
+                    } else {
+                        "us_equity".to_string()
+                    },
+                    qty: position.quantity,
+                    avg_entry_price: position.average_price,
+                    market_value: position.quantity * price,
+                    current_price,
+                    unrealized_pl: position.quantity * (price - position.average_price),
+                }
+            })
+            .collect();
+        positions.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        positions
+    }
+
+    /// Cash plus every position marked at its latest price, or its average price if none was seen.
+    pub(crate) fn equity(&self) -> f64 {
+        self.cash
+            + self
+                .positions
+                .iter()
+                .map(|(symbol, position)| {
+                    let mark = self
+                        .marks
+                        .get(symbol)
+                        .copied()
+                        .unwrap_or(position.average_price);
+                    position.quantity * mark
+                })
+                .sum::<f64>()
+    }
+}
+
+/// Price an event marks its symbol at: the quote mid, the trade price or the bar close.
+pub(crate) fn mark(event: &EventType) -> Option<f64> {
+    match *event {
+        EventType::Quote {
+            bid_price,
+            ask_price,
+            ..
+        } if bid_price > 0.0 && ask_price > 0.0 => Some((bid_price + ask_price) / 2.0),
+        EventType::Trade { price, .. } => Some(price),
+        EventType::Bar { close, .. } | EventType::DailyBar { close, .. } => Some(close),
+        _ => None,
+    }
+}
+
+fn day(timestamp: &str) -> Option<i64> {
+    time::parse_rfc3339(timestamp).map(|nanos| nanos.div_euclid(NANOS_PER_DAY))
+}
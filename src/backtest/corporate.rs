@@ -0,0 +1,124 @@
+use crate::datastructures::{corporate_action::CorporateAction, event::EventType};
+use std::collections::VecDeque;
+
+/// Corporate actions of a backtest, handed out as the replay reaches their ex-dates.
+pub(crate) struct CorporateActions {
+    all: Vec<CorporateAction>,
+    pending: VecDeque<CorporateAction>,
+}
+
+impl CorporateActions {
+    pub(crate) fn new(actions: &[CorporateAction]) -> Self {
+        let mut all = actions.to_vec();
+        all.sort_by(|a, b| a.ex_date().cmp(b.ex_date()));
+        CorporateActions {
+            pending: all.iter().cloned().collect(),
+            all,
+        }
+    }
+
+    /// Actions with an ex-date on or before `date` (YYYY-MM-DD) not handed out yet.
+    pub(crate) fn due(&mut self, date: &str) -> Vec<CorporateAction> {
+        let mut due = vec![];
+        while self
+            .pending
+            .front()
+            .is_some_and(|action| action.ex_date() <= date)
+        {
+            due.extend(self.pending.pop_front());
+        }
+        due
+    }
+
+    /// Product of the ratios of the symbol's splits after `date`: what a price on `date` is divided by to
+    /// express it in shares as of the last split.
+    pub(crate) fn split_factor(&self, symbol: &str, date: &str) -> f64 {
+        self.all
+            .iter()
+            .filter_map(|action| match action {
+                CorporateAction::Split {
+                    symbol: split_symbol,
+                    ex_date,
+                    ratio,
+                } if split_symbol == symbol && ex_date.as_str() > date && *ratio > 0.0 => {
+                    Some(*ratio)
+                }
+                _ => None,
+            })
+            .product()
+    }
+
+    /// Restates an event's prices and sizes in shares as of the last split.
+    pub(crate) fn adjust(&self, event: &mut EventType, date: &str) {
+        let Some(symbol) = event.symbol() else {
+            return;
+        };
+        let factor = self.split_factor(symbol, date);
+        if factor == 1.0 {
+            return;
+        }
+        let size = |size: &mut f64| *size *= factor;
+        match event {
+            EventType::Trade { price, volume, .. } => {
+                *price /= factor;
+                size(volume);
+            }
+            EventType::Quote {
+                bid_price,
+                ask_price,
+                bid_size,
+                ask_size,
+                ..
+            } => {
+                *bid_price /= factor;
+                *ask_price /= factor;
+                size(bid_size);
+                size(ask_size);
+            }
+            EventType::Bar {
+                open,
+                high,
+                low,
+                close,
+                volume,
+                ..
+            }
+            | EventType::UpdatedBar {
+                open,
+                high,
+                low,
+                close,
+                volume,
+                ..
+            }
+            | EventType::DailyBar {
+                open,
+                high,
+                low,
+                close,
+                volume,
+                ..
+            } => {
+                for price in [open, high, low, close] {
+                    *price /= factor;
+                }
+                size(volume);
+            }
+            EventType::OrderBook { bids, asks, .. } => {
+                for (price, level_size) in bids.iter_mut().chain(asks.iter_mut()) {
+                    *price /= factor;
+                    *level_size *= factor;
+                }
+            }
+            EventType::Luld {
+                limit_up,
+                limit_down,
+                ..
+            } => {
+                *limit_up /= factor;
+                *limit_down /= factor;
+            }
+            _ => {}
+        }
+    }
+}
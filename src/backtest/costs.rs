@@ -0,0 +1,120 @@
+use crate::datastructures::order::{Order, OrderSide, OrderType};
+use std::sync::Arc;
+
+/// Market state a simulated fill happens in.
+#[derive(Debug, Clone, Copy)]
+pub struct FillContext {
+    /// Price the matching engine filled at, before slippage.
+    pub price: f64,
+    /// Latest quote for the symbol, if any was seen.
+    pub bid: Option<f64>,
+    pub ask: Option<f64>,
+}
+
+/// Adjusts simulated fill prices for execution costs the matching engine does not see.
+pub trait SlippageModel: Send + Sync {
+    /// Returns the price the order actually executes at. Limit prices are enforced afterwards.
+    fn fill_price(&self, order: &Order, context: &FillContext) -> f64;
+}
+
+/// Fees charged for a simulated fill.
+pub trait CommissionModel: Send + Sync {
+    fn commission(&self, order: &Order, price: f64) -> f64;
+}
+
+pub struct NoSlippage;
+
+impl SlippageModel for NoSlippage {
+    fn fill_price(&self, _order: &Order, context: &FillContext) -> f64 {
+        context.price
+    }
+}
+
+/// Moves every fill against the order by a fixed number of basis points.
+pub struct FixedBpsSlippage(pub f64);
+
+impl SlippageModel for FixedBpsSlippage {
+    fn fill_price(&self, order: &Order, context: &FillContext) -> f64 {
+        let slippage = context.price * self.0 / 10_000.0;
+        match order.side {
+            OrderSide::Buy => context.price + slippage,
+            OrderSide::Sell => context.price - slippage,
+        }
+    }
+}
+
+/// Charges half the latest quoted spread on top of the fill price, for bar or trade data where fill prices
+/// carry no spread. Fills without a quote are left unchanged.
+pub struct SpreadCrossingSlippage;
+
+impl SlippageModel for SpreadCrossingSlippage {
+    fn fill_price(&self, order: &Order, context: &FillContext) -> f64 {
+        let (Some(bid), Some(ask)) = (context.bid, context.ask) else {
+            return context.price;
+        };
+        let half_spread = (ask - bid).max(0.0) / 2.0;
+        match order.side {
+            OrderSide::Buy => context.price + half_spread,
+            OrderSide::Sell => context.price - half_spread,
+        }
+    }
+}
+
+pub struct NoCommission;
+
+impl CommissionModel for NoCommission {
+    fn commission(&self, _order: &Order, _price: f64) -> f64 {
+        0.0
+    }
+}
+
+/// Fee per share or unit, with a minimum per order.
+pub struct PerShareCommission {
+    pub per_share: f64,
+    pub minimum: f64,
+}
+
+impl CommissionModel for PerShareCommission {
+    fn commission(&self, order: &Order, _price: f64) -> f64 {
+        (order.quantity * self.per_share).max(self.minimum)
+    }
+}
+
+/// Fee as basis points of the traded notional, as crypto venues charge.
+pub struct BpsCommission(pub f64);
+
+impl CommissionModel for BpsCommission {
+    fn commission(&self, order: &Order, price: f64) -> f64 {
+        order.quantity * price * self.0 / 10_000.0
+    }
+}
+
+/// Slippage and commission applied to simulated fills. Free and frictionless by default.
+#[derive(Clone)]
+pub struct ExecutionCosts {
+    pub slippage: Arc<dyn SlippageModel>,
+    pub commission: Arc<dyn CommissionModel>,
+}
+
+impl Default for ExecutionCosts {
+    fn default() -> Self {
+        ExecutionCosts {
+            slippage: Arc::new(NoSlippage),
+            commission: Arc::new(NoCommission),
+        }
+    }
+}
+
+impl ExecutionCosts {
+    /// Fill price after slippage, never worse than the order's limit price.
+    pub(crate) fn fill_price(&self, order: &Order, context: &FillContext) -> f64 {
+        let price = self.slippage.fill_price(order, context);
+        match (order.order_type, order.limit_price) {
+            (OrderType::Limit | OrderType::StopLimit, Some(limit)) => match order.side {
+                OrderSide::Buy => price.min(limit),
+                OrderSide::Sell => price.max(limit),
+            },
+            _ => price,
+        }
+    }
+}
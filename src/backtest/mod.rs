@@ -0,0 +1,283 @@
+mod broker;
+mod corporate;
+mod costs;
+mod news;
+
+pub(crate) use broker::{mark, SimBroker};
+use corporate::CorporateActions;
+pub use costs::{
+    BpsCommission, CommissionModel, ExecutionCosts, FillContext, FixedBpsSlippage, NoCommission,
+    NoSlippage, PerShareCommission, SlippageModel, SpreadCrossingSlippage,
+};
+pub use news::read_news_archive;
+use news::NewsQueue;
+
+use crate::{
+    datastructures::{corporate_action::CorporateAction, event::EventType, news::NewsArticle},
+    strategy::{Fill, Strategy, StrategyContext},
+    time,
+};
+use futures_util::{Stream, StreamExt};
+use serde::Serialize;
+use std::{fmt, time::Duration};
+
+#[derive(Clone)]
+pub struct BacktestConfig {
+    pub initial_cash: f64,
+    pub costs: ExecutionCosts,
+    /// How often `Strategy::on_timer` is called, in event time. `None` never calls it.
+    pub timer_interval: Option<Duration>,
+    /// Splits and dividends applied as the replay reaches their ex-dates, e.g. from
+    /// `AlpacaClient::get_corporate_actions`. Dividends are paid in cash; splits adjust positions and working
+    /// orders unless `adjust_prices` is set.
+    pub corporate_actions: Vec<CorporateAction>,
+    /// Restate every event in shares as of the last split, so split-adjusted prices reach the strategy without
+    /// gaps. Use with raw (unadjusted) data; positions are then held in adjusted shares throughout.
+    pub adjust_prices: bool,
+    /// Articles interleaved with the replay by publication time, e.g. from `AlpacaClient::get_news` or
+    /// `read_news_archive`. Each reaches the strategy through `on_event` as the news stream would send it,
+    /// ahead of any market event at or after its time.
+    pub news: Vec<NewsArticle>,
+    /// Delay from an article's publication to the strategy seeing it, so reactions trade on the prices that
+    /// followed the news rather than the ones it moved.
+    pub news_latency: Duration,
+}
+
+impl Default for BacktestConfig {
+    fn default() -> Self {
+        BacktestConfig {
+            initial_cash: 100_000.0,
+            costs: ExecutionCosts::default(),
+            timer_interval: None,
+            corporate_actions: Vec::new(),
+            adjust_prices: false,
+            news: Vec::new(),
+            news_latency: Duration::ZERO,
+        }
+    }
+}
+
+/// Account equity at the timestamp of a market event.
+#[derive(Debug, Clone, Serialize)]
+pub struct EquityPoint {
+    pub timestamp: String,
+    pub equity: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BacktestReport {
+    pub initial_cash: f64,
+    pub final_cash: f64,
+    pub final_equity: f64,
+    /// Before commissions.
+    pub realized_pnl: f64,
+    pub commissions: f64,
+    /// Received on long positions, net of those paid on shorts.
+    pub dividends: f64,
+    /// Largest peak-to-trough decline of the equity curve, as a fraction of the peak.
+    pub max_drawdown: f64,
+    pub fills: Vec<Fill>,
+    /// One point per distinct event timestamp.
+    pub equity_curve: Vec<EquityPoint>,
+}
+
+impl BacktestReport {
+    /// Return over the whole run, as a fraction of the initial cash.
+    pub fn total_return(&self) -> f64 {
+        if self.initial_cash != 0.0 {
+            self.final_equity / self.initial_cash - 1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+impl fmt::Display for BacktestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Initial cash: {:.2}", self.initial_cash)?;
+        writeln!(f, "Final equity: {:.2}", self.final_equity)?;
+        writeln!(f, "Total return: {:.2}%", self.total_return() * 100.0)?;
+        writeln!(f, "Realized P&L: {:.2}", self.realized_pnl)?;
+        writeln!(f, "Commissions: {:.2}", self.commissions)?;
+        writeln!(f, "Dividends: {:.2}", self.dividends)?;
+        writeln!(f, "Max drawdown: {:.2}%", self.max_drawdown * 100.0)?;
+        write!(f, "Fills: {}", self.fills.len())
+    }
+}
+
+/// Drives a strategy with historical events, e.g. from a `ReplayFeed`, against a simulated account.
+/// Timer ticks and news due by an event's timestamp fire first, in time order. The event then fills the working orders it allows and
+/// reaches the strategy through `on_fill`, `on_order_update` and `on_event`; orders the strategy submits are
+/// matched from the next event for their symbol onwards.
+pub struct Backtest {
+    config: BacktestConfig,
+}
+
+impl Backtest {
+    pub fn new(config: BacktestConfig) -> Self {
+        Backtest { config }
+    }
+
+    /// Runs until the event stream ends.
+    pub async fn run<S, E>(&self, strategy: &mut S, mut events: E) -> BacktestReport
+    where
+        S: Strategy + ?Sized,
+        E: Stream<Item = EventType> + Unpin,
+    {
+        let mut broker = SimBroker::new(self.config.initial_cash, self.config.costs.clone());
+        let mut context = StrategyContext::default();
+        let mut fills = Vec::new();
+        let mut equity_curve: Vec<EquityPoint> = Vec::new();
+        let mut peak = self.config.initial_cash;
+        let mut max_drawdown: f64 = 0.0;
+        let timer_interval = self
+            .config
+            .timer_interval
+            .map(|interval| interval.as_nanos() as i64)
+            .filter(|interval| *interval > 0);
+        let mut next_timer: Option<i64> = None;
+        let mut corporate_actions = CorporateActions::new(&self.config.corporate_actions);
+        let mut news = NewsQueue::new(&self.config.news, self.config.news_latency);
+
+        while let Some(mut event) = events.next().await {
+            if let Some(date) = event.timestamp().and_then(time::date) {
+                for action in corporate_actions.due(&date) {
+                    context.timestamp = event.timestamp().map(str::to_string);
+                    self.apply(
+                        &action,
+                        &corporate_actions,
+                        &mut broker,
+                        strategy,
+                        &mut context,
+                    );
+                }
+                if self.config.adjust_prices {
+                    corporate_actions.adjust(&mut event, &date);
+                }
+            }
+
+            if let Some(now) = event.timestamp().and_then(time::parse_rfc3339) {
+                if let Some(interval) = timer_interval {
+                    next_timer.get_or_insert(now + interval);
+                }
+                loop {
+                    let timer = next_timer.filter(|next| *next <= now);
+                    let article = news.next_due().filter(|due| *due <= now);
+                    match (timer, article) {
+                        (Some(next), article) if article.is_none_or(|due| next <= due) => {
+                            context.timestamp = Some(time::format_rfc3339(next));
+                            strategy.on_timer(&mut context);
+                            next_timer = timer_interval.map(|interval| next + interval);
+                        }
+                        (_, Some(_)) => {
+                            let Some((due, article)) = news.pop() else {
+                                break;
+                            };
+                            context.timestamp = Some(time::format_rfc3339(due));
+                            strategy.on_event(&article, &mut context);
+                        }
+                        _ => break,
+                    }
+                    execute(&mut broker, &mut context);
+                }
+            }
+
+            let event_fills = broker.on_event(&event);
+            context.timestamp = event.timestamp().map(str::to_string);
+            for fill in &event_fills {
+                context
+                    .positions
+                    .insert(fill.symbol.clone(), broker.position(&fill.symbol));
+            }
+            for fill in &event_fills {
+                strategy.on_fill(fill, &mut context);
+                if let Some(order) = broker.order(&fill.order_id).cloned() {
+                    strategy.on_order_update(&order, &mut context);
+                }
+            }
+            strategy.on_event(&event, &mut context);
+            fills.extend(event_fills);
+            execute(&mut broker, &mut context);
+
+            let Some(timestamp) = event.timestamp() else {
+                continue;
+            };
+            let equity = broker.equity();
+            peak = peak.max(equity);
+            if peak > 0.0 {
+                max_drawdown = max_drawdown.max((peak - equity) / peak);
+            }
+            match equity_curve.last_mut() {
+                Some(last) if last.timestamp == timestamp => last.equity = equity,
+                _ => equity_curve.push(EquityPoint {
+                    timestamp: timestamp.to_string(),
+                    equity,
+                }),
+            }
+        }
+
+        BacktestReport {
+            initial_cash: self.config.initial_cash,
+            final_cash: broker.cash(),
+            final_equity: broker.equity(),
+            realized_pnl: broker.realized_pnl(),
+            commissions: broker.commissions(),
+            dividends: broker.dividends(),
+            max_drawdown,
+            fills,
+            equity_curve,
+        }
+    }
+}
+
+impl Backtest {
+    /// Applies a corporate action as of its ex-date and tells the strategy about it if it changed the account.
+    fn apply<S: Strategy + ?Sized>(
+        &self,
+        action: &CorporateAction,
+        actions: &CorporateActions,
+        broker: &mut SimBroker,
+        strategy: &mut S,
+        context: &mut StrategyContext,
+    ) {
+        let action = match action {
+            // Adjusted events already express everything in post-split shares.
+            CorporateAction::Split { .. } if self.config.adjust_prices => return,
+            CorporateAction::Split { symbol, ratio, .. } => {
+                broker.apply_split(symbol, *ratio);
+                context
+                    .positions
+                    .insert(symbol.clone(), broker.position(symbol));
+                action.clone()
+            }
+            CorporateAction::CashDividend {
+                symbol,
+                ex_date,
+                amount,
+            } => {
+                let mut amount = *amount;
+                if self.config.adjust_prices {
+                    amount /= actions.split_factor(symbol, ex_date);
+                }
+                broker.apply_dividend(symbol, amount);
+                CorporateAction::CashDividend {
+                    symbol: symbol.clone(),
+                    ex_date: ex_date.clone(),
+                    amount,
+                }
+            }
+        };
+        strategy.on_corporate_action(&action, context);
+        execute(broker, context);
+    }
+}
+
+/// Carries out the cancels and orders a callback asked for.
+fn execute(broker: &mut SimBroker, context: &mut StrategyContext) {
+    for order_id in context.cancels.drain(..) {
+        broker.cancel(&order_id);
+    }
+    for order in context.orders.drain(..) {
+        broker.submit(order, context.timestamp.as_deref());
+    }
+}
@@ -0,0 +1,56 @@
+use crate::{
+    datastructures::{event::EventType, news::NewsArticle},
+    time,
+};
+use std::{collections::VecDeque, io, path::Path, time::Duration};
+
+/// Reads an archive of news articles in the shape the news REST API returns them: a JSON array, or one
+/// article per line. Lines that do not parse are skipped.
+pub fn read_news_archive(path: impl AsRef<Path>) -> io::Result<Vec<NewsArticle>> {
+    let contents = std::fs::read_to_string(path)?;
+    if contents.trim_start().starts_with('[') {
+        return serde_json::from_str(&contents).map_err(io::Error::from);
+    }
+    Ok(contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(article) => Some(article),
+            Err(e) => {
+                tracing::warn!(error = %e, "Skipping unreadable news line");
+                None
+            }
+        })
+        .collect())
+}
+
+/// News of a backtest, handed out once the replay reaches its publication time plus the reaction latency.
+pub(crate) struct NewsQueue {
+    pending: VecDeque<(i64, EventType)>,
+}
+
+impl NewsQueue {
+    pub(crate) fn new(articles: &[NewsArticle], latency: Duration) -> Self {
+        let latency = latency.as_nanos() as i64;
+        let mut pending: Vec<(i64, EventType)> = articles
+            .iter()
+            .filter_map(|article| {
+                let published = time::parse_rfc3339(&article.created_at)?;
+                Some((published + latency, article.to_event()))
+            })
+            .collect();
+        pending.sort_by_key(|(due, _)| *due);
+        NewsQueue {
+            pending: pending.into(),
+        }
+    }
+
+    /// When the next article reaches the strategy, in nanoseconds since the Unix epoch.
+    pub(crate) fn next_due(&self) -> Option<i64> {
+        self.pending.front().map(|(due, _)| *due)
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<(i64, EventType)> {
+        self.pending.pop_front()
+    }
+}
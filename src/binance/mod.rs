@@ -0,0 +1,812 @@
+mod stream;
+
+pub use stream::{Balance, UserDataEvent, UserDataStream};
+
+use crate::{
+    datastructures::{
+        account::{Account, Position},
+        asset::Asset,
+        client::{FeedType, MarketDataClient, SubscriptionParams, TradingClient},
+        config::Config,
+        event::{EventBatch, ParseMode},
+        market::{Bar, BarAdjustment, Quote},
+        order::{
+            CancelOutcome, Order, OrderResponse, OrderSide, OrderStatus, OrderType, TimeInForce,
+        },
+    },
+    http::{signing, RestPolicies},
+    journal::OrderJournal,
+    store::{self, OrderStore},
+    time,
+};
+use async_trait::async_trait;
+use futures_util::SinkExt;
+use reqwest::{Client as HttpClient, Method};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    sync::{Arc, Mutex},
+};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{
+    connect_async, tungstenite::protocol::Message, MaybeTlsStream, WebSocketStream,
+};
+
+/// Docs: https://developers.binance.com/docs/binance-spot-api-docs/rest-api
+const REST_URL: &str = "https://api.binance.com";
+/// Docs: https://developers.binance.com/docs/binance-spot-api-docs/testnet
+const TESTNET_REST_URL: &str = "https://testnet.binance.vision";
+/// Docs: https://developers.binance.com/docs/binance-spot-api-docs/web-socket-streams
+const STREAM_URL: &str = "wss://stream.binance.com:9443";
+const TESTNET_STREAM_URL: &str = "wss://stream.testnet.binance.vision";
+
+/// How long a signed request stays valid after its timestamp, in milliseconds.
+const RECV_WINDOW: &str = "5000";
+/// Binance's code for cancelling an order that is unknown or no longer open.
+const UNKNOWN_ORDER: i64 = -2011;
+const NANOS_PER_MILLI: i64 = 1_000_000;
+
+/// Error response of the Binance REST API.
+#[derive(Debug, Clone)]
+pub struct BinanceError {
+    pub status: u16,
+    /// Binance's error code, e.g. -2010 for a rejected order. Zero if the response had none.
+    pub code: i64,
+    pub message: String,
+}
+
+impl fmt::Display for BinanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Binance error {} ({}): {}",
+            self.code, self.status, self.message
+        )
+    }
+}
+
+impl Error for BinanceError {}
+
+#[derive(Deserialize)]
+struct RawError {
+    #[serde(default)]
+    code: i64,
+    #[serde(default)]
+    msg: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawSymbol {
+    symbol: String,
+    status: String,
+    base_asset: String,
+    quote_asset: String,
+}
+
+#[derive(Deserialize)]
+struct ExchangeInfo {
+    symbols: Vec<RawSymbol>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawOrder {
+    symbol: String,
+    order_id: u64,
+    client_order_id: String,
+    price: String,
+    orig_qty: String,
+    executed_qty: String,
+    cummulative_quote_qty: String,
+    status: String,
+    #[serde(rename = "type")]
+    order_type: String,
+    side: String,
+    #[serde(default)]
+    stop_price: String,
+    #[serde(default, alias = "transactTime")]
+    time: i64,
+}
+
+#[derive(Deserialize)]
+struct RawBalance {
+    asset: String,
+    free: String,
+    locked: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawAccount {
+    #[serde(default)]
+    uid: u64,
+    can_trade: bool,
+    balances: Vec<RawBalance>,
+}
+
+#[derive(Deserialize)]
+struct RawPrice {
+    symbol: String,
+    price: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawBookTicker {
+    bid_price: String,
+    bid_qty: String,
+    ask_price: String,
+    ask_qty: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListenKey {
+    listen_key: String,
+}
+
+/// Binance sends every number as a string.
+fn decimal(value: &str) -> f64 {
+    value.parse().unwrap_or(0.0)
+}
+
+fn format_millis(millis: i64) -> String {
+    time::format_rfc3339(millis * NANOS_PER_MILLI)
+}
+
+/// Symbol as Binance writes it, e.g. "BTCUSDT" for "BTC/USDT".
+fn binance_symbol(symbol: &str) -> String {
+    symbol.replace('/', "").to_uppercase()
+}
+
+fn order_status(status: &str) -> OrderStatus {
+    match status {
+        "PENDING_NEW" => OrderStatus::PendingNew,
+        "PARTIALLY_FILLED" => OrderStatus::PartiallyFilled,
+        "FILLED" => OrderStatus::Filled,
+        "CANCELED" => OrderStatus::Canceled,
+        "PENDING_CANCEL" => OrderStatus::PendingCancel,
+        "REJECTED" => OrderStatus::Rejected,
+        "EXPIRED" | "EXPIRED_IN_MATCH" => OrderStatus::Expired,
+        _ => OrderStatus::New,
+    }
+}
+
+fn order_type(order_type: &str) -> OrderType {
+    match order_type {
+        "LIMIT" | "LIMIT_MAKER" => OrderType::Limit,
+        "STOP_LOSS" | "TAKE_PROFIT" => OrderType::Stop,
+        "STOP_LOSS_LIMIT" | "TAKE_PROFIT_LIMIT" => OrderType::StopLimit,
+        _ => OrderType::Market,
+    }
+}
+
+fn order_side(side: &str) -> OrderSide {
+    match side {
+        "SELL" => OrderSide::Sell,
+        _ => OrderSide::Buy,
+    }
+}
+
+impl RawOrder {
+    fn into_response(self, pair: String) -> OrderResponse {
+        let filled_qty = decimal(&self.executed_qty);
+        let positive = |value: f64| (value > 0.0).then_some(value);
+        OrderResponse {
+            id: format!("{}:{}", self.symbol, self.order_id),
+            client_order_id: self.client_order_id,
+            symbol: pair,
+            status: order_status(&self.status),
+            created_at: format_millis(self.time),
+            side: order_side(&self.side),
+            order_type: order_type(&self.order_type),
+            qty: Some(decimal(&self.orig_qty)),
+            filled_qty,
+            filled_avg_price: positive(filled_qty)
+                .map(|filled| decimal(&self.cummulative_quote_qty) / filled),
+            limit_price: positive(decimal(&self.price)),
+            stop_price: positive(decimal(&self.stop_price)),
+            metadata: Default::default(),
+        }
+    }
+}
+
+/// Client for Binance spot, trading on the spot testnet unless real trading is enabled. Symbols are written
+/// as pairs, as Alpaca writes crypto, e.g. "BTC/USDT", and sent to Binance without the slash. Order ids are
+/// `<Binance symbol>:<order id>`, e.g. "BTCUSDT:28457", since Binance numbers orders per symbol. Applies the
+/// config's REST policies, journal, order store and pre-trade checks like `AlpacaClient`.
+#[derive(Clone)]
+pub struct BinanceClient {
+    http_client: HttpClient,
+    rest: RestPolicies,
+    rest_url: &'static str,
+    stream_url: &'static str,
+    api_key: String,
+    secret_key: String,
+    quote_asset: String,
+    journal: Option<OrderJournal>,
+    order_store: Option<Arc<dyn OrderStore>>,
+    /// Pair of each Binance symbol, e.g. "BTCUSDT" to "BTC/USDT", loaded from the exchange info on first use.
+    pairs: Arc<Mutex<HashMap<String, String>>>,
+    /// Binance symbol of orders placed or seen, by client order id.
+    order_symbols: Arc<Mutex<HashMap<String, String>>>,
+}
+
+impl BinanceClient {
+    /// Client for the spot testnet, or the live exchange if real trading is enabled. Uses the config's Binance
+    /// keys.
+    pub fn new(config: &Config) -> Result<Self, reqwest::Error> {
+        let (rest_url, stream_url) = if config.enable_real_trading {
+            (REST_URL, STREAM_URL)
+        } else {
+            (TESTNET_REST_URL, TESTNET_STREAM_URL)
+        };
+
+        Ok(BinanceClient {
+            http_client: config.http.build_client()?,
+            rest: RestPolicies::new(config),
+            rest_url,
+            stream_url,
+            api_key: config.binance_api_key.clone().unwrap_or_default(),
+            secret_key: config.binance_secret_key.clone().unwrap_or_default(),
+            quote_asset: "USDT".to_string(),
+            journal: config.journal.clone(),
+            order_store: config.order_store.clone(),
+            pairs: Arc::new(Mutex::new(HashMap::new())),
+            order_symbols: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// Asset the account is valued in and positions are priced against. Defaults to USDT.
+    pub fn with_quote_asset(mut self, quote_asset: impl Into<String>) -> Self {
+        self.quote_asset = quote_asset.into();
+        self
+    }
+
+    /// Signed calls are signed afresh for every attempt. Only `idempotent` calls are retried.
+    async fn request(
+        &self,
+        method: Method,
+        path: &str,
+        params: &[(&str, String)],
+        signed: bool,
+        idempotent: bool,
+    ) -> Result<String, Box<dyn Error>> {
+        let url = format!("{}{}", self.rest_url, path);
+        let build = || {
+            let mut query = url::form_urlencoded::Serializer::new(String::new());
+            for (name, value) in params {
+                query.append_pair(name, value);
+            }
+            if signed {
+                query.append_pair(
+                    "timestamp",
+                    &(time::now_nanos() / NANOS_PER_MILLI).to_string(),
+                );
+                query.append_pair("recvWindow", RECV_WINDOW);
+            }
+            let mut query = query.finish();
+            if signed {
+                let signature = signing::hmac_sha256(self.secret_key.as_bytes(), query.as_bytes());
+                query.push_str(&format!("&signature={}", signing::hex(&signature)));
+            }
+            let url = if query.is_empty() {
+                url.clone()
+            } else {
+                format!("{}?{}", url, query)
+            };
+            let mut request = self.http_client.request(method.clone(), url);
+            if !self.api_key.is_empty() {
+                request = request.header("X-MBX-APIKEY", &self.api_key);
+            }
+            Ok(request.build()?)
+        };
+        let response = self.rest.send(&self.http_client, idempotent, build).await?;
+
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            let error: RawError =
+                serde_json::from_str(&body).unwrap_or(RawError { code: 0, msg: body });
+            return Err(BinanceError {
+                status: status.as_u16(),
+                code: error.code,
+                message: error.msg,
+            }
+            .into());
+        }
+        Ok(body)
+    }
+
+    async fn exchange_info(&self, symbol: Option<&str>) -> Result<Vec<RawSymbol>, Box<dyn Error>> {
+        let params = match symbol {
+            Some(symbol) => vec![("symbol", binance_symbol(symbol))],
+            None => vec![("permissions", "SPOT".to_string())],
+        };
+        let body = self
+            .request(Method::GET, "/api/v3/exchangeInfo", &params, false, true)
+            .await?;
+        let info: ExchangeInfo = serde_json::from_str(&body)?;
+        let mut pairs = self.pairs.lock().unwrap();
+        for symbol in &info.symbols {
+            pairs.insert(
+                symbol.symbol.clone(),
+                format!("{}/{}", symbol.base_asset, symbol.quote_asset),
+            );
+        }
+        Ok(info.symbols)
+    }
+
+    /// Loads the pair of every Binance symbol, once.
+    async fn load_pairs(&self) -> Result<(), Box<dyn Error>> {
+        if self.pairs.lock().unwrap().is_empty() {
+            self.exchange_info(None).await?;
+        }
+        Ok(())
+    }
+
+    /// Pair of a Binance symbol, or the symbol itself if it is not known.
+    fn pair(&self, symbol: &str) -> String {
+        self.pairs
+            .lock()
+            .unwrap()
+            .get(symbol)
+            .cloned()
+            .unwrap_or_else(|| symbol.to_string())
+    }
+
+    fn to_response(&self, raw: RawOrder) -> OrderResponse {
+        let pair = self.pair(&raw.symbol);
+        self.order_symbols
+            .lock()
+            .unwrap()
+            .insert(raw.client_order_id.clone(), raw.symbol.clone());
+        let mut order = raw.into_response(pair);
+        if let Some(journal) = &self.journal {
+            journal.annotate(&mut order);
+        }
+        order
+    }
+
+    /// Last price of every symbol quoted in the quote asset, by base asset.
+    async fn prices(&self) -> Result<HashMap<String, f64>, Box<dyn Error>> {
+        self.load_pairs().await?;
+        let body = self
+            .request(Method::GET, "/api/v3/ticker/price", &[], false, true)
+            .await?;
+        let prices: Vec<RawPrice> = serde_json::from_str(&body)?;
+        let suffix = format!("/{}", self.quote_asset);
+        Ok(prices
+            .into_iter()
+            .filter_map(|price| {
+                let pair = self.pair(&price.symbol);
+                let base = pair.strip_suffix(&suffix)?;
+                Some((base.to_string(), decimal(&price.price)))
+            })
+            .collect())
+    }
+
+    async fn balances(&self) -> Result<RawAccount, Box<dyn Error>> {
+        let body = self
+            .request(Method::GET, "/api/v3/account", &[], true, true)
+            .await?;
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    /// Opens a listen key for the user data stream.
+    /// Docs: https://developers.binance.com/docs/binance-spot-api-docs/user-data-stream
+    async fn create_listen_key(&self) -> Result<String, Box<dyn Error>> {
+        let body = self
+            .request(Method::POST, "/api/v3/userDataStream", &[], false, true)
+            .await?;
+        Ok(serde_json::from_str::<ListenKey>(&body)?.listen_key)
+    }
+
+    /// Extends a listen key by 60 minutes.
+    async fn keep_alive(&self, listen_key: &str) -> Result<(), Box<dyn Error>> {
+        let params = [("listenKey", listen_key.to_string())];
+        self.request(Method::PUT, "/api/v3/userDataStream", &params, false, true)
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TradingClient for BinanceClient {
+    /// Docs: https://developers.binance.com/docs/binance-spot-api-docs/rest-api/trading-endpoints#new-order-trade
+    /// Day orders are sent good til cancelled, as crypto trades around the clock. Trailing stops and opening or
+    /// closing auction orders are not supported.
+    async fn create_order(&self, order: &Order) -> Result<(), Box<dyn Error>> {
+        let order_type = match order.order_type {
+            OrderType::Market => "MARKET",
+            OrderType::Limit => "LIMIT",
+            OrderType::Stop => "STOP_LOSS",
+            OrderType::StopLimit => "STOP_LOSS_LIMIT",
+            OrderType::TrailingStop => {
+                return Err("Binance spot has no trailing stop orders".into())
+            }
+        };
+        let time_in_force = match order.time_in_force {
+            TimeInForce::Day | TimeInForce::Gtc => "GTC",
+            TimeInForce::Ioc => "IOC",
+            TimeInForce::Fok => "FOK",
+            TimeInForce::Opg | TimeInForce::Cls => {
+                return Err("Binance has no opening or closing auction".into())
+            }
+        };
+        let symbol = binance_symbol(&order.symbol);
+        let mut params = vec![
+            ("symbol", symbol.clone()),
+            (
+                "side",
+                match order.side {
+                    OrderSide::Buy => "BUY",
+                    OrderSide::Sell => "SELL",
+                }
+                .to_string(),
+            ),
+            ("type", order_type.to_string()),
+            ("quantity", order.quantity.to_string()),
+            ("newOrderRespType", "RESULT".to_string()),
+        ];
+        if matches!(order.order_type, OrderType::Limit | OrderType::StopLimit) {
+            params.push(("timeInForce", time_in_force.to_string()));
+        }
+        if let Some(limit_price) = order.limit_price {
+            params.push(("price", limit_price.to_string()));
+        }
+        if let Some(stop_price) = order.stop_price {
+            params.push(("stopPrice", stop_price.to_string()));
+        }
+        if let Some(client_order_id) = &order.client_order_id {
+            params.push(("newClientOrderId", client_order_id.clone()));
+            self.order_symbols
+                .lock()
+                .unwrap()
+                .insert(client_order_id.clone(), symbol);
+        }
+
+        tracing::debug!(?order, "Submitting order");
+        if let Some(journal) = &self.journal {
+            journal.record(order)?;
+        }
+        let body = {
+            let result = self
+                .request(
+                    Method::POST,
+                    "/api/v3/order",
+                    &params,
+                    true,
+                    order.client_order_id.is_some(),
+                )
+                .await;
+            #[cfg(feature = "metrics")]
+            {
+                let metrics = crate::metrics::registry();
+                metrics.orders_submitted.inc();
+                if result.is_err() {
+                    metrics.orders_rejected.inc();
+                }
+            }
+            result?
+        };
+
+        if let Some(store) = &self.order_store {
+            match serde_json::from_str::<RawOrder>(&body) {
+                Ok(raw) => {
+                    let mut accepted = self.to_response(raw);
+                    accepted.metadata = order.metadata.clone();
+                    if let Err(e) = store.record_submission(&accepted).await {
+                        tracing::error!(error = %e, "Failed to store submitted order");
+                    }
+                }
+                Err(e) => tracing::warn!(error = %e, "Unexpected create order response"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Docs: https://developers.binance.com/docs/binance-spot-api-docs/rest-api/trading-endpoints#current-open-orders-user_data
+    async fn get_open_orders(&self) -> Result<Vec<OrderResponse>, Box<dyn Error>> {
+        self.load_pairs().await?;
+        let body = self
+            .request(Method::GET, "/api/v3/openOrders", &[], true, true)
+            .await?;
+        let raw: Vec<RawOrder> = serde_json::from_str(&body)?;
+        let mut orders: Vec<OrderResponse> =
+            raw.into_iter().map(|raw| self.to_response(raw)).collect();
+        orders.sort_by_key(|order| time::parse_rfc3339(&order.created_at));
+        for order in &orders {
+            store::record_logged(&self.order_store, order).await;
+        }
+        Ok(orders)
+    }
+
+    /// Docs: https://developers.binance.com/docs/binance-spot-api-docs/rest-api/trading-endpoints#query-order-user_data
+    async fn get_order(&self, order_id: &str) -> Result<OrderResponse, Box<dyn Error>> {
+        let (symbol, id) = order_id
+            .split_once(':')
+            .ok_or_else(|| format!("Not a Binance order id: {}", order_id))?;
+        self.load_pairs().await?;
+        let params = [("symbol", symbol.to_string()), ("orderId", id.to_string())];
+        let body = self
+            .request(Method::GET, "/api/v3/order", &params, true, true)
+            .await?;
+        let order = self.to_response(serde_json::from_str(&body)?);
+        store::record_logged(&self.order_store, &order).await;
+        Ok(order)
+    }
+
+    /// Binance needs the order's symbol too, so only orders this client placed or has seen, and open orders,
+    /// can be found.
+    async fn get_order_by_client_id(
+        &self,
+        client_order_id: &str,
+    ) -> Result<OrderResponse, Box<dyn Error>> {
+        let known = self
+            .order_symbols
+            .lock()
+            .unwrap()
+            .get(client_order_id)
+            .cloned();
+        let symbol = match known {
+            Some(symbol) => symbol,
+            None => {
+                let open = self.get_open_orders().await?;
+                return open
+                    .into_iter()
+                    .find(|order| order.client_order_id == client_order_id)
+                    .ok_or_else(|| format!("Unknown client order id: {}", client_order_id).into());
+            }
+        };
+        self.load_pairs().await?;
+        let params = [
+            ("symbol", symbol),
+            ("origClientOrderId", client_order_id.to_string()),
+        ];
+        let body = self
+            .request(Method::GET, "/api/v3/order", &params, true, true)
+            .await?;
+        let order = self.to_response(serde_json::from_str(&body)?);
+        store::record_logged(&self.order_store, &order).await;
+        Ok(order)
+    }
+
+    /// Docs: https://developers.binance.com/docs/binance-spot-api-docs/rest-api/trading-endpoints#cancel-order-trade
+    /// Binance cancels synchronously, so the response already shows the final state.
+    async fn cancel_order(&self, order_id: &str) -> Result<CancelOutcome, Box<dyn Error>> {
+        let (symbol, id) = order_id
+            .split_once(':')
+            .ok_or_else(|| format!("Not a Binance order id: {}", order_id))?;
+        self.load_pairs().await?;
+        let params = [("symbol", symbol.to_string()), ("orderId", id.to_string())];
+        let body = match self
+            .request(Method::DELETE, "/api/v3/order", &params, true, true)
+            .await
+        {
+            Ok(body) => Some(body),
+            // Usually already filled or cancelled. Its actual state decides the outcome.
+            Err(e) if e.downcast_ref::<BinanceError>().map(|e| e.code) == Some(UNKNOWN_ORDER) => {
+                None
+            }
+            Err(e) => return Err(e),
+        };
+        let Some(body) = body else {
+            return Ok(CancelOutcome::from_order(self.get_order(order_id).await?));
+        };
+        let order = self.to_response(serde_json::from_str(&body)?);
+        store::record_logged(&self.order_store, &order).await;
+        Ok(CancelOutcome::from_order(order))
+    }
+
+    async fn get_asset(&self, symbol: &str) -> Result<Asset, Box<dyn Error>> {
+        let raw = self
+            .exchange_info(Some(symbol))
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("Unknown symbol: {}", symbol))?;
+        Ok(to_asset(raw))
+    }
+
+    /// Every spot pair currently trading.
+    async fn list_assets(&self) -> Result<Vec<Asset>, Box<dyn Error>> {
+        Ok(self
+            .exchange_info(None)
+            .await?
+            .into_iter()
+            .filter(|raw| raw.status == "TRADING")
+            .map(to_asset)
+            .collect())
+    }
+
+    /// Valued in the quote asset at the last price of each balance's pair against it. Balances without such a
+    /// pair are left out of equity. Binance does not report the previous close, so `last_equity` is equity.
+    async fn get_account(&self) -> Result<Account, Box<dyn Error>> {
+        let account = self.balances().await?;
+        let prices = self.prices().await?;
+        let mut cash = 0.0;
+        let mut free_cash = 0.0;
+        let mut long_market_value = 0.0;
+        for balance in &account.balances {
+            let total = decimal(&balance.free) + decimal(&balance.locked);
+            if balance.asset == self.quote_asset {
+                cash = total;
+                free_cash = decimal(&balance.free);
+            } else if let Some(price) = prices.get(&balance.asset) {
+                long_market_value += total * price;
+            }
+        }
+        let equity = cash + long_market_value;
+        Ok(Account {
+            id: account.uid.to_string(),
+            status: if account.can_trade {
+                "ACTIVE".to_string()
+            } else {
+                "TRADING_BLOCKED".to_string()
+            },
+            currency: self.quote_asset.clone(),
+            cash,
+            equity,
+            last_equity: equity,
+            buying_power: free_cash,
+            long_market_value,
+            short_market_value: 0.0,
+            non_marginable_buying_power: Some(free_cash),
+        })
+    }
+
+    /// Every balance other than the quote asset that has a pair against it, as a position in that pair. Binance
+    /// does not track cost basis, so the entry price and unrealized P&L are zero.
+    async fn get_positions(&self) -> Result<Vec<Position>, Box<dyn Error>> {
+        let account = self.balances().await?;
+        let prices = self.prices().await?;
+        Ok(account
+            .balances
+            .into_iter()
+            .filter(|balance| balance.asset != self.quote_asset)
+            .filter_map(|balance| {
+                let qty = decimal(&balance.free) + decimal(&balance.locked);
+                let price = *prices.get(&balance.asset)?;
+                (qty > 0.0).then(|| Position {
+                    symbol: format!("{}/{}", balance.asset, self.quote_asset),
+                    exchange: "BINANCE".to_string(),
+                    asset_class: "crypto".to_string(),
+                    qty,
+                    avg_entry_price: 0.0,
+                    market_value: qty * price,
+                    current_price: Some(price),
+                    unrealized_pl: 0.0,
+                })
+            })
+            .collect())
+    }
+}
+
+fn to_asset(raw: RawSymbol) -> Asset {
+    let trading = raw.status == "TRADING";
+    Asset {
+        symbol: format!("{}/{}", raw.base_asset, raw.quote_asset),
+        exchange: "BINANCE".to_string(),
+        asset_class: "crypto".to_string(),
+        status: if trading { "active" } else { "inactive" }.to_string(),
+        tradable: trading,
+        shortable: false,
+        fractionable: true,
+        attributes: vec![],
+    }
+}
+
+#[async_trait]
+impl MarketDataClient for BinanceClient {
+    /// Docs: https://developers.binance.com/docs/binance-spot-api-docs/rest-api/market-data-endpoints#symbol-order-book-ticker
+    /// Binance does not timestamp the top of book, so the quote is stamped when it arrives.
+    async fn get_latest_quote(&self, symbol: &str) -> Result<Quote, Box<dyn Error>> {
+        let params = [("symbol", binance_symbol(symbol))];
+        let body = self
+            .request(
+                Method::GET,
+                "/api/v3/ticker/bookTicker",
+                &params,
+                false,
+                true,
+            )
+            .await?;
+        let raw: RawBookTicker = serde_json::from_str(&body)?;
+        Ok(Quote {
+            symbol: symbol.to_string(),
+            bid_price: decimal(&raw.bid_price),
+            ask_price: decimal(&raw.ask_price),
+            bid_size: decimal(&raw.bid_qty),
+            ask_size: decimal(&raw.ask_qty),
+            timestamp: time::format_rfc3339(time::now_nanos()),
+        })
+    }
+
+    /// Docs: https://developers.binance.com/docs/binance-spot-api-docs/rest-api/market-data-endpoints#klinecandlestick-data
+    /// Days run from midnight UTC. Crypto has no corporate actions, so the adjustment is ignored.
+    async fn get_daily_bars(
+        &self,
+        symbol: &str,
+        start: &str,
+        end: &str,
+        _adjustment: BarAdjustment,
+    ) -> Result<Vec<Bar>, Box<dyn Error>> {
+        let millis = |date: &str| {
+            time::parse_rfc3339(&format!("{}T00:00:00Z", date))
+                .map(|nanos| nanos / NANOS_PER_MILLI)
+                .ok_or_else(|| format!("Not a YYYY-MM-DD date: {}", date))
+        };
+        let mut from = millis(start)?;
+        let until = millis(end)? + 86_400_000 - 1;
+
+        let mut bars = Vec::new();
+        while from <= until {
+            let params = [
+                ("symbol", binance_symbol(symbol)),
+                ("interval", "1d".to_string()),
+                ("startTime", from.to_string()),
+                ("endTime", until.to_string()),
+                ("limit", "1000".to_string()),
+            ];
+            let body = self
+                .request(Method::GET, "/api/v3/klines", &params, false, true)
+                .await?;
+            // [open time, open, high, low, close, volume, close time, ...]
+            let page: Vec<Vec<Value>> = serde_json::from_str(&body)?;
+            let Some(last_close) = page.last().and_then(|kline| kline.get(6)?.as_i64()) else {
+                break;
+            };
+            for kline in page {
+                let field = |i: usize| kline.get(i).and_then(Value::as_str).map_or(0.0, decimal);
+                bars.push(Bar {
+                    symbol: symbol.to_string(),
+                    open: field(1),
+                    high: field(2),
+                    low: field(3),
+                    close: field(4),
+                    volume: field(5),
+                    timestamp: format_millis(
+                        kline.first().and_then(Value::as_i64).unwrap_or_default(),
+                    ),
+                });
+            }
+            from = last_close + 1;
+        }
+        Ok(bars)
+    }
+
+    /// Docs: https://developers.binance.com/docs/binance-spot-api-docs/web-socket-streams
+    /// Trades, quotes (the best bid and offer), minute and daily bars and 20-level order books are streamed;
+    /// Binance has no limit up/limit down bands or news, and needs explicit symbols rather than "*". Market
+    /// data is public, so the stream is not authenticated.
+    async fn subscribe(
+        &self,
+        params: SubscriptionParams,
+    ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, Box<dyn Error>> {
+        if matches!(params.feed_type, FeedType::News | FeedType::Options) {
+            return Err(format!("Binance has no {:?} feed", params.feed_type).into());
+        }
+        let streams = stream::stream_names(&params.subscription_request)?;
+        self.load_pairs().await?;
+
+        let url = format!("{}/stream", self.stream_url);
+        let (mut socket, response) = connect_async(url).await?;
+        if response.status() != 101 {
+            return Err(
+                format!("Connection failed with status code: {}", response.status()).into(),
+            );
+        }
+        let request = json!({ "method": "SUBSCRIBE", "params": streams, "id": 1 });
+        socket.send(Message::Text(request.to_string())).await?;
+        Ok(socket)
+    }
+
+    fn parse_frame(&self, frame: &str, _mode: ParseMode) -> Result<EventBatch, serde_json::Error> {
+        stream::parse_frame(frame, |symbol| self.pair(symbol))
+    }
+}
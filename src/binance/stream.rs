@@ -0,0 +1,416 @@
+use super::{binance_symbol, decimal, format_millis, order_side, order_status, order_type};
+use super::{BinanceClient, NANOS_PER_MILLI};
+use crate::{
+    datastructures::{
+        client::SubscriptionRequest,
+        event::{EventBatch, EventType},
+        order::OrderResponse,
+    },
+    store, time,
+};
+use futures_util::StreamExt;
+use serde::{de::Error as _, Deserialize};
+use serde_json::Value;
+use std::{error::Error, time::Duration};
+use tokio::{net::TcpStream, sync::mpsc, task::JoinHandle};
+use tokio_tungstenite::{
+    connect_async, tungstenite::protocol::Message, MaybeTlsStream, WebSocketStream,
+};
+use tokio_util::sync::CancellationToken;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Listen keys expire an hour after they were last extended.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(30 * 60);
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// Binance stream names for the request, e.g. "btcusdt@trade".
+pub(super) fn stream_names(request: &SubscriptionRequest) -> Result<Vec<String>, &'static str> {
+    let channels = [
+        (&request.trades, "trade"),
+        (&request.quotes, "bookTicker"),
+        (&request.bars, "kline_1m"),
+        (&request.updated_bars, "kline_1m"),
+        (&request.daily_bars, "kline_1d"),
+        (&request.orderbooks, "depth20@100ms"),
+    ];
+    let mut names = vec![];
+    for (symbols, stream) in channels {
+        for symbol in symbols {
+            if *symbol == "*" {
+                return Err("Binance streams need explicit symbols");
+            }
+            let name = format!("{}@{}", binance_symbol(symbol).to_lowercase(), stream);
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+    if !request.lulds.is_empty() || !request.news.is_empty() {
+        tracing::warn!("Binance has no limit up/limit down or news streams; ignoring them");
+    }
+    Ok(names)
+}
+
+#[derive(Deserialize)]
+struct Frame {
+    stream: String,
+    data: Value,
+}
+
+#[derive(Deserialize)]
+struct RawKline {
+    /// Open time.
+    t: i64,
+    i: String,
+    o: String,
+    h: String,
+    l: String,
+    c: String,
+    v: String,
+    /// Closed.
+    x: bool,
+}
+
+/// Maps a frame of the combined market data stream into events. Closed minute and daily klines are bars and
+/// daily bars, and updates to the open minute kline are updated bars. Quotes and order books carry no
+/// exchange time and are stamped on arrival. Replies to requests carry no data and give no events.
+pub(super) fn parse_frame(
+    frame: &str,
+    pair: impl Fn(&str) -> String,
+) -> Result<EventBatch, serde_json::Error> {
+    let mut batch = EventBatch::new();
+    let value: Value = serde_json::from_str(frame)?;
+    if value.get("stream").is_none() {
+        return Ok(batch);
+    }
+    let Frame { stream, data } = serde_json::from_value(value)?;
+    let field = |name: &str| data.get(name).and_then(Value::as_str).map_or(0.0, decimal);
+    let symbol = |name: &str| {
+        data.get(name)
+            .and_then(Value::as_str)
+            .map(&pair)
+            .ok_or_else(|| serde_json::Error::custom(format!("Missing symbol in {}", stream)))
+    };
+    let now = || time::format_rfc3339(time::now_nanos());
+
+    let kind = stream.split_once('@').map_or("", |(_, kind)| kind);
+    let event = match kind {
+        "trade" => EventType::Trade {
+            symbol: symbol("s")?,
+            price: field("p"),
+            volume: field("q"),
+            timestamp: format_millis(data.get("T").and_then(Value::as_i64).unwrap_or_default()),
+        },
+        "bookTicker" => EventType::Quote {
+            symbol: symbol("s")?,
+            bid_price: field("b"),
+            ask_price: field("a"),
+            bid_size: field("B"),
+            ask_size: field("A"),
+            timestamp: now(),
+        },
+        _ if kind.starts_with("kline_") => {
+            let kline: RawKline = serde_json::from_value(data["k"].clone())?;
+            let symbol = symbol("s")?;
+            let (open, high, low, close) = (
+                decimal(&kline.o),
+                decimal(&kline.h),
+                decimal(&kline.l),
+                decimal(&kline.c),
+            );
+            let volume = decimal(&kline.v);
+            let timestamp = format_millis(kline.t);
+            match (kline.i.as_str(), kline.x) {
+                ("1d", true) => EventType::DailyBar {
+                    symbol,
+                    open,
+                    high,
+                    low,
+                    close,
+                    volume,
+                    timestamp,
+                },
+                ("1d", false) => return Ok(batch),
+                (_, true) => EventType::Bar {
+                    symbol,
+                    open,
+                    high,
+                    low,
+                    close,
+                    volume,
+                    timestamp,
+                },
+                (_, false) => EventType::UpdatedBar {
+                    symbol,
+                    open,
+                    high,
+                    low,
+                    close,
+                    volume,
+                    timestamp,
+                },
+            }
+        }
+        _ if kind.starts_with("depth") => {
+            let levels = |name: &str| -> Vec<(f64, f64)> {
+                data.get(name)
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|level| {
+                        let price = level.get(0)?.as_str()?;
+                        let size = level.get(1)?.as_str()?;
+                        Some((decimal(price), decimal(size)))
+                    })
+                    .collect()
+            };
+            let raw_symbol = stream.split('@').next().unwrap_or_default().to_uppercase();
+            EventType::OrderBook {
+                symbol: pair(&raw_symbol),
+                bids: levels("bids"),
+                asks: levels("asks"),
+                // Partial book streams send the top levels in full every time.
+                reset: true,
+                timestamp: now(),
+            }
+        }
+        _ => return Ok(batch),
+    };
+    batch.push(event);
+    Ok(batch)
+}
+
+/// A balance of one asset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Balance {
+    pub asset: String,
+    pub free: f64,
+    pub locked: f64,
+}
+
+/// Account event from the Binance user data stream.
+#[derive(Debug, Clone)]
+pub enum UserDataEvent {
+    /// An order changed, e.g. it was accepted, filled or cancelled.
+    Order(OrderResponse),
+    /// Balances that changed, after the change.
+    Balances(Vec<Balance>),
+}
+
+/// Account events of a `BinanceClient`, read from a user data stream opened with a listen key. The key is
+/// extended every 30 minutes, and the stream reconnects with a new one when the connection drops. Order
+/// updates also go to the client's order store. Stops with the cancellation token or when dropped.
+pub struct UserDataStream {
+    receiver: mpsc::Receiver<UserDataEvent>,
+    task: JoinHandle<()>,
+}
+
+impl UserDataStream {
+    /// `None` once the stream has stopped.
+    pub async fn next(&mut self) -> Option<UserDataEvent> {
+        self.receiver.recv().await
+    }
+}
+
+impl Drop for UserDataStream {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl BinanceClient {
+    /// Opens the user data stream. Fails if the first listen key cannot be created or the first connection
+    /// fails; later failures are retried.
+    pub async fn user_data_stream(
+        &self,
+        cancel: CancellationToken,
+    ) -> Result<UserDataStream, Box<dyn Error>> {
+        let (listen_key, socket) = self.connect_user_data().await?;
+
+        let (sender, receiver) = mpsc::channel(1024);
+        let client = self.clone();
+        let task = tokio::spawn(async move {
+            let mut connection = Some((listen_key, socket));
+            let mut delay = RECONNECT_DELAY;
+            loop {
+                let (listen_key, mut socket) = match connection.take() {
+                    Some(connection) => connection,
+                    None => {
+                        tokio::select! {
+                            _ = tokio::time::sleep(delay) => {}
+                            _ = cancel.cancelled() => return,
+                        }
+                        match client.connect_user_data().await.map_err(|e| e.to_string()) {
+                            Ok(connection) => {
+                                tracing::info!("User data stream reconnected");
+                                delay = RECONNECT_DELAY;
+                                connection
+                            }
+                            Err(e) => {
+                                tracing::warn!(error = %e, "User data stream reconnect failed");
+                                delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+                                continue;
+                            }
+                        }
+                    }
+                };
+
+                let mut keep_alive = tokio::time::interval(KEEP_ALIVE_INTERVAL);
+                keep_alive.reset();
+                loop {
+                    tokio::select! {
+                        message = socket.next() => match message {
+                            Some(Ok(Message::Text(text))) => {
+                                if text.contains("\"listenKeyExpired\"") {
+                                    tracing::info!("Listen key expired");
+                                    break;
+                                }
+                                let Some(event) = client.parse_user_event(&text) else {
+                                    continue;
+                                };
+                                if let UserDataEvent::Order(order) = &event {
+                                    store::record_logged(&client.order_store, order).await;
+                                }
+                                if sender.send(event).await.is_err() {
+                                    return;
+                                }
+                            }
+                            Some(Ok(Message::Close(_))) | None => break,
+                            Some(Ok(_)) => {}
+                            Some(Err(e)) => {
+                                tracing::warn!(error = %e, "User data stream error");
+                                break;
+                            }
+                        },
+                        _ = keep_alive.tick() => {
+                            let result = client.keep_alive(&listen_key).await.map_err(|e| e.to_string());
+                            if let Err(e) = result {
+                                tracing::warn!(error = %e, "Failed to extend listen key");
+                            }
+                        }
+                        _ = cancel.cancelled() => {
+                            let _ = socket.close(None).await;
+                            return;
+                        }
+                    }
+                }
+            }
+        });
+        Ok(UserDataStream { receiver, task })
+    }
+
+    /// Creates a listen key and connects to its stream.
+    async fn connect_user_data(&self) -> Result<(String, Socket), Box<dyn Error>> {
+        let listen_key = self.create_listen_key().await?;
+        let url = format!("{}/ws/{}", self.stream_url, listen_key);
+        let (socket, _) = connect_async(url).await?;
+        Ok((listen_key, socket))
+    }
+
+    /// Docs: https://developers.binance.com/docs/binance-spot-api-docs/user-data-stream
+    fn parse_user_event(&self, text: &str) -> Option<UserDataEvent> {
+        let data: Value = serde_json::from_str(text).ok()?;
+        let string = |name: &str| data.get(name).and_then(Value::as_str).unwrap_or_default();
+        let number = |name: &str| decimal(string(name));
+        match string("e") {
+            "executionReport" => {
+                let filled_qty = number("z");
+                let positive = |value: f64| (value > 0.0).then_some(value);
+                let raw_symbol = string("s");
+                // Cancels report the cancelled order's id in "C".
+                let client_order_id = match string("C") {
+                    "" => string("c"),
+                    original => original,
+                };
+                let created = data.get("O").and_then(Value::as_i64).unwrap_or_default();
+                let mut order = OrderResponse {
+                    id: format!("{}:{}", raw_symbol, data.get("i")?.as_u64()?),
+                    client_order_id: client_order_id.to_string(),
+                    symbol: self.pair(raw_symbol),
+                    status: order_status(string("X")),
+                    created_at: time::format_rfc3339(created * NANOS_PER_MILLI),
+                    side: order_side(string("S")),
+                    order_type: order_type(string("o")),
+                    qty: Some(number("q")),
+                    filled_qty,
+                    filled_avg_price: positive(filled_qty).map(|filled| number("Z") / filled),
+                    limit_price: positive(number("p")),
+                    stop_price: positive(number("P")),
+                    metadata: Default::default(),
+                };
+                if let Some(journal) = &self.journal {
+                    journal.annotate(&mut order);
+                }
+                Some(UserDataEvent::Order(order))
+            }
+            "outboundAccountPosition" => {
+                let balances = data
+                    .get("B")?
+                    .as_array()?
+                    .iter()
+                    .map(|balance| {
+                        let string = |name: &str| {
+                            balance
+                                .get(name)
+                                .and_then(Value::as_str)
+                                .unwrap_or_default()
+                        };
+                        Balance {
+                            asset: string("a").to_string(),
+                            free: decimal(string("f")),
+                            locked: decimal(string("l")),
+                        }
+                    })
+                    .collect();
+                Some(UserDataEvent::Balances(balances))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(frame: &str) -> EventBatch {
+        parse_frame(frame, |symbol| symbol.to_string()).unwrap()
+    }
+
+    #[test]
+    fn keeps_fractional_trade_and_quote_sizes() {
+        let batch = parse(
+            r#"{"stream":"btcusdt@trade","data":{"e":"trade","s":"BTCUSDT","p":"42000.10","q":"0.00150000","T":1704205800000}}"#,
+        );
+        match &batch[..] {
+            [EventType::Trade { price, volume, .. }] => {
+                assert_eq!(*price, 42000.1);
+                assert_eq!(*volume, 0.0015);
+            }
+            other => panic!("Expected one trade, got {:?}", other),
+        }
+
+        let batch = parse(
+            r#"{"stream":"btcusdt@bookTicker","data":{"s":"BTCUSDT","b":"41999.9","B":"0.25","a":"42000.1","A":"1.5"}}"#,
+        );
+        match &batch[..] {
+            [EventType::Quote {
+                bid_size, ask_size, ..
+            }] => assert_eq!((*bid_size, *ask_size), (0.25, 1.5)),
+            other => panic!("Expected one quote, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn keeps_fractional_kline_volume() {
+        let batch = parse(
+            r#"{"stream":"btcusdt@kline_1m","data":{"s":"BTCUSDT","k":{"t":1704205800000,"i":"1m","o":"1","h":"2","l":"0.5","c":"1.5","v":"0.75","x":true}}}"#,
+        );
+        match &batch[..] {
+            [EventType::Bar { volume, .. }] => assert_eq!(*volume, 0.75),
+            other => panic!("Expected one bar, got {:?}", other),
+        }
+    }
+}
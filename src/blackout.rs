@@ -0,0 +1,322 @@
+use crate::{
+    datastructures::order::{Order, STRATEGY_TAG},
+    risk::closing_quantity,
+    time,
+};
+use std::{
+    error::Error,
+    fmt,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio::{sync::watch, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
+
+/// Longest the schedule sleeps between checks, so windows added while it waits are picked up.
+const MAX_SCHEDULE_SLEEP: Duration = Duration::from_secs(60);
+
+/// What happens to new entries during a blackout. Orders that only reduce a position always pass.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlackoutAction {
+    Block,
+    /// Scale the entry quantity, e.g. `Scale(0.5)` halves it.
+    Scale(f64),
+}
+
+/// A period, such as a scheduled economic release, during which new entries are blocked or reduced.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlackoutWindow {
+    pub name: String,
+    /// Nanoseconds since the Unix epoch.
+    pub start: i64,
+    /// Exclusive.
+    pub end: i64,
+    pub action: BlackoutAction,
+    /// Strategy the window applies to, matched against the order's strategy tag. `None` applies to every order.
+    pub strategy: Option<String>,
+}
+
+impl BlackoutWindow {
+    /// Window between two RFC 3339 timestamps that blocks entries for every strategy.
+    pub fn new(name: impl Into<String>, start: &str, end: &str) -> Option<Self> {
+        Some(BlackoutWindow {
+            name: name.into(),
+            start: time::parse_rfc3339(start)?,
+            end: time::parse_rfc3339(end)?,
+            action: BlackoutAction::Block,
+            strategy: None,
+        })
+    }
+
+    /// Window from `before` an RFC 3339 instant until `after` it.
+    pub fn around(
+        name: impl Into<String>,
+        at: &str,
+        before: Duration,
+        after: Duration,
+    ) -> Option<Self> {
+        let at = time::parse_rfc3339(at)?;
+        Some(Self::around_nanos(name.into(), at, before, after))
+    }
+
+    /// FOMC statement at 2:00pm ET on a YYYY-MM-DD meeting date: 15 minutes before until the press conference
+    /// has had time to end.
+    pub fn fomc(date: &str) -> Option<Self> {
+        let at = time::us_eastern(date, 14, 0)?;
+        Some(Self::around_nanos(
+            format!("FOMC {}", date),
+            at,
+            Duration::from_secs(15 * 60),
+            Duration::from_secs(90 * 60),
+        ))
+    }
+
+    /// CPI release at 8:30am ET on a YYYY-MM-DD date, from 15 minutes before to 30 minutes after.
+    pub fn cpi(date: &str) -> Option<Self> {
+        let at = time::us_eastern(date, 8, 30)?;
+        Some(Self::around_nanos(
+            format!("CPI {}", date),
+            at,
+            Duration::from_secs(15 * 60),
+            Duration::from_secs(30 * 60),
+        ))
+    }
+
+    pub fn action(mut self, action: BlackoutAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Limits the window to one strategy's orders.
+    pub fn strategy(mut self, strategy: impl Into<String>) -> Self {
+        self.strategy = Some(strategy.into());
+        self
+    }
+
+    pub fn contains(&self, nanos: i64) -> bool {
+        (self.start..self.end).contains(&nanos)
+    }
+
+    fn applies_to(&self, strategy: Option<&str>) -> bool {
+        self.strategy.is_none() || self.strategy.as_deref() == strategy
+    }
+
+    fn around_nanos(name: String, at: i64, before: Duration, after: Duration) -> Self {
+        BlackoutWindow {
+            name,
+            start: at - before.as_nanos() as i64,
+            end: at + after.as_nanos() as i64,
+            action: BlackoutAction::Block,
+            strategy: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BlackoutViolation {
+    pub window: String,
+    pub symbol: String,
+}
+
+impl fmt::Display for BlackoutViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "New {} entries are blocked during the {} blackout",
+            self.symbol, self.window
+        )
+    }
+}
+
+impl Error for BlackoutViolation {}
+
+/// Global and per-strategy blackout windows.
+#[derive(Debug, Clone, Default)]
+pub struct BlackoutCalendar {
+    windows: Arc<RwLock<Vec<BlackoutWindow>>>,
+}
+
+impl BlackoutCalendar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_windows(windows: impl IntoIterator<Item = BlackoutWindow>) -> Self {
+        let calendar = BlackoutCalendar::new();
+        for window in windows {
+            calendar.add(window);
+        }
+        calendar
+    }
+
+    pub fn add(&self, window: BlackoutWindow) {
+        self.windows.write().unwrap().push(window);
+    }
+
+    /// Removes every window with the name. Returns false if there was none.
+    pub fn remove(&self, name: &str) -> bool {
+        let mut windows = self.windows.write().unwrap();
+        let before = windows.len();
+        windows.retain(|window| window.name != name);
+        windows.len() != before
+    }
+
+    /// Drops windows that ended before `now`.
+    pub fn prune(&self, now: i64) {
+        self.windows
+            .write()
+            .unwrap()
+            .retain(|window| window.end > now);
+    }
+
+    /// Every window, sorted by start.
+    pub fn windows(&self) -> Vec<BlackoutWindow> {
+        let mut windows = self.windows.read().unwrap().clone();
+        windows.sort_by_key(|window| window.start);
+        windows
+    }
+
+    /// Windows in effect at `now`, global and per-strategy alike.
+    pub fn active(&self, now: i64) -> Vec<BlackoutWindow> {
+        self.windows()
+            .into_iter()
+            .filter(|window| window.contains(now))
+            .collect()
+    }
+
+    /// When the next window starts or ends after `now`.
+    pub fn next_change(&self, now: i64) -> Option<i64> {
+        self.windows
+            .read()
+            .unwrap()
+            .iter()
+            .flat_map(|window| [window.start, window.end])
+            .filter(|at| *at > now)
+            .min()
+    }
+
+    /// Checks an order against the windows in effect now. See `check_at`.
+    pub fn check(&self, order: &Order, position: f64) -> Result<Order, BlackoutViolation> {
+        self.check_at(order, position, time::now_nanos())
+    }
+
+    /// Checks an order against the windows in effect at `now` that apply to its strategy, given the
+    /// current signed `position` in the symbol. Only the part of the order that opens or adds to a position
+    /// is blocked or scaled; the most restrictive window wins. Scaled whole-share quantities are rounded down.
+    pub fn check_at(
+        &self,
+        order: &Order,
+        position: f64,
+        now: i64,
+    ) -> Result<Order, BlackoutViolation> {
+        let strategy = order.metadata.get(STRATEGY_TAG).map(String::as_str);
+        let Some(window) = self
+            .windows
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|window| window.contains(now) && window.applies_to(strategy))
+            .min_by(|a, b| scale(a.action).total_cmp(&scale(b.action)))
+            .cloned()
+        else {
+            return Ok(order.clone());
+        };
+
+        let closing = closing_quantity(order, position);
+        let mut entry = (order.quantity - closing) * scale(window.action);
+        if order.quantity.fract() == 0.0 {
+            entry = entry.floor();
+        }
+        if entry == order.quantity - closing {
+            return Ok(order.clone());
+        }
+        if closing + entry <= 0.0 {
+            return Err(BlackoutViolation {
+                window: window.name,
+                symbol: order.symbol.clone(),
+            });
+        }
+
+        tracing::info!(
+            window = %window.name,
+            symbol = %order.symbol,
+            from = order.quantity,
+            to = closing + entry,
+            "Reduced order during blackout"
+        );
+        let mut order = order.clone();
+        order.quantity = closing + entry;
+        Ok(order)
+    }
+
+    /// Follows the calendar in the background, publishing the windows in effect whenever one starts or ends.
+    pub fn schedule(&self, cancel: CancellationToken) -> BlackoutSchedule {
+        let calendar = self.clone();
+        let (sender, active) = watch::channel(calendar.active(time::now_nanos()));
+        let task = tokio::spawn(async move {
+            loop {
+                let now = time::now_nanos();
+                let wait = calendar
+                    .next_change(now)
+                    .map(|at| Duration::from_nanos((at - now) as u64))
+                    .unwrap_or(MAX_SCHEDULE_SLEEP)
+                    .min(MAX_SCHEDULE_SLEEP);
+                tokio::select! {
+                    _ = tokio::time::sleep(wait) => {}
+                    _ = cancel.cancelled() => return,
+                }
+
+                let now = time::now_nanos();
+                let current = calendar.active(now);
+                let previous = sender.borrow().clone();
+                if current == previous {
+                    continue;
+                }
+                for window in current.iter().filter(|w| !previous.contains(w)) {
+                    tracing::warn!(window = %window.name, strategy = ?window.strategy, action = ?window.action, "Blackout started");
+                }
+                for window in previous.iter().filter(|w| !current.contains(w)) {
+                    tracing::info!(window = %window.name, strategy = ?window.strategy, "Blackout ended");
+                }
+                if sender.send(current).is_err() {
+                    return;
+                }
+                calendar.prune(now);
+            }
+        });
+
+        BlackoutSchedule { active, task }
+    }
+}
+
+fn scale(action: BlackoutAction) -> f64 {
+    match action {
+        BlackoutAction::Block => 0.0,
+        BlackoutAction::Scale(factor) => factor.clamp(0.0, 1.0),
+    }
+}
+
+/// Background task started by `BlackoutCalendar::schedule`. Stops when dropped.
+pub struct BlackoutSchedule {
+    active: watch::Receiver<Vec<BlackoutWindow>>,
+    task: JoinHandle<()>,
+}
+
+impl BlackoutSchedule {
+    /// Windows in effect as of the last change.
+    pub fn active(&self) -> Vec<BlackoutWindow> {
+        self.active.borrow().clone()
+    }
+
+    /// Waits for a window to start or end.
+    pub async fn changed(&mut self) -> Option<Vec<BlackoutWindow>> {
+        self.active.changed().await.ok()?;
+        Some(self.active.borrow().clone())
+    }
+}
+
+impl Drop for BlackoutSchedule {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
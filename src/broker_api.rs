@@ -0,0 +1,204 @@
+use crate::{
+    alpaca::AlpacaClient,
+    datastructures::{
+        account::{Account, Position},
+        broker::{BrokerAccount, CreateAccountRequest, Journal, JournalRequest},
+        config::Config,
+        order::{Order, OrderResponse},
+    },
+};
+use reqwest::Response;
+use serde::de::DeserializeOwned;
+use std::error::Error;
+
+/// Client for Alpaca's Broker API, for apps that open and trade accounts on behalf of end customers rather than
+/// trading their own. Authenticates with the partner's Broker API keys, given as the config's Alpaca keys, and
+/// applies the same rate limiting, retry and circuit breaker settings as `AlpacaClient`.
+#[derive(Clone)]
+pub struct BrokerClient {
+    inner: AlpacaClient,
+}
+
+impl BrokerClient {
+    /// Targets the sandbox unless `enable_real_trading` is set.
+    pub fn new(config: &Config) -> Result<Self, reqwest::Error> {
+        Ok(BrokerClient {
+            inner: AlpacaClient::broker_api(config)?,
+        })
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.inner.base_url(), path)
+    }
+
+    /// Docs: https://docs.alpaca.markets/reference/createaccount
+    pub async fn create_account(
+        &self,
+        request: &CreateAccountRequest,
+    ) -> Result<BrokerAccount, Box<dyn Error>> {
+        let request = self
+            .inner
+            .http_client()
+            .post(self.url("/v1/accounts"))
+            .json(request);
+        parse(self.inner.send(request, false).await?).await
+    }
+
+    /// Docs: https://docs.alpaca.markets/reference/getaccount
+    pub async fn get_account(&self, account_id: &str) -> Result<BrokerAccount, Box<dyn Error>> {
+        let request = self
+            .inner
+            .http_client()
+            .get(self.url(&format!("/v1/accounts/{}", account_id)));
+        parse(self.inner.send(request, true).await?).await
+    }
+
+    /// Docs: https://docs.alpaca.markets/reference/getallaccounts
+    pub async fn list_accounts(&self) -> Result<Vec<BrokerAccount>, Box<dyn Error>> {
+        let request = self.inner.http_client().get(self.url("/v1/accounts"));
+        parse(self.inner.send(request, true).await?).await
+    }
+
+    /// Docs: https://docs.alpaca.markets/reference/createjournal
+    pub async fn create_journal(
+        &self,
+        request: &JournalRequest,
+    ) -> Result<Journal, Box<dyn Error>> {
+        let request = self
+            .inner
+            .http_client()
+            .post(self.url("/v1/journals"))
+            .json(request);
+        parse(self.inner.send(request, false).await?).await
+    }
+
+    /// Docs: https://docs.alpaca.markets/reference/getalljournals
+    pub async fn list_journals(&self) -> Result<Vec<Journal>, Box<dyn Error>> {
+        let request = self.inner.http_client().get(self.url("/v1/journals"));
+        parse(self.inner.send(request, true).await?).await
+    }
+
+    /// Trading on behalf of one customer account.
+    pub fn trading(&self, account_id: impl Into<String>) -> AccountTrading {
+        AccountTrading {
+            client: self.clone(),
+            account_id: account_id.into(),
+        }
+    }
+}
+
+/// Orders, positions and balances of one customer account, using the same types as `AlpacaClient`.
+/// Order metadata is recorded to and read back from the config's journal as usual.
+#[derive(Clone)]
+pub struct AccountTrading {
+    client: BrokerClient,
+    account_id: String,
+}
+
+impl AccountTrading {
+    pub fn account_id(&self) -> &str {
+        &self.account_id
+    }
+
+    fn url(&self, path: &str) -> String {
+        self.client
+            .url(&format!("/v1/trading/accounts/{}{}", self.account_id, path))
+    }
+
+    fn annotate(&self, order: &mut OrderResponse) {
+        if let Some(journal) = self.client.inner.journal() {
+            journal.annotate(order);
+        }
+    }
+
+    /// Docs: https://docs.alpaca.markets/reference/createorderforaccount
+    pub async fn create_order(&self, order: &Order) -> Result<OrderResponse, Box<dyn Error>> {
+        if let Some(journal) = self.client.inner.journal() {
+            journal.record(order)?;
+        }
+        let request = self
+            .client
+            .inner
+            .http_client()
+            .post(self.url("/orders"))
+            .json(order);
+        let mut accepted: OrderResponse = parse(
+            self.client
+                .inner
+                .send(request, order.client_order_id.is_some())
+                .await?,
+        )
+        .await?;
+        accepted.metadata = order.metadata.clone();
+        Ok(accepted)
+    }
+
+    /// Docs: https://docs.alpaca.markets/reference/getallordersforaccount
+    pub async fn get_open_orders(&self) -> Result<Vec<OrderResponse>, Box<dyn Error>> {
+        let request = self
+            .client
+            .inner
+            .http_client()
+            .get(self.url("/orders"))
+            .query(&[("status", "open"), ("direction", "asc"), ("limit", "500")]);
+        let mut orders: Vec<OrderResponse> =
+            parse(self.client.inner.send(request, true).await?).await?;
+        for order in &mut orders {
+            self.annotate(order);
+        }
+        Ok(orders)
+    }
+
+    /// Docs: https://docs.alpaca.markets/reference/getorderforaccount
+    pub async fn get_order(&self, order_id: &str) -> Result<OrderResponse, Box<dyn Error>> {
+        let request = self
+            .client
+            .inner
+            .http_client()
+            .get(self.url(&format!("/orders/{}", order_id)));
+        let mut order: OrderResponse = parse(self.client.inner.send(request, true).await?).await?;
+        self.annotate(&mut order);
+        Ok(order)
+    }
+
+    /// Requests the cancel without waiting for it to take effect; poll `get_order` to confirm.
+    /// Docs: https://docs.alpaca.markets/reference/deleteorderforaccount
+    pub async fn cancel_order(&self, order_id: &str) -> Result<(), Box<dyn Error>> {
+        let request = self
+            .client
+            .inner
+            .http_client()
+            .delete(self.url(&format!("/orders/{}", order_id)));
+        let response = self.client.inner.send(request, true).await?;
+        if !response.status().is_success() {
+            return Err(format!(
+                "Cancel order failed with status code: {}",
+                response.status()
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Docs: https://docs.alpaca.markets/reference/gettradingaccount
+    pub async fn get_account(&self) -> Result<Account, Box<dyn Error>> {
+        let request = self.client.inner.http_client().get(self.url("/account"));
+        parse(self.client.inner.send(request, true).await?).await
+    }
+
+    /// Docs: https://docs.alpaca.markets/reference/getpositionsforaccount
+    pub async fn get_positions(&self) -> Result<Vec<Position>, Box<dyn Error>> {
+        let request = self.client.inner.http_client().get(self.url("/positions"));
+        parse(self.client.inner.send(request, true).await?).await
+    }
+}
+
+/// Decodes a successful response, or turns an error response into an error carrying Alpaca's message.
+async fn parse<T: DeserializeOwned>(response: Response) -> Result<T, Box<dyn Error>> {
+    let status = response.status();
+    let body = response.text().await?;
+    if !status.is_success() {
+        return Err(format!("Broker API request failed with status {}: {}", status, body).into());
+    }
+    Ok(serde_json::from_str(&body)?)
+}
@@ -0,0 +1,152 @@
+use crate::{
+    datastructures::{
+        account::{Account, Position},
+        client::BrokerClient,
+        order::{Order, OrderSide},
+    },
+    risk::closing_quantity,
+};
+use std::{
+    error::Error,
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct BuyingPowerConfig {
+    /// How long account state is reused before it is read again.
+    pub max_age: Duration,
+    /// Fraction of buying power kept in reserve, e.g. 0.02 so orders priced at a stale quote still fit.
+    pub headroom: f64,
+}
+
+impl Default for BuyingPowerConfig {
+    fn default() -> Self {
+        BuyingPowerConfig {
+            max_age: Duration::from_secs(5),
+            headroom: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InsufficientBuyingPower {
+    pub symbol: String,
+    /// Estimated notional of the part of the order that opens or adds to a position.
+    pub required: f64,
+    pub available: f64,
+}
+
+impl fmt::Display for InsufficientBuyingPower {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Insufficient buying power for {}: order needs about {:.2}, {:.2} available",
+            self.symbol, self.required, self.available
+        )
+    }
+}
+
+impl Error for InsufficientBuyingPower {}
+
+struct CachedAccount {
+    read_at: Instant,
+    buying_power: f64,
+    positions: Vec<Position>,
+}
+
+/// Checks that an order fits in the account's buying power before it is sent, so it fails locally instead
+/// of being rejected by the broker. Account state is cached for `max_age`, and the notional of each order
+/// that passes is deducted from the cached buying power so a burst of orders cannot overspend it.
+/// Add it to a client with `ClientBuilder::with` to check every `create_order`, or call `check` directly.
+#[derive(Clone)]
+pub struct BuyingPowerCheck {
+    config: BuyingPowerConfig,
+    cached: Arc<Mutex<Option<CachedAccount>>>,
+}
+
+impl BuyingPowerCheck {
+    pub fn new(config: BuyingPowerConfig) -> Self {
+        BuyingPowerCheck {
+            config,
+            cached: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Replaces the cached account state, e.g. with an account and positions read for another purpose.
+    pub fn update(&self, account: &Account, positions: Vec<Position>) {
+        *self.cached.lock().unwrap() = Some(CachedAccount {
+            read_at: Instant::now(),
+            buying_power: account.buying_power,
+            positions,
+        });
+    }
+
+    /// Forgets the cached state so the next check reads it again, e.g. after a fill or a cancel.
+    pub fn invalidate(&self) {
+        self.cached.lock().unwrap().take();
+    }
+
+    /// Fails if the order's estimated notional is more than the buying power available. Orders that only
+    /// reduce a position always pass. Market orders are valued at the latest quote.
+    pub async fn check<C: BrokerClient + ?Sized>(
+        &self,
+        client: &C,
+        order: &Order,
+    ) -> Result<(), Box<dyn Error>> {
+        let fresh = self
+            .cached
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|cached| cached.read_at.elapsed() < self.config.max_age);
+        if !fresh {
+            let account = client.get_account().await?;
+            let positions = client.get_positions().await?;
+            self.update(&account, positions);
+        }
+
+        let opening = {
+            let cached = self.cached.lock().unwrap();
+            let position = cached
+                .as_ref()
+                .and_then(|cached| cached.positions.iter().find(|p| p.symbol == order.symbol))
+                .map(|position| position.qty)
+                .unwrap_or_default();
+            order.quantity - closing_quantity(order, position)
+        };
+        if opening <= 0.0 {
+            return Ok(());
+        }
+
+        let price = match order.limit_price.or(order.stop_price) {
+            Some(price) => price,
+            None => {
+                let quote = client.get_latest_quote(&order.symbol).await?;
+                match order.side {
+                    OrderSide::Buy if quote.ask_price > 0.0 => quote.ask_price,
+                    OrderSide::Sell if quote.bid_price > 0.0 => quote.bid_price,
+                    _ => quote.mid(),
+                }
+            }
+        };
+        let required = opening * price;
+
+        let mut cached = self.cached.lock().unwrap();
+        let Some(cached) = cached.as_mut() else {
+            return Ok(());
+        };
+        let available = cached.buying_power * (1.0 - self.config.headroom.clamp(0.0, 1.0));
+        if required > available {
+            return Err(InsufficientBuyingPower {
+                symbol: order.symbol.clone(),
+                required,
+                available: available.max(0.0),
+            }
+            .into());
+        }
+        cached.buying_power -= required;
+        Ok(())
+    }
+}
@@ -0,0 +1,212 @@
+use crate::{
+    datastructures::{
+        client::{FeedType, MarketDataClient, SubscriptionParamsBuilder},
+        event::ParseMode,
+    },
+    time,
+};
+use futures_util::StreamExt;
+use serde_json::Value;
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+use tokio_tungstenite::tungstenite::Message;
+
+/// The symbol Alpaca's test feed streams around the clock.
+pub const TEST_SYMBOL: &str = "FAKEPACA";
+
+#[derive(Debug, Clone)]
+pub struct ConnectivityConfig {
+    /// How long the connection, authentication and subscription acknowledgement may take.
+    pub handshake_timeout: Duration,
+    /// How long to collect events once subscribed.
+    pub listen: Duration,
+}
+
+impl Default for ConnectivityConfig {
+    fn default() -> Self {
+        ConnectivityConfig {
+            handshake_timeout: Duration::from_secs(10),
+            listen: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Delay from an event's own timestamp to its arrival. Includes any difference between the local clock and
+/// Alpaca's.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LatencySummary {
+    pub min: Duration,
+    pub median: Duration,
+    pub max: Duration,
+}
+
+/// Outcome of a connectivity check against the test feed.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectivityReport {
+    /// Time to connect, authenticate and have the subscription acknowledged. `None` if that failed.
+    pub handshake: Option<Duration>,
+    /// Time from the acknowledgement to the first event.
+    pub first_event: Option<Duration>,
+    pub events: u64,
+    pub latency: Option<LatencySummary>,
+    /// Why the check failed.
+    pub error: Option<String>,
+}
+
+impl ConnectivityReport {
+    /// The handshake succeeded and at least one event arrived.
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none() && self.handshake.is_some() && self.events > 0
+    }
+}
+
+impl fmt::Display for ConnectivityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.handshake {
+            Some(handshake) => write!(f, "handshake {:?}", handshake)?,
+            None => write!(f, "handshake failed")?,
+        }
+        write!(f, ", {} events", self.events)?;
+        if let Some(first_event) = self.first_event {
+            write!(f, ", first after {:?}", first_event)?;
+        }
+        if let Some(latency) = self.latency {
+            write!(
+                f,
+                ", latency min {:?} median {:?} max {:?}",
+                latency.min, latency.median, latency.max
+            )?;
+        }
+        if let Some(error) = &self.error {
+            write!(f, ": {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Subscribes to trades, quotes and bars of `TEST_SYMBOL` on the test feed, checks the handshake and listens
+/// for `config.listen`, so credentials and the network path can be verified before markets open. Failures
+/// are reported rather than returned.
+pub async fn check_test_feed<C: MarketDataClient>(
+    client: &C,
+    config: &ConnectivityConfig,
+) -> ConnectivityReport {
+    let mut report = ConnectivityReport::default();
+    let params = SubscriptionParamsBuilder::new()
+        .feed_type(FeedType::Test)
+        .trades(&[TEST_SYMBOL])
+        .quotes(&[TEST_SYMBOL])
+        .bars(&[TEST_SYMBOL])
+        .build();
+
+    let started = Instant::now();
+    let handshake = tokio::time::timeout(config.handshake_timeout, async {
+        let mut socket = client.subscribe(params).await.map_err(|e| e.to_string())?;
+        // Events that arrive before the acknowledgement are kept for the report.
+        let mut early = vec![];
+        loop {
+            let text = match socket.next().await {
+                Some(Ok(Message::Text(text))) => text,
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => return Err(e.to_string()),
+                None => {
+                    return Err(
+                        "Connection closed before the subscription was acknowledged".to_string()
+                    )
+                }
+            };
+            let messages: Vec<Value> = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+            for message in &messages {
+                match message["T"].as_str() {
+                    Some("error") => {
+                        return Err(format!(
+                            "Stream error {}: {}",
+                            message["code"], message["msg"]
+                        ))
+                    }
+                    Some("subscription") => {
+                        let trades = message["trades"].as_array();
+                        if !trades.is_some_and(|trades| trades.iter().any(|s| s == TEST_SYMBOL)) {
+                            return Err(format!("Subscription not acknowledged: {}", message));
+                        }
+                        return Ok((socket, early));
+                    }
+                    _ => {}
+                }
+            }
+            early.push((text, Instant::now()));
+        }
+    })
+    .await;
+    let (mut socket, early) = match handshake {
+        Ok(Ok(connected)) => connected,
+        Ok(Err(e)) => {
+            report.error = Some(e);
+            return report;
+        }
+        Err(_) => {
+            report.error = Some(format!(
+                "Handshake took longer than {:?}",
+                config.handshake_timeout
+            ));
+            return report;
+        }
+    };
+    let subscribed = Instant::now();
+    report.handshake = Some(subscribed - started);
+
+    let mut latencies = vec![];
+    let mut record = |text: &str, received: Instant, report: &mut ConnectivityReport| {
+        let Ok(events) = client.parse_frame(text, ParseMode::Lenient) else {
+            return;
+        };
+        let now = time::now_nanos();
+        for event in events.iter() {
+            report.events += 1;
+            report
+                .first_event
+                .get_or_insert(received.saturating_duration_since(subscribed));
+            if let Some(timestamp) = event.timestamp().and_then(time::parse_rfc3339) {
+                latencies.push(Duration::from_nanos((now - timestamp).max(0) as u64));
+            }
+        }
+    };
+    for (text, received) in early {
+        record(&text, received, &mut report);
+    }
+    let deadline = tokio::time::sleep(config.listen);
+    tokio::pin!(deadline);
+    loop {
+        tokio::select! {
+            message = socket.next() => match message {
+                Some(Ok(Message::Text(text))) => record(&text, Instant::now(), &mut report),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => {
+                    report.error = Some(e.to_string());
+                    break;
+                }
+                None => {
+                    report.error = Some("Connection closed while listening".to_string());
+                    break;
+                }
+            },
+            _ = &mut deadline => break,
+        }
+    }
+    let _ = socket.close(None).await;
+
+    latencies.sort();
+    if let (Some(&min), Some(&max)) = (latencies.first(), latencies.last()) {
+        report.latency = Some(LatencySummary {
+            min,
+            median: latencies[latencies.len() / 2],
+            max,
+        });
+    }
+    if report.error.is_none() && report.events == 0 {
+        report.error = Some(format!("No events within {:?}", config.listen));
+    }
+    report
+}
@@ -1,10 +1,145 @@
-struct AccountStatus {
-    balance: f32,
-    positions: Vec<Position>,
+use super::number;
+use serde::{Deserialize, Serialize};
+
+/// Docs: https://docs.alpaca.markets/reference/getaccount-1
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Account {
+    pub id: String,
+    pub status: String,
+    pub currency: String,
+    #[serde(deserialize_with = "number::deserialize")]
+    pub cash: f64,
+    #[serde(deserialize_with = "number::deserialize")]
+    pub equity: f64,
+    #[serde(deserialize_with = "number::deserialize")]
+    pub last_equity: f64,
+    #[serde(deserialize_with = "number::deserialize")]
+    pub buying_power: f64,
+    #[serde(deserialize_with = "number::deserialize")]
+    pub long_market_value: f64,
+    #[serde(deserialize_with = "number::deserialize")]
+    pub short_market_value: f64,
+    /// Buying power that does not rely on margin or unsettled proceeds; in a cash account, settled cash.
+    #[serde(default, deserialize_with = "number::deserialize_option")]
+    pub non_marginable_buying_power: Option<f64>,
+}
+
+impl Account {
+    /// Cash that can be spent now. Falls back to the cash balance when the broker does not report it.
+    pub fn settled_cash(&self) -> f64 {
+        self.non_marginable_buying_power.unwrap_or(self.cash)
+    }
+}
+
+/// Docs: https://docs.alpaca.markets/reference/getallopenpositions
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Position {
+    pub symbol: String,
+    pub exchange: String,
+    pub asset_class: String,
+    /// Negative for short positions.
+    #[serde(deserialize_with = "number::deserialize")]
+    pub qty: f64,
+    #[serde(deserialize_with = "number::deserialize")]
+    pub avg_entry_price: f64,
+    /// Negative for short positions.
+    #[serde(deserialize_with = "number::deserialize")]
+    pub market_value: f64,
+    #[serde(default, deserialize_with = "number::deserialize_option")]
+    pub current_price: Option<f64>,
+    #[serde(deserialize_with = "number::deserialize")]
+    pub unrealized_pl: f64,
+}
+
+/// What a non-trading account activity was.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityKind {
+    Deposit,
+    Withdrawal,
+    /// Including taxes withheld from one, which come as their own negative entries.
+    Dividend,
+    Interest,
+    Fee,
+    /// Cash or shares journaled from or to another account.
+    Transfer,
+    /// Split, merger, spin-off, name change or similar, changing holdings rather than cash.
+    CorporateAction,
+    /// Anything else, with the broker's code for it.
+    Other(String),
 }
 
-struct Position {
-    symbol: String,
-    quantity: u32,
-    average_price: f32,
-}
\ No newline at end of file
+impl ActivityKind {
+    /// Docs: https://docs.alpaca.markets/reference/getaccountactivities-1
+    pub fn from_alpaca(code: &str) -> Self {
+        match code {
+            "CSD" => ActivityKind::Deposit,
+            "CSW" => ActivityKind::Withdrawal,
+            _ if code.starts_with("DIV") => ActivityKind::Dividend,
+            "INT" | "INTNRA" | "INTTW" => ActivityKind::Interest,
+            "FEE" | "PTC" => ActivityKind::Fee,
+            "JNL" | "JNLC" | "JNLS" => ActivityKind::Transfer,
+            "MA" | "NC" | "REORG" | "SC" | "SSO" | "SSP" | "SPIN" | "SPLIT" => {
+                ActivityKind::CorporateAction
+            }
+            other => ActivityKind::Other(other.to_string()),
+        }
+    }
+
+    /// Cash moved in or out of the account rather than earned or spent in it.
+    pub fn is_cash_flow(&self) -> bool {
+        matches!(
+            self,
+            ActivityKind::Deposit | ActivityKind::Withdrawal | ActivityKind::Transfer
+        )
+    }
+}
+
+/// Entry on the account's ledger other than a fill, e.g. a deposit or a dividend. These explain changes in
+/// equity that are not trading P/L.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountActivity {
+    pub id: String,
+    pub kind: ActivityKind,
+    /// YYYY-MM-DD.
+    pub date: String,
+    /// Cash in, or out when negative.
+    pub net_amount: f64,
+    pub symbol: Option<String>,
+    /// Shares added, or removed when negative, by a corporate action or a transfer of shares.
+    pub quantity: Option<f64>,
+    pub description: String,
+}
+
+/// Docs: https://docs.alpaca.markets/reference/getaccountactivities-1
+#[cfg(feature = "alpaca")]
+#[derive(Deserialize)]
+pub(crate) struct RawActivity {
+    pub(crate) id: String,
+    activity_type: String,
+    #[serde(default)]
+    date: String,
+    #[serde(default, deserialize_with = "number::deserialize_option")]
+    net_amount: Option<f64>,
+    symbol: Option<String>,
+    #[serde(default, deserialize_with = "number::deserialize_option")]
+    qty: Option<f64>,
+    #[serde(default)]
+    description: String,
+}
+
+#[cfg(feature = "alpaca")]
+impl RawActivity {
+    pub(crate) fn into_activity(self) -> AccountActivity {
+        AccountActivity {
+            kind: ActivityKind::from_alpaca(&self.activity_type),
+            id: self.id,
+            // Sometimes a full timestamp.
+            date: self.date.chars().take(10).collect(),
+            net_amount: self.net_amount.unwrap_or_default(),
+            symbol: self.symbol.filter(|symbol| !symbol.is_empty()),
+            quantity: self.qty,
+            description: self.description,
+        }
+    }
+}
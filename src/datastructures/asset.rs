@@ -1,7 +1,29 @@
 use serde::Deserialize;
 
-#[derive(Debug, Deserialize)]
+/// Docs: https://docs.alpaca.markets/reference/get-v2-assets-1
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct Asset {
     pub symbol: String,
     pub exchange: String,
+    /// "us_equity" or "crypto".
+    #[serde(default, rename = "class")]
+    pub asset_class: String,
+    /// "active" or "inactive".
+    #[serde(default)]
+    pub status: String,
+    #[serde(default)]
+    pub tradable: bool,
+    #[serde(default)]
+    pub shortable: bool,
+    #[serde(default)]
+    pub fractionable: bool,
+    /// E.g. "options_enabled".
+    #[serde(default)]
+    pub attributes: Vec<String>,
+}
+
+impl Asset {
+    pub fn is_optionable(&self) -> bool {
+        self.attributes.iter().any(|a| a == "options_enabled")
+    }
 }
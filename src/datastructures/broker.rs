@@ -0,0 +1,159 @@
+use super::number;
+use serde::{Deserialize, Serialize};
+
+/// Contact details of an end customer.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Contact {
+    pub email_address: String,
+    pub phone_number: String,
+    pub street_address: Vec<String>,
+    pub city: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    pub postal_code: String,
+    /// ISO 3166-1 alpha-3, e.g. "USA".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Identity {
+    pub given_name: String,
+    pub family_name: String,
+    /// YYYY-MM-DD.
+    pub date_of_birth: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tax_id: Option<String>,
+    /// E.g. "USA_SSN".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tax_id_type: Option<String>,
+    pub country_of_citizenship: String,
+    pub country_of_birth: String,
+    pub country_of_tax_residence: String,
+    /// E.g. ["employment_income"].
+    pub funding_source: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Disclosures {
+    pub is_control_person: bool,
+    pub is_affiliated_exchange_or_finra: bool,
+    pub is_politically_exposed: bool,
+    pub immediate_family_exposed: bool,
+}
+
+/// An agreement the customer accepted, e.g. "customer_agreement" or "margin_agreement".
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Agreement {
+    pub agreement: String,
+    /// RFC 3339.
+    pub signed_at: String,
+    pub ip_address: String,
+}
+
+/// Docs: https://docs.alpaca.markets/reference/createaccount
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CreateAccountRequest {
+    pub contact: Contact,
+    pub identity: Identity,
+    pub disclosures: Disclosures,
+    pub agreements: Vec<Agreement>,
+    /// Asset classes the account may trade, e.g. ["us_equity", "crypto"]. Alpaca's default if empty.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub enabled_assets: Vec<String>,
+}
+
+/// End customer account under a Broker API partner.
+/// Docs: https://docs.alpaca.markets/reference/getaccount
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BrokerAccount {
+    pub id: String,
+    pub account_number: String,
+    /// E.g. "SUBMITTED", "APPROVED", "ACTIVE".
+    pub status: String,
+    pub currency: String,
+    #[serde(default, deserialize_with = "number::deserialize_option")]
+    pub last_equity: Option<f64>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum JournalEntryType {
+    /// Cash moved between accounts.
+    #[serde(rename = "JNLC")]
+    Cash,
+    /// Securities moved between accounts.
+    #[serde(rename = "JNLS")]
+    Securities,
+}
+
+/// Moves cash or securities between accounts, e.g. from the partner's firm account to a customer's.
+/// Docs: https://docs.alpaca.markets/reference/createjournal
+#[derive(Debug, Clone, Serialize)]
+pub struct JournalRequest {
+    pub from_account: String,
+    pub to_account: String,
+    pub entry_type: JournalEntryType,
+    /// Cash journals only; sent as a string like every Alpaca decimal.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<String>,
+    /// Securities journals only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub qty: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl JournalRequest {
+    pub fn cash(from_account: &str, to_account: &str, amount: f64) -> Self {
+        JournalRequest {
+            from_account: from_account.to_string(),
+            to_account: to_account.to_string(),
+            entry_type: JournalEntryType::Cash,
+            amount: Some(amount.to_string()),
+            symbol: None,
+            qty: None,
+            description: None,
+        }
+    }
+
+    pub fn securities(from_account: &str, to_account: &str, symbol: &str, qty: f64) -> Self {
+        JournalRequest {
+            from_account: from_account.to_string(),
+            to_account: to_account.to_string(),
+            entry_type: JournalEntryType::Securities,
+            amount: None,
+            symbol: Some(symbol.to_string()),
+            qty: Some(qty.to_string()),
+            description: None,
+        }
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+}
+
+/// Docs: https://docs.alpaca.markets/reference/getalljournals
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Journal {
+    pub id: String,
+    pub entry_type: JournalEntryType,
+    pub from_account: String,
+    pub to_account: String,
+    /// E.g. "queued", "pending", "executed", "rejected".
+    pub status: String,
+    #[serde(default, deserialize_with = "number::deserialize_option")]
+    pub net_amount: Option<f64>,
+    #[serde(default)]
+    pub symbol: Option<String>,
+    #[serde(default, deserialize_with = "number::deserialize_option")]
+    pub qty: Option<f64>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub settle_date: Option<String>,
+}
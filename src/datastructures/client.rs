@@ -1,9 +1,21 @@
-use super::{asset::Asset, config::Config, order::Order};
+use super::{
+    account::{Account, AccountActivity, Position},
+    asset::Asset,
+    config::Config,
+    event::{EventBatch, EventType, ParseMode},
+    market::{Bar, BarAdjustment, Quote},
+    order::{CancelOutcome, Order, OrderResponse},
+};
+#[cfg(feature = "alpaca")]
+use crate::alpaca::AlpacaClient;
+use crate::sim::{SimClient, SimConfig};
 use async_trait::async_trait;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, str::FromStr, sync::Arc};
 use tokio::net::TcpStream;
 use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum FeedType {
     Stocks,
     Crypto,
@@ -12,16 +24,78 @@ pub enum FeedType {
     Test,
 }
 
+/// How many order book levels per side to keep from the orderbook channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BookDepth {
+    #[default]
+    Full,
+    /// Only the best `n` price levels on each side.
+    Top(usize),
+}
+
+impl BookDepth {
+    /// Keeps the best levels of one side of a book snapshot. Bids are sorted highest first, asks lowest first.
+    /// Not for incremental updates, whose levels are changes rather than the book.
+    pub fn truncate(&self, levels: &mut Vec<(f64, f64)>, bids: bool) {
+        let BookDepth::Top(depth) = *self else {
+            return;
+        };
+        if levels.len() <= depth {
+            return;
+        }
+        if bids {
+            levels.sort_by(|a, b| b.0.total_cmp(&a.0));
+        } else {
+            levels.sort_by(|a, b| a.0.total_cmp(&b.0));
+        }
+        levels.truncate(depth);
+    }
+}
+
+#[derive(Clone)]
 pub struct SubscriptionParams {
     pub feed_type: FeedType,
     pub subscription_request: SubscriptionRequest,
 }
 
+impl SubscriptionParams {
+    /// Splits the subscription into several whose requests each cover at most `max_symbols` distinct symbols.
+    /// A wildcard ("*") subscription cannot be split and is kept on the first shard.
+    pub fn shard(&self, max_symbols: usize) -> Vec<SubscriptionParams> {
+        let request = &self.subscription_request;
+        let mut seen = HashSet::new();
+        let symbols: Vec<&'static str> = request
+            .channels()
+            .flatten()
+            .copied()
+            .filter(|symbol| seen.insert(*symbol))
+            .collect();
+
+        if max_symbols == 0 || symbols.len() <= max_symbols || symbols.contains(&"*") {
+            return vec![self.clone()];
+        }
+
+        symbols
+            .chunks(max_symbols)
+            .map(|chunk| SubscriptionParams {
+                feed_type: self.feed_type,
+                subscription_request: request.restricted_to(chunk),
+            })
+            .collect()
+    }
+}
+
 pub struct SubscriptionParamsBuilder {
     feed_type: Option<FeedType>,
     subscription_request: SubscriptionRequestBuilder,
 }
 
+impl Default for SubscriptionParamsBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SubscriptionParamsBuilder {
     pub fn new() -> Self {
         SubscriptionParamsBuilder {
@@ -65,6 +139,21 @@ impl SubscriptionParamsBuilder {
         self
     }
 
+    pub fn orderbook_depth(mut self, orderbook_depth: BookDepth) -> Self {
+        self.subscription_request = self.subscription_request.orderbook_depth(orderbook_depth);
+        self
+    }
+
+    pub fn lulds(mut self, lulds: &[&'static str]) -> Self {
+        self.subscription_request = self.subscription_request.lulds(lulds);
+        self
+    }
+
+    pub fn news(mut self, news: &[&'static str]) -> Self {
+        self.subscription_request = self.subscription_request.news(news);
+        self
+    }
+
     pub fn build(self) -> SubscriptionParams {
         SubscriptionParams {
             feed_type: self.feed_type.expect("FeedType is required"),
@@ -73,7 +162,7 @@ impl SubscriptionParamsBuilder {
     }
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Serialize)]
 pub struct SubscriptionRequest {
     /// Always "subscribe"
     pub action: &'static str,
@@ -84,7 +173,58 @@ pub struct SubscriptionRequest {
     pub updated_bars: Vec<&'static str>, // camelcase?
     pub daily_bars: Vec<&'static str>,   // camelcase?
     pub orderbooks: Vec<&'static str>,
+    /// Alpaca always streams the full book, so the depth is applied to orderbook messages as they arrive
+    /// rather than sent with the request. Levels removed near the top are not backfilled from deeper ones.
+    #[serde(skip)]
+    pub orderbook_depth: BookDepth,
+    /// Limit up/limit down price bands. Stocks only.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub lulds: Vec<&'static str>,
+    /// News articles mentioning the symbols, or "*" for all. News feed only.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub news: Vec<&'static str>,
+}
+
+impl SubscriptionRequest {
+    fn channels(&self) -> impl Iterator<Item = &Vec<&'static str>> {
+        [
+            &self.trades,
+            &self.quotes,
+            &self.bars,
+            &self.updated_bars,
+            &self.daily_bars,
+            &self.orderbooks,
+            &self.lulds,
+            &self.news,
+        ]
+        .into_iter()
+    }
+
+    /// Copy of this request that only keeps the given symbols on every channel.
+    fn restricted_to(&self, symbols: &[&'static str]) -> SubscriptionRequest {
+        let keep = |channel: &Vec<&'static str>| {
+            channel
+                .iter()
+                .filter(|symbol| symbols.contains(symbol))
+                .copied()
+                .collect()
+        };
+
+        SubscriptionRequest {
+            action: self.action,
+            trades: keep(&self.trades),
+            quotes: keep(&self.quotes),
+            bars: keep(&self.bars),
+            updated_bars: keep(&self.updated_bars),
+            daily_bars: keep(&self.daily_bars),
+            orderbooks: keep(&self.orderbooks),
+            orderbook_depth: self.orderbook_depth,
+            lulds: keep(&self.lulds),
+            news: keep(&self.news),
+        }
+    }
 }
+
 pub struct SubscriptionRequestBuilder {
     trades: Vec<&'static str>,
     quotes: Vec<&'static str>,
@@ -92,6 +232,15 @@ pub struct SubscriptionRequestBuilder {
     updated_bars: Vec<&'static str>,
     daily_bars: Vec<&'static str>,
     orderbooks: Vec<&'static str>,
+    orderbook_depth: BookDepth,
+    lulds: Vec<&'static str>,
+    news: Vec<&'static str>,
+}
+
+impl Default for SubscriptionRequestBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SubscriptionRequestBuilder {
@@ -103,6 +252,9 @@ impl SubscriptionRequestBuilder {
             updated_bars: vec![],
             daily_bars: vec![],
             orderbooks: vec![],
+            orderbook_depth: BookDepth::Full,
+            lulds: vec![],
+            news: vec![],
         }
     }
 
@@ -136,6 +288,22 @@ impl SubscriptionRequestBuilder {
         self
     }
 
+    /// Crypto only. Defaults to the full book.
+    pub fn orderbook_depth(mut self, orderbook_depth: BookDepth) -> Self {
+        self.orderbook_depth = orderbook_depth;
+        self
+    }
+
+    pub fn lulds(mut self, lulds: &[&'static str]) -> Self {
+        self.lulds = lulds.to_vec();
+        self
+    }
+
+    pub fn news(mut self, news: &[&'static str]) -> Self {
+        self.news = news.to_vec();
+        self
+    }
+
     pub fn build(self) -> SubscriptionRequest {
         SubscriptionRequest {
             action: "subscribe",
@@ -145,19 +313,356 @@ impl SubscriptionRequestBuilder {
             updated_bars: self.updated_bars,
             daily_bars: self.daily_bars,
             orderbooks: self.orderbooks,
+            orderbook_depth: self.orderbook_depth,
+            lulds: self.lulds,
+            news: self.news,
         }
     }
 }
 
+/// A broker account: orders, positions and account state. Object safe, so clients of different brokers can be
+/// held as `Box<dyn TradingClient + Send + Sync>`; boxed and `Arc`-wrapped clients implement the trait
+/// themselves and work wherever a client is expected. Market data comes from a `MarketDataClient`, which may be
+/// another provider; see `SplitClient`.
 #[async_trait]
 pub trait TradingClient {
-    fn new(config: &Config) -> Self
-    where
-        Self: Sized;
     async fn create_order(&self, order: &Order) -> Result<(), Box<dyn std::error::Error>>; // TODO: OrderResponse
+    /// Orders that are still working, oldest first.
+    async fn get_open_orders(&self) -> Result<Vec<OrderResponse>, Box<dyn std::error::Error>>;
+    async fn get_order(&self, order_id: &str) -> Result<OrderResponse, Box<dyn std::error::Error>>;
+    async fn get_order_by_client_id(
+        &self,
+        client_order_id: &str,
+    ) -> Result<OrderResponse, Box<dyn std::error::Error>>;
+    /// Cancels an open order and waits until the broker reports it in a terminal state, so a cancel
+    /// that lost a race against a fill can be told apart from one that took effect.
+    async fn cancel_order(
+        &self,
+        order_id: &str,
+    ) -> Result<CancelOutcome, Box<dyn std::error::Error>>;
     async fn get_asset(&self, symbol: &str) -> Result<Asset, Box<dyn std::error::Error>>;
+    /// Every active asset.
+    async fn list_assets(&self) -> Result<Vec<Asset>, Box<dyn std::error::Error>>;
+    async fn get_account(&self) -> Result<Account, Box<dyn std::error::Error>>;
+    async fn get_positions(&self) -> Result<Vec<Position>, Box<dyn std::error::Error>>;
+    /// Non-trading account activity dated `since` (YYYY-MM-DD) or later, e.g. deposits, withdrawals and
+    /// dividends, oldest first. Defaults to none, for brokers that do not report it.
+    async fn get_account_activities(
+        &self,
+        _since: &str,
+    ) -> Result<Vec<AccountActivity>, Box<dyn std::error::Error>> {
+        Ok(vec![])
+    }
+}
+
+/// Streaming and historical market data. Object safe, and implemented for boxed and `Arc`-wrapped clients like
+/// `TradingClient`.
+#[async_trait]
+pub trait MarketDataClient {
+    /// Latest quote snapshot over REST, for when the streamed quote cannot be trusted.
+    async fn get_latest_quote(&self, symbol: &str) -> Result<Quote, Box<dyn std::error::Error>>;
+    /// Official daily bars for the trading days from `start` to `end` inclusive, as YYYY-MM-DD dates, adjusted
+    /// for corporate actions as asked. Where the provider cannot adjust them, they are adjusted locally from
+    /// its corporate actions.
+    async fn get_daily_bars(
+        &self,
+        symbol: &str,
+        start: &str,
+        end: &str,
+        adjustment: BarAdjustment,
+    ) -> Result<Vec<Bar>, Box<dyn std::error::Error>>;
     async fn subscribe(
         &self,
         params: SubscriptionParams,
     ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, Box<dyn std::error::Error>>;
+    /// Parses a text frame of a stream opened by `subscribe`. Defaults to Alpaca's message format; providers
+    /// with their own format map it into the same events.
+    fn parse_frame(&self, frame: &str, mode: ParseMode) -> Result<EventBatch, serde_json::Error> {
+        EventType::parse_batch_with(frame, mode)
+    }
+}
+
+/// A client that both trades and serves market data, as `AlpacaClient` does. Implemented for every type
+/// implementing both traits, so `Box<dyn BrokerClient + Send + Sync>` can stand in for either.
+pub trait BrokerClient: TradingClient + MarketDataClient {}
+
+impl<C: TradingClient + MarketDataClient + ?Sized> BrokerClient for C {}
+
+macro_rules! forward_clients {
+    ($wrapper:ident) => {
+        #[async_trait]
+        impl<C> TradingClient for $wrapper<C>
+        where
+            C: TradingClient + Send + Sync + ?Sized,
+        {
+            async fn create_order(&self, order: &Order) -> Result<(), Box<dyn std::error::Error>> {
+                (**self).create_order(order).await
+            }
+
+            async fn get_open_orders(
+                &self,
+            ) -> Result<Vec<OrderResponse>, Box<dyn std::error::Error>> {
+                (**self).get_open_orders().await
+            }
+
+            async fn get_order(
+                &self,
+                order_id: &str,
+            ) -> Result<OrderResponse, Box<dyn std::error::Error>> {
+                (**self).get_order(order_id).await
+            }
+
+            async fn get_order_by_client_id(
+                &self,
+                client_order_id: &str,
+            ) -> Result<OrderResponse, Box<dyn std::error::Error>> {
+                (**self).get_order_by_client_id(client_order_id).await
+            }
+
+            async fn cancel_order(
+                &self,
+                order_id: &str,
+            ) -> Result<CancelOutcome, Box<dyn std::error::Error>> {
+                (**self).cancel_order(order_id).await
+            }
+
+            async fn get_asset(&self, symbol: &str) -> Result<Asset, Box<dyn std::error::Error>> {
+                (**self).get_asset(symbol).await
+            }
+
+            async fn list_assets(&self) -> Result<Vec<Asset>, Box<dyn std::error::Error>> {
+                (**self).list_assets().await
+            }
+
+            async fn get_account(&self) -> Result<Account, Box<dyn std::error::Error>> {
+                (**self).get_account().await
+            }
+
+            async fn get_positions(&self) -> Result<Vec<Position>, Box<dyn std::error::Error>> {
+                (**self).get_positions().await
+            }
+
+            async fn get_account_activities(
+                &self,
+                since: &str,
+            ) -> Result<Vec<AccountActivity>, Box<dyn std::error::Error>> {
+                (**self).get_account_activities(since).await
+            }
+        }
+
+        #[async_trait]
+        impl<C> MarketDataClient for $wrapper<C>
+        where
+            C: MarketDataClient + Send + Sync + ?Sized,
+        {
+            async fn get_latest_quote(
+                &self,
+                symbol: &str,
+            ) -> Result<Quote, Box<dyn std::error::Error>> {
+                (**self).get_latest_quote(symbol).await
+            }
+
+            async fn get_daily_bars(
+                &self,
+                symbol: &str,
+                start: &str,
+                end: &str,
+                adjustment: BarAdjustment,
+            ) -> Result<Vec<Bar>, Box<dyn std::error::Error>> {
+                (**self)
+                    .get_daily_bars(symbol, start, end, adjustment)
+                    .await
+            }
+
+            async fn subscribe(
+                &self,
+                params: SubscriptionParams,
+            ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, Box<dyn std::error::Error>>
+            {
+                (**self).subscribe(params).await
+            }
+
+            fn parse_frame(
+                &self,
+                frame: &str,
+                mode: ParseMode,
+            ) -> Result<EventBatch, serde_json::Error> {
+                (**self).parse_frame(frame, mode)
+            }
+        }
+    };
+}
+
+forward_clients!(Box);
+forward_clients!(Arc);
+
+/// Orders and account calls go to `trading`, market data calls to `data`, e.g. Alpaca for execution with
+/// another provider's data, for code that needs both from one client.
+#[derive(Debug, Clone)]
+pub struct SplitClient<T, D> {
+    pub trading: T,
+    pub data: D,
+}
+
+impl<T, D> SplitClient<T, D> {
+    pub fn new(trading: T, data: D) -> Self {
+        SplitClient { trading, data }
+    }
+}
+
+#[async_trait]
+impl<T, D> TradingClient for SplitClient<T, D>
+where
+    T: TradingClient + Send + Sync,
+    D: Send + Sync,
+{
+    async fn create_order(&self, order: &Order) -> Result<(), Box<dyn std::error::Error>> {
+        self.trading.create_order(order).await
+    }
+
+    async fn get_open_orders(&self) -> Result<Vec<OrderResponse>, Box<dyn std::error::Error>> {
+        self.trading.get_open_orders().await
+    }
+
+    async fn get_order(&self, order_id: &str) -> Result<OrderResponse, Box<dyn std::error::Error>> {
+        self.trading.get_order(order_id).await
+    }
+
+    async fn get_order_by_client_id(
+        &self,
+        client_order_id: &str,
+    ) -> Result<OrderResponse, Box<dyn std::error::Error>> {
+        self.trading.get_order_by_client_id(client_order_id).await
+    }
+
+    async fn cancel_order(
+        &self,
+        order_id: &str,
+    ) -> Result<CancelOutcome, Box<dyn std::error::Error>> {
+        self.trading.cancel_order(order_id).await
+    }
+
+    async fn get_asset(&self, symbol: &str) -> Result<Asset, Box<dyn std::error::Error>> {
+        self.trading.get_asset(symbol).await
+    }
+
+    async fn list_assets(&self) -> Result<Vec<Asset>, Box<dyn std::error::Error>> {
+        self.trading.list_assets().await
+    }
+
+    async fn get_account(&self) -> Result<Account, Box<dyn std::error::Error>> {
+        self.trading.get_account().await
+    }
+
+    async fn get_positions(&self) -> Result<Vec<Position>, Box<dyn std::error::Error>> {
+        self.trading.get_positions().await
+    }
+
+    async fn get_account_activities(
+        &self,
+        since: &str,
+    ) -> Result<Vec<AccountActivity>, Box<dyn std::error::Error>> {
+        self.trading.get_account_activities(since).await
+    }
+}
+
+#[async_trait]
+impl<T, D> MarketDataClient for SplitClient<T, D>
+where
+    T: Send + Sync,
+    D: MarketDataClient + Send + Sync,
+{
+    async fn get_latest_quote(&self, symbol: &str) -> Result<Quote, Box<dyn std::error::Error>> {
+        self.data.get_latest_quote(symbol).await
+    }
+
+    async fn get_daily_bars(
+        &self,
+        symbol: &str,
+        start: &str,
+        end: &str,
+        adjustment: BarAdjustment,
+    ) -> Result<Vec<Bar>, Box<dyn std::error::Error>> {
+        self.data
+            .get_daily_bars(symbol, start, end, adjustment)
+            .await
+    }
+
+    async fn subscribe(
+        &self,
+        params: SubscriptionParams,
+    ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, Box<dyn std::error::Error>> {
+        self.data.subscribe(params).await
+    }
+
+    fn parse_frame(&self, frame: &str, mode: ParseMode) -> Result<EventBatch, serde_json::Error> {
+        self.data.parse_frame(frame, mode)
+    }
+}
+
+/// Brokers `create_client` can connect to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Broker {
+    #[cfg(feature = "alpaca")]
+    Alpaca,
+    /// Binance spot, with the config's Binance keys.
+    #[cfg(feature = "binance")]
+    Binance,
+    /// Gemini, on its sandbox unless real trading is enabled, with the config's Gemini keys.
+    #[cfg(feature = "gemini")]
+    Gemini,
+    /// Kraken spot, with the config's Kraken keys.
+    #[cfg(feature = "kraken")]
+    Kraken,
+    /// Schwab, with the config's Schwab app keys and refresh token.
+    #[cfg(feature = "schwab")]
+    Schwab,
+    /// In-process simulation with the default `SimConfig`. The config is not used.
+    Simulated,
+    /// `MockTradingClient` with $100,000 of cash. The config is not used.
+    #[cfg(feature = "testing")]
+    Mock,
+}
+
+impl FromStr for Broker {
+    type Err = String;
+
+    /// Parses the broker's name as in config files, e.g. "alpaca" or "simulated".
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        serde_json::from_value(serde_json::Value::String(name.to_lowercase()))
+            .map_err(|_| format!("Unknown broker: {}", name))
+    }
+}
+
+/// Client for `broker`, chosen at runtime, e.g. from a config file or command line flag.
+#[cfg_attr(
+    not(any(
+        feature = "alpaca",
+        feature = "binance",
+        feature = "gemini",
+        feature = "kraken",
+        feature = "schwab"
+    )),
+    allow(unused_variables)
+)]
+pub fn create_client(
+    broker: Broker,
+    config: &Config,
+) -> Result<Box<dyn BrokerClient + Send + Sync>, reqwest::Error> {
+    Ok(match broker {
+        #[cfg(feature = "alpaca")]
+        Broker::Alpaca => Box::new(AlpacaClient::new(config)?),
+        #[cfg(feature = "binance")]
+        Broker::Binance => Box::new(crate::binance::BinanceClient::new(config)?),
+        #[cfg(feature = "gemini")]
+        Broker::Gemini => Box::new(crate::gemini::GeminiClient::new(config)?),
+        #[cfg(feature = "kraken")]
+        Broker::Kraken => Box::new(crate::kraken::KrakenClient::new(config)?),
+        #[cfg(feature = "schwab")]
+        Broker::Schwab => Box::new(crate::schwab::SchwabClient::new(config)?),
+        Broker::Simulated => Box::new(SimClient::with_config(SimConfig::default())),
+        #[cfg(feature = "testing")]
+        Broker::Mock => Box::new(crate::testing::MockTradingClient::with_cash(100_000.0)),
+    })
 }
@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+
+/// A corporate action that changes a holder's share count or pays them cash.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CorporateAction {
+    /// `ratio` new shares per old share: 4.0 for a 4-for-1 split, 0.1 for a 1-for-10 reverse split.
+    Split {
+        symbol: String,
+        /// YYYY-MM-DD. The first day the symbol trades at the new price.
+        ex_date: String,
+        ratio: f64,
+    },
+    /// `amount` per share, paid to whoever holds the shares going into `ex_date`.
+    CashDividend {
+        symbol: String,
+        ex_date: String,
+        amount: f64,
+    },
+}
+
+impl CorporateAction {
+    pub fn symbol(&self) -> &str {
+        match self {
+            CorporateAction::Split { symbol, .. }
+            | CorporateAction::CashDividend { symbol, .. } => symbol,
+        }
+    }
+
+    pub fn ex_date(&self) -> &str {
+        match self {
+            CorporateAction::Split { ex_date, .. }
+            | CorporateAction::CashDividend { ex_date, .. } => ex_date,
+        }
+    }
+}
+
+#[cfg(feature = "alpaca")]
+#[derive(Deserialize)]
+pub(crate) struct RawSplit {
+    symbol: String,
+    new_rate: f64,
+    old_rate: f64,
+    ex_date: String,
+}
+
+#[cfg(feature = "alpaca")]
+#[derive(Deserialize)]
+pub(crate) struct RawCashDividend {
+    symbol: String,
+    rate: f64,
+    ex_date: String,
+}
+
+#[cfg(feature = "alpaca")]
+#[derive(Default, Deserialize)]
+pub(crate) struct RawCorporateActions {
+    #[serde(default)]
+    forward_splits: Vec<RawSplit>,
+    #[serde(default)]
+    reverse_splits: Vec<RawSplit>,
+    #[serde(default)]
+    cash_dividends: Vec<RawCashDividend>,
+}
+
+#[cfg(feature = "alpaca")]
+impl RawCorporateActions {
+    pub(crate) fn into_actions(self) -> impl Iterator<Item = CorporateAction> {
+        let splits = self
+            .forward_splits
+            .into_iter()
+            .chain(self.reverse_splits)
+            .filter(|split| split.old_rate > 0.0)
+            .map(|split| CorporateAction::Split {
+                symbol: split.symbol,
+                ex_date: split.ex_date,
+                ratio: split.new_rate / split.old_rate,
+            });
+        let dividends =
+            self.cash_dividends
+                .into_iter()
+                .map(|dividend| CorporateAction::CashDividend {
+                    symbol: dividend.symbol,
+                    ex_date: dividend.ex_date,
+                    amount: dividend.rate,
+                });
+        splits.chain(dividends)
+    }
+}
+
+/// One page of `/v1/corporate-actions`.
+#[cfg(feature = "alpaca")]
+#[derive(Deserialize)]
+pub(crate) struct CorporateActionsPage {
+    #[serde(default)]
+    pub(crate) corporate_actions: RawCorporateActions,
+    pub(crate) next_page_token: Option<String>,
+}
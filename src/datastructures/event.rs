@@ -1,22 +1,31 @@
-use serde::de::Error as SerdeError;
-use serde::Deserialize;
-use serde_json::Error;
+use crate::time;
+use serde::de::{Error as SerdeError, IgnoredAny, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Error, Map, Value};
+use smallvec::SmallVec;
+use std::collections::HashSet;
 use std::fmt;
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
 
-#[derive(Debug, Clone)]
+/// Serializes with a `type` tag naming the variant. This is the crate's own format, used for recordings;
+/// Alpaca frames are read with `parse_all`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
 pub enum EventType {
     Trade {
         symbol: String,
         price: f64,
-        volume: u64,
+        volume: f64,
         timestamp: String,
     },
     Quote {
         symbol: String,
         bid_price: f64,
         ask_price: f64,
-        bid_size: u64,
-        ask_size: u64,
+        bid_size: f64,
+        ask_size: f64,
         timestamp: String,
     },
     Bar {
@@ -25,7 +34,7 @@ pub enum EventType {
         high: f64,
         low: f64,
         close: f64,
-        volume: u64,
+        volume: f64,
         timestamp: String,
     },
     UpdatedBar {
@@ -34,7 +43,7 @@ pub enum EventType {
         high: f64,
         low: f64,
         close: f64,
-        volume: u64,
+        volume: f64,
         timestamp: String,
     },
     DailyBar {
@@ -43,89 +52,591 @@ pub enum EventType {
         high: f64,
         low: f64,
         close: f64,
-        volume: u64,
+        volume: f64,
         timestamp: String,
     },
     OrderBook {
         symbol: String,
-        bids: Vec<(f64, u64)>, // (price, size)
-        asks: Vec<(f64, u64)>, // (price, size)
+        bids: Vec<(f64, f64)>, // (price, size)
+        asks: Vec<(f64, f64)>, // (price, size)
+        /// The levels replace the whole book rather than update it, as on the first message after subscribing.
+        #[serde(default)]
+        reset: bool,
         timestamp: String,
     },
+    /// Limit up/limit down band update. Orders priced outside the band are rejected by the venue.
+    Luld {
+        symbol: String,
+        limit_up: f64,
+        limit_down: f64,
+        timestamp: String,
+    },
+    /// News article from the news feed, sent when it is published and again on updates.
+    News {
+        id: u64,
+        headline: String,
+        summary: String,
+        /// Symbols the article mentions.
+        symbols: Vec<String>,
+        source: String,
+        url: String,
+        /// Publication time.
+        timestamp: String,
+    },
+    /// No message arrived on a stream connection within the configured staleness timeout.
+    /// Quotes received before this event may be frozen.
+    StaleConnection { silent_for: Duration },
+}
+
+/// Events parsed from one stream frame. Typical frames fit inline; market-open bursts spill to a single heap allocation.
+pub type EventBatch = SmallVec<[EventType; 8]>;
+
+/// How stream frames are checked against the fields and message types this crate knows about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    /// Unknown fields and message types are skipped silently, so Alpaca adding to its schema never breaks parsing.
+    #[default]
+    Lenient,
+    /// Parses the same way, but logs a warning the first time each unknown field or message type is seen so the
+    /// schema can be updated deliberately. Costs a second pass over every frame; meant for tests and CI.
+    Strict,
+}
+
+/// Part of the US equity trading day, by US Eastern wall-clock time. Extended-hours prints and quotes are thinner
+/// and wider than regular-session ones, so indicators and daily bars usually leave them out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MarketSession {
+    /// 4:00 to 9:30.
+    PreMarket,
+    /// 9:30 to 16:00.
+    Regular,
+    /// 16:00 to 20:00.
+    AfterHours,
+    /// Overnight and weekends.
+    Closed,
+}
+
+impl MarketSession {
+    /// Session of an instant in nanoseconds since the Unix epoch. Holidays and early closes are not known.
+    pub fn at(nanos: i64) -> Self {
+        if time::us_eastern_weekday(nanos) > 5 {
+            return MarketSession::Closed;
+        }
+        let local = nanos.div_euclid(1_000_000_000) + time::us_eastern_offset(nanos);
+        match local.rem_euclid(86_400) / 60 {
+            240..=569 => MarketSession::PreMarket,
+            570..=959 => MarketSession::Regular,
+            960..=1199 => MarketSession::AfterHours,
+            _ => MarketSession::Closed,
+        }
+    }
+
+    /// Session of a symbol's data stamped at an RFC 3339 `timestamp`. Crypto pairs, e.g. "BTC/USD", trade around
+    /// the clock and are always regular.
+    pub fn of(symbol: &str, timestamp: &str) -> Option<Self> {
+        if symbol.contains('/') {
+            return Some(MarketSession::Regular);
+        }
+        time::parse_rfc3339(timestamp).map(Self::at)
+    }
+
+    pub fn is_regular(&self) -> bool {
+        *self == MarketSession::Regular
+    }
+
+    /// Pre-market or after hours.
+    pub fn is_extended(&self) -> bool {
+        matches!(self, MarketSession::PreMarket | MarketSession::AfterHours)
+    }
+}
+
+/// Fields Alpaca documents for each message type besides `T`, `S` and `t`, whether or not they are parsed.
+fn known_fields(kind: &str) -> Option<&'static [&'static str]> {
+    Some(match kind {
+        "t" => &["p", "s", "i", "x", "c", "z", "tks"],
+        "q" => &["bp", "bs", "ap", "as", "bx", "ax", "c", "z"],
+        "b" | "u" | "d" => &["o", "h", "l", "c", "v", "n", "vw"],
+        "l" => &["u", "d", "i", "z"],
+        "o" => &["b", "a", "r"],
+        "n" => &[
+            "id",
+            "headline",
+            "summary",
+            "author",
+            "created_at",
+            "updated_at",
+            "url",
+            "content",
+            "symbols",
+            "source",
+        ],
+        _ => return None,
+    })
+}
+
+/// Warns about each unknown message type or field once per process.
+fn report_unknown(frame: &str) {
+    static REPORTED: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+    let Ok(messages) = serde_json::from_str::<Vec<Map<String, Value>>>(frame) else {
+        return;
+    };
+
+    let mut reported = REPORTED.get_or_init(Default::default).lock().unwrap();
+    for message in messages {
+        let kind = message.get("T").and_then(Value::as_str).unwrap_or_default();
+        if matches!(kind, "success" | "subscription" | "error") {
+            continue;
+        }
+        let Some(known) = known_fields(kind) else {
+            if reported.insert(kind.to_string()) {
+                tracing::warn!(kind, "Unknown stream message type");
+            }
+            continue;
+        };
+        for field in message.keys() {
+            let field = field.as_str();
+            if matches!(field, "T" | "S" | "t") || known.contains(&field) {
+                continue;
+            }
+            if reported.insert(format!("{}.{}", kind, field)) {
+                tracing::warn!(kind, field, "Unknown stream message field");
+            }
+        }
+    }
+}
+
+/// Reads a number, treating anything else as absent. `c` is the close of a bar but the condition list of a trade
+/// or quote.
+fn number_or_ignored<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f64>, D::Error> {
+    struct NumberVisitor;
+
+    impl<'de> Visitor<'de> for NumberVisitor {
+        type Value = Option<f64>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a number or any other value")
+        }
+
+        fn visit_f64<E: SerdeError>(self, value: f64) -> Result<Option<f64>, E> {
+            Ok(Some(value))
+        }
+
+        fn visit_u64<E: SerdeError>(self, value: u64) -> Result<Option<f64>, E> {
+            Ok(Some(value as f64))
+        }
+
+        fn visit_i64<E: SerdeError>(self, value: i64) -> Result<Option<f64>, E> {
+            Ok(Some(value as f64))
+        }
+
+        fn visit_str<E: SerdeError>(self, _: &str) -> Result<Option<f64>, E> {
+            Ok(None)
+        }
+
+        fn visit_bool<E: SerdeError>(self, _: bool) -> Result<Option<f64>, E> {
+            Ok(None)
+        }
+
+        fn visit_unit<E: SerdeError>(self) -> Result<Option<f64>, E> {
+            Ok(None)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Option<f64>, A::Error> {
+            while seq.next_element::<IgnoredAny>()?.is_some() {}
+            Ok(None)
+        }
+
+        fn visit_map<A: serde::de::MapAccess<'de>>(
+            self,
+            mut map: A,
+        ) -> Result<Option<f64>, A::Error> {
+            while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {}
+            Ok(None)
+        }
+    }
+
+    deserializer.deserialize_any(NumberVisitor)
+}
+
+/// A single element of an Alpaca stream frame. Control messages ("success", "subscription", "error")
+/// share the same array as market data, so everything except the type tag is optional.
+#[derive(Deserialize)]
+struct RawEvent {
+    #[serde(rename = "T")]
+    kind: String,
+    #[serde(rename = "S", default)]
+    symbol: String,
+    p: Option<f64>,
+    s: Option<f64>,
+    bp: Option<f64>,
+    bs: Option<f64>,
+    ap: Option<f64>,
+    #[serde(rename = "as")]
+    as_: Option<f64>,
+    o: Option<f64>,
+    h: Option<f64>,
+    l: Option<f64>,
+    #[serde(default, deserialize_with = "number_or_ignored")]
+    c: Option<f64>,
+    /// Fractional for crypto.
+    v: Option<f64>,
+    u: Option<f64>,
+    d: Option<f64>,
+    #[serde(default)]
+    t: String,
+    /// Orderbook bids, asks and reset flag.
+    b: Option<Vec<RawLevel>>,
+    a: Option<Vec<RawLevel>>,
+    r: Option<bool>,
+    /// News fields.
+    id: Option<u64>,
+    #[serde(default)]
+    headline: String,
+    #[serde(default)]
+    summary: String,
+    #[serde(default)]
+    symbols: Vec<String>,
+    #[serde(default)]
+    source: String,
+    #[serde(default)]
+    url: String,
+    #[serde(default)]
+    created_at: String,
+}
+
+/// A price level of an orderbook message. A size of zero removes the level.
+#[derive(Deserialize)]
+struct RawLevel {
+    p: f64,
+    s: f64,
+}
+
+fn levels(raw: Option<Vec<RawLevel>>) -> Vec<(f64, f64)> {
+    raw.unwrap_or_default()
+        .into_iter()
+        .map(|level| (level.p, level.s))
+        .collect()
+}
+
+impl RawEvent {
+    /// Returns `None` for control messages that carry no market data.
+    fn into_event(self) -> Option<Result<EventType, Error>> {
+        let event = match self.kind.as_str() {
+            "success" | "subscription" => return None,
+            "error" => return Some(Err(SerdeError::custom("Stream reported an error"))),
+            "t" => EventType::Trade {
+                symbol: self.symbol,
+                price: self.p.unwrap_or_default(),
+                volume: self.s.unwrap_or_default(),
+                timestamp: self.t,
+            },
+            "q" => EventType::Quote {
+                symbol: self.symbol,
+                bid_price: self.bp.unwrap_or_default(),
+                ask_price: self.ap.unwrap_or_default(),
+                bid_size: self.bs.unwrap_or_default(),
+                ask_size: self.as_.unwrap_or_default(),
+                timestamp: self.t,
+            },
+            "b" => EventType::Bar {
+                symbol: self.symbol,
+                open: self.o.unwrap_or_default(),
+                high: self.h.unwrap_or_default(),
+                low: self.l.unwrap_or_default(),
+                close: self.c.unwrap_or_default(),
+                volume: self.v.unwrap_or_default(),
+                timestamp: self.t,
+            },
+            "u" => EventType::UpdatedBar {
+                symbol: self.symbol,
+                open: self.o.unwrap_or_default(),
+                high: self.h.unwrap_or_default(),
+                low: self.l.unwrap_or_default(),
+                close: self.c.unwrap_or_default(),
+                volume: self.v.unwrap_or_default(),
+                timestamp: self.t,
+            },
+            "d" => EventType::DailyBar {
+                symbol: self.symbol,
+                open: self.o.unwrap_or_default(),
+                high: self.h.unwrap_or_default(),
+                low: self.l.unwrap_or_default(),
+                close: self.c.unwrap_or_default(),
+                volume: self.v.unwrap_or_default(),
+                timestamp: self.t,
+            },
+            "l" => EventType::Luld {
+                symbol: self.symbol,
+                limit_up: self.u.unwrap_or_default(),
+                limit_down: self.d.unwrap_or_default(),
+                timestamp: self.t,
+            },
+            "o" => EventType::OrderBook {
+                symbol: self.symbol,
+                bids: levels(self.b),
+                asks: levels(self.a),
+                reset: self.r.unwrap_or_default(),
+                timestamp: self.t,
+            },
+            "n" => EventType::News {
+                id: self.id.unwrap_or_default(),
+                headline: self.headline,
+                summary: self.summary,
+                symbols: self.symbols,
+                source: self.source,
+                url: self.url,
+                timestamp: self.created_at,
+            },
+            // New message types are reported by `ParseMode::Strict`.
+            _ => return None,
+        };
+        Some(Ok(event))
+    }
+}
+
+/// Converts each array element as it is read instead of collecting the raw elements first.
+struct BatchVisitor;
+
+impl<'de> Visitor<'de> for BatchVisitor {
+    type Value = EventBatch;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an array of stream messages")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<EventBatch, A::Error> {
+        let mut batch = EventBatch::new();
+        if let Some(size) = seq.size_hint() {
+            batch.reserve(size);
+        }
+        while let Some(raw_event) = seq.next_element::<RawEvent>()? {
+            if let Some(event) = raw_event.into_event() {
+                batch.push(event.map_err(A::Error::custom)?);
+            }
+        }
+        Ok(batch)
+    }
 }
 
 impl EventType {
-    pub fn from_str(s: &str) -> Result<Self, Error> {
-        #[derive(Deserialize)]
-        struct RawEvent {
-            T: String,
-            S: String,
-            bp: Option<f64>,
-            bs: Option<f64>,
-            ap: Option<f64>,
-            as_: Option<f64>,
-            o: Option<f64>,
-            h: Option<f64>,
-            l: Option<f64>,
-            c: Option<f64>,
-            v: Option<u64>,
-            t: String,
-            bids: Option<Vec<(f64, u64)>>,
-            asks: Option<Vec<(f64, u64)>>,
+    /// Parses every market data event in a stream frame, skipping control messages.
+    pub fn parse_all(s: &str) -> Result<Vec<Self>, Error> {
+        Ok(Self::parse_batch(s)?.into_vec())
+    }
+
+    /// Like `parse_all`, but parses the frame in a single pass without per-element intermediate allocations.
+    pub fn parse_batch(s: &str) -> Result<EventBatch, Error> {
+        Self::parse_batch_with(s, ParseMode::Lenient)
+    }
+
+    /// `parse_batch` with a choice of how unknown fields and message types are reported.
+    pub fn parse_batch_with(s: &str, mode: ParseMode) -> Result<EventBatch, Error> {
+        if mode == ParseMode::Strict {
+            report_unknown(s);
         }
+        let mut deserializer = serde_json::Deserializer::from_str(s);
+        let batch = deserializer.deserialize_seq(BatchVisitor)?;
+        deserializer.end()?;
+        Ok(batch)
+    }
+
+    /// Symbol the event refers to, if it carries market data. News can mention several; see its `symbols`.
+    pub fn symbol(&self) -> Option<&str> {
+        match self {
+            EventType::Trade { symbol, .. }
+            | EventType::Quote { symbol, .. }
+            | EventType::Bar { symbol, .. }
+            | EventType::UpdatedBar { symbol, .. }
+            | EventType::DailyBar { symbol, .. }
+            | EventType::OrderBook { symbol, .. }
+            | EventType::Luld { symbol, .. } => Some(symbol),
+            EventType::News { .. } | EventType::StaleConnection { .. } => None,
+        }
+    }
+
+    /// Session a trade, quote, minute bar or order book falls in, by its timestamp; a bar by the start of its
+    /// minute. `None` for other events, including daily bars, which span the whole day.
+    pub fn session(&self) -> Option<MarketSession> {
+        let timestamp = match self {
+            EventType::Trade { timestamp, .. }
+            | EventType::Quote { timestamp, .. }
+            | EventType::Bar { timestamp, .. }
+            | EventType::UpdatedBar { timestamp, .. }
+            | EventType::OrderBook { timestamp, .. } => timestamp,
+            _ => return None,
+        };
+        MarketSession::of(self.symbol()?, timestamp)
+    }
+
+    /// Whether the event is market data from outside the regular session.
+    pub fn is_extended_hours(&self) -> bool {
+        self.session().is_some_and(|session| !session.is_regular())
+    }
+
+    /// Exchange timestamp of a market data event.
+    pub fn timestamp(&self) -> Option<&str> {
+        match self {
+            EventType::Trade { timestamp, .. }
+            | EventType::Quote { timestamp, .. }
+            | EventType::Bar { timestamp, .. }
+            | EventType::UpdatedBar { timestamp, .. }
+            | EventType::DailyBar { timestamp, .. }
+            | EventType::OrderBook { timestamp, .. }
+            | EventType::Luld { timestamp, .. }
+            | EventType::News { timestamp, .. } => Some(timestamp),
+            EventType::StaleConnection { .. } => None,
+        }
+    }
+}
+
+impl FromStr for EventType {
+    type Err = Error;
 
+    /// Parses the first market data event in a stream frame.
+    fn from_str(s: &str) -> Result<Self, Error> {
         let raw_event: Vec<RawEvent> = serde_json::from_str(s)?;
 
         if raw_event.is_empty() {
             return Err(SerdeError::custom("Empty event list"));
         }
 
-        let event = &raw_event[0];
-
-        match event.T.as_str() {
-            "q" => Ok(EventType::Quote {
-                symbol: event.S.clone(),
-                bid_price: event.bp.unwrap_or_default(),
-                ask_price: event.ap.unwrap_or_default(),
-                bid_size: event.bs.unwrap_or_default() as u64,
-                ask_size: event.as_.unwrap_or_default() as u64,
-                timestamp: event.t.clone(),
-            }),
-            "b" => Ok(EventType::Bar {
-                symbol: event.S.clone(),
-                open: event.o.unwrap_or_default(),
-                high: event.h.unwrap_or_default(),
-                low: event.l.unwrap_or_default(),
-                close: event.c.unwrap_or_default(),
-                volume: event.v.unwrap_or_default(),
-                timestamp: event.t.clone(),
-            }),
-            _ => Err(SerdeError::custom("Unknown event type")),
-        }
+        raw_event
+            .into_iter()
+            .find_map(RawEvent::into_event)
+            .unwrap_or_else(|| Err(SerdeError::custom("No market data event in list")))
     }
 }
 
 impl fmt::Display for EventType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            EventType::Trade { symbol, price, volume, timestamp } => {
-                write!(f, "Trade: symbol={}, price={}, volume={}, timestamp={}", symbol, price, volume, timestamp)
+            EventType::Trade {
+                symbol,
+                price,
+                volume,
+                timestamp,
+            } => {
+                write!(
+                    f,
+                    "Trade: symbol={}, price={}, volume={}, timestamp={}",
+                    symbol, price, volume, timestamp
+                )
             }
-            EventType::Quote { symbol, bid_price, ask_price, bid_size, ask_size, timestamp } => {
+            EventType::Quote {
+                symbol,
+                bid_price,
+                ask_price,
+                bid_size,
+                ask_size,
+                timestamp,
+            } => {
                 write!(f, "Quote: symbol={}, bid_price={}, ask_price={}, bid_size={}, ask_size={}, timestamp={}", symbol, bid_price, ask_price, bid_size, ask_size, timestamp)
             }
-            EventType::Bar { symbol, open, high, low, close, volume, timestamp } => {
-                write!(f, "Bar: symbol={}, open={}, high={}, low={}, close={}, volume={}, timestamp={}", symbol, open, high, low, close, volume, timestamp)
+            EventType::Bar {
+                symbol,
+                open,
+                high,
+                low,
+                close,
+                volume,
+                timestamp,
+            } => {
+                write!(
+                    f,
+                    "Bar: symbol={}, open={}, high={}, low={}, close={}, volume={}, timestamp={}",
+                    symbol, open, high, low, close, volume, timestamp
+                )
             }
-            EventType::UpdatedBar { symbol, open, high, low, close, volume, timestamp } => {
+            EventType::UpdatedBar {
+                symbol,
+                open,
+                high,
+                low,
+                close,
+                volume,
+                timestamp,
+            } => {
                 write!(f, "UpdatedBar: symbol={}, open={}, high={}, low={}, close={}, volume={}, timestamp={}", symbol, open, high, low, close, volume, timestamp)
             }
-            EventType::DailyBar { symbol, open, high, low, close, volume, timestamp } => {
+            EventType::DailyBar {
+                symbol,
+                open,
+                high,
+                low,
+                close,
+                volume,
+                timestamp,
+            } => {
                 write!(f, "DailyBar: symbol={}, open={}, high={}, low={}, close={}, volume={}, timestamp={}", symbol, open, high, low, close, volume, timestamp)
             }
-            EventType::OrderBook { symbol, bids, asks, timestamp } => {
-                write!(f, "OrderBook: symbol={}, bids={:?}, asks={:?}, timestamp={}", symbol, bids, asks, timestamp)
+            EventType::OrderBook {
+                symbol,
+                bids,
+                asks,
+                reset,
+                timestamp,
+            } => {
+                write!(
+                    f,
+                    "OrderBook: symbol={}, bids={:?}, asks={:?}, reset={}, timestamp={}",
+                    symbol, bids, asks, reset, timestamp
+                )
+            }
+            EventType::Luld {
+                symbol,
+                limit_up,
+                limit_down,
+                timestamp,
+            } => {
+                write!(
+                    f,
+                    "Luld: symbol={}, limit_up={}, limit_down={}, timestamp={}",
+                    symbol, limit_up, limit_down, timestamp
+                )
+            }
+            EventType::News {
+                id,
+                headline,
+                symbols,
+                source,
+                timestamp,
+                ..
+            } => {
+                write!(
+                    f,
+                    "News: id={}, headline={}, symbols={:?}, source={}, timestamp={}",
+                    id, headline, symbols, source, timestamp
+                )
+            }
+            EventType::StaleConnection { silent_for } => {
+                write!(f, "StaleConnection: silent_for={:?}", silent_for)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_fractional_crypto_sizes() {
+        let events = EventType::parse_all(
+            r#"[{"T":"success","msg":"authenticated"},
+                {"T":"t","S":"BTC/USD","p":42000.1,"s":0.0015,"t":"2024-01-02T14:30:00Z"},
+                {"T":"q","S":"BTC/USD","bp":41999.9,"bs":0.25,"ap":42000.1,"as":1.5,"t":"2024-01-02T14:30:00Z"},
+                {"T":"b","S":"BTC/USD","o":1,"h":2,"l":0.5,"c":1.5,"v":0.75,"t":"2024-01-02T14:30:00Z"}]"#,
+        )
+        .unwrap();
+        match &events[..] {
+            [EventType::Trade { volume, .. }, EventType::Quote {
+                bid_size, ask_size, ..
+            }, EventType::Bar { volume: bar, .. }] => {
+                assert_eq!(*volume, 0.0015);
+                assert_eq!((*bid_size, *ask_size), (0.25, 1.5));
+                assert_eq!(*bar, 0.75);
             }
+            other => panic!("Expected a trade, quote and bar, got {:?}", other),
         }
     }
 }
@@ -1,5 +1,220 @@
+use super::{corporate_action::CorporateAction, event::EventType};
+use crate::time;
+use serde::{Deserialize, Serialize};
+
 pub struct MarketData {
     pub symbol: String,
     pub price: f32,
     pub volume: u32,
 }
+
+/// Top of book for a symbol.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quote {
+    pub symbol: String,
+    pub bid_price: f64,
+    pub ask_price: f64,
+    pub bid_size: f64,
+    pub ask_size: f64,
+    pub timestamp: String,
+}
+
+impl Quote {
+    /// Extracts the quote from a quote event.
+    pub fn from_event(event: &EventType) -> Option<Quote> {
+        match event {
+            EventType::Quote {
+                symbol,
+                bid_price,
+                ask_price,
+                bid_size,
+                ask_size,
+                timestamp,
+            } => Some(Quote {
+                symbol: symbol.clone(),
+                bid_price: *bid_price,
+                ask_price: *ask_price,
+                bid_size: *bid_size,
+                ask_size: *ask_size,
+                timestamp: timestamp.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn mid(&self) -> f64 {
+        (self.bid_price + self.ask_price) / 2.0
+    }
+
+    pub fn spread(&self) -> f64 {
+        self.ask_price - self.bid_price
+    }
+}
+
+/// OHLCV bar for one symbol. `timestamp` is the start of the bar's interval.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bar {
+    pub symbol: String,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    pub timestamp: String,
+}
+
+impl Bar {
+    /// Extracts the bar from a minute, updated or daily bar event.
+    pub fn from_event(event: &EventType) -> Option<Bar> {
+        match event {
+            EventType::Bar {
+                symbol,
+                open,
+                high,
+                low,
+                close,
+                volume,
+                timestamp,
+            }
+            | EventType::UpdatedBar {
+                symbol,
+                open,
+                high,
+                low,
+                close,
+                volume,
+                timestamp,
+            }
+            | EventType::DailyBar {
+                symbol,
+                open,
+                high,
+                low,
+                close,
+                volume,
+                timestamp,
+            } => Some(Bar {
+                symbol: symbol.clone(),
+                open: *open,
+                high: *high,
+                low: *low,
+                close: *close,
+                volume: *volume,
+                timestamp: timestamp.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// The bar as a daily bar event if `daily`, else as a minute bar event, e.g. to replay fetched history with
+    /// `ReplayFeed::events`.
+    pub fn into_event(self, daily: bool) -> EventType {
+        let Bar {
+            symbol,
+            open,
+            high,
+            low,
+            close,
+            volume,
+            timestamp,
+        } = self;
+        if daily {
+            EventType::DailyBar {
+                symbol,
+                open,
+                high,
+                low,
+                close,
+                volume,
+                timestamp,
+            }
+        } else {
+            EventType::Bar {
+                symbol,
+                open,
+                high,
+                low,
+                close,
+                volume,
+                timestamp,
+            }
+        }
+    }
+}
+
+/// How historical prices are restated for corporate actions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BarAdjustment {
+    /// Prices as traded.
+    #[default]
+    Raw,
+    /// Prices and volumes restated in shares as of the last split, so a series has no jump on a split date.
+    Split,
+    /// Split-adjusted, with prices before each ex-date also scaled down by the dividend, as if it were
+    /// reinvested.
+    TotalReturn,
+}
+
+impl BarAdjustment {
+    /// Value of Alpaca's `adjustment` query parameter.
+    #[cfg(feature = "alpaca")]
+    pub(crate) fn as_alpaca(self) -> &'static str {
+        match self {
+            BarAdjustment::Raw => "raw",
+            BarAdjustment::Split => "split",
+            BarAdjustment::TotalReturn => "all",
+        }
+    }
+
+    /// Adjusts raw daily bars of one symbol, oldest first, for a broker that cannot adjust them itself.
+    /// `actions` must include every action after the first bar, including those after the last, since a later
+    /// split restates every earlier price.
+    pub fn apply(self, bars: &mut [Bar], actions: &[CorporateAction]) {
+        let Some(symbol) = bars.first().map(|bar| bar.symbol.clone()) else {
+            return;
+        };
+        if self == BarAdjustment::Raw {
+            return;
+        }
+        let dates: Vec<String> = bars
+            .iter()
+            .map(|bar| time::date(&bar.timestamp).unwrap_or_default())
+            .collect();
+        let mut splits = vec![1.0; bars.len()];
+        let mut dividends = vec![1.0; bars.len()];
+        for action in actions.iter().filter(|action| action.symbol() == symbol) {
+            // Bars before the ex-date.
+            let before = dates.partition_point(|date| date.as_str() < action.ex_date());
+            match *action {
+                CorporateAction::Split { ratio, .. } if ratio > 0.0 => {
+                    splits[..before]
+                        .iter_mut()
+                        .for_each(|factor| *factor *= ratio);
+                }
+                CorporateAction::CashDividend { amount, .. }
+                    if self == BarAdjustment::TotalReturn && before > 0 =>
+                {
+                    // The amount is per share as of the ex-date, the same basis as the raw close before it.
+                    let close = bars[before - 1].close;
+                    if close > amount {
+                        let factor = 1.0 - amount / close;
+                        dividends[..before]
+                            .iter_mut()
+                            .for_each(|total| *total *= factor);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for ((bar, split), dividend) in bars.iter_mut().zip(splits).zip(dividends) {
+            let price = dividend / split;
+            bar.open *= price;
+            bar.high *= price;
+            bar.low *= price;
+            bar.close *= price;
+            bar.volume *= split;
+        }
+    }
+}
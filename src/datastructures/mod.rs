@@ -1,6 +1,13 @@
+pub mod account;
 pub mod asset;
+#[cfg(feature = "broker-api")]
+pub mod broker;
 pub mod client;
 pub mod config;
+pub mod corporate_action;
 pub mod market;
+pub mod news;
 pub mod order;
+pub mod typed_order;
 pub mod event;
+mod number;
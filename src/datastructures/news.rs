@@ -0,0 +1,51 @@
+use super::event::EventType;
+use serde::{Deserialize, Serialize};
+
+/// Docs: https://docs.alpaca.markets/reference/news-3
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NewsArticle {
+    pub id: u64,
+    pub headline: String,
+    #[serde(default)]
+    pub author: String,
+    #[serde(default)]
+    pub summary: String,
+    /// Full text, often HTML. Empty unless requested.
+    #[serde(default)]
+    pub content: String,
+    #[serde(default)]
+    pub url: String,
+    /// Symbols the article mentions.
+    #[serde(default)]
+    pub symbols: Vec<String>,
+    #[serde(default)]
+    pub source: String,
+    /// Publication time.
+    pub created_at: String,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+impl NewsArticle {
+    /// The article as the news stream sends it on publication.
+    pub fn to_event(&self) -> EventType {
+        EventType::News {
+            id: self.id,
+            headline: self.headline.clone(),
+            summary: self.summary.clone(),
+            symbols: self.symbols.clone(),
+            source: self.source.clone(),
+            url: self.url.clone(),
+            timestamp: self.created_at.clone(),
+        }
+    }
+}
+
+/// One page of `/v1beta1/news`.
+#[cfg(feature = "alpaca")]
+#[derive(Deserialize)]
+pub(crate) struct NewsPage {
+    #[serde(default)]
+    pub(crate) news: Vec<NewsArticle>,
+    pub(crate) next_page_token: Option<String>,
+}
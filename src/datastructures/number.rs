@@ -0,0 +1,32 @@
+use serde::{de::Error, Deserialize, Deserializer};
+
+// Alpaca encodes most decimal fields as JSON strings ("qty": "10", "limit_price": "101.5").
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StringOrNumber {
+    String(String),
+    Number(f64),
+}
+
+impl StringOrNumber {
+    fn parse<E: Error>(self) -> Result<f64, E> {
+        match self {
+            StringOrNumber::String(s) => s.parse().map_err(E::custom),
+            StringOrNumber::Number(n) => Ok(n),
+        }
+    }
+}
+
+/// Deserializes a decimal sent either as a string or as a number.
+pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    StringOrNumber::deserialize(deserializer)?.parse()
+}
+
+/// Like `deserialize`, for fields that may be null or absent.
+pub(crate) fn deserialize_option<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<f64>, D::Error> {
+    Option::<StringOrNumber>::deserialize(deserializer)?
+        .map(StringOrNumber::parse)
+        .transpose()
+}
@@ -1,28 +1,282 @@
+use super::number;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
-#[derive(Debug, Deserialize, Serialize)]
+/// Free-form key/value tags attached to an order, such as strategy version or signal id.
+/// Alpaca does not store them; they are kept in the `OrderJournal` and matched back by client order id.
+pub type OrderMetadata = BTreeMap<String, String>;
+
+/// Metadata key naming the strategy that owns an order.
+pub const STRATEGY_TAG: &str = "strategy";
+
+/// Docs: https://docs.alpaca.markets/reference/postorder
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Order {
-    symbol: String,
-    quantity: u32,
-    order_type: OrderType,
-    time_in_force: String, // "gtc", "ioc", etc.
+    pub symbol: String,
+    #[serde(rename = "qty")]
+    pub quantity: f64,
+    pub side: OrderSide,
+    #[serde(rename = "type")]
+    pub order_type: OrderType,
+    pub time_in_force: TimeInForce,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit_price: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_price: Option<f64>,
+    /// Unique per order. Setting it makes submission safe to retry, since the broker rejects duplicates.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_order_id: Option<String>,
+    /// Never sent to the broker.
+    #[serde(skip)]
+    pub metadata: OrderMetadata,
 }
 
-// Response after placing an order
+impl Order {
+    pub fn builder() -> OrderBuilder {
+        OrderBuilder::default()
+    }
+}
+
+#[derive(Default)]
+pub struct OrderBuilder {
+    symbol: Option<String>,
+    quantity: Option<f64>,
+    side: Option<OrderSide>,
+    order_type: Option<OrderType>,
+    time_in_force: Option<TimeInForce>,
+    limit_price: Option<f64>,
+    stop_price: Option<f64>,
+    client_order_id: Option<String>,
+    metadata: OrderMetadata,
+}
+
+impl OrderBuilder {
+    pub fn symbol(mut self, symbol: impl Into<String>) -> Self {
+        self.symbol = Some(symbol.into());
+        self
+    }
+
+    pub fn quantity(mut self, quantity: f64) -> Self {
+        self.quantity = Some(quantity);
+        self
+    }
+
+    pub fn side(mut self, side: OrderSide) -> Self {
+        self.side = Some(side);
+        self
+    }
+
+    /// Defaults to a market order.
+    pub fn order_type(mut self, order_type: OrderType) -> Self {
+        self.order_type = Some(order_type);
+        self
+    }
+
+    /// Defaults to day.
+    pub fn time_in_force(mut self, time_in_force: TimeInForce) -> Self {
+        self.time_in_force = Some(time_in_force);
+        self
+    }
+
+    pub fn limit_price(mut self, limit_price: f64) -> Self {
+        self.limit_price = Some(limit_price);
+        self
+    }
+
+    pub fn stop_price(mut self, stop_price: f64) -> Self {
+        self.stop_price = Some(stop_price);
+        self
+    }
+
+    pub fn client_order_id(mut self, client_order_id: impl Into<String>) -> Self {
+        self.client_order_id = Some(client_order_id.into());
+        self
+    }
+
+    /// Attaches a metadata entry. Tagged orders without a client order id get a random one so the tags can be
+    /// matched back to the broker's copy of the order.
+    pub fn tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// Tags the order as belonging to a strategy, so a later instance of the strategy can adopt it.
+    pub fn strategy(self, strategy: impl Into<String>) -> Self {
+        self.tag(STRATEGY_TAG, strategy)
+    }
+
+    pub fn metadata(mut self, metadata: OrderMetadata) -> Self {
+        self.metadata.extend(metadata);
+        self
+    }
+
+    pub fn build(self) -> Result<Order, &'static str> {
+        let client_order_id = match self.client_order_id {
+            None if !self.metadata.is_empty() => Some(format!("{:032x}", rand::random::<u128>())),
+            client_order_id => client_order_id,
+        };
+        let order = Order {
+            symbol: self.symbol.ok_or("Symbol must be set")?,
+            quantity: self.quantity.ok_or("Quantity must be set")?,
+            side: self.side.ok_or("Side must be set")?,
+            order_type: self.order_type.unwrap_or(OrderType::Market),
+            time_in_force: self.time_in_force.unwrap_or(TimeInForce::Day),
+            limit_price: self.limit_price,
+            stop_price: self.stop_price,
+            client_order_id,
+            metadata: self.metadata,
+        };
+
+        if order.quantity <= 0.0 {
+            return Err("Quantity must be positive");
+        }
+        let needs_limit = matches!(order.order_type, OrderType::Limit | OrderType::StopLimit);
+        if needs_limit != order.limit_price.is_some() {
+            return Err("Limit price must be set exactly for limit and stop limit orders");
+        }
+        let needs_stop = matches!(order.order_type, OrderType::Stop | OrderType::StopLimit);
+        if needs_stop != order.stop_price.is_some() {
+            return Err("Stop price must be set exactly for stop and stop limit orders");
+        }
+
+        Ok(order)
+    }
+}
+
+/// Order as reported by the broker after it was placed.
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct OrderResponse {
     pub id: String,
+    pub client_order_id: String,
+    pub symbol: String,
     pub status: OrderStatus,
+    pub created_at: String,
+    pub side: OrderSide,
+    #[serde(rename = "type")]
+    pub order_type: OrderType,
+    #[serde(default, deserialize_with = "number::deserialize_option")]
+    pub qty: Option<f64>,
+    #[serde(deserialize_with = "number::deserialize")]
+    pub filled_qty: f64,
+    #[serde(default, deserialize_with = "number::deserialize_option")]
+    pub filled_avg_price: Option<f64>,
+    #[serde(default, deserialize_with = "number::deserialize_option")]
+    pub limit_price: Option<f64>,
+    #[serde(default, deserialize_with = "number::deserialize_option")]
+    pub stop_price: Option<f64>,
+    /// Filled in from the journal when the client has one. Empty otherwise.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: OrderMetadata,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub enum OrderType {
+/// Final state of an order after a cancel request, as confirmed by the broker.
+/// Each variant carries the order as last reported.
+#[derive(Debug, Clone)]
+pub enum CancelOutcome {
+    /// The cancel took effect. `filled_qty` may still be non-zero if the order partially filled first.
+    Canceled(OrderResponse),
+    /// The order filled before the cancel reached it.
+    Filled(OrderResponse),
+    /// The order ended some other way, e.g. expired or rejected.
+    Closed(OrderResponse),
+    /// The order was still working when verification gave up.
+    Unconfirmed(OrderResponse),
+}
+
+impl CancelOutcome {
+    /// Classifies the latest known state of an order. Non-terminal orders are `Unconfirmed`.
+    pub fn from_order(order: OrderResponse) -> Self {
+        match order.status {
+            OrderStatus::Canceled => CancelOutcome::Canceled(order),
+            OrderStatus::Filled => CancelOutcome::Filled(order),
+            status if status.is_terminal() => CancelOutcome::Closed(order),
+            _ => CancelOutcome::Unconfirmed(order),
+        }
+    }
+
+    /// Lowercase name of the outcome, e.g. "canceled".
+    pub fn name(&self) -> &'static str {
+        match self {
+            CancelOutcome::Canceled(_) => "canceled",
+            CancelOutcome::Filled(_) => "filled",
+            CancelOutcome::Closed(_) => "closed",
+            CancelOutcome::Unconfirmed(_) => "unconfirmed",
+        }
+    }
+
+    pub fn order(&self) -> &OrderResponse {
+        match self {
+            CancelOutcome::Canceled(order)
+            | CancelOutcome::Filled(order)
+            | CancelOutcome::Closed(order)
+            | CancelOutcome::Unconfirmed(order) => order,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderSide {
     Buy,
     Sell,
 }
 
-// Example of order status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderType {
+    Market,
+    Limit,
+    Stop,
+    StopLimit,
+    TrailingStop,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeInForce {
+    Day,
+    Gtc,
+    Opg,
+    Cls,
+    Ioc,
+    Fok,
+}
+
+/// Docs: https://docs.alpaca.markets/docs/orders-at-alpaca#order-lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum OrderStatus {
+    New,
+    PartiallyFilled,
     Filled,
-    Pending,
-    Cancelled,
+    DoneForDay,
+    Canceled,
+    Expired,
+    Replaced,
+    PendingCancel,
+    PendingReplace,
+    PendingReview,
+    Accepted,
+    PendingNew,
+    AcceptedForBidding,
+    Stopped,
+    Rejected,
+    Suspended,
+    Calculated,
+    Held,
+}
+
+impl OrderStatus {
+    /// True once the order can no longer be filled.
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            OrderStatus::Filled
+                | OrderStatus::Canceled
+                | OrderStatus::Expired
+                | OrderStatus::Replaced
+                | OrderStatus::Rejected
+                | OrderStatus::DoneForDay
+        )
+    }
 }
@@ -0,0 +1,137 @@
+use super::order::{Order, OrderBuilder, OrderMetadata, OrderSide, OrderType, TimeInForce};
+use std::marker::PhantomData;
+
+/// State of a `TypedOrderBuilder` for a market order, the state it starts in. Can be built.
+pub enum Market {}
+/// State of a `TypedOrderBuilder` for a limit order, or a stop limit order with its stop price set, that still
+/// needs a limit price.
+pub enum NeedsLimitPrice {}
+/// State of a `TypedOrderBuilder` for a stop order, or a stop limit order with its limit price set, that still
+/// needs a stop price.
+pub enum NeedsStopPrice {}
+/// State of a `TypedOrderBuilder` for a stop limit order with neither price set.
+pub enum NeedsPrices {}
+/// State of a `TypedOrderBuilder` with every price its order type needs. Can be built.
+pub enum Ready {}
+
+/// Order builder that only has `build` once the order's type and prices agree, so a limit order without a
+/// limit price, or a market order with one, does not compile. Symbol, quantity and side are given up front.
+/// For example, `Order::typed("AAPL", 10.0, OrderSide::Buy).limit().limit_price(185.0).build()`.
+pub struct TypedOrderBuilder<S> {
+    inner: OrderBuilder,
+    state: PhantomData<S>,
+}
+
+impl Order {
+    /// Starts a `TypedOrderBuilder` for a market order.
+    pub fn typed(
+        symbol: impl Into<String>,
+        quantity: f64,
+        side: OrderSide,
+    ) -> TypedOrderBuilder<Market> {
+        TypedOrderBuilder {
+            inner: Order::builder()
+                .symbol(symbol)
+                .quantity(quantity)
+                .side(side),
+            state: PhantomData,
+        }
+    }
+}
+
+impl<S> TypedOrderBuilder<S> {
+    fn into_state<T>(self) -> TypedOrderBuilder<T> {
+        TypedOrderBuilder {
+            inner: self.inner,
+            state: PhantomData,
+        }
+    }
+
+    fn order_type<T>(mut self, order_type: OrderType) -> TypedOrderBuilder<T> {
+        self.inner = self.inner.order_type(order_type);
+        self.into_state()
+    }
+
+    /// Defaults to day.
+    pub fn time_in_force(mut self, time_in_force: TimeInForce) -> Self {
+        self.inner = self.inner.time_in_force(time_in_force);
+        self
+    }
+
+    pub fn client_order_id(mut self, client_order_id: impl Into<String>) -> Self {
+        self.inner = self.inner.client_order_id(client_order_id);
+        self
+    }
+
+    /// As `OrderBuilder::tag`.
+    pub fn tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.inner = self.inner.tag(key, value);
+        self
+    }
+
+    pub fn strategy(mut self, strategy: impl Into<String>) -> Self {
+        self.inner = self.inner.strategy(strategy);
+        self
+    }
+
+    pub fn metadata(mut self, metadata: OrderMetadata) -> Self {
+        self.inner = self.inner.metadata(metadata);
+        self
+    }
+}
+
+impl TypedOrderBuilder<Market> {
+    pub fn limit(self) -> TypedOrderBuilder<NeedsLimitPrice> {
+        self.order_type(OrderType::Limit)
+    }
+
+    pub fn stop(self) -> TypedOrderBuilder<NeedsStopPrice> {
+        self.order_type(OrderType::Stop)
+    }
+
+    pub fn stop_limit(self) -> TypedOrderBuilder<NeedsPrices> {
+        self.order_type(OrderType::StopLimit)
+    }
+
+    pub fn trailing_stop(self) -> TypedOrderBuilder<Ready> {
+        self.order_type(OrderType::TrailingStop)
+    }
+
+    /// Fails only if the quantity is not positive.
+    pub fn build(self) -> Result<Order, &'static str> {
+        self.inner.build()
+    }
+}
+
+impl TypedOrderBuilder<NeedsLimitPrice> {
+    pub fn limit_price(mut self, limit_price: f64) -> TypedOrderBuilder<Ready> {
+        self.inner = self.inner.limit_price(limit_price);
+        self.into_state()
+    }
+}
+
+impl TypedOrderBuilder<NeedsStopPrice> {
+    pub fn stop_price(mut self, stop_price: f64) -> TypedOrderBuilder<Ready> {
+        self.inner = self.inner.stop_price(stop_price);
+        self.into_state()
+    }
+}
+
+impl TypedOrderBuilder<NeedsPrices> {
+    pub fn limit_price(mut self, limit_price: f64) -> TypedOrderBuilder<NeedsStopPrice> {
+        self.inner = self.inner.limit_price(limit_price);
+        self.into_state()
+    }
+
+    pub fn stop_price(mut self, stop_price: f64) -> TypedOrderBuilder<NeedsLimitPrice> {
+        self.inner = self.inner.stop_price(stop_price);
+        self.into_state()
+    }
+}
+
+impl TypedOrderBuilder<Ready> {
+    /// Fails only if the quantity is not positive.
+    pub fn build(self) -> Result<Order, &'static str> {
+        self.inner.build()
+    }
+}
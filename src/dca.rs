@@ -0,0 +1,162 @@
+use crate::{
+    datastructures::{
+        client::BrokerClient,
+        order::{Order, OrderSide, OrderType, TimeInForce},
+    },
+    funding::{DeferredOrders, FundingConfig},
+    time,
+};
+use std::{collections::BTreeMap, error::Error};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// Metadata key marking orders sent by a dollar-cost averaging buy.
+pub const DCA_TAG: &str = "dca";
+
+#[derive(Debug, Clone, Default)]
+pub struct DcaConfig {
+    /// Dollars to buy of each symbol every run.
+    pub amounts: BTreeMap<String, f64>,
+    /// Buy fractional quantities. Otherwise quantities are rounded down and the remainder stays in cash.
+    pub fractional: bool,
+    /// Days of the week a scheduled run buys, 1 for Monday to 5 for Friday. Empty buys every weekday.
+    pub weekdays: Vec<u32>,
+    /// When settled cash does not cover a run, its buys wait for the cash to settle instead of being sent to
+    /// be rejected. `None` sends them regardless.
+    pub funding: Option<FundingConfig>,
+}
+
+/// Outcome of one dollar-cost averaging run.
+#[derive(Debug, Clone, Default)]
+pub struct DcaReport {
+    pub planned: Vec<Order>,
+    pub submitted: Vec<Order>,
+    /// Orders that failed, with the error message.
+    pub failed: Vec<(Order, String)>,
+    /// Orders held back until settled cash covers them.
+    pub deferred: Vec<Order>,
+}
+
+/// Buys the configured dollar amount of each symbol at the latest quote. If settled cash does not cover the
+/// whole run and `config.funding` is set, no order is sent now: they are all deferred together and the
+/// returned handle sends them once the cash settles. Dropping the handle abandons them.
+pub async fn dca_buy<C>(
+    client: &C,
+    config: &DcaConfig,
+    cancel: CancellationToken,
+) -> Result<(DcaReport, Option<DeferredOrders>), Box<dyn Error>>
+where
+    C: BrokerClient + Clone + Send + Sync + 'static,
+{
+    let mut report = DcaReport::default();
+    let mut required = 0.0;
+    for (symbol, amount) in &config.amounts {
+        let price = client.get_latest_quote(symbol).await?.ask_price;
+        if price <= 0.0 {
+            return Err(format!("No ask price for {}", symbol).into());
+        }
+        let fractional = config.fractional || symbol.contains('/');
+        let quantity = if fractional {
+            amount / price
+        } else {
+            (amount / price).floor()
+        };
+        if quantity <= 0.0 {
+            tracing::warn!(%symbol, amount, price, "DCA amount buys less than one share");
+            continue;
+        }
+        required += quantity * price;
+        report.planned.push(
+            Order::builder()
+                .symbol(symbol.clone())
+                .quantity(quantity)
+                .side(OrderSide::Buy)
+                .order_type(OrderType::Market)
+                .time_in_force(if fractional {
+                    TimeInForce::Gtc
+                } else {
+                    TimeInForce::Day
+                })
+                .tag(DCA_TAG, "true")
+                .build()?,
+        );
+    }
+
+    if let Some(funding) = &config.funding {
+        let settled = client.get_account().await?.settled_cash();
+        if required > settled {
+            tracing::info!(required, settled, "DCA buys deferred until cash settles");
+            report.deferred = report.planned.clone();
+            let deferred = DeferredOrders::spawn(
+                client.clone(),
+                report.deferred.clone(),
+                required,
+                funding.clone(),
+                cancel,
+            );
+            return Ok((report, Some(deferred)));
+        }
+    }
+
+    for order in &report.planned {
+        match client.create_order(order).await.map_err(|e| e.to_string()) {
+            Ok(()) => {
+                tracing::info!(symbol = %order.symbol, quantity = order.quantity, "DCA order sent");
+                report.submitted.push(order.clone());
+            }
+            Err(e) => {
+                tracing::error!(symbol = %order.symbol, error = %e, "DCA order failed");
+                report.failed.push((order.clone(), e));
+            }
+        }
+    }
+    Ok((report, None))
+}
+
+/// Runs `dca_buy` at `hour:minute` US Eastern time on the configured weekdays, until cancelled or the returned
+/// handle is dropped. Deferred buys are kept waiting across runs; a failed run is logged and skipped.
+pub fn schedule_dca<C>(
+    client: C,
+    config: DcaConfig,
+    hour: u32,
+    minute: u32,
+    cancel: CancellationToken,
+) -> DcaSchedule
+where
+    C: BrokerClient + Clone + Send + Sync + 'static,
+{
+    let task = tokio::spawn(async move {
+        let mut pending: Vec<DeferredOrders> = vec![];
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(time::until_us_eastern(hour, minute)) => {}
+                _ = cancel.cancelled() => return,
+            }
+            let weekday = time::us_eastern_weekday(time::now_nanos()) as u32;
+            if weekday > 5 || !(config.weekdays.is_empty() || config.weekdays.contains(&weekday)) {
+                continue;
+            }
+            pending.retain(|deferred| !deferred.is_finished());
+            let result = dca_buy(&client, &config, cancel.child_token())
+                .await
+                .map_err(|e| e.to_string());
+            match result {
+                Ok((_, Some(deferred))) => pending.push(deferred),
+                Ok((_, None)) => {}
+                Err(e) => tracing::error!(error = %e, "Scheduled DCA buy failed"),
+            }
+        }
+    });
+    DcaSchedule { task }
+}
+
+/// Background task started by `schedule_dca`. Stops when dropped, abandoning any deferred buys.
+pub struct DcaSchedule {
+    task: JoinHandle<()>,
+}
+
+impl Drop for DcaSchedule {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -0,0 +1,196 @@
+use crate::{
+    datastructures::client::TradingClient,
+    journal::{JournalEvent, OrderJournal},
+    killswitch::KillSwitch,
+    risk::TradingHalt,
+    time,
+};
+use std::{collections::VecDeque, time::Duration};
+use tokio::{sync::watch, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
+
+/// Equity against its peak at one point in time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrawdownState {
+    pub equity: f64,
+    pub peak: f64,
+    /// Fraction of `peak` lost, from 0 to 1.
+    pub drawdown: f64,
+    /// Nanoseconds since the Unix epoch.
+    pub at: i64,
+}
+
+/// Running peak of equity, over all updates or over a rolling window.
+#[derive(Debug, Clone, Default)]
+pub struct DrawdownTracker {
+    window: Option<Duration>,
+    /// Candidate peaks with their times, equity decreasing from front to back.
+    peaks: VecDeque<(i64, f64)>,
+}
+
+impl DrawdownTracker {
+    /// Measures drawdown from the highest equity seen within `window`, or ever with `None`.
+    pub fn new(window: Option<Duration>) -> Self {
+        DrawdownTracker {
+            window,
+            peaks: VecDeque::new(),
+        }
+    }
+
+    /// Records equity at `at`, in nanoseconds since the Unix epoch.
+    pub fn update(&mut self, equity: f64, at: i64) -> DrawdownState {
+        while self.peaks.back().is_some_and(|(_, peak)| *peak <= equity) {
+            self.peaks.pop_back();
+        }
+        self.peaks.push_back((at, equity));
+        if let Some(window) = self.window {
+            let start = at - window.as_nanos() as i64;
+            while self.peaks.front().is_some_and(|(time, _)| *time < start) {
+                self.peaks.pop_front();
+            }
+        }
+
+        let peak = self.peaks.front().map_or(equity, |(_, peak)| *peak);
+        DrawdownState {
+            equity,
+            peak,
+            drawdown: if peak > 0.0 {
+                ((peak - equity) / peak).max(0.0)
+            } else {
+                0.0
+            },
+            at,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.peaks.clear();
+    }
+}
+
+#[derive(Clone)]
+pub struct DrawdownConfig {
+    /// Drawdown, as a fraction of peak equity, at which trading is halted. 0.1 halts after a 10% loss.
+    pub threshold: f64,
+    /// Rolling window the peak is taken over. `None` uses the peak since the monitor started.
+    pub window: Option<Duration>,
+    /// How often account equity is read.
+    pub poll_interval: Duration,
+    /// Set on a breach. Put the same halt in `RiskLimits::halt` so only reducing orders are accepted.
+    pub halt: TradingHalt,
+    /// Publishes a `JournalEvent::Drawdown` on a breach, e.g. for webhooks.
+    pub journal: Option<OrderJournal>,
+    /// Also cancel every open order and close every position with a `KillSwitch`.
+    pub flatten: bool,
+}
+
+impl Default for DrawdownConfig {
+    fn default() -> Self {
+        DrawdownConfig {
+            threshold: 0.1,
+            window: None,
+            poll_interval: Duration::from_secs(5),
+            halt: TradingHalt::new(),
+            journal: None,
+            flatten: false,
+        }
+    }
+}
+
+/// Polls account equity and halts trading once its drawdown reaches the threshold. The halt stays until
+/// resumed through `TradingHalt::resume`; the monitor keeps tracking equity meanwhile but does not act again
+/// until then. Stops when dropped.
+pub struct DrawdownMonitor {
+    latest: watch::Receiver<Option<DrawdownState>>,
+    halt: TradingHalt,
+    task: JoinHandle<()>,
+}
+
+impl DrawdownMonitor {
+    pub fn spawn<C>(client: C, config: DrawdownConfig, cancel: CancellationToken) -> DrawdownMonitor
+    where
+        C: TradingClient + Clone + Send + Sync + 'static,
+    {
+        let (sender, latest) = watch::channel(None);
+        let halt = config.halt.clone();
+        let task = tokio::spawn(async move {
+            let kill_switch = KillSwitch::new(client.clone());
+            let mut tracker = DrawdownTracker::new(config.window);
+            let mut ticker = tokio::time::interval(config.poll_interval);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    _ = cancel.cancelled() => return,
+                }
+                let equity = match client.get_account().await.map_err(|e| e.to_string()) {
+                    Ok(account) => account.equity,
+                    Err(e) => {
+                        tracing::error!(error = %e, "Failed to read equity for drawdown");
+                        continue;
+                    }
+                };
+                let state = tracker.update(equity, time::now_nanos());
+                if sender.send(Some(state)).is_err() {
+                    return;
+                }
+                if state.drawdown < config.threshold || config.halt.is_halted() {
+                    continue;
+                }
+
+                config.halt.halt(format!(
+                    "drawdown of {:.2}% from peak equity {:.2} reached the {:.2}% limit",
+                    state.drawdown * 100.0,
+                    state.peak,
+                    config.threshold * 100.0
+                ));
+                if let Some(journal) = &config.journal {
+                    journal.publish(JournalEvent::Drawdown {
+                        equity: state.equity,
+                        peak: state.peak,
+                        drawdown: state.drawdown,
+                    });
+                }
+                if config.flatten {
+                    let token = kill_switch.arm();
+                    match kill_switch
+                        .panic_close(&token)
+                        .await
+                        .map_err(|e| e.to_string())
+                    {
+                        Ok(report) if report.is_complete() => {}
+                        Ok(report) => {
+                            tracing::error!(
+                                ?report,
+                                "Drawdown flatten left orders or positions open"
+                            )
+                        }
+                        Err(e) => tracing::error!(error = %e, "Drawdown flatten failed"),
+                    }
+                }
+            }
+        });
+
+        DrawdownMonitor { latest, halt, task }
+    }
+
+    /// Most recent reading, if equity has been read yet.
+    pub fn latest(&self) -> Option<DrawdownState> {
+        *self.latest.borrow()
+    }
+
+    /// Waits for the next reading.
+    pub async fn changed(&mut self) -> Option<DrawdownState> {
+        self.latest.changed().await.ok()?;
+        *self.latest.borrow()
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halt.is_halted()
+    }
+}
+
+impl Drop for DrawdownMonitor {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
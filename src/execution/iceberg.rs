@@ -0,0 +1,115 @@
+use super::{Children, ExecutionHandle, ExecutionState};
+use crate::datastructures::{
+    client::BrokerClient,
+    order::{Order, OrderType},
+};
+use rand::Rng;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, Clone)]
+pub struct IcebergConfig {
+    /// Quantity shown on the book at a time.
+    pub display_quantity: f64,
+    /// Each slice is varied by up to this fraction of the display quantity either way, so the refills do not
+    /// repeat one recognisable size. Zero shows the same size every time.
+    pub display_variance: f64,
+    /// How often the working slice is read back for fills.
+    pub poll_interval: Duration,
+}
+
+impl Default for IcebergConfig {
+    fn default() -> Self {
+        IcebergConfig {
+            display_quantity: 100.0,
+            display_variance: 0.0,
+            poll_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Client-side iceberg for brokers without native reserve orders: a limit parent is worked one slice of the
+/// display quantity at a time at the parent's limit price, with the next slice sent once the working one fills.
+/// The execution fails if a slice is cancelled, expires or is rejected before filling.
+pub struct Iceberg;
+
+impl Iceberg {
+    pub fn start<C>(
+        client: C,
+        parent: Order,
+        config: IcebergConfig,
+        cancel: CancellationToken,
+    ) -> Result<ExecutionHandle, &'static str>
+    where
+        C: BrokerClient + Send + Sync + 'static,
+    {
+        if parent.order_type != OrderType::Limit || parent.limit_price.is_none() {
+            return Err("Iceberg parent must be a limit order");
+        }
+        if parent.quantity <= 0.0 {
+            return Err("Parent quantity must be positive");
+        }
+        if config.display_quantity <= 0.0 {
+            return Err("Display quantity must be positive");
+        }
+        if !(0.0..1.0).contains(&config.display_variance) {
+            return Err("Display variance must be at least 0 and less than 1");
+        }
+
+        let mut children = Children::new(parent);
+        let (sender, progress) = watch::channel(children.progress.clone());
+        let task = tokio::spawn(async move {
+            children.arrive(&client).await;
+            let mut poll = tokio::time::interval(config.poll_interval);
+            // Parent quantity filled once the working slice fills.
+            let mut slice_end = 0.0;
+
+            let state = loop {
+                if children.progress.remaining() <= 0.0 {
+                    break ExecutionState::Completed;
+                }
+                if !children.is_working() {
+                    // A slice that ended short was cancelled, expired or rejected at the broker.
+                    if children.progress.filled + 1e-9 < slice_end {
+                        break ExecutionState::Failed("Iceberg slice ended unfilled".to_string());
+                    }
+                    let variance = config.display_variance * config.display_quantity;
+                    let display = if variance > 0.0 {
+                        config.display_quantity + rand::thread_rng().gen_range(-variance..=variance)
+                    } else {
+                        config.display_quantity
+                    };
+                    // A slice too small to round to a whole share shows the rest at once.
+                    let quantity = match children.round(display) {
+                        quantity if quantity > 0.0 => quantity,
+                        _ => children.round(children.progress.remaining()),
+                    };
+                    let submitted = children
+                        .submit(&client, quantity, None)
+                        .await
+                        .map_err(|e| e.to_string());
+                    if let Err(e) = submitted {
+                        break ExecutionState::Failed(e);
+                    }
+                    slice_end = children.progress.filled + quantity;
+                    sender.send_replace(children.progress.clone());
+                }
+
+                tokio::select! {
+                    _ = poll.tick() => {
+                        if let Err(e) = children.poll(&client).await.map_err(|e| e.to_string()) {
+                            tracing::warn!(error = %e, "Failed to read iceberg slice");
+                        }
+                    }
+                    _ = cancel.cancelled() => break ExecutionState::Cancelled,
+                }
+                sender.send_replace(children.progress.clone());
+            };
+
+            children.finish(&client, state, &sender, "iceberg").await;
+        });
+
+        Ok(ExecutionHandle { progress, task })
+    }
+}
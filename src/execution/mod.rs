@@ -0,0 +1,372 @@
+mod iceberg;
+mod pov;
+mod schedule;
+mod twap;
+mod vwap;
+
+pub use iceberg::{Iceberg, IcebergConfig};
+pub use pov::{Pov, PovConfig};
+pub use twap::{Twap, TwapConfig};
+pub use vwap::{volume_profile, Vwap, VwapConfig};
+
+use crate::datastructures::{
+    client::{BrokerClient, TradingClient},
+    event::EventType,
+    order::{Order, OrderResponse, OrderSide, OrderType},
+};
+use serde::Serialize;
+use std::error::Error;
+use tokio::{sync::watch, task::JoinHandle};
+
+/// Metadata key holding the client order id of the parent order on each child order.
+pub const PARENT_TAG: &str = "parent";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum ExecutionState {
+    Working,
+    /// The whole parent quantity filled.
+    Completed,
+    /// Stopped through the cancellation token before completing. Any working child was cancelled.
+    Cancelled,
+    /// Stopped by an error, e.g. a rejected child order.
+    Failed(String),
+    /// The scheduled window ended before the whole parent quantity filled.
+    Expired,
+}
+
+/// How far an execution algorithm has worked its parent order.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExecutionProgress {
+    pub symbol: String,
+    pub side: OrderSide,
+    pub quantity: f64,
+    pub filled: f64,
+    pub average_price: Option<f64>,
+    /// Child orders sent so far.
+    pub children: usize,
+    /// Volume traded by everyone else since the algorithm started, for participation algorithms.
+    pub market_volume: f64,
+    /// Quote midpoint when the algorithm started.
+    pub arrival_price: Option<f64>,
+    /// Market price the fills are measured against, from the trade tape since the start: the volume-weighted
+    /// average for VWAP and POV, the time-weighted average for TWAP.
+    pub benchmark: Option<f64>,
+    pub state: ExecutionState,
+}
+
+impl ExecutionProgress {
+    fn new(parent: &Order) -> Self {
+        ExecutionProgress {
+            symbol: parent.symbol.clone(),
+            side: parent.side,
+            quantity: parent.quantity,
+            filled: 0.0,
+            average_price: None,
+            children: 0,
+            market_volume: 0.0,
+            arrival_price: None,
+            benchmark: None,
+            state: ExecutionState::Working,
+        }
+    }
+
+    pub fn remaining(&self) -> f64 {
+        (self.quantity - self.filled).max(0.0)
+    }
+
+    /// Share of the market volume executed, counting the algorithm's own fills in the total.
+    pub fn participation(&self) -> f64 {
+        let total = self.market_volume + self.filled;
+        if total > 0.0 {
+            self.filled / total
+        } else {
+            0.0
+        }
+    }
+
+    /// Cost of the fills against the benchmark in basis points, positive when worse: paid above it on a buy or
+    /// received below it on a sell.
+    pub fn slippage_bps(&self) -> Option<f64> {
+        self.cost_bps(self.benchmark?)
+    }
+
+    /// Cost of the fills against the arrival price in basis points, positive when worse.
+    pub fn arrival_slippage_bps(&self) -> Option<f64> {
+        self.cost_bps(self.arrival_price?)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.state != ExecutionState::Working
+    }
+
+    fn cost_bps(&self, reference: f64) -> Option<f64> {
+        let average_price = self.average_price?;
+        if reference <= 0.0 {
+            return None;
+        }
+        let cost = (average_price - reference) / reference * 10_000.0;
+        Some(match self.side {
+            OrderSide::Buy => cost,
+            OrderSide::Sell => -cost,
+        })
+    }
+}
+
+/// Market average a benchmark is taken as.
+#[derive(Debug, Clone, Copy)]
+enum Benchmark {
+    VolumeWeighted,
+    TimeWeighted,
+}
+
+/// Trades in the parent's symbol seen on the tape while an algorithm runs.
+#[derive(Default)]
+struct Tape {
+    volume: f64,
+    notional: f64,
+    last_price: Option<f64>,
+    /// Sum and count of the last trade price taken at regular times, for a time-weighted average.
+    sampled: f64,
+    samples: usize,
+}
+
+impl Tape {
+    /// Records a trade in `symbol`, returning its volume. Other events count as no volume.
+    fn record(&mut self, event: &EventType, symbol: &str) -> f64 {
+        match event {
+            EventType::Trade {
+                symbol: traded,
+                price,
+                volume,
+                ..
+            } if traded == symbol => {
+                self.volume += *volume;
+                self.notional += price * *volume;
+                self.last_price = Some(*price);
+                *volume
+            }
+            _ => 0.0,
+        }
+    }
+
+    fn sample(&mut self) {
+        if let Some(price) = self.last_price {
+            self.sampled += price;
+            self.samples += 1;
+        }
+    }
+
+    fn benchmark(&self, benchmark: Benchmark) -> Option<f64> {
+        match benchmark {
+            Benchmark::VolumeWeighted if self.volume > 0.0 => Some(self.notional / self.volume),
+            Benchmark::TimeWeighted if self.samples > 0 => Some(self.sampled / self.samples as f64),
+            _ => None,
+        }
+    }
+}
+
+/// Execution algorithm running in the background. Stop it gracefully with the cancellation token it was
+/// started with; dropping the handle stops it at once and leaves any working child order at the broker.
+pub struct ExecutionHandle {
+    progress: watch::Receiver<ExecutionProgress>,
+    task: JoinHandle<()>,
+}
+
+impl ExecutionHandle {
+    pub fn progress(&self) -> ExecutionProgress {
+        self.progress.borrow().clone()
+    }
+
+    /// Waits for the next change in progress. `None` once the algorithm has stopped.
+    pub async fn changed(&mut self) -> Option<ExecutionProgress> {
+        self.progress.changed().await.ok()?;
+        Some(self.progress.borrow().clone())
+    }
+
+    /// Waits until the algorithm completes, is cancelled or fails.
+    pub async fn finished(&mut self) -> ExecutionProgress {
+        // The sender is dropped once the task ends, with the final progress already published.
+        let _ = self.progress.wait_for(ExecutionProgress::is_done).await;
+        self.progress.borrow().clone()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+impl Drop for ExecutionHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Child order currently at the broker and how much of it has filled.
+struct WorkingChild {
+    client_order_id: String,
+    limit_price: Option<f64>,
+    filled: f64,
+    notional: f64,
+}
+
+/// Child orders of one parent, sent one at a time, with their fills folded into the parent's progress.
+struct Children {
+    parent: Order,
+    /// Base for child client order ids.
+    parent_id: String,
+    working: Option<WorkingChild>,
+    progress: ExecutionProgress,
+    notional: f64,
+}
+
+impl Children {
+    fn new(parent: Order) -> Self {
+        let parent_id = parent
+            .client_order_id
+            .clone()
+            .unwrap_or_else(|| format!("{:016x}", rand::random::<u64>()));
+        Children {
+            progress: ExecutionProgress::new(&parent),
+            parent,
+            parent_id,
+            working: None,
+            notional: 0.0,
+        }
+    }
+
+    /// Records the quote midpoint as the arrival price. A failed read leaves it unset.
+    async fn arrive<C: BrokerClient + ?Sized>(&mut self, client: &C) {
+        let quote = client.get_latest_quote(&self.parent.symbol).await.ok();
+        self.progress.arrival_price = quote.map(|quote| quote.mid()).filter(|mid| *mid > 0.0);
+    }
+
+    fn is_working(&self) -> bool {
+        self.working.is_some()
+    }
+
+    /// Rounds a child quantity down to whole shares unless the parent is fractional.
+    fn round(&self, quantity: f64) -> f64 {
+        let quantity = quantity.min(self.progress.remaining());
+        if self.parent.quantity.fract() == 0.0 {
+            quantity.floor()
+        } else {
+            quantity
+        }
+    }
+
+    /// Limit price of the working child, if it has one.
+    fn working_limit(&self) -> Option<f64> {
+        self.working.as_ref()?.limit_price
+    }
+
+    /// Sends a child for `quantity` at the parent's price, or at `limit_price` when given. A market parent's
+    /// child given a limit price goes out as a limit order.
+    async fn submit<C: TradingClient + ?Sized>(
+        &mut self,
+        client: &C,
+        quantity: f64,
+        limit_price: Option<f64>,
+    ) -> Result<(), Box<dyn Error>> {
+        let client_order_id = format!("{}-{}", self.parent_id, self.progress.children + 1);
+        let mut child = self.parent.clone();
+        child.quantity = quantity;
+        if let Some(limit_price) = limit_price {
+            child.limit_price = Some(limit_price);
+            if child.order_type == OrderType::Market {
+                child.order_type = OrderType::Limit;
+            }
+        }
+        child.client_order_id = Some(client_order_id.clone());
+        child
+            .metadata
+            .insert(PARENT_TAG.to_string(), self.parent_id.clone());
+        client.create_order(&child).await?;
+        tracing::debug!(%client_order_id, quantity, "Child order sent");
+        self.progress.children += 1;
+        self.working = Some(WorkingChild {
+            client_order_id,
+            limit_price: child.limit_price,
+            filled: 0.0,
+            notional: 0.0,
+        });
+        Ok(())
+    }
+
+    /// Reads the working child back from the broker and records new fills.
+    async fn poll<C: TradingClient + ?Sized>(&mut self, client: &C) -> Result<(), Box<dyn Error>> {
+        let Some(working) = &self.working else {
+            return Ok(());
+        };
+        let order = client
+            .get_order_by_client_id(&working.client_order_id)
+            .await?;
+        self.record(&order);
+        Ok(())
+    }
+
+    /// Cancels the working child, recording whatever filled before the cancel took effect.
+    async fn cancel<C: TradingClient + ?Sized>(
+        &mut self,
+        client: &C,
+    ) -> Result<(), Box<dyn Error>> {
+        let Some(working) = &self.working else {
+            return Ok(());
+        };
+        let order = client
+            .get_order_by_client_id(&working.client_order_id)
+            .await?;
+        if order.status.is_terminal() {
+            self.record(&order);
+            return Ok(());
+        }
+        let outcome = client.cancel_order(&order.id).await?;
+        self.record(outcome.order());
+        Ok(())
+    }
+
+    /// Cancels any working child unless the parent completed, then publishes the final progress.
+    async fn finish<C: TradingClient + ?Sized>(
+        mut self,
+        client: &C,
+        state: ExecutionState,
+        sender: &watch::Sender<ExecutionProgress>,
+        algorithm: &str,
+    ) {
+        if state != ExecutionState::Completed {
+            if let Err(e) = self.cancel(client).await.map_err(|e| e.to_string()) {
+                tracing::error!(error = %e, "Failed to cancel child order");
+            }
+        }
+        let progress = &self.progress;
+        tracing::info!(
+            algorithm,
+            symbol = %progress.symbol,
+            filled = progress.filled,
+            average_price = ?progress.average_price,
+            benchmark = ?progress.benchmark,
+            slippage_bps = ?progress.slippage_bps(),
+            ?state,
+            "Execution stopped"
+        );
+        self.progress.state = state;
+        sender.send_replace(self.progress);
+    }
+
+    fn record(&mut self, order: &OrderResponse) {
+        let Some(working) = &mut self.working else {
+            return;
+        };
+        if order.filled_qty > working.filled {
+            // The broker reports the average over the whole child, so only the change is new.
+            let notional = order.filled_qty * order.filled_avg_price.unwrap_or_default();
+            self.progress.filled += order.filled_qty - working.filled;
+            self.notional += notional - working.notional;
+            self.progress.average_price = Some(self.notional / self.progress.filled);
+            working.filled = order.filled_qty;
+            working.notional = notional;
+        }
+        if order.status.is_terminal() {
+            self.working = None;
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use super::{Benchmark, Children, ExecutionHandle, ExecutionState, Tape};
+use crate::{
+    datastructures::{
+        client::BrokerClient,
+        event::EventType,
+        order::{Order, OrderType},
+    },
+    fees::{FeeModel, Liquidity},
+};
+use futures_util::{Stream, StreamExt};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, Clone)]
+pub struct PovConfig {
+    /// Largest share of the total traded volume to take, e.g. 0.1 for 10%.
+    pub participation: f64,
+    /// Smallest child order. Volume builds up until at least this much may be sent, except for the last child.
+    pub min_child: f64,
+    /// Largest child order, so one burst of volume is not answered with one large order.
+    pub max_child: Option<f64>,
+    /// How often the working child is read back for fills.
+    pub poll_interval: Duration,
+    /// Venue fees. With a market parent, children rest at the near touch as limit orders while `edge_bps` does
+    /// not cover the taker fee, and are moved whenever the touch moves.
+    pub fees: Option<FeeModel>,
+    /// Expected edge of the parent order in basis points, weighed against `fees`.
+    pub edge_bps: f64,
+}
+
+impl Default for PovConfig {
+    fn default() -> Self {
+        PovConfig {
+            participation: 0.1,
+            min_child: 1.0,
+            max_child: None,
+            poll_interval: Duration::from_secs(1),
+            fees: None,
+            edge_bps: 0.0,
+        }
+    }
+}
+
+/// Percentage-of-volume execution: works a parent order in child orders paced by the trade tape, so the
+/// algorithm's fills stay at or under the configured share of the symbol's volume. The tape is expected to
+/// include the algorithm's own prints, as the SIP feed does; they are taken out of the market volume.
+/// One child works at a time, at the parent's type and limit price unless `PovConfig::fees` calls for maker
+/// placement.
+pub struct Pov;
+
+impl Pov {
+    /// Starts working `parent` against the trades in `tape`, e.g. `bus.subscribe().into_stream()`.
+    pub fn start<C, S>(
+        client: C,
+        parent: Order,
+        config: PovConfig,
+        tape: S,
+        cancel: CancellationToken,
+    ) -> Result<ExecutionHandle, &'static str>
+    where
+        C: BrokerClient + Send + Sync + 'static,
+        S: Stream<Item = EventType> + Send + 'static,
+    {
+        if !(config.participation > 0.0 && config.participation < 1.0) {
+            return Err("Participation must be between 0 and 1");
+        }
+        if parent.quantity <= 0.0 {
+            return Err("Parent quantity must be positive");
+        }
+
+        let mut children = Children::new(parent);
+        let (sender, progress) = watch::channel(children.progress.clone());
+        let task = tokio::spawn(async move {
+            children.arrive(&client).await;
+            let mut tape = Box::pin(tape);
+            let mut trades = Tape::default();
+            let mut poll = tokio::time::interval(config.poll_interval);
+            // Each unit of other volume allows this much of ours.
+            let ratio = config.participation / (1.0 - config.participation);
+            let maker = config.fees.as_ref().is_some_and(|fees| {
+                children.parent.order_type == OrderType::Market
+                    && fees.placement(config.edge_bps) == Liquidity::Maker
+            });
+            let mut touch: Option<f64> = None;
+
+            let state = loop {
+                tokio::select! {
+                    event = tape.next() => match event {
+                        Some(event @ EventType::Trade { .. }) => {
+                            trades.record(&event, &children.parent.symbol);
+                        }
+                        Some(EventType::Quote { symbol, bid_price, ask_price, .. }) if maker && symbol == children.parent.symbol => {
+                            touch = Liquidity::Maker.limit_price(children.parent.side, bid_price, ask_price)
+                                .filter(|price| *price > 0.0);
+                            let moved = children.working_limit().is_some_and(|limit| Some(limit) != touch);
+                            if moved {
+                                if let Err(e) = children.cancel(&client).await.map_err(|e| e.to_string()) {
+                                    tracing::warn!(error = %e, "Failed to cancel child order to follow the touch");
+                                }
+                            }
+                        }
+                        Some(_) => continue,
+                        None => break ExecutionState::Failed("Trade feed ended".to_string()),
+                    },
+                    _ = poll.tick(), if children.is_working() => {
+                        if let Err(e) = children.poll(&client).await.map_err(|e| e.to_string()) {
+                            tracing::warn!(error = %e, "Failed to read child order");
+                        }
+                    }
+                    _ = cancel.cancelled() => break ExecutionState::Cancelled,
+                }
+
+                let (filled, remaining) = (children.progress.filled, children.progress.remaining());
+                children.progress.market_volume = (trades.volume - filled).max(0.0);
+                children.progress.benchmark = trades.benchmark(Benchmark::VolumeWeighted);
+                if remaining <= 0.0 {
+                    break ExecutionState::Completed;
+                }
+                if !children.is_working() {
+                    let allowed = ratio * children.progress.market_volume - filled;
+                    let quantity =
+                        children.round(config.max_child.map_or(allowed, |max| allowed.min(max)));
+                    let minimum = config.min_child.min(remaining).max(f64::EPSILON);
+                    if quantity >= minimum && (!maker || touch.is_some()) {
+                        let submitted = children
+                            .submit(&client, quantity, touch.filter(|_| maker))
+                            .await
+                            .map_err(|e| e.to_string());
+                        if let Err(e) = submitted {
+                            break ExecutionState::Failed(e);
+                        }
+                    }
+                }
+                sender.send_replace(children.progress.clone());
+            };
+
+            children.finish(&client, state, &sender, "POV").await;
+        });
+
+        Ok(ExecutionHandle { progress, task })
+    }
+}
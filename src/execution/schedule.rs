@@ -0,0 +1,100 @@
+use super::{Benchmark, Children, ExecutionHandle, ExecutionState, Tape};
+use crate::datastructures::{client::BrokerClient, event::EventType, order::Order};
+use futures_util::{Stream, StreamExt};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+/// How a scheduled algorithm paces its parent order.
+pub(super) struct Schedule {
+    /// Cumulative fraction of the parent to have sent by the start of each slice, ending at 1.
+    pub targets: Vec<f64>,
+    pub duration: Duration,
+    pub poll_interval: Duration,
+    pub benchmark: Benchmark,
+    pub algorithm: &'static str,
+}
+
+/// Works `parent` over equal slices of the schedule's duration. At the start of each slice the working child is
+/// cancelled and a new one sent for whatever brings the parent up to that slice's target, so a child that did not
+/// fill is caught up in the next slice. The execution expires if anything is left unfilled when the window ends.
+pub(super) fn start<C, S>(
+    client: C,
+    parent: Order,
+    schedule: Schedule,
+    tape: S,
+    cancel: CancellationToken,
+) -> ExecutionHandle
+where
+    C: BrokerClient + Send + Sync + 'static,
+    S: Stream<Item = EventType> + Send + 'static,
+{
+    let mut children = Children::new(parent);
+    let (sender, progress) = watch::channel(children.progress.clone());
+    let task = tokio::spawn(async move {
+        children.arrive(&client).await;
+        let mut tape = Box::pin(tape);
+        let mut tape_open = true;
+        let mut trades = Tape::default();
+        let mut slices = tokio::time::interval(schedule.duration / schedule.targets.len() as u32);
+        let mut poll = tokio::time::interval(schedule.poll_interval);
+        let mut slice = 0;
+
+        let state = loop {
+            tokio::select! {
+                event = tape.next(), if tape_open => match event {
+                    Some(event) => {
+                        trades.record(&event, &children.parent.symbol);
+                    }
+                    // The benchmark stops updating, but the schedule does not depend on the tape.
+                    None => tape_open = false,
+                },
+                _ = slices.tick() => {
+                    if let Err(e) = children.cancel(&client).await.map_err(|e| e.to_string()) {
+                        tracing::warn!(error = %e, "Failed to cancel child order at the end of its slice");
+                        continue;
+                    }
+                    let Some(target) = schedule.targets.get(slice) else {
+                        break if children.progress.remaining() <= 0.0 {
+                            ExecutionState::Completed
+                        } else {
+                            ExecutionState::Expired
+                        };
+                    };
+                    slice += 1;
+                    trades.sample();
+                    let quantity =
+                        children.round(children.parent.quantity * target - children.progress.filled);
+                    if quantity > 0.0 {
+                        let submitted = children
+                            .submit(&client, quantity, None)
+                            .await
+                            .map_err(|e| e.to_string());
+                        if let Err(e) = submitted {
+                            break ExecutionState::Failed(e);
+                        }
+                    }
+                }
+                _ = poll.tick(), if children.is_working() => {
+                    if let Err(e) = children.poll(&client).await.map_err(|e| e.to_string()) {
+                        tracing::warn!(error = %e, "Failed to read child order");
+                    }
+                }
+                _ = cancel.cancelled() => break ExecutionState::Cancelled,
+            }
+
+            children.progress.market_volume = (trades.volume - children.progress.filled).max(0.0);
+            children.progress.benchmark = trades.benchmark(schedule.benchmark);
+            if children.progress.remaining() <= 0.0 {
+                break ExecutionState::Completed;
+            }
+            sender.send_replace(children.progress.clone());
+        };
+
+        children
+            .finish(&client, state, &sender, schedule.algorithm)
+            .await;
+    });
+
+    ExecutionHandle { progress, task }
+}
@@ -0,0 +1,66 @@
+use super::{
+    schedule::{self, Schedule},
+    Benchmark, ExecutionHandle,
+};
+use crate::datastructures::{client::BrokerClient, event::EventType, order::Order};
+use futures_util::Stream;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, Clone)]
+pub struct TwapConfig {
+    /// Window the parent is spread over.
+    pub duration: Duration,
+    /// Number of equal child slices.
+    pub slices: usize,
+    /// How often the working child is read back for fills.
+    pub poll_interval: Duration,
+}
+
+impl Default for TwapConfig {
+    fn default() -> Self {
+        TwapConfig {
+            duration: Duration::from_secs(30 * 60),
+            slices: 30,
+            poll_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Time-weighted execution: works a parent order in equal child slices over a window, each at the parent's
+/// type and limit price. Fills are measured against the time-weighted average trade price on the tape.
+pub struct Twap;
+
+impl Twap {
+    /// Starts working `parent` now. `tape` only feeds the benchmark; pass `futures_util::stream::empty()` to
+    /// run without one.
+    pub fn start<C, S>(
+        client: C,
+        parent: Order,
+        config: TwapConfig,
+        tape: S,
+        cancel: CancellationToken,
+    ) -> Result<ExecutionHandle, &'static str>
+    where
+        C: BrokerClient + Send + Sync + 'static,
+        S: Stream<Item = EventType> + Send + 'static,
+    {
+        if config.slices == 0 || config.duration.is_zero() {
+            return Err("TWAP needs at least one slice and a non-zero duration");
+        }
+        if parent.quantity <= 0.0 {
+            return Err("Parent quantity must be positive");
+        }
+        let targets = (1..=config.slices)
+            .map(|slice| slice as f64 / config.slices as f64)
+            .collect();
+        let schedule = Schedule {
+            targets,
+            duration: config.duration,
+            poll_interval: config.poll_interval,
+            benchmark: Benchmark::TimeWeighted,
+            algorithm: "TWAP",
+        };
+        Ok(schedule::start(client, parent, schedule, tape, cancel))
+    }
+}
@@ -0,0 +1,123 @@
+use super::{
+    schedule::{self, Schedule},
+    Benchmark, ExecutionHandle,
+};
+use crate::{
+    datastructures::{client::BrokerClient, event::EventType, market::Bar, order::Order},
+    time,
+};
+use futures_util::Stream;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, Clone)]
+pub struct VwapConfig {
+    /// Window the parent is spread over.
+    pub duration: Duration,
+    /// Relative volume expected in each equal slice of the window, e.g. from `volume_profile`. Empty trades an
+    /// even profile, the same as TWAP with one slice a minute.
+    pub profile: Vec<f64>,
+    /// How often the working child is read back for fills.
+    pub poll_interval: Duration,
+}
+
+impl Default for VwapConfig {
+    fn default() -> Self {
+        VwapConfig {
+            duration: Duration::from_secs(30 * 60),
+            profile: vec![],
+            poll_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Volume-weighted execution: works a parent order over a window in child slices sized by the volume expected
+/// in each slice, each at the parent's type and limit price. Fills are measured against the market VWAP on the
+/// tape over the execution.
+pub struct Vwap;
+
+impl Vwap {
+    /// Starts working `parent` now, with the first slice of the profile.
+    pub fn start<C, S>(
+        client: C,
+        parent: Order,
+        config: VwapConfig,
+        tape: S,
+        cancel: CancellationToken,
+    ) -> Result<ExecutionHandle, &'static str>
+    where
+        C: BrokerClient + Send + Sync + 'static,
+        S: Stream<Item = EventType> + Send + 'static,
+    {
+        if config.duration.is_zero() {
+            return Err("VWAP needs a non-zero duration");
+        }
+        if parent.quantity <= 0.0 {
+            return Err("Parent quantity must be positive");
+        }
+        if config
+            .profile
+            .iter()
+            .any(|volume| volume.is_nan() || *volume < 0.0)
+        {
+            return Err("Volume profile must not be negative");
+        }
+        let profile = match config.profile.iter().sum::<f64>() {
+            total if total > 0.0 => config.profile,
+            _ if config.profile.is_empty() => {
+                vec![1.0; (config.duration.as_secs() / 60).max(1) as usize]
+            }
+            _ => return Err("Volume profile is all zero"),
+        };
+
+        let total: f64 = profile.iter().sum();
+        let mut cumulative = 0.0;
+        let mut targets: Vec<f64> = profile
+            .iter()
+            .map(|volume| {
+                cumulative += volume;
+                cumulative / total
+            })
+            .collect();
+        // Rounding must not leave a sliver of the parent unscheduled.
+        if let Some(last) = targets.last_mut() {
+            *last = 1.0;
+        }
+        let schedule = Schedule {
+            targets,
+            duration: config.duration,
+            poll_interval: config.poll_interval,
+            benchmark: Benchmark::VolumeWeighted,
+            algorithm: "VWAP",
+        };
+        Ok(schedule::start(client, parent, schedule, tape, cancel))
+    }
+}
+
+/// Share of volume in each of `buckets` equal slices of a window of the trading day, from intraday bars of past
+/// sessions. The window runs from `start` to `end` minutes after midnight US Eastern time, e.g. 570 to 960 for
+/// the regular session; bars outside it are ignored. Sums to 1 unless no bar fell in the window.
+pub fn volume_profile(bars: &[Bar], start: u32, end: u32, buckets: usize) -> Vec<f64> {
+    let mut profile = vec![0.0; buckets];
+    if buckets == 0 || end <= start {
+        return profile;
+    }
+    for bar in bars {
+        let Some(nanos) = time::parse_rfc3339(&bar.timestamp) else {
+            continue;
+        };
+        let local = nanos / 1_000_000_000 + time::us_eastern_offset(nanos);
+        let minute = (local.rem_euclid(86_400) / 60) as u32;
+        if (start..end).contains(&minute) {
+            let bucket = (minute - start) as usize * buckets / (end - start) as usize;
+            profile[bucket] += bar.volume;
+        }
+    }
+    let total: f64 = profile.iter().sum();
+    if total > 0.0 {
+        for volume in &mut profile {
+            *volume /= total;
+        }
+    }
+    profile
+}
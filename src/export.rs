@@ -0,0 +1,299 @@
+#[cfg(feature = "parquet")]
+use crate::parquet::{Column, ColumnType, ParquetWriter, Value};
+use crate::shutdown::Sink;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+/// A strategy decision together with the inputs that produced it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Signal {
+    pub id: String,
+    pub symbol: String,
+    /// Positive for long, negative for short. Magnitude is the strategy's confidence.
+    pub value: f64,
+    pub timestamp: String,
+    /// Feature and indicator values at signal time, keyed by name.
+    pub features: BTreeMap<String, f64>,
+}
+
+/// What eventually happened to the trade taken on a signal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradeOutcome {
+    pub entry_price: f64,
+    pub exit_price: f64,
+    pub quantity: f64,
+    pub realized_pnl: f64,
+    pub closed_at: String,
+}
+
+/// One line of the exported dataset.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LabeledSignal {
+    #[serde(flatten)]
+    pub signal: Signal,
+    /// `None` when the signal was never traded or the trade was still open at export time.
+    pub outcome: Option<TradeOutcome>,
+}
+
+#[cfg(feature = "parquet")]
+const ROWS_PER_GROUP: usize = 10_000;
+
+enum Output {
+    Jsonl(BufWriter<File>),
+    #[cfg(feature = "parquet")]
+    Parquet {
+        writer: ParquetWriter,
+        features: Vec<String>,
+    },
+}
+
+/// Writes signals to a JSONL or Parquet file once their outcome is known, producing a labeled training set.
+pub struct SignalExporter {
+    output: Output,
+    pending: HashMap<String, Signal>,
+}
+
+impl SignalExporter {
+    /// Appends to `path`, creating it if needed.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(SignalExporter {
+            output: Output::Jsonl(BufWriter::new(file)),
+            pending: HashMap::new(),
+        })
+    }
+
+    /// Writes a Parquet file at `path`, replacing any file there. Columns are `id`, `symbol`, `value` and
+    /// `timestamp`, then `feature_<name>` for each of `features`, null where a signal lacks it, then the outcome
+    /// fields, null for unlabeled signals. A signal with a feature outside `features` fails to write.
+    /// The file is readable once `close`d.
+    #[cfg(feature = "parquet")]
+    pub fn create_parquet(path: impl AsRef<Path>, features: &[&str]) -> io::Result<Self> {
+        let mut columns = vec![
+            Column::required("id", ColumnType::String),
+            Column::required("symbol", ColumnType::String),
+            Column::required("value", ColumnType::Double),
+            Column::required("timestamp", ColumnType::String),
+        ];
+        columns.extend(
+            features
+                .iter()
+                .map(|name| Column::optional(&format!("feature_{}", name), ColumnType::Double)),
+        );
+        columns.extend([
+            Column::optional("entry_price", ColumnType::Double),
+            Column::optional("exit_price", ColumnType::Double),
+            Column::optional("quantity", ColumnType::Double),
+            Column::optional("realized_pnl", ColumnType::Double),
+            Column::optional("closed_at", ColumnType::String),
+        ]);
+        Ok(SignalExporter {
+            output: Output::Parquet {
+                writer: ParquetWriter::create(path, columns, ROWS_PER_GROUP)?,
+                features: features.iter().map(|name| name.to_string()).collect(),
+            },
+            pending: HashMap::new(),
+        })
+    }
+
+    /// Holds the signal until `record_outcome` is called with its id.
+    pub fn record_signal(&mut self, signal: Signal) {
+        self.pending.insert(signal.id.clone(), signal);
+    }
+
+    /// Writes the labeled row for a previously recorded signal.
+    pub fn record_outcome(&mut self, signal_id: &str, outcome: TradeOutcome) -> io::Result<()> {
+        let signal = self.pending.remove(signal_id).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Unknown signal id: {}", signal_id),
+            )
+        })?;
+        self.write(LabeledSignal {
+            signal,
+            outcome: Some(outcome),
+        })
+    }
+
+    /// Number of signals still waiting for an outcome.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        match &mut self.output {
+            Output::Jsonl(writer) => writer.flush(),
+            #[cfg(feature = "parquet")]
+            Output::Parquet { writer, .. } => writer.flush(),
+        }
+    }
+
+    /// Writes every signal still waiting for an outcome as unlabeled and flushes the file, finishing a Parquet
+    /// file.
+    pub fn close(mut self) -> io::Result<()> {
+        self.write_pending()
+    }
+
+    fn write_pending(&mut self) -> io::Result<()> {
+        let mut pending: Vec<Signal> = self.pending.drain().map(|(_, signal)| signal).collect();
+        pending.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+        for signal in pending {
+            self.write(LabeledSignal {
+                signal,
+                outcome: None,
+            })?;
+        }
+        match &mut self.output {
+            Output::Jsonl(writer) => {
+                writer.flush()?;
+                writer.get_ref().sync_all()
+            }
+            #[cfg(feature = "parquet")]
+            Output::Parquet { writer, .. } => {
+                writer.finish()?;
+                writer.sync()
+            }
+        }
+    }
+
+    fn write(&mut self, row: LabeledSignal) -> io::Result<()> {
+        match &mut self.output {
+            Output::Jsonl(writer) => {
+                serde_json::to_writer(&mut *writer, &row)?;
+                writer.write_all(b"\n")
+            }
+            #[cfg(feature = "parquet")]
+            Output::Parquet { writer, features } => writer.write_row(parquet_row(row, features)?),
+        }
+    }
+}
+
+#[cfg(feature = "parquet")]
+fn parquet_row(row: LabeledSignal, features: &[String]) -> io::Result<Vec<Value>> {
+    let LabeledSignal { signal, outcome } = row;
+    if let Some(name) = signal.features.keys().find(|name| !features.contains(name)) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Feature {} is not a column of the Parquet export", name),
+        ));
+    }
+    let mut values = vec![
+        Value::String(signal.id),
+        Value::String(signal.symbol),
+        Value::Double(signal.value),
+        Value::String(signal.timestamp),
+    ];
+    values.extend(features.iter().map(|name| {
+        signal
+            .features
+            .get(name)
+            .map_or(Value::Null, |value| Value::Double(*value))
+    }));
+    match outcome {
+        Some(outcome) => values.extend([
+            Value::Double(outcome.entry_price),
+            Value::Double(outcome.exit_price),
+            Value::Double(outcome.quantity),
+            Value::Double(outcome.realized_pnl),
+            Value::String(outcome.closed_at),
+        ]),
+        None => values.extend(std::iter::repeat_n(Value::Null, 5)),
+    }
+    Ok(values)
+}
+
+/// Shared with the code recording signals, e.g. as `Arc<Mutex<SignalExporter>>`.
+#[async_trait]
+impl Sink for Mutex<SignalExporter> {
+    /// Writes every signal still waiting for an outcome as unlabeled, like `SignalExporter::close`.
+    async fn flush_and_close(&self) -> Result<(), Box<dyn Error>> {
+        Ok(self.lock().unwrap().write_pending()?)
+    }
+}
+
+#[cfg(all(test, feature = "parquet"))]
+mod tests {
+    use super::*;
+    use crate::parquet::ParquetReader;
+
+    fn signal(id: &str, features: &[(&str, f64)]) -> Signal {
+        Signal {
+            id: id.to_string(),
+            symbol: "AAPL".to_string(),
+            value: 0.75,
+            timestamp: format!("2024-05-01T14:30:0{}Z", id),
+            features: features
+                .iter()
+                .map(|(name, value)| (name.to_string(), *value))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn exports_labeled_and_unlabeled_signals_as_parquet() {
+        let path = std::env::temp_dir().join(format!("signals-{}.parquet", std::process::id()));
+        let mut exporter = SignalExporter::create_parquet(&path, &["rsi", "spread"]).unwrap();
+        exporter.record_signal(signal("1", &[("rsi", 71.5), ("spread", 0.01)]));
+        exporter.record_signal(signal("2", &[("rsi", 28.0)]));
+        exporter
+            .record_outcome(
+                "1",
+                TradeOutcome {
+                    entry_price: 190.0,
+                    exit_price: 191.5,
+                    quantity: 10.0,
+                    realized_pnl: 15.0,
+                    closed_at: "2024-05-01T15:00:00Z".to_string(),
+                },
+            )
+            .unwrap();
+        exporter.close().unwrap();
+
+        let mut reader = ParquetReader::open(&path).unwrap();
+        let names: Vec<&str> = reader.columns().iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "id",
+                "symbol",
+                "value",
+                "timestamp",
+                "feature_rsi",
+                "feature_spread",
+                "entry_price",
+                "exit_price",
+                "quantity",
+                "realized_pnl",
+                "closed_at"
+            ]
+        );
+        let rows = reader.read_row_group(0).unwrap();
+        assert_eq!(rows[0][0], Value::String("1".to_string()));
+        assert_eq!(rows[0][5], Value::Double(0.01));
+        assert_eq!(rows[0][9], Value::Double(15.0));
+        assert_eq!(rows[1][0], Value::String("2".to_string()));
+        assert_eq!(rows[1][4], Value::Double(28.0));
+        assert_eq!(rows[1][5], Value::Null);
+        assert_eq!(rows[1][10], Value::Null);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn refuses_features_outside_the_parquet_columns() {
+        let path =
+            std::env::temp_dir().join(format!("signals-extra-{}.parquet", std::process::id()));
+        let mut exporter = SignalExporter::create_parquet(&path, &["rsi"]).unwrap();
+        exporter.record_signal(signal("1", &[("macd", 1.0)]));
+        let error = exporter.close().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,197 @@
+use crate::{
+    backtest::CommissionModel,
+    datastructures::order::{Order, OrderSide, OrderType},
+    strategy::Fill,
+    time,
+};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Trailing period over which venues measure volume for fee tiers.
+const VOLUME_WINDOW: Duration = Duration::from_secs(30 * 86_400);
+
+/// Whether a fill added liquidity to the book or took it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Liquidity {
+    /// Rested on the book and was hit.
+    Maker,
+    /// Crossed the spread.
+    Taker,
+}
+
+impl Liquidity {
+    /// Limit price that places an order on the near side of the book as a maker, or `None` for a taker to
+    /// send a market order.
+    pub fn limit_price(&self, side: OrderSide, bid: f64, ask: f64) -> Option<f64> {
+        match (self, side) {
+            (Liquidity::Taker, _) => None,
+            (Liquidity::Maker, OrderSide::Buy) => Some(bid),
+            (Liquidity::Maker, OrderSide::Sell) => Some(ask),
+        }
+    }
+}
+
+/// Fees that apply from `min_volume` of trailing 30-day notional upwards.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeeTier {
+    pub min_volume: f64,
+    pub maker_bps: f64,
+    pub taker_bps: f64,
+}
+
+impl FeeTier {
+    pub fn bps(&self, liquidity: Liquidity) -> f64 {
+        match liquidity {
+            Liquidity::Maker => self.maker_bps,
+            Liquidity::Taker => self.taker_bps,
+        }
+    }
+}
+
+/// A venue's maker/taker fee tiers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeeSchedule {
+    pub venue: String,
+    /// Sorted by `min_volume`.
+    tiers: Vec<FeeTier>,
+}
+
+impl FeeSchedule {
+    /// Schedule from tiers in any order. A venue with no tiers charges nothing.
+    pub fn new(venue: impl Into<String>, mut tiers: Vec<FeeTier>) -> Self {
+        tiers.sort_by(|a, b| a.min_volume.total_cmp(&b.min_volume));
+        FeeSchedule {
+            venue: venue.into(),
+            tiers,
+        }
+    }
+
+    /// Alpaca's crypto fee schedule.
+    pub fn alpaca_crypto() -> Self {
+        let tier = |min_volume, maker_bps, taker_bps| FeeTier {
+            min_volume,
+            maker_bps,
+            taker_bps,
+        };
+        FeeSchedule::new(
+            "alpaca",
+            vec![
+                tier(0.0, 15.0, 25.0),
+                tier(100_000.0, 12.0, 22.0),
+                tier(500_000.0, 10.0, 20.0),
+                tier(1_000_000.0, 8.0, 18.0),
+                tier(10_000_000.0, 5.0, 15.0),
+                tier(25_000_000.0, 2.0, 13.0),
+                tier(50_000_000.0, 2.0, 12.0),
+                tier(100_000_000.0, 0.0, 10.0),
+            ],
+        )
+    }
+
+    pub fn tiers(&self) -> &[FeeTier] {
+        &self.tiers
+    }
+
+    /// Tier for a trailing 30-day volume.
+    pub fn tier(&self, volume: f64) -> FeeTier {
+        self.tiers
+            .iter()
+            .rev()
+            .find(|tier| volume >= tier.min_volume)
+            .or(self.tiers.first())
+            .copied()
+            .unwrap_or(FeeTier {
+                min_volume: 0.0,
+                maker_bps: 0.0,
+                taker_bps: 0.0,
+            })
+    }
+
+    pub fn fee(&self, notional: f64, liquidity: Liquidity, volume: f64) -> f64 {
+        notional.abs() * self.tier(volume).bps(liquidity) / 10_000.0
+    }
+}
+
+/// Fees at one venue, with the tier set by the notional traded there over the trailing 30 days. Use it as the
+/// commission model of a backtest or `SimClient`, or call `apply` on live fills, so fees reach `Fill::commission`
+/// and from there the P&L.
+#[derive(Debug, Clone)]
+pub struct FeeModel {
+    schedule: Arc<FeeSchedule>,
+    /// Notional traded with its time, oldest first.
+    volume: Arc<Mutex<VecDeque<(i64, f64)>>>,
+}
+
+impl FeeModel {
+    pub fn new(schedule: FeeSchedule) -> Self {
+        FeeModel {
+            schedule: Arc::new(schedule),
+            volume: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+
+    pub fn schedule(&self) -> &FeeSchedule {
+        &self.schedule
+    }
+
+    /// Counts volume traded before this model was created, e.g. from the venue's account statement.
+    pub fn seed_volume(&self, notional: f64) {
+        self.record(notional, time::now_nanos());
+    }
+
+    /// Notional traded over the trailing 30 days.
+    pub fn volume(&self) -> f64 {
+        let mut volume = self.volume.lock().unwrap();
+        let start = time::now_nanos() - VOLUME_WINDOW.as_nanos() as i64;
+        while volume.front().is_some_and(|(at, _)| *at < start) {
+            volume.pop_front();
+        }
+        volume.iter().map(|(_, notional)| notional).sum()
+    }
+
+    pub fn tier(&self) -> FeeTier {
+        self.schedule.tier(self.volume())
+    }
+
+    /// Maker when the expected edge of a trade, in basis points, does not cover the taker fee; crossing the
+    /// spread would then cost more than the trade is expected to make.
+    pub fn placement(&self, edge_bps: f64) -> Liquidity {
+        if edge_bps < self.tier().taker_bps {
+            Liquidity::Maker
+        } else {
+            Liquidity::Taker
+        }
+    }
+
+    /// Fee for a fill at the current tier. The fill's notional counts towards later tiers.
+    pub fn charge(&self, notional: f64, liquidity: Liquidity) -> f64 {
+        let fee = self.schedule.fee(notional, liquidity, self.volume());
+        self.record(notional.abs(), time::now_nanos());
+        fee
+    }
+
+    /// Sets the commission of a live fill from the schedule.
+    pub fn apply(&self, fill: &mut Fill, liquidity: Liquidity) {
+        fill.commission = self.charge(fill.quantity * fill.price, liquidity);
+    }
+
+    fn record(&self, notional: f64, at: i64) {
+        self.volume.lock().unwrap().push_back((at, notional));
+    }
+}
+
+/// Simulated fills of market and stop orders pay the taker fee; limit orders are assumed to have rested and
+/// pay the maker fee. Volume is measured in wall-clock time, so a backtest runs at the tier its whole traded
+/// volume reaches.
+impl CommissionModel for FeeModel {
+    fn commission(&self, order: &Order, price: f64) -> f64 {
+        let liquidity = match order.order_type {
+            OrderType::Limit => Liquidity::Maker,
+            _ => Liquidity::Taker,
+        };
+        self.charge(order.quantity * price, liquidity)
+    }
+}
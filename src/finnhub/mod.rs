@@ -0,0 +1,437 @@
+mod stream;
+
+use crate::{
+    datastructures::{
+        client::{FeedType, MarketDataClient, SubscriptionParams},
+        config::Config,
+        corporate_action::CorporateAction,
+        event::{EventBatch, ParseMode},
+        market::{Bar, BarAdjustment, Quote},
+    },
+    http::RestPolicies,
+    time,
+};
+use async_trait::async_trait;
+use futures_util::SinkExt;
+use reqwest::Client as HttpClient;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::HashMap, error::Error, fmt};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{
+    connect_async, tungstenite::protocol::Message, MaybeTlsStream, WebSocketStream,
+};
+
+/// Docs: https://finnhub.io/docs/api
+const REST_URL: &str = "https://finnhub.io/api/v1";
+/// Docs: https://finnhub.io/docs/api/websocket-trades
+const STREAM_URL: &str = "wss://ws.finnhub.io";
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+const NANOS_PER_MILLI: i64 = 1_000_000;
+
+/// Error response of the Finnhub API.
+#[derive(Debug, Clone)]
+pub struct FinnhubError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl fmt::Display for FinnhubError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Finnhub error ({}): {}", self.status, self.message)
+    }
+}
+
+impl Error for FinnhubError {}
+
+/// Docs: https://finnhub.io/docs/api/company-profile2
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompanyProfile {
+    #[serde(default)]
+    pub ticker: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub exchange: String,
+    #[serde(default)]
+    pub country: String,
+    /// Currency the company reports in.
+    #[serde(default)]
+    pub currency: String,
+    #[serde(default, rename = "finnhubIndustry")]
+    pub industry: String,
+    /// IPO date, YYYY-MM-DD.
+    #[serde(default)]
+    pub ipo: String,
+    /// In millions of `currency`.
+    #[serde(default, rename = "marketCapitalization")]
+    pub market_cap: f64,
+    /// In millions.
+    #[serde(default, rename = "shareOutstanding")]
+    pub shares_outstanding: f64,
+    #[serde(default, rename = "weburl")]
+    pub website: String,
+}
+
+/// The day so far of a symbol. Docs: https://finnhub.io/docs/api/quote
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriceQuote {
+    pub symbol: String,
+    /// Last price.
+    pub price: f64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub previous_close: f64,
+    /// Time of the last price.
+    pub timestamp: String,
+}
+
+#[derive(Deserialize)]
+struct RawPriceQuote {
+    c: f64,
+    o: f64,
+    h: f64,
+    l: f64,
+    pc: f64,
+    t: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawSplit {
+    symbol: String,
+    date: String,
+    from_factor: f64,
+    to_factor: f64,
+}
+
+#[derive(Deserialize)]
+struct RawDividend {
+    symbol: String,
+    /// Ex-date.
+    date: String,
+    amount: f64,
+}
+
+/// Candles as parallel arrays, oldest first. `s` is "no_data" when there are none.
+#[derive(Deserialize)]
+struct RawCandles {
+    s: String,
+    #[serde(default)]
+    o: Vec<f64>,
+    #[serde(default)]
+    h: Vec<f64>,
+    #[serde(default)]
+    l: Vec<f64>,
+    #[serde(default)]
+    c: Vec<f64>,
+    #[serde(default)]
+    v: Vec<f64>,
+    #[serde(default)]
+    t: Vec<i64>,
+}
+
+fn format_millis(millis: i64) -> String {
+    time::format_rfc3339(millis * NANOS_PER_MILLI)
+}
+
+/// Crypto and forex symbols carry their exchange, e.g. "BINANCE:BTCUSDT".
+fn is_crypto(symbol: &str) -> bool {
+    symbol.contains(':')
+}
+
+/// Market data and fundamentals from Finnhub. Finnhub does not trade, so pair it with a broker in a
+/// `SplitClient`. Stocks are written by ticker and crypto by Finnhub's exchange-prefixed symbol, e.g.
+/// "BINANCE:BTCUSDT". The free plan covers the trade stream, `get_price`, `get_profile` and `get_metrics`;
+/// `get_latest_quote`, `get_daily_bars` and corporate actions need a paid plan. Applies the config's REST
+/// policies like `AlpacaClient`; the free plan allows 60 calls a minute.
+#[derive(Clone)]
+pub struct FinnhubClient {
+    http_client: HttpClient,
+    rest: RestPolicies,
+    api_key: String,
+}
+
+impl FinnhubClient {
+    /// Uses the config's Finnhub key.
+    pub fn new(config: &Config) -> Result<Self, reqwest::Error> {
+        Ok(FinnhubClient {
+            http_client: config.http.build_client()?,
+            rest: RestPolicies::new(config),
+            api_key: config.finnhub_api_key.clone().unwrap_or_default(),
+        })
+    }
+
+    /// Every call is a read, so all are retried.
+    async fn get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<T, Box<dyn Error>> {
+        let url = format!("{}{}", REST_URL, path);
+        let build = || {
+            Ok(self
+                .http_client
+                .get(&url)
+                .query(query)
+                .header("X-Finnhub-Token", &self.api_key)
+                .build()?)
+        };
+        let response = self.rest.send(&self.http_client, true, build).await?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            let message = serde_json::from_str::<Value>(&body)
+                .ok()
+                .and_then(|value| Some(value.get("error")?.as_str()?.to_string()))
+                .unwrap_or(body);
+            return Err(FinnhubError {
+                status: status.as_u16(),
+                message,
+            }
+            .into());
+        }
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    /// Last price and the day's range so far. Unlike `get_latest_quote`, on the free plan.
+    pub async fn get_price(&self, symbol: &str) -> Result<PriceQuote, Box<dyn Error>> {
+        let raw: RawPriceQuote = self
+            .get("/quote", &[("symbol", symbol.to_string())])
+            .await?;
+        // Unknown symbols give zeros rather than an error.
+        if raw.t == 0 {
+            return Err(format!("No quote for {}", symbol).into());
+        }
+        Ok(PriceQuote {
+            symbol: symbol.to_string(),
+            price: raw.c,
+            open: raw.o,
+            high: raw.h,
+            low: raw.l,
+            previous_close: raw.pc,
+            timestamp: time::format_rfc3339(raw.t * NANOS_PER_SECOND),
+        })
+    }
+
+    /// Docs: https://finnhub.io/docs/api/company-profile2
+    pub async fn get_profile(&self, symbol: &str) -> Result<CompanyProfile, Box<dyn Error>> {
+        let profile: Value = self
+            .get("/stock/profile2", &[("symbol", symbol.to_string())])
+            .await?;
+        // Unknown symbols give an empty object.
+        if profile.as_object().is_none_or(|profile| profile.is_empty()) {
+            return Err(format!("No profile for {}", symbol).into());
+        }
+        Ok(serde_json::from_value(profile)?)
+    }
+
+    /// Latest basic financials by Finnhub's name for them, e.g. "peTTM", "epsTTM" or "52WeekHigh". Metrics
+    /// without a value are left out.
+    /// Docs: https://finnhub.io/docs/api/company-basic-financials
+    pub async fn get_metrics(&self, symbol: &str) -> Result<HashMap<String, f64>, Box<dyn Error>> {
+        #[derive(Deserialize)]
+        struct Financials {
+            #[serde(default)]
+            metric: HashMap<String, Value>,
+        }
+        let financials: Financials = self
+            .get(
+                "/stock/metric",
+                &[
+                    ("symbol", symbol.to_string()),
+                    ("metric", "all".to_string()),
+                ],
+            )
+            .await?;
+        Ok(financials
+            .metric
+            .into_iter()
+            .filter_map(|(name, value)| Some((name, value.as_f64()?)))
+            .collect())
+    }
+
+    /// Splits and cash dividends with an ex-date from `start` to `end` inclusive, as YYYY-MM-DD dates, sorted by
+    /// ex-date.
+    /// Docs: https://finnhub.io/docs/api/stock-splits and https://finnhub.io/docs/api/stock-dividends
+    pub async fn get_corporate_actions(
+        &self,
+        symbols: &[&str],
+        start: &str,
+        end: &str,
+    ) -> Result<Vec<CorporateAction>, Box<dyn Error>> {
+        let mut actions = Vec::new();
+        for symbol in symbols {
+            let query = [
+                ("symbol", symbol.to_string()),
+                ("from", start.to_string()),
+                ("to", end.to_string()),
+            ];
+            let splits: Vec<RawSplit> = self.get("/stock/split", &query).await?;
+            actions.extend(
+                splits
+                    .into_iter()
+                    .filter(|split| split.from_factor > 0.0)
+                    .map(|split| CorporateAction::Split {
+                        symbol: split.symbol,
+                        ex_date: split.date,
+                        ratio: split.to_factor / split.from_factor,
+                    }),
+            );
+            let dividends: Vec<RawDividend> = self.get("/stock/dividend", &query).await?;
+            actions.extend(
+                dividends
+                    .into_iter()
+                    .map(|dividend| CorporateAction::CashDividend {
+                        symbol: dividend.symbol,
+                        ex_date: dividend.date,
+                        amount: dividend.amount,
+                    }),
+            );
+        }
+        actions.sort_by(|a, b| a.ex_date().cmp(b.ex_date()));
+        Ok(actions)
+    }
+
+    /// Docs: https://finnhub.io/docs/api/stock-candles and https://finnhub.io/docs/api/crypto-candles
+    async fn fetch_daily_bars(
+        &self,
+        symbol: &str,
+        start: &str,
+        end: &str,
+    ) -> Result<Vec<Bar>, Box<dyn Error>> {
+        let seconds = |date: &str| {
+            time::parse_rfc3339(&format!("{}T00:00:00Z", date))
+                .map(|nanos| nanos / NANOS_PER_SECOND)
+                .ok_or_else(|| format!("Not a YYYY-MM-DD date: {}", date))
+        };
+        let path = if is_crypto(symbol) {
+            "/crypto/candle"
+        } else {
+            "/stock/candle"
+        };
+        let query = [
+            ("symbol", symbol.to_string()),
+            ("resolution", "D".to_string()),
+            ("from", seconds(start)?.to_string()),
+            ("to", (seconds(end)? + 86_399).to_string()),
+        ];
+        let raw: RawCandles = self.get(path, &query).await?;
+        if raw.s != "ok" {
+            return Ok(vec![]);
+        }
+        Ok((0..raw.t.len())
+            .map(|i| {
+                let field = |values: &[f64]| values.get(i).copied().unwrap_or_default();
+                Bar {
+                    symbol: symbol.to_string(),
+                    open: field(&raw.o),
+                    high: field(&raw.h),
+                    low: field(&raw.l),
+                    close: field(&raw.c),
+                    volume: field(&raw.v),
+                    timestamp: time::format_rfc3339(raw.t[i] * NANOS_PER_SECOND),
+                }
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl MarketDataClient for FinnhubClient {
+    /// Docs: https://finnhub.io/docs/api/last-bid-ask
+    async fn get_latest_quote(&self, symbol: &str) -> Result<Quote, Box<dyn Error>> {
+        #[derive(Deserialize)]
+        struct BidAsk {
+            #[serde(default)]
+            b: f64,
+            #[serde(default)]
+            bv: f64,
+            #[serde(default)]
+            a: f64,
+            #[serde(default)]
+            av: f64,
+            t: i64,
+        }
+        let raw: BidAsk = self
+            .get("/stock/bidask", &[("symbol", symbol.to_string())])
+            .await?;
+        Ok(Quote {
+            symbol: symbol.to_string(),
+            bid_price: raw.b,
+            ask_price: raw.a,
+            bid_size: raw.bv,
+            ask_size: raw.av,
+            timestamp: format_millis(raw.t),
+        })
+    }
+
+    /// Finnhub serves stock bars adjusted for splits, so raw and total return bars are first unadjusted locally
+    /// from its splits. Crypto has no corporate actions, so the adjustment is ignored for it.
+    async fn get_daily_bars(
+        &self,
+        symbol: &str,
+        start: &str,
+        end: &str,
+        adjustment: BarAdjustment,
+    ) -> Result<Vec<Bar>, Box<dyn Error>> {
+        let mut bars = self.fetch_daily_bars(symbol, start, end).await?;
+        if is_crypto(symbol) || adjustment == BarAdjustment::Split {
+            return Ok(bars);
+        }
+        // Splits after `end` restate the bars too.
+        let today =
+            time::date(&time::format_rfc3339(time::now_nanos())).unwrap_or_else(|| end.to_string());
+        let actions = self.get_corporate_actions(&[symbol], start, &today).await?;
+        // Adjusting for the inverse of every split restores the prices as traded.
+        let reversed: Vec<CorporateAction> = actions
+            .iter()
+            .filter_map(|action| match action {
+                CorporateAction::Split {
+                    symbol,
+                    ex_date,
+                    ratio,
+                } => Some(CorporateAction::Split {
+                    symbol: symbol.clone(),
+                    ex_date: ex_date.clone(),
+                    ratio: 1.0 / ratio,
+                }),
+                CorporateAction::CashDividend { .. } => None,
+            })
+            .collect();
+        BarAdjustment::Split.apply(&mut bars, &reversed);
+        adjustment.apply(&mut bars, &actions);
+        Ok(bars)
+    }
+
+    /// Docs: https://finnhub.io/docs/api/websocket-trades
+    /// Trades only, for stocks and crypto on one connection. Finnhub needs explicit symbols rather than "*".
+    async fn subscribe(
+        &self,
+        params: SubscriptionParams,
+    ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, Box<dyn Error>> {
+        if !matches!(params.feed_type, FeedType::Stocks | FeedType::Crypto) {
+            return Err(format!("Finnhub has no {:?} feed", params.feed_type).into());
+        }
+        let messages = stream::subscribe_messages(&params.subscription_request)?;
+
+        let mut url = url::Url::parse(STREAM_URL)?;
+        url.query_pairs_mut().append_pair("token", &self.api_key);
+        let (mut socket, response) = connect_async(url.as_str()).await?;
+        if response.status() != 101 {
+            return Err(
+                format!("Connection failed with status code: {}", response.status()).into(),
+            );
+        }
+        for message in messages {
+            socket.send(Message::Text(message.to_string())).await?;
+        }
+        Ok(socket)
+    }
+
+    fn parse_frame(&self, frame: &str, _mode: ParseMode) -> Result<EventBatch, serde_json::Error> {
+        stream::parse_frame(frame)
+    }
+}
@@ -0,0 +1,80 @@
+use super::format_millis;
+use crate::datastructures::{
+    client::SubscriptionRequest,
+    event::{EventBatch, EventType},
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// One subscribe message per traded symbol, as Finnhub takes them.
+pub(super) fn subscribe_messages(
+    request: &SubscriptionRequest,
+) -> Result<Vec<Value>, &'static str> {
+    let mut symbols: Vec<&str> = vec![];
+    for symbol in &request.trades {
+        if *symbol == "*" {
+            return Err("Finnhub streams need explicit symbols");
+        }
+        if !symbols.contains(symbol) {
+            symbols.push(symbol);
+        }
+    }
+    let others = [
+        &request.quotes,
+        &request.bars,
+        &request.updated_bars,
+        &request.daily_bars,
+        &request.orderbooks,
+        &request.lulds,
+        &request.news,
+    ];
+    if others.iter().any(|channel| !channel.is_empty()) {
+        tracing::warn!("Finnhub streams trades only; ignoring other channels");
+    }
+    Ok(symbols
+        .into_iter()
+        .map(|symbol| json!({ "type": "subscribe", "symbol": symbol }))
+        .collect())
+}
+
+#[derive(Deserialize)]
+struct RawTrade {
+    s: String,
+    p: f64,
+    #[serde(default)]
+    v: f64,
+    t: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum RawMessage {
+    Trade {
+        data: Vec<RawTrade>,
+    },
+    Error {
+        #[serde(default)]
+        msg: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// Maps a frame into events. A trade message batches the trades since the last one. Pings give no events, and
+/// errors, e.g. for an unknown symbol, are logged.
+pub(super) fn parse_frame(frame: &str) -> Result<EventBatch, serde_json::Error> {
+    let mut batch = EventBatch::new();
+    match serde_json::from_str(frame)? {
+        RawMessage::Trade { data } => {
+            batch.extend(data.into_iter().map(|trade| EventType::Trade {
+                symbol: trade.s,
+                price: trade.p,
+                volume: trade.v,
+                timestamp: format_millis(trade.t),
+            }))
+        }
+        RawMessage::Error { msg } => tracing::warn!(message = %msg, "Finnhub stream error"),
+        RawMessage::Other => {}
+    }
+    Ok(batch)
+}
@@ -0,0 +1,147 @@
+use crate::datastructures::{client::TradingClient, order::Order};
+use std::time::{Duration, Instant};
+use tokio::{sync::watch, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, Clone)]
+pub struct FundingConfig {
+    /// How often the account is read while waiting for cash to settle.
+    pub poll_interval: Duration,
+    /// How long deferred orders wait before they are given up. Settlement over a weekend takes up to three days.
+    pub max_wait: Duration,
+}
+
+impl Default for FundingConfig {
+    fn default() -> Self {
+        FundingConfig {
+            poll_interval: Duration::from_secs(60),
+            max_wait: Duration::from_secs(4 * 86_400),
+        }
+    }
+}
+
+/// Orders that were sent once the cash settled.
+#[derive(Debug, Clone, Default)]
+pub struct FundedOutcome {
+    pub submitted: Vec<Order>,
+    /// Orders that failed, with the error message.
+    pub failed: Vec<(Order, String)>,
+}
+
+#[derive(Debug, Clone)]
+pub enum DeferredState {
+    /// Waiting for `required` of settled cash, with `available` as last read.
+    Waiting { required: f64, available: f64 },
+    /// The cash settled and the orders were sent.
+    Sent(FundedOutcome),
+    /// The cash did not settle within `FundingConfig::max_wait`. Nothing was sent.
+    Expired { required: f64, available: f64 },
+    /// Cancelled before the cash settled. Nothing was sent.
+    Cancelled,
+}
+
+impl DeferredState {
+    pub fn is_done(&self) -> bool {
+        !matches!(self, DeferredState::Waiting { .. })
+    }
+}
+
+/// Orders held back until the account has the settled cash to pay for them, e.g. buys of a scheduled DCA or
+/// rebalance run that would otherwise be rejected while sale proceeds settle. The account is polled every
+/// `FundingConfig::poll_interval`; once its settled cash covers `required`, the orders are sent in order. Stops
+/// with the cancellation token it was started with or when dropped, in both cases sending nothing more.
+pub struct DeferredOrders {
+    state: watch::Receiver<DeferredState>,
+    task: JoinHandle<()>,
+}
+
+impl DeferredOrders {
+    pub fn spawn<C>(
+        client: C,
+        orders: Vec<Order>,
+        required: f64,
+        config: FundingConfig,
+        cancel: CancellationToken,
+    ) -> DeferredOrders
+    where
+        C: TradingClient + Send + Sync + 'static,
+    {
+        let (sender, state) = watch::channel(DeferredState::Waiting {
+            required,
+            available: 0.0,
+        });
+        let task = tokio::spawn(async move {
+            let started = Instant::now();
+            let mut available = 0.0;
+            let mut interval = tokio::time::interval(config.poll_interval);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = cancel.cancelled() => {
+                        sender.send_replace(DeferredState::Cancelled);
+                        return;
+                    }
+                }
+                match client.get_account().await.map_err(|e| e.to_string()) {
+                    Ok(account) => available = account.settled_cash(),
+                    Err(e) => tracing::warn!(error = %e, "Failed to read settled cash"),
+                }
+                if available >= required {
+                    break;
+                }
+                if started.elapsed() >= config.max_wait {
+                    tracing::error!(
+                        required,
+                        available,
+                        "Settled cash did not cover deferred orders in time"
+                    );
+                    sender.send_replace(DeferredState::Expired {
+                        required,
+                        available,
+                    });
+                    return;
+                }
+                sender.send_replace(DeferredState::Waiting {
+                    required,
+                    available,
+                });
+            }
+
+            let mut outcome = FundedOutcome::default();
+            for order in orders {
+                match client.create_order(&order).await.map_err(|e| e.to_string()) {
+                    Ok(()) => {
+                        tracing::info!(symbol = %order.symbol, quantity = order.quantity, "Deferred order sent");
+                        outcome.submitted.push(order);
+                    }
+                    Err(e) => {
+                        tracing::error!(symbol = %order.symbol, error = %e, "Deferred order failed");
+                        outcome.failed.push((order, e));
+                    }
+                }
+            }
+            sender.send_replace(DeferredState::Sent(outcome));
+        });
+        DeferredOrders { state, task }
+    }
+
+    pub fn state(&self) -> DeferredState {
+        self.state.borrow().clone()
+    }
+
+    /// Waits until the orders are sent, expire or are cancelled.
+    pub async fn finished(&mut self) -> DeferredState {
+        let _ = self.state.wait_for(DeferredState::is_done).await;
+        self.state.borrow().clone()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+impl Drop for DeferredOrders {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -0,0 +1,680 @@
+mod stream;
+
+use crate::{
+    datastructures::{
+        account::{Account, Position},
+        asset::Asset,
+        client::{FeedType, MarketDataClient, SubscriptionParams, TradingClient},
+        config::Config,
+        event::{EventBatch, ParseMode},
+        market::{Bar, BarAdjustment, Quote},
+        order::{
+            CancelOutcome, Order, OrderResponse, OrderSide, OrderStatus, OrderType, TimeInForce,
+        },
+    },
+    http::{signing, RestPolicies},
+    journal::OrderJournal,
+    store::{self, OrderStore},
+    time,
+};
+use async_trait::async_trait;
+use futures_util::SinkExt;
+use reqwest::{
+    header::{CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE},
+    Client as HttpClient, Method,
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc, Mutex,
+    },
+};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{
+    connect_async, tungstenite::protocol::Message, MaybeTlsStream, WebSocketStream,
+};
+
+/// Docs: https://docs.gemini.com/rest-api/
+const REST_URL: &str = "https://api.gemini.com";
+/// Docs: https://docs.gemini.com/rest-api/#sandbox
+const SANDBOX_REST_URL: &str = "https://api.sandbox.gemini.com";
+/// Docs: https://docs.gemini.com/websocket-api/#market-data-version-2
+const STREAM_URL: &str = "wss://api.gemini.com/v2/marketdata";
+const SANDBOX_STREAM_URL: &str = "wss://api.sandbox.gemini.com/v2/marketdata";
+
+/// Quote currencies, longest first so "btcgusd" is read as BTC/GUSD rather than BTCG/USD.
+const QUOTE_CURRENCIES: [&str; 10] = [
+    "USDT", "USDC", "GUSD", "DAI", "USD", "EUR", "GBP", "SGD", "BTC", "ETH",
+];
+/// Gemini's reason for cancelling an order that is unknown.
+const UNKNOWN_ORDER: &str = "OrderNotFound";
+const NANOS_PER_MILLI: i64 = 1_000_000;
+
+/// Error response of the Gemini REST API.
+#[derive(Debug, Clone)]
+pub struct GeminiError {
+    pub status: u16,
+    /// Gemini's reason, e.g. "InsufficientFunds". Empty if the response had none.
+    pub reason: String,
+    pub message: String,
+}
+
+impl fmt::Display for GeminiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Gemini error {} ({}): {}",
+            self.reason, self.status, self.message
+        )
+    }
+}
+
+impl Error for GeminiError {}
+
+#[derive(Deserialize)]
+struct RawError {
+    #[serde(default)]
+    reason: String,
+    #[serde(default)]
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct RawOrder {
+    order_id: String,
+    #[serde(default)]
+    client_order_id: Option<String>,
+    symbol: String,
+    side: String,
+    #[serde(rename = "type")]
+    order_type: String,
+    timestampms: i64,
+    is_live: bool,
+    is_cancelled: bool,
+    executed_amount: String,
+    original_amount: String,
+    #[serde(default)]
+    avg_execution_price: String,
+    #[serde(default)]
+    price: String,
+    #[serde(default)]
+    stop_price: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawBalance {
+    currency: String,
+    amount: String,
+    available: String,
+}
+
+#[derive(Deserialize)]
+struct RawPrice {
+    pair: String,
+    price: String,
+}
+
+#[derive(Deserialize)]
+struct RawSymbol {
+    symbol: String,
+    base_currency: String,
+    quote_currency: String,
+    status: String,
+}
+
+/// Gemini sends most numbers as strings.
+fn decimal(value: &str) -> f64 {
+    value.parse().unwrap_or(0.0)
+}
+
+fn format_millis(millis: i64) -> String {
+    time::format_rfc3339(millis * NANOS_PER_MILLI)
+}
+
+/// Symbol as Gemini writes it, e.g. "btcusd" for "BTC/USD".
+fn gemini_symbol(symbol: &str) -> String {
+    symbol.replace('/', "").to_lowercase()
+}
+
+/// Pair of a Gemini symbol, e.g. "BTC/USD" for "btcusd", or the symbol in capitals if its quote currency is
+/// not known.
+fn pair(symbol: &str) -> String {
+    let symbol = symbol.to_uppercase();
+    QUOTE_CURRENCIES
+        .iter()
+        .find_map(|quote| {
+            let base = symbol.strip_suffix(quote)?;
+            (!base.is_empty()).then(|| format!("{}/{}", base, quote))
+        })
+        .unwrap_or(symbol)
+}
+
+impl RawOrder {
+    fn into_response(self) -> OrderResponse {
+        let filled_qty = decimal(&self.executed_amount);
+        let qty = decimal(&self.original_amount);
+        let positive = |value: f64| (value > 0.0).then_some(value);
+        let status = match (self.is_live, self.is_cancelled) {
+            (true, _) if filled_qty > 0.0 => OrderStatus::PartiallyFilled,
+            (true, _) => OrderStatus::New,
+            (false, true) => OrderStatus::Canceled,
+            (false, false) => OrderStatus::Filled,
+        };
+        let stop_price = self.stop_price.as_deref().map(decimal).and_then(positive);
+        OrderResponse {
+            id: self.order_id,
+            client_order_id: self.client_order_id.unwrap_or_default(),
+            symbol: pair(&self.symbol),
+            status,
+            created_at: format_millis(self.timestampms),
+            side: match self.side.as_str() {
+                "sell" => OrderSide::Sell,
+                _ => OrderSide::Buy,
+            },
+            order_type: match (self.order_type.as_str(), stop_price) {
+                (_, Some(_)) => OrderType::StopLimit,
+                ("market buy" | "market sell", _) => OrderType::Market,
+                _ => OrderType::Limit,
+            },
+            qty: Some(qty),
+            filled_qty,
+            filled_avg_price: positive(filled_qty)
+                .and_then(|_| positive(decimal(&self.avg_execution_price))),
+            limit_price: positive(decimal(&self.price)),
+            stop_price,
+            metadata: Default::default(),
+        }
+    }
+}
+
+/// Client for the Gemini exchange, trading on its sandbox unless real trading is enabled. Symbols are written
+/// as pairs, as Alpaca writes crypto, e.g. "BTC/USD", and sent to Gemini in lowercase without the slash.
+/// Gemini only takes limit and stop limit orders. Applies the config's REST policies, journal, order store and
+/// pre-trade checks like `AlpacaClient`.
+#[derive(Clone)]
+pub struct GeminiClient {
+    http_client: HttpClient,
+    rest: RestPolicies,
+    rest_url: &'static str,
+    stream_url: &'static str,
+    api_key: String,
+    secret_key: String,
+    quote_asset: String,
+    journal: Option<OrderJournal>,
+    order_store: Option<Arc<dyn OrderStore>>,
+    /// Private calls need a nonce larger than any before it for the same key.
+    last_nonce: Arc<AtomicI64>,
+    stream_state: Arc<Mutex<stream::StreamState>>,
+}
+
+impl GeminiClient {
+    /// Client for the sandbox, or the live exchange if real trading is enabled. Uses the config's Gemini keys.
+    pub fn new(config: &Config) -> Result<Self, reqwest::Error> {
+        let (rest_url, stream_url) = if config.enable_real_trading {
+            (REST_URL, STREAM_URL)
+        } else {
+            (SANDBOX_REST_URL, SANDBOX_STREAM_URL)
+        };
+
+        Ok(GeminiClient {
+            http_client: config.http.build_client()?,
+            rest: RestPolicies::new(config),
+            rest_url,
+            stream_url,
+            api_key: config.gemini_api_key.clone().unwrap_or_default(),
+            secret_key: config.gemini_secret_key.clone().unwrap_or_default(),
+            quote_asset: "USD".to_string(),
+            journal: config.journal.clone(),
+            order_store: config.order_store.clone(),
+            last_nonce: Arc::new(AtomicI64::new(0)),
+            stream_state: Arc::new(Mutex::new(stream::StreamState::default())),
+        })
+    }
+
+    /// Asset the account is valued in and positions are priced against. Defaults to USD.
+    pub fn with_quote_asset(mut self, quote_asset: impl Into<String>) -> Self {
+        self.quote_asset = quote_asset.into();
+        self
+    }
+
+    /// Milliseconds since the epoch, or one more than the last nonce if the clock has not moved on.
+    fn nonce(&self) -> i64 {
+        let now = time::now_nanos() / NANOS_PER_MILLI;
+        let last = self
+            .last_nonce
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| {
+                Some(now.max(last + 1))
+            })
+            .unwrap();
+        now.max(last + 1)
+    }
+
+    /// Private calls are POSTed with the request as a signed payload, signed afresh with a new nonce for every
+    /// attempt. Only `idempotent` calls are retried.
+    async fn request<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        payload: Option<Value>,
+        idempotent: bool,
+    ) -> Result<T, Box<dyn Error>> {
+        let url = format!("{}{}", self.rest_url, path);
+        let build = || {
+            let request = match &payload {
+                Some(payload) => {
+                    let mut payload = payload.clone();
+                    payload["request"] = json!(path);
+                    payload["nonce"] = json!(self.nonce().to_string());
+                    let payload = signing::base64_encode(payload.to_string().as_bytes());
+                    let signature =
+                        signing::hmac_sha384(self.secret_key.as_bytes(), payload.as_bytes());
+                    self.http_client
+                        .request(Method::POST, &url)
+                        .header(CONTENT_TYPE, "text/plain")
+                        .header(CONTENT_LENGTH, "0")
+                        .header(CACHE_CONTROL, "no-cache")
+                        .header("X-GEMINI-APIKEY", &self.api_key)
+                        .header("X-GEMINI-PAYLOAD", payload)
+                        .header("X-GEMINI-SIGNATURE", signing::hex(&signature))
+                }
+                None => self.http_client.request(Method::GET, &url),
+            };
+            Ok(request.build()?)
+        };
+        let response = self.rest.send(&self.http_client, idempotent, build).await?;
+
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            let error: RawError = serde_json::from_str(&body).unwrap_or(RawError {
+                reason: String::new(),
+                message: body,
+            });
+            return Err(GeminiError {
+                status: status.as_u16(),
+                reason: error.reason,
+                message: error.message,
+            }
+            .into());
+        }
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    fn to_response(&self, raw: RawOrder) -> OrderResponse {
+        let mut order = raw.into_response();
+        if let Some(journal) = &self.journal {
+            journal.annotate(&mut order);
+        }
+        order
+    }
+
+    /// Docs: https://docs.gemini.com/rest-api/#order-status
+    async fn order_status(&self, payload: Value) -> Result<OrderResponse, Box<dyn Error>> {
+        // Looking up by client order id gives every order with it.
+        let raw: Value = self
+            .request("/v1/order/status", Some(payload), true)
+            .await?;
+        let raw = match raw {
+            Value::Array(orders) => orders.into_iter().next().ok_or("Unknown order")?,
+            order => order,
+        };
+        let order = self.to_response(serde_json::from_value(raw)?);
+        store::record_logged(&self.order_store, &order).await;
+        Ok(order)
+    }
+
+    /// Docs: https://docs.gemini.com/rest-api/#get-available-balances
+    async fn balances(&self) -> Result<Vec<RawBalance>, Box<dyn Error>> {
+        self.request("/v1/balances", Some(json!({})), true).await
+    }
+
+    /// Last price of every symbol quoted in the quote asset, by base asset.
+    /// Docs: https://docs.gemini.com/rest-api/#price-feed
+    async fn prices(&self) -> Result<HashMap<String, f64>, Box<dyn Error>> {
+        let prices: Vec<RawPrice> = self.request("/v1/pricefeed", None, true).await?;
+        let suffix = format!("/{}", self.quote_asset);
+        Ok(prices
+            .into_iter()
+            .filter_map(|price| {
+                let pair = pair(&price.pair);
+                let base = pair.strip_suffix(&suffix)?;
+                Some((base.to_string(), decimal(&price.price)))
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl TradingClient for GeminiClient {
+    /// Docs: https://docs.gemini.com/rest-api/#new-order
+    /// Day orders are sent good til cancelled, as crypto trades around the clock. Market, stop and trailing
+    /// stop orders are not supported, nor are opening or closing auction orders; send a marketable limit order
+    /// that is immediate or cancel instead of a market order.
+    async fn create_order(&self, order: &Order) -> Result<(), Box<dyn Error>> {
+        let order_type = match order.order_type {
+            OrderType::Limit => "exchange limit",
+            OrderType::StopLimit => "exchange stop limit",
+            OrderType::Market | OrderType::Stop | OrderType::TrailingStop => {
+                return Err("Gemini only takes limit and stop limit orders".into())
+            }
+        };
+        let options: Vec<&str> = match order.time_in_force {
+            TimeInForce::Day | TimeInForce::Gtc => vec![],
+            TimeInForce::Ioc => vec!["immediate-or-cancel"],
+            TimeInForce::Fok => vec!["fill-or-kill"],
+            TimeInForce::Opg | TimeInForce::Cls => {
+                return Err("Gemini has no opening or closing auction".into())
+            }
+        };
+        let mut payload = json!({
+            "symbol": gemini_symbol(&order.symbol),
+            "amount": order.quantity.to_string(),
+            "price": order.limit_price.ok_or("Gemini orders need a limit price")?.to_string(),
+            "side": match order.side {
+                OrderSide::Buy => "buy",
+                OrderSide::Sell => "sell",
+            },
+            "type": order_type,
+            "options": options,
+        });
+        if let Some(stop_price) = order.stop_price {
+            payload["stop_price"] = json!(stop_price.to_string());
+        }
+        if let Some(client_order_id) = &order.client_order_id {
+            payload["client_order_id"] = json!(client_order_id);
+        }
+
+        tracing::debug!(?order, "Submitting order");
+        if let Some(journal) = &self.journal {
+            journal.record(order)?;
+        }
+        let raw: RawOrder = {
+            let result = self
+                .request(
+                    "/v1/order/new",
+                    Some(payload),
+                    order.client_order_id.is_some(),
+                )
+                .await;
+            #[cfg(feature = "metrics")]
+            {
+                let metrics = crate::metrics::registry();
+                metrics.orders_submitted.inc();
+                if result.is_err() {
+                    metrics.orders_rejected.inc();
+                }
+            }
+            result?
+        };
+
+        if let Some(store) = &self.order_store {
+            let mut accepted = self.to_response(raw);
+            accepted.metadata = order.metadata.clone();
+            if let Err(e) = store.record_submission(&accepted).await {
+                tracing::error!(error = %e, "Failed to store submitted order");
+            }
+        }
+        Ok(())
+    }
+
+    /// Docs: https://docs.gemini.com/rest-api/#get-active-orders
+    async fn get_open_orders(&self) -> Result<Vec<OrderResponse>, Box<dyn Error>> {
+        let raw: Vec<RawOrder> = self.request("/v1/orders", Some(json!({})), true).await?;
+        let mut orders: Vec<OrderResponse> =
+            raw.into_iter().map(|raw| self.to_response(raw)).collect();
+        orders.sort_by_key(|order| time::parse_rfc3339(&order.created_at));
+        for order in &orders {
+            store::record_logged(&self.order_store, order).await;
+        }
+        Ok(orders)
+    }
+
+    async fn get_order(&self, order_id: &str) -> Result<OrderResponse, Box<dyn Error>> {
+        let id: u64 = order_id
+            .parse()
+            .map_err(|_| format!("Not a Gemini order id: {}", order_id))?;
+        self.order_status(json!({ "order_id": id })).await
+    }
+
+    async fn get_order_by_client_id(
+        &self,
+        client_order_id: &str,
+    ) -> Result<OrderResponse, Box<dyn Error>> {
+        self.order_status(json!({ "client_order_id": client_order_id }))
+            .await
+    }
+
+    /// Docs: https://docs.gemini.com/rest-api/#cancel-order
+    /// Gemini cancels synchronously, so the response already shows the final state.
+    async fn cancel_order(&self, order_id: &str) -> Result<CancelOutcome, Box<dyn Error>> {
+        let id: u64 = order_id
+            .parse()
+            .map_err(|_| format!("Not a Gemini order id: {}", order_id))?;
+        let raw = match self
+            .request::<RawOrder>("/v1/order/cancel", Some(json!({ "order_id": id })), true)
+            .await
+        {
+            Ok(raw) => Some(raw),
+            Err(e) => {
+                let unknown = e
+                    .downcast_ref::<GeminiError>()
+                    .is_some_and(|e| e.reason == UNKNOWN_ORDER);
+                // Usually already filled or cancelled. Its actual state decides the outcome.
+                if !unknown {
+                    return Err(e);
+                }
+                None
+            }
+        };
+        let Some(raw) = raw else {
+            return Ok(CancelOutcome::from_order(self.get_order(order_id).await?));
+        };
+        let order = self.to_response(raw);
+        store::record_logged(&self.order_store, &order).await;
+        Ok(CancelOutcome::from_order(order))
+    }
+
+    /// Docs: https://docs.gemini.com/rest-api/#symbol-details
+    async fn get_asset(&self, symbol: &str) -> Result<Asset, Box<dyn Error>> {
+        let path = format!("/v1/symbols/details/{}", gemini_symbol(symbol));
+        let raw: RawSymbol = self.request(&path, None, true).await?;
+        // Closed symbols, and those taking only cancels, cannot be traded.
+        let trading = !matches!(raw.status.as_str(), "closed" | "cancel_only");
+        Ok(Asset {
+            symbol: format!("{}/{}", raw.base_currency, raw.quote_currency),
+            exchange: "GEMINI".to_string(),
+            asset_class: "crypto".to_string(),
+            status: if trading { "active" } else { "inactive" }.to_string(),
+            tradable: trading,
+            shortable: false,
+            fractionable: true,
+            attributes: vec![raw.symbol.to_lowercase()],
+        })
+    }
+
+    /// Docs: https://docs.gemini.com/rest-api/#symbols
+    /// Every listed symbol. Gemini lists symbols without their status, so all are reported tradable; see
+    /// `get_asset` for a symbol's status.
+    async fn list_assets(&self) -> Result<Vec<Asset>, Box<dyn Error>> {
+        let symbols: Vec<String> = self.request("/v1/symbols", None, true).await?;
+        Ok(symbols
+            .into_iter()
+            .map(|symbol| Asset {
+                symbol: pair(&symbol),
+                exchange: "GEMINI".to_string(),
+                asset_class: "crypto".to_string(),
+                status: "active".to_string(),
+                tradable: true,
+                shortable: false,
+                fractionable: true,
+                attributes: vec![symbol],
+            })
+            .collect())
+    }
+
+    /// Valued in the quote asset at the last price of each balance's pair against it. Balances without such a
+    /// pair are left out of equity. Gemini does not report the previous close or an account id, so
+    /// `last_equity` is equity and the id is empty.
+    async fn get_account(&self) -> Result<Account, Box<dyn Error>> {
+        let balances = self.balances().await?;
+        let prices = self.prices().await?;
+        let mut cash = 0.0;
+        let mut free_cash = 0.0;
+        let mut long_market_value = 0.0;
+        for balance in &balances {
+            let total = decimal(&balance.amount);
+            if balance.currency == self.quote_asset {
+                cash = total;
+                free_cash = decimal(&balance.available);
+            } else if let Some(price) = prices.get(&balance.currency) {
+                long_market_value += total * price;
+            }
+        }
+        let equity = cash + long_market_value;
+        Ok(Account {
+            id: String::new(),
+            status: "ACTIVE".to_string(),
+            currency: self.quote_asset.clone(),
+            cash,
+            equity,
+            last_equity: equity,
+            buying_power: free_cash,
+            long_market_value,
+            short_market_value: 0.0,
+            non_marginable_buying_power: Some(free_cash),
+        })
+    }
+
+    /// Every balance other than the quote asset that has a pair against it, as a position in that pair. Gemini
+    /// does not track cost basis, so the entry price and unrealized P&L are zero.
+    async fn get_positions(&self) -> Result<Vec<Position>, Box<dyn Error>> {
+        let balances = self.balances().await?;
+        let prices = self.prices().await?;
+        Ok(balances
+            .into_iter()
+            .filter(|balance| balance.currency != self.quote_asset)
+            .filter_map(|balance| {
+                let qty = decimal(&balance.amount);
+                let price = *prices.get(&balance.currency)?;
+                (qty > 0.0).then(|| Position {
+                    symbol: format!("{}/{}", balance.currency, self.quote_asset),
+                    exchange: "GEMINI".to_string(),
+                    asset_class: "crypto".to_string(),
+                    qty,
+                    avg_entry_price: 0.0,
+                    market_value: qty * price,
+                    current_price: Some(price),
+                    unrealized_pl: 0.0,
+                })
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl MarketDataClient for GeminiClient {
+    /// Docs: https://docs.gemini.com/rest-api/#current-order-book
+    /// The top level of the book. Gemini does not timestamp it, so the quote is stamped when it arrives.
+    async fn get_latest_quote(&self, symbol: &str) -> Result<Quote, Box<dyn Error>> {
+        #[derive(Deserialize)]
+        struct Level {
+            price: String,
+            amount: String,
+        }
+        #[derive(Deserialize)]
+        struct Book {
+            bids: Vec<Level>,
+            asks: Vec<Level>,
+        }
+        let path = format!(
+            "/v1/book/{}?limit_bids=1&limit_asks=1",
+            gemini_symbol(symbol)
+        );
+        let book: Book = self.request(&path, None, true).await?;
+        let level = |levels: &[Level]| {
+            levels.first().map_or((0.0, 0.0), |level| {
+                (decimal(&level.price), decimal(&level.amount))
+            })
+        };
+        let ((bid_price, bid_size), (ask_price, ask_size)) = (level(&book.bids), level(&book.asks));
+        Ok(Quote {
+            symbol: symbol.to_string(),
+            bid_price,
+            ask_price,
+            bid_size,
+            ask_size,
+            timestamp: time::format_rfc3339(time::now_nanos()),
+        })
+    }
+
+    /// Docs: https://docs.gemini.com/rest-api/#candles
+    /// Days run from midnight UTC. Gemini serves only recent candles, whatever the start. Crypto has no
+    /// corporate actions, so the adjustment is ignored.
+    async fn get_daily_bars(
+        &self,
+        symbol: &str,
+        start: &str,
+        end: &str,
+        _adjustment: BarAdjustment,
+    ) -> Result<Vec<Bar>, Box<dyn Error>> {
+        let millis = |date: &str| {
+            time::parse_rfc3339(&format!("{}T00:00:00Z", date))
+                .map(|nanos| nanos / NANOS_PER_MILLI)
+                .ok_or_else(|| format!("Not a YYYY-MM-DD date: {}", date))
+        };
+        let range = millis(start)?..=millis(end)?;
+
+        let path = format!("/v2/candles/{}/1day", gemini_symbol(symbol));
+        // [open time, open, high, low, close, volume], newest first.
+        let candles: Vec<Vec<f64>> = self.request(&path, None, true).await?;
+        let mut bars: Vec<Bar> = candles
+            .into_iter()
+            .filter(|candle| candle.len() >= 6 && range.contains(&(candle[0] as i64)))
+            .map(|candle| Bar {
+                symbol: symbol.to_string(),
+                open: candle[1],
+                high: candle[2],
+                low: candle[3],
+                close: candle[4],
+                volume: candle[5],
+                timestamp: format_millis(candle[0] as i64),
+            })
+            .collect();
+        bars.reverse();
+        Ok(bars)
+    }
+
+    /// Docs: https://docs.gemini.com/websocket-api/#market-data-version-2
+    /// Trades and order books (from the level 2 channel), and minute and daily bars are streamed; Gemini has no
+    /// quote stream, limit up/limit down bands or news, and needs explicit symbols rather than "*". Market data
+    /// is public, so the stream is not authenticated.
+    async fn subscribe(
+        &self,
+        params: SubscriptionParams,
+    ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, Box<dyn Error>> {
+        if matches!(params.feed_type, FeedType::News | FeedType::Options) {
+            return Err(format!("Gemini has no {:?} feed", params.feed_type).into());
+        }
+        let request = stream::subscribe_message(&params.subscription_request, gemini_symbol)?;
+
+        let (mut socket, response) = connect_async(self.stream_url).await?;
+        if response.status() != 101 {
+            return Err(
+                format!("Connection failed with status code: {}", response.status()).into(),
+            );
+        }
+        // A new connection starts with new snapshots.
+        *self.stream_state.lock().unwrap() = stream::StreamState::default();
+        socket.send(Message::Text(request.to_string())).await?;
+        Ok(socket)
+    }
+
+    fn parse_frame(&self, frame: &str, _mode: ParseMode) -> Result<EventBatch, serde_json::Error> {
+        stream::parse_frame(frame, &mut self.stream_state.lock().unwrap(), pair)
+    }
+}
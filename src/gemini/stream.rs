@@ -0,0 +1,239 @@
+use super::{decimal, format_millis};
+use crate::datastructures::{
+    client::SubscriptionRequest,
+    event::{EventBatch, EventType},
+};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+
+/// What the stream has seen since subscribing. Gemini flags neither book snapshots nor closed candles.
+#[derive(Default)]
+pub(super) struct StreamState {
+    /// Symbols whose book snapshot arrived.
+    books: HashSet<String>,
+    /// Open time and open, high, low, close and volume of the latest candle, by symbol and channel.
+    candles: HashMap<(String, String), (i64, [f64; 5])>,
+}
+
+/// Subscribe message for the market data v2 channels the request needs.
+pub(super) fn subscribe_message(
+    request: &SubscriptionRequest,
+    gemini_symbol: impl Fn(&str) -> String,
+) -> Result<Value, &'static str> {
+    let symbols = |channels: &[&Vec<&'static str>]| -> Result<Vec<String>, &'static str> {
+        let mut symbols = vec![];
+        for symbol in channels.iter().copied().flatten() {
+            if *symbol == "*" {
+                return Err("Gemini streams need explicit symbols");
+            }
+            let symbol = gemini_symbol(symbol).to_uppercase();
+            if !symbols.contains(&symbol) {
+                symbols.push(symbol);
+            }
+        }
+        Ok(symbols)
+    };
+    let channels = [
+        ("l2", symbols(&[&request.trades, &request.orderbooks])?),
+        (
+            "candles_1m",
+            symbols(&[&request.bars, &request.updated_bars])?,
+        ),
+        ("candles_1d", symbols(&[&request.daily_bars])?),
+    ];
+    if !request.quotes.is_empty() {
+        tracing::warn!("Gemini has no quote stream; subscribe to order books instead");
+    }
+    if !request.lulds.is_empty() || !request.news.is_empty() {
+        tracing::warn!("Gemini has no limit up/limit down or news streams; ignoring them");
+    }
+    let subscriptions: Vec<Value> = channels
+        .into_iter()
+        .filter(|(_, symbols)| !symbols.is_empty())
+        .map(|(name, symbols)| json!({ "name": name, "symbols": symbols }))
+        .collect();
+    Ok(json!({ "type": "subscribe", "subscriptions": subscriptions }))
+}
+
+/// Maps a market data v2 message into events. The level 2 channel gives trades and order books, so
+/// subscribing to either streams both; the first book update of a symbol is the whole book. Minute candles
+/// give an updated bar on every change and a bar once the next one opens; daily candles give a daily bar once
+/// the next one opens. The candle history sent after subscribing is not replayed. Book updates carry no
+/// exchange time and are stamped on arrival.
+pub(super) fn parse_frame(
+    frame: &str,
+    state: &mut StreamState,
+    pair: impl Fn(&str) -> String,
+) -> Result<EventBatch, serde_json::Error> {
+    let mut batch = EventBatch::new();
+    let value: Value = serde_json::from_str(frame)?;
+    let string = |value: &Value, name: &str| {
+        value
+            .get(name)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+    };
+    let kind = string(&value, "type");
+    let raw_symbol = string(&value, "symbol");
+    let trade = |trade: &Value| EventType::Trade {
+        symbol: pair(&raw_symbol),
+        price: decimal(&string(trade, "price")),
+        volume: decimal(&string(trade, "quantity")),
+        timestamp: format_millis(
+            trade
+                .get("timestamp")
+                .and_then(Value::as_i64)
+                .unwrap_or_default(),
+        ),
+    };
+
+    match kind.as_str() {
+        "trade" => batch.push(trade(&value)),
+        "l2_updates" => {
+            // The snapshot comes with recent trades, which are history.
+            let reset = state.books.insert(raw_symbol.clone());
+            let (mut bids, mut asks) = (vec![], vec![]);
+            for change in value
+                .get("changes")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+            {
+                let field = |i: usize| change.get(i).and_then(Value::as_str).unwrap_or_default();
+                let level = (decimal(field(1)), decimal(field(2)));
+                match field(0) {
+                    "buy" => bids.push(level),
+                    _ => asks.push(level),
+                }
+            }
+            batch.push(EventType::OrderBook {
+                symbol: pair(&raw_symbol),
+                bids,
+                asks,
+                reset,
+                timestamp: crate::time::format_rfc3339(crate::time::now_nanos()),
+            });
+        }
+        "candles_1m_updates" | "candles_1d_updates" => {
+            let daily = kind == "candles_1d_updates";
+            // [open time, open, high, low, close, volume], newest first.
+            let mut candles: Vec<(i64, [f64; 5])> = value
+                .get("changes")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(|candle| {
+                    let field =
+                        |i: usize| candle.get(i).and_then(Value::as_f64).unwrap_or_default();
+                    Some((
+                        candle.get(0)?.as_i64()?,
+                        [field(1), field(2), field(3), field(4), field(5)],
+                    ))
+                })
+                .collect();
+            candles.sort_by_key(|(time, _)| *time);
+            let key = (raw_symbol.clone(), kind.clone());
+            let history = !state.candles.contains_key(&key);
+            for (time, bar) in candles {
+                let previous = state.candles.insert(key.clone(), (time, bar));
+                if history {
+                    continue;
+                }
+                let symbol = pair(&raw_symbol);
+                if let Some((opened, [open, high, low, close, volume])) =
+                    previous.filter(|(opened, _)| *opened < time)
+                {
+                    let timestamp = format_millis(opened);
+                    batch.push(if daily {
+                        EventType::DailyBar {
+                            symbol: symbol.clone(),
+                            open,
+                            high,
+                            low,
+                            close,
+                            volume,
+                            timestamp,
+                        }
+                    } else {
+                        EventType::Bar {
+                            symbol: symbol.clone(),
+                            open,
+                            high,
+                            low,
+                            close,
+                            volume,
+                            timestamp,
+                        }
+                    });
+                }
+                if !daily {
+                    let [open, high, low, close, volume] = bar;
+                    batch.push(EventType::UpdatedBar {
+                        symbol,
+                        open,
+                        high,
+                        low,
+                        close,
+                        volume,
+                        timestamp: format_millis(time),
+                    });
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(batch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(frame: &str, state: &mut StreamState) -> EventBatch {
+        parse_frame(frame, state, |symbol| symbol.to_string()).unwrap()
+    }
+
+    #[test]
+    fn keeps_fractional_trade_quantity() {
+        let batch = parse(
+            r#"{"type":"trade","symbol":"BTCUSD","event_id":1,"timestamp":1704205800000,"price":"42000.10","quantity":"0.0015","side":"buy"}"#,
+            &mut StreamState::default(),
+        );
+        match &batch[..] {
+            [EventType::Trade { price, volume, .. }] => {
+                assert_eq!(*price, 42000.1);
+                assert_eq!(*volume, 0.0015);
+            }
+            other => panic!("Expected one trade, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn keeps_fractional_candle_volume() {
+        let mut state = StreamState::default();
+        let candles = |changes: &str| {
+            format!(
+                r#"{{"type":"candles_1m_updates","symbol":"BTCUSD","changes":[{}]}}"#,
+                changes
+            )
+        };
+        // The first message is history and only sets the open candle.
+        assert!(parse(&candles("[1704205800000,1,2,0.5,1.5,0.75]"), &mut state).is_empty());
+
+        let batch = parse(
+            &candles("[1704205860000,1.5,1.5,1.5,1.5,0.125]"),
+            &mut state,
+        );
+        let bar = batch.iter().find_map(|event| match event {
+            EventType::Bar { volume, .. } => Some(*volume),
+            _ => None,
+        });
+        let updated = batch.iter().find_map(|event| match event {
+            EventType::UpdatedBar { volume, .. } => Some(*volume),
+            _ => None,
+        });
+        assert_eq!(bar, Some(0.75));
+        assert_eq!(updated, Some(0.125));
+    }
+}
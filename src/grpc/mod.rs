@@ -0,0 +1,555 @@
+mod proto;
+
+use crate::{
+    datastructures::{client::TradingClient, event::EventType},
+    http::signing,
+    stream::EventBus,
+};
+use bytes::Bytes;
+use futures_util::future::poll_fn;
+use h2::{
+    server::{self, SendResponse},
+    RecvStream, SendStream,
+};
+use http::{
+    header::{AUTHORIZATION, CONTENT_TYPE},
+    HeaderMap, HeaderValue, Request, Response,
+};
+use std::{error::Error, net::SocketAddr, sync::Arc};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::broadcast,
+    task::{JoinHandle, JoinSet},
+};
+
+/// Largest request message accepted.
+const MAX_MESSAGE: usize = 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct GrpcConfig {
+    /// Defaults to localhost only. Connections are plaintext, so put a TLS proxy in front before exposing it further.
+    pub addr: SocketAddr,
+    /// When set, every call must carry `authorization: Bearer <key>` metadata.
+    pub api_key: Option<String>,
+    /// Serves `CreateOrder` and `CancelOrder`, which are refused otherwise. Needs `api_key`.
+    pub allow_trading: bool,
+}
+
+impl Default for GrpcConfig {
+    fn default() -> Self {
+        GrpcConfig {
+            addr: SocketAddr::from(([127, 0, 0, 1], 50051)),
+            api_key: None,
+            allow_trading: false,
+        }
+    }
+}
+
+/// Status a call ends with, sent in the trailers.
+struct Status {
+    code: u32,
+    message: String,
+}
+
+impl Status {
+    const OK: u32 = 0;
+    const CANCELLED: u32 = 1;
+    const UNKNOWN: u32 = 2;
+    const INVALID_ARGUMENT: u32 = 3;
+    const PERMISSION_DENIED: u32 = 7;
+    const RESOURCE_EXHAUSTED: u32 = 8;
+    const UNIMPLEMENTED: u32 = 12;
+    const UNAVAILABLE: u32 = 14;
+    const UNAUTHENTICATED: u32 = 16;
+
+    fn new(code: u32, message: impl Into<String>) -> Self {
+        Status {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// gRPC service for trading through a client from other languages. The service is defined in
+/// `src/grpc/trading.proto`; generate stubs for it with the usual protobuf tooling. Methods:
+/// - `CreateOrder`, with `allow_trading`: places an order and returns it as the broker reports it
+/// - `CancelOrder`, with `allow_trading`: cancels an order and returns how it ended
+/// - `GetPositions`, `GetAccount`: fetched from the client on each call
+/// - `StreamMarketData`: market data events from the bus for the requested symbols, until the caller cancels
+///
+/// Speaks HTTP/2 without TLS or compression. Stops, along with every call in progress, when dropped.
+pub struct GrpcServer {
+    addr: SocketAddr,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl GrpcServer {
+    /// Binds `config.addr` and starts serving. Market data is taken from a new subscription to `bus`.
+    pub async fn serve<C>(
+        client: C,
+        bus: &EventBus,
+        config: GrpcConfig,
+    ) -> Result<GrpcServer, Box<dyn Error>>
+    where
+        C: TradingClient + Clone + Send + Sync + 'static,
+    {
+        if config.allow_trading && config.api_key.is_none() {
+            return Err("Trading calls need an API key".into());
+        }
+        let listener = TcpListener::bind(config.addr).await?;
+        let addr = listener.local_addr()?;
+        // Each streaming call gets its own receiver; a slow one skips ahead rather than holding up the others.
+        // The forwarder owns the only sender, so streaming calls see the bus end.
+        let (live, template) = broadcast::channel(1024);
+
+        let mut subscriber = bus.subscribe();
+        let forwarder = tokio::spawn(async move {
+            while let Some(batch) = subscriber.recv_batch().await {
+                // Only fails when no call is streaming.
+                let _ = live.send(batch);
+            }
+        });
+        let config = Arc::new(config);
+        let acceptor = tokio::spawn(async move {
+            // Owned here so that aborting the acceptor ends every connection too.
+            let mut connections = JoinSet::new();
+            loop {
+                let socket = match listener.accept().await {
+                    Ok((socket, _)) => socket,
+                    Err(e) => {
+                        tracing::warn!(error = %e, "gRPC server failed to accept connection");
+                        continue;
+                    }
+                };
+                while connections.try_join_next().is_some() {}
+                let connection = serve_connection(
+                    socket,
+                    client.clone(),
+                    template.resubscribe(),
+                    config.clone(),
+                );
+                connections.spawn(async move {
+                    if let Err(e) = connection.await {
+                        tracing::debug!(error = %e, "gRPC connection closed");
+                    }
+                });
+            }
+        });
+
+        tracing::info!(%addr, "gRPC server listening");
+        Ok(GrpcServer {
+            addr,
+            tasks: vec![forwarder, acceptor],
+        })
+    }
+
+    /// Address actually bound, e.g. when the configured port was 0.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for GrpcServer {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+async fn serve_connection<C>(
+    socket: TcpStream,
+    client: C,
+    live: broadcast::Receiver<Arc<[EventType]>>,
+    config: Arc<GrpcConfig>,
+) -> Result<(), h2::Error>
+where
+    C: TradingClient + Clone + Send + Sync + 'static,
+{
+    let mut connection = server::handshake(socket).await?;
+    let mut calls = JoinSet::new();
+    // Accepting also drives the connection, so this keeps going while calls are in progress.
+    while let Some(accepted) = connection.accept().await {
+        let (request, respond) = accepted?;
+        while calls.try_join_next().is_some() {}
+        calls.spawn(call(
+            request,
+            respond,
+            client.clone(),
+            live.resubscribe(),
+            config.clone(),
+        ));
+    }
+    Ok(())
+}
+
+async fn call<C: TradingClient>(
+    request: Request<RecvStream>,
+    mut respond: SendResponse<Bytes>,
+    client: C,
+    live: broadcast::Receiver<Arc<[EventType]>>,
+    config: Arc<GrpcConfig>,
+) {
+    let head = Response::builder()
+        .header(CONTENT_TYPE, "application/grpc")
+        .body(())
+        .unwrap();
+    let mut stream = match respond.send_response(head, false) {
+        Ok(stream) => stream,
+        Err(e) => {
+            tracing::debug!(error = %e, "gRPC call closed");
+            return;
+        }
+    };
+
+    let (head, body) = request.into_parts();
+    let result = match check(&head.headers, config.api_key.as_deref()) {
+        Ok(()) => {
+            dispatch(
+                head.uri.path(),
+                body,
+                &mut stream,
+                &client,
+                config.allow_trading,
+                live,
+            )
+            .await
+        }
+        Err(status) => Err(status),
+    };
+    let status = result.err().unwrap_or(Status::new(Status::OK, ""));
+    if status.code != Status::OK {
+        tracing::debug!(method = head.uri.path(), code = status.code, message = %status.message, "gRPC call failed");
+    }
+
+    let mut trailers = HeaderMap::new();
+    trailers.insert("grpc-status", HeaderValue::from(status.code));
+    if let Ok(message) = HeaderValue::from_str(&percent_encode(&status.message)) {
+        if !message.is_empty() {
+            trailers.insert("grpc-message", message);
+        }
+    }
+    if let Err(e) = stream.send_trailers(trailers) {
+        tracing::debug!(error = %e, "gRPC call closed");
+    }
+}
+
+/// Rejects calls that are not gRPC or lack the API key.
+fn check(headers: &HeaderMap, api_key: Option<&str>) -> Result<(), Status> {
+    let content_type = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if !content_type.starts_with("application/grpc") {
+        return Err(Status::new(
+            Status::INVALID_ARGUMENT,
+            "Expected application/grpc content",
+        ));
+    }
+    let Some(api_key) = api_key else {
+        return Ok(());
+    };
+    let presented = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    if !signing::constant_time_eq(presented.as_bytes(), api_key.as_bytes()) {
+        return Err(Status::new(Status::UNAUTHENTICATED, "Invalid API key"));
+    }
+    Ok(())
+}
+
+async fn dispatch<C: TradingClient>(
+    path: &str,
+    body: RecvStream,
+    stream: &mut SendStream<Bytes>,
+    client: &C,
+    allow_trading: bool,
+    live: broadcast::Receiver<Arc<[EventType]>>,
+) -> Result<(), Status> {
+    let unimplemented = || Status::new(Status::UNIMPLEMENTED, format!("Unknown method {}", path));
+    let method = path
+        .strip_prefix("/trading.Trading/")
+        .ok_or_else(unimplemented)?;
+    if ![
+        "CreateOrder",
+        "CancelOrder",
+        "GetPositions",
+        "GetAccount",
+        "StreamMarketData",
+    ]
+    .contains(&method)
+    {
+        return Err(unimplemented());
+    }
+    if !allow_trading && ["CreateOrder", "CancelOrder"].contains(&method) {
+        return Err(Status::new(
+            Status::PERMISSION_DENIED,
+            "Trading calls are disabled",
+        ));
+    }
+
+    let request = read_message(body).await?;
+    let invalid = |message: String| Status::new(Status::INVALID_ARGUMENT, message);
+    let broker = |e: Box<dyn Error>| Status::new(Status::UNKNOWN, e.to_string());
+    let reply = match method {
+        "CreateOrder" => {
+            let order = proto::decode_create_order(&request).map_err(invalid)?;
+            client.create_order(&order).await.map_err(broker)?;
+            // Always set when decoded, so the placed order can be returned.
+            let client_order_id = order.client_order_id.unwrap_or_default();
+            let placed = client
+                .get_order_by_client_id(&client_order_id)
+                .await
+                .map_err(|e| {
+                    Status::new(
+                        Status::UNKNOWN,
+                        format!(
+                            "Order {} was placed but could not be fetched: {}",
+                            client_order_id, e
+                        ),
+                    )
+                })?;
+            proto::encode_order(&placed)
+        }
+        "CancelOrder" => {
+            let order_id = proto::decode_cancel_order(&request).map_err(invalid)?;
+            let outcome = client.cancel_order(&order_id).await.map_err(broker)?;
+            proto::encode_cancel_outcome(&outcome)
+        }
+        "GetPositions" => proto::encode_positions(&client.get_positions().await.map_err(broker)?),
+        "GetAccount" => proto::encode_account(&client.get_account().await.map_err(broker)?),
+        _ => {
+            let symbols = proto::decode_market_data_request(&request).map_err(invalid)?;
+            return stream_market_data(symbols, stream, live).await;
+        }
+    };
+    send_message(stream, reply).await
+}
+
+/// Sends market data events until the caller cancels or the bus ends.
+async fn stream_market_data(
+    symbols: Vec<String>,
+    stream: &mut SendStream<Bytes>,
+    mut live: broadcast::Receiver<Arc<[EventType]>>,
+) -> Result<(), Status> {
+    loop {
+        let received = tokio::select! {
+            received = live.recv() => received,
+            _ = poll_fn(|cx| stream.poll_reset(cx)) => {
+                return Err(Status::new(Status::CANCELLED, "Cancelled by the caller"));
+            }
+        };
+        let batch = match received {
+            Ok(batch) => batch,
+            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                tracing::warn!(batches = missed, "gRPC market data stream fell behind");
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => {
+                return Err(Status::new(Status::UNAVAILABLE, "Market data stream ended"));
+            }
+        };
+        for event in batch.iter() {
+            let wanted = symbols.is_empty()
+                || event
+                    .symbol()
+                    .is_some_and(|symbol| symbols.iter().any(|wanted| wanted == symbol));
+            if let Some(message) = proto::encode_event(event).filter(|_| wanted) {
+                send_message(stream, message).await?;
+            }
+        }
+    }
+}
+
+/// The single message of a unary or server-streaming call.
+async fn read_message(mut body: RecvStream) -> Result<Vec<u8>, Status> {
+    let mut buffer = vec![];
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|e| Status::new(Status::CANCELLED, e.to_string()))?;
+        let _ = body.flow_control().release_capacity(chunk.len());
+        buffer.extend_from_slice(&chunk);
+        if buffer.len() > MAX_MESSAGE + 5 {
+            return Err(Status::new(
+                Status::RESOURCE_EXHAUSTED,
+                "Request message is too large",
+            ));
+        }
+    }
+
+    // Each message is prefixed with a compression flag and its length.
+    let Some((prefix, message)) = buffer.split_first_chunk::<5>() else {
+        return Err(Status::new(
+            Status::INVALID_ARGUMENT,
+            "Missing request message",
+        ));
+    };
+    if prefix[0] != 0 {
+        return Err(Status::new(
+            Status::UNIMPLEMENTED,
+            "Compressed messages are not supported",
+        ));
+    }
+    let len = u32::from_be_bytes(prefix[1..].try_into().unwrap()) as usize;
+    if message.len() != len {
+        return Err(Status::new(
+            Status::INVALID_ARGUMENT,
+            "Expected exactly one request message",
+        ));
+    }
+    Ok(message.to_vec())
+}
+
+/// Sends one message, waiting for the caller to take data when it is slower than the server.
+async fn send_message(stream: &mut SendStream<Bytes>, message: Vec<u8>) -> Result<(), Status> {
+    let closed = |e: h2::Error| Status::new(Status::CANCELLED, e.to_string());
+    let mut frame = Vec::with_capacity(message.len() + 5);
+    frame.push(0);
+    frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&message);
+
+    let mut data = Bytes::from(frame);
+    while !data.is_empty() {
+        stream.reserve_capacity(data.len());
+        let capacity = match poll_fn(|cx| stream.poll_capacity(cx)).await {
+            Some(capacity) => capacity.map_err(closed)?,
+            None => return Err(Status::new(Status::CANCELLED, "Call closed")),
+        };
+        if capacity > 0 {
+            let chunk = data.split_to(capacity.min(data.len()));
+            stream.send_data(chunk, false).map_err(closed)?;
+        }
+    }
+    Ok(())
+}
+
+/// Percent-encodes a status message as gRPC requires, leaving printable ASCII other than '%' as is.
+fn percent_encode(message: &str) -> String {
+    message
+        .bytes()
+        .map(|byte| match byte {
+            b'%' => "%25".to_string(),
+            0x20..=0x7e => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        sim::{SimClient, SimConfig},
+        stream::EventStream,
+    };
+
+    fn bus() -> EventBus {
+        EventBus::new(EventStream::from_task(16, |_sender| async {}), 16)
+    }
+
+    /// Makes one call and returns its grpc-status and reply messages.
+    async fn call(
+        addr: SocketAddr,
+        method: &str,
+        api_key: Option<&str>,
+        message: &[u8],
+    ) -> (u32, Vec<u8>) {
+        let socket = TcpStream::connect(addr).await.unwrap();
+        let (client, connection) = h2::client::handshake(socket).await.unwrap();
+        tokio::spawn(connection);
+        let mut client = client.ready().await.unwrap();
+        let mut request = Request::post(format!("http://{}/trading.Trading/{}", addr, method))
+            .header(CONTENT_TYPE, "application/grpc");
+        if let Some(api_key) = api_key {
+            request = request.header(AUTHORIZATION, format!("Bearer {}", api_key));
+        }
+        let (response, mut body) = client
+            .send_request(request.body(()).unwrap(), false)
+            .unwrap();
+        let mut framed = vec![0];
+        framed.extend_from_slice(&(message.len() as u32).to_be_bytes());
+        framed.extend_from_slice(message);
+        body.send_data(framed.into(), true).unwrap();
+
+        let mut reply = response.await.unwrap().into_body();
+        let mut data = vec![];
+        while let Some(chunk) = reply.data().await {
+            let chunk = chunk.unwrap();
+            reply.flow_control().release_capacity(chunk.len()).unwrap();
+            data.extend_from_slice(&chunk);
+        }
+        let trailers = reply.trailers().await.unwrap().unwrap();
+        let status = trailers["grpc-status"].to_str().unwrap().parse().unwrap();
+        (status, data)
+    }
+
+    /// `CreateOrderRequest` buying one share of AAPL at market.
+    fn buy_one() -> Vec<u8> {
+        let mut message = vec![0x0a, 4];
+        message.extend_from_slice(b"AAPL");
+        message.push(0x11);
+        message.extend_from_slice(&1.0f64.to_le_bytes());
+        message.extend_from_slice(&[0x18, 1]);
+        message
+    }
+
+    #[tokio::test]
+    async fn trading_needs_an_api_key() {
+        let config = GrpcConfig {
+            addr: "127.0.0.1:0".parse().unwrap(),
+            api_key: None,
+            allow_trading: true,
+        };
+        let client = SimClient::with_config(SimConfig::default());
+        assert!(GrpcServer::serve(client, &bus(), config).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn refuses_trading_calls_unless_allowed() {
+        let config = GrpcConfig {
+            addr: "127.0.0.1:0".parse().unwrap(),
+            api_key: Some("secret".to_string()),
+            allow_trading: false,
+        };
+        let client = SimClient::with_config(SimConfig::default());
+        let server = GrpcServer::serve(client.clone(), &bus(), config)
+            .await
+            .unwrap();
+        let addr = server.local_addr();
+
+        let (status, _) = call(addr, "CreateOrder", Some("secret"), &buy_one()).await;
+        assert_eq!(status, Status::PERMISSION_DENIED);
+        let (status, _) = call(addr, "CancelOrder", Some("secret"), &[0x0a, 1, b'x']).await;
+        assert_eq!(status, Status::PERMISSION_DENIED);
+        assert!(client.get_open_orders().await.unwrap().is_empty());
+
+        let (status, _) = call(addr, "GetPositions", Some("secret"), &[]).await;
+        assert_eq!(status, Status::OK);
+        let (status, _) = call(addr, "GetPositions", None, &[]).await;
+        assert_eq!(status, Status::UNAUTHENTICATED);
+    }
+
+    #[tokio::test]
+    async fn places_orders_when_allowed() {
+        let config = GrpcConfig {
+            addr: "127.0.0.1:0".parse().unwrap(),
+            api_key: Some("secret".to_string()),
+            allow_trading: true,
+        };
+        let client = SimClient::with_config(SimConfig::default());
+        let server = GrpcServer::serve(client.clone(), &bus(), config)
+            .await
+            .unwrap();
+        let addr = server.local_addr();
+
+        let (status, _) = call(addr, "CreateOrder", Some("wrong"), &buy_one()).await;
+        assert_eq!(status, Status::UNAUTHENTICATED);
+        let (status, reply) = call(addr, "CreateOrder", Some("secret"), &buy_one()).await;
+        assert_eq!(status, Status::OK);
+        assert!(reply.len() > 5);
+        let open = client.get_open_orders().await.unwrap();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].symbol, "AAPL");
+    }
+}
@@ -0,0 +1,738 @@
+use crate::datastructures::{
+    account::{Account, Position},
+    event::EventType,
+    order::{CancelOutcome, Order, OrderResponse, OrderSide, OrderStatus, OrderType, TimeInForce},
+};
+
+/// Proto enums list their values from 1 in these orders, leaving 0 as unspecified.
+const SIDES: [OrderSide; 2] = [OrderSide::Buy, OrderSide::Sell];
+const ORDER_TYPES: [OrderType; 5] = [
+    OrderType::Market,
+    OrderType::Limit,
+    OrderType::Stop,
+    OrderType::StopLimit,
+    OrderType::TrailingStop,
+];
+const TIMES_IN_FORCE: [TimeInForce; 6] = [
+    TimeInForce::Day,
+    TimeInForce::Gtc,
+    TimeInForce::Opg,
+    TimeInForce::Cls,
+    TimeInForce::Ioc,
+    TimeInForce::Fok,
+];
+const ORDER_STATUSES: [OrderStatus; 18] = [
+    OrderStatus::New,
+    OrderStatus::PartiallyFilled,
+    OrderStatus::Filled,
+    OrderStatus::DoneForDay,
+    OrderStatus::Canceled,
+    OrderStatus::Expired,
+    OrderStatus::Replaced,
+    OrderStatus::PendingCancel,
+    OrderStatus::PendingReplace,
+    OrderStatus::PendingReview,
+    OrderStatus::Accepted,
+    OrderStatus::PendingNew,
+    OrderStatus::AcceptedForBidding,
+    OrderStatus::Stopped,
+    OrderStatus::Rejected,
+    OrderStatus::Suspended,
+    OrderStatus::Calculated,
+    OrderStatus::Held,
+];
+
+/// Builds a protobuf message. Scalars equal to their proto3 default are left out, as protoc-generated code does.
+#[derive(Default)]
+struct Encoder {
+    buffer: Vec<u8>,
+}
+
+impl Encoder {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.buffer.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.buffer.push(value as u8);
+    }
+
+    fn key(&mut self, field: u32, wire_type: u64) {
+        self.varint((field as u64) << 3 | wire_type);
+    }
+
+    fn bytes(&mut self, field: u32, value: &[u8]) {
+        self.key(field, 2);
+        self.varint(value.len() as u64);
+        self.buffer.extend_from_slice(value);
+    }
+
+    fn string(mut self, field: u32, value: &str) -> Self {
+        if !value.is_empty() {
+            self.bytes(field, value.as_bytes());
+        }
+        self
+    }
+
+    fn double(self, field: u32, value: f64) -> Self {
+        if value == 0.0 {
+            return self;
+        }
+        self.optional_double(field, Some(value))
+    }
+
+    /// For fields declared `optional`, where zero is sent when present.
+    fn optional_double(mut self, field: u32, value: Option<f64>) -> Self {
+        if let Some(value) = value {
+            self.key(field, 1);
+            self.buffer.extend_from_slice(&value.to_le_bytes());
+        }
+        self
+    }
+
+    fn uint64(mut self, field: u32, value: u64) -> Self {
+        if value != 0 {
+            self.key(field, 0);
+            self.varint(value);
+        }
+        self
+    }
+
+    fn bool(self, field: u32, value: bool) -> Self {
+        self.uint64(field, value as u64)
+    }
+
+    /// Number of `value` in a proto enum whose values are `values` in order from 1.
+    fn enumeration<T: PartialEq>(self, field: u32, values: &[T], value: &T) -> Self {
+        let number = values
+            .iter()
+            .position(|known| known == value)
+            .map_or(0, |i| i + 1);
+        self.uint64(field, number as u64)
+    }
+
+    /// Always written, so an empty message still marks which `oneof` member is set.
+    fn message(mut self, field: u32, message: Encoder) -> Self {
+        self.bytes(field, &message.buffer);
+        self
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.buffer
+    }
+}
+
+enum Field<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(&'a [u8]),
+}
+
+/// A decoded protobuf message: its fields in wire order, other than 32-bit ones, which no message here has.
+struct Decoded<'a> {
+    fields: Vec<(u32, Field<'a>)>,
+}
+
+impl<'a> Decoded<'a> {
+    fn parse(mut buffer: &'a [u8]) -> Result<Self, &'static str> {
+        let mut fields = vec![];
+        while !buffer.is_empty() {
+            let key = read_varint(&mut buffer)?;
+            let field = match key & 7 {
+                0 => Field::Varint(read_varint(&mut buffer)?),
+                1 => Field::Fixed64(u64::from_le_bytes(
+                    take(&mut buffer, 8)?.try_into().unwrap(),
+                )),
+                2 => {
+                    let len = read_varint(&mut buffer)? as usize;
+                    Field::Bytes(take(&mut buffer, len)?)
+                }
+                5 => {
+                    take(&mut buffer, 4)?;
+                    continue;
+                }
+                _ => return Err("Unsupported protobuf wire type"),
+            };
+            fields.push(((key >> 3) as u32, field));
+        }
+        Ok(Decoded { fields })
+    }
+
+    /// Last occurrence of a field, which is the one that counts for a non-repeated field.
+    fn last(&self, number: u32) -> Option<&Field<'a>> {
+        self.fields
+            .iter()
+            .rev()
+            .find(|(field, _)| *field == number)
+            .map(|(_, field)| field)
+    }
+
+    fn string(&self, number: u32) -> Result<String, &'static str> {
+        match self.last(number) {
+            None => Ok(String::new()),
+            Some(field) => as_string(field),
+        }
+    }
+
+    fn strings(&self, number: u32) -> Result<Vec<String>, &'static str> {
+        self.fields
+            .iter()
+            .filter(|(field, _)| *field == number)
+            .map(|(_, field)| as_string(field))
+            .collect()
+    }
+
+    fn double(&self, number: u32) -> Result<Option<f64>, &'static str> {
+        match self.last(number) {
+            None => Ok(None),
+            Some(Field::Fixed64(bits)) => Ok(Some(f64::from_bits(*bits))),
+            Some(_) => Err("Expected a double field"),
+        }
+    }
+
+    fn uint64(&self, number: u32) -> Result<u64, &'static str> {
+        match self.last(number) {
+            None => Ok(0),
+            Some(Field::Varint(value)) => Ok(*value),
+            Some(_) => Err("Expected an integer field"),
+        }
+    }
+
+    /// Value of an enum field whose values are `values` in order from 1. `None` when unspecified.
+    fn enumeration<T: Copy>(&self, number: u32, values: &[T]) -> Result<Option<T>, &'static str> {
+        match self.uint64(number)? {
+            0 => Ok(None),
+            n => values
+                .get(n as usize - 1)
+                .copied()
+                .map(Some)
+                .ok_or("Unknown enum value"),
+        }
+    }
+}
+
+fn as_string(field: &Field) -> Result<String, &'static str> {
+    match field {
+        Field::Bytes(bytes) => std::str::from_utf8(bytes)
+            .map(str::to_string)
+            .map_err(|_| "String field is not UTF-8"),
+        _ => Err("Expected a string field"),
+    }
+}
+
+fn read_varint(buffer: &mut &[u8]) -> Result<u64, &'static str> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = buffer.split_first().ok_or("Truncated protobuf message")?;
+        *buffer = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err("Protobuf varint is too long")
+}
+
+fn take<'a>(buffer: &mut &'a [u8], len: usize) -> Result<&'a [u8], &'static str> {
+    if buffer.len() < len {
+        return Err("Truncated protobuf message");
+    }
+    let (head, rest) = buffer.split_at(len);
+    *buffer = rest;
+    Ok(head)
+}
+
+/// `CreateOrderRequest` as an order, validated as `OrderBuilder` does. A client order id is generated when
+/// none is given, so the placed order can be looked up.
+pub(super) fn decode_create_order(message: &[u8]) -> Result<Order, String> {
+    let request = Decoded::parse(message)?;
+    let mut builder = Order::builder()
+        .symbol(request.string(1)?)
+        .quantity(request.double(2)?.unwrap_or_default())
+        .side(request.enumeration(3, &SIDES)?.ok_or("Side must be set")?);
+    if let Some(order_type) = request.enumeration(4, &ORDER_TYPES)? {
+        builder = builder.order_type(order_type);
+    }
+    if let Some(time_in_force) = request.enumeration(5, &TIMES_IN_FORCE)? {
+        builder = builder.time_in_force(time_in_force);
+    }
+    if let Some(limit_price) = request.double(6)? {
+        builder = builder.limit_price(limit_price);
+    }
+    if let Some(stop_price) = request.double(7)? {
+        builder = builder.stop_price(stop_price);
+    }
+    let client_order_id = match request.string(8)? {
+        id if id.is_empty() => format!("{:032x}", rand::random::<u128>()),
+        id => id,
+    };
+    Ok(builder.client_order_id(client_order_id).build()?)
+}
+
+/// Order id of a `CancelOrderRequest`.
+pub(super) fn decode_cancel_order(message: &[u8]) -> Result<String, String> {
+    let order_id = Decoded::parse(message)?.string(1)?;
+    if order_id.is_empty() {
+        return Err("Order id must be set".to_string());
+    }
+    Ok(order_id)
+}
+
+/// Symbols of a `MarketDataRequest`.
+pub(super) fn decode_market_data_request(message: &[u8]) -> Result<Vec<String>, String> {
+    Ok(Decoded::parse(message)?.strings(1)?)
+}
+
+fn order(order: &OrderResponse) -> Encoder {
+    Encoder::default()
+        .string(1, &order.id)
+        .string(2, &order.client_order_id)
+        .string(3, &order.symbol)
+        .enumeration(4, &ORDER_STATUSES, &order.status)
+        .string(5, &order.created_at)
+        .enumeration(6, &SIDES, &order.side)
+        .enumeration(7, &ORDER_TYPES, &order.order_type)
+        .optional_double(8, order.qty)
+        .double(9, order.filled_qty)
+        .optional_double(10, order.filled_avg_price)
+        .optional_double(11, order.limit_price)
+        .optional_double(12, order.stop_price)
+}
+
+pub(super) fn encode_order(placed: &OrderResponse) -> Vec<u8> {
+    order(placed).finish()
+}
+
+pub(super) fn encode_cancel_outcome(outcome: &CancelOutcome) -> Vec<u8> {
+    let number = match outcome {
+        CancelOutcome::Canceled(_) => 1,
+        CancelOutcome::Filled(_) => 2,
+        CancelOutcome::Closed(_) => 3,
+        CancelOutcome::Unconfirmed(_) => 4,
+    };
+    Encoder::default()
+        .uint64(1, number)
+        .message(2, order(outcome.order()))
+        .finish()
+}
+
+pub(super) fn encode_positions(positions: &[Position]) -> Vec<u8> {
+    positions
+        .iter()
+        .fold(Encoder::default(), |reply, position| {
+            reply.message(
+                1,
+                Encoder::default()
+                    .string(1, &position.symbol)
+                    .string(2, &position.exchange)
+                    .string(3, &position.asset_class)
+                    .double(4, position.qty)
+                    .double(5, position.avg_entry_price)
+                    .double(6, position.market_value)
+                    .optional_double(7, position.current_price)
+                    .double(8, position.unrealized_pl),
+            )
+        })
+        .finish()
+}
+
+pub(super) fn encode_account(account: &Account) -> Vec<u8> {
+    Encoder::default()
+        .string(1, &account.id)
+        .string(2, &account.status)
+        .string(3, &account.currency)
+        .double(4, account.cash)
+        .double(5, account.equity)
+        .double(6, account.last_equity)
+        .double(7, account.buying_power)
+        .double(8, account.long_market_value)
+        .double(9, account.short_market_value)
+        .optional_double(10, account.non_marginable_buying_power)
+        .finish()
+}
+
+/// `MarketEvent` for a market data event. `None` for news and connection events, which the service does not carry.
+pub(super) fn encode_event(event: &EventType) -> Option<Vec<u8>> {
+    let bar = |symbol: &str, ohlc: [f64; 4], volume: f64, timestamp: &str| {
+        Encoder::default()
+            .string(1, symbol)
+            .double(2, ohlc[0])
+            .double(3, ohlc[1])
+            .double(4, ohlc[2])
+            .double(5, ohlc[3])
+            .double(6, volume)
+            .string(7, timestamp)
+    };
+    let levels = |book: Encoder, field: u32, levels: &[(f64, f64)]| {
+        levels.iter().fold(book, |book, (price, size)| {
+            book.message(field, Encoder::default().double(1, *price).double(2, *size))
+        })
+    };
+    let (field, message) = match event {
+        EventType::Trade {
+            symbol,
+            price,
+            volume,
+            timestamp,
+        } => (
+            1,
+            Encoder::default()
+                .string(1, symbol)
+                .double(2, *price)
+                .double(3, *volume)
+                .string(4, timestamp),
+        ),
+        EventType::Quote {
+            symbol,
+            bid_price,
+            ask_price,
+            bid_size,
+            ask_size,
+            timestamp,
+        } => (
+            2,
+            Encoder::default()
+                .string(1, symbol)
+                .double(2, *bid_price)
+                .double(3, *ask_price)
+                .double(4, *bid_size)
+                .double(5, *ask_size)
+                .string(6, timestamp),
+        ),
+        EventType::Bar {
+            symbol,
+            open,
+            high,
+            low,
+            close,
+            volume,
+            timestamp,
+        } => (
+            3,
+            bar(symbol, [*open, *high, *low, *close], *volume, timestamp),
+        ),
+        EventType::UpdatedBar {
+            symbol,
+            open,
+            high,
+            low,
+            close,
+            volume,
+            timestamp,
+        } => (
+            4,
+            bar(symbol, [*open, *high, *low, *close], *volume, timestamp),
+        ),
+        EventType::DailyBar {
+            symbol,
+            open,
+            high,
+            low,
+            close,
+            volume,
+            timestamp,
+        } => (
+            5,
+            bar(symbol, [*open, *high, *low, *close], *volume, timestamp),
+        ),
+        EventType::OrderBook {
+            symbol,
+            bids,
+            asks,
+            reset,
+            timestamp,
+        } => {
+            let book = levels(Encoder::default().string(1, symbol), 2, bids);
+            (
+                6,
+                levels(book, 3, asks).bool(4, *reset).string(5, timestamp),
+            )
+        }
+        EventType::Luld {
+            symbol,
+            limit_up,
+            limit_down,
+            timestamp,
+        } => (
+            7,
+            Encoder::default()
+                .string(1, symbol)
+                .double(2, *limit_up)
+                .double(3, *limit_down)
+                .string(4, timestamp),
+        ),
+        EventType::News { .. } | EventType::StaleConnection { .. } => return None,
+    };
+    Some(Encoder::default().message(field, message).finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order_response() -> OrderResponse {
+        OrderResponse {
+            id: "ord-1".to_string(),
+            client_order_id: "client-1".to_string(),
+            symbol: "AAPL".to_string(),
+            status: OrderStatus::PartiallyFilled,
+            created_at: "2024-01-02T14:30:00Z".to_string(),
+            side: OrderSide::Sell,
+            order_type: OrderType::StopLimit,
+            qty: Some(10.0),
+            filled_qty: 4.0,
+            filled_avg_price: Some(189.5),
+            limit_price: Some(189.0),
+            stop_price: None,
+            metadata: Default::default(),
+        }
+    }
+
+    fn message<'a>(decoded: &Decoded<'a>, number: u32) -> Decoded<'a> {
+        match decoded.last(number) {
+            Some(Field::Bytes(bytes)) => Decoded::parse(bytes).unwrap(),
+            _ => panic!("field {} is not a message", number),
+        }
+    }
+
+    #[test]
+    fn varints_round_trip() {
+        for value in [
+            0,
+            1,
+            127,
+            128,
+            300,
+            16_383,
+            16_384,
+            u32::MAX as u64,
+            u64::MAX,
+        ] {
+            let mut encoder = Encoder::default();
+            encoder.varint(value);
+            let buffer = encoder.finish();
+            let mut rest = buffer.as_slice();
+            assert_eq!(read_varint(&mut rest), Ok(value));
+            assert!(rest.is_empty());
+        }
+        let mut encoder = Encoder::default();
+        encoder.varint(300);
+        assert_eq!(encoder.finish(), [0xac, 0x02]);
+    }
+
+    #[test]
+    fn rejects_malformed_messages() {
+        assert!(Decoded::parse(&[0x08]).is_err());
+        assert!(Decoded::parse(&[0x08, 0x80]).is_err());
+        assert!(Decoded::parse(&[0x0a, 0x05, b'a']).is_err());
+        assert!(Decoded::parse(&[0x11, 0, 0, 0]).is_err());
+        assert!(Decoded::parse(&[0x08; 11]).is_err());
+        assert!(Decoded::parse(&[0x0b]).is_err());
+        assert!(Decoded::parse(&[0x0a, 0x01, 0xff])
+            .unwrap()
+            .string(1)
+            .is_err());
+    }
+
+    #[test]
+    fn decodes_create_order() {
+        let request = Encoder::default()
+            .string(1, "AAPL")
+            .double(2, 5.0)
+            .enumeration(3, &SIDES, &OrderSide::Buy)
+            .enumeration(4, &ORDER_TYPES, &OrderType::Limit)
+            .enumeration(5, &TIMES_IN_FORCE, &TimeInForce::Gtc)
+            .optional_double(6, Some(187.25))
+            .string(8, "mine")
+            .finish();
+        let order = decode_create_order(&request).unwrap();
+        assert_eq!(order.symbol, "AAPL");
+        assert_eq!(order.quantity, 5.0);
+        assert_eq!(order.side, OrderSide::Buy);
+        assert_eq!(order.order_type, OrderType::Limit);
+        assert_eq!(order.time_in_force, TimeInForce::Gtc);
+        assert_eq!(order.limit_price, Some(187.25));
+        assert_eq!(order.stop_price, None);
+        assert_eq!(order.client_order_id.as_deref(), Some("mine"));
+    }
+
+    #[test]
+    fn create_order_defaults_and_validation() {
+        let request = Encoder::default()
+            .string(1, "AAPL")
+            .double(2, 1.0)
+            .enumeration(3, &SIDES, &OrderSide::Sell)
+            .finish();
+        let order = decode_create_order(&request).unwrap();
+        assert_eq!(order.order_type, OrderType::Market);
+        assert_eq!(order.time_in_force, TimeInForce::Day);
+        assert_eq!(order.client_order_id.map(|id| id.len()), Some(32));
+
+        let unsided = Encoder::default().string(1, "AAPL").double(2, 1.0).finish();
+        assert_eq!(
+            decode_create_order(&unsided).unwrap_err(),
+            "Side must be set"
+        );
+
+        let unknown_side = Encoder::default()
+            .string(1, "AAPL")
+            .double(2, 1.0)
+            .uint64(3, 9)
+            .finish();
+        assert_eq!(
+            decode_create_order(&unknown_side).unwrap_err(),
+            "Unknown enum value"
+        );
+
+        let wrong_type = Encoder::default().double(1, 1.0).finish();
+        assert!(decode_create_order(&wrong_type).is_err());
+    }
+
+    #[test]
+    fn skips_unknown_fields() {
+        let mut request = Encoder::default()
+            .string(1, "AAPL")
+            .double(2, 1.0)
+            .enumeration(3, &SIDES, &OrderSide::Buy)
+            .string(40, "added later")
+            .uint64(41, 7);
+        request.key(42, 5);
+        request.buffer.extend_from_slice(&[1, 2, 3, 4]);
+        let order = decode_create_order(&request.finish()).unwrap();
+        assert_eq!(order.symbol, "AAPL");
+    }
+
+    #[test]
+    fn encodes_order_and_cancel_outcome() {
+        let encoded = encode_order(&order_response());
+        let order = Decoded::parse(&encoded).unwrap();
+        assert_eq!(order.string(1).unwrap(), "ord-1");
+        assert_eq!(order.string(2).unwrap(), "client-1");
+        assert_eq!(order.string(3).unwrap(), "AAPL");
+        assert_eq!(order.uint64(4).unwrap(), 2);
+        assert_eq!(
+            order.enumeration(4, &ORDER_STATUSES).unwrap(),
+            Some(OrderStatus::PartiallyFilled)
+        );
+        assert_eq!(order.uint64(6).unwrap(), 2);
+        assert_eq!(order.uint64(7).unwrap(), 4);
+        assert_eq!(order.double(8).unwrap(), Some(10.0));
+        assert_eq!(order.double(9).unwrap(), Some(4.0));
+        assert_eq!(order.double(10).unwrap(), Some(189.5));
+        assert_eq!(order.double(11).unwrap(), Some(189.0));
+        assert_eq!(order.double(12).unwrap(), None);
+
+        let encoded = encode_cancel_outcome(&CancelOutcome::Filled(order_response()));
+        let reply = Decoded::parse(&encoded).unwrap();
+        assert_eq!(reply.uint64(1).unwrap(), 2);
+        assert_eq!(message(&reply, 2).string(1).unwrap(), "ord-1");
+    }
+
+    #[test]
+    fn encodes_positions_and_account() {
+        let position = |symbol: &str, qty: f64| Position {
+            symbol: symbol.to_string(),
+            exchange: "NASDAQ".to_string(),
+            asset_class: "us_equity".to_string(),
+            qty,
+            avg_entry_price: 100.0,
+            market_value: qty * 110.0,
+            current_price: Some(110.0),
+            unrealized_pl: qty * 10.0,
+        };
+        let encoded = encode_positions(&[position("AAPL", 3.0), position("MSFT", -2.0)]);
+        let reply = Decoded::parse(&encoded).unwrap();
+        assert_eq!(reply.fields.len(), 2);
+        let short = message(&reply, 1);
+        assert_eq!(short.string(1).unwrap(), "MSFT");
+        assert_eq!(short.double(4).unwrap(), Some(-2.0));
+        assert_eq!(short.double(6).unwrap(), Some(-220.0));
+        assert!(Decoded::parse(&encode_positions(&[]))
+            .unwrap()
+            .fields
+            .is_empty());
+
+        let account = Account {
+            id: "acct".to_string(),
+            status: "ACTIVE".to_string(),
+            currency: "USD".to_string(),
+            cash: 1000.0,
+            equity: 2500.5,
+            last_equity: 2400.0,
+            buying_power: 0.0,
+            long_market_value: 1500.5,
+            short_market_value: 0.0,
+            non_marginable_buying_power: Some(0.0),
+        };
+        let encoded = encode_account(&account);
+        let decoded = Decoded::parse(&encoded).unwrap();
+        assert_eq!(decoded.string(1).unwrap(), "acct");
+        assert_eq!(decoded.double(5).unwrap(), Some(2500.5));
+        // Zero is left out of plain fields but sent for optional ones.
+        assert_eq!(decoded.double(7).unwrap(), None);
+        assert_eq!(decoded.double(10).unwrap(), Some(0.0));
+    }
+
+    #[test]
+    fn encodes_market_events() {
+        let trade = EventType::Trade {
+            symbol: "AAPL".to_string(),
+            price: 190.25,
+            volume: 0.5,
+            timestamp: "2024-01-02T14:30:00Z".to_string(),
+        };
+        let encoded = encode_event(&trade).unwrap();
+        let event = Decoded::parse(&encoded).unwrap();
+        let trade = message(&event, 1);
+        assert_eq!(trade.string(1).unwrap(), "AAPL");
+        assert_eq!(trade.double(2).unwrap(), Some(190.25));
+        assert_eq!(trade.double(3).unwrap(), Some(0.5));
+
+        let book = EventType::OrderBook {
+            symbol: "BTC/USD".to_string(),
+            bids: vec![(42_000.0, 0.5), (41_999.5, 1.25)],
+            asks: vec![],
+            reset: true,
+            timestamp: String::new(),
+        };
+        let encoded = encode_event(&book).unwrap();
+        let event = Decoded::parse(&encoded).unwrap();
+        let book = message(&event, 6);
+        let bids: Vec<_> = book
+            .fields
+            .iter()
+            .filter(|(number, _)| *number == 2)
+            .map(|(_, field)| match field {
+                Field::Bytes(bytes) => {
+                    let level = Decoded::parse(bytes).unwrap();
+                    (level.double(1).unwrap(), level.double(2).unwrap())
+                }
+                _ => panic!("level is not a message"),
+            })
+            .collect();
+        assert_eq!(
+            bids,
+            [(Some(42_000.0), Some(0.5)), (Some(41_999.5), Some(1.25))]
+        );
+        assert_eq!(book.uint64(4).unwrap(), 1);
+        assert!(book.last(3).is_none());
+    }
+
+    #[test]
+    fn decodes_requests() {
+        let request = Encoder::default()
+            .string(1, "AAPL")
+            .string(1, "MSFT")
+            .finish();
+        assert_eq!(
+            decode_market_data_request(&request).unwrap(),
+            ["AAPL", "MSFT"]
+        );
+        assert!(decode_market_data_request(&[]).unwrap().is_empty());
+        assert_eq!(
+            decode_cancel_order(&Encoder::default().string(1, "ord-1").finish()).unwrap(),
+            "ord-1"
+        );
+        assert!(decode_cancel_order(&[]).is_err());
+    }
+}
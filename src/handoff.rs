@@ -0,0 +1,65 @@
+use crate::{
+    datastructures::{
+        client::TradingClient,
+        order::{OrderResponse, OrderSide, STRATEGY_TAG},
+    },
+    journal::{Handoff, OrderJournal},
+    store::{OrderQuery, OrderStore},
+};
+use std::{collections::HashMap, error::Error};
+
+/// Everything a newly started strategy instance takes over from the one it replaces.
+#[derive(Debug, Clone)]
+pub struct AdoptedState {
+    pub strategy: String,
+    /// Open orders tagged to the strategy. They keep working; the new instance manages them from here on.
+    pub working_orders: Vec<OrderResponse>,
+    /// Net filled quantity per symbol across the strategy's recorded orders. Negative for shorts.
+    /// Empty when no order store was provided.
+    pub positions: HashMap<String, f64>,
+    /// State saved by the previous instance, if it stopped cleanly.
+    pub handoff: Option<Handoff>,
+}
+
+/// Collects the working orders, positions and saved state belonging to `strategy` so an updated instance can
+/// continue where the previous one stopped instead of flattening. Ownership is determined by the `strategy` tag
+/// recorded in `journal`; positions are attributed from `store` because the broker only reports account totals.
+pub async fn adopt<C: TradingClient>(
+    client: &C,
+    journal: &OrderJournal,
+    store: Option<&dyn OrderStore>,
+    strategy: &str,
+) -> Result<AdoptedState, Box<dyn Error>> {
+    let owned = |order: &OrderResponse| {
+        order.metadata.get(STRATEGY_TAG).map(String::as_str) == Some(strategy)
+    };
+
+    let mut working_orders = client.get_open_orders().await?;
+    for order in &mut working_orders {
+        journal.annotate(order);
+    }
+    working_orders.retain(|order| owned(order));
+
+    let mut positions = HashMap::new();
+    if let Some(store) = store {
+        for stored in store.history(&OrderQuery::default()).await? {
+            let order = &stored.order;
+            if !owned(order) || order.filled_qty == 0.0 {
+                continue;
+            }
+            let signed = match order.side {
+                OrderSide::Buy => order.filled_qty,
+                OrderSide::Sell => -order.filled_qty,
+            };
+            *positions.entry(order.symbol.clone()).or_insert(0.0) += signed;
+        }
+        positions.retain(|_, quantity| *quantity != 0.0);
+    }
+
+    Ok(AdoptedState {
+        strategy: strategy.to_string(),
+        working_orders,
+        positions,
+        handoff: journal.handoff(strategy),
+    })
+}
@@ -0,0 +1,258 @@
+use reqwest::StatusCode;
+use std::{
+    error::Error,
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+use tokio::time::Instant;
+
+#[derive(Debug, Clone, Copy)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failures that open the circuit.
+    pub failure_threshold: u32,
+    /// How long the circuit stays open before letting probe requests through.
+    pub open_duration: Duration,
+    /// Requests allowed through concurrently while half-open.
+    pub half_open_probes: u32,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        CircuitBreakerConfig {
+            failure_threshold: 5,
+            open_duration: Duration::from_secs(30),
+            half_open_probes: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+/// Counters for state changes and fast-failed requests.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CircuitBreakerMetrics {
+    pub opened: u64,
+    pub half_opened: u64,
+    pub closed: u64,
+    pub rejected: u64,
+}
+
+/// Returned instead of sending a request while the broker is considered down.
+#[derive(Debug, Clone)]
+pub struct CircuitOpen {
+    pub retry_after: Duration,
+}
+
+impl fmt::Display for CircuitOpen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Circuit breaker is open; retry in {:.1}s",
+            self.retry_after.as_secs_f64()
+        )
+    }
+}
+
+impl Error for CircuitOpen {}
+
+struct Inner {
+    state: CircuitState,
+    consecutive_failures: u32,
+    opened_at: Instant,
+    probes_in_flight: u32,
+}
+
+/// Lets one request through the breaker. Dropping it without `record`, as when the request is cancelled or
+/// times out, gives back its half-open probe slot so the circuit does not stay half-open for good.
+#[must_use]
+pub struct CircuitPermit<'a> {
+    breaker: &'a CircuitBreaker,
+    /// Half-open spell the probe slot was taken in, if it was a probe.
+    probe: Option<u64>,
+}
+
+impl CircuitPermit<'_> {
+    /// Records the outcome of the request. Transport errors and 5xx responses count as failures.
+    pub fn record(mut self, status: Option<StatusCode>) {
+        self.probe = None;
+        self.breaker.record(status);
+    }
+}
+
+impl Drop for CircuitPermit<'_> {
+    fn drop(&mut self) {
+        if let Some(half_opened) = self.probe {
+            self.breaker.release(half_opened);
+        }
+    }
+}
+
+/// Fails requests fast after repeated broker failures instead of letting each one time out.
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    inner: Mutex<Inner>,
+    opened: AtomicU64,
+    half_opened: AtomicU64,
+    closed: AtomicU64,
+    rejected: AtomicU64,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        CircuitBreaker {
+            config,
+            inner: Mutex::new(Inner {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                opened_at: Instant::now(),
+                probes_in_flight: 0,
+            }),
+            opened: AtomicU64::new(0),
+            half_opened: AtomicU64::new(0),
+            closed: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    /// Must be called before every request; the outcome is then reported through `CircuitPermit::record`.
+    pub fn try_acquire(&self) -> Result<CircuitPermit<'_>, CircuitOpen> {
+        let mut inner = self.inner.lock().unwrap();
+
+        if inner.state == CircuitState::Open {
+            let elapsed = inner.opened_at.elapsed();
+            if elapsed < self.config.open_duration {
+                self.rejected.fetch_add(1, Ordering::Relaxed);
+                return Err(CircuitOpen {
+                    retry_after: self.config.open_duration - elapsed,
+                });
+            }
+            inner.state = CircuitState::HalfOpen;
+            inner.probes_in_flight = 0;
+            self.half_opened.fetch_add(1, Ordering::Relaxed);
+        }
+
+        if inner.state == CircuitState::HalfOpen {
+            if inner.probes_in_flight >= self.config.half_open_probes.max(1) {
+                self.rejected.fetch_add(1, Ordering::Relaxed);
+                return Err(CircuitOpen {
+                    retry_after: Duration::ZERO,
+                });
+            }
+            inner.probes_in_flight += 1;
+            return Ok(CircuitPermit {
+                breaker: self,
+                probe: Some(self.half_opened.load(Ordering::Relaxed)),
+            });
+        }
+
+        Ok(CircuitPermit {
+            breaker: self,
+            probe: None,
+        })
+    }
+
+    fn record(&self, status: Option<StatusCode>) {
+        let failed = status.is_none_or(|status| status.is_server_error());
+        let mut inner = self.inner.lock().unwrap();
+
+        if !failed {
+            inner.consecutive_failures = 0;
+            if inner.state != CircuitState::Closed {
+                inner.state = CircuitState::Closed;
+                self.closed.fetch_add(1, Ordering::Relaxed);
+            }
+            return;
+        }
+
+        inner.consecutive_failures += 1;
+        let should_open = match inner.state {
+            CircuitState::HalfOpen => true,
+            CircuitState::Closed => inner.consecutive_failures >= self.config.failure_threshold,
+            CircuitState::Open => false,
+        };
+        if should_open {
+            inner.state = CircuitState::Open;
+            inner.opened_at = Instant::now();
+            self.opened.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Frees a probe slot whose request never reported back, unless the circuit has left that half-open spell.
+    fn release(&self, half_opened: u64) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.state == CircuitState::HalfOpen
+            && self.half_opened.load(Ordering::Relaxed) == half_opened
+        {
+            inner.probes_in_flight = inner.probes_in_flight.saturating_sub(1);
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.inner.lock().unwrap().state
+    }
+
+    pub fn metrics(&self) -> CircuitBreakerMetrics {
+        CircuitBreakerMetrics {
+            opened: self.opened.load(Ordering::Relaxed),
+            half_opened: self.half_opened.load(Ordering::Relaxed),
+            closed: self.closed.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn half_open() -> CircuitBreaker {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 1,
+            open_duration: Duration::ZERO,
+            half_open_probes: 1,
+        });
+        breaker.try_acquire().unwrap().record(None);
+        assert_eq!(breaker.state(), CircuitState::Open);
+        breaker
+    }
+
+    #[test]
+    fn releases_the_probe_of_a_dropped_request() {
+        let breaker = half_open();
+        let probe = breaker.try_acquire().unwrap();
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert!(breaker.try_acquire().is_err());
+        drop(probe);
+
+        breaker.try_acquire().unwrap().record(Some(StatusCode::OK));
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn ignores_a_probe_dropped_after_its_spell() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 1,
+            open_duration: Duration::ZERO,
+            half_open_probes: 2,
+        });
+        breaker.try_acquire().unwrap().record(None);
+        let stale = breaker.try_acquire().unwrap();
+        breaker.try_acquire().unwrap().record(None);
+
+        // A new half-open spell: the stale probe must not free one of its slots.
+        let first = breaker.try_acquire().unwrap();
+        let second = breaker.try_acquire().unwrap();
+        drop(stale);
+        assert!(breaker.try_acquire().is_err());
+        drop((first, second));
+    }
+}
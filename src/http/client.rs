@@ -0,0 +1,67 @@
+use reqwest::{Certificate, Client, Proxy};
+use std::time::Duration;
+
+/// Settings for the underlying REST client. All optional; unset fields keep reqwest's defaults.
+#[derive(Clone, Default)]
+pub struct HttpClientConfig {
+    pub connect_timeout: Option<Duration>,
+    /// Total time allowed per request, including reading the response body.
+    pub request_timeout: Option<Duration>,
+    /// Proxy used for every REST call: HTTP, HTTPS or SOCKS5, e.g. `Proxy::all("socks5://127.0.0.1:1080")`. Use
+    /// `socks5h://` to have the proxy resolve host names.
+    pub proxy: Option<Proxy>,
+    /// Trusted in addition to the system roots, e.g. for a TLS-inspecting corporate proxy.
+    pub root_certificates: Vec<Certificate>,
+    pub user_agent: Option<String>,
+}
+
+impl HttpClientConfig {
+    /// Builds the REST client. Fails when the TLS backend cannot be initialized or a setting is rejected.
+    pub(crate) fn build_client(&self) -> Result<Client, reqwest::Error> {
+        let mut builder = Client::builder();
+        if let Some(connect_timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(connect_timeout);
+        }
+        if let Some(request_timeout) = self.request_timeout {
+            builder = builder.timeout(request_timeout);
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(proxy.clone());
+        }
+        for certificate in &self.root_certificates {
+            builder = builder.add_root_certificate(certificate.clone());
+        }
+        if let Some(user_agent) = &self.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+        builder.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{io::AsyncReadExt, net::TcpListener};
+
+    #[tokio::test]
+    async fn routes_requests_through_socks5_proxies() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy = format!("socks5://{}", listener.local_addr().unwrap());
+        let client = HttpClientConfig {
+            proxy: Some(Proxy::all(proxy).unwrap()),
+            ..Default::default()
+        }
+        .build_client()
+        .unwrap();
+
+        let request = tokio::spawn(async move { client.get("http://192.0.2.1/").send().await });
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut greeting = [0u8; 1];
+        socket.read_exact(&mut greeting).await.unwrap();
+        drop(socket);
+
+        // A SOCKS5 handshake starts with the protocol version.
+        assert_eq!(greeting[0], 5);
+        assert!(request.await.unwrap().is_err());
+    }
+}
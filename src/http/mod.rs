@@ -0,0 +1,43 @@
+mod circuit_breaker;
+mod client;
+mod rate_limit;
+#[cfg(any(
+    feature = "binance",
+    feature = "finnhub",
+    feature = "gemini",
+    feature = "kraken",
+    feature = "polygon",
+    feature = "schwab",
+    feature = "tiingo",
+    feature = "yahoo"
+))]
+mod rest;
+mod retry;
+#[cfg(any(
+    feature = "binance",
+    feature = "gemini",
+    feature = "grpc",
+    feature = "kraken",
+    feature = "postgres",
+    feature = "server"
+))]
+pub(crate) mod signing;
+
+pub use circuit_breaker::{
+    CircuitBreaker, CircuitBreakerConfig, CircuitBreakerMetrics, CircuitOpen, CircuitPermit,
+    CircuitState,
+};
+pub use client::HttpClientConfig;
+pub use rate_limit::{RateLimitConfig, RateLimiter};
+#[cfg(any(
+    feature = "binance",
+    feature = "finnhub",
+    feature = "gemini",
+    feature = "kraken",
+    feature = "polygon",
+    feature = "schwab",
+    feature = "tiingo",
+    feature = "yahoo"
+))]
+pub(crate) use rest::RestPolicies;
+pub use retry::RetryPolicy;
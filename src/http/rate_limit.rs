@@ -0,0 +1,111 @@
+use reqwest::{header::HeaderMap, StatusCode};
+use std::{
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::time::Instant;
+
+/// Client-side request budget. Alpaca allows 200 requests per minute per account by default.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitConfig {
+    pub requests_per_minute: u32,
+    /// Requests that may be sent back to back before the per-minute pacing kicks in.
+    pub burst: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            requests_per_minute: 200,
+            burst: 10,
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+    /// Set when the server reported an exhausted budget; no requests are sent before it.
+    blocked_until: Option<Instant>,
+}
+
+/// Token bucket shared by every REST call of a client, corrected by the server's rate-limit headers.
+/// Requests wait for capacity instead of being sent into a 429.
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        RateLimiter {
+            config,
+            bucket: Mutex::new(Bucket {
+                tokens: config.burst as f64,
+                last_refill: Instant::now(),
+                blocked_until: None,
+            }),
+        }
+    }
+
+    /// Waits until a request may be sent and takes a token for it.
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap();
+                let now = Instant::now();
+                self.refill(&mut bucket, now);
+
+                match bucket.blocked_until {
+                    Some(until) if until > now => until - now,
+                    _ => {
+                        bucket.blocked_until = None;
+                        if bucket.tokens >= 1.0 {
+                            bucket.tokens -= 1.0;
+                            return;
+                        }
+                        Duration::from_secs_f64((1.0 - bucket.tokens) / self.tokens_per_second())
+                    }
+                }
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Aligns the bucket with `X-RateLimit-Remaining` / `X-RateLimit-Reset` and backs off after a 429.
+    pub fn observe(&self, status: StatusCode, headers: &HeaderMap) {
+        let header = |name: &str| -> Option<u64> { headers.get(name)?.to_str().ok()?.parse().ok() };
+        let remaining = header("x-ratelimit-remaining");
+        let reset = header("x-ratelimit-reset").map(until_unix_time);
+
+        let mut bucket = self.bucket.lock().unwrap();
+        if let Some(remaining) = remaining {
+            bucket.tokens = bucket.tokens.min(remaining as f64);
+        }
+
+        if status == StatusCode::TOO_MANY_REQUESTS || remaining == Some(0) {
+            let backoff = reset.unwrap_or(Duration::from_secs(1));
+            bucket.tokens = 0.0;
+            bucket.blocked_until = Some(Instant::now() + backoff);
+        }
+    }
+
+    fn tokens_per_second(&self) -> f64 {
+        self.config.requests_per_minute.max(1) as f64 / 60.0
+    }
+
+    fn refill(&self, bucket: &mut Bucket, now: Instant) {
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.tokens_per_second())
+            .min(self.config.burst.max(1) as f64);
+        bucket.last_refill = now;
+    }
+}
+
+fn until_unix_time(reset: u64) -> Duration {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    Duration::from_secs(reset.saturating_sub(now))
+}
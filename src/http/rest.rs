@@ -0,0 +1,118 @@
+use super::{CircuitBreaker, RateLimiter, RetryPolicy};
+use crate::datastructures::config::Config;
+use reqwest::{Client, Request, Response};
+use std::{error::Error, sync::Arc, time::Instant};
+use tracing::Instrument;
+
+/// The config's rate limiting, retries and circuit breaker, for broker clients other than `AlpacaClient` that
+/// apply them the same way. The default applies none.
+#[derive(Clone, Default)]
+pub(crate) struct RestPolicies {
+    rate_limiter: Option<Arc<RateLimiter>>,
+    retry: Option<RetryPolicy>,
+    circuit_breaker: Option<Arc<CircuitBreaker>>,
+}
+
+impl RestPolicies {
+    pub(crate) fn new(config: &Config) -> Self {
+        RestPolicies {
+            rate_limiter: config
+                .rate_limit
+                .map(|rate_limit| Arc::new(RateLimiter::new(rate_limit))),
+            retry: config.retry,
+            circuit_breaker: config
+                .circuit_breaker
+                .map(|circuit_breaker| Arc::new(CircuitBreaker::new(circuit_breaker))),
+        }
+    }
+
+    /// Sends the request `build` makes, building it afresh for every attempt so signed requests carry a current
+    /// timestamp. Only `idempotent` requests are retried.
+    pub(crate) async fn send(
+        &self,
+        http_client: &Client,
+        idempotent: bool,
+        build: impl Fn() -> Result<Request, Box<dyn Error>>,
+    ) -> Result<Response, Box<dyn Error>> {
+        let max_attempts = match self.retry {
+            Some(retry) if idempotent => retry.max_attempts.max(1),
+            _ => 1,
+        };
+
+        let mut attempt = 1;
+        loop {
+            let request = build()?;
+            let permit = self
+                .circuit_breaker
+                .as_ref()
+                .map(|circuit_breaker| circuit_breaker.try_acquire())
+                .transpose()?;
+
+            let result = self.execute(http_client, request).await;
+
+            if let Some(permit) = permit {
+                permit.record(result.as_ref().ok().map(Response::status));
+            }
+
+            let retryable = match &result {
+                Ok(response) => RetryPolicy::is_retryable(response.status()),
+                Err(_) => true,
+            };
+            match self.retry {
+                Some(retry) if retryable && attempt < max_attempts => {
+                    tokio::time::sleep(retry.backoff(attempt)).await;
+                    attempt += 1;
+                }
+                _ => return result.map_err(|e| e as Box<dyn Error>),
+            }
+        }
+    }
+
+    async fn execute(
+        &self,
+        http_client: &Client,
+        request: Request,
+    ) -> Result<Response, Box<dyn Error + Send + Sync>> {
+        let span = tracing::info_span!(
+            "http_request",
+            method = %request.method(),
+            url = %request.url().path(),
+            status = tracing::field::Empty,
+            latency_ms = tracing::field::Empty,
+        );
+
+        async {
+            if let Some(rate_limiter) = &self.rate_limiter {
+                rate_limiter.acquire().await;
+            }
+
+            let started = Instant::now();
+            let result = http_client.execute(request).await;
+            let span = tracing::Span::current();
+            span.record("latency_ms", started.elapsed().as_millis() as u64);
+            #[cfg(feature = "metrics")]
+            crate::metrics::registry()
+                .rest_latency
+                .observe(started.elapsed());
+
+            match &result {
+                Ok(response) => {
+                    span.record("status", response.status().as_u16());
+                    if let Some(rate_limiter) = &self.rate_limiter {
+                        rate_limiter.observe(response.status(), response.headers());
+                    }
+                    if response.status().is_success() {
+                        tracing::debug!("Request completed");
+                    } else {
+                        tracing::warn!("Request failed");
+                    }
+                }
+                Err(e) => tracing::warn!(error = %e, "Request error"),
+            }
+
+            Ok(result?)
+        }
+        .instrument(span)
+        .await
+    }
+}
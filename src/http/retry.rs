@@ -0,0 +1,39 @@
+use rand::Rng;
+use reqwest::StatusCode;
+use std::time::Duration;
+
+/// Exponential backoff with full jitter for transient REST failures.
+/// Only applied to idempotent calls: reads, cancels, and order submissions carrying a `client_order_id`.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total attempts including the first one.
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before the attempt following `attempt` (1-based): uniformly random up to the exponential cap.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(2_u32.saturating_pow(attempt.saturating_sub(1)));
+        let cap = exponential.min(self.max_delay);
+        cap.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+    }
+
+    /// 429 and 5xx responses are worth another try; everything else is final.
+    pub fn is_retryable(status: StatusCode) -> bool {
+        status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+    }
+}
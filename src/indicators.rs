@@ -0,0 +1,462 @@
+use crate::{datastructures::event::EventType, time};
+use std::{collections::VecDeque, time::Duration};
+
+/// A streaming indicator, updated one event at a time. Feed each instance the events of a single symbol.
+/// Bars contribute their close (or OHLC, where the indicator uses it)
+/// and trades their price. `UpdatedBar` corrections and other events are ignored.
+pub trait Indicator {
+    type Output;
+
+    fn update_event(&mut self, event: &EventType) -> Option<Self::Output>;
+
+    /// Latest output. `None` until enough data has been seen.
+    fn value(&self) -> Option<Self::Output>;
+}
+
+/// High, low and close of a bar or, for a trade, its price three times.
+fn hlc(event: &EventType) -> Option<(f64, f64, f64)> {
+    match *event {
+        EventType::Bar {
+            high, low, close, ..
+        } => Some((high, low, close)),
+        EventType::Trade { price, .. } => Some((price, price, price)),
+        _ => None,
+    }
+}
+
+fn close(event: &EventType) -> Option<f64> {
+    hlc(event).map(|(_, _, close)| close)
+}
+
+/// Simple moving average over the last `period` values.
+#[derive(Debug, Clone)]
+pub struct Sma {
+    period: usize,
+    window: VecDeque<f64>,
+    sum: f64,
+}
+
+impl Sma {
+    pub fn new(period: usize) -> Self {
+        assert!(period > 0, "Period must be greater than zero");
+        Sma {
+            period,
+            window: VecDeque::with_capacity(period + 1),
+            sum: 0.0,
+        }
+    }
+
+    pub fn update(&mut self, value: f64) -> Option<f64> {
+        self.window.push_back(value);
+        self.sum += value;
+        if self.window.len() > self.period {
+            self.sum -= self.window.pop_front().unwrap_or_default();
+        }
+        self.current()
+    }
+
+    fn current(&self) -> Option<f64> {
+        (self.window.len() == self.period).then(|| self.sum / self.period as f64)
+    }
+}
+
+impl Indicator for Sma {
+    type Output = f64;
+
+    fn update_event(&mut self, event: &EventType) -> Option<f64> {
+        self.update(close(event)?)
+    }
+
+    fn value(&self) -> Option<f64> {
+        self.current()
+    }
+}
+
+/// Exponential moving average with smoothing 2 / (period + 1), seeded with the SMA of the first `period` values.
+#[derive(Debug, Clone)]
+pub struct Ema {
+    alpha: f64,
+    seed: Sma,
+    value: Option<f64>,
+}
+
+impl Ema {
+    pub fn new(period: usize) -> Self {
+        Ema {
+            alpha: 2.0 / (period as f64 + 1.0),
+            seed: Sma::new(period),
+            value: None,
+        }
+    }
+
+    pub fn update(&mut self, value: f64) -> Option<f64> {
+        self.value = match self.value {
+            Some(previous) => Some(previous + self.alpha * (value - previous)),
+            None => self.seed.update(value),
+        };
+        self.value
+    }
+}
+
+impl Indicator for Ema {
+    type Output = f64;
+
+    fn update_event(&mut self, event: &EventType) -> Option<f64> {
+        self.update(close(event)?)
+    }
+
+    fn value(&self) -> Option<f64> {
+        self.value
+    }
+}
+
+/// Relative strength index with Wilder's smoothing, from 0 to 100.
+#[derive(Debug, Clone)]
+pub struct Rsi {
+    period: usize,
+    previous: Option<f64>,
+    changes: usize,
+    average_gain: f64,
+    average_loss: f64,
+}
+
+impl Rsi {
+    pub fn new(period: usize) -> Self {
+        assert!(period > 0, "Period must be greater than zero");
+        Rsi {
+            period,
+            previous: None,
+            changes: 0,
+            average_gain: 0.0,
+            average_loss: 0.0,
+        }
+    }
+
+    pub fn update(&mut self, value: f64) -> Option<f64> {
+        let previous = self.previous.replace(value)?;
+        let change = value - previous;
+        let (gain, loss) = (change.max(0.0), (-change).max(0.0));
+
+        self.changes += 1;
+        let period = self.period as f64;
+        if self.changes <= self.period {
+            // Plain average over the first `period` changes.
+            self.average_gain += gain / period;
+            self.average_loss += loss / period;
+        } else {
+            self.average_gain = (self.average_gain * (period - 1.0) + gain) / period;
+            self.average_loss = (self.average_loss * (period - 1.0) + loss) / period;
+        }
+        self.current()
+    }
+
+    fn current(&self) -> Option<f64> {
+        if self.changes < self.period {
+            return None;
+        }
+        if self.average_loss == 0.0 {
+            return Some(if self.average_gain == 0.0 {
+                50.0
+            } else {
+                100.0
+            });
+        }
+        Some(100.0 - 100.0 / (1.0 + self.average_gain / self.average_loss))
+    }
+}
+
+impl Indicator for Rsi {
+    type Output = f64;
+
+    fn update_event(&mut self, event: &EventType) -> Option<f64> {
+        self.update(close(event)?)
+    }
+
+    fn value(&self) -> Option<f64> {
+        self.current()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MacdValue {
+    pub macd: f64,
+    pub signal: f64,
+    pub histogram: f64,
+}
+
+/// Moving average convergence divergence: fast EMA minus slow EMA, with an EMA of that as the signal line.
+#[derive(Debug, Clone)]
+pub struct Macd {
+    fast: Ema,
+    slow: Ema,
+    signal: Ema,
+    value: Option<MacdValue>,
+}
+
+impl Macd {
+    pub fn new(fast: usize, slow: usize, signal: usize) -> Self {
+        assert!(
+            fast < slow,
+            "Fast period must be shorter than the slow period"
+        );
+        Macd {
+            fast: Ema::new(fast),
+            slow: Ema::new(slow),
+            signal: Ema::new(signal),
+            value: None,
+        }
+    }
+
+    pub fn update(&mut self, value: f64) -> Option<MacdValue> {
+        let fast = self.fast.update(value);
+        let slow = self.slow.update(value);
+        let (Some(fast), Some(slow)) = (fast, slow) else {
+            return None;
+        };
+        let macd = fast - slow;
+        let signal = self.signal.update(macd)?;
+        self.value = Some(MacdValue {
+            macd,
+            signal,
+            histogram: macd - signal,
+        });
+        self.value
+    }
+}
+
+impl Default for Macd {
+    /// The usual 12/26/9 setup.
+    fn default() -> Self {
+        Macd::new(12, 26, 9)
+    }
+}
+
+impl Indicator for Macd {
+    type Output = MacdValue;
+
+    fn update_event(&mut self, event: &EventType) -> Option<MacdValue> {
+        self.update(close(event)?)
+    }
+
+    fn value(&self) -> Option<MacdValue> {
+        self.value
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bands {
+    pub upper: f64,
+    pub middle: f64,
+    pub lower: f64,
+}
+
+/// Bollinger Bands: SMA of the last `period` values plus and minus `k` population standard deviations.
+#[derive(Debug, Clone)]
+pub struct BollingerBands {
+    sma: Sma,
+    squares: f64,
+    k: f64,
+}
+
+impl BollingerBands {
+    pub fn new(period: usize, k: f64) -> Self {
+        BollingerBands {
+            sma: Sma::new(period),
+            squares: 0.0,
+            k,
+        }
+    }
+
+    pub fn update(&mut self, value: f64) -> Option<Bands> {
+        self.squares += value * value;
+        if self.sma.window.len() == self.sma.period {
+            let oldest = self.sma.window.front().copied().unwrap_or_default();
+            self.squares -= oldest * oldest;
+        }
+        self.sma.update(value);
+        self.current()
+    }
+
+    fn current(&self) -> Option<Bands> {
+        let middle = self.sma.current()?;
+        let variance = (self.squares / self.sma.period as f64 - middle * middle).max(0.0);
+        let width = self.k * variance.sqrt();
+        Some(Bands {
+            upper: middle + width,
+            middle,
+            lower: middle - width,
+        })
+    }
+}
+
+impl Default for BollingerBands {
+    /// 20 periods, 2 standard deviations.
+    fn default() -> Self {
+        BollingerBands::new(20, 2.0)
+    }
+}
+
+impl Indicator for BollingerBands {
+    type Output = Bands;
+
+    fn update_event(&mut self, event: &EventType) -> Option<Bands> {
+        self.update(close(event)?)
+    }
+
+    fn value(&self) -> Option<Bands> {
+        self.current()
+    }
+}
+
+/// Average true range with Wilder's smoothing.
+#[derive(Debug, Clone)]
+pub struct Atr {
+    period: usize,
+    previous_close: Option<f64>,
+    ranges: usize,
+    value: f64,
+}
+
+impl Atr {
+    pub fn new(period: usize) -> Self {
+        assert!(period > 0, "Period must be greater than zero");
+        Atr {
+            period,
+            previous_close: None,
+            ranges: 0,
+            value: 0.0,
+        }
+    }
+
+    pub fn update(&mut self, high: f64, low: f64, close: f64) -> Option<f64> {
+        let true_range = match self.previous_close.replace(close) {
+            Some(previous) => (high - low)
+                .max((high - previous).abs())
+                .max((low - previous).abs()),
+            None => high - low,
+        };
+
+        self.ranges += 1;
+        let period = self.period as f64;
+        if self.ranges <= self.period {
+            self.value += true_range / period;
+        } else {
+            self.value = (self.value * (period - 1.0) + true_range) / period;
+        }
+        self.current()
+    }
+
+    fn current(&self) -> Option<f64> {
+        (self.ranges >= self.period).then_some(self.value)
+    }
+}
+
+impl Indicator for Atr {
+    type Output = f64;
+
+    fn update_event(&mut self, event: &EventType) -> Option<f64> {
+        let (high, low, close) = hlc(event)?;
+        self.update(high, low, close)
+    }
+
+    fn value(&self) -> Option<f64> {
+        self.current()
+    }
+}
+
+/// Volume-weighted average price over a trailing time window. Bars are weighted at their typical price,
+/// (high + low + close) / 3.
+#[derive(Debug, Clone)]
+pub struct RollingVwap {
+    window: i64,
+    /// (timestamp in nanoseconds, price * volume, volume), oldest first.
+    entries: VecDeque<(i64, f64, f64)>,
+    notional: f64,
+    volume: f64,
+}
+
+impl RollingVwap {
+    pub fn new(window: Duration) -> Self {
+        RollingVwap {
+            window: window.as_nanos() as i64,
+            entries: VecDeque::new(),
+            notional: 0.0,
+            volume: 0.0,
+        }
+    }
+
+    /// `timestamp` is in nanoseconds since the Unix epoch and should not go backwards.
+    pub fn update(&mut self, price: f64, volume: f64, timestamp: i64) -> Option<f64> {
+        self.entries.push_back((timestamp, price * volume, volume));
+        self.notional += price * volume;
+        self.volume += volume;
+        while let Some(&(oldest, notional, volume)) = self.entries.front() {
+            if oldest > timestamp - self.window {
+                break;
+            }
+            self.entries.pop_front();
+            self.notional -= notional;
+            self.volume -= volume;
+        }
+        self.current()
+    }
+
+    fn current(&self) -> Option<f64> {
+        (self.volume > 0.0).then(|| self.notional / self.volume)
+    }
+}
+
+impl Indicator for RollingVwap {
+    type Output = f64;
+
+    fn update_event(&mut self, event: &EventType) -> Option<f64> {
+        let timestamp = time::parse_rfc3339(event.timestamp()?)?;
+        match *event {
+            EventType::Bar {
+                high,
+                low,
+                close,
+                volume,
+                ..
+            } => self.update((high + low + close) / 3.0, volume, timestamp),
+            EventType::Trade { price, volume, .. } => self.update(price, volume, timestamp),
+            _ => None,
+        }
+    }
+
+    fn value(&self) -> Option<f64> {
+        self.current()
+    }
+}
+
+/// Feeds the inner indicator regular-session data only, so thin pre-market and after-hours prints of US equities
+/// do not skew it. Crypto passes through, and so would daily bars.
+#[derive(Debug, Clone)]
+pub struct RegularHours<I> {
+    inner: I,
+}
+
+impl<I: Indicator> RegularHours<I> {
+    pub fn new(inner: I) -> Self {
+        RegularHours { inner }
+    }
+
+    pub fn inner(&self) -> &I {
+        &self.inner
+    }
+}
+
+impl<I: Indicator> Indicator for RegularHours<I> {
+    type Output = I::Output;
+
+    fn update_event(&mut self, event: &EventType) -> Option<I::Output> {
+        if event.is_extended_hours() {
+            return None;
+        }
+        self.inner.update_event(event)
+    }
+
+    fn value(&self) -> Option<I::Output> {
+        self.inner.value()
+    }
+}
@@ -0,0 +1,317 @@
+use crate::{
+    datastructures::{
+        account::AccountActivity,
+        order::{Order, OrderMetadata, OrderResponse},
+    },
+    shutdown::Sink,
+    strategy::Fill,
+    time,
+};
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::broadcast;
+
+/// Events buffered per subscriber.
+const EVENT_CAPACITY: usize = 1024;
+
+/// Metadata recorded for one submitted order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub client_order_id: String,
+    pub symbol: String,
+    pub metadata: OrderMetadata,
+    pub recorded_at: String,
+}
+
+/// State a stopping strategy instance leaves behind for its replacement.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Handoff {
+    pub strategy: String,
+    /// Version of the instance that wrote the handoff.
+    pub version: String,
+    pub state: serde_json::Value,
+    pub recorded_at: String,
+}
+
+impl Handoff {
+    pub fn state<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        T::deserialize(&self.state)
+    }
+}
+
+/// An order that could not be sent because the broker was down, kept until it is resubmitted or discarded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedIntent {
+    /// Client order id of the order, which makes resubmitting it safe.
+    pub intent_id: String,
+    pub order: Order,
+    /// `Order::metadata` is not serialized with the order, so it is kept here.
+    pub metadata: OrderMetadata,
+    pub reason: String,
+    pub queued_at: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntentResolution {
+    Resubmitted,
+    Discarded,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ResolvedIntent {
+    intent_id: String,
+    resolution: IntentResolution,
+    recorded_at: String,
+}
+
+/// Something worth telling other systems about: every record the journal writes, plus whatever is passed to
+/// `OrderJournal::publish`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum JournalEvent {
+    OrderRecorded(JournalEntry),
+    Handoff(Handoff),
+    IntentQueued(QueuedIntent),
+    IntentResolved {
+        intent_id: String,
+        resolution: IntentResolution,
+    },
+    OrderUpdate(OrderResponse),
+    Fill(Fill),
+    /// `drawdown` is the fraction of `peak` equity lost.
+    Drawdown {
+        equity: f64,
+        peak: f64,
+        drawdown: f64,
+    },
+    /// Deposit, withdrawal, dividend or other non-trading entry on the account.
+    AccountActivity(AccountActivity),
+}
+
+impl From<JournalRecord> for JournalEvent {
+    fn from(record: JournalRecord) -> Self {
+        match record {
+            JournalRecord::Order(entry) => JournalEvent::OrderRecorded(entry),
+            JournalRecord::Handoff(handoff) => JournalEvent::Handoff(handoff),
+            JournalRecord::Intent(intent) => JournalEvent::IntentQueued(intent),
+            JournalRecord::Resolved(resolved) => JournalEvent::IntentResolved {
+                intent_id: resolved.intent_id,
+                resolution: resolved.resolution,
+            },
+        }
+    }
+}
+
+/// One line of the journal file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum JournalRecord {
+    Order(JournalEntry),
+    Handoff(Handoff),
+    Intent(QueuedIntent),
+    Resolved(ResolvedIntent),
+}
+
+struct JournalState {
+    writer: Option<BufWriter<File>>,
+    metadata: HashMap<String, OrderMetadata>,
+    handoffs: HashMap<String, Handoff>,
+    /// Unresolved intents, oldest first.
+    intents: Vec<QueuedIntent>,
+}
+
+impl JournalState {
+    fn apply(&mut self, record: JournalRecord) {
+        match record {
+            JournalRecord::Order(entry) => {
+                self.metadata.insert(entry.client_order_id, entry.metadata);
+            }
+            JournalRecord::Handoff(handoff) => {
+                self.handoffs.insert(handoff.strategy.clone(), handoff);
+            }
+            JournalRecord::Intent(intent) => {
+                self.intents
+                    .retain(|queued| queued.intent_id != intent.intent_id);
+                self.intents.push(intent);
+            }
+            JournalRecord::Resolved(resolved) => {
+                self.intents
+                    .retain(|queued| queued.intent_id != resolved.intent_id);
+            }
+        }
+    }
+
+    fn append(&mut self, record: JournalRecord) -> io::Result<()> {
+        if let Some(writer) = &mut self.writer {
+            serde_json::to_writer(&mut *writer, &record)?;
+            writer.write_all(b"\n")?;
+            writer.flush()?;
+        }
+        self.apply(record);
+        Ok(())
+    }
+}
+
+/// Local record of the metadata attached to submitted orders, keyed by client order id, of strategy handoffs
+/// and of orders queued during broker outages.
+/// When configured on a client, tags are recorded before submission and echoed back on every order it returns.
+#[derive(Clone)]
+pub struct OrderJournal {
+    state: Arc<Mutex<JournalState>>,
+    events: broadcast::Sender<JournalEvent>,
+}
+
+impl OrderJournal {
+    /// Journal that only lives as long as the process.
+    pub fn in_memory() -> Self {
+        OrderJournal {
+            state: Arc::new(Mutex::new(JournalState {
+                writer: None,
+                metadata: HashMap::new(),
+                handoffs: HashMap::new(),
+                intents: Vec::new(),
+            })),
+            events: broadcast::channel(EVENT_CAPACITY).0,
+        }
+    }
+
+    /// Appends to the JSONL file at `path`, creating it if needed. Records already in the file are loaded,
+    /// so tags, handoffs and queued orders survive restarts.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut state = JournalState {
+            writer: None,
+            metadata: HashMap::new(),
+            handoffs: HashMap::new(),
+            intents: Vec::new(),
+        };
+        if path.exists() {
+            for line in BufReader::new(File::open(path)?).lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                state.apply(serde_json::from_str(&line)?);
+            }
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        state.writer = Some(BufWriter::new(file));
+        Ok(OrderJournal {
+            state: Arc::new(Mutex::new(state)),
+            events: broadcast::channel(EVENT_CAPACITY).0,
+        })
+    }
+
+    /// Records the order's metadata. Orders without a client order id or without metadata are skipped,
+    /// since there would be nothing to match them back with.
+    pub fn record(&self, order: &Order) -> io::Result<()> {
+        let Some(client_order_id) = &order.client_order_id else {
+            return Ok(());
+        };
+        if order.metadata.is_empty() {
+            return Ok(());
+        }
+
+        self.append(JournalRecord::Order(JournalEntry {
+            client_order_id: client_order_id.clone(),
+            symbol: order.symbol.clone(),
+            metadata: order.metadata.clone(),
+            recorded_at: time::format_rfc3339(time::now_nanos()),
+        }))
+    }
+
+    pub fn metadata(&self, client_order_id: &str) -> Option<OrderMetadata> {
+        self.state
+            .lock()
+            .unwrap()
+            .metadata
+            .get(client_order_id)
+            .cloned()
+    }
+
+    /// Copies the recorded metadata onto an order returned by the broker.
+    pub fn annotate(&self, order: &mut OrderResponse) {
+        if let Some(metadata) = self.metadata(&order.client_order_id) {
+            order.metadata = metadata;
+        }
+    }
+
+    /// Saves a strategy's state for the instance that replaces it. Replaces any earlier handoff for the strategy.
+    pub fn record_handoff(
+        &self,
+        strategy: &str,
+        version: &str,
+        state: &impl Serialize,
+    ) -> io::Result<()> {
+        let state = serde_json::to_value(state)?;
+        self.append(JournalRecord::Handoff(Handoff {
+            strategy: strategy.to_string(),
+            version: version.to_string(),
+            state,
+            recorded_at: time::format_rfc3339(time::now_nanos()),
+        }))
+    }
+
+    /// Most recent handoff recorded for the strategy.
+    pub fn handoff(&self, strategy: &str) -> Option<Handoff> {
+        self.state.lock().unwrap().handoffs.get(strategy).cloned()
+    }
+
+    /// Queues an order that could not be sent. An intent with the same id replaces the earlier one.
+    pub fn record_intent(&self, intent: &QueuedIntent) -> io::Result<()> {
+        self.append(JournalRecord::Intent(intent.clone()))
+    }
+
+    /// Removes an intent from the queue.
+    pub fn resolve_intent(&self, intent_id: &str, resolution: IntentResolution) -> io::Result<()> {
+        self.append(JournalRecord::Resolved(ResolvedIntent {
+            intent_id: intent_id.to_string(),
+            resolution,
+            recorded_at: time::format_rfc3339(time::now_nanos()),
+        }))
+    }
+
+    /// Intents that have been neither resubmitted nor discarded, oldest first.
+    pub fn pending_intents(&self) -> Vec<QueuedIntent> {
+        self.state.lock().unwrap().intents.clone()
+    }
+
+    /// Events published from now on. Subscribers that fall far behind skip the oldest events.
+    pub fn subscribe(&self) -> broadcast::Receiver<JournalEvent> {
+        self.events.subscribe()
+    }
+
+    /// Passes an event to subscribers without writing it to the journal, e.g. fills and order updates.
+    pub fn publish(&self, event: JournalEvent) {
+        // Only fails when nobody is subscribed.
+        let _ = self.events.send(event);
+    }
+
+    fn append(&self, record: JournalRecord) -> io::Result<()> {
+        self.state.lock().unwrap().append(record.clone())?;
+        self.publish(record.into());
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Sink for OrderJournal {
+    /// Records are flushed as they are written, so this only syncs the file.
+    async fn flush_and_close(&self) -> Result<(), Box<dyn Error>> {
+        if let Some(writer) = &mut self.state.lock().unwrap().writer {
+            writer.flush()?;
+            writer.get_ref().sync_all()?;
+        }
+        Ok(())
+    }
+}
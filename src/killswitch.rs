@@ -0,0 +1,340 @@
+use crate::datastructures::{
+    client::TradingClient,
+    order::{CancelOutcome, Order, OrderSide, OrderStatus, OrderType, TimeInForce},
+};
+use futures_util::future::join_all;
+use std::{
+    error::Error,
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// How long an armed kill switch waits for `panic_close` before the token expires.
+const DEFAULT_ARM_WINDOW: Duration = Duration::from_secs(30);
+
+/// Metadata key and client order id prefix of the closing orders, so they can be told apart afterwards.
+pub const PANIC_TAG: &str = "panic_close";
+
+#[derive(Debug, Clone)]
+pub struct InvalidConfirmation;
+
+impl fmt::Display for InvalidConfirmation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Kill switch not armed, or the confirmation token is wrong or expired"
+        )
+    }
+}
+
+impl Error for InvalidConfirmation {}
+
+/// What `panic_close` did. Anything in the failure lists still needs attention by hand.
+#[derive(Debug, Default)]
+pub struct PanicReport {
+    /// Orders confirmed closed, whether by the cancel or otherwise.
+    pub cancelled: Vec<CancelOutcome>,
+    /// Order id and error of every cancel that failed, including orders still working when the cancel could
+    /// not be confirmed.
+    pub cancel_failures: Vec<(String, String)>,
+    /// Market orders sent to close positions that the broker reported as accepted or filled.
+    pub closing_orders: Vec<Order>,
+    /// Symbol and error of every position that could not be closed, including closing orders the broker
+    /// rejected after taking them or whose status could not be read back.
+    pub close_failures: Vec<(String, String)>,
+}
+
+impl PanicReport {
+    pub fn is_complete(&self) -> bool {
+        self.cancel_failures.is_empty() && self.close_failures.is_empty()
+    }
+}
+
+struct Armed {
+    token: String,
+    at: Instant,
+}
+
+/// Cancels every open order and market-closes every position in one call. Two steps guard against a stray
+/// call: `arm` hands out a token, and `panic_close` only runs when given that token before it expires.
+#[derive(Clone)]
+pub struct KillSwitch<C> {
+    client: C,
+    arm_window: Duration,
+    armed: Arc<Mutex<Option<Armed>>>,
+}
+
+impl<C: TradingClient + Send + Sync> KillSwitch<C> {
+    pub fn new(client: C) -> Self {
+        KillSwitch {
+            client,
+            arm_window: DEFAULT_ARM_WINDOW,
+            armed: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// How long a token stays valid. Defaults to 30 seconds.
+    pub fn arm_window(mut self, arm_window: Duration) -> Self {
+        self.arm_window = arm_window;
+        self
+    }
+
+    /// Arms the switch and returns the token `panic_close` must be given. Re-arming replaces the token.
+    pub fn arm(&self) -> String {
+        let token = format!("{:016x}", rand::random::<u64>());
+        tracing::warn!("Kill switch armed");
+        *self.armed.lock().unwrap() = Some(Armed {
+            token: token.clone(),
+            at: Instant::now(),
+        });
+        token
+    }
+
+    pub fn disarm(&self) {
+        self.armed.lock().unwrap().take();
+    }
+
+    /// Cancels all open orders, waiting for each to reach a final state, then sends a market order closing
+    /// each remaining position and reads it back to confirm the broker accepted it. Failures are collected in
+    /// the report rather than stopping the run. The token is used up either way.
+    pub async fn panic_close(&self, confirmation: &str) -> Result<PanicReport, Box<dyn Error>> {
+        {
+            let mut armed = self.armed.lock().unwrap();
+            let valid = armed.as_ref().is_some_and(|armed| {
+                armed.token == confirmation && armed.at.elapsed() <= self.arm_window
+            });
+            armed.take();
+            if !valid {
+                return Err(InvalidConfirmation.into());
+            }
+        }
+        tracing::error!("Kill switch triggered: cancelling all orders and flattening");
+
+        let mut report = PanicReport::default();
+        let open = self.client.get_open_orders().await?;
+        let cancels = join_all(open.iter().map(|order| async {
+            let result = self
+                .client
+                .cancel_order(&order.id)
+                .await
+                .map_err(|e| e.to_string());
+            (order.id.clone(), result)
+        }))
+        .await;
+        for (order_id, result) in cancels {
+            match result {
+                Ok(CancelOutcome::Unconfirmed(order)) => {
+                    let e = format!("Cancel not confirmed, order still {:?}", order.status);
+                    tracing::error!(%order_id, error = %e, "Kill switch failed to cancel order");
+                    report.cancel_failures.push((order_id, e));
+                }
+                Ok(outcome) => report.cancelled.push(outcome),
+                Err(e) => {
+                    tracing::error!(%order_id, error = %e, "Kill switch failed to cancel order");
+                    report.cancel_failures.push((order_id, e));
+                }
+            }
+        }
+
+        // Read positions after the cancels, since orders may have filled on the way out.
+        let positions = self.client.get_positions().await?;
+        for position in positions {
+            if position.qty == 0.0 {
+                continue;
+            }
+            let order = Order::builder()
+                .symbol(position.symbol.clone())
+                .quantity(position.qty.abs())
+                .side(if position.qty > 0.0 {
+                    OrderSide::Sell
+                } else {
+                    OrderSide::Buy
+                })
+                .order_type(OrderType::Market)
+                .time_in_force(if position.asset_class == "crypto" {
+                    TimeInForce::Gtc
+                } else {
+                    TimeInForce::Day
+                })
+                .client_order_id(format!("{}-{:016x}", PANIC_TAG, rand::random::<u64>()))
+                .tag(PANIC_TAG, "true")
+                .build()?;
+            match self.close(&order).await {
+                Ok(()) => report.closing_orders.push(order),
+                Err(e) => {
+                    tracing::error!(symbol = %position.symbol, error = %e, "Kill switch failed to close position");
+                    report.close_failures.push((position.symbol, e));
+                }
+            }
+        }
+
+        tracing::warn!(
+            cancelled = report.cancelled.len(),
+            closing = report.closing_orders.len(),
+            failures = report.cancel_failures.len() + report.close_failures.len(),
+            "Kill switch finished"
+        );
+        Ok(report)
+    }
+
+    /// Sends a closing order and checks the broker took it. Some brokers accept an order and reject it a
+    /// moment later, e.g. for buying power, so a successful submission alone does not mean it is working.
+    async fn close(&self, order: &Order) -> Result<(), String> {
+        self.client
+            .create_order(order)
+            .await
+            .map_err(|e| e.to_string())?;
+        // Always set by `panic_close`.
+        let client_order_id = order.client_order_id.as_deref().unwrap_or_default();
+        let placed = self
+            .client
+            .get_order_by_client_id(client_order_id)
+            .await
+            .map_err(|e| format!("Closing order sent but not confirmed: {}", e))?;
+        if placed.status.is_terminal() && placed.status != OrderStatus::Filled {
+            return Err(format!("Closing order ended {:?}", placed.status));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        datastructures::account::Position,
+        testing::{MockCall, MockOrder, MockTradingClient},
+    };
+
+    fn position(symbol: &str, qty: f64) -> Position {
+        Position {
+            symbol: symbol.to_string(),
+            exchange: "NASDAQ".to_string(),
+            asset_class: "us_equity".to_string(),
+            qty,
+            avg_entry_price: 100.0,
+            market_value: qty * 100.0,
+            current_price: Some(100.0),
+            unrealized_pl: 0.0,
+        }
+    }
+
+    #[tokio::test]
+    async fn needs_the_armed_token() {
+        let kill_switch = KillSwitch::new(MockTradingClient::with_cash(0.0));
+        assert!(kill_switch.panic_close("guess").await.is_err());
+        let token = kill_switch.arm();
+        kill_switch.disarm();
+        assert!(kill_switch.panic_close(&token).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn cancels_orders_and_closes_positions() {
+        let client = MockTradingClient::with_cash(10_000.0);
+        client
+            .create_order(
+                &Order::builder()
+                    .symbol("MSFT")
+                    .quantity(1.0)
+                    .side(OrderSide::Buy)
+                    .order_type(OrderType::Limit)
+                    .limit_price(300.0)
+                    .build()
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        client.set_position(position("AAPL", 5.0));
+        client.set_position(position("TSLA", -2.0));
+        client.push_order(MockOrder::Fill { price: 100.0 });
+        client.push_order(MockOrder::Rest);
+        let kill_switch = KillSwitch::new(client.clone());
+
+        let report = kill_switch.panic_close(&kill_switch.arm()).await.unwrap();
+        assert!(report.is_complete());
+        assert_eq!(report.cancelled.len(), 1);
+        let closing: Vec<_> = report
+            .closing_orders
+            .iter()
+            .map(|order| (order.symbol.as_str(), order.side, order.quantity))
+            .collect();
+        assert_eq!(
+            closing,
+            [
+                ("AAPL", OrderSide::Sell, 5.0),
+                ("TSLA", OrderSide::Buy, 2.0)
+            ]
+        );
+        assert!(report.closing_orders[0]
+            .client_order_id
+            .as_deref()
+            .unwrap()
+            .starts_with(PANIC_TAG));
+    }
+
+    #[tokio::test]
+    async fn reports_cancels_it_cannot_confirm() {
+        let client = MockTradingClient::with_cash(10_000.0);
+        for _ in 0..2 {
+            client
+                .create_order(
+                    &Order::builder()
+                        .symbol("MSFT")
+                        .quantity(1.0)
+                        .side(OrderSide::Buy)
+                        .order_type(OrderType::Limit)
+                        .limit_price(300.0)
+                        .build()
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+        }
+        client.hold("mock-2");
+        let kill_switch = KillSwitch::new(client.clone());
+
+        let report = kill_switch.panic_close(&kill_switch.arm()).await.unwrap();
+        assert!(!report.is_complete());
+        assert_eq!(report.cancelled.len(), 1);
+        assert_eq!(report.cancel_failures[0].0, "mock-2");
+        assert!(report.cancel_failures[0].1.contains("not confirmed"));
+    }
+
+    #[tokio::test]
+    async fn reports_closing_orders_the_broker_rejects() {
+        let client = MockTradingClient::with_cash(10_000.0);
+        client.set_position(position("AAPL", 5.0));
+        client.set_position(position("MSFT", 1.0));
+        client.set_position(position("TSLA", 2.0));
+        client.push_order(MockOrder::Reject("insufficient qty".to_string()));
+        client.push_order(MockOrder::LateReject);
+        client.push_order(MockOrder::Rest);
+        let kill_switch = KillSwitch::new(client.clone());
+
+        let report = kill_switch.panic_close(&kill_switch.arm()).await.unwrap();
+        assert!(!report.is_complete());
+        assert_eq!(report.closing_orders.len(), 1);
+        assert_eq!(report.closing_orders[0].symbol, "TSLA");
+        let failed: Vec<_> = report
+            .close_failures
+            .iter()
+            .map(|(symbol, _)| symbol.as_str())
+            .collect();
+        assert_eq!(failed, ["AAPL", "MSFT"]);
+        assert!(report.close_failures[1].1.contains("Rejected"));
+    }
+
+    #[tokio::test]
+    async fn reports_closing_orders_it_cannot_confirm() {
+        let client = MockTradingClient::with_cash(10_000.0);
+        client.set_position(position("AAPL", 5.0));
+        client.fail(MockCall::GetOrder, "timed out");
+        let kill_switch = KillSwitch::new(client.clone());
+
+        let report = kill_switch.panic_close(&kill_switch.arm()).await.unwrap();
+        assert!(report.closing_orders.is_empty());
+        assert_eq!(client.submitted().len(), 1);
+        assert!(report.close_failures[0].1.contains("not confirmed"));
+    }
+}
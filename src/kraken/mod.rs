@@ -0,0 +1,867 @@
+mod stream;
+
+use crate::{
+    datastructures::{
+        account::{Account, Position},
+        asset::Asset,
+        client::{FeedType, MarketDataClient, SubscriptionParams, TradingClient},
+        config::Config,
+        event::{EventBatch, ParseMode},
+        market::{Bar, BarAdjustment, Quote},
+        order::{
+            CancelOutcome, Order, OrderResponse, OrderSide, OrderStatus, OrderType, TimeInForce,
+        },
+    },
+    http::{signing, RestPolicies},
+    journal::OrderJournal,
+    store::{self, OrderStore},
+    time,
+};
+use async_trait::async_trait;
+use futures_util::SinkExt;
+use reqwest::{header::CONTENT_TYPE, Client as HttpClient, Method};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc, Mutex,
+    },
+};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{
+    connect_async, tungstenite::protocol::Message, MaybeTlsStream, WebSocketStream,
+};
+
+/// Docs: https://docs.kraken.com/api/docs/rest-api/add-order
+const REST_URL: &str = "https://api.kraken.com";
+/// Docs: https://docs.kraken.com/api/docs/websocket-v2/trade
+const STREAM_URL: &str = "wss://ws.kraken.com/v2";
+
+/// Kraken's error for cancelling an order that is unknown or no longer open.
+const UNKNOWN_ORDER: &str = "EOrder:Unknown order";
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+
+/// Error response of the Kraken REST API.
+#[derive(Debug, Clone)]
+pub struct KrakenError {
+    pub status: u16,
+    /// Kraken's errors, e.g. "EOrder:Insufficient funds".
+    pub errors: Vec<String>,
+}
+
+impl fmt::Display for KrakenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Kraken error ({}): {}",
+            self.status,
+            self.errors.join(", ")
+        )
+    }
+}
+
+impl Error for KrakenError {}
+
+#[derive(Deserialize)]
+struct Envelope<T> {
+    #[serde(default)]
+    error: Vec<String>,
+    result: Option<T>,
+}
+
+#[derive(Deserialize)]
+struct RawPair {
+    altname: String,
+    #[serde(default)]
+    wsname: String,
+    base: String,
+    quote: String,
+    #[serde(default)]
+    status: String,
+}
+
+#[derive(Deserialize)]
+struct RawDescription {
+    pair: String,
+    #[serde(rename = "type")]
+    side: String,
+    ordertype: String,
+    price: String,
+    price2: String,
+}
+
+#[derive(Deserialize)]
+struct RawOrder {
+    #[serde(default)]
+    cl_ord_id: Option<String>,
+    status: String,
+    opentm: f64,
+    descr: RawDescription,
+    vol: String,
+    vol_exec: String,
+    /// Average fill price.
+    price: String,
+}
+
+#[derive(Deserialize)]
+struct AddedOrder {
+    #[serde(default)]
+    txid: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct RawBalance {
+    balance: String,
+    #[serde(default)]
+    hold_trade: String,
+}
+
+#[derive(Deserialize)]
+struct RawTicker {
+    /// Ask price, whole lot volume and lot volume.
+    a: Vec<String>,
+    /// Bid price, whole lot volume and lot volume.
+    b: Vec<String>,
+    /// Last trade price and volume.
+    c: Vec<String>,
+}
+
+/// Kraken sends most numbers as strings.
+fn decimal(value: &str) -> f64 {
+    value.parse().unwrap_or(0.0)
+}
+
+/// Asset as the crate writes it. Kraken still calls bitcoin "XBT" and dogecoin "XDG" in places.
+fn asset_name(asset: &str) -> &str {
+    match asset {
+        "XBT" => "BTC",
+        "XDG" => "DOGE",
+        asset => asset,
+    }
+}
+
+/// Symbol of a pair from its WebSocket name, e.g. "BTC/USD" for "XBT/USD".
+fn pair_symbol(wsname: &str) -> Option<String> {
+    let (base, quote) = wsname.split_once('/')?;
+    Some(format!("{}/{}", asset_name(base), asset_name(quote)))
+}
+
+fn order_status(status: &str, filled_qty: f64) -> OrderStatus {
+    match status {
+        "pending" => OrderStatus::PendingNew,
+        "open" if filled_qty > 0.0 => OrderStatus::PartiallyFilled,
+        "closed" => OrderStatus::Filled,
+        "canceled" => OrderStatus::Canceled,
+        "expired" => OrderStatus::Expired,
+        _ => OrderStatus::New,
+    }
+}
+
+fn order_type(order_type: &str) -> OrderType {
+    match order_type {
+        "limit" => OrderType::Limit,
+        "stop-loss" | "take-profit" => OrderType::Stop,
+        "stop-loss-limit" | "take-profit-limit" => OrderType::StopLimit,
+        "trailing-stop" | "trailing-stop-limit" => OrderType::TrailingStop,
+        _ => OrderType::Market,
+    }
+}
+
+impl RawOrder {
+    fn into_response(self, id: String, symbol: String) -> OrderResponse {
+        let filled_qty = decimal(&self.vol_exec);
+        let positive = |value: f64| (value > 0.0).then_some(value);
+        let order_type = order_type(&self.descr.ordertype);
+        let (price, price2) = (decimal(&self.descr.price), decimal(&self.descr.price2));
+        // Stop orders carry their trigger as the price, and a limit as the second price.
+        let (limit_price, stop_price) = match order_type {
+            OrderType::Limit => (positive(price), None),
+            OrderType::Stop => (None, positive(price)),
+            OrderType::StopLimit => (positive(price2), positive(price)),
+            _ => (None, None),
+        };
+        OrderResponse {
+            id,
+            client_order_id: self.cl_ord_id.unwrap_or_default(),
+            symbol,
+            status: order_status(&self.status, filled_qty),
+            created_at: time::format_rfc3339((self.opentm * NANOS_PER_SECOND as f64) as i64),
+            side: match self.descr.side.as_str() {
+                "sell" => OrderSide::Sell,
+                _ => OrderSide::Buy,
+            },
+            order_type,
+            qty: Some(decimal(&self.vol)),
+            filled_qty,
+            filled_avg_price: positive(filled_qty).and_then(|_| positive(decimal(&self.price))),
+            limit_price,
+            stop_price,
+            metadata: Default::default(),
+        }
+    }
+}
+
+/// Kraken's names for its pairs and assets, and the crate's.
+#[derive(Default)]
+struct Pairs {
+    /// Symbol of each pair by Kraken pair name and alternative name, e.g. "XXBTZUSD" and "XBTUSD" to "BTC/USD".
+    symbols: HashMap<String, String>,
+    /// Kraken pair name of each symbol.
+    names: HashMap<String, String>,
+    /// Asset of each Kraken asset code, e.g. "XXBT" to "BTC".
+    assets: HashMap<String, String>,
+}
+
+/// Client for Kraken spot. Symbols are written as pairs, as Alpaca writes crypto and Kraken's WebSocket v2 feed
+/// does, e.g. "BTC/USD", and translated to Kraken's pair names for REST calls. Order ids are Kraken's
+/// transaction ids. Client order ids must be UUIDs or at most 18 characters. Kraken has no spot sandbox, so
+/// unless real trading is enabled orders are only validated by Kraken, not placed. Applies the config's REST
+/// policies, journal, order store and pre-trade checks like `AlpacaClient`.
+#[derive(Clone)]
+pub struct KrakenClient {
+    http_client: HttpClient,
+    rest: RestPolicies,
+    api_key: String,
+    /// Decoded from the config's base64. `None` if it is missing or not base64.
+    secret_key: Option<Vec<u8>>,
+    validate_only: bool,
+    quote_asset: String,
+    journal: Option<OrderJournal>,
+    order_store: Option<Arc<dyn OrderStore>>,
+    /// Loaded from the asset pairs on first use.
+    pairs: Arc<Mutex<Pairs>>,
+    /// Private calls need a nonce larger than any before it for the same key.
+    last_nonce: Arc<AtomicI64>,
+    candles: Arc<stream::Candles>,
+}
+
+impl KrakenClient {
+    /// Client with the config's Kraken keys.
+    pub fn new(config: &Config) -> Result<Self, reqwest::Error> {
+        Ok(KrakenClient {
+            http_client: config.http.build_client()?,
+            rest: RestPolicies::new(config),
+            api_key: config.kraken_api_key.clone().unwrap_or_default(),
+            secret_key: config
+                .kraken_secret_key
+                .as_deref()
+                .and_then(signing::base64_decode),
+            validate_only: !config.enable_real_trading,
+            quote_asset: "USD".to_string(),
+            journal: config.journal.clone(),
+            order_store: config.order_store.clone(),
+            pairs: Arc::new(Mutex::new(Pairs::default())),
+            last_nonce: Arc::new(AtomicI64::new(0)),
+            candles: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// Asset the account is valued in and positions are priced against. Defaults to USD.
+    pub fn with_quote_asset(mut self, quote_asset: impl Into<String>) -> Self {
+        self.quote_asset = quote_asset.into();
+        self
+    }
+
+    /// Microseconds since the epoch, or one more than the last nonce if the clock has not moved on.
+    fn nonce(&self) -> i64 {
+        let now = time::now_nanos() / 1_000;
+        let last = self
+            .last_nonce
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| {
+                Some(now.max(last + 1))
+            })
+            .unwrap();
+        now.max(last + 1)
+    }
+
+    /// Private calls are POSTed and signed afresh, with a new nonce, for every attempt. Only `idempotent` calls
+    /// are retried.
+    async fn request<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        params: &[(&str, String)],
+        private: bool,
+        idempotent: bool,
+    ) -> Result<T, Box<dyn Error>> {
+        let path = format!(
+            "/0/{}/{}",
+            if private { "private" } else { "public" },
+            endpoint
+        );
+        let url = format!("{}{}", REST_URL, path);
+        let secret_key = match (&self.secret_key, private) {
+            (None, true) => return Err("Kraken secret key is missing or not base64".into()),
+            (secret_key, _) => secret_key,
+        };
+        let build = || {
+            let mut form = url::form_urlencoded::Serializer::new(String::new());
+            let nonce = private.then(|| self.nonce());
+            if let Some(nonce) = nonce {
+                form.append_pair("nonce", &nonce.to_string());
+            }
+            for (name, value) in params {
+                form.append_pair(name, value);
+            }
+            let form = form.finish();
+
+            let request = match (nonce, secret_key) {
+                (Some(nonce), Some(secret_key)) => {
+                    let mut message = path.as_bytes().to_vec();
+                    message.extend_from_slice(&signing::sha256(
+                        format!("{}{}", nonce, form).as_bytes(),
+                    ));
+                    let signature = signing::hmac_sha512(secret_key, &message);
+                    self.http_client
+                        .request(Method::POST, &url)
+                        .header("API-Key", &self.api_key)
+                        .header("API-Sign", signing::base64_encode(&signature))
+                        .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                        .body(form)
+                }
+                _ if form.is_empty() => self.http_client.request(Method::GET, &url),
+                _ => self
+                    .http_client
+                    .request(Method::GET, format!("{}?{}", url, form)),
+            };
+            Ok(request.build()?)
+        };
+        let response = self.rest.send(&self.http_client, idempotent, build).await?;
+
+        let status = response.status();
+        let body = response.text().await?;
+        let envelope: Envelope<T> = match serde_json::from_str(&body) {
+            Ok(envelope) => envelope,
+            Err(_) if !status.is_success() => {
+                return Err(KrakenError {
+                    status: status.as_u16(),
+                    errors: vec![body],
+                }
+                .into())
+            }
+            Err(e) => return Err(e.into()),
+        };
+        // Warnings start with "W" and come with a result.
+        let errors: Vec<String> = envelope
+            .error
+            .into_iter()
+            .filter(|error| error.starts_with('E'))
+            .collect();
+        match envelope.result {
+            Some(result) if errors.is_empty() && status.is_success() => Ok(result),
+            _ => Err(KrakenError {
+                status: status.as_u16(),
+                errors,
+            }
+            .into()),
+        }
+    }
+
+    /// Docs: https://docs.kraken.com/api/docs/rest-api/get-tradable-asset-pairs
+    async fn asset_pairs(&self, name: Option<&str>) -> Result<Vec<RawPair>, Box<dyn Error>> {
+        let params: Vec<(&str, String)> = name
+            .map(|name| ("pair", name.to_string()))
+            .into_iter()
+            .collect();
+        let raw: HashMap<String, RawPair> =
+            self.request("AssetPairs", &params, false, true).await?;
+        let mut pairs = self.pairs.lock().unwrap();
+        let mut found = Vec::new();
+        for (name, pair) in raw {
+            let Some(symbol) = pair_symbol(&pair.wsname) else {
+                continue;
+            };
+            if let Some((base, quote)) = symbol.split_once('/') {
+                pairs.assets.insert(pair.base.clone(), base.to_string());
+                pairs.assets.insert(pair.quote.clone(), quote.to_string());
+            }
+            pairs.symbols.insert(name.clone(), symbol.clone());
+            pairs.symbols.insert(pair.altname.clone(), symbol.clone());
+            pairs.names.insert(symbol, name);
+            found.push(pair);
+        }
+        Ok(found)
+    }
+
+    /// Loads the names of every pair and asset, once.
+    async fn load_pairs(&self) -> Result<(), Box<dyn Error>> {
+        if self.pairs.lock().unwrap().names.is_empty() {
+            self.asset_pairs(None).await?;
+        }
+        Ok(())
+    }
+
+    /// Kraken's name for the pair `symbol`, e.g. "XXBTZUSD" for "BTC/USD".
+    async fn pair_name(&self, symbol: &str) -> Result<String, Box<dyn Error>> {
+        self.load_pairs().await?;
+        self.pairs
+            .lock()
+            .unwrap()
+            .names
+            .get(symbol)
+            .cloned()
+            .ok_or_else(|| format!("Unknown Kraken pair: {}", symbol).into())
+    }
+
+    /// Symbol of a Kraken pair name, or the name itself if it is not known.
+    fn symbol(&self, name: &str) -> String {
+        self.pairs
+            .lock()
+            .unwrap()
+            .symbols
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| name.to_string())
+    }
+
+    /// Asset of a Kraken asset code. Codes of assets without pairs are kept, less any legacy "X" or "Z" prefix.
+    fn asset(&self, code: &str) -> String {
+        if let Some(asset) = self.pairs.lock().unwrap().assets.get(code) {
+            return asset.clone();
+        }
+        let legacy = code.len() == 4 && code.starts_with(['X', 'Z']);
+        asset_name(if legacy { &code[1..] } else { code }).to_string()
+    }
+
+    fn to_responses(&self, raw: HashMap<String, RawOrder>) -> Vec<OrderResponse> {
+        let mut orders: Vec<OrderResponse> = raw
+            .into_iter()
+            .map(|(id, raw)| {
+                let symbol = self.symbol(&raw.descr.pair);
+                let mut order = raw.into_response(id, symbol);
+                if let Some(journal) = &self.journal {
+                    journal.annotate(&mut order);
+                }
+                order
+            })
+            .collect();
+        orders.sort_by_key(|order| time::parse_rfc3339(&order.created_at));
+        orders
+    }
+
+    /// Docs: https://docs.kraken.com/api/docs/rest-api/get-open-orders
+    async fn open_orders(
+        &self,
+        params: &[(&str, String)],
+    ) -> Result<Vec<OrderResponse>, Box<dyn Error>> {
+        #[derive(Deserialize)]
+        struct OpenOrders {
+            open: HashMap<String, RawOrder>,
+        }
+        self.load_pairs().await?;
+        let raw: OpenOrders = self.request("OpenOrders", params, true, true).await?;
+        Ok(self.to_responses(raw.open))
+    }
+
+    /// Balance of each asset, with the part held for open orders.
+    /// Docs: https://docs.kraken.com/api/docs/rest-api/get-extended-balance
+    async fn balances(&self) -> Result<Vec<(String, f64, f64)>, Box<dyn Error>> {
+        self.load_pairs().await?;
+        let raw: HashMap<String, RawBalance> = self.request("BalanceEx", &[], true, true).await?;
+        Ok(raw
+            .into_iter()
+            // Staked and earning balances, e.g. "DOT.S", cannot be traded.
+            .filter(|(code, _)| !code.contains('.'))
+            .map(|(code, balance)| {
+                (
+                    self.asset(&code),
+                    decimal(&balance.balance),
+                    decimal(&balance.hold_trade),
+                )
+            })
+            .collect())
+    }
+
+    /// Last price of every asset quoted in the quote asset, by base asset.
+    /// Docs: https://docs.kraken.com/api/docs/rest-api/get-ticker-information
+    async fn prices(&self) -> Result<HashMap<String, f64>, Box<dyn Error>> {
+        self.load_pairs().await?;
+        let tickers: HashMap<String, RawTicker> = self.request("Ticker", &[], false, true).await?;
+        let suffix = format!("/{}", self.quote_asset);
+        Ok(tickers
+            .into_iter()
+            .filter_map(|(name, ticker)| {
+                let symbol = self.symbol(&name);
+                let base = symbol.strip_suffix(&suffix)?;
+                Some((base.to_string(), decimal(ticker.c.first()?)))
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl TradingClient for KrakenClient {
+    /// Docs: https://docs.kraken.com/api/docs/rest-api/add-order
+    /// Day orders are sent good til cancelled, as crypto trades around the clock. Trailing stops, fill or kill
+    /// and opening or closing auction orders are not supported.
+    async fn create_order(&self, order: &Order) -> Result<(), Box<dyn Error>> {
+        let order_type = match order.order_type {
+            OrderType::Market => "market",
+            OrderType::Limit => "limit",
+            OrderType::Stop => "stop-loss",
+            OrderType::StopLimit => "stop-loss-limit",
+            OrderType::TrailingStop => {
+                return Err("Trailing stops are not supported for Kraken".into())
+            }
+        };
+        let time_in_force = match order.time_in_force {
+            TimeInForce::Day | TimeInForce::Gtc => "GTC",
+            TimeInForce::Ioc => "IOC",
+            TimeInForce::Fok => return Err("Kraken spot has no fill or kill orders".into()),
+            TimeInForce::Opg | TimeInForce::Cls => {
+                return Err("Kraken has no opening or closing auction".into())
+            }
+        };
+        let mut params = vec![
+            ("pair", self.pair_name(&order.symbol).await?),
+            (
+                "type",
+                match order.side {
+                    OrderSide::Buy => "buy",
+                    OrderSide::Sell => "sell",
+                }
+                .to_string(),
+            ),
+            ("ordertype", order_type.to_string()),
+            ("volume", order.quantity.to_string()),
+        ];
+        if matches!(order.order_type, OrderType::Limit | OrderType::StopLimit) {
+            params.push(("timeinforce", time_in_force.to_string()));
+        }
+        match order.order_type {
+            OrderType::Stop | OrderType::StopLimit => {
+                if let Some(stop_price) = order.stop_price {
+                    params.push(("price", stop_price.to_string()));
+                }
+                if let Some(limit_price) = order.limit_price {
+                    params.push(("price2", limit_price.to_string()));
+                }
+            }
+            _ => {
+                if let Some(limit_price) = order.limit_price {
+                    params.push(("price", limit_price.to_string()));
+                }
+            }
+        }
+        if let Some(client_order_id) = &order.client_order_id {
+            params.push(("cl_ord_id", client_order_id.clone()));
+        }
+        if self.validate_only {
+            params.push(("validate", "true".to_string()));
+        }
+
+        tracing::debug!(?order, "Submitting order");
+        if let Some(journal) = &self.journal {
+            journal.record(order)?;
+        }
+        let added: AddedOrder = {
+            let result = self
+                .request("AddOrder", &params, true, order.client_order_id.is_some())
+                .await;
+            #[cfg(feature = "metrics")]
+            {
+                let metrics = crate::metrics::registry();
+                metrics.orders_submitted.inc();
+                if result.is_err() {
+                    metrics.orders_rejected.inc();
+                }
+            }
+            result?
+        };
+
+        let Some(id) = added.txid.into_iter().next() else {
+            tracing::info!(
+                ?order,
+                "Order validated but not placed, as real trading is not enabled"
+            );
+            return Ok(());
+        };
+        if let Some(store) = &self.order_store {
+            // Kraken only answers with the order's id, so the rest is as submitted.
+            let accepted = OrderResponse {
+                id,
+                client_order_id: order.client_order_id.clone().unwrap_or_default(),
+                symbol: order.symbol.clone(),
+                status: OrderStatus::New,
+                created_at: time::format_rfc3339(time::now_nanos()),
+                side: order.side,
+                order_type: order.order_type,
+                qty: Some(order.quantity),
+                filled_qty: 0.0,
+                filled_avg_price: None,
+                limit_price: order.limit_price,
+                stop_price: order.stop_price,
+                metadata: order.metadata.clone(),
+            };
+            if let Err(e) = store.record_submission(&accepted).await {
+                tracing::error!(error = %e, "Failed to store submitted order");
+            }
+        }
+        Ok(())
+    }
+
+    async fn get_open_orders(&self) -> Result<Vec<OrderResponse>, Box<dyn Error>> {
+        let orders = self.open_orders(&[]).await?;
+        for order in &orders {
+            store::record_logged(&self.order_store, order).await;
+        }
+        Ok(orders)
+    }
+
+    /// Docs: https://docs.kraken.com/api/docs/rest-api/get-orders-info
+    async fn get_order(&self, order_id: &str) -> Result<OrderResponse, Box<dyn Error>> {
+        self.load_pairs().await?;
+        let params = [("txid", order_id.to_string())];
+        let raw: HashMap<String, RawOrder> =
+            self.request("QueryOrders", &params, true, true).await?;
+        let order = self
+            .to_responses(raw)
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("Unknown order id: {}", order_id))?;
+        store::record_logged(&self.order_store, &order).await;
+        Ok(order)
+    }
+
+    /// Docs: https://docs.kraken.com/api/docs/rest-api/get-closed-orders
+    /// Looks through open orders first, then closed ones.
+    async fn get_order_by_client_id(
+        &self,
+        client_order_id: &str,
+    ) -> Result<OrderResponse, Box<dyn Error>> {
+        #[derive(Deserialize)]
+        struct ClosedOrders {
+            closed: HashMap<String, RawOrder>,
+        }
+        let params = [("cl_ord_id", client_order_id.to_string())];
+        let open = self.open_orders(&params).await?;
+        let order = match open.into_iter().next() {
+            Some(order) => order,
+            None => {
+                let raw: ClosedOrders = self.request("ClosedOrders", &params, true, true).await?;
+                self.to_responses(raw.closed)
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| format!("Unknown client order id: {}", client_order_id))?
+            }
+        };
+        store::record_logged(&self.order_store, &order).await;
+        Ok(order)
+    }
+
+    /// Docs: https://docs.kraken.com/api/docs/rest-api/cancel-order
+    /// Kraken answers with a count only, so the order is read back for the outcome.
+    async fn cancel_order(&self, order_id: &str) -> Result<CancelOutcome, Box<dyn Error>> {
+        let params = [("txid", order_id.to_string())];
+        if let Err(e) = self
+            .request::<Value>("CancelOrder", &params, true, true)
+            .await
+        {
+            let unknown = e
+                .downcast_ref::<KrakenError>()
+                .is_some_and(|e| e.errors.iter().any(|error| error == UNKNOWN_ORDER));
+            // Usually already filled or cancelled. Its actual state decides the outcome.
+            if !unknown {
+                return Err(e);
+            }
+        }
+        Ok(CancelOutcome::from_order(self.get_order(order_id).await?))
+    }
+
+    async fn get_asset(&self, symbol: &str) -> Result<Asset, Box<dyn Error>> {
+        let name = self.pair_name(symbol).await?;
+        let raw = self
+            .asset_pairs(Some(&name))
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("Unknown symbol: {}", symbol))?;
+        Ok(to_asset(raw))
+    }
+
+    /// Every spot pair currently trading.
+    async fn list_assets(&self) -> Result<Vec<Asset>, Box<dyn Error>> {
+        Ok(self
+            .asset_pairs(None)
+            .await?
+            .into_iter()
+            .filter(|raw| raw.status == "online")
+            .map(to_asset)
+            .collect())
+    }
+
+    /// Valued in the quote asset at the last price of each balance's pair against it. Balances without such a
+    /// pair, and staked balances, are left out of equity. Kraken does not report the previous close or an
+    /// account id, so `last_equity` is equity and the id is empty.
+    async fn get_account(&self) -> Result<Account, Box<dyn Error>> {
+        let balances = self.balances().await?;
+        let prices = self.prices().await?;
+        let mut cash = 0.0;
+        let mut free_cash = 0.0;
+        let mut long_market_value = 0.0;
+        for (asset, total, held) in &balances {
+            if *asset == self.quote_asset {
+                cash = *total;
+                free_cash = total - held;
+            } else if let Some(price) = prices.get(asset) {
+                long_market_value += total * price;
+            }
+        }
+        let equity = cash + long_market_value;
+        Ok(Account {
+            id: String::new(),
+            status: "ACTIVE".to_string(),
+            currency: self.quote_asset.clone(),
+            cash,
+            equity,
+            last_equity: equity,
+            buying_power: free_cash,
+            long_market_value,
+            short_market_value: 0.0,
+            non_marginable_buying_power: Some(free_cash),
+        })
+    }
+
+    /// Every balance other than the quote asset that has a pair against it, as a position in that pair. Kraken
+    /// does not track cost basis, so the entry price and unrealized P&L are zero.
+    async fn get_positions(&self) -> Result<Vec<Position>, Box<dyn Error>> {
+        let balances = self.balances().await?;
+        let prices = self.prices().await?;
+        Ok(balances
+            .into_iter()
+            .filter(|(asset, _, _)| *asset != self.quote_asset)
+            .filter_map(|(asset, qty, _)| {
+                let price = *prices.get(&asset)?;
+                (qty > 0.0).then(|| Position {
+                    symbol: format!("{}/{}", asset, self.quote_asset),
+                    exchange: "KRAKEN".to_string(),
+                    asset_class: "crypto".to_string(),
+                    qty,
+                    avg_entry_price: 0.0,
+                    market_value: qty * price,
+                    current_price: Some(price),
+                    unrealized_pl: 0.0,
+                })
+            })
+            .collect())
+    }
+}
+
+fn to_asset(raw: RawPair) -> Asset {
+    let trading = raw.status == "online";
+    Asset {
+        symbol: pair_symbol(&raw.wsname).unwrap_or(raw.altname),
+        exchange: "KRAKEN".to_string(),
+        asset_class: "crypto".to_string(),
+        status: if trading { "active" } else { "inactive" }.to_string(),
+        tradable: trading,
+        shortable: false,
+        fractionable: true,
+        attributes: vec![],
+    }
+}
+
+#[async_trait]
+impl MarketDataClient for KrakenClient {
+    /// Docs: https://docs.kraken.com/api/docs/rest-api/get-ticker-information
+    /// Kraken does not timestamp the top of book, so the quote is stamped when it arrives.
+    async fn get_latest_quote(&self, symbol: &str) -> Result<Quote, Box<dyn Error>> {
+        let params = [("pair", self.pair_name(symbol).await?)];
+        let tickers: HashMap<String, RawTicker> =
+            self.request("Ticker", &params, false, true).await?;
+        let ticker = tickers
+            .into_values()
+            .next()
+            .ok_or_else(|| format!("No ticker for {}", symbol))?;
+        let field = |side: &[String], i: usize| side.get(i).map_or(0.0, |value| decimal(value));
+        Ok(Quote {
+            symbol: symbol.to_string(),
+            bid_price: field(&ticker.b, 0),
+            ask_price: field(&ticker.a, 0),
+            bid_size: field(&ticker.b, 2),
+            ask_size: field(&ticker.a, 2),
+            timestamp: time::format_rfc3339(time::now_nanos()),
+        })
+    }
+
+    /// Docs: https://docs.kraken.com/api/docs/rest-api/get-ohlc-data
+    /// Days run from midnight UTC. Kraken serves only the latest 720 days, whatever the start. Crypto has no
+    /// corporate actions, so the adjustment is ignored.
+    async fn get_daily_bars(
+        &self,
+        symbol: &str,
+        start: &str,
+        end: &str,
+        _adjustment: BarAdjustment,
+    ) -> Result<Vec<Bar>, Box<dyn Error>> {
+        let seconds = |date: &str| {
+            time::parse_rfc3339(&format!("{}T00:00:00Z", date))
+                .map(|nanos| nanos / NANOS_PER_SECOND)
+                .ok_or_else(|| format!("Not a YYYY-MM-DD date: {}", date))
+        };
+        let from = seconds(start)?;
+        let until = seconds(end)?;
+
+        let params = [
+            ("pair", self.pair_name(symbol).await?),
+            ("interval", "1440".to_string()),
+            ("since", (from - 1).to_string()),
+        ];
+        let result: HashMap<String, Value> = self.request("OHLC", &params, false, true).await?;
+        // Besides the pair's candles, the result has the id to poll from as "last".
+        let candles = result
+            .into_iter()
+            .find(|(name, _)| name != "last")
+            .and_then(|(_, candles)| candles.as_array().cloned())
+            .unwrap_or_default();
+        // [time, open, high, low, close, vwap, volume, count]
+        Ok(candles
+            .into_iter()
+            .filter_map(|candle| {
+                let opened = candle.get(0)?.as_i64()?;
+                let field = |i: usize| candle.get(i).and_then(Value::as_str).map_or(0.0, decimal);
+                (from..=until).contains(&opened).then(|| Bar {
+                    symbol: symbol.to_string(),
+                    open: field(1),
+                    high: field(2),
+                    low: field(3),
+                    close: field(4),
+                    volume: field(6),
+                    timestamp: time::format_rfc3339(opened * NANOS_PER_SECOND),
+                })
+            })
+            .collect())
+    }
+
+    /// Docs: https://docs.kraken.com/api/docs/websocket-v2/trade
+    /// Trades, quotes (the best bid and offer), minute and daily bars and order books are streamed; Kraken has
+    /// no limit up/limit down bands or news, and needs explicit symbols rather than "*". Market data is public,
+    /// so the stream is not authenticated.
+    async fn subscribe(
+        &self,
+        params: SubscriptionParams,
+    ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, Box<dyn Error>> {
+        if matches!(params.feed_type, FeedType::News | FeedType::Options) {
+            return Err(format!("Kraken has no {:?} feed", params.feed_type).into());
+        }
+        let requests = stream::subscribe_messages(&params.subscription_request)?;
+
+        let (mut socket, response) = connect_async(STREAM_URL).await?;
+        if response.status() != 101 {
+            return Err(
+                format!("Connection failed with status code: {}", response.status()).into(),
+            );
+        }
+        for request in requests {
+            socket.send(Message::Text(request.to_string())).await?;
+        }
+        Ok(socket)
+    }
+
+    fn parse_frame(&self, frame: &str, _mode: ParseMode) -> Result<EventBatch, serde_json::Error> {
+        stream::parse_frame(frame, &self.candles)
+    }
+}
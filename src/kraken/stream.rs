@@ -0,0 +1,302 @@
+use crate::{
+    datastructures::{
+        client::{BookDepth, SubscriptionRequest},
+        event::{EventBatch, EventType},
+    },
+    time,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{collections::HashMap, sync::Mutex};
+
+/// Depths the book channel offers.
+const BOOK_DEPTHS: [usize; 5] = [10, 25, 100, 500, 1000];
+const MINUTE: u32 = 1;
+const DAY: u32 = 1440;
+
+/// Subscribe requests for the WebSocket v2 channels the request needs, one per channel.
+pub(super) fn subscribe_messages(
+    request: &SubscriptionRequest,
+) -> Result<Vec<Value>, &'static str> {
+    let symbols = |channels: &[&Vec<&'static str>]| -> Result<Vec<&'static str>, &'static str> {
+        let mut symbols = vec![];
+        for symbol in channels.iter().copied().flatten() {
+            if *symbol == "*" {
+                return Err("Kraken channels need explicit symbols");
+            }
+            if !symbols.contains(symbol) {
+                symbols.push(*symbol);
+            }
+        }
+        Ok(symbols)
+    };
+    let depth = match request.orderbook_depth {
+        BookDepth::Full => 1000,
+        BookDepth::Top(levels) => BOOK_DEPTHS
+            .into_iter()
+            .find(|depth| *depth >= levels)
+            .unwrap_or(1000),
+    };
+
+    let channels = [
+        ("trade", symbols(&[&request.trades])?, json!({})),
+        (
+            "ticker",
+            symbols(&[&request.quotes])?,
+            json!({ "event_trigger": "bbo" }),
+        ),
+        (
+            "ohlc",
+            symbols(&[&request.bars, &request.updated_bars])?,
+            json!({ "interval": MINUTE }),
+        ),
+        (
+            "ohlc",
+            symbols(&[&request.daily_bars])?,
+            json!({ "interval": DAY }),
+        ),
+        (
+            "book",
+            symbols(&[&request.orderbooks])?,
+            json!({ "depth": depth }),
+        ),
+    ];
+    if !request.lulds.is_empty() || !request.news.is_empty() {
+        tracing::warn!("Kraken has no limit up/limit down or news channels; ignoring them");
+    }
+    Ok(channels
+        .into_iter()
+        .filter(|(_, symbols, _)| !symbols.is_empty())
+        .map(|(channel, symbols, mut params)| {
+            params["channel"] = json!(channel);
+            params["symbol"] = json!(symbols);
+            json!({ "method": "subscribe", "params": params })
+        })
+        .collect())
+}
+
+#[derive(Deserialize)]
+struct Frame {
+    channel: String,
+    #[serde(rename = "type")]
+    kind: String,
+    data: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+struct RawCandle {
+    symbol: String,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    volume: f64,
+    interval_begin: String,
+    interval: u32,
+}
+
+/// The latest state of a symbol's open candle, by symbol and interval in minutes. Kraken does not mark
+/// candles closed, so a candle is taken as closed once one for a later interval arrives.
+pub(super) type Candles = Mutex<HashMap<(String, u32), OpenCandle>>;
+
+pub(super) struct OpenCandle {
+    begin: String,
+    /// Open, high, low, close and volume.
+    bar: (f64, f64, f64, f64, f64),
+}
+
+/// Maps a frame of the WebSocket v2 feed into events. Minute candles give an updated bar on every change and a
+/// bar once they close; daily candles give a daily bar once they close. The snapshot sent after subscribing
+/// only sets the open candle, so history is not replayed. Quotes carry no exchange time and are stamped on
+/// arrival. Acknowledgements, heartbeats and status messages give no events.
+pub(super) fn parse_frame(frame: &str, candles: &Candles) -> Result<EventBatch, serde_json::Error> {
+    let mut batch = EventBatch::new();
+    let value: Value = serde_json::from_str(frame)?;
+    if !matches!(
+        value.get("channel").and_then(Value::as_str),
+        Some("trade" | "ticker" | "ohlc" | "book")
+    ) {
+        return Ok(batch);
+    }
+    let Frame {
+        channel,
+        kind,
+        data,
+    } = serde_json::from_value(value)?;
+    let snapshot = kind == "snapshot";
+
+    for data in data {
+        let number = |name: &str| data.get(name).and_then(Value::as_f64).unwrap_or_default();
+        let string = |name: &str| {
+            data.get(name)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        };
+        let timestamp = || match string("timestamp") {
+            timestamp if timestamp.is_empty() => time::format_rfc3339(time::now_nanos()),
+            timestamp => timestamp,
+        };
+        match channel.as_str() {
+            "trade" => batch.push(EventType::Trade {
+                symbol: string("symbol"),
+                price: number("price"),
+                volume: number("qty"),
+                timestamp: timestamp(),
+            }),
+            "ticker" => batch.push(EventType::Quote {
+                symbol: string("symbol"),
+                bid_price: number("bid"),
+                ask_price: number("ask"),
+                bid_size: number("bid_qty"),
+                ask_size: number("ask_qty"),
+                timestamp: timestamp(),
+            }),
+            "book" => {
+                let levels = |name: &str| -> Vec<(f64, f64)> {
+                    data.get(name)
+                        .and_then(Value::as_array)
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|level| {
+                            Some((level.get("price")?.as_f64()?, level.get("qty")?.as_f64()?))
+                        })
+                        .collect()
+                };
+                batch.push(EventType::OrderBook {
+                    symbol: string("symbol"),
+                    bids: levels("bids"),
+                    asks: levels("asks"),
+                    reset: snapshot,
+                    timestamp: timestamp(),
+                });
+            }
+            _ => {
+                let candle: RawCandle = serde_json::from_value(data)?;
+                on_candle(candle, snapshot, candles, &mut batch);
+            }
+        }
+    }
+    Ok(batch)
+}
+
+fn on_candle(candle: RawCandle, snapshot: bool, candles: &Candles, batch: &mut EventBatch) {
+    let open = OpenCandle {
+        begin: candle.interval_begin.clone(),
+        bar: (
+            candle.open,
+            candle.high,
+            candle.low,
+            candle.close,
+            candle.volume,
+        ),
+    };
+    let previous = candles
+        .lock()
+        .unwrap()
+        .insert((candle.symbol.clone(), candle.interval), open);
+    if snapshot {
+        return;
+    }
+
+    let symbol = candle.symbol;
+    if let Some(previous) = previous.filter(|previous| previous.begin < candle.interval_begin) {
+        let (open, high, low, close, volume) = previous.bar;
+        let timestamp = previous.begin;
+        match candle.interval {
+            MINUTE => batch.push(EventType::Bar {
+                symbol: symbol.clone(),
+                open,
+                high,
+                low,
+                close,
+                volume,
+                timestamp,
+            }),
+            DAY => batch.push(EventType::DailyBar {
+                symbol: symbol.clone(),
+                open,
+                high,
+                low,
+                close,
+                volume,
+                timestamp,
+            }),
+            _ => {}
+        }
+    }
+    if candle.interval == MINUTE {
+        batch.push(EventType::UpdatedBar {
+            symbol,
+            open: candle.open,
+            high: candle.high,
+            low: candle.low,
+            close: candle.close,
+            volume: candle.volume,
+            timestamp: candle.interval_begin,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_fractional_trade_and_quote_sizes() {
+        let candles = Candles::default();
+        let batch = parse_frame(
+            r#"{"channel":"trade","type":"update","data":[{"symbol":"BTC/USD","side":"buy","price":42000.1,"qty":0.0015,"ord_type":"market","trade_id":1,"timestamp":"2024-01-02T14:30:00.000000Z"}]}"#,
+            &candles,
+        )
+        .unwrap();
+        match &batch[..] {
+            [EventType::Trade { price, volume, .. }] => {
+                assert_eq!(*price, 42000.1);
+                assert_eq!(*volume, 0.0015);
+            }
+            other => panic!("Expected one trade, got {:?}", other),
+        }
+
+        let batch = parse_frame(
+            r#"{"channel":"ticker","type":"update","data":[{"symbol":"BTC/USD","bid":41999.9,"bid_qty":0.25,"ask":42000.1,"ask_qty":1.5}]}"#,
+            &candles,
+        )
+        .unwrap();
+        match &batch[..] {
+            [EventType::Quote {
+                bid_size, ask_size, ..
+            }] => assert_eq!((*bid_size, *ask_size), (0.25, 1.5)),
+            other => panic!("Expected one quote, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn keeps_fractional_candle_volume() {
+        let candles = Candles::default();
+        let candle = |begin: &str, volume: f64| {
+            format!(
+                r#"{{"channel":"ohlc","type":"update","data":[{{"symbol":"BTC/USD","open":1.0,"high":2.0,"low":0.5,"close":1.5,"volume":{},"interval_begin":"{}","interval":1}}]}}"#,
+                volume, begin
+            )
+        };
+        let batch = parse_frame(&candle("2024-01-02T14:30:00Z", 0.75), &candles).unwrap();
+        match &batch[..] {
+            [EventType::UpdatedBar { volume, .. }] => assert_eq!(*volume, 0.75),
+            other => panic!("Expected an updated bar, got {:?}", other),
+        }
+
+        // The next minute closes the first one.
+        let batch = parse_frame(&candle("2024-01-02T14:31:00Z", 0.125), &candles).unwrap();
+        match &batch[..] {
+            [EventType::Bar {
+                volume, timestamp, ..
+            }, EventType::UpdatedBar { volume: open, .. }] => {
+                assert_eq!(*volume, 0.75);
+                assert_eq!(timestamp, "2024-01-02T14:30:00Z");
+                assert_eq!(*open, 0.125);
+            }
+            other => panic!("Expected a bar and an updated bar, got {:?}", other),
+        }
+    }
+}
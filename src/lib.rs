@@ -1,2 +1,85 @@
+pub mod activity;
+pub mod aggregator;
+#[cfg(feature = "alpaca")]
 pub mod alpaca;
+pub mod attribution;
+pub mod backtest;
+#[cfg(feature = "binance")]
+pub mod binance;
+pub mod blackout;
+#[cfg(feature = "broker-api")]
+pub mod broker_api;
+pub mod buying_power;
+pub mod connectivity;
 pub mod datastructures;
+pub mod dca;
+pub mod drawdown;
+pub mod execution;
+pub mod export;
+pub mod fees;
+#[cfg(feature = "finnhub")]
+pub mod finnhub;
+pub mod funding;
+#[cfg(feature = "gemini")]
+pub mod gemini;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod handoff;
+pub mod http;
+pub mod indicators;
+pub mod journal;
+pub mod killswitch;
+#[cfg(feature = "kraken")]
+pub mod kraken;
+pub mod liveness;
+pub mod lots;
+pub mod luld;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod middleware;
+pub mod observer;
+pub mod orderbook;
+pub mod outage;
+pub mod pairs;
+#[cfg(feature = "parquet")]
+mod parquet;
+pub mod pnl;
+#[cfg(feature = "polygon")]
+pub mod polygon;
+pub mod portfolio;
+pub mod priority;
+pub mod quotes;
+pub mod rebalance;
+pub mod reconcile;
+pub mod recorder;
+pub mod replay;
+pub mod report;
+pub mod risk;
+pub mod roll;
+pub mod router;
+#[cfg(feature = "schwab")]
+pub mod schwab;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod shutdown;
+pub mod sim;
+pub mod sizing;
+pub mod snapshot;
+pub mod spreads;
+pub mod store;
+pub mod strategy;
+pub mod stress;
+pub mod stream;
+pub mod supervisor;
+pub mod sweep;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "tiingo")]
+pub mod tiingo;
+pub mod time;
+pub mod universe;
+pub mod webhook;
+#[cfg(feature = "yahoo")]
+pub mod yahoo;
+
+pub use tokio_util::sync::CancellationToken;
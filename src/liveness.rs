@@ -0,0 +1,254 @@
+use serde::Serialize;
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// One component's heartbeat, e.g. the strategy runner's loop or a stream reader. Clones beat for the same
+/// component.
+#[derive(Debug, Clone)]
+pub struct Heartbeat {
+    name: Arc<str>,
+    max_silence: Duration,
+    epoch: Instant,
+    /// Nanoseconds from `epoch` to the last beat.
+    last: Arc<AtomicU64>,
+}
+
+impl Heartbeat {
+    pub fn beat(&self) {
+        let elapsed = self.epoch.elapsed().as_nanos() as u64;
+        self.last.store(elapsed, Ordering::Relaxed);
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// How long the component may go without beating before it counts as wedged.
+    pub fn max_silence(&self) -> Duration {
+        self.max_silence
+    }
+
+    pub fn silent_for(&self) -> Duration {
+        let last = Duration::from_nanos(self.last.load(Ordering::Relaxed));
+        self.epoch.elapsed().saturating_sub(last)
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.silent_for() <= self.max_silence
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ComponentStatus {
+    name: String,
+    silent_for_ms: u128,
+    alive: bool,
+}
+
+/// Components that must keep beating for the process to count as alive. A process can be running yet wedged,
+/// e.g. on a stuck callback or a stream that never reconnects; `LivenessReporter` tells an external supervisor
+/// only while every component is beating, so systemd or Kubernetes can restart it.
+#[derive(Debug, Clone, Default)]
+pub struct Liveness {
+    components: Arc<Mutex<Vec<Heartbeat>>>,
+}
+
+impl Liveness {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a component that must beat at least every `max_silence`. It starts out having just beaten.
+    pub fn register(&self, name: impl Into<String>, max_silence: Duration) -> Heartbeat {
+        let heartbeat = Heartbeat {
+            name: name.into().into(),
+            max_silence,
+            epoch: Instant::now(),
+            last: Arc::new(AtomicU64::new(0)),
+        };
+        self.components.lock().unwrap().push(heartbeat.clone());
+        heartbeat
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.components
+            .lock()
+            .unwrap()
+            .iter()
+            .all(Heartbeat::is_alive)
+    }
+
+    /// Components silent for longer than they may be, with how long they have been silent.
+    pub fn wedged(&self) -> Vec<(String, Duration)> {
+        self.components
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|heartbeat| !heartbeat.is_alive())
+            .map(|heartbeat| (heartbeat.name().to_string(), heartbeat.silent_for()))
+            .collect()
+    }
+
+    fn statuses(&self) -> Vec<ComponentStatus> {
+        self.components
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|heartbeat| ComponentStatus {
+                name: heartbeat.name().to_string(),
+                silent_for_ms: heartbeat.silent_for().as_millis(),
+                alive: heartbeat.is_alive(),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LivenessConfig {
+    /// File rewritten on every report while alive, for a probe that checks its age, e.g.
+    /// `find /tmp/alive -mmin -1`. Removed when the reporter stops.
+    pub file: Option<PathBuf>,
+    /// Sends `READY=1` and then `WATCHDOG=1` on every report while alive to the socket in `NOTIFY_SOCKET`, for
+    /// a systemd unit with `Type=notify` and `WatchdogSec=`. Does nothing when the variable is not set.
+    pub sd_notify: bool,
+    /// How often liveness is reported. Keep it well under the probe's threshold or half of `WatchdogSec`.
+    pub interval: Duration,
+}
+
+impl Default for LivenessConfig {
+    fn default() -> Self {
+        LivenessConfig {
+            file: None,
+            sd_notify: false,
+            interval: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Background task reporting liveness to an external supervisor. Stops with the cancellation token or when
+/// dropped.
+pub struct LivenessReporter {
+    task: JoinHandle<()>,
+}
+
+impl LivenessReporter {
+    pub fn spawn(
+        liveness: Liveness,
+        config: LivenessConfig,
+        cancel: CancellationToken,
+    ) -> LivenessReporter {
+        let task = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(config.interval);
+            let mut ready = false;
+            let mut wedged = false;
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = cancel.cancelled() => break,
+                }
+
+                if !liveness.is_alive() {
+                    if !wedged {
+                        tracing::error!(components = ?liveness.wedged(), "Process wedged, no longer reporting liveness");
+                        wedged = true;
+                    }
+                    continue;
+                }
+                if wedged {
+                    tracing::info!("Process alive again");
+                    wedged = false;
+                }
+
+                if let Some(file) = &config.file {
+                    if let Err(e) = write_file(file, &liveness).await {
+                        tracing::warn!(error = %e, path = %file.display(), "Failed to write liveness file");
+                    }
+                }
+                if config.sd_notify {
+                    let state = if ready {
+                        "WATCHDOG=1"
+                    } else {
+                        "READY=1\nWATCHDOG=1"
+                    };
+                    match sd_notify(state) {
+                        Ok(()) => ready = true,
+                        Err(e) => tracing::warn!(error = %e, "Failed to notify systemd"),
+                    }
+                }
+            }
+
+            if let Some(file) = &config.file {
+                let _ = tokio::fs::remove_file(file).await;
+            }
+            if config.sd_notify {
+                let _ = sd_notify("STOPPING=1");
+            }
+        });
+        LivenessReporter { task }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+impl Drop for LivenessReporter {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Replaces the file through a rename so a probe never reads it half written.
+async fn write_file(path: &Path, liveness: &Liveness) -> io::Result<()> {
+    let contents = serde_json::json!({
+        "timestamp": crate::time::format_rfc3339(crate::time::now_nanos()),
+        "components": liveness.statuses(),
+    });
+    let temporary = path.with_extension("tmp");
+    tokio::fs::write(&temporary, contents.to_string()).await?;
+    tokio::fs::rename(&temporary, path).await
+}
+
+/// Sends a state change to systemd's notification socket, a path or an abstract socket starting with '@'.
+#[cfg(unix)]
+fn sd_notify(state: &str) -> io::Result<()> {
+    use std::os::unix::net::UnixDatagram;
+
+    let Ok(path) = std::env::var("NOTIFY_SOCKET") else {
+        return Ok(());
+    };
+    let socket = UnixDatagram::unbound()?;
+    match path.strip_prefix('@') {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::{linux::net::SocketAddrExt, unix::net::SocketAddr};
+            let address = SocketAddr::from_abstract_name(name)?;
+            socket.send_to_addr(state.as_bytes(), &address)?;
+        }
+        #[cfg(not(target_os = "linux"))]
+        Some(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Abstract sockets are only supported on Linux",
+            ))
+        }
+        None => {
+            socket.send_to(state.as_bytes(), &path)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn sd_notify(_state: &str) -> io::Result<()> {
+    Ok(())
+}
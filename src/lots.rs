@@ -0,0 +1,275 @@
+use crate::{datastructures::order::OrderSide, strategy::Fill, time};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    error::Error,
+    time::Duration,
+};
+
+/// Quantities this close to zero are float noise from splitting fractional fills, e.g. 0.3 - 0.1 - 0.2.
+const EPSILON: f64 = 1e-9;
+
+/// Which open lots a closing fill is matched against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LotMethod {
+    #[default]
+    Fifo,
+    Lifo,
+}
+
+/// Quantity bought (or sold short) by one fill and not yet closed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaxLot {
+    pub id: u64,
+    pub symbol: String,
+    /// Remaining quantity. Negative for short lots.
+    pub quantity: f64,
+    /// Per share, including the opening commission: the price paid for a long lot, or received net for a
+    /// short one.
+    pub cost_basis: f64,
+    pub opened_at: String,
+    /// Order id of the opening fill.
+    pub order_id: String,
+}
+
+/// The part of a lot closed by one fill.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RealizedLot {
+    pub lot_id: u64,
+    pub symbol: String,
+    /// Negative when a short lot was covered.
+    pub quantity: f64,
+    /// Total paid, including commissions: the purchase for a long lot, the cover for a short one.
+    pub cost_basis: f64,
+    /// Total received, net of commissions: the sale for a long lot, the short sale for a short one.
+    pub proceeds: f64,
+    pub gain: f64,
+    pub opened_at: String,
+    pub closed_at: String,
+    pub holding_period: Duration,
+    /// Held for more than one year.
+    pub long_term: bool,
+}
+
+/// Open tax lots per symbol, built from fills. Buys add long lots or cover short ones; sells close long lots
+/// or open short ones. Closing fills are matched with the configured method unless specific lots are named.
+#[derive(Debug, Clone, Default)]
+pub struct LotTracker {
+    method: LotMethod,
+    lots: HashMap<String, VecDeque<TaxLot>>,
+    realized: Vec<RealizedLot>,
+    next_id: u64,
+}
+
+impl LotTracker {
+    pub fn new(method: LotMethod) -> Self {
+        LotTracker {
+            method,
+            ..Self::default()
+        }
+    }
+
+    /// Records a fill, matching any quantity it closes with the configured method.
+    pub fn on_fill(&mut self, fill: &Fill) -> Vec<RealizedLot> {
+        self.close(fill, &[])
+            .expect("closing without named lots cannot fail")
+    }
+
+    /// Records a fill, closing the named lots first, each up to the given quantity. Whatever the named lots
+    /// do not cover is matched with the configured method. Fails, recording nothing, if a named lot is not an
+    /// open lot of the symbol on the side the fill closes.
+    pub fn on_fill_with_lots(
+        &mut self,
+        fill: &Fill,
+        lots: &[(u64, f64)],
+    ) -> Result<Vec<RealizedLot>, Box<dyn Error>> {
+        self.close(fill, lots)
+    }
+
+    /// Open lots of a symbol, oldest first.
+    pub fn open_lots(&self, symbol: &str) -> Vec<TaxLot> {
+        self.lots
+            .get(symbol)
+            .map(|lots| lots.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Net quantity held across a symbol's open lots.
+    pub fn quantity(&self, symbol: &str) -> f64 {
+        self.lots
+            .get(symbol)
+            .map(|lots| lots.iter().map(|lot| lot.quantity).sum())
+            .unwrap_or_default()
+    }
+
+    /// Every closed lot portion, in the order they were closed.
+    pub fn realized(&self) -> &[RealizedLot] {
+        &self.realized
+    }
+
+    /// Closed lot portions with a closing date from `start` to `end` inclusive, as YYYY-MM-DD dates.
+    pub fn realized_between(&self, start: &str, end: &str) -> Vec<RealizedLot> {
+        self.realized
+            .iter()
+            .filter(|lot| {
+                time::date(&lot.closed_at)
+                    .is_some_and(|date| date.as_str() >= start && date.as_str() <= end)
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn close(
+        &mut self,
+        fill: &Fill,
+        named: &[(u64, f64)],
+    ) -> Result<Vec<RealizedLot>, Box<dyn Error>> {
+        let direction = match fill.side {
+            OrderSide::Buy => 1.0,
+            OrderSide::Sell => -1.0,
+        };
+        let open = self.lots.entry(fill.symbol.clone()).or_default();
+        for (id, _) in named {
+            if !open
+                .iter()
+                .any(|lot| lot.id == *id && lot.quantity * direction < 0.0)
+            {
+                return Err(format!("No open {} lot {} to close", fill.symbol, id).into());
+            }
+        }
+
+        let commission_per_share = fill.commission / fill.quantity;
+        let mut remaining = fill.quantity;
+        let mut realized = vec![];
+        for (id, limit) in named {
+            if let Some(lot) = open.iter_mut().find(|lot| lot.id == *id) {
+                if let Some(closed) = take(lot, limit.min(remaining), fill, commission_per_share) {
+                    remaining -= closed.quantity.abs();
+                    realized.push(closed);
+                }
+            }
+        }
+        let closing: Vec<&mut TaxLot> = match self.method {
+            LotMethod::Fifo => open.iter_mut().collect(),
+            LotMethod::Lifo => open.iter_mut().rev().collect(),
+        };
+        for lot in closing {
+            if remaining <= EPSILON {
+                break;
+            }
+            if lot.quantity * direction >= 0.0 {
+                continue;
+            }
+            if let Some(closed) = take(lot, remaining, fill, commission_per_share) {
+                remaining -= closed.quantity.abs();
+                realized.push(closed);
+            }
+        }
+        open.retain(|lot| lot.quantity != 0.0);
+
+        if remaining > EPSILON {
+            let opening_commission = commission_per_share * direction;
+            self.next_id += 1;
+            open.push_back(TaxLot {
+                id: self.next_id,
+                symbol: fill.symbol.clone(),
+                quantity: direction * remaining,
+                cost_basis: fill.price + opening_commission,
+                opened_at: fill.timestamp.clone(),
+                order_id: fill.order_id.clone(),
+            });
+        }
+        self.realized.extend(realized.iter().cloned());
+        Ok(realized)
+    }
+}
+
+/// Closes up to `quantity` of the lot at the fill's price.
+fn take(
+    lot: &mut TaxLot,
+    quantity: f64,
+    fill: &Fill,
+    commission_per_share: f64,
+) -> Option<RealizedLot> {
+    // Within float noise of the whole lot closes all of it, so no dust lot is left open.
+    let quantity = if quantity >= lot.quantity.abs() - EPSILON {
+        lot.quantity.abs()
+    } else {
+        quantity
+    };
+    if quantity <= EPSILON {
+        return None;
+    }
+    let sign = lot.quantity.signum();
+    let opening = quantity * lot.cost_basis;
+    let closing = quantity * (fill.price - sign * commission_per_share);
+    let (cost_basis, proceeds) = if sign > 0.0 {
+        (opening, closing)
+    } else {
+        (closing, opening)
+    };
+    let holding_period = match (
+        time::parse_rfc3339(&lot.opened_at),
+        time::parse_rfc3339(&fill.timestamp),
+    ) {
+        (Some(opened), Some(closed)) => Duration::from_nanos((closed - opened).max(0) as u64),
+        _ => Duration::ZERO,
+    };
+    lot.quantity -= sign * quantity;
+    Some(RealizedLot {
+        lot_id: lot.id,
+        symbol: lot.symbol.clone(),
+        quantity: sign * quantity,
+        cost_basis,
+        proceeds,
+        gain: proceeds - cost_basis,
+        opened_at: lot.opened_at.clone(),
+        closed_at: fill.timestamp.clone(),
+        holding_period,
+        long_term: is_long_term(&lot.opened_at, &fill.timestamp),
+    })
+}
+
+/// Whether a position opened at `opened` and closed at `closed` was held for more than one year, i.e.
+/// closed after the anniversary of the opening date.
+fn is_long_term(opened: &str, closed: &str) -> bool {
+    let (Some(opened), Some(closed)) = (time::date(opened), time::date(closed)) else {
+        return false;
+    };
+    let Ok(year) = opened[..4].parse::<u32>() else {
+        return false;
+    };
+    closed > format!("{:04}{}", year + 1, &opened[4..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(side: OrderSide, quantity: f64, price: f64) -> Fill {
+        Fill {
+            order_id: "order".to_string(),
+            symbol: "BTC/USD".to_string(),
+            side,
+            quantity,
+            price,
+            commission: 0.0,
+            timestamp: "2024-03-01T15:00:00Z".to_string(),
+        }
+    }
+
+    #[test]
+    fn closes_fractional_lots_without_leaving_dust() {
+        let mut lots = LotTracker::new(LotMethod::Fifo);
+        lots.on_fill(&fill(OrderSide::Buy, 0.1, 60_000.0));
+        lots.on_fill(&fill(OrderSide::Buy, 0.2, 62_000.0));
+
+        let realized = lots.on_fill(&fill(OrderSide::Sell, 0.3, 65_000.0));
+
+        assert_eq!(realized.len(), 2);
+        assert_eq!(realized[1].quantity, 0.2);
+        assert!(lots.open_lots("BTC/USD").is_empty());
+        assert_eq!(lots.quantity("BTC/USD"), 0.0);
+    }
+}
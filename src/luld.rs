@@ -0,0 +1,108 @@
+use crate::datastructures::{event::EventType, order::Order};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+/// Current limit up/limit down band for a symbol.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LuldBand {
+    pub limit_up: f64,
+    pub limit_down: f64,
+    pub timestamp: String,
+}
+
+impl LuldBand {
+    pub fn contains(&self, price: f64) -> bool {
+        price >= self.limit_down && price <= self.limit_up
+    }
+}
+
+/// What to do with a limit order priced outside the current band.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LuldPolicy {
+    Reject,
+    /// Move the limit price to the nearest band edge.
+    Clamp,
+}
+
+#[derive(Debug, Clone)]
+pub struct LuldViolation {
+    pub symbol: String,
+    pub limit_price: f64,
+    pub band: LuldBand,
+}
+
+impl fmt::Display for LuldViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Limit price {} for {} is outside the LULD band [{}, {}]",
+            self.limit_price, self.symbol, self.band.limit_down, self.band.limit_up
+        )
+    }
+}
+
+impl Error for LuldViolation {}
+
+/// Latest LULD band per symbol, fed from `EventType::Luld` events.
+#[derive(Clone, Default)]
+pub struct LuldBands {
+    bands: Arc<RwLock<HashMap<String, LuldBand>>>,
+}
+
+impl LuldBands {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the band carried by a LULD event. Other events are ignored.
+    pub fn update(&self, event: &EventType) {
+        if let EventType::Luld {
+            symbol,
+            limit_up,
+            limit_down,
+            timestamp,
+        } = event
+        {
+            self.bands.write().unwrap().insert(
+                symbol.clone(),
+                LuldBand {
+                    limit_up: *limit_up,
+                    limit_down: *limit_down,
+                    timestamp: timestamp.clone(),
+                },
+            );
+        }
+    }
+
+    pub fn band(&self, symbol: &str) -> Option<LuldBand> {
+        self.bands.read().unwrap().get(symbol).cloned()
+    }
+
+    /// Checks a limit order against the symbol's current band. Orders without a limit price,
+    /// and symbols without a known band, pass through unchanged.
+    pub fn validate(&self, order: &Order, policy: LuldPolicy) -> Result<Order, LuldViolation> {
+        let (Some(limit_price), Some(band)) = (order.limit_price, self.band(&order.symbol)) else {
+            return Ok(order.clone());
+        };
+        if band.contains(limit_price) {
+            return Ok(order.clone());
+        }
+
+        match policy {
+            LuldPolicy::Reject => Err(LuldViolation {
+                symbol: order.symbol.clone(),
+                limit_price,
+                band,
+            }),
+            LuldPolicy::Clamp => {
+                let mut order = order.clone();
+                order.limit_price = Some(limit_price.clamp(band.limit_down, band.limit_up));
+                Ok(order)
+            }
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use std::{
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+    time::Duration,
+};
+
+/// Upper bounds, in seconds, of the REST latency histogram buckets.
+const LATENCY_BUCKETS: [f64; 10] = [0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Monotonically increasing count.
+#[derive(Debug, Default)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    pub fn add(&self, n: u64) {
+        self.0.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Distribution of durations over fixed buckets, in the shape Prometheus expects.
+#[derive(Debug)]
+pub struct Histogram {
+    bounds: &'static [f64],
+    /// Non-cumulative count per bucket; the extra last slot is the `+Inf` bucket.
+    buckets: Vec<AtomicU64>,
+    /// Sum of observations as `f64` bits.
+    sum: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Histogram {
+            bounds,
+            buckets: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            sum: AtomicU64::new(0f64.to_bits()),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    pub fn observe(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        let bucket = self
+            .bounds
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(self.bounds.len());
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        let _ = self
+            .sum
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                Some((f64::from_bits(bits) + seconds).to_bits())
+            });
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Total of all observations in seconds.
+    pub fn sum(&self) -> f64 {
+        f64::from_bits(self.sum.load(Ordering::Relaxed))
+    }
+
+    /// Cumulative `(upper bound, count)` pairs, ending with the `+Inf` bucket.
+    pub fn buckets(&self) -> Vec<(f64, u64)> {
+        let mut cumulative = 0;
+        self.bounds
+            .iter()
+            .copied()
+            .chain([f64::INFINITY])
+            .zip(&self.buckets)
+            .map(|(bound, count)| {
+                cumulative += count.load(Ordering::Relaxed);
+                (bound, cumulative)
+            })
+            .collect()
+    }
+}
+
+/// Health metrics recorded by the client. Only compiled with the `metrics` feature.
+/// Read the fields directly to feed another registry, or serve `render` from a scrape endpoint.
+#[derive(Debug)]
+pub struct Metrics {
+    pub orders_submitted: Counter,
+    /// Orders the broker answered with a non-success status.
+    pub orders_rejected: Counter,
+    pub rest_latency: Histogram,
+    /// Websocket frames received. Use `rate()` on the scraped counter for messages per second.
+    pub ws_messages: Counter,
+    pub ws_reconnects: Counter,
+    pub parse_errors: Counter,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        Metrics {
+            orders_submitted: Counter::default(),
+            orders_rejected: Counter::default(),
+            rest_latency: Histogram::new(&LATENCY_BUCKETS),
+            ws_messages: Counter::default(),
+            ws_reconnects: Counter::default(),
+            parse_errors: Counter::default(),
+        }
+    }
+
+    /// Renders every metric in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let counters = [
+            (
+                "trading_client_orders_submitted_total",
+                "Orders submitted.",
+                &self.orders_submitted,
+            ),
+            (
+                "trading_client_orders_rejected_total",
+                "Orders rejected by the broker.",
+                &self.orders_rejected,
+            ),
+            (
+                "trading_client_ws_messages_total",
+                "Websocket messages received.",
+                &self.ws_messages,
+            ),
+            (
+                "trading_client_ws_reconnects_total",
+                "Websocket reconnects.",
+                &self.ws_reconnects,
+            ),
+            (
+                "trading_client_parse_errors_total",
+                "Stream messages that failed to parse.",
+                &self.parse_errors,
+            ),
+        ];
+        for (name, help, counter) in counters {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} counter", name);
+            let _ = writeln!(out, "{} {}", name, counter.get());
+        }
+
+        let name = "trading_client_rest_request_duration_seconds";
+        let _ = writeln!(out, "# HELP {} REST request latency.", name);
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        for (bound, count) in self.rest_latency.buckets() {
+            let le = if bound.is_infinite() {
+                "+Inf".to_string()
+            } else {
+                bound.to_string()
+            };
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, le, count);
+        }
+        let _ = writeln!(out, "{}_sum {}", name, self.rest_latency.sum());
+        let _ = writeln!(out, "{}_count {}", name, self.rest_latency.count());
+        out
+    }
+}
+
+/// Process-wide metrics shared by every client and stream.
+pub fn registry() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(Metrics::new)
+}
@@ -0,0 +1,517 @@
+use crate::{
+    buying_power::BuyingPowerCheck,
+    datastructures::{
+        account::{Account, AccountActivity, Position},
+        asset::Asset,
+        client::{BrokerClient, MarketDataClient, SubscriptionParams, TradingClient},
+        event::{EventBatch, ParseMode},
+        market::{Bar, BarAdjustment, Quote},
+        order::{CancelOutcome, Order, OrderResponse},
+    },
+    http::{RateLimitConfig, RateLimiter},
+    risk::{RiskEngine, RiskLimits},
+    time,
+};
+use async_trait::async_trait;
+use serde::Serialize;
+use std::{
+    error::Error,
+    fs::{File, OpenOptions},
+    future::Future,
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+/// A client call, as seen by middleware.
+#[derive(Debug, Clone, Copy)]
+pub enum Call<'a> {
+    CreateOrder(&'a Order),
+    GetOpenOrders,
+    GetOrder(&'a str),
+    GetOrderByClientId(&'a str),
+    CancelOrder(&'a str),
+    GetAsset(&'a str),
+    ListAssets,
+    GetAccount,
+    GetPositions,
+    GetAccountActivities(&'a str),
+    GetLatestQuote(&'a str),
+    GetDailyBars(&'a str),
+    Subscribe,
+}
+
+impl Call<'_> {
+    /// Name of the client method, e.g. "create_order".
+    pub fn name(&self) -> &'static str {
+        match self {
+            Call::CreateOrder(_) => "create_order",
+            Call::GetOpenOrders => "get_open_orders",
+            Call::GetOrder(_) => "get_order",
+            Call::GetOrderByClientId(_) => "get_order_by_client_id",
+            Call::CancelOrder(_) => "cancel_order",
+            Call::GetAsset(_) => "get_asset",
+            Call::ListAssets => "list_assets",
+            Call::GetAccount => "get_account",
+            Call::GetPositions => "get_positions",
+            Call::GetAccountActivities(_) => "get_account_activities",
+            Call::GetLatestQuote(_) => "get_latest_quote",
+            Call::GetDailyBars(_) => "get_daily_bars",
+            Call::Subscribe => "subscribe",
+        }
+    }
+
+    /// Submissions and cancels, the calls that change what is working at the broker.
+    pub fn is_order_entry(&self) -> bool {
+        matches!(self, Call::CreateOrder(_) | Call::CancelOrder(_))
+    }
+}
+
+/// A layer of behavior around a client's calls, added with `ClientBuilder::with`. Every hook defaults to doing
+/// nothing, so a middleware only implements the ones it needs. `C` is the client the layer wraps.
+#[async_trait]
+pub trait Middleware<C: ?Sized + Sync>: Send + Sync {
+    /// Runs before every call is passed on. An error fails the call without passing it on.
+    async fn before(&self, _call: Call<'_>) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    /// Runs before an order is submitted, after `before`, with the wrapped client for any lookups. Returns the
+    /// order to submit, e.g. resized, or `None` to report it submitted without passing it on.
+    async fn on_order(&self, _client: &C, order: Order) -> Result<Option<Order>, Box<dyn Error>> {
+        Ok(Some(order))
+    }
+
+    /// Runs after every call `before` let through, with its outcome.
+    fn after(&self, _call: Call<'_>, _outcome: Result<(), &dyn Error>) {}
+}
+
+/// A client wrapped in a middleware. Implements the client traits the wrapped client does.
+#[derive(Clone)]
+pub struct Layered<C, M> {
+    client: C,
+    middleware: M,
+}
+
+impl<C, M> Layered<C, M>
+where
+    C: Sync,
+    M: Middleware<C>,
+{
+    pub fn new(client: C, middleware: M) -> Self {
+        Layered { client, middleware }
+    }
+
+    pub fn client(&self) -> &C {
+        &self.client
+    }
+
+    pub fn middleware(&self) -> &M {
+        &self.middleware
+    }
+
+    async fn call<T, F>(&self, call: Call<'_>, run: F) -> Result<T, Box<dyn Error>>
+    where
+        F: Future<Output = Result<T, Box<dyn Error>>> + Send,
+    {
+        self.middleware.before(call).await?;
+        let result = run.await;
+        self.middleware
+            .after(call, result.as_ref().map(|_| ()).map_err(|e| e.as_ref()));
+        result
+    }
+}
+
+#[async_trait]
+impl<C, M> TradingClient for Layered<C, M>
+where
+    C: TradingClient + Send + Sync,
+    M: Middleware<C>,
+{
+    async fn create_order(&self, order: &Order) -> Result<(), Box<dyn Error>> {
+        let submit = async {
+            let order = self
+                .middleware
+                .on_order(&self.client, order.clone())
+                .await?;
+            match order {
+                Some(order) => self.client.create_order(&order).await,
+                None => Ok(()),
+            }
+        };
+        self.call(Call::CreateOrder(order), submit).await
+    }
+
+    async fn get_open_orders(&self) -> Result<Vec<OrderResponse>, Box<dyn Error>> {
+        self.call(Call::GetOpenOrders, self.client.get_open_orders())
+            .await
+    }
+
+    async fn get_order(&self, order_id: &str) -> Result<OrderResponse, Box<dyn Error>> {
+        self.call(Call::GetOrder(order_id), self.client.get_order(order_id))
+            .await
+    }
+
+    async fn get_order_by_client_id(
+        &self,
+        client_order_id: &str,
+    ) -> Result<OrderResponse, Box<dyn Error>> {
+        self.call(
+            Call::GetOrderByClientId(client_order_id),
+            self.client.get_order_by_client_id(client_order_id),
+        )
+        .await
+    }
+
+    async fn cancel_order(&self, order_id: &str) -> Result<CancelOutcome, Box<dyn Error>> {
+        self.call(
+            Call::CancelOrder(order_id),
+            self.client.cancel_order(order_id),
+        )
+        .await
+    }
+
+    async fn get_asset(&self, symbol: &str) -> Result<Asset, Box<dyn Error>> {
+        self.call(Call::GetAsset(symbol), self.client.get_asset(symbol))
+            .await
+    }
+
+    async fn list_assets(&self) -> Result<Vec<Asset>, Box<dyn Error>> {
+        self.call(Call::ListAssets, self.client.list_assets()).await
+    }
+
+    async fn get_account(&self) -> Result<Account, Box<dyn Error>> {
+        self.call(Call::GetAccount, self.client.get_account()).await
+    }
+
+    async fn get_positions(&self) -> Result<Vec<Position>, Box<dyn Error>> {
+        self.call(Call::GetPositions, self.client.get_positions())
+            .await
+    }
+
+    async fn get_account_activities(
+        &self,
+        since: &str,
+    ) -> Result<Vec<AccountActivity>, Box<dyn Error>> {
+        self.call(
+            Call::GetAccountActivities(since),
+            self.client.get_account_activities(since),
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl<C, M> MarketDataClient for Layered<C, M>
+where
+    C: MarketDataClient + Send + Sync,
+    M: Middleware<C>,
+{
+    async fn get_latest_quote(&self, symbol: &str) -> Result<Quote, Box<dyn Error>> {
+        self.call(
+            Call::GetLatestQuote(symbol),
+            self.client.get_latest_quote(symbol),
+        )
+        .await
+    }
+
+    async fn get_daily_bars(
+        &self,
+        symbol: &str,
+        start: &str,
+        end: &str,
+        adjustment: BarAdjustment,
+    ) -> Result<Vec<Bar>, Box<dyn Error>> {
+        self.call(
+            Call::GetDailyBars(symbol),
+            self.client.get_daily_bars(symbol, start, end, adjustment),
+        )
+        .await
+    }
+
+    async fn subscribe(
+        &self,
+        params: SubscriptionParams,
+    ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, Box<dyn Error>> {
+        self.call(Call::Subscribe, self.client.subscribe(params))
+            .await
+    }
+
+    fn parse_frame(&self, frame: &str, mode: ParseMode) -> Result<EventBatch, serde_json::Error> {
+        self.client.parse_frame(frame, mode)
+    }
+}
+
+/// Stacks middleware around a client. Each `with` wraps everything added before it, so the last layer added
+/// sees a call first and its outcome last; e.g. with an `AuditLog` added after a `RiskManager`, orders the risk
+/// checks refuse are audited too.
+pub struct ClientBuilder<C> {
+    client: C,
+}
+
+impl<C: Sync> ClientBuilder<C> {
+    pub fn new(client: C) -> Self {
+        ClientBuilder { client }
+    }
+
+    pub fn with<M: Middleware<C>>(self, middleware: M) -> ClientBuilder<Layered<C, M>> {
+        ClientBuilder {
+            client: Layered::new(self.client, middleware),
+        }
+    }
+
+    pub fn build(self) -> C {
+        self.client
+    }
+}
+
+/// Holds every call until the request budget allows it, for clients without their own rate limiting. Clones
+/// draw from the same budget.
+#[derive(Clone)]
+pub struct RateLimit {
+    limiter: Arc<RateLimiter>,
+}
+
+impl RateLimit {
+    pub fn new(config: RateLimitConfig) -> Self {
+        RateLimit {
+            limiter: Arc::new(RateLimiter::new(config)),
+        }
+    }
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self::new(RateLimitConfig::default())
+    }
+}
+
+#[async_trait]
+impl<C: ?Sized + Sync> Middleware<C> for RateLimit {
+    async fn before(&self, _call: Call<'_>) -> Result<(), Box<dyn Error>> {
+        self.limiter.acquire().await;
+        Ok(())
+    }
+}
+
+/// Checks every order against `RiskLimits` before it is submitted, reading positions and prices through the
+/// wrapped client. Orders may be resized or refused with a `RiskViolation`.
+#[derive(Clone)]
+pub struct RiskManager {
+    engine: RiskEngine,
+}
+
+impl RiskManager {
+    pub fn new(limits: RiskLimits) -> Self {
+        RiskManager {
+            engine: RiskEngine::new(limits),
+        }
+    }
+}
+
+#[async_trait]
+impl<C: BrokerClient + ?Sized + Sync> Middleware<C> for RiskManager {
+    async fn on_order(&self, client: &C, order: Order) -> Result<Option<Order>, Box<dyn Error>> {
+        Ok(Some(self.engine.check(client, &order).await?))
+    }
+}
+
+/// Fails orders that need more buying power than the account has before they are submitted. The cached
+/// account state is forgotten when a submission fails, since the order's notional was set aside when it
+/// passed. Add it before a `RiskManager`, so the risk checks run first and it sees orders as they resized them.
+#[async_trait]
+impl<C: BrokerClient + ?Sized + Sync> Middleware<C> for BuyingPowerCheck {
+    async fn on_order(&self, client: &C, order: Order) -> Result<Option<Order>, Box<dyn Error>> {
+        self.check(client, &order).await?;
+        Ok(Some(order))
+    }
+
+    fn after(&self, call: Call<'_>, outcome: Result<(), &dyn Error>) {
+        if matches!(call, Call::CreateOrder(_)) && outcome.is_err() {
+            self.invalidate();
+        }
+    }
+}
+
+/// Reports order submissions as accepted without passing them on, and refuses cancels, so a strategy can run
+/// against a live account without trading. Everything else passes through. Clones keep
+/// the same record of orders.
+#[derive(Clone, Default)]
+pub struct DryRun {
+    orders: Arc<Mutex<Vec<Order>>>,
+}
+
+impl DryRun {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every order that would have been submitted, in order.
+    pub fn orders(&self) -> Vec<Order> {
+        self.orders.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl<C: ?Sized + Sync> Middleware<C> for DryRun {
+    async fn before(&self, call: Call<'_>) -> Result<(), Box<dyn Error>> {
+        match call {
+            Call::CancelOrder(order_id) => {
+                Err(format!("Dry run: not cancelling {}", order_id).into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn on_order(&self, _client: &C, order: Order) -> Result<Option<Order>, Box<dyn Error>> {
+        tracing::info!(
+            symbol = %order.symbol,
+            quantity = order.quantity,
+            side = ?order.side,
+            "Dry run: order not sent"
+        );
+        self.orders.lock().unwrap().push(order);
+        Ok(None)
+    }
+}
+
+#[derive(Serialize)]
+struct AuditRecord<'a> {
+    at: String,
+    call: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    order: Option<&'a Order>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    order_id: Option<&'a str>,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Appends a JSONL record of every order submission and cancel, with its outcome, to a file.
+#[derive(Clone)]
+pub struct AuditLog {
+    writer: Arc<Mutex<BufWriter<File>>>,
+}
+
+impl AuditLog {
+    /// Appends to the file at `path`, creating it if needed.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(AuditLog {
+            writer: Arc::new(Mutex::new(BufWriter::new(file))),
+        })
+    }
+
+    fn write(&self, record: &AuditRecord) -> io::Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        serde_json::to_writer(&mut *writer, record)?;
+        writer.write_all(b"\n")?;
+        writer.flush()
+    }
+}
+
+#[async_trait]
+impl<C: ?Sized + Sync> Middleware<C> for AuditLog {
+    fn after(&self, call: Call<'_>, outcome: Result<(), &dyn Error>) {
+        let (order, order_id) = match call {
+            Call::CreateOrder(order) => (Some(order), None),
+            Call::CancelOrder(order_id) => (None, Some(order_id)),
+            _ => return,
+        };
+        let record = AuditRecord {
+            at: time::format_rfc3339(time::now_nanos()),
+            call: call.name(),
+            order,
+            order_id,
+            ok: outcome.is_ok(),
+            error: outcome.err().map(|e| e.to_string()),
+        };
+        if let Err(e) = self.write(&record) {
+            tracing::error!(error = %e, "Failed to write audit record");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        buying_power::{BuyingPowerConfig, InsufficientBuyingPower},
+        datastructures::order::{OrderSide, OrderType},
+        risk::{RiskAction, RiskViolation},
+        testing::{MockCall, MockTradingClient},
+    };
+
+    fn buy(symbol: &str, quantity: f64) -> Order {
+        Order::builder()
+            .symbol(symbol)
+            .quantity(quantity)
+            .side(OrderSide::Buy)
+            .order_type(OrderType::Limit)
+            .limit_price(100.0)
+            .build()
+            .unwrap()
+    }
+
+    fn checked(
+        client: &MockTradingClient,
+        limits: RiskLimits,
+    ) -> Layered<Layered<MockTradingClient, BuyingPowerCheck>, RiskManager> {
+        ClientBuilder::new(client.clone())
+            .with(BuyingPowerCheck::new(BuyingPowerConfig::default()))
+            .with(RiskManager::new(limits))
+            .build()
+    }
+
+    #[tokio::test]
+    async fn checks_buying_power_of_orders_as_risk_checks_resized_them() {
+        let broker = MockTradingClient::with_cash(1_000.0);
+        let client = checked(
+            &broker,
+            RiskLimits {
+                max_order_quantity: Some(5.0),
+                action: RiskAction::Resize,
+                ..RiskLimits::default()
+            },
+        );
+
+        client.create_order(&buy("AAPL", 20.0)).await.unwrap();
+        client.create_order(&buy("AAPL", 20.0)).await.unwrap();
+        let error = client.create_order(&buy("AAPL", 20.0)).await.unwrap_err();
+
+        assert!(error.is::<InsufficientBuyingPower>());
+        let submitted = broker.submitted();
+        assert_eq!(submitted.len(), 2);
+        assert!(submitted.iter().all(|order| order.quantity == 5.0));
+    }
+
+    #[tokio::test]
+    async fn refuses_orders_the_risk_checks_reject_without_submitting() {
+        let broker = MockTradingClient::with_cash(1_000.0);
+        let client = checked(
+            &broker,
+            RiskLimits {
+                restricted: ["GME".to_string()].into(),
+                ..RiskLimits::default()
+            },
+        );
+
+        let error = client.create_order(&buy("GME", 1.0)).await.unwrap_err();
+        assert_eq!(error.downcast_ref::<RiskViolation>().unwrap().symbol, "GME");
+        assert!(broker.submitted().is_empty());
+    }
+
+    #[tokio::test]
+    async fn releases_buying_power_set_aside_for_failed_submissions() {
+        let broker = MockTradingClient::with_cash(1_000.0);
+        let client = checked(&broker, RiskLimits::default());
+
+        broker.fail(MockCall::CreateOrder, "broker unavailable");
+        assert!(client.create_order(&buy("AAPL", 8.0)).await.is_err());
+        client.create_order(&buy("AAPL", 8.0)).await.unwrap();
+        assert_eq!(broker.submitted().len(), 2);
+    }
+}
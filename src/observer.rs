@@ -0,0 +1,130 @@
+use crate::datastructures::{
+    account::{Account, AccountActivity, Position},
+    asset::Asset,
+    client::{MarketDataClient, SubscriptionParams, TradingClient},
+    event::{EventBatch, ParseMode},
+    market::{Bar, BarAdjustment, Quote},
+    order::{CancelOutcome, Order, OrderResponse},
+};
+use async_trait::async_trait;
+use std::{error::Error, fmt};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+/// Returned for every order submission or cancel attempted through a `ReadOnlyClient`.
+#[derive(Debug, Clone)]
+pub struct ReadOnlyError {
+    pub operation: &'static str,
+}
+
+impl fmt::Display for ReadOnlyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Refused to {}: client is read-only", self.operation)
+    }
+}
+
+impl Error for ReadOnlyError {}
+
+/// Wraps a client for processes that watch an account but must never trade, such as dashboards and analytics
+/// jobs sharing the trading credentials. Order submissions and cancels fail with `ReadOnlyError` without
+/// reaching the wrapped client, which cannot be borrowed back out; everything else passes through.
+#[derive(Clone)]
+pub struct ReadOnlyClient<C> {
+    client: C,
+}
+
+impl<C: TradingClient> ReadOnlyClient<C> {
+    pub fn wrap(client: C) -> Self {
+        ReadOnlyClient { client }
+    }
+
+    fn refuse(operation: &'static str) -> Box<dyn Error> {
+        tracing::error!(operation, "Trading attempted through a read-only client");
+        Box::new(ReadOnlyError { operation })
+    }
+}
+
+#[async_trait]
+impl<C> TradingClient for ReadOnlyClient<C>
+where
+    C: TradingClient + Send + Sync,
+{
+    async fn create_order(&self, _order: &Order) -> Result<(), Box<dyn Error>> {
+        Err(Self::refuse("submit an order"))
+    }
+
+    async fn get_open_orders(&self) -> Result<Vec<OrderResponse>, Box<dyn Error>> {
+        self.client.get_open_orders().await
+    }
+
+    async fn get_order(&self, order_id: &str) -> Result<OrderResponse, Box<dyn Error>> {
+        self.client.get_order(order_id).await
+    }
+
+    async fn get_order_by_client_id(
+        &self,
+        client_order_id: &str,
+    ) -> Result<OrderResponse, Box<dyn Error>> {
+        self.client.get_order_by_client_id(client_order_id).await
+    }
+
+    async fn cancel_order(&self, _order_id: &str) -> Result<CancelOutcome, Box<dyn Error>> {
+        Err(Self::refuse("cancel an order"))
+    }
+
+    async fn get_asset(&self, symbol: &str) -> Result<Asset, Box<dyn Error>> {
+        self.client.get_asset(symbol).await
+    }
+
+    async fn list_assets(&self) -> Result<Vec<Asset>, Box<dyn Error>> {
+        self.client.list_assets().await
+    }
+
+    async fn get_account(&self) -> Result<Account, Box<dyn Error>> {
+        self.client.get_account().await
+    }
+
+    async fn get_positions(&self) -> Result<Vec<Position>, Box<dyn Error>> {
+        self.client.get_positions().await
+    }
+
+    async fn get_account_activities(
+        &self,
+        since: &str,
+    ) -> Result<Vec<AccountActivity>, Box<dyn Error>> {
+        self.client.get_account_activities(since).await
+    }
+}
+
+#[async_trait]
+impl<C> MarketDataClient for ReadOnlyClient<C>
+where
+    C: MarketDataClient + Send + Sync,
+{
+    async fn get_latest_quote(&self, symbol: &str) -> Result<Quote, Box<dyn Error>> {
+        self.client.get_latest_quote(symbol).await
+    }
+
+    async fn get_daily_bars(
+        &self,
+        symbol: &str,
+        start: &str,
+        end: &str,
+        adjustment: BarAdjustment,
+    ) -> Result<Vec<Bar>, Box<dyn Error>> {
+        self.client
+            .get_daily_bars(symbol, start, end, adjustment)
+            .await
+    }
+
+    async fn subscribe(
+        &self,
+        params: SubscriptionParams,
+    ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, Box<dyn Error>> {
+        self.client.subscribe(params).await
+    }
+
+    fn parse_frame(&self, frame: &str, mode: ParseMode) -> Result<EventBatch, serde_json::Error> {
+        self.client.parse_frame(frame, mode)
+    }
+}
@@ -0,0 +1,295 @@
+use crate::datastructures::{client::BookDepth, event::EventType};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+/// How the top of the book looks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookState {
+    Normal,
+    /// Best bid equals best ask.
+    Locked,
+    /// Best bid above best ask, usually a missed update.
+    Crossed,
+    /// One or both sides are empty.
+    OneSided,
+}
+
+impl fmt::Display for BookState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BookState::Normal => write!(f, "normal"),
+            BookState::Locked => write!(f, "locked"),
+            BookState::Crossed => write!(f, "crossed"),
+            BookState::OneSided => write!(f, "one-sided"),
+        }
+    }
+}
+
+/// Level-2 book for one symbol, maintained from the crypto orderbook feed. Levels are keyed by exact price, so the
+/// price in an update has to match the one it replaces, which holds for Alpaca's feed. A size of zero removes a level.
+#[derive(Debug, Clone)]
+pub struct OrderBook {
+    symbol: String,
+    // Keyed by the price's bit pattern, which orders the same way as the price for the non-negative prices quoted.
+    bids: BTreeMap<u64, f64>,
+    asks: BTreeMap<u64, f64>,
+    depth: BookDepth,
+    timestamp: Option<String>,
+}
+
+impl OrderBook {
+    pub fn new(symbol: impl Into<String>) -> Self {
+        OrderBook {
+            symbol: symbol.into(),
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            depth: BookDepth::Full,
+            timestamp: None,
+        }
+    }
+
+    /// Keeps only the best levels on each side after every update. Use the same depth as the subscription, since
+    /// levels trimmed from the feed are not backfilled and anything deeper would go stale.
+    pub fn with_depth(mut self, depth: BookDepth) -> Self {
+        self.depth = depth;
+        self.trim();
+        self
+    }
+
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    /// Timestamp of the last message applied.
+    pub fn timestamp(&self) -> Option<&str> {
+        self.timestamp.as_deref()
+    }
+
+    /// Replaces the whole book.
+    pub fn apply_snapshot(&mut self, bids: &[(f64, f64)], asks: &[(f64, f64)], timestamp: &str) {
+        self.bids.clear();
+        self.asks.clear();
+        self.apply_update(bids, asks, timestamp);
+    }
+
+    /// Sets the size at each given price level, removing levels with size zero.
+    pub fn apply_update(&mut self, bids: &[(f64, f64)], asks: &[(f64, f64)], timestamp: &str) {
+        for (levels, side) in [(bids, &mut self.bids), (asks, &mut self.asks)] {
+            for &(price, size) in levels {
+                if size <= 0.0 {
+                    side.remove(&price.to_bits());
+                } else {
+                    side.insert(price.to_bits(), size);
+                }
+            }
+        }
+        self.timestamp = Some(timestamp.to_string());
+        self.trim();
+    }
+
+    /// Applies an orderbook event for this symbol, as a snapshot when its reset flag is set and as an incremental
+    /// update otherwise. Returns false for other events.
+    pub fn update(&mut self, event: &EventType) -> bool {
+        match event {
+            EventType::OrderBook {
+                symbol,
+                bids,
+                asks,
+                reset,
+                timestamp,
+            } if *symbol == self.symbol => {
+                if *reset {
+                    self.apply_snapshot(bids, asks, timestamp);
+                } else {
+                    self.apply_update(bids, asks, timestamp);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+        self.timestamp = None;
+    }
+
+    /// Highest bid as (price, size).
+    pub fn best_bid(&self) -> Option<(f64, f64)> {
+        self.bids
+            .iter()
+            .next_back()
+            .map(|(&price, &size)| (f64::from_bits(price), size))
+    }
+
+    /// Lowest ask as (price, size).
+    pub fn best_ask(&self) -> Option<(f64, f64)> {
+        self.asks
+            .iter()
+            .next()
+            .map(|(&price, &size)| (f64::from_bits(price), size))
+    }
+
+    pub fn mid(&self) -> Option<f64> {
+        Some((self.best_bid()?.0 + self.best_ask()?.0) / 2.0)
+    }
+
+    pub fn spread(&self) -> Option<f64> {
+        Some(self.best_ask()?.0 - self.best_bid()?.0)
+    }
+
+    /// Up to `levels` bid levels, best first.
+    pub fn bids(&self, levels: usize) -> Vec<(f64, f64)> {
+        self.bids
+            .iter()
+            .rev()
+            .take(levels)
+            .map(|(&price, &size)| (f64::from_bits(price), size))
+            .collect()
+    }
+
+    /// Up to `levels` ask levels, best first.
+    pub fn asks(&self, levels: usize) -> Vec<(f64, f64)> {
+        self.asks
+            .iter()
+            .take(levels)
+            .map(|(&price, &size)| (f64::from_bits(price), size))
+            .collect()
+    }
+
+    /// Number of bid and ask levels in the book.
+    pub fn depth(&self) -> (usize, usize) {
+        (self.bids.len(), self.asks.len())
+    }
+
+    /// Total bid and ask size within the best `levels` levels of each side.
+    pub fn size_within(&self, levels: usize) -> (f64, f64) {
+        (
+            self.bids.values().rev().take(levels).sum(),
+            self.asks.values().take(levels).sum(),
+        )
+    }
+
+    pub fn state(&self) -> BookState {
+        match (self.best_bid(), self.best_ask()) {
+            (Some((bid, _)), Some((ask, _))) if bid > ask => BookState::Crossed,
+            (Some((bid, _)), Some((ask, _))) if bid == ask => BookState::Locked,
+            (Some(_), Some(_)) => BookState::Normal,
+            _ => BookState::OneSided,
+        }
+    }
+
+    pub fn is_crossed(&self) -> bool {
+        self.state() == BookState::Crossed
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.state() == BookState::Locked
+    }
+
+    fn trim(&mut self) {
+        let BookDepth::Top(depth) = self.depth else {
+            return;
+        };
+        while self.bids.len() > depth {
+            self.bids.pop_first();
+        }
+        while self.asks.len() > depth {
+            self.asks.pop_last();
+        }
+    }
+}
+
+/// Books for every symbol on the orderbook feed, created as their first message arrives.
+#[derive(Debug, Clone, Default)]
+pub struct OrderBooks {
+    books: HashMap<String, OrderBook>,
+    depth: BookDepth,
+}
+
+impl OrderBooks {
+    pub fn new(depth: BookDepth) -> Self {
+        OrderBooks {
+            books: HashMap::new(),
+            depth,
+        }
+    }
+
+    /// Applies an orderbook event and returns the updated book, warning if it left the book crossed.
+    /// Other events are ignored.
+    pub fn update(&mut self, event: &EventType) -> Option<&OrderBook> {
+        let EventType::OrderBook { symbol, .. } = event else {
+            return None;
+        };
+        let book = self
+            .books
+            .entry(symbol.clone())
+            .or_insert_with(|| OrderBook::new(symbol.clone()).with_depth(self.depth));
+        book.update(event);
+        if book.is_crossed() {
+            tracing::warn!(%symbol, bid = ?book.best_bid(), ask = ?book.best_ask(), "Order book crossed");
+        }
+        Some(book)
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<&OrderBook> {
+        self.books.get(symbol)
+    }
+
+    /// Drops a symbol's book. Not needed after a reconnect, as the snapshot sent on resubscribing resets it.
+    pub fn reset(&mut self, symbol: &str) {
+        self.books.remove(symbol);
+    }
+
+    pub fn clear(&mut self) {
+        self.books.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_best_levels_at_a_depth() {
+        let mut book = OrderBook::new("BTC/USD").with_depth(BookDepth::Top(2));
+        book.apply_snapshot(
+            &[(100.0, 1.0), (99.0, 2.0)],
+            &[(101.0, 1.0), (102.0, 2.0)],
+            "t1",
+        );
+        // An update beyond the kept depth, then the removal of the best level on each side.
+        book.apply_update(&[(98.0, 3.0)], &[(103.0, 3.0)], "t2");
+        assert_eq!(book.bids(5), [(100.0, 1.0), (99.0, 2.0)]);
+        book.apply_update(
+            &[(100.0, 0.0), (98.0, 3.0)],
+            &[(101.0, 0.0), (103.0, 3.0)],
+            "t3",
+        );
+        assert_eq!(book.bids(5), [(99.0, 2.0), (98.0, 3.0)]);
+        assert_eq!(book.asks(5), [(102.0, 2.0), (103.0, 3.0)]);
+        assert_eq!(book.timestamp(), Some("t3"));
+    }
+
+    #[test]
+    fn resets_on_snapshots() {
+        let mut book = OrderBook::new("BTC/USD");
+        let event = |reset, bids: &[(f64, f64)]| EventType::OrderBook {
+            symbol: "BTC/USD".to_string(),
+            bids: bids.to_vec(),
+            asks: vec![(101.0, 1.0)],
+            reset,
+            timestamp: String::new(),
+        };
+        assert!(book.update(&event(true, &[(100.0, 1.0), (99.0, 1.0)])));
+        assert!(book.update(&event(false, &[(99.5, 1.0)])));
+        assert_eq!(book.depth(), (3, 1));
+        assert!(book.update(&event(true, &[(98.0, 1.0)])));
+        assert_eq!(book.depth(), (1, 1));
+        assert_eq!(book.state(), BookState::Normal);
+        assert!(!OrderBook::new("ETH/USD").update(&event(true, &[])));
+    }
+}
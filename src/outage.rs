@@ -0,0 +1,254 @@
+use crate::{
+    datastructures::{client::TradingClient, order::Order},
+    http::CircuitOpen,
+    journal::{IntentResolution, OrderJournal, QueuedIntent},
+    time,
+};
+use std::{
+    error::Error,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// What happens to orders queued during an outage once the broker is reachable again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryPolicy {
+    /// Keep every queued order until it is resubmitted or discarded by hand.
+    Review,
+    /// Resubmit queued orders younger than `max_age`. Older ones are kept for review, since the
+    /// market has likely moved on.
+    Resubmit { max_age: Duration },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct OutageConfig {
+    /// Consecutive broker failures that switch to queued-intent mode.
+    pub failure_threshold: u32,
+    pub recovery: RecoveryPolicy,
+}
+
+impl Default for OutageConfig {
+    fn default() -> Self {
+        OutageConfig {
+            failure_threshold: 3,
+            recovery: RecoveryPolicy::Review,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Submission {
+    Sent,
+    /// The broker could not be reached; the order was journaled instead.
+    Queued(Box<QueuedIntent>),
+}
+
+/// Outcome of `OutageGuard::recover`.
+#[derive(Debug, Clone, Default)]
+pub struct RecoveryReport {
+    pub resubmitted: Vec<QueuedIntent>,
+    /// Intents left for review, either by policy or because resubmitting them failed.
+    pub pending: Vec<QueuedIntent>,
+}
+
+#[derive(Default)]
+struct OutageState {
+    consecutive_failures: u32,
+    since: Option<String>,
+}
+
+/// Keeps strategies' orders from being dropped while the broker's order endpoints are down.
+/// Transport errors and open circuit breaker errors count as broker failures; since `create_order` does not
+/// surface 5xx responses as errors, configure a circuit breaker for them to count too. Any order that fails this
+/// way is journaled with a timestamp, and once failures reach the threshold orders are queued without being sent
+/// until `recover` finds the broker reachable again.
+#[derive(Clone)]
+pub struct OutageGuard {
+    config: OutageConfig,
+    journal: OrderJournal,
+    state: Arc<Mutex<OutageState>>,
+}
+
+impl OutageGuard {
+    /// Queued orders are kept in `journal`; use a file-backed journal to keep them across restarts.
+    pub fn new(config: OutageConfig, journal: OrderJournal) -> Self {
+        OutageGuard {
+            config,
+            journal,
+            state: Arc::new(Mutex::new(OutageState::default())),
+        }
+    }
+
+    /// When the current outage was detected, if there is one.
+    pub fn outage_since(&self) -> Option<String> {
+        self.state.lock().unwrap().since.clone()
+    }
+
+    pub fn in_outage(&self) -> bool {
+        self.state.lock().unwrap().since.is_some()
+    }
+
+    /// Orders queued and not yet resubmitted or discarded, oldest first.
+    pub fn pending(&self) -> Vec<QueuedIntent> {
+        self.journal.pending_intents()
+    }
+
+    /// Sends the order, or queues it if the broker is down. Orders without a client order id are given one,
+    /// so a queued order can be resubmitted without risk of a duplicate if the original did reach the broker.
+    pub async fn submit<C: TradingClient>(
+        &self,
+        client: &C,
+        order: &Order,
+    ) -> Result<Submission, Box<dyn Error>> {
+        let mut order = order.clone();
+        let intent_id = order
+            .client_order_id
+            .get_or_insert_with(|| format!("{:032x}", rand::random::<u128>()))
+            .clone();
+
+        if self.in_outage() {
+            return Ok(Submission::Queued(Box::new(self.queue(
+                intent_id,
+                order,
+                "Broker outage",
+            )?)));
+        }
+
+        match client.create_order(&order).await {
+            Ok(()) => {
+                self.state.lock().unwrap().consecutive_failures = 0;
+                Ok(Submission::Sent)
+            }
+            Err(e) if is_outage_error(e.as_ref()) => {
+                self.record_failure();
+                Ok(Submission::Queued(Box::new(self.queue(
+                    intent_id,
+                    order,
+                    &e.to_string(),
+                )?)))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Probes the broker and, if it answers, leaves outage mode and applies the recovery policy to the queue.
+    /// Returns the probe's error while the broker is still down.
+    pub async fn recover<C: TradingClient>(
+        &self,
+        client: &C,
+    ) -> Result<RecoveryReport, Box<dyn Error>> {
+        if let Err(e) = client.get_account().await {
+            if is_outage_error(e.as_ref()) {
+                self.record_failure();
+            }
+            return Err(e);
+        }
+        if let Some(since) = self.state.lock().unwrap().since.take() {
+            tracing::info!(%since, "Broker outage over");
+        }
+        self.state.lock().unwrap().consecutive_failures = 0;
+
+        let mut report = RecoveryReport::default();
+        for intent in self.pending() {
+            let resubmit = match self.config.recovery {
+                RecoveryPolicy::Review => false,
+                RecoveryPolicy::Resubmit { max_age } => {
+                    time::age(&intent.queued_at).is_some_and(|age| age <= max_age)
+                }
+            };
+            if !resubmit || self.in_outage() {
+                report.pending.push(intent);
+                continue;
+            }
+
+            match self.resubmit(client, &intent.intent_id).await {
+                Ok(()) => report.resubmitted.push(intent),
+                Err(e) => {
+                    tracing::warn!(intent_id = %intent.intent_id, error = %e, "Failed to resubmit queued order");
+                    report.pending.push(intent);
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Sends a queued order and removes it from the queue. It stays queued if sending fails.
+    pub async fn resubmit<C: TradingClient>(
+        &self,
+        client: &C,
+        intent_id: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let intent = self
+            .pending()
+            .into_iter()
+            .find(|intent| intent.intent_id == intent_id)
+            .ok_or_else(|| format!("No queued order {}", intent_id))?;
+
+        let mut order = intent.order;
+        order.metadata = intent.metadata;
+        if let Err(e) = client.create_order(&order).await {
+            if is_outage_error(e.as_ref()) {
+                self.record_failure();
+            }
+            return Err(e);
+        }
+        self.journal
+            .resolve_intent(intent_id, IntentResolution::Resubmitted)?;
+        Ok(())
+    }
+
+    /// Drops a queued order without sending it. Returns false if there was no such order.
+    pub fn discard(&self, intent_id: &str) -> Result<bool, Box<dyn Error>> {
+        if !self
+            .pending()
+            .iter()
+            .any(|intent| intent.intent_id == intent_id)
+        {
+            return Ok(false);
+        }
+        self.journal
+            .resolve_intent(intent_id, IntentResolution::Discarded)?;
+        Ok(true)
+    }
+
+    fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures += 1;
+        if state.since.is_none() && state.consecutive_failures >= self.config.failure_threshold {
+            let since = time::format_rfc3339(time::now_nanos());
+            tracing::error!(
+                failures = state.consecutive_failures,
+                "Broker outage detected; queueing orders"
+            );
+            state.since = Some(since);
+        }
+    }
+
+    fn queue(
+        &self,
+        intent_id: String,
+        order: Order,
+        reason: &str,
+    ) -> Result<QueuedIntent, Box<dyn Error>> {
+        let intent = QueuedIntent {
+            intent_id,
+            metadata: order.metadata.clone(),
+            order,
+            reason: reason.to_string(),
+            queued_at: time::format_rfc3339(time::now_nanos()),
+        };
+        tracing::warn!(intent_id = %intent.intent_id, symbol = %intent.order.symbol, reason, "Queued order");
+        self.journal.record_intent(&intent)?;
+        Ok(intent)
+    }
+}
+
+/// Whether the error means the broker could not be reached, as opposed to the request being refused.
+pub(crate) fn is_outage_error(error: &(dyn Error + 'static)) -> bool {
+    if error.is::<CircuitOpen>() {
+        return true;
+    }
+    error
+        .downcast_ref::<reqwest::Error>()
+        .is_some_and(|e| e.is_connect() || e.is_timeout() || e.is_request())
+}
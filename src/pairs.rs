@@ -0,0 +1,386 @@
+use crate::datastructures::{
+    client::TradingClient,
+    event::EventType,
+    order::{Order, OrderSide, OrderType, TimeInForce},
+};
+use std::{collections::VecDeque, error::Error};
+
+/// Metadata key holding the pair's name on each leg order.
+pub const PAIR_TAG: &str = "pair";
+
+/// Least-squares slope of `a` on `b`: how many units of `b` hedge one unit of `a`. `None` without at least two
+/// observations or when `b` does not vary.
+pub fn hedge_ratio(a: &[f64], b: &[f64]) -> Option<f64> {
+    let n = a.len().min(b.len());
+    if n < 2 {
+        return None;
+    }
+    let mean_a = a[..n].iter().sum::<f64>() / n as f64;
+    let mean_b = b[..n].iter().sum::<f64>() / n as f64;
+    let (mut covariance, mut variance) = (0.0, 0.0);
+    for (x, y) in a[..n].iter().zip(&b[..n]) {
+        covariance += (x - mean_a) * (y - mean_b);
+        variance += (y - mean_b) * (y - mean_b);
+    }
+    (variance > 0.0).then(|| covariance / variance)
+}
+
+#[derive(Debug, Clone)]
+pub struct PairConfig {
+    /// Bar closes of both symbols kept for the hedge ratio and the spread's mean and deviation.
+    pub lookback: usize,
+    /// Fixed hedge ratio. `None` estimates it from the lookback while flat and keeps it while a position is open.
+    pub hedge_ratio: Option<f64>,
+    /// Trade fractional quantities. Otherwise both legs are rounded to whole shares.
+    pub fractional: bool,
+}
+
+impl Default for PairConfig {
+    fn default() -> Self {
+        PairConfig {
+            lookback: 60,
+            hedge_ratio: None,
+            fractional: false,
+        }
+    }
+}
+
+/// Which way the pair is held. The spread is the first symbol's price less the hedge ratio times the second's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PairDirection {
+    /// Long the first symbol, short the second, for a spread expected to rise.
+    LongSpread,
+    /// Short the first symbol, long the second, for a spread expected to fall.
+    ShortSpread,
+}
+
+/// One side of an open pair.
+#[derive(Debug, Clone)]
+pub struct PairLeg {
+    pub symbol: String,
+    /// Signed: negative for the short leg.
+    pub quantity: f64,
+    /// Average fill price once read back from the broker, the price when the leg was sent until then.
+    pub entry_price: f64,
+    pub client_order_id: String,
+}
+
+impl PairLeg {
+    fn pnl(&self, price: f64) -> f64 {
+        self.quantity * (price - self.entry_price)
+    }
+}
+
+/// A long/short pair managed as one position: the hedge ratio and the spread's z-score come from bar closes
+/// of both symbols, both legs are sent together, and both are unwound together. If one leg of an entry fails,
+/// the other is reversed so the pair is never left half open.
+#[derive(Debug, Clone)]
+pub struct PairPosition {
+    name: String,
+    symbols: [String; 2],
+    config: PairConfig,
+    /// Bar closes of both symbols at the same timestamps, oldest first.
+    history: VecDeque<(f64, f64)>,
+    /// Latest bar close of each symbol with its timestamp, waiting for the other symbol's bar.
+    pending: [Option<(String, f64)>; 2],
+    prices: [Option<f64>; 2],
+    /// Hedge ratio the open position was sized with.
+    locked_ratio: Option<f64>,
+    direction: Option<PairDirection>,
+    legs: Vec<PairLeg>,
+    realized: f64,
+}
+
+impl PairPosition {
+    pub fn new(
+        name: impl Into<String>,
+        first: impl Into<String>,
+        second: impl Into<String>,
+        config: PairConfig,
+    ) -> Self {
+        PairPosition {
+            name: name.into(),
+            symbols: [first.into(), second.into()],
+            config,
+            history: VecDeque::new(),
+            pending: [None, None],
+            prices: [None, None],
+            locked_ratio: None,
+            direction: None,
+            legs: vec![],
+            realized: 0.0,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn symbols(&self) -> (&str, &str) {
+        (&self.symbols[0], &self.symbols[1])
+    }
+
+    /// Updates prices from trades, quote midpoints and bars of either symbol. Bars of both symbols with the same
+    /// timestamp are added to the history. Returns false for events about other symbols.
+    pub fn update(&mut self, event: &EventType) -> bool {
+        let Some(index) = event
+            .symbol()
+            .and_then(|symbol| self.symbols.iter().position(|s| s == symbol))
+        else {
+            return false;
+        };
+        match event {
+            EventType::Trade { price, .. } => self.prices[index] = Some(*price),
+            EventType::Quote {
+                bid_price,
+                ask_price,
+                ..
+            } if *bid_price > 0.0 && *ask_price > 0.0 => {
+                self.prices[index] = Some((bid_price + ask_price) / 2.0);
+            }
+            EventType::Bar {
+                close, timestamp, ..
+            }
+            | EventType::DailyBar {
+                close, timestamp, ..
+            } => {
+                self.prices[index] = Some(*close);
+                self.pending[index] = Some((timestamp.clone(), *close));
+                if let [Some((first_at, first)), Some((second_at, second))] = &self.pending {
+                    if first_at == second_at {
+                        let (first, second) = (*first, *second);
+                        self.record(first, second);
+                        self.pending = [None, None];
+                    }
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Adds a pair of closes to the history directly, e.g. when seeding it from historical bars.
+    pub fn record(&mut self, first: f64, second: f64) {
+        self.history.push_back((first, second));
+        while self.history.len() > self.config.lookback {
+            self.history.pop_front();
+        }
+        self.prices = [Some(first), Some(second)];
+    }
+
+    /// Ratio the position is or would be sized with.
+    pub fn hedge_ratio(&self) -> Option<f64> {
+        if let Some(ratio) = self.locked_ratio.or(self.config.hedge_ratio) {
+            return Some(ratio);
+        }
+        let (first, second): (Vec<f64>, Vec<f64>) = self.history.iter().copied().unzip();
+        hedge_ratio(&first, &second)
+    }
+
+    /// Current spread: the first price less the hedge ratio times the second.
+    pub fn spread(&self) -> Option<f64> {
+        Some(self.prices[0]? - self.hedge_ratio()? * self.prices[1]?)
+    }
+
+    /// Standard deviations the current spread is from its mean over the history.
+    pub fn zscore(&self) -> Option<f64> {
+        let ratio = self.hedge_ratio()?;
+        let spreads: Vec<f64> = self
+            .history
+            .iter()
+            .map(|(first, second)| first - ratio * second)
+            .collect();
+        if spreads.len() < 2 {
+            return None;
+        }
+        let mean = spreads.iter().sum::<f64>() / spreads.len() as f64;
+        let variance =
+            spreads.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / spreads.len() as f64;
+        let deviation = variance.sqrt();
+        if deviation <= 0.0 {
+            return None;
+        }
+        Some((self.spread()? - mean) / deviation)
+    }
+
+    pub fn direction(&self) -> Option<PairDirection> {
+        self.direction
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.direction.is_some()
+    }
+
+    pub fn legs(&self) -> &[PairLeg] {
+        &self.legs
+    }
+
+    /// Unrealized P/L of both legs at the latest prices.
+    pub fn unrealized_pnl(&self) -> f64 {
+        self.legs
+            .iter()
+            .zip(&self.prices)
+            .map(|(leg, price)| price.map_or(0.0, |price| leg.pnl(price)))
+            .sum()
+    }
+
+    /// P/L of pairs closed so far, at the prices they were unwound at.
+    pub fn realized_pnl(&self) -> f64 {
+        self.realized
+    }
+
+    pub fn pnl(&self) -> f64 {
+        self.realized + self.unrealized_pnl()
+    }
+
+    /// Opens the pair with about `notional` in the first symbol and the hedge ratio's worth of the second, both
+    /// at market. If either leg is rejected the other is reversed and the pair stays flat.
+    pub async fn open<C: TradingClient + ?Sized>(
+        &mut self,
+        client: &C,
+        direction: PairDirection,
+        notional: f64,
+    ) -> Result<(), Box<dyn Error>> {
+        if self.is_open() {
+            return Err("Pair is already open".into());
+        }
+        let ratio = self.hedge_ratio().ok_or("No hedge ratio yet")?;
+        let (Some(first_price), Some(second_price)) = (self.prices[0], self.prices[1]) else {
+            return Err("No price for both symbols yet".into());
+        };
+
+        let round = |quantity: f64| {
+            if self.config.fractional {
+                quantity
+            } else {
+                quantity.round()
+            }
+        };
+        let first_quantity = round(notional / first_price);
+        let second_quantity = round(first_quantity * ratio);
+        if first_quantity <= 0.0 || second_quantity <= 0.0 {
+            return Err("Notional too small for whole shares of both legs".into());
+        }
+        let sign = match direction {
+            PairDirection::LongSpread => 1.0,
+            PairDirection::ShortSpread => -1.0,
+        };
+        let entry = rand::random::<u32>();
+        let legs = vec![
+            self.leg(0, sign * first_quantity, first_price, entry),
+            self.leg(1, -sign * second_quantity, second_price, entry),
+        ];
+
+        let orders = legs
+            .iter()
+            .map(|leg| self.order(&leg.symbol, leg.quantity, &leg.client_order_id))
+            .collect::<Result<Vec<_>, _>>()?;
+        let (first, second) = futures_util::join!(
+            client.create_order(&orders[0]),
+            client.create_order(&orders[1])
+        );
+        let (first, second) = (
+            first.map_err(|e| e.to_string()),
+            second.map_err(|e| e.to_string()),
+        );
+        if let Err(e) = first.as_ref().and(second.as_ref()) {
+            // Reverse whichever leg went through.
+            for (leg, sent) in legs.iter().zip([first.is_ok(), second.is_ok()]) {
+                if !sent {
+                    continue;
+                }
+                let id = format!("{}-unwind", leg.client_order_id);
+                let reverse = self.order(&leg.symbol, -leg.quantity, &id)?;
+                if let Err(unwind) = client.create_order(&reverse).await {
+                    tracing::error!(pair = %self.name, symbol = %leg.symbol, error = %unwind, "Failed to reverse pair leg");
+                }
+            }
+            return Err(format!("Pair {} not opened: {}", self.name, e).into());
+        }
+
+        tracing::info!(pair = %self.name, ?direction, ratio, "Pair opened");
+        self.locked_ratio = Some(ratio);
+        self.direction = Some(direction);
+        self.legs = legs;
+        Ok(())
+    }
+
+    /// Reads both legs back from the broker and takes their filled quantities and average prices.
+    pub async fn sync<C: TradingClient + ?Sized>(
+        &mut self,
+        client: &C,
+    ) -> Result<(), Box<dyn Error>> {
+        for leg in &mut self.legs {
+            let order = client.get_order_by_client_id(&leg.client_order_id).await?;
+            if let Some(price) = order.filled_avg_price.filter(|_| order.filled_qty > 0.0) {
+                leg.entry_price = price;
+                leg.quantity = order.filled_qty.copysign(leg.quantity);
+            }
+        }
+        Ok(())
+    }
+
+    /// Unwinds both legs together at market and returns the P/L realized at the latest prices.
+    pub async fn close<C: TradingClient + ?Sized>(
+        &mut self,
+        client: &C,
+    ) -> Result<f64, Box<dyn Error>> {
+        if !self.is_open() {
+            return Err("Pair is not open".into());
+        }
+        let orders = self
+            .legs
+            .iter()
+            .map(|leg| {
+                let id = format!("{}-close", leg.client_order_id);
+                self.order(&leg.symbol, -leg.quantity, &id)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let (first, second) = futures_util::join!(
+            client.create_order(&orders[0]),
+            client.create_order(&orders[1])
+        );
+        // A failed leg is left open; the pair stays open so closing can be retried.
+        first.map_err(|e| format!("Failed to close {}: {}", orders[0].symbol, e))?;
+        second.map_err(|e| format!("Failed to close {}: {}", orders[1].symbol, e))?;
+
+        let pnl = self.unrealized_pnl();
+        tracing::info!(pair = %self.name, pnl, "Pair closed");
+        self.realized += pnl;
+        self.direction = None;
+        self.locked_ratio = None;
+        self.legs.clear();
+        Ok(pnl)
+    }
+
+    fn leg(&self, index: usize, quantity: f64, price: f64, entry: u32) -> PairLeg {
+        PairLeg {
+            symbol: self.symbols[index].clone(),
+            quantity,
+            entry_price: price,
+            client_order_id: format!("{}-{:08x}-{}", self.name, entry, index),
+        }
+    }
+
+    /// Market order for a signed quantity.
+    fn order(
+        &self,
+        symbol: &str,
+        quantity: f64,
+        client_order_id: &str,
+    ) -> Result<Order, &'static str> {
+        Order::builder()
+            .symbol(symbol)
+            .quantity(quantity.abs())
+            .side(if quantity > 0.0 {
+                OrderSide::Buy
+            } else {
+                OrderSide::Sell
+            })
+            .order_type(OrderType::Market)
+            .time_in_force(TimeInForce::Day)
+            .client_order_id(client_order_id)
+            .tag(PAIR_TAG, self.name.as_str())
+            .build()
+    }
+}
@@ -0,0 +1,963 @@
+//! Minimal Parquet files: flat schemas of boolean, double and string columns, written uncompressed with plain
+//! encoding and one data page per column chunk. Files written here open in pandas, DuckDB, Spark and anything
+//! else built on a full Parquet implementation; the reader only takes files written here.
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufWriter, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+const MAGIC: &[u8; 4] = b"PAR1";
+
+/// Nesting allowed when reading metadata, so a corrupt file cannot exhaust the stack.
+const MAX_DEPTH: usize = 32;
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ColumnType {
+    Boolean,
+    Double,
+    /// UTF-8 text.
+    String,
+}
+
+impl ColumnType {
+    /// The physical type's number in the format.
+    fn physical(self) -> i32 {
+        match self {
+            ColumnType::Boolean => 0,
+            ColumnType::Double => 5,
+            ColumnType::String => 6,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Column {
+    pub name: String,
+    pub column_type: ColumnType,
+    /// Whether the column may hold nulls.
+    pub optional: bool,
+}
+
+impl Column {
+    pub fn required(name: &str, column_type: ColumnType) -> Self {
+        Column {
+            name: name.to_string(),
+            column_type,
+            optional: false,
+        }
+    }
+
+    pub fn optional(name: &str, column_type: ColumnType) -> Self {
+        Column {
+            name: name.to_string(),
+            column_type,
+            optional: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    Null,
+    Boolean(bool),
+    Double(f64),
+    String(String),
+}
+
+impl Value {
+    fn fits(&self, column: &Column) -> bool {
+        matches!(
+            (self, column.column_type),
+            (Value::Boolean(_), ColumnType::Boolean)
+                | (Value::Double(_), ColumnType::Double)
+                | (Value::String(_), ColumnType::String)
+        ) || (*self == Value::Null && column.optional)
+    }
+
+    /// Bytes the value takes in a page, for sizing files.
+    fn encoded_len(&self) -> u64 {
+        match self {
+            Value::Null | Value::Boolean(_) => 1,
+            Value::Double(_) => 8,
+            Value::String(text) => 4 + text.len() as u64,
+        }
+    }
+}
+
+fn varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+/// Compact-protocol Thrift element types.
+const BOOLEAN_TRUE: u8 = 1;
+const BOOLEAN_FALSE: u8 = 2;
+const I32: u8 = 5;
+const I64: u8 = 6;
+const BINARY: u8 = 8;
+const LIST: u8 = 9;
+const STRUCT: u8 = 12;
+
+/// A Thrift struct in the compact protocol, which is how Parquet metadata is stored.
+#[derive(Default)]
+struct ThriftStruct {
+    bytes: Vec<u8>,
+    last_field: i16,
+}
+
+impl ThriftStruct {
+    fn field(&mut self, id: i16, kind: u8) {
+        match id - self.last_field {
+            delta @ 1..=15 => self.bytes.push((delta as u8) << 4 | kind),
+            _ => {
+                self.bytes.push(kind);
+                varint(&mut self.bytes, zigzag(id as i64));
+            }
+        }
+        self.last_field = id;
+    }
+
+    fn list_header(&mut self, id: i16, kind: u8, length: usize) {
+        self.field(id, LIST);
+        if length < 15 {
+            self.bytes.push((length as u8) << 4 | kind);
+        } else {
+            self.bytes.push(0xf0 | kind);
+            varint(&mut self.bytes, length as u64);
+        }
+    }
+
+    fn i32(mut self, id: i16, value: i32) -> Self {
+        self.field(id, I32);
+        varint(&mut self.bytes, zigzag(value as i64));
+        self
+    }
+
+    fn i64(mut self, id: i16, value: i64) -> Self {
+        self.field(id, I64);
+        varint(&mut self.bytes, zigzag(value));
+        self
+    }
+
+    fn binary(mut self, id: i16, value: &[u8]) -> Self {
+        self.field(id, BINARY);
+        varint(&mut self.bytes, value.len() as u64);
+        self.bytes.extend_from_slice(value);
+        self
+    }
+
+    fn structure(mut self, id: i16, value: ThriftStruct) -> Self {
+        self.field(id, STRUCT);
+        self.bytes.extend(value.finish());
+        self
+    }
+
+    fn i32_list(mut self, id: i16, values: &[i32]) -> Self {
+        self.list_header(id, I32, values.len());
+        for value in values {
+            varint(&mut self.bytes, zigzag(*value as i64));
+        }
+        self
+    }
+
+    fn binary_list(mut self, id: i16, values: &[&[u8]]) -> Self {
+        self.list_header(id, BINARY, values.len());
+        for value in values {
+            varint(&mut self.bytes, value.len() as u64);
+            self.bytes.extend_from_slice(value);
+        }
+        self
+    }
+
+    fn struct_list(mut self, id: i16, values: Vec<ThriftStruct>) -> Self {
+        self.list_header(id, STRUCT, values.len());
+        for value in values {
+            self.bytes.extend(value.finish());
+        }
+        self
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.bytes.push(0);
+        self.bytes
+    }
+}
+
+/// A decoded Thrift value. Booleans, doubles and maps appear in no field the reader needs, so they are
+/// skipped over.
+#[derive(Debug)]
+enum Thrift {
+    Skipped,
+    Int(i64),
+    Binary(Vec<u8>),
+    List(Vec<Thrift>),
+    Struct(HashMap<i16, Thrift>),
+}
+
+impl Thrift {
+    fn get(&self, id: i16) -> Option<&Thrift> {
+        match self {
+            Thrift::Struct(fields) => fields.get(&id),
+            _ => None,
+        }
+    }
+
+    fn int(&self, id: i16) -> Option<i64> {
+        match self.get(id)? {
+            Thrift::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn binary(&self, id: i16) -> Option<&[u8]> {
+        match self.get(id)? {
+            Thrift::Binary(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn list(&self, id: i16) -> &[Thrift] {
+        match self.get(id) {
+            Some(Thrift::List(values)) => values,
+            _ => &[],
+        }
+    }
+}
+
+struct ThriftReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ThriftReader<'a> {
+    fn byte(&mut self) -> io::Result<u8> {
+        let (byte, rest) = self
+            .bytes
+            .split_first()
+            .ok_or_else(|| invalid("Truncated Parquet metadata"))?;
+        self.bytes = rest;
+        Ok(*byte)
+    }
+
+    fn take(&mut self, length: usize) -> io::Result<&'a [u8]> {
+        if length > self.bytes.len() {
+            return Err(invalid("Truncated Parquet metadata"));
+        }
+        let (taken, rest) = self.bytes.split_at(length);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn varint(&mut self) -> io::Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid("Parquet varint is too long"))
+    }
+
+    fn signed(&mut self) -> io::Result<i64> {
+        let value = self.varint()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    fn value(&mut self, kind: u8, depth: usize) -> io::Result<Thrift> {
+        if depth > MAX_DEPTH {
+            return Err(invalid("Parquet metadata is nested too deeply"));
+        }
+        Ok(match kind {
+            BOOLEAN_TRUE | BOOLEAN_FALSE => Thrift::Skipped,
+            3 => Thrift::Int(self.byte()? as i8 as i64),
+            4..=6 => Thrift::Int(self.signed()?),
+            7 => {
+                self.take(8)?;
+                Thrift::Skipped
+            }
+            BINARY => {
+                let length = self.varint()? as usize;
+                Thrift::Binary(self.take(length)?.to_vec())
+            }
+            LIST | 10 => {
+                let header = self.byte()?;
+                let length = match header >> 4 {
+                    15 => self.varint()? as usize,
+                    length => length as usize,
+                };
+                let kind = header & 0x0f;
+                let mut values = Vec::with_capacity(length.min(self.bytes.len()));
+                for _ in 0..length {
+                    values.push(match kind {
+                        // List elements carry booleans as a byte each.
+                        BOOLEAN_TRUE | BOOLEAN_FALSE => {
+                            self.byte()?;
+                            Thrift::Skipped
+                        }
+                        kind => self.value(kind, depth + 1)?,
+                    });
+                }
+                Thrift::List(values)
+            }
+            11 => {
+                let length = self.varint()? as usize;
+                if length > 0 {
+                    let kinds = self.byte()?;
+                    for _ in 0..length {
+                        self.value(kinds >> 4, depth + 1)?;
+                        self.value(kinds & 0x0f, depth + 1)?;
+                    }
+                }
+                Thrift::Skipped
+            }
+            STRUCT => self.structure(depth + 1)?,
+            kind => return Err(invalid(format!("Unknown Thrift type {}", kind))),
+        })
+    }
+
+    fn structure(&mut self, depth: usize) -> io::Result<Thrift> {
+        let mut fields = HashMap::new();
+        let mut last_field = 0i16;
+        loop {
+            let header = self.byte()?;
+            if header == 0 {
+                return Ok(Thrift::Struct(fields));
+            }
+            let id = match header >> 4 {
+                0 => self.signed()? as i16,
+                delta => last_field + delta as i16,
+            };
+            last_field = id;
+            fields.insert(id, self.value(header & 0x0f, depth)?);
+        }
+    }
+}
+
+/// Encodes levels or booleans as runs of repeated values, the RLE half of the format's hybrid encoding.
+fn rle(values: &[u8], bytes: &mut Vec<u8>) {
+    let mut i = 0;
+    while i < values.len() {
+        let run = values[i..]
+            .iter()
+            .take_while(|value| **value == values[i])
+            .count();
+        varint(bytes, (run as u64) << 1);
+        bytes.push(values[i]);
+        i += run;
+    }
+}
+
+/// Decodes `count` one-bit values in the RLE/bit-packed hybrid encoding.
+fn unrle(bytes: &[u8], count: usize) -> io::Result<Vec<u8>> {
+    let mut reader = ThriftReader { bytes };
+    let mut values = Vec::with_capacity(count);
+    while values.len() < count {
+        let header = reader.varint()?;
+        if header & 1 == 0 {
+            let run = (header >> 1) as usize;
+            let value = reader.byte()?;
+            values.extend(std::iter::repeat_n(value, run.min(count - values.len())));
+        } else {
+            let packed = reader.take((header >> 1) as usize)?;
+            for byte in packed {
+                for bit in 0..8 {
+                    if values.len() < count {
+                        values.push(byte >> bit & 1);
+                    }
+                }
+            }
+        }
+    }
+    Ok(values)
+}
+
+/// Where a column chunk was written.
+struct Chunk {
+    offset: u64,
+    length: u64,
+    values: usize,
+}
+
+struct RowGroup {
+    rows: usize,
+    chunks: Vec<Chunk>,
+}
+
+/// Writes rows to a Parquet file. Rows are buffered and written as a row group every `rows_per_group` rows
+/// or on `flush`. The file is only readable once `finish` writes the footer; dropping the writer finishes it.
+pub(crate) struct ParquetWriter {
+    writer: BufWriter<File>,
+    columns: Vec<Column>,
+    /// Values of the rows not yet written, by column.
+    pending: Vec<Vec<Value>>,
+    pending_bytes: u64,
+    rows_per_group: usize,
+    row_groups: Vec<RowGroup>,
+    offset: u64,
+    finished: bool,
+}
+
+impl ParquetWriter {
+    /// Creates the file at `path`, replacing any file already there.
+    pub fn create(
+        path: impl AsRef<Path>,
+        columns: Vec<Column>,
+        rows_per_group: usize,
+    ) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        Ok(ParquetWriter {
+            writer,
+            pending: vec![vec![]; columns.len()],
+            columns,
+            pending_bytes: 0,
+            rows_per_group: rows_per_group.max(1),
+            row_groups: vec![],
+            offset: MAGIC.len() as u64,
+            finished: false,
+        })
+    }
+
+    /// Size of the file so far, counting rows not yet written.
+    pub fn bytes(&self) -> u64 {
+        self.offset + self.pending_bytes
+    }
+
+    /// One value per column, in schema order.
+    pub fn write_row(&mut self, row: Vec<Value>) -> io::Result<()> {
+        if self.finished {
+            return Err(io::Error::other("Parquet file is already finished"));
+        }
+        if row.len() != self.columns.len() {
+            return Err(invalid("Row does not match the Parquet schema"));
+        }
+        if let Some(column) = self
+            .columns
+            .iter()
+            .zip(&row)
+            .find_map(|(column, value)| (!value.fits(column)).then_some(column))
+        {
+            return Err(invalid(format!(
+                "Wrong value type for column {}",
+                column.name
+            )));
+        }
+        for (values, value) in self.pending.iter_mut().zip(row) {
+            self.pending_bytes += value.encoded_len();
+            values.push(value);
+        }
+        if self.pending[0].len() >= self.rows_per_group {
+            self.write_row_group()?;
+        }
+        Ok(())
+    }
+
+    /// Writes buffered rows as a row group. The file still needs `finish` to be readable.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.write_row_group()?;
+        self.writer.flush()
+    }
+
+    fn write_row_group(&mut self) -> io::Result<()> {
+        let rows = self.pending.first().map_or(0, Vec::len);
+        if rows == 0 {
+            return Ok(());
+        }
+        let mut chunks = Vec::with_capacity(self.columns.len());
+        for (column, values) in self.columns.iter().zip(&mut self.pending) {
+            let page = encode_page(column, values);
+            let header = ThriftStruct::default()
+                .i32(1, 0)
+                .i32(2, page.len() as i32)
+                .i32(3, page.len() as i32)
+                .structure(
+                    5,
+                    ThriftStruct::default()
+                        .i32(1, values.len() as i32)
+                        .i32(2, 0)
+                        .i32(3, 3)
+                        .i32(4, 3),
+                )
+                .finish();
+            self.writer.write_all(&header)?;
+            self.writer.write_all(&page)?;
+            let length = (header.len() + page.len()) as u64;
+            chunks.push(Chunk {
+                offset: self.offset,
+                length,
+                values: values.len(),
+            });
+            self.offset += length;
+            values.clear();
+        }
+        self.pending_bytes = 0;
+        self.row_groups.push(RowGroup { rows, chunks });
+        Ok(())
+    }
+
+    /// Writes any buffered rows and the footer. Later calls do nothing.
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        self.write_row_group()?;
+
+        let mut schema = vec![ThriftStruct::default()
+            .binary(4, b"schema")
+            .i32(5, self.columns.len() as i32)];
+        for column in &self.columns {
+            let mut element = ThriftStruct::default()
+                .i32(1, column.column_type.physical())
+                .i32(3, column.optional as i32)
+                .binary(4, column.name.as_bytes());
+            if column.column_type == ColumnType::String {
+                // UTF8, as both the legacy converted type and the logical string type.
+                element = element.i32(6, 0).structure(
+                    10,
+                    ThriftStruct::default().structure(1, ThriftStruct::default()),
+                );
+            }
+            schema.push(element);
+        }
+        let row_groups = self
+            .row_groups
+            .iter()
+            .map(|group| {
+                let chunks = self
+                    .columns
+                    .iter()
+                    .zip(&group.chunks)
+                    .map(|(column, chunk)| {
+                        let encodings: &[i32] = if column.optional { &[0, 3] } else { &[0] };
+                        let metadata = ThriftStruct::default()
+                            .i32(1, column.column_type.physical())
+                            .i32_list(2, encodings)
+                            .binary_list(3, &[column.name.as_bytes()])
+                            .i32(4, 0)
+                            .i64(5, chunk.values as i64)
+                            .i64(6, chunk.length as i64)
+                            .i64(7, chunk.length as i64)
+                            .i64(9, chunk.offset as i64);
+                        ThriftStruct::default()
+                            .i64(2, chunk.offset as i64)
+                            .structure(3, metadata)
+                    })
+                    .collect();
+                ThriftStruct::default()
+                    .struct_list(1, chunks)
+                    .i64(
+                        2,
+                        group.chunks.iter().map(|chunk| chunk.length).sum::<u64>() as i64,
+                    )
+                    .i64(3, group.rows as i64)
+            })
+            .collect();
+        let rows: usize = self.row_groups.iter().map(|group| group.rows).sum();
+        let footer = ThriftStruct::default()
+            .i32(1, 1)
+            .struct_list(2, schema)
+            .i64(3, rows as i64)
+            .struct_list(4, row_groups)
+            .binary(
+                6,
+                concat!("trading-client version ", env!("CARGO_PKG_VERSION")).as_bytes(),
+            )
+            .finish();
+        self.writer.write_all(&footer)?;
+        self.writer
+            .write_all(&(footer.len() as u32).to_le_bytes())?;
+        self.writer.write_all(MAGIC)?;
+        self.writer.flush()?;
+        self.finished = true;
+        Ok(())
+    }
+
+    /// Syncs the file to disk. Call after `finish`.
+    pub fn sync(&self) -> io::Result<()> {
+        self.writer.get_ref().sync_all()
+    }
+}
+
+impl Drop for ParquetWriter {
+    fn drop(&mut self) {
+        if let Err(e) = self.finish() {
+            tracing::error!(error = %e, "Failed to finish Parquet file");
+        }
+    }
+}
+
+/// A data page: definition levels for optional columns, then the non-null values in plain encoding.
+fn encode_page(column: &Column, values: &[Value]) -> Vec<u8> {
+    let mut page = vec![];
+    if column.optional {
+        let levels: Vec<u8> = values
+            .iter()
+            .map(|value| (*value != Value::Null) as u8)
+            .collect();
+        let mut encoded = vec![];
+        rle(&levels, &mut encoded);
+        page.extend_from_slice(&(encoded.len() as u32).to_le_bytes());
+        page.extend(encoded);
+    }
+    let mut bits = 0;
+    for value in values {
+        match value {
+            Value::Null => {}
+            Value::Boolean(value) => {
+                if bits % 8 == 0 {
+                    page.push(0);
+                }
+                *page.last_mut().unwrap() |= (*value as u8) << (bits % 8);
+                bits += 1;
+            }
+            Value::Double(value) => page.extend_from_slice(&value.to_le_bytes()),
+            Value::String(text) => {
+                page.extend_from_slice(&(text.len() as u32).to_le_bytes());
+                page.extend_from_slice(text.as_bytes());
+            }
+        }
+    }
+    page
+}
+
+fn decode_values(column: &Column, bytes: &[u8], count: usize) -> io::Result<Vec<Value>> {
+    let mut reader = ThriftReader { bytes };
+    (0..count)
+        .map(|i| {
+            Ok(match column.column_type {
+                ColumnType::Boolean => {
+                    let byte = bytes.get(i / 8).ok_or_else(|| invalid("Truncated page"))?;
+                    Value::Boolean(byte >> (i % 8) & 1 == 1)
+                }
+                ColumnType::Double => {
+                    Value::Double(f64::from_le_bytes(reader.take(8)?.try_into().unwrap()))
+                }
+                ColumnType::String => {
+                    let length = u32::from_le_bytes(reader.take(4)?.try_into().unwrap());
+                    let text = reader.take(length as usize)?;
+                    Value::String(String::from_utf8_lossy(text).into_owned())
+                }
+            })
+        })
+        .collect()
+}
+
+/// Reads files written by `ParquetWriter` a row group at a time.
+pub(crate) struct ParquetReader {
+    file: File,
+    columns: Vec<Column>,
+    row_groups: Vec<RowGroup>,
+}
+
+impl ParquetReader {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+        let length = file.seek(SeekFrom::End(0))?;
+        if length < 12 {
+            return Err(invalid("Not a Parquet file"));
+        }
+        let mut tail = [0; 8];
+        file.seek(SeekFrom::End(-8))?;
+        file.read_exact(&mut tail)?;
+        let footer_length = u32::from_le_bytes(tail[..4].try_into().unwrap()) as u64;
+        if &tail[4..] != MAGIC {
+            return Err(invalid("Parquet file has no footer; it was not finished"));
+        }
+        if footer_length + 12 > length {
+            return Err(invalid("Parquet footer is larger than the file"));
+        }
+        let mut footer = vec![0; footer_length as usize];
+        file.seek(SeekFrom::Start(length - 8 - footer_length))?;
+        file.read_exact(&mut footer)?;
+        let metadata = ThriftReader { bytes: &footer }.structure(0)?;
+
+        let columns = metadata
+            .list(2)
+            .iter()
+            .skip(1)
+            .map(|element| {
+                if element.int(5).is_some_and(|children| children > 0) {
+                    return Err(invalid("Nested Parquet columns are not supported"));
+                }
+                let column_type = match element.int(1) {
+                    Some(0) => ColumnType::Boolean,
+                    Some(5) => ColumnType::Double,
+                    Some(6) => ColumnType::String,
+                    _ => return Err(invalid("Unsupported Parquet column type")),
+                };
+                Ok(Column {
+                    name: String::from_utf8_lossy(element.binary(4).unwrap_or_default())
+                        .into_owned(),
+                    column_type,
+                    optional: element.int(3) == Some(1),
+                })
+            })
+            .collect::<io::Result<Vec<Column>>>()?;
+        let row_groups = metadata
+            .list(4)
+            .iter()
+            .map(|group| {
+                let chunks = group
+                    .list(1)
+                    .iter()
+                    .map(|chunk| {
+                        let metadata = chunk
+                            .get(3)
+                            .ok_or_else(|| invalid("Parquet column chunk has no metadata"))?;
+                        if metadata.int(4) != Some(0) {
+                            return Err(invalid("Compressed Parquet files are not supported"));
+                        }
+                        if metadata.get(11).is_some() {
+                            return Err(invalid(
+                                "Dictionary-encoded Parquet files are not supported",
+                            ));
+                        }
+                        Ok(Chunk {
+                            offset: metadata.int(9).unwrap_or_default() as u64,
+                            length: metadata.int(7).unwrap_or_default() as u64,
+                            values: metadata.int(5).unwrap_or_default() as usize,
+                        })
+                    })
+                    .collect::<io::Result<Vec<Chunk>>>()?;
+                if chunks.len() != columns.len() {
+                    return Err(invalid("Parquet row group does not match the schema"));
+                }
+                Ok(RowGroup {
+                    rows: group.int(3).unwrap_or_default() as usize,
+                    chunks,
+                })
+            })
+            .collect::<io::Result<Vec<RowGroup>>>()?;
+        Ok(ParquetReader {
+            file,
+            columns,
+            row_groups,
+        })
+    }
+
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    pub fn row_groups(&self) -> usize {
+        self.row_groups.len()
+    }
+
+    /// Rows of the `index`-th row group, one value per column in schema order.
+    pub fn read_row_group(&mut self, index: usize) -> io::Result<Vec<Vec<Value>>> {
+        let group = &self.row_groups[index];
+        let mut rows = vec![Vec::with_capacity(self.columns.len()); group.rows];
+        for (column, chunk) in self.columns.iter().zip(&group.chunks) {
+            let mut bytes = vec![0; chunk.length as usize];
+            self.file.seek(SeekFrom::Start(chunk.offset))?;
+            self.file.read_exact(&mut bytes)?;
+            let values = read_chunk(column, &bytes, chunk.values)?;
+            if values.len() != group.rows {
+                return Err(invalid("Parquet column chunk does not match its row count"));
+            }
+            for (row, value) in rows.iter_mut().zip(values) {
+                row.push(value);
+            }
+        }
+        Ok(rows)
+    }
+}
+
+/// Values of a column chunk, from each of its data pages.
+fn read_chunk(column: &Column, mut bytes: &[u8], count: usize) -> io::Result<Vec<Value>> {
+    let mut values = Vec::with_capacity(count);
+    while !bytes.is_empty() {
+        let mut reader = ThriftReader { bytes };
+        let header = reader.structure(0)?;
+        let page_length = header.int(3).unwrap_or_default() as usize;
+        let page = reader.take(page_length)?;
+        bytes = reader.bytes;
+        let data = header
+            .get(5)
+            .filter(|_| header.int(1) == Some(0))
+            .ok_or_else(|| invalid("Only version 1 data pages are supported"))?;
+        if data.int(2) != Some(0) {
+            return Err(invalid("Only plain-encoded Parquet pages are supported"));
+        }
+        let page_values = data.int(1).unwrap_or_default() as usize;
+
+        let (levels, encoded) = if column.optional {
+            let length = page
+                .get(..4)
+                .map(|length| u32::from_le_bytes(length.try_into().unwrap()) as usize)
+                .filter(|length| 4 + length <= page.len())
+                .ok_or_else(|| invalid("Truncated Parquet page"))?;
+            (
+                unrle(&page[4..4 + length], page_values)?,
+                &page[4 + length..],
+            )
+        } else {
+            (vec![1; page_values], page)
+        };
+        let present = levels.iter().filter(|level| **level == 1).count();
+        let mut decoded = decode_values(column, encoded, present)?.into_iter();
+        for level in levels {
+            values.push(match level {
+                1 => decoded.next().unwrap(),
+                _ => Value::Null,
+            });
+        }
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}-{}.parquet", name, std::process::id()))
+    }
+
+    fn schema() -> Vec<Column> {
+        vec![
+            Column::required("symbol", ColumnType::String),
+            Column::optional("price", ColumnType::Double),
+            Column::optional("reset", ColumnType::Boolean),
+        ]
+    }
+
+    fn row(i: usize) -> Vec<Value> {
+        vec![
+            Value::String(format!("S{}", i)),
+            match i % 3 {
+                0 => Value::Null,
+                _ => Value::Double(i as f64 + 0.25),
+            },
+            match i % 4 {
+                0 => Value::Null,
+                n => Value::Boolean(n % 2 == 1),
+            },
+        ]
+    }
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        let path = path("parquet-round-trip");
+        let mut writer = ParquetWriter::create(&path, schema(), 7).unwrap();
+        for i in 0..20 {
+            writer.write_row(row(i)).unwrap();
+        }
+        writer.finish().unwrap();
+        drop(writer);
+
+        let mut reader = ParquetReader::open(&path).unwrap();
+        assert_eq!(reader.columns(), schema());
+        assert_eq!(reader.row_groups(), 3);
+        let mut rows = vec![];
+        for group in 0..reader.row_groups() {
+            rows.extend(reader.read_row_group(group).unwrap());
+        }
+        assert_eq!(rows, (0..20).map(row).collect::<Vec<_>>());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn lays_out_the_file_as_the_format_requires() {
+        let path = path("parquet-layout");
+        let mut writer = ParquetWriter::create(
+            &path,
+            vec![Column::required("price", ColumnType::Double)],
+            10,
+        )
+        .unwrap();
+        writer.write_row(vec![Value::Double(1.5)]).unwrap();
+        drop(writer);
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(&bytes[..4], b"PAR1");
+        assert_eq!(&bytes[bytes.len() - 4..], b"PAR1");
+        let footer_length =
+            u32::from_le_bytes(bytes[bytes.len() - 8..bytes.len() - 4].try_into().unwrap());
+        let footer = &bytes[bytes.len() - 8 - footer_length as usize..bytes.len() - 8];
+        let metadata = ThriftReader { bytes: footer }.structure(0).unwrap();
+        assert_eq!(metadata.int(1), Some(1));
+        assert_eq!(metadata.int(3), Some(1));
+        let schema = metadata.list(2);
+        assert_eq!(schema[0].binary(4), Some(&b"schema"[..]));
+        assert_eq!(schema[0].int(5), Some(1));
+        assert_eq!(schema[1].binary(4), Some(&b"price"[..]));
+        // DOUBLE, REQUIRED.
+        assert_eq!((schema[1].int(1), schema[1].int(3)), (Some(5), Some(0)));
+        let chunk = metadata.list(4)[0].list(1)[0].get(3).unwrap();
+        assert_eq!(chunk.int(9), Some(4));
+        assert_eq!(chunk.int(5), Some(1));
+
+        // The page itself: a header, then the plain double.
+        let mut reader = ThriftReader { bytes: &bytes[4..] };
+        let header = reader.structure(0).unwrap();
+        assert_eq!((header.int(1), header.int(3)), (Some(0), Some(8)));
+        assert_eq!(reader.take(8).unwrap(), 1.5f64.to_le_bytes());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn encodes_thrift_field_ids_and_lists() {
+        let bytes = ThriftStruct::default()
+            .i32(1, -1)
+            .i64(20, 300)
+            .binary_list(21, &[b"a", b"bc"])
+            .finish();
+        // Short field delta in the header, then a long one written out as zigzag.
+        assert_eq!(bytes[..2], [0x15, 0x01]);
+        assert_eq!(bytes[2..5], [0x06, 0x28, 0xd8]);
+        let decoded = ThriftReader { bytes: &bytes }.structure(0).unwrap();
+        assert_eq!(decoded.int(1), Some(-1));
+        assert_eq!(decoded.int(20), Some(300));
+        assert_eq!(decoded.list(21).len(), 2);
+
+        let long: Vec<i32> = (0..20).collect();
+        let bytes = ThriftStruct::default().i32_list(1, &long).finish();
+        let decoded = ThriftReader { bytes: &bytes }.structure(0).unwrap();
+        assert_eq!(decoded.list(1).len(), 20);
+    }
+
+    #[test]
+    fn decodes_bit_packed_levels() {
+        // One bit-packed group of eight values, then a run of three ones.
+        let levels = unrle(&[0x03, 0b1010_0101, 0x06, 0x01], 11).unwrap();
+        assert_eq!(levels, [1, 0, 1, 0, 0, 1, 0, 1, 1, 1, 1]);
+        let mut encoded = vec![];
+        rle(&levels, &mut encoded);
+        assert_eq!(unrle(&encoded, 11).unwrap(), levels);
+    }
+
+    #[test]
+    fn refuses_unfinished_files_and_bad_rows() {
+        let path = path("parquet-unfinished");
+        let mut writer = ParquetWriter::create(&path, schema(), 2).unwrap();
+        writer.write_row(row(1)).unwrap();
+        writer.write_row(row(2)).unwrap();
+        assert!(writer.write_row(vec![Value::Null]).is_err());
+        assert!(writer
+            .write_row(vec![Value::Null, Value::Null, Value::Null])
+            .is_err());
+        writer.flush().unwrap();
+        assert!(ParquetReader::open(&path).is_err());
+        writer.finish().unwrap();
+        assert!(writer.write_row(row(3)).is_err());
+        drop(writer);
+        assert!(ParquetReader::open(&path).is_ok());
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,278 @@
+use crate::{
+    backtest::mark,
+    datastructures::{
+        account::{AccountActivity, Position},
+        event::EventType,
+        order::OrderSide,
+    },
+    strategy::Fill,
+    time,
+};
+use serde::Serialize;
+use std::{collections::HashMap, fmt};
+use tokio::sync::watch;
+
+/// Profit and loss of one symbol.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SymbolPnl {
+    pub symbol: String,
+    /// Negative for shorts.
+    pub quantity: f64,
+    pub average_cost: f64,
+    /// Latest price seen, if any.
+    pub mark: Option<f64>,
+    /// Closed quantity times the difference from average cost, before commissions.
+    pub realized: f64,
+    /// Open quantity marked against average cost. Zero until a price is seen.
+    pub unrealized: f64,
+    pub commissions: f64,
+    /// Signed market value at the mark, or at cost without one.
+    pub exposure: f64,
+}
+
+impl SymbolPnl {
+    /// Realized plus unrealized, net of commissions.
+    pub fn total(&self) -> f64 {
+        self.realized + self.unrealized - self.commissions
+    }
+}
+
+/// Profit and loss across every symbol.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PortfolioPnl {
+    pub realized: f64,
+    pub unrealized: f64,
+    pub commissions: f64,
+    pub long_exposure: f64,
+    /// Positive market value of short positions.
+    pub short_exposure: f64,
+    /// Deposits and transfers in less withdrawals and transfers out, from account activity. Moves equity
+    /// without being P/L.
+    pub net_deposits: f64,
+    /// Dividends and interest less fees, and other cash from account activity. Not part of `total`.
+    pub income: f64,
+}
+
+impl PortfolioPnl {
+    /// Realized plus unrealized, net of commissions.
+    pub fn total(&self) -> f64 {
+        self.realized + self.unrealized - self.commissions
+    }
+
+    pub fn gross_exposure(&self) -> f64 {
+        self.long_exposure + self.short_exposure
+    }
+
+    pub fn net_exposure(&self) -> f64 {
+        self.long_exposure - self.short_exposure
+    }
+}
+
+impl fmt::Display for PortfolioPnl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "P/L {:.2} (realized {:.2}, unrealized {:.2}, commissions {:.2}), exposure long {:.2} short {:.2}",
+            self.total(),
+            self.realized,
+            self.unrealized,
+            self.commissions,
+            self.long_exposure,
+            self.short_exposure
+        )?;
+        if self.net_deposits != 0.0 || self.income != 0.0 {
+            write!(
+                f,
+                ", net deposits {:.2}, income {:.2}",
+                self.net_deposits, self.income
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Every symbol's P/L and the portfolio total as of one fill or mark.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PnlSnapshot {
+    /// Sorted by symbol.
+    pub symbols: Vec<SymbolPnl>,
+    pub portfolio: PortfolioPnl,
+    /// RFC 3339.
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Book {
+    quantity: f64,
+    average_cost: f64,
+    realized: f64,
+    commissions: f64,
+}
+
+/// Running realized and unrealized P/L per symbol on average cost, fed with fills, from the simulator or the
+/// strategy runner's `on_fill`, and with market data for marks.
+#[derive(Debug, Clone, Default)]
+pub struct PnlTracker {
+    books: HashMap<String, Book>,
+    marks: HashMap<String, f64>,
+    net_deposits: f64,
+    income: f64,
+    snapshots: Option<watch::Sender<PnlSnapshot>>,
+}
+
+impl PnlTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts from positions already held, e.g. from `get_positions` at startup, at their average entry price.
+    pub fn with_positions(positions: &[Position]) -> Self {
+        let mut tracker = PnlTracker::new();
+        for position in positions {
+            tracker.books.insert(
+                position.symbol.clone(),
+                Book {
+                    quantity: position.qty,
+                    average_cost: position.avg_entry_price,
+                    ..Book::default()
+                },
+            );
+            if let Some(price) = position.current_price {
+                tracker.marks.insert(position.symbol.clone(), price);
+            }
+        }
+        tracker
+    }
+
+    pub fn on_fill(&mut self, fill: &Fill) {
+        let signed = match fill.side {
+            OrderSide::Buy => fill.quantity,
+            OrderSide::Sell => -fill.quantity,
+        };
+        let book = self.books.entry(fill.symbol.clone()).or_default();
+        book.commissions += fill.commission;
+
+        if book.quantity == 0.0 || book.quantity.signum() == signed.signum() {
+            let held = book.quantity.abs();
+            book.average_cost =
+                (book.average_cost * held + fill.price * signed.abs()) / (held + signed.abs());
+        } else {
+            let closed = signed.abs().min(book.quantity.abs());
+            book.realized += closed * (fill.price - book.average_cost) * book.quantity.signum();
+            if signed.abs() > book.quantity.abs() {
+                // Flipped through zero; the remainder opens at the fill price.
+                book.average_cost = fill.price;
+            }
+        }
+        book.quantity += signed;
+        if book.quantity == 0.0 {
+            book.average_cost = 0.0;
+        }
+        // A fill is also the latest price.
+        self.marks.insert(fill.symbol.clone(), fill.price);
+        self.publish();
+    }
+
+    /// Books the cash of a non-trading account activity, e.g. from an `ActivityMonitor`, so equity changes that
+    /// are not trading P/L are accounted for. Share changes from corporate actions are not applied.
+    pub fn on_activity(&mut self, activity: &AccountActivity) {
+        if activity.kind.is_cash_flow() {
+            self.net_deposits += activity.net_amount;
+        } else {
+            self.income += activity.net_amount;
+        }
+        self.publish();
+    }
+
+    /// Marks the event's symbol at its quote mid, trade price or bar close. Other events are ignored.
+    pub fn on_event(&mut self, event: &EventType) {
+        if let (Some(symbol), Some(price)) = (event.symbol(), mark(event)) {
+            self.set_mark(symbol, price);
+        }
+    }
+
+    pub fn set_mark(&mut self, symbol: &str, price: f64) {
+        let previous = self.marks.insert(symbol.to_string(), price);
+        if previous != Some(price) && self.books.contains_key(symbol) {
+            self.publish();
+        }
+    }
+
+    /// P/L of one symbol, if it has ever been traded or held.
+    pub fn symbol(&self, symbol: &str) -> Option<SymbolPnl> {
+        let book = self.books.get(symbol)?;
+        let mark = self.marks.get(symbol).copied();
+        let price = mark.unwrap_or(book.average_cost);
+        Some(SymbolPnl {
+            symbol: symbol.to_string(),
+            quantity: book.quantity,
+            average_cost: book.average_cost,
+            mark,
+            realized: book.realized,
+            unrealized: book.quantity * (price - book.average_cost),
+            commissions: book.commissions,
+            exposure: book.quantity * price,
+        })
+    }
+
+    /// Every symbol traded or held, sorted by symbol.
+    pub fn symbols(&self) -> Vec<SymbolPnl> {
+        let mut symbols: Vec<SymbolPnl> = self
+            .books
+            .keys()
+            .filter_map(|symbol| self.symbol(symbol))
+            .collect();
+        symbols.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        symbols
+    }
+
+    pub fn portfolio(&self) -> PortfolioPnl {
+        let portfolio = PortfolioPnl {
+            net_deposits: self.net_deposits,
+            income: self.income,
+            ..PortfolioPnl::default()
+        };
+        self.symbols().iter().fold(portfolio, |mut total, symbol| {
+            total.realized += symbol.realized;
+            total.unrealized += symbol.unrealized;
+            total.commissions += symbol.commissions;
+            if symbol.exposure >= 0.0 {
+                total.long_exposure += symbol.exposure;
+            } else {
+                total.short_exposure -= symbol.exposure;
+            }
+            total
+        })
+    }
+
+    pub fn snapshot(&self) -> PnlSnapshot {
+        PnlSnapshot {
+            symbols: self.symbols(),
+            portfolio: self.portfolio(),
+            updated_at: time::format_rfc3339(time::now_nanos()),
+        }
+    }
+
+    /// Channel holding the latest snapshot, replaced on every fill and on every mark that moves a held or
+    /// traded symbol, so a UI can render it without polling. Snapshots are only built while a receiver is
+    /// open. Clones of the tracker publish to the same channel.
+    pub fn subscribe(&mut self) -> watch::Receiver<PnlSnapshot> {
+        match &self.snapshots {
+            Some(sender) => {
+                sender.send_replace(self.snapshot());
+                sender.subscribe()
+            }
+            None => {
+                let (sender, receiver) = watch::channel(self.snapshot());
+                self.snapshots = Some(sender);
+                receiver
+            }
+        }
+    }
+
+    fn publish(&self) {
+        if let Some(sender) = self.snapshots.as_ref().filter(|s| s.receiver_count() > 0) {
+            sender.send_replace(self.snapshot());
+        }
+    }
+}
@@ -0,0 +1,409 @@
+mod stream;
+
+use crate::{
+    datastructures::{
+        client::{FeedType, MarketDataClient, SubscriptionParams},
+        config::Config,
+        corporate_action::CorporateAction,
+        event::{EventBatch, ParseMode},
+        market::{Bar, BarAdjustment, Quote},
+    },
+    http::RestPolicies,
+    time,
+};
+use async_trait::async_trait;
+use futures_util::{SinkExt, StreamExt};
+use reqwest::Client as HttpClient;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use std::{error::Error, fmt, time::Duration};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{
+    connect_async, tungstenite::protocol::Message, MaybeTlsStream, WebSocketStream,
+};
+
+/// Docs: https://polygon.io/docs/stocks/getting-started
+const REST_URL: &str = "https://api.polygon.io";
+/// Docs: https://polygon.io/docs/stocks/ws_getting-started
+const STREAM_URL: &str = "wss://socket.polygon.io";
+/// Same streams 15 minutes behind, for plans without real-time data.
+const DELAYED_STREAM_URL: &str = "wss://delayed.polygon.io";
+const STREAM_AUTH_TIMEOUT: Duration = Duration::from_secs(10);
+const NANOS_PER_MILLI: i64 = 1_000_000;
+
+/// Error response of the Polygon.io API.
+#[derive(Debug, Clone)]
+pub struct PolygonError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl PolygonError {
+    /// Takes the message from the body's "error" or "message", or the whole body.
+    fn new(status: u16, body: String) -> Self {
+        let message = serde_json::from_str::<Value>(&body)
+            .ok()
+            .and_then(|value| {
+                let message = value.get("error").or(value.get("message"))?;
+                Some(message.as_str()?.to_string())
+            })
+            .unwrap_or(body);
+        PolygonError { status, message }
+    }
+}
+
+impl fmt::Display for PolygonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Polygon error ({}): {}", self.status, self.message)
+    }
+}
+
+impl Error for PolygonError {}
+
+/// One page of a reference endpoint. `next_url` is set while more remain.
+#[derive(Deserialize)]
+struct Page<T> {
+    #[serde(default = "Vec::new")]
+    results: Vec<T>,
+    #[serde(default)]
+    next_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawSplit {
+    ticker: String,
+    execution_date: String,
+    split_from: f64,
+    split_to: f64,
+}
+
+#[derive(Deserialize)]
+struct RawDividend {
+    ticker: String,
+    ex_dividend_date: String,
+    cash_amount: f64,
+}
+
+#[derive(Deserialize)]
+struct RawAggregate {
+    o: f64,
+    h: f64,
+    l: f64,
+    c: f64,
+    v: f64,
+    t: i64,
+}
+
+fn format_millis(millis: i64) -> String {
+    time::format_rfc3339(millis * NANOS_PER_MILLI)
+}
+
+/// Ticker as Polygon's REST API writes it: "X:BTCUSD" for the crypto pair "BTC/USD", stocks as they are.
+fn ticker(symbol: &str) -> String {
+    if symbol.contains('/') {
+        format!("X:{}", symbol.replace('/', ""))
+    } else {
+        symbol.to_string()
+    }
+}
+
+/// Market data from Polygon.io, for stocks, options and crypto. Polygon does not trade, so pair it with a
+/// broker in a `SplitClient`, e.g. Alpaca for execution with Polygon's data. Crypto pairs are written as Alpaca
+/// writes them, e.g. "BTC/USD", and option contracts by their OCC symbol. Applies the config's REST policies like
+/// `AlpacaClient`.
+#[derive(Clone)]
+pub struct PolygonClient {
+    http_client: HttpClient,
+    rest: RestPolicies,
+    api_key: String,
+    stream_url: &'static str,
+}
+
+impl PolygonClient {
+    /// Uses the config's Polygon key.
+    pub fn new(config: &Config) -> Result<Self, reqwest::Error> {
+        Ok(PolygonClient {
+            http_client: config.http.build_client()?,
+            rest: RestPolicies::new(config),
+            api_key: config.polygon_api_key.clone().unwrap_or_default(),
+            stream_url: STREAM_URL,
+        })
+    }
+
+    /// Streams from the 15 minute delayed cluster, for plans without real-time data.
+    pub fn with_delayed_stream(mut self) -> Self {
+        self.stream_url = DELAYED_STREAM_URL;
+        self
+    }
+
+    /// `url` is a path, or a whole URL as in `next_url`. Every call is a read, so all are retried.
+    async fn get<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        query: &[(&str, String)],
+    ) -> Result<T, Box<dyn Error>> {
+        let url = if url.starts_with("https://") {
+            url.to_string()
+        } else {
+            format!("{}{}", REST_URL, url)
+        };
+        let build = || {
+            Ok(self
+                .http_client
+                .get(&url)
+                .query(query)
+                .bearer_auth(&self.api_key)
+                .build()?)
+        };
+        let response = self.rest.send(&self.http_client, true, build).await?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(PolygonError::new(status.as_u16(), body).into());
+        }
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    /// Every page of a reference endpoint.
+    async fn get_all<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<Vec<T>, Box<dyn Error>> {
+        let mut page: Page<T> = self.get(path, query).await?;
+        let mut results = std::mem::take(&mut page.results);
+        while let Some(next_url) = page.next_url.take() {
+            page = self.get(&next_url, &[]).await?;
+            results.append(&mut page.results);
+        }
+        Ok(results)
+    }
+
+    /// Splits and cash dividends with an ex-date from `start` to `end` inclusive, as YYYY-MM-DD dates, sorted by
+    /// ex-date.
+    /// Docs: https://polygon.io/docs/stocks/get_v3_reference_splits
+    /// and https://polygon.io/docs/stocks/get_v3_reference_dividends
+    pub async fn get_corporate_actions(
+        &self,
+        symbols: &[&str],
+        start: &str,
+        end: &str,
+    ) -> Result<Vec<CorporateAction>, Box<dyn Error>> {
+        let mut actions = Vec::new();
+        for symbol in symbols {
+            let query = |from: &'static str, to: &'static str| {
+                [
+                    ("ticker", symbol.to_string()),
+                    (from, start.to_string()),
+                    (to, end.to_string()),
+                    ("limit", "1000".to_string()),
+                ]
+            };
+            let splits: Vec<RawSplit> = self
+                .get_all(
+                    "/v3/reference/splits",
+                    &query("execution_date.gte", "execution_date.lte"),
+                )
+                .await?;
+            actions.extend(
+                splits
+                    .into_iter()
+                    .filter(|split| split.split_from > 0.0)
+                    .map(|split| CorporateAction::Split {
+                        symbol: split.ticker,
+                        ex_date: split.execution_date,
+                        ratio: split.split_to / split.split_from,
+                    }),
+            );
+            let dividends: Vec<RawDividend> = self
+                .get_all(
+                    "/v3/reference/dividends",
+                    &query("ex_dividend_date.gte", "ex_dividend_date.lte"),
+                )
+                .await?;
+            actions.extend(
+                dividends
+                    .into_iter()
+                    .map(|dividend| CorporateAction::CashDividend {
+                        symbol: dividend.ticker,
+                        ex_date: dividend.ex_dividend_date,
+                        amount: dividend.cash_amount,
+                    }),
+            );
+        }
+        actions.sort_by(|a, b| a.ex_date().cmp(b.ex_date()));
+        Ok(actions)
+    }
+
+    /// Docs: https://polygon.io/docs/stocks/get_v2_aggs_ticker__stocksticker__range__multiplier___timespan___from___to
+    async fn fetch_daily_bars(
+        &self,
+        symbol: &str,
+        start: &str,
+        end: &str,
+        split_adjusted: bool,
+    ) -> Result<Vec<Bar>, Box<dyn Error>> {
+        let path = format!(
+            "/v2/aggs/ticker/{}/range/1/day/{}/{}",
+            ticker(symbol),
+            start,
+            end
+        );
+        let query = [
+            ("adjusted", split_adjusted.to_string()),
+            ("sort", "asc".to_string()),
+            ("limit", "50000".to_string()),
+        ];
+        let page: Page<RawAggregate> = self.get(&path, &query).await?;
+        Ok(page
+            .results
+            .into_iter()
+            .map(|raw| Bar {
+                symbol: symbol.to_string(),
+                open: raw.o,
+                high: raw.h,
+                low: raw.l,
+                close: raw.c,
+                volume: raw.v,
+                timestamp: format_millis(raw.t),
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl MarketDataClient for PolygonClient {
+    /// Docs: https://polygon.io/docs/stocks/get_v2_last_nbbo__stocksticker
+    /// and https://polygon.io/docs/crypto/get_v1_last_quote_currencies__from___to. Crypto pairs are recognized by
+    /// their slash; Polygon gives no sizes for them, so both are zero.
+    async fn get_latest_quote(&self, symbol: &str) -> Result<Quote, Box<dyn Error>> {
+        if let Some((base, quote)) = symbol.split_once('/') {
+            #[derive(Deserialize)]
+            struct Last {
+                bid: f64,
+                ask: f64,
+                timestamp: i64,
+            }
+            #[derive(Deserialize)]
+            struct LastQuote {
+                last: Last,
+            }
+            let path = format!("/v1/last_quote/currencies/{}/{}", base, quote);
+            let LastQuote { last } = self.get(&path, &[]).await?;
+            return Ok(Quote {
+                symbol: symbol.to_string(),
+                bid_price: last.bid,
+                ask_price: last.ask,
+                bid_size: 0.0,
+                ask_size: 0.0,
+                timestamp: format_millis(last.timestamp),
+            });
+        }
+
+        #[derive(Deserialize)]
+        struct Nbbo {
+            #[serde(rename = "p", default)]
+            bid_price: f64,
+            #[serde(rename = "s", default)]
+            bid_size: f64,
+            #[serde(rename = "P", default)]
+            ask_price: f64,
+            #[serde(rename = "S", default)]
+            ask_size: f64,
+            /// SIP time in nanoseconds.
+            t: i64,
+        }
+        #[derive(Deserialize)]
+        struct LastNbbo {
+            results: Nbbo,
+        }
+        let path = format!("/v2/last/nbbo/{}", symbol);
+        let LastNbbo { results: nbbo } = self.get(&path, &[]).await?;
+        Ok(Quote {
+            symbol: symbol.to_string(),
+            bid_price: nbbo.bid_price,
+            ask_price: nbbo.ask_price,
+            bid_size: nbbo.bid_size,
+            ask_size: nbbo.ask_size,
+            timestamp: time::format_rfc3339(nbbo.t),
+        })
+    }
+
+    /// Polygon adjusts bars for splits only, so total return bars are adjusted locally from its splits and
+    /// dividends. Crypto has no corporate actions, so the adjustment is ignored for it.
+    async fn get_daily_bars(
+        &self,
+        symbol: &str,
+        start: &str,
+        end: &str,
+        adjustment: BarAdjustment,
+    ) -> Result<Vec<Bar>, Box<dyn Error>> {
+        if symbol.contains('/') {
+            return self.fetch_daily_bars(symbol, start, end, false).await;
+        }
+        match adjustment {
+            BarAdjustment::Raw => self.fetch_daily_bars(symbol, start, end, false).await,
+            BarAdjustment::Split => self.fetch_daily_bars(symbol, start, end, true).await,
+            BarAdjustment::TotalReturn => {
+                let mut bars = self.fetch_daily_bars(symbol, start, end, false).await?;
+                // Splits after `end` restate the bars too.
+                let today = time::date(&time::format_rfc3339(time::now_nanos()))
+                    .unwrap_or_else(|| end.to_string());
+                let actions = self.get_corporate_actions(&[symbol], start, &today).await?;
+                adjustment.apply(&mut bars, &actions);
+                Ok(bars)
+            }
+        }
+    }
+
+    /// Docs: https://polygon.io/docs/stocks/ws_getting-started
+    /// Trades, quotes and minute bars stream for every feed, limit up/limit down bands for stocks and order books
+    /// for crypto. Polygon has no updated or daily bar streams, and its news is not streamed.
+    async fn subscribe(
+        &self,
+        params: SubscriptionParams,
+    ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, Box<dyn Error>> {
+        let cluster = match params.feed_type {
+            FeedType::Stocks => "stocks",
+            FeedType::Crypto => "crypto",
+            FeedType::Options => "options",
+            FeedType::News | FeedType::Test => {
+                return Err(format!("Polygon has no {:?} feed", params.feed_type).into())
+            }
+        };
+        let channels = stream::subscribe_params(&params.subscription_request, params.feed_type);
+
+        let url = format!("{}/{}", self.stream_url, cluster);
+        let (mut socket, response) = connect_async(url.as_str()).await?;
+        if response.status() != 101 {
+            return Err(
+                format!("Connection failed with status code: {}", response.status()).into(),
+            );
+        }
+        let auth_message = json!({ "action": "auth", "params": self.api_key });
+        socket.send(Message::Text(auth_message.to_string())).await?;
+        let auth = tokio::time::timeout(STREAM_AUTH_TIMEOUT, async {
+            while let Some(message) = socket.next().await {
+                if let Message::Text(text) = message? {
+                    if let Some(result) = stream::auth_result(&text) {
+                        return Ok(result);
+                    }
+                }
+            }
+            Err(tokio_tungstenite::tungstenite::Error::ConnectionClosed)
+        })
+        .await
+        .map_err(|_| "Timed out authenticating to the Polygon stream")??;
+        auth.map_err(|message| format!("Polygon stream authentication failed: {}", message))?;
+
+        if !channels.is_empty() {
+            let subscribe = json!({ "action": "subscribe", "params": channels });
+            socket.send(Message::Text(subscribe.to_string())).await?;
+        }
+        Ok(socket)
+    }
+
+    fn parse_frame(&self, frame: &str, _mode: ParseMode) -> Result<EventBatch, serde_json::Error> {
+        stream::parse_frame(frame)
+    }
+}
@@ -0,0 +1,212 @@
+use super::format_millis;
+use crate::datastructures::{
+    client::{FeedType, SubscriptionRequest},
+    event::{EventBatch, EventType},
+};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Comma separated channels for the request, e.g. "T.AAPL,Q.AAPL". Crypto channels are prefixed with "X" and
+/// written with dashes, e.g. "XT.BTC-USD", and option contracts with "O:". "*" subscribes to every symbol.
+pub(super) fn subscribe_params(request: &SubscriptionRequest, feed_type: FeedType) -> String {
+    let crypto = feed_type == FeedType::Crypto;
+    let channel = |prefix: &str, symbol: &str| match feed_type {
+        FeedType::Crypto => format!("X{}.{}", prefix, symbol.replace('/', "-")),
+        FeedType::Options if symbol != "*" && !symbol.starts_with("O:") => {
+            format!("{}.O:{}", prefix, symbol)
+        }
+        _ => format!("{}.{}", prefix, symbol),
+    };
+    let mut channels = vec![];
+    let mut add = |prefix: &str, symbols: &[&'static str]| {
+        for symbol in symbols {
+            let channel = channel(prefix, symbol);
+            if !channels.contains(&channel) {
+                channels.push(channel);
+            }
+        }
+    };
+    add("T", &request.trades);
+    add("Q", &request.quotes);
+    // Minute aggregates are "AM", or "XA" for crypto.
+    add(if crypto { "A" } else { "AM" }, &request.bars);
+    if crypto {
+        add("L2", &request.orderbooks);
+    } else if !request.orderbooks.is_empty() {
+        tracing::warn!("Polygon streams order books for crypto only; ignoring them");
+    }
+    if feed_type == FeedType::Stocks {
+        for symbol in &request.lulds {
+            let channel = format!("LULD.{}", symbol);
+            if !channels.contains(&channel) {
+                channels.push(channel);
+            }
+        }
+    } else if !request.lulds.is_empty() {
+        tracing::warn!("Polygon streams limit up/limit down bands for stocks only; ignoring them");
+    }
+    if !request.updated_bars.is_empty() || !request.daily_bars.is_empty() {
+        tracing::warn!("Polygon streams closed minute bars only; ignoring updated and daily bars");
+    }
+    if !request.news.is_empty() {
+        tracing::warn!("Polygon has no news stream; ignoring it");
+    }
+    channels.join(",")
+}
+
+/// Outcome of authenticating, if `frame` answers it.
+pub(super) fn auth_result(frame: &str) -> Option<Result<(), String>> {
+    let messages: Vec<Value> = serde_json::from_str(frame).ok()?;
+    messages.iter().find_map(|message| {
+        let status = message.get("status")?.as_str()?;
+        let text = message.get("message").and_then(Value::as_str);
+        match status {
+            "auth_success" => Some(Ok(())),
+            "auth_failed" | "auth_timeout" | "error" => {
+                Some(Err(text.unwrap_or(status).to_string()))
+            }
+            _ => None,
+        }
+    })
+}
+
+/// Stock, option and crypto messages share a shape; crypto ones are prefixed with "X" and name their pair
+/// rather than their symbol.
+#[derive(Deserialize)]
+#[serde(tag = "ev")]
+enum RawMessage {
+    #[serde(rename = "T", alias = "XT")]
+    Trade {
+        #[serde(alias = "pair")]
+        sym: String,
+        p: f64,
+        #[serde(default)]
+        s: f64,
+        t: i64,
+    },
+    #[serde(rename = "Q", alias = "XQ")]
+    Quote {
+        #[serde(alias = "pair")]
+        sym: String,
+        #[serde(default)]
+        bp: f64,
+        #[serde(default)]
+        bs: f64,
+        #[serde(default)]
+        ap: f64,
+        #[serde(rename = "as", default)]
+        ask_size: f64,
+        t: i64,
+    },
+    #[serde(rename = "AM", alias = "XA")]
+    Aggregate {
+        #[serde(alias = "pair")]
+        sym: String,
+        o: f64,
+        h: f64,
+        l: f64,
+        c: f64,
+        v: f64,
+        /// Start of the minute.
+        s: i64,
+    },
+    #[serde(rename = "XL2")]
+    Book {
+        pair: String,
+        #[serde(default)]
+        b: Vec<(f64, f64)>,
+        #[serde(default)]
+        a: Vec<(f64, f64)>,
+        t: i64,
+    },
+    #[serde(rename = "LULD")]
+    Luld {
+        #[serde(rename = "T")]
+        symbol: String,
+        h: f64,
+        l: f64,
+        t: i64,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// Symbol as this crate writes it: "BTC/USD" for the pair "BTC-USD", option contracts without their "O:".
+fn symbol(raw: &str) -> String {
+    raw.strip_prefix("O:").unwrap_or(raw).replace('-', "/")
+}
+
+/// Limit up/limit down times are documented in milliseconds but sent in microseconds, so their unit is read from
+/// their magnitude.
+fn luld_timestamp(t: i64) -> String {
+    if t > 100_000_000_000_000 {
+        format_millis(t / 1_000)
+    } else {
+        format_millis(t)
+    }
+}
+
+/// Maps a frame, an array of messages, into events. Minute aggregates are closed minute bars, and each crypto
+/// level 2 message carries the top of the book, up to 100 levels a side, and replaces it. Status messages give
+/// no events.
+pub(super) fn parse_frame(frame: &str) -> Result<EventBatch, serde_json::Error> {
+    let messages: Vec<RawMessage> = serde_json::from_str(frame)?;
+    let mut batch = EventBatch::new();
+    for message in messages {
+        batch.push(match message {
+            RawMessage::Trade { sym, p, s, t } => EventType::Trade {
+                symbol: symbol(&sym),
+                price: p,
+                volume: s,
+                timestamp: format_millis(t),
+            },
+            RawMessage::Quote {
+                sym,
+                bp,
+                bs,
+                ap,
+                ask_size,
+                t,
+            } => EventType::Quote {
+                symbol: symbol(&sym),
+                bid_price: bp,
+                ask_price: ap,
+                bid_size: bs,
+                ask_size,
+                timestamp: format_millis(t),
+            },
+            RawMessage::Aggregate {
+                sym,
+                o,
+                h,
+                l,
+                c,
+                v,
+                s,
+            } => EventType::Bar {
+                symbol: symbol(&sym),
+                open: o,
+                high: h,
+                low: l,
+                close: c,
+                volume: v,
+                timestamp: format_millis(s),
+            },
+            RawMessage::Book { pair, b, a, t } => EventType::OrderBook {
+                symbol: symbol(&pair),
+                bids: b,
+                asks: a,
+                reset: true,
+                timestamp: format_millis(t),
+            },
+            RawMessage::Luld { symbol, h, l, t } => EventType::Luld {
+                symbol,
+                limit_up: h,
+                limit_down: l,
+                timestamp: luld_timestamp(t),
+            },
+            RawMessage::Other => continue,
+        });
+    }
+    Ok(batch)
+}
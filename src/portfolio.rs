@@ -0,0 +1,240 @@
+use crate::datastructures::{
+    account::{Account, Position},
+    client::TradingClient,
+};
+use futures_util::future::join_all;
+use std::{collections::BTreeMap, fmt, sync::Arc};
+
+/// Quote currencies stripped from crypto symbols written without a slash, e.g. Alpaca's "BTCUSD" positions.
+/// "USDT" and "USDC" come before "USD" so they are stripped whole.
+const CRYPTO_QUOTES: [&str; 4] = ["USDT", "USDC", "USD", "EUR"];
+
+/// Key positions are merged under: the base asset of a crypto holding, e.g. "BTC" for "BTC/USD", "BTC/USDT"
+/// or Alpaca's "BTCUSD", and the symbol of anything else.
+fn holding(position: &Position) -> String {
+    let symbol = position.symbol.to_uppercase();
+    if let Some((base, _)) = symbol.split_once('/') {
+        return base.to_string();
+    }
+    if position.asset_class.eq_ignore_ascii_case("crypto") {
+        for quote in CRYPTO_QUOTES {
+            if let Some(base) = symbol.strip_suffix(quote).filter(|base| !base.is_empty()) {
+                return base.to_string();
+            }
+        }
+    }
+    symbol
+}
+
+/// One broker account's holdings as reported.
+#[derive(Debug, Clone)]
+pub struct BrokerHoldings {
+    pub broker: String,
+    pub account: Account,
+    pub positions: Vec<Position>,
+}
+
+/// A holding merged across brokers.
+#[derive(Debug, Clone)]
+pub struct AggregatedPosition {
+    /// Symbol, or base asset for crypto.
+    pub symbol: String,
+    /// Negative when net short.
+    pub qty: f64,
+    pub market_value: f64,
+    /// Average entry price weighted by each broker's quantity. Zero when the positions net out.
+    pub avg_entry_price: f64,
+    pub unrealized_pl: f64,
+    /// Quantity and market value at each broker holding it, in the order the brokers were added.
+    pub by_broker: Vec<(String, f64, f64)>,
+}
+
+/// Holdings across every broker account, summed. Amounts are added as each broker reports them, so accounts
+/// should report in one currency; stablecoins count as dollars.
+#[derive(Debug, Clone)]
+pub struct PortfolioView {
+    pub cash: f64,
+    pub equity: f64,
+    pub last_equity: f64,
+    pub buying_power: f64,
+    pub long_market_value: f64,
+    pub short_market_value: f64,
+    /// Merged positions, largest absolute market value first.
+    pub positions: Vec<AggregatedPosition>,
+    /// Each broker's account and positions, in the order the brokers were added.
+    pub brokers: Vec<BrokerHoldings>,
+    /// Brokers that could not be queried, with the error. Their holdings are missing from the totals.
+    pub failures: Vec<(String, String)>,
+}
+
+impl PortfolioView {
+    /// Merges brokers' holdings into one view.
+    pub fn from_holdings(brokers: Vec<BrokerHoldings>, failures: Vec<(String, String)>) -> Self {
+        let mut merged: BTreeMap<String, AggregatedPosition> = BTreeMap::new();
+        for holdings in &brokers {
+            for position in &holdings.positions {
+                let symbol = holding(position);
+                let aggregated =
+                    merged
+                        .entry(symbol.clone())
+                        .or_insert_with(|| AggregatedPosition {
+                            symbol,
+                            qty: 0.0,
+                            market_value: 0.0,
+                            avg_entry_price: 0.0,
+                            unrealized_pl: 0.0,
+                            by_broker: vec![],
+                        });
+                // Summed cost for now; divided by the quantity below.
+                aggregated.avg_entry_price += position.qty * position.avg_entry_price;
+                aggregated.qty += position.qty;
+                aggregated.market_value += position.market_value;
+                aggregated.unrealized_pl += position.unrealized_pl;
+                aggregated.by_broker.push((
+                    holdings.broker.clone(),
+                    position.qty,
+                    position.market_value,
+                ));
+            }
+        }
+        let mut positions: Vec<AggregatedPosition> = merged
+            .into_values()
+            .map(|mut position| {
+                position.avg_entry_price = if position.qty != 0.0 {
+                    position.avg_entry_price / position.qty
+                } else {
+                    0.0
+                };
+                position
+            })
+            .collect();
+        positions.sort_by(|a, b| b.market_value.abs().total_cmp(&a.market_value.abs()));
+
+        let total = |amount: fn(&Account) -> f64| -> f64 {
+            brokers
+                .iter()
+                .map(|holdings| amount(&holdings.account))
+                .sum()
+        };
+        PortfolioView {
+            cash: total(|account| account.cash),
+            equity: total(|account| account.equity),
+            last_equity: total(|account| account.last_equity),
+            buying_power: total(|account| account.buying_power),
+            long_market_value: total(|account| account.long_market_value),
+            short_market_value: total(|account| account.short_market_value),
+            positions,
+            brokers,
+            failures,
+        }
+    }
+
+    /// Whether every broker was queried.
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// The merged position in `symbol`, or the base asset for crypto, e.g. "BTC".
+    pub fn position(&self, symbol: &str) -> Option<&AggregatedPosition> {
+        let symbol = symbol.to_uppercase();
+        self.positions
+            .iter()
+            .find(|position| position.symbol == symbol)
+    }
+}
+
+impl fmt::Display for PortfolioView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Equity: {:.2} Cash: {:.2} Buying power: {:.2}",
+            self.equity, self.cash, self.buying_power
+        )?;
+        writeln!(f, "Brokers:")?;
+        for holdings in &self.brokers {
+            writeln!(
+                f,
+                "  {:<12} equity={:>12.2} cash={:>12.2} positions={}",
+                holdings.broker,
+                holdings.account.equity,
+                holdings.account.cash,
+                holdings.positions.len()
+            )?;
+        }
+        for (broker, error) in &self.failures {
+            writeln!(f, "  {:<12} unavailable: {}", broker, error)?;
+        }
+        writeln!(f, "Positions:")?;
+        for position in &self.positions {
+            writeln!(
+                f,
+                "  {:<8} {:>12.4} {:>12.2}",
+                position.symbol, position.qty, position.market_value
+            )?;
+            for (broker, qty, market_value) in &position.by_broker {
+                writeln!(f, "    {:<12} {:>12.4} {:>12.2}", broker, qty, market_value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Queries several broker accounts at once and merges their holdings into a `PortfolioView`, e.g. an Alpaca
+/// stock account alongside a Kraken crypto account.
+#[derive(Clone, Default)]
+pub struct PortfolioAggregator {
+    brokers: Vec<(String, Arc<dyn TradingClient + Send + Sync>)>,
+}
+
+impl PortfolioAggregator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a broker account under `name`, which labels it in the breakdown.
+    pub fn broker(
+        mut self,
+        name: impl Into<String>,
+        client: impl TradingClient + Send + Sync + 'static,
+    ) -> Self {
+        self.brokers.push((name.into(), Arc::new(client)));
+        self
+    }
+
+    /// Names of the brokers, in the order they were added.
+    pub fn brokers(&self) -> impl Iterator<Item = &str> {
+        self.brokers.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Fetches every account and its positions concurrently. A broker that fails is listed in
+    /// `PortfolioView::failures` rather than failing the whole view.
+    pub async fn fetch(&self) -> PortfolioView {
+        let results = join_all(self.brokers.iter().map(|(name, client)| async move {
+            let (account, positions) =
+                futures_util::join!(client.get_account(), client.get_positions());
+            let holdings = match (account, positions) {
+                (Ok(account), Ok(positions)) => Ok(BrokerHoldings {
+                    broker: name.clone(),
+                    account,
+                    positions,
+                }),
+                (Err(e), _) | (_, Err(e)) => Err(e.to_string()),
+            };
+            (name, holdings)
+        }))
+        .await;
+
+        let mut brokers = vec![];
+        let mut failures = vec![];
+        for (name, holdings) in results {
+            match holdings {
+                Ok(holdings) => brokers.push(holdings),
+                Err(e) => {
+                    tracing::warn!(broker = %name, error = %e, "Failed to fetch broker holdings");
+                    failures.push((name.clone(), e));
+                }
+            }
+        }
+        PortfolioView::from_holdings(brokers, failures)
+    }
+}
@@ -0,0 +1,347 @@
+use crate::{
+    blackout::BlackoutViolation,
+    buying_power::InsufficientBuyingPower,
+    datastructures::{
+        account::{Account, AccountActivity, Position},
+        asset::Asset,
+        client::{MarketDataClient, SubscriptionParams, TradingClient},
+        event::{EventBatch, ParseMode},
+        market::{Bar, BarAdjustment, Quote},
+        order::{CancelOutcome, Order, OrderResponse},
+    },
+    http::CircuitOpen,
+    luld::LuldViolation,
+    observer::ReadOnlyError,
+    quotes::StaleQuote,
+    risk::RiskViolation,
+};
+use async_trait::async_trait;
+use std::{error::Error, future::Future, io, sync::Arc};
+use tokio::{
+    net::TcpStream,
+    runtime::{Builder, Handle, Runtime},
+};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+#[derive(Debug, Clone)]
+pub struct OrderLaneConfig {
+    /// Threads reserved for the order path. One is plenty unless many strategies share the lane.
+    pub worker_threads: usize,
+    pub thread_name: String,
+}
+
+impl Default for OrderLaneConfig {
+    fn default() -> Self {
+        OrderLaneConfig {
+            worker_threads: 1,
+            thread_name: "order-lane".to_string(),
+        }
+    }
+}
+
+/// Shuts the runtime down without blocking, since the last lane may be dropped from inside another runtime.
+struct LaneRuntime(Option<Runtime>);
+
+impl Drop for LaneRuntime {
+    fn drop(&mut self) {
+        if let Some(runtime) = self.0.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+/// Wraps a client so order submissions, cancels and order lookups run on a runtime of their own, with dedicated
+/// threads, instead of queueing behind market data processing on the caller's runtime. The request is sent and
+/// its response read on the lane's threads however busy the caller's runtime is; only resuming the caller waits
+/// on its own runtime. Account, asset, quote and streaming calls go straight to the wrapped client.
+///
+/// The lane's runtime stops once every clone is dropped.
+#[derive(Clone)]
+pub struct OrderLane<C> {
+    client: C,
+    handle: Handle,
+    _runtime: Arc<LaneRuntime>,
+}
+
+impl<C> OrderLane<C>
+where
+    C: TradingClient + Clone + Send + Sync + 'static,
+{
+    pub fn with_config(client: C, config: OrderLaneConfig) -> io::Result<Self> {
+        let runtime = Builder::new_multi_thread()
+            .worker_threads(config.worker_threads.max(1))
+            .thread_name(config.thread_name)
+            .enable_all()
+            .build()?;
+        Ok(OrderLane {
+            client,
+            handle: runtime.handle().clone(),
+            _runtime: Arc::new(LaneRuntime(Some(runtime))),
+        })
+    }
+
+    pub fn client(&self) -> &C {
+        &self.client
+    }
+
+    /// Handle to the lane's runtime, for other latency-sensitive work such as order-state bookkeeping.
+    pub fn handle(&self) -> &Handle {
+        &self.handle
+    }
+
+    /// Runs `call` with the client on the lane and waits for its result.
+    async fn run<F, Fut, T>(&self, call: F) -> Result<T, Box<dyn Error>>
+    where
+        F: FnOnce(C) -> Fut,
+        Fut: Future<Output = Result<T, Box<dyn Error + Send + Sync>>> + Send + 'static,
+        T: Send + 'static,
+    {
+        let task = self.handle.spawn(call(self.client.clone()));
+        let result = task.await.map_err(|e| e.to_string())?;
+        result.map_err(|e| -> Box<dyn Error> { e })
+    }
+}
+
+/// Moves a client's error off the lane's threads. Errors callers act on keep their type, so they can still be
+/// told apart with `downcast_ref`, e.g. a `RiskViolation` or a `reqwest::Error` for an outage; any other error
+/// is carried as its message.
+fn sendable(error: Box<dyn Error>) -> Box<dyn Error + Send + Sync> {
+    macro_rules! keep {
+        ($error:ident: $($kind:ty),* $(,)?) => {
+            $(
+                let $error = match $error.downcast::<$kind>() {
+                    Ok(error) => return error,
+                    Err(error) => error,
+                };
+            )*
+        };
+    }
+    keep!(
+        error: CircuitOpen,
+        reqwest::Error,
+        io::Error,
+        serde_json::Error,
+        RiskViolation,
+        InsufficientBuyingPower,
+        BlackoutViolation,
+        LuldViolation,
+        StaleQuote,
+        ReadOnlyError,
+    );
+    #[cfg(feature = "alpaca")]
+    keep!(error: crate::alpaca::AlpacaError);
+    #[cfg(feature = "binance")]
+    keep!(error: crate::binance::BinanceError);
+    #[cfg(feature = "gemini")]
+    keep!(error: crate::gemini::GeminiError);
+    #[cfg(feature = "kraken")]
+    keep!(error: crate::kraken::KrakenError);
+    #[cfg(feature = "schwab")]
+    keep!(error: crate::schwab::SchwabError, crate::schwab::OrderPreviewed);
+    error.to_string().into()
+}
+
+#[async_trait]
+impl<C> TradingClient for OrderLane<C>
+where
+    C: TradingClient + Clone + Send + Sync + 'static,
+{
+    async fn create_order(&self, order: &Order) -> Result<(), Box<dyn Error>> {
+        let order = order.clone();
+        self.run(|client| async move { client.create_order(&order).await.map_err(sendable) })
+            .await
+    }
+
+    async fn get_open_orders(&self) -> Result<Vec<OrderResponse>, Box<dyn Error>> {
+        self.run(|client| async move { client.get_open_orders().await.map_err(sendable) })
+            .await
+    }
+
+    async fn get_order(&self, order_id: &str) -> Result<OrderResponse, Box<dyn Error>> {
+        let order_id = order_id.to_string();
+        self.run(|client| async move { client.get_order(&order_id).await.map_err(sendable) })
+            .await
+    }
+
+    async fn get_order_by_client_id(
+        &self,
+        client_order_id: &str,
+    ) -> Result<OrderResponse, Box<dyn Error>> {
+        let client_order_id = client_order_id.to_string();
+        self.run(|client| async move {
+            client
+                .get_order_by_client_id(&client_order_id)
+                .await
+                .map_err(sendable)
+        })
+        .await
+    }
+
+    async fn cancel_order(&self, order_id: &str) -> Result<CancelOutcome, Box<dyn Error>> {
+        let order_id = order_id.to_string();
+        self.run(|client| async move { client.cancel_order(&order_id).await.map_err(sendable) })
+            .await
+    }
+
+    async fn get_asset(&self, symbol: &str) -> Result<Asset, Box<dyn Error>> {
+        self.client.get_asset(symbol).await
+    }
+
+    async fn list_assets(&self) -> Result<Vec<Asset>, Box<dyn Error>> {
+        self.client.list_assets().await
+    }
+
+    async fn get_account(&self) -> Result<Account, Box<dyn Error>> {
+        self.client.get_account().await
+    }
+
+    async fn get_positions(&self) -> Result<Vec<Position>, Box<dyn Error>> {
+        self.client.get_positions().await
+    }
+
+    async fn get_account_activities(
+        &self,
+        since: &str,
+    ) -> Result<Vec<AccountActivity>, Box<dyn Error>> {
+        self.client.get_account_activities(since).await
+    }
+}
+
+#[async_trait]
+impl<C> MarketDataClient for OrderLane<C>
+where
+    C: MarketDataClient + Send + Sync,
+{
+    async fn get_latest_quote(&self, symbol: &str) -> Result<Quote, Box<dyn Error>> {
+        self.client.get_latest_quote(symbol).await
+    }
+
+    async fn get_daily_bars(
+        &self,
+        symbol: &str,
+        start: &str,
+        end: &str,
+        adjustment: BarAdjustment,
+    ) -> Result<Vec<Bar>, Box<dyn Error>> {
+        self.client
+            .get_daily_bars(symbol, start, end, adjustment)
+            .await
+    }
+
+    async fn subscribe(
+        &self,
+        params: SubscriptionParams,
+    ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, Box<dyn Error>> {
+        self.client.subscribe(params).await
+    }
+
+    fn parse_frame(&self, frame: &str, mode: ParseMode) -> Result<EventBatch, serde_json::Error> {
+        self.client.parse_frame(frame, mode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        datastructures::order::OrderSide,
+        outage,
+        risk::RiskLimits,
+        sim::{SimClient, SimConfig},
+        testing::MockTradingClient,
+    };
+    use std::time::{Duration, Instant};
+
+    fn buy(symbol: &str) -> Order {
+        Order::builder()
+            .symbol(symbol)
+            .quantity(1.0)
+            .side(OrderSide::Buy)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn keeps_the_type_of_errors_callers_act_on() {
+        let open: Box<dyn Error> = Box::new(CircuitOpen {
+            retry_after: Duration::from_secs(1),
+        });
+        let open: Box<dyn Error> = sendable(open);
+        assert!(outage::is_outage_error(open.as_ref()));
+
+        let other: Box<dyn Error> = sendable("Order not found".into());
+        assert_eq!(other.to_string(), "Order not found");
+        assert!(!outage::is_outage_error(other.as_ref()));
+    }
+
+    #[cfg(feature = "schwab")]
+    #[test]
+    fn keeps_schwab_previews_apart_from_placed_orders() {
+        let previewed: Box<dyn Error> = Box::new(crate::schwab::OrderPreviewed {
+            symbol: "AAPL".to_string(),
+            preview: serde_json::Value::Null,
+        });
+        let previewed = sendable(previewed);
+        assert!(previewed.is::<crate::schwab::OrderPreviewed>());
+    }
+
+    #[tokio::test]
+    async fn passes_risk_rejections_through() {
+        let client = SimClient::with_config(SimConfig {
+            risk_limits: Some(RiskLimits {
+                restricted: ["GME".to_string()].into(),
+                ..RiskLimits::default()
+            }),
+            ..SimConfig::default()
+        });
+        let lane = OrderLane::with_config(client, OrderLaneConfig::default()).unwrap();
+
+        let error = lane.create_order(&buy("GME")).await.unwrap_err();
+        let violation = error.downcast_ref::<RiskViolation>().unwrap();
+        assert_eq!(violation.symbol, "GME");
+        lane.create_order(&buy("AAPL")).await.unwrap();
+    }
+
+    #[test]
+    fn reaches_the_broker_while_the_caller_is_blocked() {
+        let runtime = Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(async {
+            let client = MockTradingClient::with_cash(10_000.0);
+            let lane = OrderLane::with_config(client.clone(), OrderLaneConfig::default()).unwrap();
+            let submit = tokio::spawn({
+                let lane = lane.clone();
+                async move {
+                    lane.create_order(&buy("AAPL"))
+                        .await
+                        .map_err(|e| e.to_string())
+                }
+            });
+            // Lets the submission start, then holds the caller's only thread as a burst of market data would.
+            tokio::task::yield_now().await;
+            let start = Instant::now();
+            while client.submitted().is_empty() && start.elapsed() < Duration::from_secs(5) {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            let latency = start.elapsed();
+            assert_eq!(client.submitted().len(), 1);
+            assert!(latency < Duration::from_secs(1), "took {:?}", latency);
+            submit.await.unwrap().unwrap();
+        });
+    }
+
+    #[test]
+    fn round_trips_quickly() {
+        let runtime = Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(async {
+            let client = MockTradingClient::with_cash(1_000_000.0);
+            let lane = OrderLane::with_config(client.clone(), OrderLaneConfig::default()).unwrap();
+            let start = Instant::now();
+            for _ in 0..100 {
+                lane.create_order(&buy("AAPL")).await.unwrap();
+            }
+            let per_call = start.elapsed() / 100;
+            assert_eq!(client.submitted().len(), 100);
+            assert!(per_call < Duration::from_millis(20), "took {:?}", per_call);
+        });
+    }
+}
@@ -0,0 +1,247 @@
+use crate::{
+    datastructures::{
+        client::{BrokerClient, MarketDataClient},
+        event::EventType,
+        market::Quote,
+        order::{Order, OrderSide, OrderType},
+    },
+    stream::EventBus,
+    time,
+};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+use tokio::task::JoinHandle;
+
+/// A quote along with when it was cached.
+#[derive(Debug, Clone)]
+pub struct CachedQuote {
+    pub quote: Quote,
+    pub received_at: Instant,
+}
+
+impl CachedQuote {
+    /// Time since the quote's exchange timestamp, or since it was received if the timestamp cannot be parsed.
+    pub fn age(&self) -> Duration {
+        time::age(&self.quote.timestamp).unwrap_or_else(|| self.received_at.elapsed())
+    }
+}
+
+/// Latest quote (NBBO for stocks) per symbol, fed from `EventType::Quote` events. Reads take a shared lock, so
+/// order placement code can look prices up without owning the stream.
+#[derive(Clone, Default)]
+pub struct QuoteCache {
+    quotes: Arc<RwLock<HashMap<String, CachedQuote>>>,
+}
+
+impl QuoteCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the quote carried by a quote event. Other events are ignored.
+    pub fn update(&self, event: &EventType) {
+        if let Some(quote) = Quote::from_event(event) {
+            self.insert(quote);
+        }
+    }
+
+    /// Caches the quote unless the cached one is newer, as can happen when sharded connections or a REST
+    /// snapshot race the stream.
+    pub fn insert(&self, quote: Quote) {
+        let mut quotes = self.quotes.write().unwrap();
+        if let Some(cached) = quotes.get(&quote.symbol) {
+            let cached_at = time::parse_rfc3339(&cached.quote.timestamp);
+            if cached_at.is_some() && cached_at > time::parse_rfc3339(&quote.timestamp) {
+                return;
+            }
+        }
+        quotes.insert(
+            quote.symbol.clone(),
+            CachedQuote {
+                quote,
+                received_at: Instant::now(),
+            },
+        );
+    }
+
+    pub fn latest(&self, symbol: &str) -> Option<CachedQuote> {
+        self.quotes.read().unwrap().get(symbol).cloned()
+    }
+
+    /// Best bid and ask prices, without copying the rest of the quote.
+    pub fn bid_ask(&self, symbol: &str) -> Option<(f64, f64)> {
+        self.quotes
+            .read()
+            .unwrap()
+            .get(symbol)
+            .map(|cached| (cached.quote.bid_price, cached.quote.ask_price))
+    }
+
+    pub fn mid(&self, symbol: &str) -> Option<f64> {
+        self.bid_ask(symbol).map(|(bid, ask)| (bid + ask) / 2.0)
+    }
+
+    /// Symbols with a cached quote.
+    pub fn symbols(&self) -> Vec<String> {
+        self.quotes.read().unwrap().keys().cloned().collect()
+    }
+
+    /// Keeps the cache up to date from a bus subscription until the returned handle is dropped or the stream ends.
+    pub fn follow(&self, bus: &EventBus) -> QuoteFeed {
+        let mut subscriber = bus.subscribe();
+        let cache = self.clone();
+        QuoteFeed {
+            task: tokio::spawn(async move {
+                while let Some(batch) = subscriber.recv_batch().await {
+                    for event in batch.iter() {
+                        cache.update(event);
+                    }
+                }
+            }),
+        }
+    }
+}
+
+/// Background task feeding a `QuoteCache`. Stops when dropped.
+pub struct QuoteFeed {
+    task: JoinHandle<()>,
+}
+
+impl QuoteFeed {
+    /// Whether the stream has ended.
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+impl Drop for QuoteFeed {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// What to do when a marketable order's reference quote is too old.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StaleQuotePolicy {
+    Reject,
+    /// Fetch a snapshot over REST and proceed if it is fresh.
+    Refetch,
+}
+
+#[derive(Debug, Clone)]
+pub struct StaleQuote {
+    pub symbol: String,
+    /// `None` when there was no quote at all.
+    pub age: Option<Duration>,
+    pub max_age: Duration,
+}
+
+impl fmt::Display for StaleQuote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.age {
+            Some(age) => write!(
+                f,
+                "Quote for {} is {:?} old, older than the allowed {:?}",
+                self.symbol, age, self.max_age
+            ),
+            None => write!(f, "No quote for {}", self.symbol),
+        }
+    }
+}
+
+impl Error for StaleQuote {}
+
+/// Refuses to place marketable orders priced off stale quotes, e.g. after a feed hiccup.
+/// Orders that rest on the book are not affected.
+#[derive(Clone)]
+pub struct StaleQuoteGuard {
+    cache: QuoteCache,
+    max_age: Duration,
+    policy: StaleQuotePolicy,
+}
+
+impl StaleQuoteGuard {
+    pub fn new(cache: QuoteCache, max_age: Duration, policy: StaleQuotePolicy) -> Self {
+        StaleQuoteGuard {
+            cache,
+            max_age,
+            policy,
+        }
+    }
+
+    /// Returns a quote for `symbol` no older than the configured maximum, refetching it if the policy allows.
+    /// A refetched quote is written back to the cache.
+    pub async fn fresh_quote<C: MarketDataClient>(
+        &self,
+        client: &C,
+        symbol: &str,
+    ) -> Result<CachedQuote, Box<dyn Error>> {
+        let cached = self.cache.latest(symbol);
+        if let Some(cached) = cached
+            .as_ref()
+            .filter(|cached| cached.age() <= self.max_age)
+        {
+            return Ok(cached.clone());
+        }
+
+        let stale = StaleQuote {
+            symbol: symbol.to_string(),
+            age: cached.as_ref().map(CachedQuote::age),
+            max_age: self.max_age,
+        };
+        if self.policy == StaleQuotePolicy::Reject {
+            return Err(stale.into());
+        }
+
+        tracing::warn!(%stale, "Refetching stale quote");
+        self.cache.insert(client.get_latest_quote(symbol).await?);
+        match self.cache.latest(symbol) {
+            Some(cached) if cached.age() <= self.max_age => Ok(cached),
+            refetched => Err(StaleQuote {
+                age: refetched.as_ref().map(CachedQuote::age),
+                ..stale
+            }
+            .into()),
+        }
+    }
+
+    /// Checks the reference quote of a marketable order. Limit orders count as marketable when they cross
+    /// the cached quote, or when there is no quote to tell. Stop orders rest until triggered and are not checked.
+    pub async fn check<C: MarketDataClient>(
+        &self,
+        client: &C,
+        order: &Order,
+    ) -> Result<(), Box<dyn Error>> {
+        let marketable = match (order.order_type, order.limit_price) {
+            (OrderType::Market, _) => true,
+            (OrderType::Limit, Some(limit_price)) => {
+                self.cache
+                    .latest(&order.symbol)
+                    .is_none_or(|cached| match order.side {
+                        OrderSide::Buy => limit_price >= cached.quote.ask_price,
+                        OrderSide::Sell => limit_price <= cached.quote.bid_price,
+                    })
+            }
+            _ => false,
+        };
+        if marketable {
+            self.fresh_quote(client, &order.symbol).await?;
+        }
+        Ok(())
+    }
+
+    /// Submits the order if it passes `check`.
+    pub async fn submit<C: BrokerClient>(
+        &self,
+        client: &C,
+        order: &Order,
+    ) -> Result<(), Box<dyn Error>> {
+        self.check(client, order).await?;
+        client.create_order(order).await
+    }
+}
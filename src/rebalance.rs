@@ -0,0 +1,304 @@
+use crate::{
+    datastructures::{
+        account::Position,
+        client::BrokerClient,
+        order::{Order, OrderSide, OrderType, TimeInForce},
+    },
+    funding::{DeferredOrders, FundingConfig},
+};
+use std::{collections::HashMap, error::Error, fmt};
+use tokio_util::sync::CancellationToken;
+
+/// Metadata key marking orders sent by `rebalance`.
+pub const REBALANCE_TAG: &str = "rebalance";
+
+#[derive(Debug, Clone)]
+pub struct RebalanceConfig {
+    /// Orders worth less than this are not sent.
+    pub min_order_notional: f64,
+    /// Trades smaller than this fraction of equity are skipped, so small drifts do not cause churn.
+    pub turnover_threshold: f64,
+    /// Fraction of equity left in cash. Target weights are scaled down to make room for it.
+    pub cash_buffer: f64,
+    /// Trade fractional quantities. Otherwise quantities are rounded towards the current position.
+    pub fractional: bool,
+    /// Close positions in symbols that have no target weight.
+    pub close_untargeted: bool,
+}
+
+impl Default for RebalanceConfig {
+    fn default() -> Self {
+        RebalanceConfig {
+            min_order_notional: 1.0,
+            turnover_threshold: 0.005,
+            cash_buffer: 0.0,
+            fractional: false,
+            close_untargeted: true,
+        }
+    }
+}
+
+/// One symbol's move from its current weight to its target.
+#[derive(Debug, Clone)]
+pub struct RebalanceTrade {
+    pub symbol: String,
+    pub price: f64,
+    pub current_weight: f64,
+    pub target_weight: f64,
+    pub current_quantity: f64,
+    pub target_quantity: f64,
+    pub order: Order,
+}
+
+impl RebalanceTrade {
+    pub fn notional(&self) -> f64 {
+        self.order.quantity * self.price
+    }
+}
+
+impl fmt::Display for RebalanceTrade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {:.2}% -> {:.2}%: {:?} {}",
+            self.symbol,
+            self.current_weight * 100.0,
+            self.target_weight * 100.0,
+            self.order.side,
+            self.order.quantity
+        )
+    }
+}
+
+/// Outcome of `rebalance`.
+#[derive(Debug, Clone, Default)]
+pub struct RebalanceReport {
+    pub equity: f64,
+    pub planned: Vec<RebalanceTrade>,
+    /// Trades whose orders were accepted. Always empty when not submitting.
+    pub submitted: Vec<RebalanceTrade>,
+    /// Trades whose orders failed, with the error message.
+    pub failed: Vec<(RebalanceTrade, String)>,
+    /// Buys held back until settled cash covers them. Only `rebalance_when_funded` defers trades.
+    pub deferred: Vec<RebalanceTrade>,
+}
+
+impl RebalanceReport {
+    /// Notional traded as a fraction of equity, counting buys and sells.
+    pub fn turnover(&self) -> f64 {
+        if self.equity <= 0.0 {
+            return 0.0;
+        }
+        self.planned
+            .iter()
+            .map(RebalanceTrade::notional)
+            .sum::<f64>()
+            / self.equity
+    }
+}
+
+/// Orders that move `positions` to the `targets` weights of `equity`, sells first. Negative weights are short
+/// targets. `prices` must cover every symbol involved; positions fall back to their current price.
+pub fn plan_rebalance(
+    targets: &HashMap<String, f64>,
+    equity: f64,
+    positions: &[Position],
+    prices: &HashMap<String, f64>,
+    config: &RebalanceConfig,
+) -> Result<Vec<RebalanceTrade>, &'static str> {
+    if equity <= 0.0 {
+        return Err("Equity must be positive");
+    }
+    if targets.values().map(|weight| weight.abs()).sum::<f64>() > 1.0 + 1e-9 {
+        return Err("Target weights add up to more than 1");
+    }
+    let scale = 1.0 - config.cash_buffer.clamp(0.0, 1.0);
+
+    let mut symbols: Vec<&String> = targets.keys().collect();
+    if config.close_untargeted {
+        symbols.extend(
+            positions
+                .iter()
+                .map(|position| &position.symbol)
+                .filter(|symbol| !targets.contains_key(*symbol)),
+        );
+    }
+    symbols.sort();
+    symbols.dedup();
+
+    let mut trades = vec![];
+    for symbol in symbols {
+        let position = positions.iter().find(|p| &p.symbol == symbol);
+        let price = prices
+            .get(symbol)
+            .copied()
+            .or_else(|| position.and_then(|p| p.current_price))
+            .filter(|price| *price > 0.0)
+            .ok_or("No price for a symbol to rebalance")?;
+        let current_quantity = position.map_or(0.0, |p| p.qty);
+        let target_weight = targets.get(symbol).copied().unwrap_or(0.0) * scale;
+
+        let exact = target_weight * equity / price;
+        let fractional = config.fractional
+            || position.is_some_and(|p| p.asset_class == "crypto")
+            || symbol.contains('/');
+        // Whole shares round towards the current position, so a rebalance never overshoots its target.
+        let target_quantity = if fractional || target_weight == 0.0 {
+            exact
+        } else if exact > current_quantity {
+            exact.floor().max(current_quantity)
+        } else {
+            exact.ceil().min(current_quantity)
+        };
+
+        let quantity = target_quantity - current_quantity;
+        let notional = quantity.abs() * price;
+        if quantity == 0.0
+            || notional < config.min_order_notional
+            || (notional < config.turnover_threshold * equity && target_weight != 0.0)
+        {
+            continue;
+        }
+
+        let Ok(order) = Order::builder()
+            .symbol(symbol.clone())
+            .quantity(quantity.abs())
+            .side(if quantity > 0.0 {
+                OrderSide::Buy
+            } else {
+                OrderSide::Sell
+            })
+            .order_type(OrderType::Market)
+            .time_in_force(if fractional {
+                TimeInForce::Gtc
+            } else {
+                TimeInForce::Day
+            })
+            .tag(REBALANCE_TAG, "true")
+            .build()
+        else {
+            continue;
+        };
+        trades.push(RebalanceTrade {
+            symbol: symbol.clone(),
+            price,
+            current_weight: current_quantity * price / equity,
+            target_weight,
+            current_quantity,
+            target_quantity,
+            order,
+        });
+    }
+
+    trades.sort_by_key(|trade| trade.order.side == OrderSide::Buy);
+    Ok(trades)
+}
+
+/// Reads the account, positions and latest quotes and plans a rebalance to `targets`, a weight per symbol.
+/// With `submit` the orders are sent, sells first so their proceeds fund the buys; otherwise the plan is only
+/// logged and returned.
+pub async fn rebalance<C: BrokerClient>(
+    client: &C,
+    targets: &HashMap<String, f64>,
+    config: &RebalanceConfig,
+    submit: bool,
+) -> Result<RebalanceReport, Box<dyn Error>> {
+    let (report, _) = execute(client, targets, config, submit, false).await?;
+    Ok(report)
+}
+
+/// Like `rebalance` with `submit`, for accounts where sale proceeds take time to settle. Sells are sent at
+/// once, and buys as far as settled cash covers them; the remaining buys are deferred rather than sent to be
+/// rejected, and go out together once enough cash has settled. The returned handle tracks them and abandons
+/// them when dropped.
+pub async fn rebalance_when_funded<C>(
+    client: &C,
+    targets: &HashMap<String, f64>,
+    config: &RebalanceConfig,
+    funding: &FundingConfig,
+    cancel: CancellationToken,
+) -> Result<(RebalanceReport, Option<DeferredOrders>), Box<dyn Error>>
+where
+    C: BrokerClient + Clone + Send + Sync + 'static,
+{
+    let (report, required) = execute(client, targets, config, true, true).await?;
+    if report.deferred.is_empty() {
+        return Ok((report, None));
+    }
+    tracing::info!(
+        trades = report.deferred.len(),
+        required,
+        "Rebalance buys deferred until cash settles"
+    );
+    let orders = report
+        .deferred
+        .iter()
+        .map(|trade| trade.order.clone())
+        .collect();
+    let deferred = DeferredOrders::spawn(client.clone(), orders, required, funding.clone(), cancel);
+    Ok((report, Some(deferred)))
+}
+
+/// Plans and sends a rebalance. With `defer`, buys that settled cash does not cover are moved to
+/// `deferred`, and the settled cash they need in total is returned.
+async fn execute<C: BrokerClient>(
+    client: &C,
+    targets: &HashMap<String, f64>,
+    config: &RebalanceConfig,
+    submit: bool,
+    defer: bool,
+) -> Result<(RebalanceReport, f64), Box<dyn Error>> {
+    let account = client.get_account().await?;
+    let positions = client.get_positions().await?;
+    let mut prices = HashMap::new();
+    for symbol in targets.keys() {
+        let held = positions
+            .iter()
+            .find(|position| &position.symbol == symbol)
+            .and_then(|position| position.current_price);
+        let price = match held {
+            Some(price) => price,
+            None => client.get_latest_quote(symbol).await?.mid(),
+        };
+        prices.insert(symbol.clone(), price);
+    }
+
+    let mut report = RebalanceReport {
+        equity: account.equity,
+        planned: plan_rebalance(targets, account.equity, &positions, &prices, config)?,
+        ..RebalanceReport::default()
+    };
+    let mut settled = account.settled_cash();
+    let mut required = 0.0;
+    for trade in &report.planned {
+        if !submit {
+            tracing::info!(%trade, "Dry run");
+            continue;
+        }
+        if defer && trade.order.side == OrderSide::Buy {
+            // Once one buy is deferred the rest are too, so they go out in the planned order.
+            if required > 0.0 || trade.notional() > settled {
+                required += trade.notional();
+                report.deferred.push(trade.clone());
+                continue;
+            }
+            settled -= trade.notional();
+        }
+        match client
+            .create_order(&trade.order)
+            .await
+            .map_err(|e| e.to_string())
+        {
+            Ok(()) => {
+                tracing::info!(%trade, "Rebalance order sent");
+                report.submitted.push(trade.clone());
+            }
+            Err(e) => {
+                tracing::error!(%trade, error = %e, "Rebalance order failed");
+                report.failed.push((trade.clone(), e));
+            }
+        }
+    }
+
+    Ok((report, required))
+}
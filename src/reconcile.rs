@@ -0,0 +1,264 @@
+use crate::{
+    datastructures::{
+        client::BrokerClient,
+        event::EventType,
+        market::{Bar, BarAdjustment},
+    },
+    time,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt,
+};
+
+/// Which events `DailyBars` builds its bars from. Pick one so overlapping channels are not counted twice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BarSource {
+    Trades,
+    MinuteBars,
+}
+
+/// Daily bars aggregated locally from streamed events, keyed by UTC date and symbol.
+pub struct DailyBars {
+    source: BarSource,
+    bars: BTreeMap<String, HashMap<String, Bar>>,
+}
+
+impl DailyBars {
+    pub fn new(source: BarSource) -> Self {
+        DailyBars {
+            source,
+            bars: BTreeMap::new(),
+        }
+    }
+
+    pub fn update(&mut self, event: &EventType) {
+        let (symbol, open, high, low, close, volume, timestamp) = match (self.source, event) {
+            (
+                BarSource::Trades,
+                EventType::Trade {
+                    symbol,
+                    price,
+                    volume,
+                    timestamp,
+                },
+            ) => (symbol, *price, *price, *price, *price, *volume, timestamp),
+            (
+                BarSource::MinuteBars,
+                EventType::Bar {
+                    symbol,
+                    open,
+                    high,
+                    low,
+                    close,
+                    volume,
+                    timestamp,
+                },
+            ) => (symbol, *open, *high, *low, *close, *volume, timestamp),
+            _ => return,
+        };
+        let Some(date) = time::date(timestamp) else {
+            return;
+        };
+
+        let bars = self.bars.entry(date.clone()).or_default();
+        match bars.get_mut(symbol) {
+            Some(bar) => {
+                bar.high = bar.high.max(high);
+                bar.low = bar.low.min(low);
+                bar.close = close;
+                bar.volume += volume;
+            }
+            None => {
+                bars.insert(
+                    symbol.clone(),
+                    Bar {
+                        symbol: symbol.clone(),
+                        open,
+                        high,
+                        low,
+                        close,
+                        volume,
+                        timestamp: format!("{}T00:00:00Z", date),
+                    },
+                );
+            }
+        }
+    }
+
+    /// Bars for one date, as YYYY-MM-DD.
+    pub fn bars(&self, date: &str) -> Vec<Bar> {
+        self.bars
+            .get(date)
+            .map(|bars| bars.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Removes and returns the bars for one date, e.g. once it has been reconciled.
+    pub fn take(&mut self, date: &str) -> Vec<Bar> {
+        self.bars
+            .remove(date)
+            .map(|bars| bars.into_values().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// How far local bars may drift from official ones before it is reported.
+#[derive(Debug, Clone, Copy)]
+pub struct ReconcileConfig {
+    /// Relative difference allowed in open, high, low and close.
+    pub price_tolerance: f64,
+    /// Relative difference allowed in volume. Streams routinely miss some trades, so this is looser.
+    pub volume_tolerance: f64,
+}
+
+impl Default for ReconcileConfig {
+    fn default() -> Self {
+        ReconcileConfig {
+            price_tolerance: 0.001,
+            volume_tolerance: 0.05,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Discrepancy {
+    /// The broker has a bar the local data does not.
+    MissingLocal { symbol: String },
+    /// The local data has a bar the broker does not.
+    MissingOfficial { symbol: String },
+    Mismatch {
+        symbol: String,
+        field: &'static str,
+        local: f64,
+        official: f64,
+    },
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Discrepancy::MissingLocal { symbol } => write!(f, "{}: no local bar", symbol),
+            Discrepancy::MissingOfficial { symbol } => write!(f, "{}: no official bar", symbol),
+            Discrepancy::Mismatch {
+                symbol,
+                field,
+                local,
+                official,
+            } => write!(
+                f,
+                "{}: {} is {} locally but {} officially",
+                symbol, field, local, official
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReconciliationReport {
+    pub date: String,
+    pub symbols_checked: usize,
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+impl ReconciliationReport {
+    pub fn is_clean(&self) -> bool {
+        self.discrepancies.is_empty()
+    }
+}
+
+impl fmt::Display for ReconciliationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} symbols checked, {} discrepancies",
+            self.date,
+            self.symbols_checked,
+            self.discrepancies.len()
+        )?;
+        for discrepancy in &self.discrepancies {
+            write!(f, "\n  {}", discrepancy)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares local and official bars for the same symbol.
+pub fn compare(local: &Bar, official: &Bar, config: &ReconcileConfig) -> Vec<Discrepancy> {
+    let differs = |local: f64, official: f64, tolerance: f64| {
+        (local - official).abs() > tolerance * official.abs().max(f64::EPSILON)
+    };
+    let fields = [
+        ("open", local.open, official.open, config.price_tolerance),
+        ("high", local.high, official.high, config.price_tolerance),
+        ("low", local.low, official.low, config.price_tolerance),
+        ("close", local.close, official.close, config.price_tolerance),
+        (
+            "volume",
+            local.volume,
+            official.volume,
+            config.volume_tolerance,
+        ),
+    ];
+    fields
+        .into_iter()
+        .filter(|(_, local, official, tolerance)| differs(*local, *official, *tolerance))
+        .map(
+            |(field, local_value, official_value, _)| Discrepancy::Mismatch {
+                symbol: local.symbol.clone(),
+                field,
+                local: local_value,
+                official: official_value,
+            },
+        )
+        .collect()
+}
+
+/// Fetches the broker's daily bar for every symbol in `local` and in `expected`, and reports where they disagree.
+/// Listing the symbols that were subscribed in `expected` also catches those with no local bar at all.
+/// `date` is YYYY-MM-DD; run it after the close, once the official bar is final.
+pub async fn reconcile<C: BrokerClient>(
+    client: &C,
+    date: &str,
+    local: &[Bar],
+    expected: &[&str],
+    config: &ReconcileConfig,
+) -> Result<ReconciliationReport, Box<dyn Error>> {
+    let mut symbols: Vec<&str> = local.iter().map(|bar| bar.symbol.as_str()).collect();
+    for symbol in expected {
+        if !symbols.contains(symbol) {
+            symbols.push(symbol);
+        }
+    }
+
+    let mut discrepancies = Vec::new();
+    for symbol in &symbols {
+        let official = client
+            .get_daily_bars(symbol, date, date, BarAdjustment::Raw)
+            .await?;
+        let official = official
+            .iter()
+            .find(|official| time::date(&official.timestamp).as_deref() == Some(date));
+        let local = local.iter().find(|bar| bar.symbol == *symbol);
+        match (local, official) {
+            (Some(local), Some(official)) => discrepancies.extend(compare(local, official, config)),
+            (Some(_), None) => discrepancies.push(Discrepancy::MissingOfficial {
+                symbol: symbol.to_string(),
+            }),
+            (None, Some(_)) => discrepancies.push(Discrepancy::MissingLocal {
+                symbol: symbol.to_string(),
+            }),
+            (None, None) => {}
+        }
+    }
+    if !discrepancies.is_empty() {
+        tracing::warn!(%date, discrepancies = discrepancies.len(), "Local bars disagree with official bars");
+    }
+
+    Ok(ReconciliationReport {
+        date: date.to_string(),
+        symbols_checked: symbols.len(),
+        discrepancies,
+    })
+}
@@ -0,0 +1,601 @@
+#[cfg(feature = "parquet")]
+use crate::parquet::{Column, ColumnType, ParquetWriter, Value};
+use crate::{datastructures::event::EventType, shutdown::Sink, time};
+use async_trait::async_trait;
+use futures_util::Stream;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Write},
+    mem::{self, Discriminant},
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+const NANOS_PER_DAY: i64 = 86_400 * 1_000_000_000;
+
+/// How recorded events are split into files under the recorder's directory. Names are shown for JSONL;
+/// Parquet files end in `.parquet` instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Partition {
+    /// Everything in `events.jsonl`.
+    None,
+    /// `<symbol>.jsonl`.
+    Symbol,
+    /// `<date>/events.jsonl`, dated by the event's timestamp in UTC.
+    Date,
+    /// `<date>/<symbol>.jsonl`.
+    DateAndSymbol,
+}
+
+/// Which events of a channel are recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sample {
+    All,
+    /// The first of every `n` events of each symbol. Zero records none.
+    OneIn(u32),
+    None,
+}
+
+impl Sample {
+    fn keeps(self, seen: u64) -> bool {
+        match self {
+            Sample::All => true,
+            Sample::OneIn(n) => n > 0 && seen.is_multiple_of(n as u64),
+            Sample::None => false,
+        }
+    }
+}
+
+/// Sampling per channel. Written to `sampling.json` in the recorder's directory when the recorder first writes,
+/// so analysis of the recording can scale counts back up; see `read_sampling`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SamplingPolicy {
+    pub trades: Sample,
+    pub quotes: Sample,
+    /// Minute, updated and daily bars.
+    pub bars: Sample,
+    pub order_books: Sample,
+    /// Limit up/limit down bands and news.
+    pub other: Sample,
+}
+
+impl Default for SamplingPolicy {
+    /// Records everything.
+    fn default() -> Self {
+        SamplingPolicy {
+            trades: Sample::All,
+            quotes: Sample::All,
+            bars: Sample::All,
+            order_books: Sample::All,
+            other: Sample::All,
+        }
+    }
+}
+
+impl SamplingPolicy {
+    pub fn sample(&self, event: &EventType) -> Sample {
+        match event {
+            EventType::Trade { .. } => self.trades,
+            EventType::Quote { .. } => self.quotes,
+            EventType::Bar { .. } | EventType::UpdatedBar { .. } | EventType::DailyBar { .. } => {
+                self.bars
+            }
+            EventType::OrderBook { .. } => self.order_books,
+            _ => self.other,
+        }
+    }
+}
+
+/// Sampling policy a recording was made with, from its directory. `None` if it has no `sampling.json`, as
+/// with recordings made before sampling, which recorded everything.
+pub fn read_sampling(directory: impl AsRef<Path>) -> io::Result<Option<SamplingPolicy>> {
+    match fs::read_to_string(directory.as_ref().join(SAMPLING_FILE)) {
+        Ok(contents) => Ok(Some(serde_json::from_str(&contents)?)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+const SAMPLING_FILE: &str = "sampling.json";
+
+/// File format of a recording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordFormat {
+    /// One serialized `EventType` per line, appended as events arrive.
+    Jsonl,
+    /// One row per event and one column per event field, for pandas, DuckDB or Spark; order book levels are
+    /// JSON text. Rows are written in groups of `rows_per_group`, and a file is only readable
+    /// once finished, on rotation, `close` or when the last clone of the recorder is dropped. Files are never
+    /// appended to: events after a `close` start the next numbered file.
+    #[cfg(feature = "parquet")]
+    Parquet { rows_per_group: usize },
+}
+
+#[derive(Debug, Clone)]
+pub struct RecorderConfig {
+    pub directory: PathBuf,
+    pub partition: Partition,
+    /// Start a new numbered file (`<name>.1.jsonl`, `<name>.2.jsonl`, ...) once a file reaches this size.
+    /// `None` never rotates by size.
+    pub max_file_bytes: Option<u64>,
+    pub sampling: SamplingPolicy,
+    pub format: RecordFormat,
+}
+
+impl RecorderConfig {
+    /// Partitioned by date and symbol, rotating at 256 MiB.
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        RecorderConfig {
+            directory: directory.into(),
+            partition: Partition::DateAndSymbol,
+            max_file_bytes: Some(256 * 1024 * 1024),
+            sampling: SamplingPolicy::default(),
+            format: RecordFormat::Jsonl,
+        }
+    }
+}
+
+/// An event encoded for the recorder's format.
+enum Encoded {
+    Line(Vec<u8>),
+    #[cfg(feature = "parquet")]
+    Row(Vec<Value>),
+}
+
+enum Output {
+    Jsonl(BufWriter<File>),
+    #[cfg(feature = "parquet")]
+    Parquet(ParquetWriter),
+}
+
+impl Output {
+    fn write(&mut self, encoded: Encoded) -> io::Result<()> {
+        match (self, encoded) {
+            (Output::Jsonl(writer), Encoded::Line(line)) => writer.write_all(&line),
+            #[cfg(feature = "parquet")]
+            (Output::Parquet(writer), Encoded::Row(row)) => writer.write_row(row),
+            #[cfg(feature = "parquet")]
+            _ => unreachable!("events are encoded for the recorder's format"),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Jsonl(writer) => writer.flush(),
+            #[cfg(feature = "parquet")]
+            Output::Parquet(writer) => writer.flush(),
+        }
+    }
+
+    /// Flushes, writing the footer of Parquet files, and syncs to disk.
+    fn finish(&mut self) -> io::Result<()> {
+        match self {
+            Output::Jsonl(writer) => {
+                writer.flush()?;
+                writer.get_ref().sync_all()
+            }
+            #[cfg(feature = "parquet")]
+            Output::Parquet(writer) => {
+                writer.finish()?;
+                writer.sync()
+            }
+        }
+    }
+}
+
+struct OpenFile {
+    output: Output,
+    bytes: u64,
+    part: u32,
+}
+
+/// Persists market data events to files, as JSONL with one serialized `EventType` per line or, with the
+/// `parquet` feature, as Parquet; see `RecordFormat`.
+/// Events without a symbol, such as `StaleConnection`, are not recorded, and events the sampling policy drops
+/// are skipped. Clones write to the same files.
+#[derive(Clone)]
+pub struct Recorder {
+    config: RecorderConfig,
+    files: Arc<Mutex<HashMap<PathBuf, OpenFile>>>,
+    sampling: Arc<Mutex<SamplingState>>,
+}
+
+#[derive(Default)]
+struct SamplingState {
+    /// Events seen per channel and symbol, for 1-in-N sampling.
+    seen: HashMap<(Discriminant<EventType>, String), u64>,
+    written: bool,
+}
+
+impl Recorder {
+    pub fn new(config: RecorderConfig) -> Self {
+        Recorder {
+            config,
+            files: Arc::new(Mutex::new(HashMap::new())),
+            sampling: Arc::new(Mutex::new(SamplingState::default())),
+        }
+    }
+
+    pub fn record(&self, event: &EventType) -> io::Result<()> {
+        let Some(symbol) = event.symbol() else {
+            return Ok(());
+        };
+        if !self.sampled(event, symbol) {
+            return Ok(());
+        }
+        self.write_sampling()?;
+        // Parquet sizes are only known once written, so those files rotate after reaching the limit.
+        let (encoded, length) = match self.config.format {
+            RecordFormat::Jsonl => {
+                let mut line = serde_json::to_vec(event)?;
+                line.push(b'\n');
+                let length = line.len() as u64;
+                (Encoded::Line(line), length)
+            }
+            #[cfg(feature = "parquet")]
+            RecordFormat::Parquet { .. } => (Encoded::Row(event_row(event)?), 0),
+        };
+
+        let base = self.base_path(symbol, event.timestamp());
+        let max_file_bytes = self.config.max_file_bytes;
+        let mut files = self.files.lock().unwrap();
+        let file = match files.get_mut(&base) {
+            Some(file) => file,
+            None => {
+                let file = open_part(&base, 0, self.config.format)?;
+                files.entry(base.clone()).or_insert(file)
+            }
+        };
+
+        if max_file_bytes.is_some_and(|max| file.bytes > 0 && file.bytes + length > max) {
+            file.output.finish()?;
+            *file = open_part(&base, file.part + 1, self.config.format)?;
+        }
+        file.output.write(encoded)?;
+        file.bytes = match &file.output {
+            Output::Jsonl(_) => file.bytes + length,
+            #[cfg(feature = "parquet")]
+            Output::Parquet(writer) => writer.bytes(),
+        };
+        Ok(())
+    }
+
+    /// Writes buffered events. Parquet files are not readable until `close`.
+    pub fn flush(&self) -> io::Result<()> {
+        for file in self.files.lock().unwrap().values_mut() {
+            file.output.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Flushes and syncs every open file and closes it. Later events reopen their JSONL file and append to it,
+    /// or start the next Parquet file.
+    pub fn close(&self) -> io::Result<()> {
+        let mut files = self.files.lock().unwrap();
+        for file in files.values_mut() {
+            file.output.finish()?;
+        }
+        files.clear();
+        Ok(())
+    }
+
+    /// Records every event of `stream` as it passes through, leaving the events themselves untouched.
+    /// Write failures are logged and do not interrupt the stream.
+    pub fn tap<S>(self, stream: S) -> Recording<S>
+    where
+        S: Stream<Item = EventType> + Unpin,
+    {
+        Recording {
+            stream,
+            recorder: self,
+        }
+    }
+
+    /// Whether the sampling policy keeps `event`, counting it.
+    fn sampled(&self, event: &EventType, symbol: &str) -> bool {
+        let sample = self.config.sampling.sample(event);
+        if !matches!(sample, Sample::OneIn(_)) {
+            return sample.keeps(0);
+        }
+        let channel = mem::discriminant(event);
+        let mut state = self.sampling.lock().unwrap();
+        let count = state.seen.entry((channel, symbol.to_string())).or_default();
+        let keeps = sample.keeps(*count);
+        *count += 1;
+        keeps
+    }
+
+    /// Writes the sampling policy next to the data, once per recorder.
+    fn write_sampling(&self) -> io::Result<()> {
+        let mut state = self.sampling.lock().unwrap();
+        if !state.written {
+            fs::create_dir_all(&self.config.directory)?;
+            let policy = serde_json::to_vec_pretty(&self.config.sampling)?;
+            fs::write(self.config.directory.join(SAMPLING_FILE), policy)?;
+            state.written = true;
+        }
+        Ok(())
+    }
+
+    /// File path without the rotation suffix and extension.
+    fn base_path(&self, symbol: &str, timestamp: Option<&str>) -> PathBuf {
+        let date = || {
+            let nanos = timestamp
+                .and_then(time::parse_rfc3339)
+                .unwrap_or_else(time::now_nanos);
+            let (year, month, day) = time::civil_from_days(nanos.div_euclid(NANOS_PER_DAY));
+            format!("{:04}-{:02}-{:02}", year, month, day)
+        };
+        // Crypto pairs contain a slash, e.g. "BTC/USD".
+        let symbol = symbol.replace('/', "-");
+
+        let directory = &self.config.directory;
+        match self.config.partition {
+            Partition::None => directory.join("events"),
+            Partition::Symbol => directory.join(symbol),
+            Partition::Date => directory.join(date()).join("events"),
+            Partition::DateAndSymbol => directory.join(date()).join(symbol),
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            tracing::error!(error = %e, "Failed to flush recorder");
+        }
+    }
+}
+
+#[async_trait]
+impl Sink for Recorder {
+    async fn flush_and_close(&self) -> Result<(), Box<dyn Error>> {
+        Ok(self.close()?)
+    }
+}
+
+/// Appends to the `part`-th JSONL file for `base`, or creates the first Parquet file from `part` on that does
+/// not exist yet, creating directories as needed.
+fn open_part(base: &Path, part: u32, format: RecordFormat) -> io::Result<OpenFile> {
+    let path = |part: u32, extension: &str| {
+        let mut name = base.file_name().unwrap_or_default().to_os_string();
+        if part > 0 {
+            name.push(format!(".{}", part));
+        }
+        name.push(extension);
+        base.with_file_name(name)
+    };
+    if let Some(parent) = base.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    match format {
+        RecordFormat::Jsonl => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path(part, ".jsonl"))?;
+            let bytes = file.metadata()?.len();
+            Ok(OpenFile {
+                output: Output::Jsonl(BufWriter::new(file)),
+                bytes,
+                part,
+            })
+        }
+        #[cfg(feature = "parquet")]
+        RecordFormat::Parquet { rows_per_group } => {
+            let mut part = part;
+            while path(part, ".parquet").try_exists()? {
+                part += 1;
+            }
+            let writer =
+                ParquetWriter::create(path(part, ".parquet"), event_columns(), rows_per_group)?;
+            Ok(OpenFile {
+                output: Output::Parquet(writer),
+                bytes: 0,
+                part,
+            })
+        }
+    }
+}
+
+/// Columns of Parquet recordings, after the fields of every `EventType` variant that has a symbol.
+#[cfg(feature = "parquet")]
+const EVENT_COLUMNS: [(&str, ColumnType); 18] = [
+    ("type", ColumnType::String),
+    ("symbol", ColumnType::String),
+    ("timestamp", ColumnType::String),
+    ("price", ColumnType::Double),
+    ("volume", ColumnType::Double),
+    ("bid_price", ColumnType::Double),
+    ("ask_price", ColumnType::Double),
+    ("bid_size", ColumnType::Double),
+    ("ask_size", ColumnType::Double),
+    ("open", ColumnType::Double),
+    ("high", ColumnType::Double),
+    ("low", ColumnType::Double),
+    ("close", ColumnType::Double),
+    ("limit_up", ColumnType::Double),
+    ("limit_down", ColumnType::Double),
+    ("bids", ColumnType::String),
+    ("asks", ColumnType::String),
+    ("reset", ColumnType::Boolean),
+];
+
+#[cfg(feature = "parquet")]
+fn event_columns() -> Vec<Column> {
+    EVENT_COLUMNS
+        .iter()
+        .map(|(name, column_type)| match *name {
+            "type" => Column::required(name, *column_type),
+            _ => Column::optional(name, *column_type),
+        })
+        .collect()
+}
+
+/// The event's fields by column, null where the variant has no such field. Lists are JSON text.
+#[cfg(feature = "parquet")]
+fn event_row(event: &EventType) -> io::Result<Vec<Value>> {
+    let serde_json::Value::Object(fields) = serde_json::to_value(event)? else {
+        return Err(io::Error::other("Event did not serialize to an object"));
+    };
+    Ok(EVENT_COLUMNS
+        .iter()
+        .map(|(name, _)| match fields.get(*name) {
+            None | Some(serde_json::Value::Null) => Value::Null,
+            Some(serde_json::Value::Bool(value)) => Value::Boolean(*value),
+            Some(serde_json::Value::String(text)) => Value::String(text.clone()),
+            Some(serde_json::Value::Number(number)) => {
+                number.as_f64().map_or(Value::Null, Value::Double)
+            }
+            Some(list) => Value::String(list.to_string()),
+        })
+        .collect())
+}
+
+/// Event of a row of a Parquet recording, the reverse of `event_row`.
+#[cfg(feature = "parquet")]
+pub(crate) fn row_event(columns: &[Column], row: Vec<Value>) -> serde_json::Result<EventType> {
+    let mut fields = serde_json::Map::new();
+    for (column, value) in columns.iter().zip(row) {
+        let value = match value {
+            Value::Null => continue,
+            Value::Boolean(value) => serde_json::Value::Bool(value),
+            Value::Double(value) => serde_json::Value::from(value),
+            Value::String(text) if matches!(column.name.as_str(), "bids" | "asks") => {
+                serde_json::from_str(&text)?
+            }
+            Value::String(text) => serde_json::Value::String(text),
+        };
+        fields.insert(column.name.clone(), value);
+    }
+    serde_json::from_value(serde_json::Value::Object(fields))
+}
+
+/// Stream returned by `Recorder::tap`.
+pub struct Recording<S> {
+    stream: S,
+    recorder: Recorder,
+}
+
+impl<S> Recording<S> {
+    pub fn recorder(&mut self) -> &mut Recorder {
+        &mut self.recorder
+    }
+}
+
+impl<S> Stream for Recording<S>
+where
+    S: Stream<Item = EventType> + Unpin,
+{
+    type Item = EventType;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<EventType>> {
+        let this = &mut *self;
+        let event = Pin::new(&mut this.stream).poll_next(cx);
+        if let Poll::Ready(Some(event)) = &event {
+            if let Err(e) = this.recorder.record(event) {
+                tracing::error!(error = %e, "Failed to record event");
+            }
+        }
+        event
+    }
+}
+
+#[cfg(all(test, feature = "parquet"))]
+mod tests {
+    use super::*;
+    use crate::parquet::ParquetReader;
+
+    fn directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("recorder-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        directory
+    }
+
+    fn trade(price: f64) -> EventType {
+        EventType::Trade {
+            symbol: "AAPL".to_string(),
+            price,
+            volume: 0.5,
+            timestamp: "2024-05-01T14:30:00Z".to_string(),
+        }
+    }
+
+    #[test]
+    fn records_events_as_parquet_rows() {
+        let directory = directory("parquet");
+        let mut config = RecorderConfig::new(&directory);
+        config.partition = Partition::None;
+        config.format = RecordFormat::Parquet { rows_per_group: 2 };
+        let recorder = Recorder::new(config);
+        recorder.record(&trade(190.25)).unwrap();
+        recorder
+            .record(&EventType::OrderBook {
+                symbol: "AAPL".to_string(),
+                bids: vec![(190.0, 1.5)],
+                asks: vec![],
+                reset: true,
+                timestamp: "2024-05-01T14:30:01Z".to_string(),
+            })
+            .unwrap();
+        recorder
+            .record(&EventType::Luld {
+                symbol: "AAPL".to_string(),
+                limit_up: 199.5,
+                limit_down: 180.5,
+                timestamp: "2024-05-01T14:30:02Z".to_string(),
+            })
+            .unwrap();
+        recorder.close().unwrap();
+
+        let mut reader = ParquetReader::open(directory.join("events.parquet")).unwrap();
+        let names: Vec<String> = reader.columns().iter().map(|c| c.name.clone()).collect();
+        let column = |name: &str| names.iter().position(|n| *n == name).unwrap();
+        assert_eq!(reader.row_groups(), 2);
+        let mut rows = reader.read_row_group(0).unwrap();
+        rows.extend(reader.read_row_group(1).unwrap());
+        assert_eq!(rows.len(), 3);
+
+        assert_eq!(rows[0][column("type")], Value::String("Trade".to_string()));
+        assert_eq!(rows[0][column("price")], Value::Double(190.25));
+        assert_eq!(rows[0][column("volume")], Value::Double(0.5));
+        assert_eq!(rows[0][column("bid_price")], Value::Null);
+        assert_eq!(
+            rows[1][column("bids")],
+            Value::String("[[190.0,1.5]]".to_string())
+        );
+        assert_eq!(rows[1][column("reset")], Value::Boolean(true));
+        assert_eq!(rows[2][column("limit_down")], Value::Double(180.5));
+        assert_eq!(rows[2][column("price")], Value::Null);
+        let _ = fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn starts_a_new_parquet_file_after_close_and_on_rotation() {
+        let directory = directory("parquet-parts");
+        let mut config = RecorderConfig::new(&directory);
+        config.partition = Partition::Symbol;
+        config.format = RecordFormat::Parquet { rows_per_group: 1 };
+        config.max_file_bytes = Some(1);
+        let recorder = Recorder::new(config);
+        recorder.record(&trade(1.0)).unwrap();
+        recorder.record(&trade(2.0)).unwrap();
+        recorder.close().unwrap();
+        recorder.record(&trade(3.0)).unwrap();
+        drop(recorder);
+
+        for name in ["AAPL.parquet", "AAPL.1.parquet", "AAPL.2.parquet"] {
+            let mut reader = ParquetReader::open(directory.join(name)).unwrap();
+            assert_eq!(reader.read_row_group(0).unwrap().len(), 1);
+        }
+        let _ = fs::remove_dir_all(&directory);
+    }
+}
@@ -0,0 +1,361 @@
+use crate::{
+    datastructures::event::EventType,
+    stream::{EventSender, EventStream},
+    time,
+};
+#[cfg(feature = "parquet")]
+use crate::{parquet::ParquetReader, recorder::row_event};
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, VecDeque},
+    io,
+    path::PathBuf,
+    time::Duration,
+};
+use tokio::{
+    fs::File,
+    io::{AsyncBufReadExt, BufReader, Lines},
+    time::Instant,
+};
+use tokio_util::sync::CancellationToken;
+
+const NANOS_PER_DAY: i64 = 86_400 * 1_000_000_000;
+
+/// How quickly recorded events are played back.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplaySpeed {
+    /// No waiting between events.
+    AsFastAsPossible,
+    /// Original spacing between events.
+    RealTime,
+    /// Original spacing divided by the factor, measured from the first event.
+    Accelerated(f64),
+    /// Maps a session window onto `playback` of real time, preserving where each event fell within the session.
+    /// E.g. `session_start` 13:30 UTC, `session_length` 6.5h and `playback` 1h replays a full US trading day in
+    /// an hour, so an event at 16:45 UTC plays 30 minutes in regardless of when the first event happened.
+    WallClock {
+        /// Session open as an offset from midnight UTC of the first event's day.
+        session_start: Duration,
+        session_length: Duration,
+        playback: Duration,
+    },
+}
+
+/// Decides when each replayed event should be released.
+pub struct ReplayClock {
+    speed: ReplaySpeed,
+    started: Instant,
+    /// Recorded timestamp (nanos since epoch) that corresponds to `started`.
+    origin: Option<i64>,
+}
+
+impl ReplayClock {
+    pub fn new(speed: ReplaySpeed) -> Self {
+        ReplayClock {
+            speed,
+            started: Instant::now(),
+            origin: None,
+        }
+    }
+
+    /// Real time at which an event recorded at `timestamp` (nanos since epoch) should be released.
+    /// `None` means immediately.
+    pub fn deadline(&mut self, timestamp: i64) -> Option<Instant> {
+        let scale = match self.speed {
+            ReplaySpeed::AsFastAsPossible => return None,
+            ReplaySpeed::RealTime => 1.0,
+            ReplaySpeed::Accelerated(factor) if factor > 0.0 => 1.0 / factor,
+            ReplaySpeed::Accelerated(_) => return None,
+            ReplaySpeed::WallClock {
+                session_length,
+                playback,
+                ..
+            } => playback.as_secs_f64() / session_length.as_secs_f64().max(f64::EPSILON),
+        };
+
+        let origin = *self.origin.get_or_insert_with(|| match self.speed {
+            ReplaySpeed::WallClock { session_start, .. } => {
+                timestamp.div_euclid(NANOS_PER_DAY) * NANOS_PER_DAY
+                    + session_start.as_nanos() as i64
+            }
+            _ => timestamp,
+        });
+
+        let offset = (timestamp - origin).max(0) as f64 * scale;
+        Some(self.started + Duration::from_nanos(offset as u64))
+    }
+
+    /// Sleeps until the event recorded at `timestamp` is due.
+    pub async fn wait_until(&mut self, timestamp: i64) {
+        if let Some(deadline) = self.deadline(timestamp) {
+            tokio::time::sleep_until(deadline).await;
+        }
+    }
+}
+
+enum Source {
+    File(Lines<BufReader<File>>),
+    Events(VecDeque<EventType>),
+    /// Read a row group at a time. The reader is lent to a blocking task while a row group is read.
+    #[cfg(feature = "parquet")]
+    Parquet {
+        reader: Option<ParquetReader>,
+        next_group: usize,
+        events: VecDeque<EventType>,
+    },
+}
+
+impl Source {
+    /// Next event, skipping lines and rows that do not parse.
+    async fn next(&mut self) -> Option<EventType> {
+        match self {
+            Source::Events(events) => events.pop_front(),
+            #[cfg(feature = "parquet")]
+            Source::Parquet {
+                reader,
+                next_group,
+                events,
+            } => loop {
+                if let Some(event) = events.pop_front() {
+                    return Some(event);
+                }
+                let mut lent = reader.take()?;
+                if *next_group >= lent.row_groups() {
+                    return None;
+                }
+                let group = *next_group;
+                *next_group += 1;
+                let (lent, rows) = tokio::task::spawn_blocking(move || {
+                    let rows = lent.read_row_group(group);
+                    (lent, rows)
+                })
+                .await
+                .ok()?;
+                let rows = match rows {
+                    Ok(rows) => rows,
+                    Err(e) => {
+                        tracing::warn!(error = %e, "Failed to read replay file");
+                        return None;
+                    }
+                };
+                for row in rows {
+                    match row_event(lent.columns(), row) {
+                        Ok(event) => events.push_back(event),
+                        Err(e) => tracing::warn!(error = %e, "Skipping unreadable replay row"),
+                    }
+                }
+                *reader = Some(lent);
+            },
+            Source::File(lines) => loop {
+                let line = match lines.next_line().await {
+                    Ok(line) => line?,
+                    Err(e) => {
+                        tracing::warn!(error = %e, "Failed to read replay file");
+                        return None;
+                    }
+                };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str(&line) {
+                    Ok(event) => return Some(event),
+                    Err(e) => tracing::warn!(error = %e, "Skipping unreadable replay line"),
+                }
+            },
+        }
+    }
+}
+
+/// Plays back recorded events as an `EventStream`, so strategies can be tested against the same interface
+/// they use live. Files are read as written by `Recorder`, as JSONL or Parquet; each source must be in timestamp
+/// order, and sources are merged by timestamp. Events without a parseable timestamp are released immediately.
+pub struct ReplayFeed {
+    files: Vec<PathBuf>,
+    events: Vec<Vec<EventType>>,
+    speed: ReplaySpeed,
+    capacity: usize,
+    cancellation: CancellationToken,
+}
+
+impl ReplayFeed {
+    pub fn new(speed: ReplaySpeed) -> Self {
+        ReplayFeed {
+            files: Vec::new(),
+            events: Vec::new(),
+            speed,
+            capacity: 10_000,
+            cancellation: CancellationToken::new(),
+        }
+    }
+
+    /// JSONL file of serialized `EventType`s or, with the `parquet` feature, a `.parquet` file recorded with
+    /// `RecordFormat::Parquet`.
+    pub fn file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.files.push(path.into());
+        self
+    }
+
+    /// Events already in memory, such as historical bars fetched over REST.
+    pub fn events(mut self, events: Vec<EventType>) -> Self {
+        self.events.push(events);
+        self
+    }
+
+    /// Events buffered ahead of the consumer. Defaults to 10,000.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Ends the replay early when cancelled.
+    pub fn cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// Opens every file and starts playback. The stream ends once all sources are exhausted.
+    pub async fn start(self) -> io::Result<EventStream> {
+        let mut sources = Vec::new();
+        for path in &self.files {
+            #[cfg(feature = "parquet")]
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "parquet")
+            {
+                sources.push(Source::Parquet {
+                    reader: Some(ParquetReader::open(path)?),
+                    next_group: 0,
+                    events: VecDeque::new(),
+                });
+                continue;
+            }
+            sources.push(Source::File(
+                BufReader::new(File::open(path).await?).lines(),
+            ));
+        }
+        sources.extend(
+            self.events
+                .into_iter()
+                .map(|events| Source::Events(events.into())),
+        );
+
+        let speed = self.speed;
+        let cancellation = self.cancellation;
+        Ok(EventStream::from_task(
+            self.capacity,
+            move |sender| async move {
+                tokio::select! {
+                    _ = play(sources, speed, sender) => {}
+                    _ = cancellation.cancelled() => {}
+                }
+            },
+        ))
+    }
+}
+
+async fn play(mut sources: Vec<Source>, speed: ReplaySpeed, sender: EventSender) {
+    let mut clock = ReplayClock::new(speed);
+    // Head event of each source, ordered by timestamp, then by source index to keep ties stable.
+    let mut heads = Vec::with_capacity(sources.len());
+    let mut order = BinaryHeap::new();
+    for (index, source) in sources.iter_mut().enumerate() {
+        let head = source.next().await;
+        if let Some(event) = &head {
+            order.push(Reverse((timestamp(event), index)));
+        }
+        heads.push(head);
+    }
+
+    while let Some(Reverse((timestamp, index))) = order.pop() {
+        let Some(event) = heads[index].take() else {
+            continue;
+        };
+        if let Some(timestamp) = timestamp {
+            clock.wait_until(timestamp).await;
+        }
+        if sender.send(event).await.is_err() {
+            return;
+        }
+
+        heads[index] = sources[index].next().await;
+        if let Some(event) = &heads[index] {
+            order.push(Reverse((self::timestamp(event), index)));
+        }
+    }
+}
+
+fn timestamp(event: &EventType) -> Option<i64> {
+    event.timestamp().and_then(time::parse_rfc3339)
+}
+
+#[cfg(all(test, feature = "parquet"))]
+mod tests {
+    use super::*;
+    use crate::recorder::{Partition, RecordFormat, Recorder, RecorderConfig};
+    use futures_util::StreamExt;
+
+    #[tokio::test]
+    async fn replays_a_parquet_recording() {
+        let directory = std::env::temp_dir().join(format!("replay-parquet-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        let mut config = RecorderConfig::new(&directory);
+        config.partition = Partition::None;
+        config.format = RecordFormat::Parquet { rows_per_group: 2 };
+        let recorder = Recorder::new(config);
+        recorder
+            .record(&EventType::Quote {
+                symbol: "AAPL".to_string(),
+                bid_price: 189.9,
+                ask_price: 190.1,
+                bid_size: 3.0,
+                ask_size: 0.5,
+                timestamp: "2024-05-01T14:30:00Z".to_string(),
+            })
+            .unwrap();
+        recorder
+            .record(&EventType::OrderBook {
+                symbol: "BTC/USD".to_string(),
+                bids: vec![(60000.0, 0.25)],
+                asks: vec![(60001.0, 1.5), (60002.0, 2.0)],
+                reset: true,
+                timestamp: "2024-05-01T14:30:01Z".to_string(),
+            })
+            .unwrap();
+        recorder
+            .record(&EventType::Trade {
+                symbol: "AAPL".to_string(),
+                price: 190.0,
+                volume: 0.125,
+                timestamp: "2024-05-01T14:30:02Z".to_string(),
+            })
+            .unwrap();
+        recorder.close().unwrap();
+
+        let events: Vec<EventType> = ReplayFeed::new(ReplaySpeed::AsFastAsPossible)
+            .file(directory.join("events.parquet"))
+            .start()
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        match &events[..] {
+            [EventType::Quote {
+                bid_size, ask_size, ..
+            }, EventType::OrderBook {
+                symbol,
+                bids,
+                asks,
+                reset: true,
+                ..
+            }, EventType::Trade { volume, .. }] => {
+                assert_eq!((*bid_size, *ask_size), (3.0, 0.5));
+                assert_eq!(symbol, "BTC/USD");
+                assert_eq!(bids, &[(60000.0, 0.25)]);
+                assert_eq!(asks.len(), 2);
+                assert_eq!(*volume, 0.125);
+            }
+            events => panic!("unexpected events: {:?}", events),
+        }
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+}
@@ -0,0 +1,201 @@
+use crate::datastructures::{account::Position, client::TradingClient};
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt,
+    time::Duration,
+};
+use tokio::{sync::watch, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
+
+const UNCLASSIFIED: &str = "Unclassified";
+
+/// A position's share of account equity.
+#[derive(Debug, Clone)]
+pub struct PositionWeight {
+    pub symbol: String,
+    pub market_value: f64,
+    /// Signed fraction of equity; negative for shorts.
+    pub weight: f64,
+}
+
+/// Snapshot of how concentrated the portfolio is. All exposures are fractions of equity.
+#[derive(Debug, Clone)]
+pub struct ConcentrationReport {
+    pub equity: f64,
+    /// Largest positions by absolute market value.
+    pub top_positions: Vec<PositionWeight>,
+    /// Gross exposure per sector. Symbols without a sector are grouped under "Unclassified".
+    pub sector_weights: BTreeMap<String, f64>,
+    /// Gross exposure per listing exchange.
+    pub venue_weights: BTreeMap<String, f64>,
+    pub long_exposure: f64,
+    pub short_exposure: f64,
+    pub gross_exposure: f64,
+    pub net_exposure: f64,
+}
+
+impl ConcentrationReport {
+    /// `sectors` maps symbols to sector names. Alpaca does not provide sector data, so it has to come from the caller.
+    pub fn from_positions(
+        equity: f64,
+        positions: &[Position],
+        sectors: &HashMap<String, String>,
+        top_n: usize,
+    ) -> ConcentrationReport {
+        let fraction = |value: f64| if equity != 0.0 { value / equity } else { 0.0 };
+
+        let mut top_positions: Vec<PositionWeight> = positions
+            .iter()
+            .map(|position| PositionWeight {
+                symbol: position.symbol.clone(),
+                market_value: position.market_value,
+                weight: fraction(position.market_value),
+            })
+            .collect();
+        top_positions.sort_by(|a, b| b.market_value.abs().total_cmp(&a.market_value.abs()));
+        top_positions.truncate(top_n);
+
+        let mut sector_weights = BTreeMap::new();
+        let mut venue_weights = BTreeMap::new();
+        let mut long_value = 0.0;
+        let mut short_value = 0.0;
+        for position in positions {
+            let gross = fraction(position.market_value.abs());
+            let sector = sectors
+                .get(&position.symbol)
+                .map(String::as_str)
+                .unwrap_or(UNCLASSIFIED);
+            *sector_weights.entry(sector.to_string()).or_insert(0.0) += gross;
+            *venue_weights
+                .entry(position.exchange.clone())
+                .or_insert(0.0) += gross;
+
+            if position.market_value >= 0.0 {
+                long_value += position.market_value;
+            } else {
+                short_value += position.market_value.abs();
+            }
+        }
+
+        ConcentrationReport {
+            equity,
+            top_positions,
+            sector_weights,
+            venue_weights,
+            long_exposure: fraction(long_value),
+            short_exposure: fraction(short_value),
+            gross_exposure: fraction(long_value + short_value),
+            net_exposure: fraction(long_value - short_value),
+        }
+    }
+
+    /// Fetches the account and positions and builds a report from them.
+    pub async fn fetch<C: TradingClient>(
+        client: &C,
+        sectors: &HashMap<String, String>,
+        top_n: usize,
+    ) -> Result<ConcentrationReport, Box<dyn Error>> {
+        let account = client.get_account().await?;
+        let positions = client.get_positions().await?;
+        Ok(ConcentrationReport::from_positions(
+            account.equity,
+            &positions,
+            sectors,
+            top_n,
+        ))
+    }
+}
+
+impl fmt::Display for ConcentrationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Equity: {:.2}", self.equity)?;
+        writeln!(
+            f,
+            "Exposure: long={:.1}% short={:.1}% gross={:.1}% net={:.1}%",
+            self.long_exposure * 100.0,
+            self.short_exposure * 100.0,
+            self.gross_exposure * 100.0,
+            self.net_exposure * 100.0
+        )?;
+        writeln!(f, "Top positions:")?;
+        for position in &self.top_positions {
+            writeln!(
+                f,
+                "  {:<8} {:>12.2} {:>6.1}%",
+                position.symbol,
+                position.market_value,
+                position.weight * 100.0
+            )?;
+        }
+        writeln!(f, "Sectors:")?;
+        for (sector, weight) in &self.sector_weights {
+            writeln!(f, "  {:<20} {:>6.1}%", sector, weight * 100.0)?;
+        }
+        writeln!(f, "Venues:")?;
+        for (venue, weight) in &self.venue_weights {
+            writeln!(f, "  {:<20} {:>6.1}%", venue, weight * 100.0)?;
+        }
+        Ok(())
+    }
+}
+
+/// Regenerates a `ConcentrationReport` on a fixed interval. Stops when dropped.
+pub struct ConcentrationMonitor {
+    latest: watch::Receiver<Option<ConcentrationReport>>,
+    task: JoinHandle<()>,
+}
+
+impl ConcentrationMonitor {
+    pub fn spawn<C>(
+        client: C,
+        sectors: HashMap<String, String>,
+        top_n: usize,
+        interval: Duration,
+        cancel: CancellationToken,
+    ) -> ConcentrationMonitor
+    where
+        C: TradingClient + Send + Sync + 'static,
+    {
+        let (sender, latest) = watch::channel(None);
+        let task = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    _ = cancel.cancelled() => return,
+                }
+                let report = ConcentrationReport::fetch(&client, &sectors, top_n)
+                    .await
+                    .map_err(|e| e.to_string());
+                match report {
+                    Ok(report) => {
+                        if sender.send(Some(report)).is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) => tracing::error!(error = %e, "Concentration report failed"),
+                }
+            }
+        });
+
+        ConcentrationMonitor { latest, task }
+    }
+
+    /// Most recent report, if one has been generated yet.
+    pub fn latest(&self) -> Option<ConcentrationReport> {
+        self.latest.borrow().clone()
+    }
+
+    /// Waits for the next report.
+    pub async fn changed(&mut self) -> Option<ConcentrationReport> {
+        self.latest.changed().await.ok()?;
+        self.latest.borrow().clone()
+    }
+}
+
+impl Drop for ConcentrationMonitor {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -0,0 +1,342 @@
+use crate::{
+    blackout::BlackoutCalendar,
+    datastructures::{
+        account::Position,
+        client::BrokerClient,
+        order::{Order, OrderSide},
+    },
+    time,
+};
+use std::{
+    collections::HashSet,
+    error::Error,
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+/// What happens to an order that would breach a quantity or notional limit. Restricted symbols, the daily loss
+/// limit, halts and blocking blackouts always reject.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RiskAction {
+    #[default]
+    Reject,
+    /// Cut the order down to the largest quantity every limit allows.
+    Resize,
+}
+
+/// Pre-trade limits. `None` disables a limit.
+#[derive(Debug, Clone, Default)]
+pub struct RiskLimits {
+    /// Largest quantity of a single order.
+    pub max_order_quantity: Option<f64>,
+    /// Largest notional value of a single order.
+    pub max_order_notional: Option<f64>,
+    /// Largest absolute quantity held in any one symbol.
+    pub max_position: Option<f64>,
+    /// Largest sum of the absolute market values of all positions.
+    pub max_gross_notional: Option<f64>,
+    /// Largest absolute difference between long and short market value.
+    pub max_net_notional: Option<f64>,
+    /// Largest loss since the previous close, as a positive amount. Once reached only orders that reduce a
+    /// position are accepted.
+    pub max_daily_loss: Option<f64>,
+    /// Symbols that may not be traded at all.
+    pub restricted: HashSet<String>,
+    /// Windows during which new entries are blocked or scaled down.
+    pub blackouts: Option<BlackoutCalendar>,
+    /// While halted only orders that reduce a position are accepted.
+    pub halt: Option<TradingHalt>,
+    pub action: RiskAction,
+}
+
+/// Switch that stops new risk being taken, e.g. by a `DrawdownMonitor` or an operator. Takes effect through
+/// `RiskLimits::halt`.
+#[derive(Debug, Clone, Default)]
+pub struct TradingHalt {
+    reason: Arc<RwLock<Option<String>>>,
+}
+
+impl TradingHalt {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Halts trading. Halting again replaces the reason.
+    pub fn halt(&self, reason: impl Into<String>) {
+        let reason = reason.into();
+        tracing::error!(%reason, "Trading halted");
+        *self.reason.write().unwrap() = Some(reason);
+    }
+
+    pub fn resume(&self) {
+        if self.reason.write().unwrap().take().is_some() {
+            tracing::warn!("Trading resumed");
+        }
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.reason.read().unwrap().is_some()
+    }
+
+    pub fn reason(&self) -> Option<String> {
+        self.reason.read().unwrap().clone()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RiskRule {
+    Restricted,
+    DailyLoss,
+    Halted,
+    Blackout,
+    OrderQuantity,
+    OrderNotional,
+    Position,
+    GrossNotional,
+    NetNotional,
+}
+
+impl fmt::Display for RiskRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RiskRule::Restricted => "restricted symbol",
+            RiskRule::DailyLoss => "max daily loss",
+            RiskRule::Halted => "trading halt",
+            RiskRule::Blackout => "blackout",
+            RiskRule::OrderQuantity => "max order quantity",
+            RiskRule::OrderNotional => "max order notional",
+            RiskRule::Position => "max position",
+            RiskRule::GrossNotional => "max gross notional",
+            RiskRule::NetNotional => "max net notional",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RiskViolation {
+    pub rule: RiskRule,
+    pub symbol: String,
+    pub message: String,
+}
+
+impl fmt::Display for RiskViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Order for {} rejected by {}: {}",
+            self.symbol, self.rule, self.message
+        )
+    }
+}
+
+impl Error for RiskViolation {}
+
+/// State of the account an order is checked against.
+#[derive(Debug, Clone, Default)]
+pub struct RiskInputs {
+    pub positions: Vec<Position>,
+    /// Equity change since the previous close.
+    pub daily_pl: Option<f64>,
+    /// Price used for the order's notional value when it has no limit or stop price.
+    pub price: Option<f64>,
+    /// Nanoseconds since the Unix epoch, for blackout windows.
+    pub now: i64,
+}
+
+/// Checks orders against `RiskLimits` before they are sent. Add a `middleware::RiskManager` to a client to
+/// check every `create_order`, or call `check` directly.
+#[derive(Debug, Clone)]
+pub struct RiskEngine {
+    limits: RiskLimits,
+}
+
+impl RiskEngine {
+    pub fn new(limits: RiskLimits) -> Self {
+        RiskEngine { limits }
+    }
+
+    pub fn limits(&self) -> &RiskLimits {
+        &self.limits
+    }
+
+    /// Reads what the limits need from the client and checks the order. Returns the order to send, which is
+    /// smaller than the one given if it was resized.
+    pub async fn check<C: BrokerClient + ?Sized>(
+        &self,
+        client: &C,
+        order: &Order,
+    ) -> Result<Order, Box<dyn Error>> {
+        let limits = &self.limits;
+        if limits.restricted.contains(&order.symbol) {
+            return Err(
+                violation(RiskRule::Restricted, order, "symbol is restricted".into()).into(),
+            );
+        }
+
+        let positions = client.get_positions().await?;
+        let daily_pl = match limits.max_daily_loss {
+            Some(_) => {
+                let account = client.get_account().await?;
+                Some(account.equity - account.last_equity)
+            }
+            None => None,
+        };
+        let needs_price = limits.max_order_notional.is_some()
+            || limits.max_gross_notional.is_some()
+            || limits.max_net_notional.is_some();
+        let mut price = positions
+            .iter()
+            .find(|position| position.symbol == order.symbol)
+            .and_then(|position| position.current_price);
+        if needs_price && price.is_none() && order.limit_price.or(order.stop_price).is_none() {
+            price = Some(client.get_latest_quote(&order.symbol).await?.mid());
+        }
+
+        let inputs = RiskInputs {
+            positions,
+            daily_pl,
+            price,
+            now: time::now_nanos(),
+        };
+        Ok(self.evaluate(order, &inputs)?)
+    }
+
+    /// Checks the order against the given account state.
+    pub fn evaluate(&self, order: &Order, inputs: &RiskInputs) -> Result<Order, RiskViolation> {
+        let limits = &self.limits;
+        if limits.restricted.contains(&order.symbol) {
+            return Err(violation(
+                RiskRule::Restricted,
+                order,
+                "symbol is restricted".into(),
+            ));
+        }
+
+        let position = inputs
+            .positions
+            .iter()
+            .find(|position| position.symbol == order.symbol)
+            .map(|position| position.qty)
+            .unwrap_or_default();
+        let closing = closing_quantity(order, position);
+
+        if let (Some(max_loss), Some(daily_pl)) = (limits.max_daily_loss, inputs.daily_pl) {
+            if -daily_pl >= max_loss && order.quantity > closing {
+                return Err(violation(
+                    RiskRule::DailyLoss,
+                    order,
+                    format!("down {:.2} today, limit {:.2}", -daily_pl, max_loss),
+                ));
+            }
+        }
+
+        if let Some(reason) = limits.halt.as_ref().and_then(TradingHalt::reason) {
+            if order.quantity > closing {
+                return Err(violation(RiskRule::Halted, order, reason));
+            }
+        }
+
+        let mut order = order.clone();
+        if let Some(blackouts) = &limits.blackouts {
+            order = blackouts
+                .check_at(&order, position, inputs.now)
+                .map_err(|e| violation(RiskRule::Blackout, &order, e.to_string()))?;
+        }
+
+        let price = order
+            .limit_price
+            .or(order.stop_price)
+            .or(inputs.price)
+            .filter(|price| *price > 0.0);
+        let gross: f64 = inputs.positions.iter().map(|p| p.market_value.abs()).sum();
+        let net: f64 = inputs.positions.iter().map(|p| p.market_value).sum();
+        let after_closing = position.abs() - closing;
+
+        // Largest total quantity each limit allows.
+        let mut allowed: Vec<(RiskRule, f64)> = vec![];
+        if let Some(max) = limits.max_order_quantity {
+            allowed.push((RiskRule::OrderQuantity, max));
+        }
+        if let Some(max) = limits.max_position {
+            allowed.push((RiskRule::Position, closing + (max - after_closing).max(0.0)));
+        }
+        let notional_limits = [
+            (RiskRule::OrderNotional, limits.max_order_notional),
+            (RiskRule::GrossNotional, limits.max_gross_notional),
+            (RiskRule::NetNotional, limits.max_net_notional),
+        ];
+        for (rule, max) in notional_limits {
+            let Some(max) = max else {
+                continue;
+            };
+            let Some(price) = price else {
+                return Err(violation(
+                    rule,
+                    &order,
+                    "no price to value the order at".into(),
+                ));
+            };
+            let quantity = match rule {
+                RiskRule::OrderNotional => max / price,
+                RiskRule::GrossNotional => {
+                    closing + ((max - (gross - closing * price)) / price).max(0.0)
+                }
+                _ => {
+                    let room = match order.side {
+                        OrderSide::Buy => max - net,
+                        OrderSide::Sell => max + net,
+                    };
+                    (room / price).max(closing)
+                }
+            };
+            allowed.push((rule, quantity));
+        }
+
+        let Some((rule, limit)) = allowed
+            .into_iter()
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .filter(|(_, limit)| order.quantity > *limit)
+        else {
+            return Ok(order);
+        };
+
+        let mut resized = limit.max(0.0);
+        if order.quantity.fract() == 0.0 {
+            resized = resized.floor();
+        }
+        if limits.action == RiskAction::Reject || resized <= 0.0 {
+            return Err(violation(
+                rule,
+                &order,
+                format!(
+                    "quantity {} is more than the {} allowed",
+                    order.quantity, resized
+                ),
+            ));
+        }
+        tracing::info!(symbol = %order.symbol, %rule, from = order.quantity, to = resized, "Resized order");
+        order.quantity = resized;
+        Ok(order)
+    }
+}
+
+/// Part of the order's quantity that reduces the current signed `position` rather than adding to it.
+pub(crate) fn closing_quantity(order: &Order, position: f64) -> f64 {
+    let reducing = match order.side {
+        OrderSide::Buy => position < 0.0,
+        OrderSide::Sell => position > 0.0,
+    };
+    if reducing {
+        order.quantity.min(position.abs())
+    } else {
+        0.0
+    }
+}
+
+fn violation(rule: RiskRule, order: &Order, message: String) -> RiskViolation {
+    RiskViolation {
+        rule,
+        symbol: order.symbol.clone(),
+        message,
+    }
+}
@@ -0,0 +1,255 @@
+use crate::{
+    datastructures::{
+        account::Position,
+        client::TradingClient,
+        order::{Order, OrderSide},
+    },
+    time,
+};
+use std::{error::Error, fmt};
+
+const NANOS_PER_DAY: i64 = 86_400 * 1_000_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionRight {
+    Call,
+    Put,
+}
+
+/// A listed option identified by its OCC symbol, e.g. "AAPL240119C00150000".
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptionContract {
+    pub underlying: String,
+    /// Expiration date as days since the Unix epoch.
+    pub expiry: i64,
+    pub right: OptionRight,
+    pub strike: f64,
+}
+
+impl OptionContract {
+    /// Parses an OCC symbol. Returns `None` for anything that is not one, such as equity symbols.
+    pub fn parse(symbol: &str) -> Option<OptionContract> {
+        let split = symbol.len().checked_sub(15)?;
+        let (underlying, rest) = (symbol.get(..split)?, symbol.get(split..)?);
+        if underlying.is_empty() || !rest.bytes().all(|b| b.is_ascii_alphanumeric()) {
+            return None;
+        }
+
+        let number = |range: std::ops::Range<usize>| rest.get(range)?.parse::<i64>().ok();
+        let (year, month, day) = (2000 + number(0..2)?, number(2..4)?, number(4..6)?);
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return None;
+        }
+        let right = match &rest[6..7] {
+            "C" => OptionRight::Call,
+            "P" => OptionRight::Put,
+            _ => return None,
+        };
+
+        Some(OptionContract {
+            underlying: underlying.trim_end().to_string(),
+            expiry: time::days_from_civil(year, month, day),
+            right,
+            strike: number(7..15)? as f64 / 1000.0,
+        })
+    }
+
+    pub fn occ_symbol(&self) -> String {
+        let (year, month, day) = time::civil_from_days(self.expiry);
+        let right = match self.right {
+            OptionRight::Call => 'C',
+            OptionRight::Put => 'P',
+        };
+        format!(
+            "{}{:02}{:02}{:02}{}{:08}",
+            self.underlying,
+            year % 100,
+            month,
+            day,
+            right,
+            (self.strike * 1000.0).round() as i64
+        )
+    }
+
+    /// Same contract with a different expiration.
+    pub fn with_expiry(&self, expiry: i64) -> OptionContract {
+        OptionContract {
+            expiry,
+            ..self.clone()
+        }
+    }
+}
+
+impl fmt::Display for OptionContract {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.occ_symbol())
+    }
+}
+
+/// Which expiration a position is rolled into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RollTarget {
+    /// Same weekday, this many weeks after the current expiry.
+    Weeks(u32),
+    /// The next standard monthly expiry (third Friday) after the current one.
+    NextMonthly,
+}
+
+impl RollTarget {
+    pub fn next_expiry(&self, expiry: i64) -> i64 {
+        match *self {
+            RollTarget::Weeks(weeks) => expiry + 7 * weeks.max(1) as i64,
+            RollTarget::NextMonthly => {
+                let (year, month, _) = time::civil_from_days(expiry);
+                let this_month = third_friday(year, month);
+                if this_month > expiry {
+                    this_month
+                } else if month == 12 {
+                    third_friday(year + 1, 1)
+                } else {
+                    third_friday(year, month + 1)
+                }
+            }
+        }
+    }
+}
+
+fn third_friday(year: i64, month: i64) -> i64 {
+    let first = time::days_from_civil(year, month, 1);
+    // Day 0 of the epoch was a Thursday; weekdays count from Sunday.
+    let weekday = (first + 4).rem_euclid(7);
+    first + (5 - weekday).rem_euclid(7) + 14
+}
+
+#[derive(Debug, Clone)]
+pub struct RollConfig {
+    /// Roll positions expiring within this many calendar days, including today.
+    pub roll_within_days: i64,
+    pub target: RollTarget,
+}
+
+impl Default for RollConfig {
+    fn default() -> Self {
+        RollConfig {
+            roll_within_days: 5,
+            target: RollTarget::NextMonthly,
+        }
+    }
+}
+
+/// A position that is due to roll and the two orders that would roll it.
+#[derive(Debug, Clone)]
+pub struct RollPlan {
+    pub from: OptionContract,
+    pub to: OptionContract,
+    /// Contracts held. Negative for short positions.
+    pub quantity: f64,
+    pub days_to_expiry: i64,
+    /// Closes the near-dated contract.
+    pub close: Order,
+    /// Reopens the same exposure in the next expiry.
+    pub open: Order,
+}
+
+impl fmt::Display for RollPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Roll {} {} -> {} ({} days to expiry)",
+            self.quantity, self.from, self.to, self.days_to_expiry
+        )
+    }
+}
+
+/// Outcome of `roll_positions`.
+#[derive(Debug, Clone, Default)]
+pub struct RollReport {
+    pub planned: Vec<RollPlan>,
+    /// Rolls whose orders were both submitted. Always empty for a dry run.
+    pub rolled: Vec<RollPlan>,
+    /// Rolls where an order could not be submitted, with the error message.
+    /// If the close went out but the open did not, the position is left flat.
+    pub failed: Vec<(RollPlan, String)>,
+}
+
+/// Option positions expiring within the configured window, with the orders needed to roll each one.
+/// `today` is days since the Unix epoch.
+pub fn plan_rolls(positions: &[Position], config: &RollConfig, today: i64) -> Vec<RollPlan> {
+    positions
+        .iter()
+        .filter(|position| position.qty != 0.0)
+        .filter_map(|position| {
+            let from = OptionContract::parse(&position.symbol)?;
+            let days_to_expiry = from.expiry - today;
+            if !(0..=config.roll_within_days).contains(&days_to_expiry) {
+                return None;
+            }
+            let to = from.with_expiry(config.target.next_expiry(from.expiry));
+
+            let (close_side, open_side) = if position.qty > 0.0 {
+                (OrderSide::Sell, OrderSide::Buy)
+            } else {
+                (OrderSide::Buy, OrderSide::Sell)
+            };
+            let leg = |symbol: String, side| {
+                Order::builder()
+                    .symbol(symbol)
+                    .quantity(position.qty.abs())
+                    .side(side)
+                    .tag("roll_from", from.occ_symbol())
+                    .build()
+                    .ok()
+            };
+
+            Some(RollPlan {
+                close: leg(from.occ_symbol(), close_side)?,
+                open: leg(to.occ_symbol(), open_side)?,
+                from,
+                to,
+                quantity: position.qty,
+                days_to_expiry,
+            })
+        })
+        .collect()
+}
+
+/// Finds option positions close to expiry and rolls them into the next expiry. With `dry_run` the plans are
+/// only logged and returned. There is no multi-leg order support yet, so each roll is sent as two orders,
+/// closing the near-dated leg first.
+pub async fn roll_positions<C: TradingClient>(
+    client: &C,
+    config: &RollConfig,
+    dry_run: bool,
+) -> Result<RollReport, Box<dyn Error>> {
+    let positions = client.get_positions().await?;
+    let today = time::now_nanos().div_euclid(NANOS_PER_DAY);
+    let mut report = RollReport {
+        planned: plan_rolls(&positions, config, today),
+        ..RollReport::default()
+    };
+
+    for plan in &report.planned {
+        if dry_run {
+            tracing::info!(%plan, "Dry run");
+            continue;
+        }
+
+        let result = match client.create_order(&plan.close).await {
+            Ok(()) => client.create_order(&plan.open).await,
+            Err(e) => Err(e),
+        }
+        .map_err(|e| e.to_string());
+        match result {
+            Ok(()) => {
+                tracing::info!(%plan, "Rolled");
+                report.rolled.push(plan.clone());
+            }
+            Err(e) => {
+                tracing::error!(%plan, error = %e, "Roll failed");
+                report.failed.push((plan.clone(), e));
+            }
+        }
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,381 @@
+use crate::{
+    datastructures::{
+        asset::Asset,
+        client::BrokerClient,
+        market::Quote,
+        order::{Order, OrderSide, OrderType},
+    },
+    fees::{FeeModel, Liquidity},
+};
+use async_trait::async_trait;
+use futures_util::future::join_all;
+use std::{error::Error, fmt, sync::Arc};
+
+/// What the router needs from a venue. Implemented for every `BrokerClient`, so clients of different brokers
+/// can sit behind one router. Errors are strings so venues can be queried concurrently from any task.
+#[async_trait]
+pub trait VenueClient: Send + Sync {
+    async fn latest_quote(&self, symbol: &str) -> Result<Quote, String>;
+    async fn asset(&self, symbol: &str) -> Result<Asset, String>;
+    async fn submit(&self, order: &Order) -> Result<(), String>;
+}
+
+#[async_trait]
+impl<C: BrokerClient + Send + Sync> VenueClient for C {
+    async fn latest_quote(&self, symbol: &str) -> Result<Quote, String> {
+        self.get_latest_quote(symbol)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn asset(&self, symbol: &str) -> Result<Asset, String> {
+        self.get_asset(symbol).await.map_err(|e| e.to_string())
+    }
+
+    async fn submit(&self, order: &Order) -> Result<(), String> {
+        self.create_order(order).await.map_err(|e| e.to_string())
+    }
+}
+
+/// A broker account orders can be routed to.
+#[derive(Clone)]
+pub struct Venue {
+    pub name: String,
+    pub client: Arc<dyn VenueClient>,
+    /// Fees charged at this venue. `None` counts as free.
+    pub fees: Option<FeeModel>,
+}
+
+impl Venue {
+    pub fn new(name: impl Into<String>, client: impl VenueClient + 'static) -> Self {
+        Venue {
+            name: name.into(),
+            client: Arc::new(client),
+            fees: None,
+        }
+    }
+
+    pub fn with_fees(mut self, fees: FeeModel) -> Self {
+        self.fees = Some(fees);
+        self
+    }
+}
+
+/// A venue that can take the order, and the price it is expected to fill at there.
+#[derive(Debug, Clone)]
+pub struct VenueQuote {
+    pub venue: String,
+    pub quote: Quote,
+    /// Price on the side the order trades against, with the venue's fee added for a buy or taken off for a
+    /// sell.
+    pub effective_price: f64,
+}
+
+/// Where an order went.
+#[derive(Debug, Clone)]
+pub struct RouteOutcome {
+    pub venue: String,
+    pub effective_price: f64,
+    /// Venues tried before the one that accepted, with the error each returned.
+    pub rejections: Vec<(String, String)>,
+}
+
+/// No venue accepted the order.
+#[derive(Debug, Clone)]
+pub struct RoutingFailed {
+    pub symbol: String,
+    /// Venues that were tried, with the error each returned.
+    pub rejections: Vec<(String, String)>,
+    /// Venues left out because they had no usable quote or do not trade the symbol.
+    pub unavailable: Vec<(String, String)>,
+}
+
+impl fmt::Display for RoutingFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "No venue accepted the order for {}", self.symbol)?;
+        for (venue, error) in self.rejections.iter().chain(&self.unavailable) {
+            write!(f, "; {}: {}", venue, error)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for RoutingFailed {}
+
+/// Sends each order to the venue with the best price for it after fees, falling back to the next best venue
+/// when one rejects it. Venues are queried concurrently for a quote and for whether they trade the symbol.
+pub struct SmartRouter {
+    venues: Vec<Venue>,
+}
+
+impl SmartRouter {
+    pub fn new(venues: Vec<Venue>) -> Self {
+        SmartRouter { venues }
+    }
+
+    pub fn venues(&self) -> &[Venue] {
+        &self.venues
+    }
+
+    /// Venues able to take `order`, best first, and the ones left out with the reason.
+    pub async fn rank(&self, order: &Order) -> (Vec<VenueQuote>, Vec<(String, String)>) {
+        let checks = join_all(self.venues.iter().map(|venue| async move {
+            let (quote, asset) = futures_util::join!(
+                venue.client.latest_quote(&order.symbol),
+                venue.client.asset(&order.symbol)
+            );
+            (venue, quote, asset)
+        }))
+        .await;
+
+        let mut ranked = vec![];
+        let mut unavailable = vec![];
+        for (venue, quote, asset) in checks {
+            match Self::assess(venue, order, quote, asset) {
+                Ok(venue_quote) => ranked.push(venue_quote),
+                Err(reason) => unavailable.push((venue.name.clone(), reason)),
+            }
+        }
+        ranked.sort_by(|a, b| match order.side {
+            OrderSide::Buy => a.effective_price.total_cmp(&b.effective_price),
+            OrderSide::Sell => b.effective_price.total_cmp(&a.effective_price),
+        });
+        (ranked, unavailable)
+    }
+
+    /// Routes `order` to the best venue, trying the others in order of price if it is rejected.
+    pub async fn route(&self, order: &Order) -> Result<RouteOutcome, Box<dyn Error>> {
+        let (ranked, unavailable) = self.rank(order).await;
+        let mut rejections = vec![];
+        for candidate in ranked {
+            let Some(venue) = self.venues.iter().find(|v| v.name == candidate.venue) else {
+                continue;
+            };
+            match venue.client.submit(order).await {
+                Ok(()) => {
+                    tracing::info!(
+                        symbol = %order.symbol,
+                        venue = %venue.name,
+                        effective_price = candidate.effective_price,
+                        "Order routed"
+                    );
+                    return Ok(RouteOutcome {
+                        venue: candidate.venue,
+                        effective_price: candidate.effective_price,
+                        rejections,
+                    });
+                }
+                Err(e) => {
+                    tracing::warn!(symbol = %order.symbol, venue = %venue.name, error = %e, "Venue rejected order");
+                    rejections.push((candidate.venue, e));
+                }
+            }
+        }
+        Err(RoutingFailed {
+            symbol: order.symbol.clone(),
+            rejections,
+            unavailable,
+        }
+        .into())
+    }
+
+    fn assess(
+        venue: &Venue,
+        order: &Order,
+        quote: Result<Quote, String>,
+        asset: Result<Asset, String>,
+    ) -> Result<VenueQuote, String> {
+        let asset = asset?;
+        if !asset.tradable {
+            return Err("Not tradable".to_string());
+        }
+        if order.quantity.fract() != 0.0 && !asset.fractionable {
+            return Err("Not fractionable".to_string());
+        }
+
+        let quote = quote?;
+        let price = match order.side {
+            OrderSide::Buy => quote.ask_price,
+            OrderSide::Sell => quote.bid_price,
+        };
+        if price <= 0.0 {
+            return Err("No quote on the side the order trades against".to_string());
+        }
+        // A limit order that does not cross the venue's quote rests there and pays the maker fee.
+        let marketable = match (order.order_type, order.limit_price) {
+            (OrderType::Limit, Some(limit)) => match order.side {
+                OrderSide::Buy => limit >= price,
+                OrderSide::Sell => limit <= price,
+            },
+            _ => true,
+        };
+        let liquidity = if marketable {
+            Liquidity::Taker
+        } else {
+            Liquidity::Maker
+        };
+        let fee_bps = venue
+            .fees
+            .as_ref()
+            .map_or(0.0, |fees| fees.tier().bps(liquidity));
+        let price = if marketable {
+            price
+        } else {
+            order.limit_price.unwrap_or(price)
+        };
+        let effective_price = match order.side {
+            OrderSide::Buy => price * (1.0 + fee_bps / 10_000.0),
+            OrderSide::Sell => price * (1.0 - fee_bps / 10_000.0),
+        };
+        Ok(VenueQuote {
+            venue: venue.name.clone(),
+            quote,
+            effective_price,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockTradingClient;
+
+    fn quote(symbol: &str, bid: f64, ask: f64) -> Quote {
+        Quote {
+            symbol: symbol.to_string(),
+            bid_price: bid,
+            ask_price: ask,
+            bid_size: 100.0,
+            ask_size: 100.0,
+            timestamp: String::new(),
+        }
+    }
+
+    fn venue(name: &str, ask: f64) -> (Venue, MockTradingClient) {
+        let client = MockTradingClient::with_cash(1_000_000.0);
+        client.set_quote(quote("AAPL", ask - 0.05, ask));
+        (Venue::new(name, client.clone()), client)
+    }
+
+    fn buy(quantity: f64) -> Order {
+        Order::builder()
+            .symbol("AAPL")
+            .quantity(quantity)
+            .side(OrderSide::Buy)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn routes_to_the_best_price() {
+        let (dear, dear_client) = venue("dear", 101.0);
+        let (cheap, cheap_client) = venue("cheap", 100.0);
+        let router = SmartRouter::new(vec![dear, cheap]);
+
+        let outcome = router.route(&buy(1.0)).await.unwrap();
+        assert_eq!(outcome.venue, "cheap");
+        assert_eq!(outcome.effective_price, 100.0);
+        assert!(outcome.rejections.is_empty());
+        assert_eq!(cheap_client.submitted().len(), 1);
+        assert!(dear_client.submitted().is_empty());
+    }
+
+    #[tokio::test]
+    async fn falls_back_when_the_best_venue_rejects() {
+        let (cheap, cheap_client) = venue("cheap", 100.0);
+        let (dear, dear_client) = venue("dear", 101.0);
+        cheap_client.fail(
+            crate::testing::MockCall::CreateOrder,
+            "insufficient buying power",
+        );
+        let router = SmartRouter::new(vec![cheap, dear]);
+
+        let outcome = router.route(&buy(1.0)).await.unwrap();
+        assert_eq!(outcome.venue, "dear");
+        assert_eq!(outcome.effective_price, 101.0);
+        assert_eq!(outcome.rejections.len(), 1);
+        assert_eq!(outcome.rejections[0].0, "cheap");
+        assert!(outcome.rejections[0]
+            .1
+            .contains("insufficient buying power"));
+        assert_eq!(dear_client.submitted().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn fails_when_every_venue_rejects() {
+        let (first, first_client) = venue("first", 100.0);
+        let (second, second_client) = venue("second", 100.5);
+        first_client.fail(crate::testing::MockCall::CreateOrder, "rejected");
+        second_client.fail(crate::testing::MockCall::CreateOrder, "rejected");
+        let unquoted = Venue::new("unquoted", MockTradingClient::with_cash(0.0));
+        let router = SmartRouter::new(vec![first, second, unquoted]);
+
+        let error = router.route(&buy(1.0)).await.unwrap_err();
+        let failed = error.downcast_ref::<RoutingFailed>().unwrap();
+        assert_eq!(failed.rejections.len(), 2);
+        assert_eq!(failed.unavailable.len(), 1);
+        assert_eq!(failed.unavailable[0].0, "unquoted");
+    }
+
+    /// Alpaca answers a rejected order with an error status; the router must see that as a rejection.
+    #[cfg(feature = "alpaca")]
+    #[tokio::test]
+    async fn falls_back_when_alpaca_rejects() {
+        use crate::{
+            alpaca::{AlpacaClient, AlpacaError},
+            datastructures::{client::TradingClient, config::Config},
+            testing::Cassette,
+        };
+
+        let path =
+            std::env::temp_dir().join(format!("router-alpaca-reject-{}.json", std::process::id()));
+        let cassette = serde_json::json!([
+            {
+                "method": "GET",
+                "url": "https://data.alpaca.markets/v2/stocks/AAPL/quotes/latest",
+                "status": 200,
+                "response": r#"{"symbol":"AAPL","quote":{"t":"2024-01-02T14:30:00Z","bp":99.95,"bs":1,"ap":100.0,"as":1}}"#
+            },
+            {
+                "method": "GET",
+                "url": "https://paper-api.alpaca.markets/v2/assets/AAPL",
+                "status": 200,
+                "response": r#"{"symbol":"AAPL","exchange":"NASDAQ","class":"us_equity","status":"active","tradable":true,"fractionable":true}"#
+            },
+            {
+                "method": "POST",
+                "url": "https://paper-api.alpaca.markets/v2/orders",
+                "status": 403,
+                "response": r#"{"code":40310000,"message":"insufficient buying power"}"#
+            }
+        ]);
+        std::fs::write(&path, cassette.to_string()).unwrap();
+        let config = Config::builder()
+            .alpaca_api_key("key".to_string())
+            .alpaca_secret_key("secret".to_string())
+            .build()
+            .unwrap();
+        let alpaca = AlpacaClient::new(&config)
+            .unwrap()
+            .with_cassette(Cassette::replay(&path).unwrap());
+        let error = alpaca.create_order(&buy(1.0)).await.unwrap_err();
+        let error = error.downcast_ref::<AlpacaError>().unwrap();
+        assert_eq!((error.status, error.code), (403, 40310000));
+
+        let alpaca = AlpacaClient::new(&config)
+            .unwrap()
+            .with_cassette(Cassette::replay(&path).unwrap());
+        let (fallback, fallback_client) = venue("fallback", 100.5);
+        let router = SmartRouter::new(vec![Venue::new("alpaca", alpaca), fallback]);
+        let outcome = router.route(&buy(1.0)).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(outcome.venue, "fallback");
+        assert_eq!(outcome.rejections[0].0, "alpaca");
+        assert_eq!(
+            outcome.rejections[0].1,
+            "Alpaca error 40310000 (403): insufficient buying power"
+        );
+        assert_eq!(fallback_client.submitted().len(), 1);
+    }
+}
@@ -0,0 +1,120 @@
+use super::{SchwabClient, SchwabError};
+use serde::Deserialize;
+use std::{
+    error::Error,
+    time::{Duration, Instant},
+};
+
+/// Docs: https://developer.schwab.com/products/trader-api--individual/details/documentation/Retail%20Trader%20API%20Production
+const AUTHORIZE_URL: &str = "https://api.schwabapi.com/v1/oauth/authorize";
+const TOKEN_URL: &str = "https://api.schwabapi.com/v1/oauth/token";
+/// Access tokens are refreshed this long before they expire, so none expires in flight.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+/// OAuth tokens of the client. Access tokens last 30 minutes and refresh tokens seven days.
+pub(super) struct Tokens {
+    access_token: String,
+    refresh_token: String,
+    expires_at: Instant,
+}
+
+impl Tokens {
+    pub(super) fn new(refresh_token: String) -> Self {
+        Tokens {
+            access_token: String::new(),
+            refresh_token,
+            expires_at: Instant::now(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RawTokens {
+    access_token: String,
+    refresh_token: String,
+    expires_in: u64,
+}
+
+impl From<RawTokens> for Tokens {
+    fn from(raw: RawTokens) -> Self {
+        Tokens {
+            access_token: raw.access_token,
+            refresh_token: raw.refresh_token,
+            expires_at: Instant::now() + Duration::from_secs(raw.expires_in),
+        }
+    }
+}
+
+impl SchwabClient {
+    /// Page where the account holder authorizes the app. Schwab redirects to `redirect_uri`, which must be
+    /// registered with the app, with the code for `authorize` in its "code" parameter.
+    pub fn authorization_url(&self, redirect_uri: &str) -> String {
+        let mut url = url::Url::parse(AUTHORIZE_URL).expect("Valid authorize URL");
+        url.query_pairs_mut()
+            .append_pair("client_id", &self.app_key)
+            .append_pair("redirect_uri", redirect_uri);
+        url.to_string()
+    }
+
+    /// Exchanges the code from an authorization redirect for tokens. Returns the refresh token, to be kept for
+    /// `ConfigBuilder::schwab_refresh_token` so the app need not be authorized again for seven days.
+    pub async fn authorize(
+        &self,
+        code: &str,
+        redirect_uri: &str,
+    ) -> Result<String, Box<dyn Error>> {
+        let raw = self
+            .token(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", redirect_uri),
+            ])
+            .await?;
+        let refresh_token = raw.refresh_token.clone();
+        *self.tokens.lock().await = raw.into();
+        Ok(refresh_token)
+    }
+
+    /// The current refresh token, which Schwab may replace when the access token is refreshed.
+    pub async fn refresh_token(&self) -> String {
+        self.tokens.lock().await.refresh_token.clone()
+    }
+
+    /// A current access token, refreshed first if it is about to expire.
+    pub(super) async fn access_token(&self) -> Result<String, Box<dyn Error>> {
+        let mut tokens = self.tokens.lock().await;
+        if tokens.access_token.is_empty() || tokens.expires_at <= Instant::now() + EXPIRY_MARGIN {
+            if tokens.refresh_token.is_empty() {
+                return Err("Schwab app is not authorized: no refresh token".into());
+            }
+            let refresh_token = tokens.refresh_token.clone();
+            let raw = self
+                .token(&[
+                    ("grant_type", "refresh_token"),
+                    ("refresh_token", &refresh_token),
+                ])
+                .await?;
+            *tokens = raw.into();
+        }
+        Ok(tokens.access_token.clone())
+    }
+
+    /// Docs: https://developer.schwab.com/user-guides/get-started/authenticate-with-oauth
+    async fn token(&self, form: &[(&str, &str)]) -> Result<RawTokens, Box<dyn Error>> {
+        let build = || {
+            Ok(self
+                .http_client
+                .post(TOKEN_URL)
+                .basic_auth(&self.app_key, Some(&self.app_secret))
+                .form(form)
+                .build()?)
+        };
+        let response = self.rest.send(&self.http_client, true, build).await?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(SchwabError::new(status.as_u16(), body).into());
+        }
+        Ok(serde_json::from_str(&body)?)
+    }
+}
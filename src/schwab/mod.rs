@@ -0,0 +1,863 @@
+mod auth;
+mod stream;
+
+use crate::{
+    datastructures::{
+        account::{Account, Position},
+        asset::Asset,
+        client::{FeedType, MarketDataClient, SubscriptionParams, TradingClient},
+        config::Config,
+        event::{EventBatch, ParseMode},
+        market::{Bar, BarAdjustment, Quote},
+        order::{
+            CancelOutcome, Order, OrderResponse, OrderSide, OrderStatus, OrderType, TimeInForce,
+        },
+    },
+    http::RestPolicies,
+    journal::OrderJournal,
+    store::{self, OrderStore},
+    time,
+};
+use async_trait::async_trait;
+use futures_util::{SinkExt, StreamExt};
+use reqwest::{header::LOCATION, Client as HttpClient, Method, Response};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{
+    connect_async, tungstenite::protocol::Message, MaybeTlsStream, WebSocketStream,
+};
+
+/// Docs: https://developer.schwab.com/products/trader-api--individual/details/documentation/Retail%20Trader%20API%20Production
+const TRADER_URL: &str = "https://api.schwabapi.com/trader/v1";
+/// Docs: https://developer.schwab.com/products/trader-api--individual/details/documentation/Market%20Data%20Production
+const MARKET_DATA_URL: &str = "https://api.schwabapi.com/marketdata/v1";
+
+/// How long `cancel_order` keeps polling for the order to reach a terminal state.
+const CANCEL_CONFIRM_TIMEOUT: Duration = Duration::from_secs(10);
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+const CANCEL_MAX_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Orders can only be listed for the last 60 days.
+const ORDER_HISTORY: Duration = Duration::from_secs(60 * 86_400);
+const STREAMER_LOGIN_TIMEOUT: Duration = Duration::from_secs(10);
+const NANOS_PER_MILLI: i64 = 1_000_000;
+
+/// Error response of the Schwab API.
+#[derive(Debug, Clone)]
+pub struct SchwabError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl SchwabError {
+    /// Takes the message from the body's "message" or OAuth "error_description", or the whole body.
+    fn new(status: u16, body: String) -> Self {
+        let message = serde_json::from_str::<Value>(&body)
+            .ok()
+            .and_then(|value| {
+                let message = value.get("message").or(value.get("error_description"))?;
+                Some(message.as_str()?.to_string())
+            })
+            .unwrap_or(body);
+        SchwabError { status, message }
+    }
+}
+
+impl fmt::Display for SchwabError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Schwab error ({}): {}", self.status, self.message)
+    }
+}
+
+impl Error for SchwabError {}
+
+/// Returned by `create_order` when real trading is not enabled: Schwab checked the order, but nothing was
+/// placed.
+#[derive(Debug, Clone)]
+pub struct OrderPreviewed {
+    pub symbol: String,
+    /// Schwab's preview of the order, with its estimated costs and any validation messages.
+    pub preview: Value,
+}
+
+impl fmt::Display for OrderPreviewed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Order for {} previewed but not placed, as real trading is not enabled",
+            self.symbol
+        )
+    }
+}
+
+impl Error for OrderPreviewed {}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountNumber {
+    account_number: String,
+    hash_value: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawInstrument {
+    symbol: String,
+    #[serde(default)]
+    asset_type: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawLeg {
+    instruction: String,
+    instrument: RawInstrument,
+}
+
+#[derive(Deserialize)]
+struct RawExecution {
+    price: f64,
+    quantity: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawActivity {
+    #[serde(default)]
+    execution_legs: Vec<RawExecution>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawOrder {
+    order_id: i64,
+    #[serde(default)]
+    entered_time: String,
+    status: String,
+    order_type: String,
+    #[serde(default)]
+    quantity: f64,
+    #[serde(default)]
+    filled_quantity: f64,
+    price: Option<f64>,
+    stop_price: Option<f64>,
+    #[serde(default)]
+    order_leg_collection: Vec<RawLeg>,
+    #[serde(default)]
+    order_activity_collection: Vec<RawActivity>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct RawBalances {
+    cash_balance: f64,
+    cash_available_for_trading: f64,
+    buying_power: Option<f64>,
+    available_funds_non_marginable_trade: Option<f64>,
+    liquidation_value: f64,
+    long_market_value: f64,
+    short_market_value: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawPosition {
+    #[serde(default)]
+    long_quantity: f64,
+    #[serde(default)]
+    short_quantity: f64,
+    #[serde(default)]
+    average_price: f64,
+    #[serde(default)]
+    market_value: f64,
+    #[serde(default)]
+    long_open_profit_loss: f64,
+    #[serde(default)]
+    short_open_profit_loss: f64,
+    instrument: RawInstrument,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawAccount {
+    #[serde(rename = "type")]
+    account_type: String,
+    account_number: String,
+    #[serde(default)]
+    is_closing_only_restricted: bool,
+    #[serde(default)]
+    positions: Vec<RawPosition>,
+    #[serde(default)]
+    current_balances: RawBalances,
+    #[serde(default)]
+    initial_balances: RawBalances,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SecuritiesAccount {
+    securities_account: RawAccount,
+}
+
+fn format_millis(millis: i64) -> String {
+    time::format_rfc3339(millis * NANOS_PER_MILLI)
+}
+
+/// Schwab writes offsets without a colon, e.g. "2024-03-15T14:30:00+0000". Normalized to UTC.
+fn schwab_time(timestamp: &str) -> String {
+    let with_colon = match timestamp.len().checked_sub(2) {
+        Some(split) if timestamp.is_char_boundary(split) => {
+            format!("{}:{}", &timestamp[..split], &timestamp[split..])
+        }
+        _ => timestamp.to_string(),
+    };
+    time::parse_rfc3339(timestamp)
+        .or_else(|| time::parse_rfc3339(&with_colon))
+        .map_or_else(|| timestamp.to_string(), time::format_rfc3339)
+}
+
+/// Time as order queries take it, e.g. "2024-03-15T14:30:00.000Z".
+fn query_time(nanos: i64) -> String {
+    let seconds = nanos.div_euclid(1_000_000_000) * 1_000_000_000;
+    time::format_rfc3339(seconds).replace('Z', ".000Z")
+}
+
+fn order_status(status: &str, filled_qty: f64) -> OrderStatus {
+    match status {
+        "WORKING" | "QUEUED" | "ACCEPTED" if filled_qty > 0.0 => OrderStatus::PartiallyFilled,
+        "WORKING" | "QUEUED" => OrderStatus::New,
+        "ACCEPTED" => OrderStatus::Accepted,
+        "FILLED" => OrderStatus::Filled,
+        "CANCELED" => OrderStatus::Canceled,
+        "PENDING_CANCEL" => OrderStatus::PendingCancel,
+        "PENDING_REPLACE" => OrderStatus::PendingReplace,
+        "REPLACED" => OrderStatus::Replaced,
+        "REJECTED" => OrderStatus::Rejected,
+        "EXPIRED" => OrderStatus::Expired,
+        "AWAITING_MANUAL_REVIEW" => OrderStatus::PendingReview,
+        _ => OrderStatus::PendingNew,
+    }
+}
+
+fn order_type(order_type: &str) -> OrderType {
+    match order_type {
+        "LIMIT" | "LIMIT_ON_CLOSE" => OrderType::Limit,
+        "STOP" => OrderType::Stop,
+        "STOP_LIMIT" => OrderType::StopLimit,
+        "TRAILING_STOP" | "TRAILING_STOP_LIMIT" => OrderType::TrailingStop,
+        _ => OrderType::Market,
+    }
+}
+
+impl RawOrder {
+    fn into_response(self, client_order_id: String) -> OrderResponse {
+        let (symbol, side) = match self.order_leg_collection.into_iter().next() {
+            Some(leg) => (
+                leg.instrument.symbol,
+                match leg.instruction.as_str() {
+                    "SELL" | "SELL_SHORT" | "SELL_TO_OPEN" | "SELL_TO_CLOSE" => OrderSide::Sell,
+                    _ => OrderSide::Buy,
+                },
+            ),
+            None => (String::new(), OrderSide::Buy),
+        };
+        let (value, quantity) = self
+            .order_activity_collection
+            .iter()
+            .flat_map(|activity| &activity.execution_legs)
+            .fold((0.0, 0.0), |(value, quantity), execution| {
+                (
+                    value + execution.price * execution.quantity,
+                    quantity + execution.quantity,
+                )
+            });
+        OrderResponse {
+            id: self.order_id.to_string(),
+            client_order_id,
+            symbol,
+            status: order_status(&self.status, self.filled_quantity),
+            created_at: schwab_time(&self.entered_time),
+            side,
+            order_type: order_type(&self.order_type),
+            qty: Some(self.quantity),
+            filled_qty: self.filled_quantity,
+            filled_avg_price: (quantity > 0.0).then(|| value / quantity),
+            limit_price: self.price,
+            stop_price: self.stop_price,
+            metadata: Default::default(),
+        }
+    }
+}
+
+/// Client for the Schwab Trader API, for accounts moved over from TD Ameritrade among others. Trades US
+/// equities in the first linked account, or the one chosen with `with_account`. Authorizes with OAuth: the app
+/// key and secret and a refresh token from the config, or one obtained with `authorization_url` and
+/// `authorize`. Schwab has no paper trading, so unless real trading is enabled orders are only previewed, not
+/// placed, and `create_order` fails with an `OrderPreviewed`. Schwab orders carry no client order id; the ids of orders this client placed are remembered, and
+/// submissions are never retried. Applies the config's REST policies, journal, order store and pre-trade checks
+/// like `AlpacaClient`.
+#[derive(Clone)]
+pub struct SchwabClient {
+    http_client: HttpClient,
+    rest: RestPolicies,
+    app_key: String,
+    app_secret: String,
+    tokens: Arc<tokio::sync::Mutex<auth::Tokens>>,
+    preview_only: bool,
+    account_number: Option<String>,
+    /// Hash of the account, which stands in for its number in URLs. Looked up on first use.
+    account_hash: Arc<Mutex<Option<String>>>,
+    journal: Option<OrderJournal>,
+    order_store: Option<Arc<dyn OrderStore>>,
+    /// Client order ids of orders this client placed, by order id.
+    client_order_ids: Arc<Mutex<HashMap<String, String>>>,
+    level_one: Arc<stream::LevelOne>,
+}
+
+impl SchwabClient {
+    /// Client with the config's Schwab app keys and refresh token.
+    pub fn new(config: &Config) -> Result<Self, reqwest::Error> {
+        Ok(SchwabClient {
+            http_client: config.http.build_client()?,
+            rest: RestPolicies::new(config),
+            app_key: config.schwab_app_key.clone().unwrap_or_default(),
+            app_secret: config.schwab_app_secret.clone().unwrap_or_default(),
+            tokens: Arc::new(tokio::sync::Mutex::new(auth::Tokens::new(
+                config.schwab_refresh_token.clone().unwrap_or_default(),
+            ))),
+            preview_only: !config.enable_real_trading,
+            account_number: None,
+            account_hash: Arc::new(Mutex::new(None)),
+            journal: config.journal.clone(),
+            order_store: config.order_store.clone(),
+            client_order_ids: Arc::new(Mutex::new(HashMap::new())),
+            level_one: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// Trades in the account with this number rather than the first one linked.
+    pub fn with_account(mut self, account_number: impl Into<String>) -> Self {
+        self.account_number = Some(account_number.into());
+        self
+    }
+
+    /// Only `idempotent` calls are retried. Fails with a `SchwabError` unless the response is a success.
+    async fn request(
+        &self,
+        method: Method,
+        url: &str,
+        body: Option<&Value>,
+        idempotent: bool,
+    ) -> Result<Response, Box<dyn Error>> {
+        let access_token = self.access_token().await?;
+        let build = || {
+            let mut request = self
+                .http_client
+                .request(method.clone(), url)
+                .bearer_auth(&access_token);
+            if let Some(body) = body {
+                request = request.json(body);
+            }
+            Ok(request.build()?)
+        };
+        let response = self.rest.send(&self.http_client, idempotent, build).await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await?;
+            return Err(SchwabError::new(status.as_u16(), body).into());
+        }
+        Ok(response)
+    }
+
+    async fn get<T: DeserializeOwned>(&self, url: &str) -> Result<T, Box<dyn Error>> {
+        let response = self.request(Method::GET, url, None, true).await?;
+        let body = response.text().await?;
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    /// Docs: https://developer.schwab.com/products/trader-api--individual/details/specifications/Retail%20Trader%20API%20Production
+    async fn account_url(&self) -> Result<String, Box<dyn Error>> {
+        let known = self.account_hash.lock().unwrap().clone();
+        let hash = match known {
+            Some(hash) => hash,
+            None => {
+                let accounts: Vec<AccountNumber> = self
+                    .get(&format!("{}/accounts/accountNumbers", TRADER_URL))
+                    .await?;
+                let account = accounts
+                    .into_iter()
+                    .find(|account| {
+                        self.account_number
+                            .as_ref()
+                            .is_none_or(|number| *number == account.account_number)
+                    })
+                    .ok_or("No such Schwab account")?;
+                *self.account_hash.lock().unwrap() = Some(account.hash_value.clone());
+                account.hash_value
+            }
+        };
+        Ok(format!("{}/accounts/{}", TRADER_URL, hash))
+    }
+
+    fn to_response(&self, raw: RawOrder) -> OrderResponse {
+        let client_order_id = self
+            .client_order_ids
+            .lock()
+            .unwrap()
+            .get(&raw.order_id.to_string())
+            .cloned()
+            .unwrap_or_default();
+        let mut order = raw.into_response(client_order_id);
+        if let Some(journal) = &self.journal {
+            journal.annotate(&mut order);
+        }
+        order
+    }
+
+    async fn account(&self) -> Result<RawAccount, Box<dyn Error>> {
+        let url = format!("{}?fields=positions", self.account_url().await?);
+        let account: SecuritiesAccount = self.get(&url).await?;
+        Ok(account.securities_account)
+    }
+
+    /// Orders entered in the last 60 days.
+    async fn recent_orders(&self) -> Result<Vec<OrderResponse>, Box<dyn Error>> {
+        let now = time::now_nanos();
+        let from = now - ORDER_HISTORY.as_nanos() as i64;
+        let mut url = url::Url::parse(&format!("{}/orders", self.account_url().await?))?;
+        url.query_pairs_mut()
+            .append_pair("fromEnteredTime", &query_time(from))
+            .append_pair("toEnteredTime", &query_time(now));
+        let raw: Vec<RawOrder> = self.get(url.as_str()).await?;
+        let mut orders: Vec<OrderResponse> =
+            raw.into_iter().map(|raw| self.to_response(raw)).collect();
+        orders.sort_by_key(|order| time::parse_rfc3339(&order.created_at));
+        Ok(orders)
+    }
+
+    /// Docs: https://developer.schwab.com/products/trader-api--individual/details/specifications/Retail%20Trader%20API%20Production
+    async fn streamer_info(&self) -> Result<stream::StreamerInfo, Box<dyn Error>> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct UserPreference {
+            streamer_info: Vec<stream::StreamerInfo>,
+        }
+        let preference: UserPreference =
+            self.get(&format!("{}/userPreference", TRADER_URL)).await?;
+        Ok(preference
+            .streamer_info
+            .into_iter()
+            .next()
+            .ok_or("Schwab sent no streamer info")?)
+    }
+}
+
+/// Body of an order for Schwab.
+fn order_body(order: &Order) -> Result<Value, &'static str> {
+    let on_close = matches!(order.time_in_force, TimeInForce::Cls);
+    let order_type = match (order.order_type, on_close) {
+        (OrderType::Market, false) => "MARKET",
+        (OrderType::Market, true) => "MARKET_ON_CLOSE",
+        (OrderType::Limit, false) => "LIMIT",
+        (OrderType::Limit, true) => "LIMIT_ON_CLOSE",
+        (OrderType::Stop, false) => "STOP",
+        (OrderType::StopLimit, false) => "STOP_LIMIT",
+        (OrderType::TrailingStop, _) => return Err("Trailing stops are not supported for Schwab"),
+        (_, true) => return Err("Only market and limit orders can be sent on close"),
+    };
+    let duration = match order.time_in_force {
+        TimeInForce::Day | TimeInForce::Cls => "DAY",
+        TimeInForce::Gtc => "GOOD_TILL_CANCEL",
+        TimeInForce::Ioc => "IMMEDIATE_OR_CANCEL",
+        TimeInForce::Fok => "FILL_OR_KILL",
+        TimeInForce::Opg => return Err("Schwab has no orders for the opening auction"),
+    };
+    let mut body = json!({
+        "orderType": order_type,
+        "session": "NORMAL",
+        "duration": duration,
+        "orderStrategyType": "SINGLE",
+        "orderLegCollection": [{
+            "instruction": match order.side {
+                OrderSide::Buy => "BUY",
+                OrderSide::Sell => "SELL",
+            },
+            "quantity": order.quantity,
+            "instrument": { "symbol": order.symbol, "assetType": "EQUITY" },
+        }],
+    });
+    if let Some(limit_price) = order.limit_price {
+        body["price"] = json!(limit_price);
+    }
+    if let Some(stop_price) = order.stop_price {
+        body["stopPrice"] = json!(stop_price);
+    }
+    Ok(body)
+}
+
+#[async_trait]
+impl TradingClient for SchwabClient {
+    /// Docs: https://developer.schwab.com/products/trader-api--individual/details/specifications/Retail%20Trader%20API%20Production
+    /// Equities only, in the regular session. Sells are plain sells, so selling short is up to the account's
+    /// settings. Trailing stops and opening auction orders are not supported.
+    async fn create_order(&self, order: &Order) -> Result<(), Box<dyn Error>> {
+        let body = order_body(order)?;
+        let account_url = self.account_url().await?;
+
+        if self.preview_only {
+            let url = format!("{}/previewOrder", account_url);
+            let response = self.request(Method::POST, &url, Some(&body), false).await?;
+            let preview = response.text().await?;
+            return Err(OrderPreviewed {
+                symbol: order.symbol.clone(),
+                preview: serde_json::from_str(&preview).unwrap_or(Value::String(preview)),
+            }
+            .into());
+        }
+
+        tracing::debug!(?order, "Submitting order");
+        if let Some(journal) = &self.journal {
+            journal.record(order)?;
+        }
+        let url = format!("{}/orders", account_url);
+        let location = {
+            let result = self
+                .request(Method::POST, &url, Some(&body), false)
+                .await
+                .map(|response| response.headers().get(LOCATION).cloned());
+            #[cfg(feature = "metrics")]
+            {
+                let metrics = crate::metrics::registry();
+                metrics.orders_submitted.inc();
+                if result.is_err() {
+                    metrics.orders_rejected.inc();
+                }
+            }
+            result?
+        };
+
+        // The new order's id is the last segment of its location.
+        let id = location
+            .as_ref()
+            .and_then(|location| location.to_str().ok())
+            .and_then(|location| location.rsplit('/').next())
+            .map(str::to_string);
+        let Some(id) = id else {
+            tracing::warn!("Create order response had no order location");
+            return Ok(());
+        };
+        if let Some(client_order_id) = &order.client_order_id {
+            self.client_order_ids
+                .lock()
+                .unwrap()
+                .insert(id.clone(), client_order_id.clone());
+        }
+        if let Some(store) = &self.order_store {
+            let accepted = OrderResponse {
+                id,
+                client_order_id: order.client_order_id.clone().unwrap_or_default(),
+                symbol: order.symbol.clone(),
+                status: OrderStatus::PendingNew,
+                created_at: time::format_rfc3339(time::now_nanos()),
+                side: order.side,
+                order_type: order.order_type,
+                qty: Some(order.quantity),
+                filled_qty: 0.0,
+                filled_avg_price: None,
+                limit_price: order.limit_price,
+                stop_price: order.stop_price,
+                metadata: order.metadata.clone(),
+            };
+            if let Err(e) = store.record_submission(&accepted).await {
+                tracing::error!(error = %e, "Failed to store submitted order");
+            }
+        }
+        Ok(())
+    }
+
+    /// Orders entered in the last 60 days that can still fill.
+    async fn get_open_orders(&self) -> Result<Vec<OrderResponse>, Box<dyn Error>> {
+        let mut orders = self.recent_orders().await?;
+        orders.retain(|order| !order.status.is_terminal());
+        for order in &orders {
+            store::record_logged(&self.order_store, order).await;
+        }
+        Ok(orders)
+    }
+
+    async fn get_order(&self, order_id: &str) -> Result<OrderResponse, Box<dyn Error>> {
+        let url = format!("{}/orders/{}", self.account_url().await?, order_id);
+        let raw: RawOrder = self.get(&url).await?;
+        let order = self.to_response(raw);
+        store::record_logged(&self.order_store, &order).await;
+        Ok(order)
+    }
+
+    /// Only orders this client placed can be found by client order id.
+    async fn get_order_by_client_id(
+        &self,
+        client_order_id: &str,
+    ) -> Result<OrderResponse, Box<dyn Error>> {
+        let order_id = self
+            .client_order_ids
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(_, id)| *id == client_order_id)
+            .map(|(order_id, _)| order_id.clone())
+            .ok_or_else(|| format!("Unknown client order id: {}", client_order_id))?;
+        self.get_order(&order_id).await
+    }
+
+    /// Polls until the order reaches a terminal state or 10 seconds pass.
+    async fn cancel_order(&self, order_id: &str) -> Result<CancelOutcome, Box<dyn Error>> {
+        let url = format!("{}/orders/{}", self.account_url().await?, order_id);
+        if let Err(e) = self.request(Method::DELETE, &url, None, true).await {
+            // 400 means the order can no longer be cancelled, usually because it already filled or was
+            // cancelled by an earlier attempt. Its actual state decides the outcome.
+            if e.downcast_ref::<SchwabError>().map(|e| e.status) != Some(400) {
+                return Err(e);
+            }
+        }
+
+        let deadline = Instant::now() + CANCEL_CONFIRM_TIMEOUT;
+        let mut delay = CANCEL_POLL_INTERVAL;
+        loop {
+            let outcome = CancelOutcome::from_order(self.get_order(order_id).await?);
+            if !matches!(outcome, CancelOutcome::Unconfirmed(_)) || Instant::now() >= deadline {
+                return Ok(outcome);
+            }
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(CANCEL_MAX_POLL_INTERVAL);
+        }
+    }
+
+    /// Docs: https://developer.schwab.com/products/trader-api--individual/details/specifications/Market%20Data%20Production
+    /// Schwab does not say whether an instrument can be shorted or bought in fractions, so neither is assumed.
+    async fn get_asset(&self, symbol: &str) -> Result<Asset, Box<dyn Error>> {
+        #[derive(Deserialize)]
+        struct Instruments {
+            #[serde(default)]
+            instruments: Vec<Value>,
+        }
+        let mut url = url::Url::parse(&format!("{}/instruments", MARKET_DATA_URL))?;
+        url.query_pairs_mut()
+            .append_pair("symbol", symbol)
+            .append_pair("projection", "symbol-search");
+        let found: Instruments = self.get(url.as_str()).await?;
+        let instrument = found
+            .instruments
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("Unknown symbol: {}", symbol))?;
+        let field = |name: &str| {
+            instrument
+                .get(name)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        };
+        Ok(Asset {
+            symbol: field("symbol"),
+            exchange: field("exchange"),
+            asset_class: "us_equity".to_string(),
+            status: "active".to_string(),
+            tradable: true,
+            shortable: false,
+            fractionable: false,
+            attributes: vec![],
+        })
+    }
+
+    /// Schwab cannot list every instrument.
+    async fn list_assets(&self) -> Result<Vec<Asset>, Box<dyn Error>> {
+        Err("Schwab cannot list assets; look them up with get_asset".into())
+    }
+
+    /// `last_equity` is the account's value at the start of the day.
+    async fn get_account(&self) -> Result<Account, Box<dyn Error>> {
+        let account = self.account().await?;
+        let current = &account.current_balances;
+        let cash_account = account.account_type == "CASH";
+        let buying_power = match current.buying_power {
+            Some(buying_power) if !cash_account => buying_power,
+            _ => current.cash_available_for_trading,
+        };
+        Ok(Account {
+            id: account.account_number,
+            status: if account.is_closing_only_restricted {
+                "TRADING_BLOCKED".to_string()
+            } else {
+                "ACTIVE".to_string()
+            },
+            currency: "USD".to_string(),
+            cash: current.cash_balance,
+            equity: current.liquidation_value,
+            last_equity: account.initial_balances.liquidation_value,
+            buying_power,
+            long_market_value: current.long_market_value,
+            short_market_value: current.short_market_value,
+            non_marginable_buying_power: if cash_account {
+                Some(current.cash_available_for_trading)
+            } else {
+                current.available_funds_non_marginable_trade
+            },
+        })
+    }
+
+    async fn get_positions(&self) -> Result<Vec<Position>, Box<dyn Error>> {
+        let account = self.account().await?;
+        Ok(account
+            .positions
+            .into_iter()
+            .map(|raw| {
+                let qty = raw.long_quantity - raw.short_quantity;
+                Position {
+                    symbol: raw.instrument.symbol,
+                    exchange: String::new(),
+                    asset_class: match raw.instrument.asset_type.as_str() {
+                        "OPTION" => "us_option",
+                        _ => "us_equity",
+                    }
+                    .to_string(),
+                    qty,
+                    avg_entry_price: raw.average_price,
+                    market_value: raw.market_value,
+                    current_price: (qty != 0.0).then(|| raw.market_value / qty),
+                    unrealized_pl: raw.long_open_profit_loss + raw.short_open_profit_loss,
+                }
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl MarketDataClient for SchwabClient {
+    /// Docs: https://developer.schwab.com/products/trader-api--individual/details/specifications/Market%20Data%20Production
+    async fn get_latest_quote(&self, symbol: &str) -> Result<Quote, Box<dyn Error>> {
+        let mut url = url::Url::parse(&format!("{}/quotes", MARKET_DATA_URL))?;
+        url.query_pairs_mut()
+            .append_pair("symbols", symbol)
+            .append_pair("fields", "quote");
+        let quotes: HashMap<String, Value> = self.get(url.as_str()).await?;
+        let quote = quotes
+            .get(symbol)
+            .and_then(|quote| quote.get("quote"))
+            .ok_or_else(|| format!("No quote for {}", symbol))?;
+        let number = |name: &str| quote.get(name).and_then(Value::as_f64).unwrap_or_default();
+        Ok(Quote {
+            symbol: symbol.to_string(),
+            bid_price: number("bidPrice"),
+            ask_price: number("askPrice"),
+            bid_size: number("bidSize"),
+            ask_size: number("askSize"),
+            timestamp: format_millis(number("quoteTime") as i64),
+        })
+    }
+
+    /// Docs: https://developer.schwab.com/products/trader-api--individual/details/specifications/Market%20Data%20Production
+    /// Schwab decides how bars are adjusted, so the adjustment is ignored.
+    async fn get_daily_bars(
+        &self,
+        symbol: &str,
+        start: &str,
+        end: &str,
+        _adjustment: BarAdjustment,
+    ) -> Result<Vec<Bar>, Box<dyn Error>> {
+        let millis = |date: &str| {
+            time::parse_rfc3339(&format!("{}T00:00:00Z", date))
+                .map(|nanos| nanos / NANOS_PER_MILLI)
+                .ok_or_else(|| format!("Not a YYYY-MM-DD date: {}", date))
+        };
+        let mut url = url::Url::parse(&format!("{}/pricehistory", MARKET_DATA_URL))?;
+        url.query_pairs_mut()
+            .append_pair("symbol", symbol)
+            .append_pair("periodType", "year")
+            .append_pair("frequencyType", "daily")
+            .append_pair("frequency", "1")
+            .append_pair("startDate", &millis(start)?.to_string())
+            .append_pair("endDate", &(millis(end)? + 86_400_000 - 1).to_string());
+
+        #[derive(Deserialize)]
+        struct Candle {
+            open: f64,
+            high: f64,
+            low: f64,
+            close: f64,
+            volume: f64,
+            datetime: i64,
+        }
+        #[derive(Deserialize)]
+        struct PriceHistory {
+            #[serde(default)]
+            candles: Vec<Candle>,
+        }
+        let history: PriceHistory = self.get(url.as_str()).await?;
+        Ok(history
+            .candles
+            .into_iter()
+            .map(|candle| Bar {
+                symbol: symbol.to_string(),
+                open: candle.open,
+                high: candle.high,
+                low: candle.low,
+                close: candle.close,
+                volume: candle.volume,
+                timestamp: format_millis(candle.datetime),
+            })
+            .collect())
+    }
+
+    /// Docs: https://developer.schwab.com/products/trader-api--individual/details/documentation/Market%20Data%20Production
+    /// Logs in to the streamer with the access token, then subscribes. Trades and quotes come from level one
+    /// equity data, bars are closed minute charts and order books are Nasdaq's; Schwab streams no updated or
+    /// daily bars, limit up/limit down bands or news, and needs explicit symbols rather than "*".
+    async fn subscribe(
+        &self,
+        params: SubscriptionParams,
+    ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, Box<dyn Error>> {
+        if !matches!(params.feed_type, FeedType::Stocks) {
+            return Err(format!("Schwab has no {:?} feed", params.feed_type).into());
+        }
+        let info = self.streamer_info().await?;
+        let subscribe = info.subscribe(&params.subscription_request)?;
+        let access_token = self.access_token().await?;
+
+        let (mut socket, response) = connect_async(info.streamer_socket_url.as_str()).await?;
+        if response.status() != 101 {
+            return Err(
+                format!("Connection failed with status code: {}", response.status()).into(),
+            );
+        }
+        socket
+            .send(Message::Text(info.login(&access_token).to_string()))
+            .await?;
+        let login = tokio::time::timeout(STREAMER_LOGIN_TIMEOUT, async {
+            while let Some(message) = socket.next().await {
+                if let Message::Text(text) = message? {
+                    if let Some(result) = stream::login_result(&text) {
+                        return Ok(result);
+                    }
+                }
+            }
+            Err(tokio_tungstenite::tungstenite::Error::ConnectionClosed)
+        })
+        .await
+        .map_err(|_| "Timed out logging in to the Schwab streamer")??;
+        login.map_err(|message| format!("Schwab streamer login failed: {}", message))?;
+
+        socket.send(Message::Text(subscribe.to_string())).await?;
+        Ok(socket)
+    }
+
+    fn parse_frame(&self, frame: &str, _mode: ParseMode) -> Result<EventBatch, serde_json::Error> {
+        stream::parse_frame(frame, &self.level_one)
+    }
+}
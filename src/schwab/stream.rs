@@ -0,0 +1,217 @@
+use super::format_millis;
+use crate::{
+    datastructures::{
+        client::SubscriptionRequest,
+        event::{EventBatch, EventType},
+    },
+    time,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{collections::HashMap, sync::Mutex};
+
+/// Level one fields: symbol, bid, ask, last, bid size, ask size, last size, quote time and trade time.
+const LEVEL_ONE_FIELDS: &str = "0,1,2,3,4,5,9,34,35";
+/// Chart fields: symbol, open, high, low, close, volume, sequence and chart time.
+const CHART_FIELDS: &str = "0,1,2,3,4,5,6,7";
+/// Book fields: symbol, book time, bids and asks.
+const BOOK_FIELDS: &str = "0,1,2,3";
+
+/// Where and as whom to connect to the streamer, from the user preferences.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct StreamerInfo {
+    pub(super) streamer_socket_url: String,
+    schwab_client_customer_id: String,
+    schwab_client_correl_id: String,
+    schwab_client_channel: String,
+    schwab_client_function_id: String,
+}
+
+impl StreamerInfo {
+    fn request(&self, id: usize, service: &str, command: &str, parameters: Value) -> Value {
+        json!({
+            "requestid": id.to_string(),
+            "service": service,
+            "command": command,
+            "SchwabClientCustomerId": self.schwab_client_customer_id,
+            "SchwabClientCorrelId": self.schwab_client_correl_id,
+            "parameters": parameters,
+        })
+    }
+
+    /// Docs: https://developer.schwab.com/products/trader-api--individual/details/documentation/Market%20Data%20Production
+    pub(super) fn login(&self, access_token: &str) -> Value {
+        let parameters = json!({
+            "Authorization": access_token,
+            "SchwabClientChannel": self.schwab_client_channel,
+            "SchwabClientFunctionId": self.schwab_client_function_id,
+        });
+        json!({ "requests": [self.request(0, "ADMIN", "LOGIN", parameters)] })
+    }
+
+    /// Subscriptions for the request, in one message.
+    pub(super) fn subscribe(&self, request: &SubscriptionRequest) -> Result<Value, &'static str> {
+        let symbols = |channels: &[&Vec<&'static str>]| -> Result<String, &'static str> {
+            let mut symbols: Vec<&str> = vec![];
+            for symbol in channels.iter().copied().flatten() {
+                if *symbol == "*" {
+                    return Err("Schwab streams need explicit symbols");
+                }
+                if !symbols.contains(symbol) {
+                    symbols.push(symbol);
+                }
+            }
+            Ok(symbols.join(","))
+        };
+        let services = [
+            (
+                "LEVELONE_EQUITIES",
+                symbols(&[&request.trades, &request.quotes])?,
+                LEVEL_ONE_FIELDS,
+            ),
+            ("CHART_EQUITY", symbols(&[&request.bars])?, CHART_FIELDS),
+            ("NASDAQ_BOOK", symbols(&[&request.orderbooks])?, BOOK_FIELDS),
+        ];
+        if !request.updated_bars.is_empty() || !request.daily_bars.is_empty() {
+            tracing::warn!(
+                "Schwab streams closed minute bars only; ignoring updated and daily bars"
+            );
+        }
+        if !request.lulds.is_empty() || !request.news.is_empty() {
+            tracing::warn!("Schwab has no limit up/limit down or news streams; ignoring them");
+        }
+        let requests: Vec<Value> = services
+            .into_iter()
+            .filter(|(_, keys, _)| !keys.is_empty())
+            .enumerate()
+            .map(|(i, (service, keys, fields))| {
+                let parameters = json!({ "keys": keys, "fields": fields });
+                self.request(i + 1, service, "SUBS", parameters)
+            })
+            .collect();
+        Ok(json!({ "requests": requests }))
+    }
+}
+
+/// Outcome of the login, if `frame` answers it.
+pub(super) fn login_result(frame: &str) -> Option<Result<(), String>> {
+    let value: Value = serde_json::from_str(frame).ok()?;
+    let response = value
+        .get("response")?
+        .as_array()?
+        .iter()
+        .find(|response| response.get("command").and_then(Value::as_str) == Some("LOGIN"))?;
+    let content = response.get("content")?;
+    match content.get("code").and_then(Value::as_i64) {
+        Some(0) => Some(Ok(())),
+        _ => Some(Err(content
+            .get("msg")
+            .and_then(Value::as_str)
+            .unwrap_or("Schwab streamer login failed")
+            .to_string())),
+    }
+}
+
+/// Latest level one fields of each symbol. The streamer only sends the fields that changed.
+pub(super) type LevelOne = Mutex<HashMap<String, HashMap<String, f64>>>;
+
+#[derive(Deserialize)]
+struct Data {
+    service: String,
+    #[serde(default)]
+    content: Vec<HashMap<String, Value>>,
+}
+
+/// Maps a streamer frame into events. Level one updates give a quote when the top of book changed and a trade
+/// when a new last trade came in, so subscribing to either streams both. Chart updates are closed minute bars
+/// and book updates replace the whole book. Responses and heartbeats give no events.
+pub(super) fn parse_frame(
+    frame: &str,
+    level_one: &LevelOne,
+) -> Result<EventBatch, serde_json::Error> {
+    let mut batch = EventBatch::new();
+    let value: Value = serde_json::from_str(frame)?;
+    let Some(data) = value.get("data") else {
+        return Ok(batch);
+    };
+    let data: Vec<Data> = serde_json::from_value(data.clone())?;
+
+    for Data { service, content } in data {
+        for content in content {
+            let Some(symbol) = content.get("key").and_then(Value::as_str) else {
+                continue;
+            };
+            let number = |field: &str| content.get(field).and_then(Value::as_f64);
+            match service.as_str() {
+                "LEVELONE_EQUITIES" => {
+                    let mut level_one = level_one.lock().unwrap();
+                    let fields = level_one.entry(symbol.to_string()).or_default();
+                    for (field, value) in &content {
+                        if let Some(value) = value.as_f64() {
+                            fields.insert(field.clone(), value);
+                        }
+                    }
+                    let field = |field: &str| fields.get(field).copied().unwrap_or_default();
+                    let millis = |field: &str| match fields.get(field) {
+                        Some(millis) => format_millis(*millis as i64),
+                        None => time::format_rfc3339(time::now_nanos()),
+                    };
+                    if ["1", "2", "4", "5"]
+                        .iter()
+                        .any(|changed| content.contains_key(*changed))
+                    {
+                        batch.push(EventType::Quote {
+                            symbol: symbol.to_string(),
+                            bid_price: field("1"),
+                            ask_price: field("2"),
+                            bid_size: field("4"),
+                            ask_size: field("5"),
+                            timestamp: millis("34"),
+                        });
+                    }
+                    if content.contains_key("35") {
+                        batch.push(EventType::Trade {
+                            symbol: symbol.to_string(),
+                            price: field("3"),
+                            volume: field("9"),
+                            timestamp: millis("35"),
+                        });
+                    }
+                }
+                "CHART_EQUITY" => batch.push(EventType::Bar {
+                    symbol: symbol.to_string(),
+                    open: number("1").unwrap_or_default(),
+                    high: number("2").unwrap_or_default(),
+                    low: number("3").unwrap_or_default(),
+                    close: number("4").unwrap_or_default(),
+                    volume: number("5").unwrap_or_default(),
+                    timestamp: format_millis(number("7").unwrap_or_default() as i64),
+                }),
+                "NASDAQ_BOOK" | "NYSE_BOOK" => {
+                    // Each level is an object with the price as "0" and the total size as "1".
+                    let levels = |field: &str| -> Vec<(f64, f64)> {
+                        content
+                            .get(field)
+                            .and_then(Value::as_array)
+                            .into_iter()
+                            .flatten()
+                            .filter_map(|level| {
+                                Some((level.get("0")?.as_f64()?, level.get("1")?.as_f64()?))
+                            })
+                            .collect()
+                    };
+                    batch.push(EventType::OrderBook {
+                        symbol: symbol.to_string(),
+                        bids: levels("2"),
+                        asks: levels("3"),
+                        reset: true,
+                        timestamp: format_millis(number("1").unwrap_or_default() as i64),
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(batch)
+}
@@ -0,0 +1,843 @@
+use crate::{
+    attribution::Blotter,
+    datastructures::{
+        client::TradingClient,
+        event::EventType,
+        order::{Order, OrderMetadata, OrderSide, OrderStatus, OrderType, TimeInForce},
+    },
+    http::signing,
+    outage,
+    stream::{BusSubscriber, EventBus},
+    time,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    error::Error,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::broadcast,
+    task::JoinHandle,
+};
+
+/// Page served at `/`.
+const DASHBOARD: &str = include_str!("dashboard.html");
+
+/// Largest request head read before the connection is dropped.
+const MAX_REQUEST: usize = 8 * 1024;
+
+/// Largest request body read before the connection is dropped.
+const MAX_BODY: usize = 64 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Defaults to localhost only. Without `api_key`, anyone who can reach it can see the account.
+    pub addr: SocketAddr,
+    /// Events kept for `/api/events` and for browsers that connect mid-session.
+    pub recent_events: usize,
+    /// Fills served as per-symbol attribution at `/api/attribution`. Without one the route is not found.
+    pub blotter: Option<Blotter>,
+    /// When set, every route but the dashboard page needs it as `Authorization: Bearer <key>`. The page asks
+    /// for it when the server refuses a request and keeps it for the browser tab.
+    pub api_key: Option<String>,
+    /// Serves the routes that place and cancel orders, so scripts can trade through the running bot. Needs
+    /// `api_key`.
+    pub allow_trading: bool,
+    /// How long a connection has to send its request before it is answered with 408 and closed.
+    pub request_timeout: Duration,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            addr: SocketAddr::from(([127, 0, 0, 1], 8080)),
+            recent_events: 100,
+            blotter: None,
+            api_key: None,
+            allow_trading: false,
+            request_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Health of the event stream as seen by the dashboard's own bus subscription.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StreamHealth {
+    pub connected: bool,
+    pub events_received: u64,
+    /// Events the dashboard skipped because it fell behind the bus.
+    pub events_missed: u64,
+    pub last_event_at: Option<String>,
+    /// When the dashboard last received anything, in local time rather than exchange time.
+    pub last_received_at: Option<String>,
+}
+
+struct Shared {
+    health: StreamHealth,
+    recent: VecDeque<EventType>,
+}
+
+/// Small web UI for monitoring a running bot from a browser: positions, open orders, stream health and live events.
+/// Its JSON routes double as a gateway for scripts and other dashboards. Routes:
+/// - `/`: the dashboard page
+/// - `/api/account`, `/api/positions`, `/api/orders`, `/api/orders/<id>`: fetched from the client on each request
+/// - `POST /api/orders`, with `allow_trading`: places the order in the body, e.g.
+///   `{"symbol": "AAPL", "qty": 1, "side": "buy", "type": "limit", "limit_price": 180}`, and returns it as the
+///   broker reports it with 201. `type` defaults to market, `time_in_force` to day. An order the broker or a
+///   pre-trade check refuses gets 422 with the reason, and one that could not reach the broker 503 or 502.
+/// - `DELETE /api/orders/<id>`, with `allow_trading`: cancels the order and returns
+///   `{"outcome": "canceled", "order": ...}`
+/// - `/api/health`: `StreamHealth`
+/// - `/api/events`: the most recent events
+/// - `/api/attribution?start=YYYY-MM-DD&end=YYYY-MM-DD&format=csv`: per-symbol attribution from the configured
+///   blotter, as JSON unless CSV is asked for. The range defaults to every fill.
+/// - `/events`: server-sent events, one JSON event per message
+/// - `/metrics`: Prometheus text, with the `metrics` feature
+///
+/// Routes are GET unless noted. Every response closes the connection. Stops when dropped.
+pub struct DashboardServer {
+    addr: SocketAddr,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl DashboardServer {
+    /// Binds `config.addr` and starts serving. Events are taken from a new subscription to `bus`.
+    pub async fn serve<C>(
+        client: C,
+        bus: &EventBus,
+        config: ServerConfig,
+    ) -> Result<DashboardServer, Box<dyn Error>>
+    where
+        C: TradingClient + Clone + Send + Sync + 'static,
+    {
+        if config.allow_trading && config.api_key.is_none() {
+            return Err("Trading routes need an API key".into());
+        }
+        let listener = TcpListener::bind(config.addr).await?;
+        let addr = listener.local_addr()?;
+        let shared = Arc::new(Mutex::new(Shared {
+            health: StreamHealth {
+                connected: true,
+                ..StreamHealth::default()
+            },
+            recent: VecDeque::with_capacity(config.recent_events),
+        }));
+        // Each browser gets its own receiver; a slow one skips ahead rather than holding up the others.
+        let (live, _) = broadcast::channel(1024);
+
+        let collector = tokio::spawn(collect(
+            bus.subscribe(),
+            shared.clone(),
+            live.clone(),
+            config.recent_events,
+        ));
+        let config = Arc::new(config);
+        let acceptor = tokio::spawn(async move {
+            loop {
+                let socket = match listener.accept().await {
+                    Ok((socket, _)) => socket,
+                    Err(e) => {
+                        tracing::warn!(error = %e, "Dashboard failed to accept connection");
+                        continue;
+                    }
+                };
+                let client = client.clone();
+                let shared = shared.clone();
+                let live = live.subscribe();
+                let config = config.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle(socket, &client, &shared, &config, live).await {
+                        tracing::debug!(error = %e, "Dashboard connection closed");
+                    }
+                });
+            }
+        });
+
+        tracing::info!(%addr, "Dashboard listening");
+        Ok(DashboardServer {
+            addr,
+            tasks: vec![collector, acceptor],
+        })
+    }
+
+    /// Address actually bound, e.g. when the configured port was 0.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for DashboardServer {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+async fn collect(
+    mut subscriber: BusSubscriber,
+    shared: Arc<Mutex<Shared>>,
+    live: broadcast::Sender<String>,
+    capacity: usize,
+) {
+    while let Some(batch) = subscriber.recv_batch().await {
+        let mut shared = shared.lock().unwrap();
+        for event in batch.iter() {
+            if let Ok(json) = serde_json::to_string(event) {
+                // Only fails when no browser is connected.
+                let _ = live.send(json);
+            }
+            if let Some(timestamp) = event.timestamp() {
+                shared.health.last_event_at = Some(timestamp.to_string());
+            }
+            if capacity > 0 {
+                if shared.recent.len() == capacity {
+                    shared.recent.pop_front();
+                }
+                shared.recent.push_back(event.clone());
+            }
+        }
+        shared.health.events_received = subscriber.received();
+        shared.health.events_missed = subscriber.missed();
+        shared.health.last_received_at = Some(time::format_rfc3339(time::now_nanos()));
+    }
+    shared.lock().unwrap().health.connected = false;
+}
+
+async fn handle<C: TradingClient>(
+    mut socket: TcpStream,
+    client: &C,
+    shared: &Mutex<Shared>,
+    config: &ServerConfig,
+    mut live: broadcast::Receiver<String>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let request =
+        match tokio::time::timeout(config.request_timeout, read_request(&mut socket)).await {
+            Ok(request) => request?,
+            Err(_) => {
+                return respond(
+                    &mut socket,
+                    "408 Request Timeout",
+                    "text/plain",
+                    "Request timed out",
+                )
+                .await
+            }
+        };
+    let Some(request) = request else {
+        return Ok(());
+    };
+    if request.headers.contains_key("transfer-encoding") {
+        return respond(
+            &mut socket,
+            "411 Length Required",
+            "text/plain",
+            "Send the body with a Content-Length",
+        )
+        .await;
+    }
+    let (route, query) = request.path.split_once('?').unwrap_or((&request.path, ""));
+    let query: HashMap<String, String> = url::form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .collect();
+    if let Some(api_key) = config.api_key.as_deref().filter(|_| route != "/") {
+        let presented = request
+            .headers
+            .get("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        if !signing::constant_time_eq(presented.as_bytes(), api_key.as_bytes()) {
+            return respond(
+                &mut socket,
+                "401 Unauthorized",
+                "text/plain",
+                "Invalid API key",
+            )
+            .await;
+        }
+    }
+
+    let json = |result: Result<String, String>| match result {
+        Ok(body) => ("200 OK", "application/json", body),
+        Err(e) => ("502 Bad Gateway", "text/plain", e),
+    };
+    let order_id = route
+        .strip_prefix("/api/orders/")
+        .filter(|id| !id.is_empty());
+    let (status, content_type, body) = match (request.method.as_str(), route) {
+        ("GET", "/") => ("200 OK", "text/html; charset=utf-8", DASHBOARD.to_string()),
+        ("GET", "/api/account") => json(
+            client
+                .get_account()
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|account| to_json(&account)),
+        ),
+        ("GET", "/api/positions") => json(
+            client
+                .get_positions()
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|positions| to_json(&positions)),
+        ),
+        ("GET", "/api/orders") => json(
+            client
+                .get_open_orders()
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|orders| to_json(&orders)),
+        ),
+        ("POST", "/api/orders") if config.allow_trading => {
+            create_order(client, &request.body).await
+        }
+        ("GET", _) if order_id.is_some() => json(
+            client
+                .get_order(order_id.unwrap_or_default())
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|order| to_json(&order)),
+        ),
+        ("DELETE", _) if order_id.is_some() && config.allow_trading => match client
+            .cancel_order(order_id.unwrap_or_default())
+            .await
+            .map_err(|e| (error_status(e.as_ref()), e.to_string()))
+        {
+            Ok(outcome) => json(to_json(&serde_json::json!({
+                "outcome": outcome.name(),
+                "order": outcome.order(),
+            }))),
+            Err((status, e)) => (status, "text/plain", e),
+        },
+        ("GET", "/api/health") => json(to_json(&shared.lock().unwrap().health)),
+        ("GET", "/api/events") => json(to_json(&shared.lock().unwrap().recent)),
+        ("GET", "/api/attribution") if config.blotter.is_some() => {
+            let param = |name: &str, default: &'static str| {
+                query.get(name).map_or(default, String::as_str).to_string()
+            };
+            let report = config
+                .blotter
+                .as_ref()
+                .map(|blotter| {
+                    blotter.attribute(&param("start", "0000-01-01"), &param("end", "9999-12-31"))
+                })
+                .unwrap_or_default();
+            if query.get("format").is_some_and(|format| format == "csv") {
+                ("200 OK", "text/csv", report.to_csv())
+            } else {
+                json(to_json(&report))
+            }
+        }
+        #[cfg(feature = "metrics")]
+        ("GET", "/metrics") => (
+            "200 OK",
+            "text/plain; version=0.0.4",
+            crate::metrics::registry().render(),
+        ),
+        ("GET", "/events") => {
+            socket
+                .write_all(
+                    b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n",
+                )
+                .await?;
+            loop {
+                match live.recv().await {
+                    Ok(json) => {
+                        socket
+                            .write_all(format!("data: {}\n\n", json).as_bytes())
+                            .await?
+                    }
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        socket
+                            .write_all(format!("event: lagged\ndata: {}\n\n", missed).as_bytes())
+                            .await?
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return Ok(()),
+                }
+            }
+        }
+        ("GET", _) => ("404 Not Found", "text/plain", "Not found".to_string()),
+        _ => (
+            "405 Method Not Allowed",
+            "text/plain",
+            "Method not allowed".to_string(),
+        ),
+    };
+    respond(&mut socket, status, content_type, &body).await
+}
+
+/// Body of `POST /api/orders`, in Alpaca's field names.
+#[derive(Deserialize)]
+struct OrderRequest {
+    symbol: String,
+    qty: f64,
+    side: OrderSide,
+    #[serde(rename = "type")]
+    order_type: Option<OrderType>,
+    time_in_force: Option<TimeInForce>,
+    limit_price: Option<f64>,
+    stop_price: Option<f64>,
+    client_order_id: Option<String>,
+    #[serde(default)]
+    metadata: OrderMetadata,
+}
+
+impl OrderRequest {
+    /// Validated as `OrderBuilder` does. A client order id is generated when none is given, so the placed order
+    /// can be looked up.
+    fn into_order(self) -> Result<Order, &'static str> {
+        let mut builder = Order::builder()
+            .symbol(self.symbol)
+            .quantity(self.qty)
+            .side(self.side)
+            .metadata(self.metadata)
+            .client_order_id(
+                self.client_order_id
+                    .unwrap_or_else(|| format!("{:032x}", rand::random::<u128>())),
+            );
+        if let Some(order_type) = self.order_type {
+            builder = builder.order_type(order_type);
+        }
+        if let Some(time_in_force) = self.time_in_force {
+            builder = builder.time_in_force(time_in_force);
+        }
+        if let Some(limit_price) = self.limit_price {
+            builder = builder.limit_price(limit_price);
+        }
+        if let Some(stop_price) = self.stop_price {
+            builder = builder.stop_price(stop_price);
+        }
+        builder.build()
+    }
+}
+
+async fn create_order<C: TradingClient>(
+    client: &C,
+    body: &[u8],
+) -> (&'static str, &'static str, String) {
+    let order = match serde_json::from_slice::<OrderRequest>(body)
+        .map_err(|e| e.to_string())
+        .and_then(|request| request.into_order().map_err(str::to_string))
+    {
+        Ok(order) => order,
+        Err(e) => return ("400 Bad Request", "text/plain", e),
+    };
+    if let Err((status, e)) = client
+        .create_order(&order)
+        .await
+        .map_err(|e| (error_status(e.as_ref()), e.to_string()))
+    {
+        return (status, "text/plain", e);
+    }
+    let client_order_id = order.client_order_id.unwrap_or_default();
+    let placed = client
+        .get_order_by_client_id(&client_order_id)
+        .await
+        .map_err(|e| e.to_string());
+    match placed {
+        // Some brokers take an order and reject it a moment later.
+        Ok(placed) if placed.status == OrderStatus::Rejected => (
+            "422 Unprocessable Entity",
+            "application/json",
+            to_json(&placed).unwrap_or_default(),
+        ),
+        Ok(placed) => match to_json(&placed) {
+            Ok(body) => ("201 Created", "application/json", body),
+            Err(e) => ("500 Internal Server Error", "text/plain", e),
+        },
+        // Sent, but the broker's view of it is unknown, so the caller is given the id to look it up with.
+        Err(e) => (
+            "202 Accepted",
+            "application/json",
+            serde_json::json!({ "client_order_id": client_order_id, "error": e }).to_string(),
+        ),
+    }
+}
+
+/// Response status for an error placing or cancelling an order: 422 when the broker or a pre-trade check
+/// refused it, 503 when the broker could not be reached, 502 otherwise.
+fn error_status(error: &(dyn Error + 'static)) -> &'static str {
+    let refused = error.is::<crate::risk::RiskViolation>()
+        || error.is::<crate::buying_power::InsufficientBuyingPower>()
+        || error.is::<crate::blackout::BlackoutViolation>()
+        || error.is::<crate::luld::LuldViolation>()
+        || error.is::<crate::observer::ReadOnlyError>()
+        || broker_refused(error);
+    if refused {
+        "422 Unprocessable Entity"
+    } else if outage::is_outage_error(error) {
+        "503 Service Unavailable"
+    } else {
+        "502 Bad Gateway"
+    }
+}
+
+/// Whether the error is a broker's answer refusing the request, as opposed to a failure to process it.
+fn broker_refused(error: &(dyn Error + 'static)) -> bool {
+    #[cfg(feature = "alpaca")]
+    if let Some(e) = error.downcast_ref::<crate::alpaca::AlpacaError>() {
+        return (400..500).contains(&e.status);
+    }
+    #[cfg(feature = "binance")]
+    if let Some(e) = error.downcast_ref::<crate::binance::BinanceError>() {
+        return (400..500).contains(&e.status);
+    }
+    #[cfg(feature = "gemini")]
+    if let Some(e) = error.downcast_ref::<crate::gemini::GeminiError>() {
+        return (400..500).contains(&e.status);
+    }
+    // Kraken refuses orders in the body of a successful response.
+    #[cfg(feature = "kraken")]
+    if let Some(e) = error.downcast_ref::<crate::kraken::KrakenError>() {
+        return e.status < 500;
+    }
+    #[cfg(feature = "schwab")]
+    if let Some(e) = error.downcast_ref::<crate::schwab::SchwabError>() {
+        return (400..500).contains(&e.status);
+    }
+    // Nothing was placed, as real trading is not enabled.
+    #[cfg(feature = "schwab")]
+    if error.is::<crate::schwab::OrderPreviewed>() {
+        return true;
+    }
+    false
+}
+
+struct Request {
+    method: String,
+    path: String,
+    /// Keyed by lowercase name.
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+/// The request, or `None` if the connection closed before a full request arrived or it was too large.
+async fn read_request(socket: &mut TcpStream) -> std::io::Result<Option<Request>> {
+    let mut buffer = Vec::with_capacity(1024);
+    let mut chunk = [0; 1024];
+    let head_end = loop {
+        let end = buffer.windows(4).position(|window| window == b"\r\n\r\n");
+        if end.map_or(buffer.len(), |end| end + 4) > MAX_REQUEST {
+            return Ok(None);
+        }
+        if let Some(end) = end {
+            break end + 4;
+        }
+        match socket.read(&mut chunk).await? {
+            0 => return Ok(None),
+            n => buffer.extend_from_slice(&chunk[..n]),
+        }
+    };
+
+    let mut body = buffer.split_off(head_end);
+    let head = String::from_utf8_lossy(&buffer);
+    let mut lines = head.lines();
+    let mut parts = lines.next().unwrap_or_default().split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Ok(None);
+    };
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        .collect();
+
+    let length = headers
+        .get("content-length")
+        .and_then(|length| length.parse().ok())
+        .unwrap_or(0);
+    if length > MAX_BODY {
+        return Ok(None);
+    }
+    while body.len() < length {
+        match socket.read(&mut chunk).await? {
+            0 => return Ok(None),
+            n => body.extend_from_slice(&chunk[..n]),
+        }
+    }
+    body.truncate(length);
+    Ok(Some(Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        headers,
+        body,
+    }))
+}
+
+async fn respond(
+    socket: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &str,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    socket.write_all(head.as_bytes()).await?;
+    socket.write_all(body.as_bytes()).await?;
+    Ok(())
+}
+
+fn to_json(value: &impl Serialize) -> Result<String, String> {
+    serde_json::to_string(value).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        risk::RiskLimits,
+        sim::{SimClient, SimConfig},
+        stream::EventStream,
+        testing::{MockCall, MockOrder, MockTradingClient},
+    };
+
+    fn bus() -> EventBus {
+        EventBus::new(EventStream::from_task(16, |_sender| async {}), 16)
+    }
+
+    fn trading() -> ServerConfig {
+        ServerConfig {
+            addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+            api_key: Some("secret".to_string()),
+            allow_trading: true,
+            ..ServerConfig::default()
+        }
+    }
+
+    /// Sends `request` as is and returns the status code and body of the response.
+    async fn send(addr: SocketAddr, request: &str) -> (u16, String) {
+        let mut socket = TcpStream::connect(addr).await.unwrap();
+        socket.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        socket.read_to_string(&mut response).await.unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+        let status = head
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .unwrap_or(0);
+        (status, body.to_string())
+    }
+
+    async fn post_order(addr: SocketAddr, body: &str) -> (u16, String) {
+        let request = format!(
+            "POST /api/orders HTTP/1.1\r\nAuthorization: Bearer secret\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        send(addr, &request).await
+    }
+
+    const BUY: &str =
+        r#"{"symbol": "AAPL", "qty": 2, "side": "buy", "type": "limit", "limit_price": 180}"#;
+
+    #[tokio::test]
+    async fn needs_the_key_in_a_header() {
+        let server = DashboardServer::serve(MockTradingClient::with_cash(0.0), &bus(), trading())
+            .await
+            .unwrap();
+        let addr = server.local_addr();
+
+        let (status, body) = send(addr, "GET / HTTP/1.1\r\n\r\n").await;
+        assert_eq!(status, 200);
+        assert!(body.contains("<html"));
+        assert_eq!(
+            send(addr, "GET /api/positions HTTP/1.1\r\n\r\n").await.0,
+            401
+        );
+        assert_eq!(
+            send(addr, "GET /api/positions?api_key=secret HTTP/1.1\r\n\r\n")
+                .await
+                .0,
+            401
+        );
+        assert_eq!(
+            send(
+                addr,
+                "GET /api/positions HTTP/1.1\r\nAuthorization: Bearer wrong\r\n\r\n"
+            )
+            .await
+            .0,
+            401
+        );
+        let (status, body) = send(
+            addr,
+            "GET /api/positions HTTP/1.1\r\nauthorization: Bearer secret\r\n\r\n",
+        )
+        .await;
+        assert_eq!((status, body.as_str()), (200, "[]"));
+    }
+
+    #[tokio::test]
+    async fn trading_needs_an_api_key() {
+        let config = ServerConfig {
+            api_key: None,
+            ..trading()
+        };
+        let client = MockTradingClient::with_cash(0.0);
+        assert!(DashboardServer::serve(client, &bus(), config)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn refuses_trading_unless_allowed() {
+        let config = ServerConfig {
+            allow_trading: false,
+            ..trading()
+        };
+        let client = MockTradingClient::with_cash(10_000.0);
+        let server = DashboardServer::serve(client.clone(), &bus(), config)
+            .await
+            .unwrap();
+        assert_eq!(post_order(server.local_addr(), BUY).await.0, 405);
+        assert!(client.submitted().is_empty());
+    }
+
+    #[tokio::test]
+    async fn places_orders() {
+        let client = MockTradingClient::with_cash(10_000.0);
+        let server = DashboardServer::serve(client.clone(), &bus(), trading())
+            .await
+            .unwrap();
+        let addr = server.local_addr();
+
+        let (status, body) = post_order(addr, BUY).await;
+        assert_eq!(status, 201);
+        let placed: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(placed["symbol"], "AAPL");
+        assert_eq!(placed["status"], "new");
+        let submitted = client.submitted();
+        assert_eq!(submitted[0].order_type, OrderType::Limit);
+        assert_eq!(submitted[0].limit_price, Some(180.0));
+
+        let (status, body) = send(
+            addr,
+            &format!(
+                "DELETE /api/orders/{} HTTP/1.1\r\nAuthorization: Bearer secret\r\n\r\n",
+                placed["id"].as_str().unwrap()
+            ),
+        )
+        .await;
+        assert_eq!(status, 200);
+        assert!(body.contains(r#""outcome":"canceled""#));
+
+        assert_eq!(post_order(addr, r#"{"symbol": "AAPL"}"#).await.0, 400);
+        assert_eq!(
+            post_order(addr, r#"{"symbol": "AAPL", "qty": -1, "side": "buy"}"#)
+                .await
+                .0,
+            400
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_broker_errors() {
+        let client = MockTradingClient::with_cash(10_000.0);
+        let server = DashboardServer::serve(client.clone(), &bus(), trading())
+            .await
+            .unwrap();
+        let addr = server.local_addr();
+
+        client.push_order(MockOrder::LateReject);
+        let (status, body) = post_order(addr, BUY).await;
+        assert_eq!(status, 422);
+        assert!(body.contains(r#""status":"rejected""#));
+
+        client.fail(MockCall::GetOrder, "timed out");
+        let (status, body) = post_order(addr, BUY).await;
+        assert_eq!(status, 202);
+        assert!(body.contains("client_order_id"));
+
+        client.fail(MockCall::CreateOrder, "broker unavailable");
+        let (status, body) = post_order(addr, BUY).await;
+        assert_eq!((status, body.as_str()), (502, "broker unavailable"));
+    }
+
+    #[cfg(feature = "schwab")]
+    #[test]
+    fn reports_schwab_previews_as_not_placed() {
+        let previewed = crate::schwab::OrderPreviewed {
+            symbol: "AAPL".to_string(),
+            preview: serde_json::Value::Null,
+        };
+        assert_eq!(error_status(&previewed), "422 Unprocessable Entity");
+    }
+
+    #[tokio::test]
+    async fn reports_pre_trade_rejections() {
+        let client = SimClient::with_config(SimConfig {
+            risk_limits: Some(RiskLimits {
+                restricted: ["AAPL".to_string()].into(),
+                ..RiskLimits::default()
+            }),
+            ..SimConfig::default()
+        });
+        let server = DashboardServer::serve(client, &bus(), trading())
+            .await
+            .unwrap();
+        let (status, body) = post_order(server.local_addr(), BUY).await;
+        assert_eq!(status, 422);
+        assert!(body.contains("AAPL"));
+    }
+
+    #[tokio::test]
+    async fn times_out_slow_requests() {
+        let config = ServerConfig {
+            request_timeout: Duration::from_millis(50),
+            ..trading()
+        };
+        let server = DashboardServer::serve(MockTradingClient::with_cash(0.0), &bus(), config)
+            .await
+            .unwrap();
+        let (status, _) = send(server.local_addr(), "GET /api/positions HTTP/1.1\r\n").await;
+        assert_eq!(status, 408);
+    }
+
+    #[tokio::test]
+    async fn reads_requests() {
+        let server = DashboardServer::serve(MockTradingClient::with_cash(0.0), &bus(), trading())
+            .await
+            .unwrap();
+        let addr = server.local_addr();
+
+        let chunked = "POST /api/orders HTTP/1.1\r\nAuthorization: Bearer secret\r\n\
+            Transfer-Encoding: chunked\r\n\r\n0\r\n\r\n";
+        assert_eq!(send(addr, chunked).await.0, 411);
+
+        let oversized = format!(
+            "GET / HTTP/1.1\r\nX-Padding: {}\r\n\r\n",
+            "a".repeat(MAX_REQUEST)
+        );
+        assert_eq!(send(addr, &oversized).await, (0, String::new()));
+
+        let too_long = format!(
+            "POST /api/orders HTTP/1.1\r\nAuthorization: Bearer secret\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY + 1
+        );
+        assert_eq!(send(addr, &too_long).await, (0, String::new()));
+        assert_eq!(send(addr, "\r\n\r\n").await, (0, String::new()));
+
+        // The head and body may arrive in pieces.
+        let mut socket = TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "POST /api/orders HTTP/1.1\r\nAuthorization: Bearer secret\r\nContent-Length: {}\r\n\r\n{}",
+            BUY.len(),
+            BUY
+        );
+        for piece in request.as_bytes().chunks(7) {
+            socket.write_all(piece).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        let mut response = String::new();
+        socket.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 201 Created\r\n"));
+    }
+}
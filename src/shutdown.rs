@@ -0,0 +1,117 @@
+use async_trait::async_trait;
+use futures_util::future::join_all;
+use std::{
+    error::Error,
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Somewhere data is written that may still hold some of it in memory, e.g. a recorder or a journal.
+#[async_trait]
+pub trait Sink: Send + Sync {
+    /// Writes out everything buffered and syncs it to disk. The sink can still be written to afterwards, but
+    /// nothing written later is covered.
+    async fn flush_and_close(&self) -> Result<(), Box<dyn Error>>;
+}
+
+/// What happened to each sink on shutdown.
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownReport {
+    pub flushed: Vec<String>,
+    /// Sinks whose flush failed, with the error.
+    pub failed: Vec<(String, String)>,
+    /// Sinks still flushing when the deadline passed.
+    pub timed_out: Vec<String>,
+}
+
+impl ShutdownReport {
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty() && self.timed_out.is_empty()
+    }
+}
+
+#[derive(Clone)]
+struct Registered {
+    name: String,
+    sink: Arc<dyn Sink>,
+}
+
+/// Sinks to flush before the process exits, so data recorded in its last seconds is not lost. Every sink is
+/// flushed concurrently and given until the deadline.
+#[derive(Clone)]
+pub struct ShutdownCoordinator {
+    sinks: Arc<Mutex<Vec<Registered>>>,
+    deadline: Duration,
+}
+
+impl ShutdownCoordinator {
+    pub fn new(deadline: Duration) -> Self {
+        ShutdownCoordinator {
+            sinks: Arc::new(Mutex::new(vec![])),
+            deadline,
+        }
+    }
+
+    pub fn register(&self, name: impl Into<String>, sink: Arc<dyn Sink>) {
+        self.sinks.lock().unwrap().push(Registered {
+            name: name.into(),
+            sink,
+        });
+    }
+
+    pub fn deadline(&self) -> Duration {
+        self.deadline
+    }
+
+    /// Flushes and closes every registered sink, waiting at most the deadline.
+    pub async fn shutdown(&self) -> ShutdownReport {
+        let sinks = self.sinks.lock().unwrap().clone();
+        let results = join_all(sinks.iter().map(|registered| async move {
+            let result = tokio::time::timeout(self.deadline, registered.sink.flush_and_close())
+                .await
+                .map(|flushed| flushed.map_err(|e| e.to_string()));
+            (registered.name.clone(), result)
+        }))
+        .await;
+
+        let mut report = ShutdownReport::default();
+        for (name, result) in results {
+            match result {
+                Ok(Ok(())) => report.flushed.push(name),
+                Ok(Err(e)) => {
+                    tracing::error!(sink = %name, error = %e, "Failed to flush sink on shutdown");
+                    report.failed.push((name, e));
+                }
+                Err(_) => {
+                    tracing::error!(sink = %name, deadline = ?self.deadline, "Sink still flushing at the shutdown deadline");
+                    report.timed_out.push(name);
+                }
+            }
+        }
+        report
+    }
+}
+
+impl Default for ShutdownCoordinator {
+    /// Five seconds to flush.
+    fn default() -> Self {
+        ShutdownCoordinator::new(Duration::from_secs(5))
+    }
+}
+
+impl fmt::Debug for ShutdownCoordinator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sinks = self.sinks.lock().unwrap();
+        f.debug_struct("ShutdownCoordinator")
+            .field(
+                "sinks",
+                &sinks
+                    .iter()
+                    .map(|registered| &registered.name)
+                    .collect::<Vec<_>>(),
+            )
+            .field("deadline", &self.deadline)
+            .finish()
+    }
+}
@@ -0,0 +1,262 @@
+use crate::{
+    backtest::{ExecutionCosts, SimBroker},
+    datastructures::{
+        account::{Account, Position},
+        asset::Asset,
+        client::{MarketDataClient, SubscriptionParams, TradingClient},
+        event::EventType,
+        market::{Bar, BarAdjustment, Quote},
+        order::{CancelOutcome, Order, OrderResponse},
+    },
+    quotes::QuoteCache,
+    risk::{RiskEngine, RiskLimits},
+    strategy::Fill,
+};
+use async_trait::async_trait;
+use futures_util::Stream;
+use std::{
+    error::Error,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::{net::TcpStream, sync::broadcast};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+#[derive(Clone)]
+pub struct SimConfig {
+    pub initial_cash: f64,
+    pub costs: ExecutionCosts,
+    /// Delay before an order or cancel reaches the simulated broker, standing in for the round trip to Alpaca.
+    pub latency: Duration,
+    /// Pre-trade limits checked before orders reach the simulated broker.
+    pub risk_limits: Option<RiskLimits>,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        SimConfig {
+            initial_cash: 100_000.0,
+            costs: ExecutionCosts::default(),
+            latency: Duration::ZERO,
+            risk_limits: None,
+        }
+    }
+}
+
+/// `TradingClient` that fills orders locally against the market data it is fed, live or replayed,
+/// instead of sending them to Alpaca. Matching follows the backtest engine: orders fill in full on the first
+/// later event for their symbol that satisfies their price.
+#[derive(Clone)]
+pub struct SimClient {
+    config: SimConfig,
+    broker: Arc<Mutex<SimBroker>>,
+    quotes: QuoteCache,
+    fills: broadcast::Sender<Fill>,
+}
+
+impl SimClient {
+    pub fn with_config(config: SimConfig) -> Self {
+        SimClient {
+            broker: Arc::new(Mutex::new(SimBroker::new(
+                config.initial_cash,
+                config.costs.clone(),
+            ))),
+            config,
+            quotes: QuoteCache::new(),
+            fills: broadcast::channel(1024).0,
+        }
+    }
+
+    /// Advances the simulation by one market data event, filling whatever working orders it allows.
+    pub fn on_event(&self, event: &EventType) -> Vec<Fill> {
+        self.quotes.update(event);
+        let fills = self.broker.lock().unwrap().on_event(event);
+        for fill in &fills {
+            tracing::debug!(order_id = %fill.order_id, symbol = %fill.symbol, price = fill.price, "Simulated fill");
+            let _ = self.fills.send(fill.clone());
+        }
+        fills
+    }
+
+    /// Feeds every event of `stream` to the simulation as it passes through, leaving the events untouched.
+    pub fn tap<S>(&self, stream: S) -> Simulated<S>
+    where
+        S: Stream<Item = EventType> + Unpin,
+    {
+        Simulated {
+            stream,
+            client: self.clone(),
+        }
+    }
+
+    /// Every fill from now on.
+    pub fn fills(&self) -> broadcast::Receiver<Fill> {
+        self.fills.subscribe()
+    }
+
+    async fn delay(&self) {
+        if !self.config.latency.is_zero() {
+            tokio::time::sleep(self.config.latency).await;
+        }
+    }
+}
+
+#[async_trait]
+impl TradingClient for SimClient {
+    async fn create_order(&self, order: &Order) -> Result<(), Box<dyn Error>> {
+        let order = match &self.config.risk_limits {
+            Some(limits) => RiskEngine::new(limits.clone()).check(self, order).await?,
+            None => order.clone(),
+        };
+        self.delay().await;
+        let id = self.broker.lock().unwrap().submit(order.clone(), None);
+        tracing::debug!(order_id = %id, ?order, "Simulated order accepted");
+        Ok(())
+    }
+
+    async fn get_open_orders(&self) -> Result<Vec<OrderResponse>, Box<dyn Error>> {
+        Ok(self.broker.lock().unwrap().open_orders())
+    }
+
+    async fn get_order(&self, order_id: &str) -> Result<OrderResponse, Box<dyn Error>> {
+        self.broker
+            .lock()
+            .unwrap()
+            .order(order_id)
+            .cloned()
+            .ok_or_else(|| format!("No order {}", order_id).into())
+    }
+
+    /// Simulated orders are identified by their client order id.
+    async fn get_order_by_client_id(
+        &self,
+        client_order_id: &str,
+    ) -> Result<OrderResponse, Box<dyn Error>> {
+        self.get_order(client_order_id).await
+    }
+
+    async fn cancel_order(&self, order_id: &str) -> Result<CancelOutcome, Box<dyn Error>> {
+        self.delay().await;
+        let mut broker = self.broker.lock().unwrap();
+        broker.cancel(order_id);
+        let order = broker
+            .order(order_id)
+            .cloned()
+            .ok_or_else(|| format!("No order {}", order_id))?;
+        Ok(CancelOutcome::from_order(order))
+    }
+
+    async fn get_asset(&self, symbol: &str) -> Result<Asset, Box<dyn Error>> {
+        Ok(sim_asset(symbol))
+    }
+
+    /// Every symbol quoted to the simulation so far.
+    async fn list_assets(&self) -> Result<Vec<Asset>, Box<dyn Error>> {
+        Ok(self
+            .quotes
+            .symbols()
+            .iter()
+            .map(|symbol| sim_asset(symbol))
+            .collect())
+    }
+
+    /// A cash account without margin, so buying power is the cash balance.
+    async fn get_account(&self) -> Result<Account, Box<dyn Error>> {
+        let broker = self.broker.lock().unwrap();
+        let positions = broker.positions();
+        let market_value = |long: bool| {
+            positions
+                .iter()
+                .filter(|position| (position.qty > 0.0) == long)
+                .fold(0.0, |total, position| total + position.market_value)
+        };
+        Ok(Account {
+            id: "sim".to_string(),
+            status: "ACTIVE".to_string(),
+            currency: "USD".to_string(),
+            cash: broker.cash(),
+            equity: broker.equity(),
+            last_equity: self.config.initial_cash,
+            buying_power: broker.cash().max(0.0),
+            long_market_value: market_value(true),
+            short_market_value: market_value(false),
+            // Trades settle at once.
+            non_marginable_buying_power: Some(broker.cash().max(0.0)),
+        })
+    }
+
+    async fn get_positions(&self) -> Result<Vec<Position>, Box<dyn Error>> {
+        Ok(self.broker.lock().unwrap().positions())
+    }
+}
+
+#[async_trait]
+impl MarketDataClient for SimClient {
+    /// Latest quote fed to the simulation.
+    async fn get_latest_quote(&self, symbol: &str) -> Result<Quote, Box<dyn Error>> {
+        self.quotes
+            .latest(symbol)
+            .map(|cached| cached.quote)
+            .ok_or_else(|| format!("No quote for {}", symbol).into())
+    }
+
+    /// The simulation keeps no history.
+    async fn get_daily_bars(
+        &self,
+        _symbol: &str,
+        _start: &str,
+        _end: &str,
+        _adjustment: BarAdjustment,
+    ) -> Result<Vec<Bar>, Box<dyn Error>> {
+        Err("SimClient has no historical bars".into())
+    }
+
+    /// The simulation has no stream of its own. Subscribe with a real client, or use a `ReplayFeed`,
+    /// and pass the events through `tap`.
+    async fn subscribe(
+        &self,
+        _params: SubscriptionParams,
+    ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, Box<dyn Error>> {
+        Err("SimClient does not stream market data; feed it events with `tap` or `on_event`".into())
+    }
+}
+
+/// Stream returned by `SimClient::tap`.
+pub struct Simulated<S> {
+    stream: S,
+    client: SimClient,
+}
+
+impl<S> Stream for Simulated<S>
+where
+    S: Stream<Item = EventType> + Unpin,
+{
+    type Item = EventType;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<EventType>> {
+        let event = Pin::new(&mut self.stream).poll_next(cx);
+        if let Poll::Ready(Some(event)) = &event {
+            self.client.on_event(event);
+        }
+        event
+    }
+}
+
+pub(crate) fn sim_asset(symbol: &str) -> Asset {
+    Asset {
+        symbol: symbol.to_string(),
+        exchange: "SIM".to_string(),
+        asset_class: if symbol.contains('/') {
+            "crypto".to_string()
+        } else {
+            "us_equity".to_string()
+        },
+        status: "active".to_string(),
+        tradable: true,
+        shortable: true,
+        fractionable: true,
+        attributes: vec![],
+    }
+}
@@ -0,0 +1,138 @@
+/// What a sizing rule needs to know about the account and the trade.
+#[derive(Debug, Clone, Copy)]
+pub struct SizingInput {
+    pub equity: f64,
+    /// Expected entry price.
+    pub price: f64,
+    /// Strategy's confidence in the signal, from 0 to 1. Scales the size linearly.
+    pub confidence: f64,
+    /// Distance from entry to the stop, in price. Lets fixed-fractional sizing risk a fraction of equity
+    /// rather than allocate it.
+    pub stop_distance: Option<f64>,
+    /// Annualized volatility of the symbol's returns, e.g. 0.25 for 25%. Required for volatility targeting.
+    pub volatility: Option<f64>,
+    /// Allow fractional quantities. Otherwise the quantity is rounded down to whole shares.
+    pub fractional: bool,
+}
+
+impl Default for SizingInput {
+    fn default() -> Self {
+        SizingInput {
+            equity: 0.0,
+            price: 0.0,
+            confidence: 1.0,
+            stop_distance: None,
+            volatility: None,
+            fractional: false,
+        }
+    }
+}
+
+/// How much equity to commit to a trade.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sizing {
+    /// Risk `fraction` of equity down to the stop, or allocate it when there is no stop.
+    FixedFractional { fraction: f64 },
+    /// Kelly criterion from the strategy's win rate and average win over average loss. `multiplier` scales the
+    /// Kelly fraction (0.5 is half Kelly) and `max_fraction` caps the share of equity allocated.
+    Kelly {
+        win_rate: f64,
+        payoff: f64,
+        multiplier: f64,
+        max_fraction: f64,
+    },
+    /// Size the position so its annualized volatility is `target` of equity, e.g. 0.1 for 10%, with notional
+    /// capped at `max_leverage` times equity.
+    VolatilityTarget { target: f64, max_leverage: f64 },
+}
+
+impl Sizing {
+    /// Half Kelly allocating at most a quarter of equity.
+    pub fn half_kelly(win_rate: f64, payoff: f64) -> Self {
+        Sizing::Kelly {
+            win_rate,
+            payoff,
+            multiplier: 0.5,
+            max_fraction: 0.25,
+        }
+    }
+
+    /// Order quantity for the trade. Zero when the rule has no edge or its inputs are missing or invalid.
+    pub fn quantity(&self, input: &SizingInput) -> f64 {
+        if !(input.equity > 0.0 && input.price > 0.0) {
+            return 0.0;
+        }
+        let notional = match *self {
+            Sizing::FixedFractional { fraction } => match input.stop_distance {
+                Some(stop_distance) if stop_distance > 0.0 => {
+                    input.equity * fraction / stop_distance * input.price
+                }
+                Some(_) => 0.0,
+                None => input.equity * fraction,
+            },
+            Sizing::Kelly {
+                win_rate,
+                payoff,
+                multiplier,
+                max_fraction,
+            } => input.equity * (kelly_fraction(win_rate, payoff) * multiplier).min(max_fraction),
+            Sizing::VolatilityTarget {
+                target,
+                max_leverage,
+            } => match input.volatility {
+                Some(volatility) if volatility > 0.0 => {
+                    input.equity * (target / volatility).min(max_leverage)
+                }
+                _ => 0.0,
+            },
+        };
+
+        let quantity = notional.max(0.0) * input.confidence.clamp(0.0, 1.0) / input.price;
+        if !quantity.is_finite() {
+            return 0.0;
+        }
+        if input.fractional {
+            quantity
+        } else {
+            quantity.floor()
+        }
+    }
+}
+
+/// Fraction of equity the Kelly criterion allocates given the probability of a win and the ratio of the
+/// average win to the average loss. Zero when there is no edge.
+pub fn kelly_fraction(win_rate: f64, payoff: f64) -> f64 {
+    if payoff <= 0.0 || !(0.0..=1.0).contains(&win_rate) {
+        return 0.0;
+    }
+    (win_rate - (1.0 - win_rate) / payoff).max(0.0)
+}
+
+/// Win rate and payoff ratio of a set of per-trade returns, ready for `Sizing::Kelly`. `None` without
+/// both wins and losses.
+pub fn win_rate_and_payoff(returns: &[f64]) -> Option<(f64, f64)> {
+    let wins: Vec<f64> = returns.iter().copied().filter(|r| *r > 0.0).collect();
+    let losses: Vec<f64> = returns.iter().copied().filter(|r| *r < 0.0).collect();
+    if wins.is_empty() || losses.is_empty() {
+        return None;
+    }
+    let average_win = wins.iter().sum::<f64>() / wins.len() as f64;
+    let average_loss = -losses.iter().sum::<f64>() / losses.len() as f64;
+    Some((
+        wins.len() as f64 / returns.len() as f64,
+        average_win / average_loss,
+    ))
+}
+
+/// Annualized standard deviation of log returns between consecutive closes, e.g. with 252 periods per year
+/// for daily bars. `None` with fewer than three closes.
+pub fn realized_volatility(closes: &[f64], periods_per_year: f64) -> Option<f64> {
+    if closes.len() < 3 || closes.iter().any(|close| *close <= 0.0) {
+        return None;
+    }
+    let returns: Vec<f64> = closes.windows(2).map(|w| (w[1] / w[0]).ln()).collect();
+    let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+    let variance =
+        returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (returns.len() - 1) as f64;
+    Some((variance * periods_per_year).sqrt())
+}
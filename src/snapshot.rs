@@ -0,0 +1,249 @@
+use crate::{
+    datastructures::{
+        client::TradingClient,
+        order::{OrderResponse, OrderSide},
+    },
+    store::{OrderQuery, OrderStore},
+    time,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    error::Error,
+    fmt, fs, io,
+    path::Path,
+};
+
+/// Relative change in cost basis below which a quantity change without fills is treated as a corporate action.
+const COST_BASIS_TOLERANCE: f64 = 0.01;
+/// Quantities and amounts closer than this are considered equal.
+const EPSILON: f64 = 1e-6;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotPosition {
+    /// Negative for shorts.
+    pub qty: f64,
+    pub avg_entry_price: f64,
+}
+
+/// Account cash and positions at a point in time, e.g. the session close.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortfolioSnapshot {
+    pub taken_at: String,
+    pub cash: f64,
+    pub equity: f64,
+    pub positions: BTreeMap<String, SnapshotPosition>,
+}
+
+impl PortfolioSnapshot {
+    pub async fn capture<C: TradingClient>(
+        client: &C,
+    ) -> Result<PortfolioSnapshot, Box<dyn Error>> {
+        let account = client.get_account().await?;
+        let positions = client.get_positions().await?;
+        Ok(PortfolioSnapshot {
+            taken_at: time::format_rfc3339(time::now_nanos()),
+            cash: account.cash,
+            equity: account.equity,
+            positions: positions
+                .into_iter()
+                .map(|position| {
+                    (
+                        position.symbol,
+                        SnapshotPosition {
+                            qty: position.qty,
+                            avg_entry_price: position.avg_entry_price,
+                        },
+                    )
+                })
+                .collect(),
+        })
+    }
+
+    /// Overwrites the file at `path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)
+    }
+
+    /// `None` if no snapshot has been saved at `path` yet.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Option<PortfolioSnapshot>> {
+        match fs::read(path) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// Fully accounted for by the bot's own fills.
+    Traded,
+    /// Quantity changed without fills while the cost basis held, as in a split or stock dividend.
+    CorporateAction,
+    /// Not explained by the bot's fills, e.g. a manual trade, an assignment or a transfer.
+    External,
+}
+
+#[derive(Debug, Clone)]
+pub struct PositionChange {
+    pub symbol: String,
+    pub before: f64,
+    pub after: f64,
+    /// Net quantity the bot's fills account for.
+    pub traded: f64,
+    pub kind: ChangeKind,
+}
+
+impl PositionChange {
+    pub fn unexplained(&self) -> f64 {
+        self.after - self.before - self.traded
+    }
+}
+
+/// Differences between two snapshots, separating what the bot did from everything else.
+#[derive(Debug, Clone)]
+pub struct SnapshotDiff {
+    pub from: String,
+    pub to: String,
+    pub position_changes: Vec<PositionChange>,
+    pub cash_change: f64,
+    /// Cash the bot's fills account for.
+    pub traded_cash: f64,
+}
+
+impl SnapshotDiff {
+    /// Compares two snapshots given the orders the bot filled between them.
+    pub fn compute(
+        before: &PortfolioSnapshot,
+        after: &PortfolioSnapshot,
+        orders: &[OrderResponse],
+    ) -> SnapshotDiff {
+        let mut traded: HashMap<&str, f64> = HashMap::new();
+        let mut traded_cash = 0.0;
+        for order in orders {
+            if order.filled_qty == 0.0 {
+                continue;
+            }
+            let signed = match order.side {
+                OrderSide::Buy => order.filled_qty,
+                OrderSide::Sell => -order.filled_qty,
+            };
+            *traded.entry(order.symbol.as_str()).or_default() += signed;
+            traded_cash -= signed * order.filled_avg_price.unwrap_or_default();
+        }
+
+        let symbols: BTreeSet<&str> = before
+            .positions
+            .keys()
+            .chain(after.positions.keys())
+            .map(String::as_str)
+            .chain(traded.keys().copied())
+            .collect();
+
+        let mut position_changes = Vec::new();
+        for symbol in symbols {
+            let before_position = before.positions.get(symbol);
+            let after_position = after.positions.get(symbol);
+            let quantity = |position: Option<&SnapshotPosition>| position.map_or(0.0, |p| p.qty);
+            let change = PositionChange {
+                symbol: symbol.to_string(),
+                before: quantity(before_position),
+                after: quantity(after_position),
+                traded: traded.get(symbol).copied().unwrap_or_default(),
+                kind: ChangeKind::Traded,
+            };
+            if change.before == change.after && change.traded == 0.0 {
+                continue;
+            }
+
+            let kind = if change.unexplained().abs() <= EPSILON {
+                ChangeKind::Traded
+            } else if change.traded == 0.0 && cost_basis_held(before_position, after_position) {
+                ChangeKind::CorporateAction
+            } else {
+                ChangeKind::External
+            };
+            position_changes.push(PositionChange { kind, ..change });
+        }
+
+        SnapshotDiff {
+            from: before.taken_at.clone(),
+            to: after.taken_at.clone(),
+            position_changes,
+            cash_change: after.cash - before.cash,
+            traded_cash,
+        }
+    }
+
+    /// Fetches the current snapshot and the bot's orders since `before` from `store`, then compares them.
+    /// Orders submitted before the earlier snapshot that filled afterwards are not counted.
+    pub async fn since<C: TradingClient>(
+        client: &C,
+        store: &dyn OrderStore,
+        before: &PortfolioSnapshot,
+    ) -> Result<(PortfolioSnapshot, SnapshotDiff), Box<dyn Error>> {
+        let after = PortfolioSnapshot::capture(client).await?;
+        let orders: Vec<OrderResponse> = store
+            .history(&OrderQuery {
+                since: Some(before.taken_at.clone()),
+                ..OrderQuery::default()
+            })
+            .await?
+            .into_iter()
+            .map(|stored| stored.order)
+            .collect();
+        let diff = SnapshotDiff::compute(before, &after, &orders);
+        Ok((after, diff))
+    }
+
+    /// Cash movement the bot's fills do not explain: deposits, withdrawals, dividends, fees.
+    pub fn unexplained_cash(&self) -> f64 {
+        self.cash_change - self.traded_cash
+    }
+
+    /// Changes the bot did not make itself.
+    pub fn flagged(&self) -> impl Iterator<Item = &PositionChange> {
+        self.position_changes
+            .iter()
+            .filter(|change| change.kind != ChangeKind::Traded)
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.flagged().next().is_none() && self.unexplained_cash().abs() <= EPSILON
+    }
+}
+
+impl fmt::Display for SnapshotDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Changes from {} to {}", self.from, self.to)?;
+        for change in &self.position_changes {
+            let flag = match change.kind {
+                ChangeKind::Traded => "",
+                ChangeKind::CorporateAction => "  [corporate action]",
+                ChangeKind::External => "  [external]",
+            };
+            writeln!(
+                f,
+                "  {:<8} {:>12} -> {:<12} traded {:>12}{}",
+                change.symbol, change.before, change.after, change.traded, flag
+            )?;
+        }
+        write!(
+            f,
+            "Cash: {:+.2} ({:+.2} from fills, {:+.2} unexplained)",
+            self.cash_change,
+            self.traded_cash,
+            self.unexplained_cash()
+        )
+    }
+}
+
+fn cost_basis_held(before: Option<&SnapshotPosition>, after: Option<&SnapshotPosition>) -> bool {
+    let (Some(before), Some(after)) = (before, after) else {
+        return false;
+    };
+    let before_cost = before.qty * before.avg_entry_price;
+    let after_cost = after.qty * after.avg_entry_price;
+    before_cost != 0.0 && ((after_cost - before_cost) / before_cost).abs() <= COST_BASIS_TOLERANCE
+}
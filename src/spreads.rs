@@ -0,0 +1,192 @@
+use crate::{datastructures::event::EventType, stream::EventBus};
+use serde::Serialize;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, RwLock},
+};
+use tokio::task::JoinHandle;
+
+/// Observations kept per symbol by default, oldest dropped first.
+const DEFAULT_CAPACITY: usize = 10_000;
+
+/// Distribution of a symbol's quoted spread, in basis points of the mid.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SpreadStats {
+    pub symbol: String,
+    pub observations: usize,
+    pub latest: f64,
+    pub min: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub p90: f64,
+    pub max: f64,
+}
+
+/// How to work an order given where the current spread sits in its distribution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tactic {
+    /// The spread is wide for the symbol; rest at the near touch and wait to be filled.
+    Passive,
+    /// The spread is at or below its median; crossing it is cheap.
+    Aggressive,
+}
+
+impl SpreadStats {
+    /// Tactic for an order placed while the spread is `spread_bps`.
+    pub fn tactic(&self, spread_bps: f64) -> Tactic {
+        if spread_bps <= self.median {
+            Tactic::Aggressive
+        } else {
+            Tactic::Passive
+        }
+    }
+
+    /// Cost in basis points expressed in median spreads, to compare fill quality across symbols. `None` for a
+    /// symbol with a zero median spread.
+    pub fn normalize(&self, cost_bps: f64) -> Option<f64> {
+        (self.median > 0.0).then(|| cost_bps / self.median)
+    }
+}
+
+/// Per-symbol history of quoted spreads, fed from quote events.
+#[derive(Clone)]
+pub struct SpreadTracker {
+    capacity: usize,
+    spreads: Arc<RwLock<HashMap<String, VecDeque<f64>>>>,
+}
+
+impl Default for SpreadTracker {
+    fn default() -> Self {
+        SpreadTracker::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+impl SpreadTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps at most `capacity` observations per symbol.
+    pub fn with_capacity(capacity: usize) -> Self {
+        SpreadTracker {
+            capacity: capacity.max(1),
+            spreads: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Records the spread of a quote event. Other events, and one-sided or crossed quotes, are ignored.
+    pub fn update(&self, event: &EventType) {
+        if let EventType::Quote {
+            symbol,
+            bid_price,
+            ask_price,
+            ..
+        } = event
+        {
+            self.record(symbol, *bid_price, *ask_price);
+        }
+    }
+
+    pub fn record(&self, symbol: &str, bid_price: f64, ask_price: f64) {
+        if bid_price <= 0.0 || ask_price < bid_price {
+            return;
+        }
+        let mid = (bid_price + ask_price) / 2.0;
+        let bps = (ask_price - bid_price) / mid * 10_000.0;
+
+        let mut spreads = self.spreads.write().unwrap();
+        let history = match spreads.get_mut(symbol) {
+            Some(history) => history,
+            None => spreads.entry(symbol.to_string()).or_default(),
+        };
+        if history.len() == self.capacity {
+            history.pop_front();
+        }
+        history.push_back(bps);
+    }
+
+    pub fn stats(&self, symbol: &str) -> Option<SpreadStats> {
+        let spreads = self.spreads.read().unwrap();
+        let history = spreads.get(symbol).filter(|history| !history.is_empty())?;
+        let mut sorted: Vec<f64> = history.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+        Some(SpreadStats {
+            symbol: symbol.to_string(),
+            observations: sorted.len(),
+            latest: *history.back()?,
+            min: sorted[0],
+            mean: sorted.iter().sum::<f64>() / sorted.len() as f64,
+            median: percentile(&sorted, 0.5),
+            p90: percentile(&sorted, 0.9),
+            max: sorted[sorted.len() - 1],
+        })
+    }
+
+    /// Spread at percentile `p`, from 0 to 1, in basis points.
+    pub fn percentile(&self, symbol: &str, p: f64) -> Option<f64> {
+        let spreads = self.spreads.read().unwrap();
+        let mut sorted: Vec<f64> = spreads.get(symbol)?.iter().copied().collect();
+        if sorted.is_empty() {
+            return None;
+        }
+        sorted.sort_by(f64::total_cmp);
+        Some(percentile(&sorted, p))
+    }
+
+    /// Tactic for the symbol at its latest spread. `None` before any quote.
+    pub fn tactic(&self, symbol: &str) -> Option<Tactic> {
+        let stats = self.stats(symbol)?;
+        Some(stats.tactic(stats.latest))
+    }
+
+    pub fn symbols(&self) -> Vec<String> {
+        let mut symbols: Vec<String> = self.spreads.read().unwrap().keys().cloned().collect();
+        symbols.sort();
+        symbols
+    }
+
+    /// Forgets every observation, e.g. at the start of a new session.
+    pub fn reset(&self) {
+        self.spreads.write().unwrap().clear();
+    }
+
+    /// Records spreads from a bus subscription until the returned handle is dropped or the stream ends.
+    pub fn follow(&self, bus: &EventBus) -> SpreadFeed {
+        let mut subscriber = bus.subscribe();
+        let tracker = self.clone();
+        SpreadFeed {
+            task: tokio::spawn(async move {
+                while let Some(batch) = subscriber.recv_batch().await {
+                    for event in batch.iter() {
+                        tracker.update(event);
+                    }
+                }
+            }),
+        }
+    }
+}
+
+/// Linear interpolation between the closest ranks of sorted, non-empty values.
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = p.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let (low, high) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[low] + (sorted[high] - sorted[low]) * (rank - low as f64)
+}
+
+/// Background task feeding a `SpreadTracker`. Stops when dropped.
+pub struct SpreadFeed {
+    task: JoinHandle<()>,
+}
+
+impl SpreadFeed {
+    /// Whether the stream has ended.
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+impl Drop for SpreadFeed {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -0,0 +1,505 @@
+use crate::{
+    datastructures::order::{OrderResponse, OrderStatus},
+    shutdown::Sink,
+    time,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use tokio::sync::{mpsc, oneshot};
+
+#[cfg(feature = "postgres")]
+mod postgres;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(feature = "postgres")]
+pub use postgres::{PostgresConfig, PostgresError, PostgresOrderStore, SslMode};
+#[cfg(feature = "sqlite")]
+pub use sqlite::{SqliteError, SqliteOrderStore};
+
+/// Latest known state of an order along with when this process first and last saw it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredOrder {
+    pub order: OrderResponse,
+    pub submitted_at: String,
+    pub updated_at: String,
+}
+
+/// Filter for `OrderStore::history`. The default matches every order.
+#[derive(Debug, Clone, Default)]
+pub struct OrderQuery {
+    pub symbol: Option<String>,
+    pub status: Option<OrderStatus>,
+    /// Only orders submitted at or after this RFC 3339 timestamp.
+    pub since: Option<String>,
+    /// Keep only the most recent `limit` matches.
+    pub limit: Option<usize>,
+}
+
+impl OrderQuery {
+    pub fn matches(&self, stored: &StoredOrder) -> bool {
+        let since = self.since.as_deref().and_then(time::parse_rfc3339);
+        self.symbol
+            .as_ref()
+            .is_none_or(|symbol| *symbol == stored.order.symbol)
+            && self
+                .status
+                .is_none_or(|status| status == stored.order.status)
+            && since.is_none_or(|since| {
+                time::parse_rfc3339(&stored.submitted_at).is_some_and(|at| at >= since)
+            })
+    }
+}
+
+/// Persistence for the orders a client submits and observes. When configured, the client records each
+/// accepted order and every state change it sees afterwards, e.g. from `get_order` or `cancel_order`.
+#[async_trait]
+pub trait OrderStore: Send + Sync {
+    /// Called once the broker has accepted an order.
+    async fn record_submission(&self, order: &OrderResponse) -> Result<(), Box<dyn Error>>;
+    /// Called whenever the client reads an order back from the broker. Unchanged orders may be ignored.
+    async fn record_update(&self, order: &OrderResponse) -> Result<(), Box<dyn Error>>;
+    /// Matching orders, oldest submission first.
+    async fn history(&self, query: &OrderQuery) -> Result<Vec<StoredOrder>, Box<dyn Error>>;
+}
+
+/// Passes the latest state of an order to the client's order store, if one is configured. Failures are logged
+/// rather than returned so bookkeeping never masks the broker's answer.
+#[cfg(any(
+    feature = "alpaca",
+    feature = "binance",
+    feature = "gemini",
+    feature = "kraken",
+    feature = "schwab"
+))]
+pub(crate) async fn record_logged(store: &Option<Arc<dyn OrderStore>>, order: &OrderResponse) {
+    if let Some(store) = store {
+        if let Err(e) = store.record_update(order).await {
+            tracing::error!(error = %e, order_id = %order.id, "Failed to store order update");
+        }
+    }
+}
+
+/// Orders in submission order, indexed by broker order id.
+#[derive(Default)]
+struct OrderTable {
+    orders: Vec<StoredOrder>,
+    index: HashMap<String, usize>,
+}
+
+impl OrderTable {
+    /// Inserts or updates the order. Returns the stored row if anything changed.
+    fn apply(&mut self, order: &OrderResponse) -> Option<&StoredOrder> {
+        let now = time::format_rfc3339(time::now_nanos());
+        match self.index.get(&order.id) {
+            Some(&position) => {
+                let stored = &mut self.orders[position];
+                if stored.order.status == order.status
+                    && stored.order.filled_qty == order.filled_qty
+                {
+                    return None;
+                }
+                let metadata = std::mem::take(&mut stored.order.metadata);
+                stored.order = order.clone();
+                if stored.order.metadata.is_empty() {
+                    stored.order.metadata = metadata;
+                }
+                stored.updated_at = now;
+                Some(stored)
+            }
+            None => {
+                self.insert(StoredOrder {
+                    order: order.clone(),
+                    submitted_at: now.clone(),
+                    updated_at: now,
+                });
+                self.orders.last()
+            }
+        }
+    }
+
+    /// Adds or replaces a row as-is, e.g. when replaying a file.
+    fn insert(&mut self, stored: StoredOrder) {
+        match self.index.get(&stored.order.id) {
+            Some(&position) => self.orders[position] = stored,
+            None => {
+                self.index
+                    .insert(stored.order.id.clone(), self.orders.len());
+                self.orders.push(stored);
+            }
+        }
+    }
+
+    fn query(&self, query: &OrderQuery) -> Vec<StoredOrder> {
+        let matches: Vec<&StoredOrder> = self
+            .orders
+            .iter()
+            .filter(|stored| query.matches(stored))
+            .collect();
+        let skip = query
+            .limit
+            .map_or(0, |limit| matches.len().saturating_sub(limit));
+        matches.into_iter().skip(skip).cloned().collect()
+    }
+}
+
+/// Keeps order history for the lifetime of the process.
+#[derive(Default)]
+pub struct MemoryOrderStore {
+    table: Mutex<OrderTable>,
+}
+
+impl MemoryOrderStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl OrderStore for MemoryOrderStore {
+    async fn record_submission(&self, order: &OrderResponse) -> Result<(), Box<dyn Error>> {
+        self.table.lock().unwrap().apply(order);
+        Ok(())
+    }
+
+    async fn record_update(&self, order: &OrderResponse) -> Result<(), Box<dyn Error>> {
+        self.table.lock().unwrap().apply(order);
+        Ok(())
+    }
+
+    async fn history(&self, query: &OrderQuery) -> Result<Vec<StoredOrder>, Box<dyn Error>> {
+        Ok(self.table.lock().unwrap().query(query))
+    }
+}
+
+/// Append-only JSONL log of order states. Every change is written as a full row, and the latest row per
+/// order wins when the file is reopened.
+pub struct FileOrderStore {
+    table: Mutex<OrderTable>,
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl FileOrderStore {
+    /// Opens or creates the log at `path`, loading any history already in it.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let mut table = OrderTable::default();
+        if path.exists() {
+            for line in BufReader::new(File::open(path)?).lines() {
+                let line = line?;
+                if !line.trim().is_empty() {
+                    table.insert(serde_json::from_str(&line)?);
+                }
+            }
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(FileOrderStore {
+            table: Mutex::new(table),
+            writer: Mutex::new(BufWriter::new(file)),
+        })
+    }
+
+    fn write(&self, order: &OrderResponse) -> Result<(), Box<dyn Error>> {
+        let mut table = self.table.lock().unwrap();
+        if let Some(stored) = table.apply(order) {
+            let mut writer = self.writer.lock().unwrap();
+            serde_json::to_writer(&mut *writer, stored)?;
+            writer.write_all(b"\n")?;
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl OrderStore for FileOrderStore {
+    async fn record_submission(&self, order: &OrderResponse) -> Result<(), Box<dyn Error>> {
+        self.write(order)
+    }
+
+    async fn record_update(&self, order: &OrderResponse) -> Result<(), Box<dyn Error>> {
+        self.write(order)
+    }
+
+    async fn history(&self, query: &OrderQuery) -> Result<Vec<StoredOrder>, Box<dyn Error>> {
+        Ok(self.table.lock().unwrap().query(query))
+    }
+}
+
+#[async_trait]
+impl Sink for FileOrderStore {
+    /// Rows are flushed as they are written, so this only syncs the file.
+    async fn flush_and_close(&self) -> Result<(), Box<dyn Error>> {
+        let mut writer = self.writer.lock().unwrap();
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        Ok(())
+    }
+}
+
+enum StoreWrite {
+    Submission(OrderResponse),
+    Update(OrderResponse),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Moves writes to another store onto a background task so slow backends never delay order submission.
+/// Writes are applied in order; `history` waits for queued writes first so reads see them.
+/// Must be created inside a Tokio runtime. Queued writes are still applied after the store is dropped.
+pub struct WriteBehindStore {
+    inner: Arc<dyn OrderStore>,
+    sender: mpsc::Sender<StoreWrite>,
+}
+
+impl WriteBehindStore {
+    /// `capacity` bounds the queue; callers only wait once that many writes are pending.
+    pub fn new(inner: Arc<dyn OrderStore>, capacity: usize) -> Self {
+        let (sender, mut receiver) = mpsc::channel(capacity.max(1));
+        let writer = inner.clone();
+        tokio::spawn(async move {
+            while let Some(write) = receiver.recv().await {
+                let result = match &write {
+                    StoreWrite::Submission(order) => writer.record_submission(order).await,
+                    StoreWrite::Update(order) => writer.record_update(order).await,
+                    StoreWrite::Flush(_) => Ok(()),
+                }
+                .map_err(|e| e.to_string());
+                match write {
+                    StoreWrite::Flush(done) => {
+                        let _ = done.send(());
+                    }
+                    StoreWrite::Submission(order) | StoreWrite::Update(order) => {
+                        if let Err(e) = result {
+                            tracing::error!(error = %e, order_id = %order.id, "Deferred order store write failed");
+                        }
+                    }
+                }
+            }
+        });
+
+        WriteBehindStore { inner, sender }
+    }
+
+    /// Waits until every write queued so far has been applied.
+    pub async fn flush(&self) {
+        let (done, applied) = oneshot::channel();
+        if self.sender.send(StoreWrite::Flush(done)).await.is_ok() {
+            let _ = applied.await;
+        }
+    }
+
+    async fn enqueue(&self, write: StoreWrite) -> Result<(), Box<dyn Error>> {
+        self.sender
+            .send(write)
+            .await
+            .map_err(|_| "Order store writer has stopped".into())
+    }
+}
+
+#[async_trait]
+impl OrderStore for WriteBehindStore {
+    async fn record_submission(&self, order: &OrderResponse) -> Result<(), Box<dyn Error>> {
+        self.enqueue(StoreWrite::Submission(order.clone())).await
+    }
+
+    async fn record_update(&self, order: &OrderResponse) -> Result<(), Box<dyn Error>> {
+        self.enqueue(StoreWrite::Update(order.clone())).await
+    }
+
+    async fn history(&self, query: &OrderQuery) -> Result<Vec<StoredOrder>, Box<dyn Error>> {
+        self.flush().await;
+        self.inner.history(query).await
+    }
+}
+
+#[async_trait]
+impl Sink for WriteBehindStore {
+    /// Waits for the queued writes to reach the inner store. Register the inner store too if it buffers.
+    async fn flush_and_close(&self) -> Result<(), Box<dyn Error>> {
+        self.flush().await;
+        Ok(())
+    }
+}
+
+/// Key-value persistence for state that is not order history, e.g. what strategies keep through
+/// `StrategyState`. Values are JSON.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    async fn get(&self, key: &str) -> Result<Option<Value>, Box<dyn Error>>;
+    /// Replaces any value already stored under the key.
+    async fn put(&self, key: &str, value: Value) -> Result<(), Box<dyn Error>>;
+    /// Removing a missing key is not an error.
+    async fn delete(&self, key: &str) -> Result<(), Box<dyn Error>>;
+    /// Keys starting with `prefix`, sorted.
+    async fn keys(&self, prefix: &str) -> Result<Vec<String>, Box<dyn Error>>;
+}
+
+fn keys_with_prefix(values: &BTreeMap<String, Value>, prefix: &str) -> Vec<String> {
+    values
+        .range(prefix.to_string()..)
+        .map(|(key, _)| key)
+        .take_while(|key| key.starts_with(prefix))
+        .cloned()
+        .collect()
+}
+
+/// Keeps values for the lifetime of the process.
+#[derive(Default)]
+pub struct MemoryStorage {
+    values: Mutex<BTreeMap<String, Value>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl Storage for MemoryStorage {
+    async fn get(&self, key: &str) -> Result<Option<Value>, Box<dyn Error>> {
+        Ok(self.values.lock().unwrap().get(key).cloned())
+    }
+
+    async fn put(&self, key: &str, value: Value) -> Result<(), Box<dyn Error>> {
+        self.values.lock().unwrap().insert(key.to_string(), value);
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), Box<dyn Error>> {
+        self.values.lock().unwrap().remove(key);
+        Ok(())
+    }
+
+    async fn keys(&self, prefix: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        Ok(keys_with_prefix(&self.values.lock().unwrap(), prefix))
+    }
+}
+
+/// One line of a `FileStorage` log. A missing value records a delete.
+#[derive(Serialize, Deserialize)]
+struct StoredValue {
+    key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    value: Option<Value>,
+}
+
+/// Append-only JSONL log of writes. The latest line per key wins when the file is reopened; `compact`
+/// rewrites the file with only the current values.
+pub struct FileStorage {
+    path: PathBuf,
+    values: Mutex<BTreeMap<String, Value>>,
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl FileStorage {
+    /// Opens or creates the log at `path`, loading the values already in it.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let mut values = BTreeMap::new();
+        if path.exists() {
+            for line in BufReader::new(File::open(path)?).lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let stored: StoredValue = serde_json::from_str(&line)?;
+                match stored.value {
+                    Some(value) => values.insert(stored.key, value),
+                    None => values.remove(&stored.key),
+                };
+            }
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(FileStorage {
+            path: path.to_path_buf(),
+            values: Mutex::new(values),
+            writer: Mutex::new(BufWriter::new(file)),
+        })
+    }
+
+    /// Rewrites the log with one line per current value, dropping overwritten values and deletes.
+    pub fn compact(&self) -> Result<(), Box<dyn Error>> {
+        let values = self.values.lock().unwrap();
+        let mut writer = self.writer.lock().unwrap();
+        let temporary = self.path.with_extension("compact");
+        let mut compacted = BufWriter::new(File::create(&temporary)?);
+        for (key, value) in values.iter() {
+            serde_json::to_writer(
+                &mut compacted,
+                &StoredValue {
+                    key: key.clone(),
+                    value: Some(value.clone()),
+                },
+            )?;
+            compacted.write_all(b"\n")?;
+        }
+        compacted.flush()?;
+        drop(compacted);
+        fs::rename(&temporary, &self.path)?;
+        *writer = BufWriter::new(OpenOptions::new().append(true).open(&self.path)?);
+        Ok(())
+    }
+
+    fn write(&self, key: &str, value: Option<Value>) -> Result<(), Box<dyn Error>> {
+        let mut values = self.values.lock().unwrap();
+        let mut writer = self.writer.lock().unwrap();
+        serde_json::to_writer(
+            &mut *writer,
+            &StoredValue {
+                key: key.to_string(),
+                value: value.clone(),
+            },
+        )?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        match value {
+            Some(value) => values.insert(key.to_string(), value),
+            None => values.remove(key),
+        };
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Storage for FileStorage {
+    async fn get(&self, key: &str) -> Result<Option<Value>, Box<dyn Error>> {
+        Ok(self.values.lock().unwrap().get(key).cloned())
+    }
+
+    async fn put(&self, key: &str, value: Value) -> Result<(), Box<dyn Error>> {
+        self.write(key, Some(value))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), Box<dyn Error>> {
+        if self.values.lock().unwrap().contains_key(key) {
+            self.write(key, None)?;
+        }
+        Ok(())
+    }
+
+    async fn keys(&self, prefix: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        Ok(keys_with_prefix(&self.values.lock().unwrap(), prefix))
+    }
+}
+
+#[async_trait]
+impl Sink for FileStorage {
+    /// Writes are flushed as they are made, so this only syncs the file.
+    async fn flush_and_close(&self) -> Result<(), Box<dyn Error>> {
+        let mut writer = self.writer.lock().unwrap();
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,702 @@
+use super::{OrderQuery, OrderStore, StoredOrder};
+use crate::{
+    datastructures::order::{OrderResponse, OrderStatus},
+    shutdown::Sink,
+    time,
+};
+use async_trait::async_trait;
+use std::{
+    error::Error,
+    ffi::{c_char, c_int, c_void, CStr, CString},
+    fmt,
+    path::Path,
+    ptr,
+    sync::{Arc, Mutex},
+};
+
+/// The subset of the SQLite C API the store uses.
+mod ffi {
+    use super::{c_char, c_int, c_void};
+
+    #[repr(C)]
+    pub struct Sqlite3 {
+        _private: [u8; 0],
+    }
+
+    #[repr(C)]
+    pub struct Stmt {
+        _private: [u8; 0],
+    }
+
+    pub const OK: c_int = 0;
+    pub const ROW: c_int = 100;
+    pub const DONE: c_int = 101;
+    pub const NULL: c_int = 5;
+    pub const OPEN_READWRITE: c_int = 0x2;
+    pub const OPEN_CREATE: c_int = 0x4;
+    pub const OPEN_FULLMUTEX: c_int = 0x10000;
+    /// `SQLITE_TRANSIENT`: SQLite copies bound values before the call returns.
+    pub const TRANSIENT: isize = -1;
+
+    #[link(name = "sqlite3")]
+    extern "C" {
+        pub fn sqlite3_open_v2(
+            filename: *const c_char,
+            db: *mut *mut Sqlite3,
+            flags: c_int,
+            vfs: *const c_char,
+        ) -> c_int;
+        pub fn sqlite3_close_v2(db: *mut Sqlite3) -> c_int;
+        pub fn sqlite3_errmsg(db: *mut Sqlite3) -> *const c_char;
+        pub fn sqlite3_busy_timeout(db: *mut Sqlite3, ms: c_int) -> c_int;
+        pub fn sqlite3_exec(
+            db: *mut Sqlite3,
+            sql: *const c_char,
+            callback: *const c_void,
+            argument: *mut c_void,
+            error: *mut *mut c_char,
+        ) -> c_int;
+        pub fn sqlite3_prepare_v2(
+            db: *mut Sqlite3,
+            sql: *const c_char,
+            bytes: c_int,
+            statement: *mut *mut Stmt,
+            tail: *mut *const c_char,
+        ) -> c_int;
+        pub fn sqlite3_finalize(statement: *mut Stmt) -> c_int;
+        pub fn sqlite3_step(statement: *mut Stmt) -> c_int;
+        pub fn sqlite3_bind_null(statement: *mut Stmt, index: c_int) -> c_int;
+        pub fn sqlite3_bind_int64(statement: *mut Stmt, index: c_int, value: i64) -> c_int;
+        pub fn sqlite3_bind_double(statement: *mut Stmt, index: c_int, value: f64) -> c_int;
+        pub fn sqlite3_bind_text(
+            statement: *mut Stmt,
+            index: c_int,
+            text: *const c_char,
+            bytes: c_int,
+            destructor: isize,
+        ) -> c_int;
+        pub fn sqlite3_column_type(statement: *mut Stmt, column: c_int) -> c_int;
+        pub fn sqlite3_column_int64(statement: *mut Stmt, column: c_int) -> i64;
+        pub fn sqlite3_column_double(statement: *mut Stmt, column: c_int) -> f64;
+        pub fn sqlite3_column_text(statement: *mut Stmt, column: c_int) -> *const u8;
+        pub fn sqlite3_column_bytes(statement: *mut Stmt, column: c_int) -> c_int;
+    }
+}
+
+/// An error reported by SQLite, with its result code.
+#[derive(Debug, Clone)]
+pub struct SqliteError {
+    pub code: i32,
+    pub message: String,
+}
+
+impl fmt::Display for SqliteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SQLite error {}: {}", self.code, self.message)
+    }
+}
+
+impl Error for SqliteError {}
+
+enum Param<'a> {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(&'a str),
+}
+
+struct Statement<'a> {
+    connection: &'a Connection,
+    raw: *mut ffi::Stmt,
+}
+
+impl Statement<'_> {
+    fn bind(&self, params: &[Param]) -> Result<(), SqliteError> {
+        for (index, param) in params.iter().enumerate() {
+            let index = index as c_int + 1;
+            // SAFETY: the statement is live and text is copied by SQLite before the call returns.
+            let code = unsafe {
+                match param {
+                    Param::Null => ffi::sqlite3_bind_null(self.raw, index),
+                    Param::Integer(value) => ffi::sqlite3_bind_int64(self.raw, index, *value),
+                    Param::Real(value) => ffi::sqlite3_bind_double(self.raw, index, *value),
+                    Param::Text(text) => ffi::sqlite3_bind_text(
+                        self.raw,
+                        index,
+                        text.as_ptr() as *const c_char,
+                        text.len() as c_int,
+                        ffi::TRANSIENT,
+                    ),
+                }
+            };
+            self.connection.check(code)?;
+        }
+        Ok(())
+    }
+
+    /// Whether a row is ready to read.
+    fn step(&self) -> Result<bool, SqliteError> {
+        // SAFETY: the statement is live.
+        match unsafe { ffi::sqlite3_step(self.raw) } {
+            ffi::ROW => Ok(true),
+            ffi::DONE => Ok(false),
+            code => Err(self.connection.error(code)),
+        }
+    }
+
+    fn text(&self, column: c_int) -> Option<String> {
+        // SAFETY: the statement is on a row, and the text stays valid until the next call on it.
+        unsafe {
+            if ffi::sqlite3_column_type(self.raw, column) == ffi::NULL {
+                return None;
+            }
+            let text = ffi::sqlite3_column_text(self.raw, column);
+            let bytes = ffi::sqlite3_column_bytes(self.raw, column) as usize;
+            Some(String::from_utf8_lossy(std::slice::from_raw_parts(text, bytes)).into_owned())
+        }
+    }
+
+    fn integer(&self, column: c_int) -> i64 {
+        // SAFETY: the statement is on a row.
+        unsafe { ffi::sqlite3_column_int64(self.raw, column) }
+    }
+
+    fn real(&self, column: c_int) -> f64 {
+        // SAFETY: the statement is on a row.
+        unsafe { ffi::sqlite3_column_double(self.raw, column) }
+    }
+}
+
+impl Drop for Statement<'_> {
+    fn drop(&mut self) {
+        // SAFETY: the statement is finalized once, here.
+        unsafe {
+            ffi::sqlite3_finalize(self.raw);
+        }
+    }
+}
+
+/// A connection to one database file.
+struct Connection {
+    raw: *mut ffi::Sqlite3,
+}
+
+// SAFETY: the connection is opened in serialized mode, so SQLite allows it to be used from any thread.
+unsafe impl Send for Connection {}
+
+impl Connection {
+    fn open(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let path = CString::new(path.to_str().ok_or("SQLite path is not valid UTF-8")?)?;
+        let mut raw = ptr::null_mut();
+        let flags = ffi::OPEN_READWRITE | ffi::OPEN_CREATE | ffi::OPEN_FULLMUTEX;
+        // SAFETY: the path is NUL-terminated and `raw` is written before it is read.
+        let code = unsafe { ffi::sqlite3_open_v2(path.as_ptr(), &mut raw, flags, ptr::null()) };
+        // A handle is returned even on failure and must still be closed.
+        let connection = Connection { raw };
+        connection.check(code)?;
+        // SAFETY: the connection is open.
+        unsafe {
+            ffi::sqlite3_busy_timeout(raw, BUSY_TIMEOUT_MS);
+        }
+        Ok(connection)
+    }
+
+    fn error(&self, code: c_int) -> SqliteError {
+        let message = if self.raw.is_null() {
+            "Out of memory".to_string()
+        } else {
+            // SAFETY: SQLite returns a NUL-terminated message that lives until the next call.
+            unsafe { CStr::from_ptr(ffi::sqlite3_errmsg(self.raw)) }
+                .to_string_lossy()
+                .into_owned()
+        };
+        SqliteError { code, message }
+    }
+
+    fn check(&self, code: c_int) -> Result<(), SqliteError> {
+        match code {
+            ffi::OK => Ok(()),
+            code => Err(self.error(code)),
+        }
+    }
+
+    /// Runs one or more statements that take no parameters.
+    fn batch(&self, sql: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let sql = CString::new(sql)?;
+        // SAFETY: the connection is open and the SQL is NUL-terminated.
+        let code = unsafe {
+            ffi::sqlite3_exec(
+                self.raw,
+                sql.as_ptr(),
+                ptr::null(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        };
+        Ok(self.check(code)?)
+    }
+
+    fn prepare(&self, sql: &str, params: &[Param]) -> Result<Statement<'_>, SqliteError> {
+        let mut raw = ptr::null_mut();
+        // SAFETY: the length is given, so the SQL needs no terminator, and `raw` is written before it is read.
+        let code = unsafe {
+            ffi::sqlite3_prepare_v2(
+                self.raw,
+                sql.as_ptr() as *const c_char,
+                sql.len() as c_int,
+                &mut raw,
+                ptr::null_mut(),
+            )
+        };
+        self.check(code)?;
+        let statement = Statement {
+            connection: self,
+            raw,
+        };
+        statement.bind(params)?;
+        Ok(statement)
+    }
+
+    fn execute(&self, sql: &str, params: &[Param]) -> Result<(), SqliteError> {
+        let statement = self.prepare(sql, params)?;
+        while statement.step()? {}
+        Ok(())
+    }
+
+    fn query<T>(
+        &self,
+        sql: &str,
+        params: &[Param],
+        mut row: impl FnMut(&Statement) -> Result<T, Box<dyn Error + Send + Sync>>,
+    ) -> Result<Vec<T>, Box<dyn Error + Send + Sync>> {
+        let statement = self.prepare(sql, params)?;
+        let mut rows = vec![];
+        while statement.step()? {
+            rows.push(row(&statement)?);
+        }
+        Ok(rows)
+    }
+
+    /// Runs `f` in a write transaction, committing if it succeeds and rolling back otherwise.
+    fn transaction<T>(
+        &self,
+        f: impl FnOnce(&Self) -> Result<T, Box<dyn Error + Send + Sync>>,
+    ) -> Result<T, Box<dyn Error + Send + Sync>> {
+        // Taking the write lock up front means another process cannot change the rows between our read and
+        // write.
+        self.batch("BEGIN IMMEDIATE")?;
+        match f(self).and_then(|value| self.batch("COMMIT").map(|_| value)) {
+            Ok(value) => Ok(value),
+            Err(e) => {
+                let _ = self.batch("ROLLBACK");
+                Err(e)
+            }
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        // SAFETY: every statement borrows the connection, so all are finalized by now.
+        unsafe {
+            ffi::sqlite3_close_v2(self.raw);
+        }
+    }
+}
+
+/// How long a write waits for another connection's lock before failing.
+const BUSY_TIMEOUT_MS: c_int = 5_000;
+
+/// Schema changes in order. A database at `user_version` n has had the first n applied.
+const MIGRATIONS: [&str; 1] = ["CREATE TABLE orders (
+        seq INTEGER PRIMARY KEY,
+        id TEXT NOT NULL UNIQUE,
+        client_order_id TEXT NOT NULL,
+        symbol TEXT NOT NULL,
+        status TEXT NOT NULL,
+        filled_qty REAL NOT NULL,
+        submitted_at TEXT NOT NULL,
+        submitted_nanos INTEGER NOT NULL,
+        updated_at TEXT NOT NULL,
+        body TEXT NOT NULL
+    );
+    CREATE INDEX orders_symbol ON orders (symbol, seq);
+    CREATE INDEX orders_status ON orders (status, seq);
+    CREATE INDEX orders_client_order_id ON orders (client_order_id);"];
+
+fn status_name(status: OrderStatus) -> Result<String, Box<dyn Error + Send + Sync>> {
+    match serde_json::to_value(status)? {
+        serde_json::Value::String(name) => Ok(name),
+        _ => Err("Order status did not serialize to a string".into()),
+    }
+}
+
+/// Order history in a SQLite database. Each write runs in its own transaction, so several processes can
+/// share one file; `symbol` and `status` queries use indexes. The schema is created or upgraded on open. Calls
+/// through `OrderStore` run on the blocking thread pool.
+pub struct SqliteOrderStore {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteOrderStore {
+    /// Opens or creates the database at `path`. ":memory:" keeps it in memory for the life of the store.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let connection = Connection::open(path.as_ref())?;
+        // Readers do not block the writer and vice versa. In-memory databases keep their own journal mode.
+        connection
+            .batch("PRAGMA journal_mode = WAL")
+            .map_err(|e| e as Box<dyn Error>)?;
+        connection
+            .transaction(|connection| {
+                let version =
+                    connection.query("PRAGMA user_version", &[], |row| Ok(row.integer(0)))?;
+                let version = version.first().copied().unwrap_or_default().max(0) as usize;
+                if version > MIGRATIONS.len() {
+                    return Err("Order database was written by a newer version".into());
+                }
+                for (applied, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+                    connection.batch(migration)?;
+                    connection.batch(&format!("PRAGMA user_version = {}", applied + 1))?;
+                }
+                Ok(())
+            })
+            .map_err(|e| e as Box<dyn Error>)?;
+        Ok(SqliteOrderStore {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    /// Runs `f` on a blocking thread, since a call can wait up to `BUSY_TIMEOUT_MS` for another process's lock
+    /// and must not hold up the runtime meanwhile.
+    async fn run<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&Connection) -> Result<T, Box<dyn Error + Send + Sync>> + Send + 'static,
+    ) -> Result<T, Box<dyn Error>> {
+        let connection = self.connection.clone();
+        tokio::task::spawn_blocking(move || f(&connection.lock().unwrap()))
+            .await?
+            .map_err(|e| e as Box<dyn Error>)
+    }
+
+    /// Inserts or updates the order, leaving unchanged orders alone as `MemoryOrderStore` does.
+    fn write(
+        connection: &Connection,
+        order: &OrderResponse,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let status = status_name(order.status)?;
+        connection.transaction(|connection| {
+            let now = time::now_nanos();
+            let at = time::format_rfc3339(now);
+            let existing = connection.query(
+                "SELECT status, filled_qty, body FROM orders WHERE id = ?1",
+                &[Param::Text(&order.id)],
+                |row| {
+                    let body = row.text(2).unwrap_or_default();
+                    let stored: OrderResponse = serde_json::from_str(&body)?;
+                    Ok((row.text(0).unwrap_or_default(), row.real(1), stored))
+                },
+            )?;
+            match existing.into_iter().next() {
+                Some((stored_status, filled_qty, _))
+                    if stored_status == status && filled_qty == order.filled_qty => {}
+                Some((_, _, stored)) => {
+                    let mut order = order.clone();
+                    if order.metadata.is_empty() {
+                        order.metadata = stored.metadata;
+                    }
+                    connection.execute(
+                        "UPDATE orders SET status = ?2, filled_qty = ?3, updated_at = ?4, body = ?5
+                         WHERE id = ?1",
+                        &[
+                            Param::Text(&order.id),
+                            Param::Text(&status),
+                            Param::Real(order.filled_qty),
+                            Param::Text(&at),
+                            Param::Text(&serde_json::to_string(&order)?),
+                        ],
+                    )?;
+                }
+                None => connection.execute(
+                    "INSERT INTO orders (id, client_order_id, symbol, status, filled_qty, submitted_at,
+                         submitted_nanos, updated_at, body)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?6, ?8)",
+                    &[
+                        Param::Text(&order.id),
+                        Param::Text(&order.client_order_id),
+                        Param::Text(&order.symbol),
+                        Param::Text(&status),
+                        Param::Real(order.filled_qty),
+                        Param::Text(&at),
+                        Param::Integer(now),
+                        Param::Text(&serde_json::to_string(order)?),
+                    ],
+                )?,
+            }
+            Ok(())
+        })
+    }
+
+    fn query(
+        connection: &Connection,
+        query: &OrderQuery,
+    ) -> Result<Vec<StoredOrder>, Box<dyn Error + Send + Sync>> {
+        let status = query.status.map(status_name).transpose()?;
+        let since = match &query.since {
+            Some(since) => {
+                Some(time::parse_rfc3339(since).ok_or("`since` is not an RFC 3339 timestamp")?)
+            }
+            None => None,
+        };
+        // The most recent `limit` matches, returned oldest first. A negative limit is no limit.
+        connection.query(
+            "SELECT submitted_at, updated_at, body FROM (
+                 SELECT seq, submitted_at, updated_at, body FROM orders
+                 WHERE (?1 IS NULL OR symbol = ?1)
+                     AND (?2 IS NULL OR status = ?2)
+                     AND (?3 IS NULL OR submitted_nanos >= ?3)
+                 ORDER BY seq DESC LIMIT ?4
+             ) ORDER BY seq",
+            &[
+                query.symbol.as_deref().map_or(Param::Null, Param::Text),
+                status.as_deref().map_or(Param::Null, Param::Text),
+                since.map_or(Param::Null, Param::Integer),
+                Param::Integer(query.limit.map_or(-1, |limit| limit as i64)),
+            ],
+            |row| {
+                Ok(StoredOrder {
+                    order: serde_json::from_str(&row.text(2).unwrap_or_default())?,
+                    submitted_at: row.text(0).unwrap_or_default(),
+                    updated_at: row.text(1).unwrap_or_default(),
+                })
+            },
+        )
+    }
+}
+
+#[async_trait]
+impl OrderStore for SqliteOrderStore {
+    async fn record_submission(&self, order: &OrderResponse) -> Result<(), Box<dyn Error>> {
+        let order = order.clone();
+        self.run(move |connection| SqliteOrderStore::write(connection, &order))
+            .await
+    }
+
+    async fn record_update(&self, order: &OrderResponse) -> Result<(), Box<dyn Error>> {
+        let order = order.clone();
+        self.run(move |connection| SqliteOrderStore::write(connection, &order))
+            .await
+    }
+
+    async fn history(&self, query: &OrderQuery) -> Result<Vec<StoredOrder>, Box<dyn Error>> {
+        let query = query.clone();
+        self.run(move |connection| SqliteOrderStore::query(connection, &query))
+            .await
+    }
+}
+
+#[async_trait]
+impl Sink for SqliteOrderStore {
+    /// Every write is committed as it is made, so this only checkpoints the write-ahead log into the file.
+    async fn flush_and_close(&self) -> Result<(), Box<dyn Error>> {
+        self.run(|connection| connection.batch("PRAGMA wal_checkpoint(TRUNCATE)"))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::path::PathBuf;
+
+    fn order(id: &str, symbol: &str, status: &str, filled_qty: f64) -> OrderResponse {
+        serde_json::from_value(json!({
+            "id": id,
+            "client_order_id": format!("client-{}", id),
+            "symbol": symbol,
+            "status": status,
+            "created_at": "2024-01-02T14:30:00Z",
+            "side": "buy",
+            "type": "market",
+            "qty": 10.0,
+            "filled_qty": filled_qty,
+        }))
+        .unwrap()
+    }
+
+    fn path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{}.db", name, std::process::id()));
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+        path
+    }
+
+    fn ids(orders: &[StoredOrder]) -> Vec<&str> {
+        orders
+            .iter()
+            .map(|stored| stored.order.id.as_str())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn records_and_updates_orders() {
+        let store = SqliteOrderStore::open(":memory:").unwrap();
+        store
+            .record_submission(&order("1", "AAPL", "new", 0.0))
+            .await
+            .unwrap();
+        let mut filled = order("1", "AAPL", "filled", 10.0);
+        filled.filled_avg_price = Some(190.25);
+        store.record_update(&filled).await.unwrap();
+
+        let history = store.history(&OrderQuery::default()).await.unwrap();
+        assert_eq!(ids(&history), ["1"]);
+        assert_eq!(history[0].order.status, OrderStatus::Filled);
+        assert_eq!(history[0].order.filled_qty, 10.0);
+        assert_eq!(history[0].order.filled_avg_price, Some(190.25));
+    }
+
+    #[tokio::test]
+    async fn keeps_metadata_the_broker_does_not_echo() {
+        let store = SqliteOrderStore::open(":memory:").unwrap();
+        let mut submitted = order("1", "AAPL", "new", 0.0);
+        submitted
+            .metadata
+            .insert("strategy".to_string(), "momentum".to_string());
+        store.record_submission(&submitted).await.unwrap();
+        store
+            .record_update(&order("1", "AAPL", "canceled", 0.0))
+            .await
+            .unwrap();
+
+        let history = store.history(&OrderQuery::default()).await.unwrap();
+        assert_eq!(history[0].order.status, OrderStatus::Canceled);
+        assert_eq!(
+            history[0]
+                .order
+                .metadata
+                .get("strategy")
+                .map(String::as_str),
+            Some("momentum")
+        );
+    }
+
+    #[tokio::test]
+    async fn queries_by_symbol_status_and_limit() {
+        let store = SqliteOrderStore::open(":memory:").unwrap();
+        for (id, symbol, status) in [
+            ("1", "AAPL", "filled"),
+            ("2", "MSFT", "new"),
+            ("3", "AAPL", "new"),
+            ("4", "AAPL", "filled"),
+        ] {
+            store
+                .record_submission(&order(id, symbol, status, 0.0))
+                .await
+                .unwrap();
+        }
+
+        let query =
+            |symbol: Option<&str>, status: Option<OrderStatus>, limit: Option<usize>| OrderQuery {
+                symbol: symbol.map(str::to_string),
+                status,
+                limit,
+                ..Default::default()
+            };
+        let history = store.history(&query(Some("AAPL"), None, None)).await;
+        assert_eq!(ids(&history.unwrap()), ["1", "3", "4"]);
+        let history = store
+            .history(&query(None, Some(OrderStatus::New), None))
+            .await;
+        assert_eq!(ids(&history.unwrap()), ["2", "3"]);
+        let history = store
+            .history(&query(Some("AAPL"), Some(OrderStatus::Filled), Some(1)))
+            .await;
+        assert_eq!(ids(&history.unwrap()), ["4"]);
+        let history = store.history(&query(None, None, Some(2))).await;
+        assert_eq!(ids(&history.unwrap()), ["3", "4"]);
+    }
+
+    #[tokio::test]
+    async fn queries_by_submission_time() {
+        let store = SqliteOrderStore::open(":memory:").unwrap();
+        store
+            .record_submission(&order("1", "AAPL", "new", 0.0))
+            .await
+            .unwrap();
+        let query = |since: &str| OrderQuery {
+            since: Some(since.to_string()),
+            ..Default::default()
+        };
+        let history = store.history(&query("2000-01-01T00:00:00Z")).await;
+        assert_eq!(ids(&history.unwrap()), ["1"]);
+        let history = store.history(&query("2200-01-01T00:00:00+01:00")).await;
+        assert!(history.unwrap().is_empty());
+        assert!(store.history(&query("yesterday")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn keeps_history_across_reopening() {
+        let path = path("sqlite-order-store");
+        {
+            let store = SqliteOrderStore::open(&path).unwrap();
+            store
+                .record_submission(&order("1", "AAPL", "new", 0.0))
+                .await
+                .unwrap();
+            store
+                .record_update(&order("1", "AAPL", "partially_filled", 4.0))
+                .await
+                .unwrap();
+            store.flush_and_close().await.unwrap();
+        }
+
+        let store = SqliteOrderStore::open(&path).unwrap();
+        let history = store.history(&OrderQuery::default()).await.unwrap();
+        assert_eq!(history[0].order.status, OrderStatus::PartiallyFilled);
+        assert_eq!(history[0].order.filled_qty, 4.0);
+        // Opening again does not rerun the migrations.
+        store
+            .record_submission(&order("2", "MSFT", "new", 0.0))
+            .await
+            .unwrap();
+        drop(store);
+        let store = SqliteOrderStore::open(&path).unwrap();
+        let history = store.history(&OrderQuery::default()).await.unwrap();
+        assert_eq!(ids(&history), ["1", "2"]);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn rolls_back_failed_writes() {
+        let store = SqliteOrderStore::open(":memory:").unwrap();
+        store
+            .record_submission(&order("1", "AAPL", "new", 0.0))
+            .await
+            .unwrap();
+        // Corrupt the stored body so the next update fails inside its transaction.
+        store
+            .connection
+            .lock()
+            .unwrap()
+            .batch("UPDATE orders SET body = 'not json'")
+            .unwrap();
+        assert!(store
+            .record_update(&order("1", "AAPL", "filled", 10.0))
+            .await
+            .is_err());
+        let status =
+            store
+                .connection
+                .lock()
+                .unwrap()
+                .query("SELECT status FROM orders", &[], |row| Ok(row.text(0)));
+        assert_eq!(status.unwrap(), [Some("new".to_string())]);
+        // The failed transaction was closed, so later writes go through.
+        store
+            .record_submission(&order("2", "MSFT", "new", 0.0))
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,85 @@
+mod runner;
+mod state;
+
+pub use runner::{RunnerConfig, StrategyRunner};
+pub use state::StrategyState;
+
+use crate::{
+    datastructures::{
+        corporate_action::CorporateAction,
+        event::EventType,
+        order::{Order, OrderResponse, OrderSide},
+    },
+    universe::UniverseChange,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// An executed quantity of an order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fill {
+    /// Client order id of the order, or an id assigned by the simulator.
+    pub order_id: String,
+    pub symbol: String,
+    pub side: OrderSide,
+    pub quantity: f64,
+    pub price: f64,
+    #[serde(default)]
+    pub commission: f64,
+    pub timestamp: String,
+}
+
+/// What a strategy can see and do while handling a callback. Orders and cancels are collected and carried
+/// out once the callback returns.
+#[derive(Debug, Default)]
+pub struct StrategyContext {
+    pub(crate) orders: Vec<Order>,
+    pub(crate) cancels: Vec<String>,
+    pub(crate) positions: HashMap<String, f64>,
+    pub(crate) timestamp: Option<String>,
+}
+
+impl StrategyContext {
+    pub fn submit(&mut self, order: Order) {
+        self.orders.push(order);
+    }
+
+    /// Cancels a working order by its client order id. Orders submitted without one are given one, which
+    /// `on_order_update` reports as `client_order_id`.
+    pub fn cancel(&mut self, order_id: impl Into<String>) {
+        self.cancels.push(order_id.into());
+    }
+
+    /// Current quantity held in `symbol`. Negative for shorts.
+    pub fn position(&self, symbol: &str) -> f64 {
+        self.positions.get(symbol).copied().unwrap_or_default()
+    }
+
+    /// Timestamp of the event being handled.
+    pub fn timestamp(&self) -> Option<&str> {
+        self.timestamp.as_deref()
+    }
+}
+
+/// Trading logic driven by market events. The same strategy runs live or on replayed data through
+/// `StrategyRunner`, and against a simulated account through `Backtest`.
+pub trait Strategy: Send {
+    fn on_event(&mut self, event: &EventType, context: &mut StrategyContext);
+
+    /// Called for every (partial) fill of an order the strategy submitted, before `on_order_update`.
+    fn on_fill(&mut self, _fill: &Fill, _context: &mut StrategyContext) {}
+
+    /// Called whenever the status or filled quantity of an order the strategy submitted changes.
+    fn on_order_update(&mut self, _order: &OrderResponse, _context: &mut StrategyContext) {}
+
+    /// Called on every tick of the configured timer interval.
+    fn on_timer(&mut self, _context: &mut StrategyContext) {}
+
+    /// Called by `Backtest` when a split or dividend takes effect, after positions, working orders and cash
+    /// have been adjusted. Prices and quantities the strategy keeps for the symbol may need the same adjustment.
+    fn on_corporate_action(&mut self, _action: &CorporateAction, _context: &mut StrategyContext) {}
+
+    /// Called by `StrategyRunner` when the configured `Universe` gains or loses symbols. Positions in removed
+    /// symbols are left for the strategy to close.
+    fn on_universe_change(&mut self, _change: &UniverseChange, _context: &mut StrategyContext) {}
+}
@@ -0,0 +1,336 @@
+use super::{Fill, Strategy, StrategyContext};
+use crate::{
+    blackout::BlackoutCalendar,
+    datastructures::{
+        client::TradingClient,
+        event::EventType,
+        order::{OrderResponse, OrderSide, OrderStatus},
+    },
+    liveness::Heartbeat,
+    shutdown::ShutdownCoordinator,
+    time,
+    universe::Universe,
+};
+use futures_util::{Stream, StreamExt};
+use std::{collections::HashMap, error::Error, mem, time::Duration};
+use tokio::sync::broadcast::error::RecvError;
+use tokio_util::sync::CancellationToken;
+
+/// Timer period used in place of a disabled timer. Far enough out to never fire.
+const DISABLED_TIMER: Duration = Duration::from_secs(60 * 60 * 24 * 365);
+/// Polls an order may go unseen at the broker before it is assumed rejected and no longer tracked.
+const MAX_MISSES: u32 = 3;
+
+#[derive(Debug, Clone)]
+pub struct RunnerConfig {
+    /// How often `Strategy::on_timer` is called. `None` never calls it.
+    pub timer_interval: Option<Duration>,
+    /// How often the strategy's working orders are checked for updates.
+    pub order_poll_interval: Duration,
+    /// Cancel the strategy's working orders when the runner stops.
+    pub cancel_on_shutdown: bool,
+    /// Blocks or reduces new entries during blackout windows, as of the timestamp of the event being handled.
+    pub blackouts: Option<BlackoutCalendar>,
+    /// Passes the universe's additions and removals to `Strategy::on_universe_change` as they are published.
+    pub universe: Option<Universe>,
+    /// Beaten from the runner's loop, so a callback that never returns stops the beats.
+    pub heartbeat: Option<Heartbeat>,
+    /// Sinks flushed once the runner has stopped and its orders' final state has been reported.
+    pub shutdown: Option<ShutdownCoordinator>,
+    /// Cancelling it stops the runner gracefully.
+    pub cancellation: CancellationToken,
+}
+
+impl Default for RunnerConfig {
+    fn default() -> Self {
+        RunnerConfig {
+            timer_interval: None,
+            order_poll_interval: Duration::from_secs(1),
+            cancel_on_shutdown: true,
+            blackouts: None,
+            universe: None,
+            heartbeat: None,
+            shutdown: None,
+            cancellation: CancellationToken::new(),
+        }
+    }
+}
+
+/// Last known state of an order the strategy submitted, keyed by client order id.
+#[derive(Default)]
+struct TrackedOrder {
+    broker_id: Option<String>,
+    status: Option<OrderStatus>,
+    filled_qty: f64,
+    filled_avg_price: f64,
+    misses: u32,
+}
+
+/// Runs a strategy against a `TradingClient` and an event stream: events and timer ticks go to the strategy,
+/// the orders and cancels it asks for go to the client, and the state of its orders is polled back into
+/// `on_fill` and `on_order_update`. Pass a `ReplayFeed` stream and a `SimClient` to run the same strategy on
+/// replayed data. Callbacks run one at a time, so the strategy needs no locking.
+pub struct StrategyRunner<C> {
+    client: C,
+    config: RunnerConfig,
+}
+
+impl<C: TradingClient + Send + Sync> StrategyRunner<C> {
+    pub fn new(client: C, config: RunnerConfig) -> Self {
+        StrategyRunner { client, config }
+    }
+
+    /// Runs until the event stream ends or the cancellation token fires. On the way out, working orders are
+    /// cancelled if configured, their final state is reported to the strategy and sinks are flushed.
+    pub async fn run<S, E>(&self, strategy: &mut S, mut events: E) -> Result<(), Box<dyn Error>>
+    where
+        S: Strategy + ?Sized,
+        E: Stream<Item = EventType> + Unpin,
+    {
+        let mut context = StrategyContext::default();
+        for position in self.client.get_positions().await? {
+            context.positions.insert(position.symbol, position.qty);
+        }
+        let mut tracked: HashMap<String, TrackedOrder> = HashMap::new();
+
+        let mut timer = tokio::time::interval(self.config.timer_interval.unwrap_or(DISABLED_TIMER));
+        timer.reset();
+        let mut poll = tokio::time::interval(self.config.order_poll_interval);
+        poll.reset();
+        let mut changes = self.config.universe.as_ref().map(Universe::subscribe);
+        let heartbeat = self.config.heartbeat.as_ref();
+        let mut beat = tokio::time::interval(heartbeat.map_or(DISABLED_TIMER, |heartbeat| {
+            (heartbeat.max_silence() / 3).max(Duration::from_millis(1))
+        }));
+
+        loop {
+            tokio::select! {
+                event = events.next() => {
+                    let Some(event) = event else {
+                        break;
+                    };
+                    context.timestamp = event.timestamp().map(str::to_string);
+                    strategy.on_event(&event, &mut context);
+                }
+                _ = timer.tick(), if self.config.timer_interval.is_some() => {
+                    context.timestamp = Some(time::format_rfc3339(time::now_nanos()));
+                    strategy.on_timer(&mut context);
+                }
+                change = async { changes.as_mut().unwrap().recv().await }, if changes.is_some() => {
+                    match change {
+                        Ok(change) => {
+                            context.timestamp = Some(change.refreshed_at.clone());
+                            strategy.on_universe_change(&change, &mut context);
+                        }
+                        Err(RecvError::Lagged(missed)) => {
+                            tracing::warn!(missed, "Strategy missed universe changes");
+                        }
+                        Err(RecvError::Closed) => changes = None,
+                    }
+                }
+                _ = poll.tick(), if !tracked.is_empty() => {
+                    self.poll_orders(strategy, &mut context, &mut tracked).await;
+                }
+                _ = beat.tick(), if heartbeat.is_some() => {
+                    if let Some(heartbeat) = heartbeat {
+                        heartbeat.beat();
+                    }
+                }
+                _ = self.config.cancellation.cancelled() => break,
+            }
+            self.execute(&mut context, &mut tracked).await;
+        }
+
+        tracing::info!(working = tracked.len(), "Strategy runner stopping");
+        if self.config.cancel_on_shutdown {
+            context.cancels = tracked.keys().cloned().collect();
+            context.orders.clear();
+            self.execute(&mut context, &mut tracked).await;
+        }
+        if !tracked.is_empty() {
+            self.poll_orders(strategy, &mut context, &mut tracked).await;
+        }
+        if let Some(shutdown) = &self.config.shutdown {
+            let report = shutdown.shutdown().await;
+            if !report.is_complete() {
+                tracing::error!(?report, "Sinks not flushed on shutdown");
+            }
+        }
+        Ok(())
+    }
+
+    /// Carries out the cancels and orders a callback asked for. Failures are logged and do not stop the runner.
+    async fn execute(
+        &self,
+        context: &mut StrategyContext,
+        tracked: &mut HashMap<String, TrackedOrder>,
+    ) {
+        for client_order_id in mem::take(&mut context.cancels) {
+            let broker_id = match tracked
+                .get(&client_order_id)
+                .and_then(|order| order.broker_id.clone())
+            {
+                Some(broker_id) => broker_id,
+                None => match self
+                    .client
+                    .get_order_by_client_id(&client_order_id)
+                    .await
+                    .map_err(|e| e.to_string())
+                {
+                    Ok(order) => order.id,
+                    Err(e) => {
+                        tracing::error!(%client_order_id, error = %e, "Failed to look up order to cancel");
+                        continue;
+                    }
+                },
+            };
+            if let Err(e) = self
+                .client
+                .cancel_order(&broker_id)
+                .await
+                .map_err(|e| e.to_string())
+            {
+                tracing::error!(%client_order_id, error = %e, "Failed to cancel order");
+            }
+        }
+
+        for mut order in mem::take(&mut context.orders) {
+            let client_order_id = order
+                .client_order_id
+                .get_or_insert_with(|| format!("{:032x}", rand::random::<u128>()))
+                .clone();
+            if let Some(blackouts) = &self.config.blackouts {
+                let now = context
+                    .timestamp()
+                    .and_then(time::parse_rfc3339)
+                    .unwrap_or_else(time::now_nanos);
+                match blackouts.check_at(&order, context.position(&order.symbol), now) {
+                    Ok(checked) => order = checked,
+                    Err(e) => {
+                        tracing::warn!(%client_order_id, error = %e, "Order blocked");
+                        continue;
+                    }
+                }
+            }
+            match self
+                .client
+                .create_order(&order)
+                .await
+                .map_err(|e| e.to_string())
+            {
+                Ok(()) => {
+                    tracked.insert(client_order_id, TrackedOrder::default());
+                }
+                Err(e) => {
+                    tracing::error!(%client_order_id, symbol = %order.symbol, error = %e, "Failed to submit order")
+                }
+            }
+        }
+    }
+
+    /// Reads the state of every tracked order and reports what changed.
+    async fn poll_orders<S: Strategy + ?Sized>(
+        &self,
+        strategy: &mut S,
+        context: &mut StrategyContext,
+        tracked: &mut HashMap<String, TrackedOrder>,
+    ) {
+        let open = match self
+            .client
+            .get_open_orders()
+            .await
+            .map_err(|e| e.to_string())
+        {
+            Ok(open) => open,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to poll open orders");
+                return;
+            }
+        };
+
+        let mut updates: Vec<OrderResponse> = open
+            .into_iter()
+            .filter(|order| tracked.contains_key(&order.client_order_id))
+            .collect();
+        let closed: Vec<String> = tracked
+            .keys()
+            .filter(|id| !updates.iter().any(|order| &order.client_order_id == *id))
+            .cloned()
+            .collect();
+        for client_order_id in closed {
+            match self
+                .client
+                .get_order_by_client_id(&client_order_id)
+                .await
+                .map_err(|e| e.to_string())
+            {
+                Ok(order) => updates.push(order),
+                Err(e) => {
+                    let Some(order) = tracked.get_mut(&client_order_id) else {
+                        continue;
+                    };
+                    order.misses += 1;
+                    if order.misses >= MAX_MISSES && order.broker_id.is_none() {
+                        tracing::warn!(%client_order_id, error = %e, "Order not found at the broker; assuming it was rejected");
+                        tracked.remove(&client_order_id);
+                    }
+                }
+            }
+        }
+
+        for order in updates {
+            apply_update(strategy, context, tracked, order);
+        }
+    }
+}
+
+fn apply_update<S: Strategy + ?Sized>(
+    strategy: &mut S,
+    context: &mut StrategyContext,
+    tracked: &mut HashMap<String, TrackedOrder>,
+    order: OrderResponse,
+) {
+    let Some(known) = tracked.get_mut(&order.client_order_id) else {
+        return;
+    };
+    known.broker_id = Some(order.id.clone());
+    known.misses = 0;
+    if known.status == Some(order.status) && known.filled_qty == order.filled_qty {
+        return;
+    }
+
+    let mut fill = None;
+    let filled_avg_price = order.filled_avg_price.unwrap_or_default();
+    if order.filled_qty > known.filled_qty {
+        let quantity = order.filled_qty - known.filled_qty;
+        let price = (filled_avg_price * order.filled_qty
+            - known.filled_avg_price * known.filled_qty)
+            / quantity;
+        fill = Some(Fill {
+            order_id: order.client_order_id.clone(),
+            symbol: order.symbol.clone(),
+            side: order.side,
+            quantity,
+            price,
+            commission: 0.0,
+            timestamp: time::format_rfc3339(time::now_nanos()),
+        });
+    }
+    known.status = Some(order.status);
+    known.filled_qty = order.filled_qty;
+    known.filled_avg_price = filled_avg_price;
+    if order.status.is_terminal() {
+        tracked.remove(&order.client_order_id);
+    }
+
+    context.timestamp = Some(time::format_rfc3339(time::now_nanos()));
+    if let Some(fill) = fill {
+        let signed = match fill.side {
+            OrderSide::Buy => fill.quantity,
+            OrderSide::Sell => -fill.quantity,
+        };
+        *context.positions.entry(fill.symbol.clone()).or_default() += signed;
+        strategy.on_fill(&fill, context);
+    }
+    strategy.on_order_update(&order, context);
+}
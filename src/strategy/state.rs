@@ -0,0 +1,77 @@
+use crate::store::Storage;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{error::Error, sync::Arc};
+
+/// A strategy's own persistent state, e.g. the time of its last signal, model parameters or cooldowns, kept in
+/// a `Storage` under the strategy's namespace so strategies sharing a store never see each other's keys.
+#[derive(Clone)]
+pub struct StrategyState {
+    storage: Arc<dyn Storage>,
+    prefix: String,
+}
+
+impl StrategyState {
+    /// State under `namespace`, usually the strategy's name. Keys are stored as `namespace/key`.
+    pub fn new(storage: Arc<dyn Storage>, namespace: &str) -> Self {
+        StrategyState {
+            storage,
+            prefix: format!("{}/", namespace),
+        }
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.prefix[..self.prefix.len() - 1]
+    }
+
+    /// `None` when nothing is stored under the key. Fails if the stored value is not a `T`.
+    pub async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Box<dyn Error>> {
+        match self.storage.get(&self.key(key)).await? {
+            Some(value) => Ok(Some(serde_json::from_value(value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Stored value, or `T::default()` when there is none.
+    pub async fn get_or_default<T: DeserializeOwned + Default>(
+        &self,
+        key: &str,
+    ) -> Result<T, Box<dyn Error>> {
+        Ok(self.get(key).await?.unwrap_or_default())
+    }
+
+    pub async fn set<T: Serialize + ?Sized>(
+        &self,
+        key: &str,
+        value: &T,
+    ) -> Result<(), Box<dyn Error>> {
+        let value = serde_json::to_value(value)?;
+        self.storage.put(&self.key(key), value).await
+    }
+
+    pub async fn remove(&self, key: &str) -> Result<(), Box<dyn Error>> {
+        self.storage.delete(&self.key(key)).await
+    }
+
+    /// Keys in the namespace, without the namespace, sorted.
+    pub async fn keys(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        Ok(self
+            .storage
+            .keys(&self.prefix)
+            .await?
+            .into_iter()
+            .map(|key| key[self.prefix.len()..].to_string())
+            .collect())
+    }
+
+    /// Removes every key in the namespace.
+    pub async fn clear(&self) -> Result<(), Box<dyn Error>> {
+        for key in self.storage.keys(&self.prefix).await? {
+            self.storage.delete(&key).await?;
+        }
+        Ok(())
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("{}{}", self.prefix, key)
+    }
+}
@@ -0,0 +1,154 @@
+use super::EventStream;
+use crate::datastructures::event::EventType;
+use futures_util::{stream, Stream};
+use std::sync::Arc;
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    task::JoinHandle,
+};
+
+/// Most events the forwarder takes off the stream and publishes as one batch.
+const MAX_BATCH: usize = 1024;
+
+/// Whatever was queued on the stream when the forwarder last woke up, shared by every subscriber.
+#[derive(Clone)]
+struct Published {
+    /// Position of the first event in the overall stream, used to count events a lagging subscriber skipped.
+    sequence: u64,
+    events: Arc<[EventType]>,
+}
+
+/// Fans a single `EventStream` out to any number of subscribers.
+/// Events are published in batches so bursts cost one broadcast rather than one per event.
+/// Subscribers that fall more than `capacity` batches behind skip ahead and have the skipped events counted as lag.
+pub struct EventBus {
+    // Kept only to mint new receivers; the forwarding task owns the sender so subscribers see the end of the stream.
+    template: broadcast::Receiver<Published>,
+    forwarder: JoinHandle<()>,
+}
+
+impl EventBus {
+    pub fn new(mut stream: EventStream, capacity: usize) -> EventBus {
+        let (sender, template) = broadcast::channel(capacity);
+        let forwarder = tokio::spawn(async move {
+            let mut sequence = 0;
+            while let Some(events) = stream.next_batch(MAX_BATCH).await {
+                let count = events.len() as u64;
+                // Only fails when nobody is subscribed, in which case the events are simply not needed.
+                let _ = sender.send(Published {
+                    sequence,
+                    events: events.into(),
+                });
+                sequence += count;
+            }
+        });
+
+        EventBus {
+            template,
+            forwarder,
+        }
+    }
+
+    /// Subscribes to events published from now on.
+    pub fn subscribe(&self) -> BusSubscriber {
+        BusSubscriber {
+            receiver: self.template.resubscribe(),
+            current: None,
+            position: 0,
+            next_sequence: None,
+            received: 0,
+            missed: 0,
+        }
+    }
+}
+
+impl Drop for EventBus {
+    fn drop(&mut self) {
+        self.forwarder.abort();
+    }
+}
+
+pub struct BusSubscriber {
+    receiver: broadcast::Receiver<Published>,
+    /// Batch `recv` is currently handing out, and how far into it it has got.
+    current: Option<Arc<[EventType]>>,
+    position: usize,
+    next_sequence: Option<u64>,
+    received: u64,
+    missed: u64,
+}
+
+impl BusSubscriber {
+    /// Next event, or `None` once the underlying stream has ended.
+    pub async fn recv(&mut self) -> Option<EventType> {
+        loop {
+            if let Some(event) = self
+                .current
+                .as_ref()
+                .and_then(|batch| batch.get(self.position))
+            {
+                let event = event.clone();
+                self.position += 1;
+                self.received += 1;
+                return Some(event);
+            }
+
+            self.current = Some(self.next_published().await?);
+            self.position = 0;
+        }
+    }
+
+    /// Next batch of events as published, without copying them. Lets consumers pay per-event overhead once per burst.
+    /// Mixing with `recv` is fine: the rest of a partially consumed batch is returned first.
+    /// `None` once the underlying stream has ended.
+    pub async fn recv_batch(&mut self) -> Option<Arc<[EventType]>> {
+        let batch = match self.current.take() {
+            Some(batch) if self.position < batch.len() => match self.position {
+                0 => batch,
+                position => batch[position..].into(),
+            },
+            _ => self.next_published().await?,
+        };
+        self.position = 0;
+        self.received += batch.len() as u64;
+        Some(batch)
+    }
+
+    /// Events delivered to this subscriber so far.
+    pub fn received(&self) -> u64 {
+        self.received
+    }
+
+    /// Events this subscriber never saw because it fell behind.
+    pub fn missed(&self) -> u64 {
+        self.missed
+    }
+
+    /// Batches published but not yet received by this subscriber.
+    pub fn backlog(&self) -> usize {
+        self.receiver.len()
+    }
+
+    pub fn into_stream(self) -> impl Stream<Item = EventType> {
+        stream::unfold(self, |mut subscriber| async move {
+            subscriber.recv().await.map(|event| (event, subscriber))
+        })
+    }
+
+    async fn next_published(&mut self) -> Option<Arc<[EventType]>> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(published) => {
+                    if let Some(expected) = self.next_sequence {
+                        self.missed += published.sequence.saturating_sub(expected);
+                    }
+                    self.next_sequence = Some(published.sequence + published.events.len() as u64);
+                    return Some(published.events);
+                }
+                // The gap is measured from sequence numbers once the next batch arrives.
+                Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
@@ -0,0 +1,333 @@
+use crate::datastructures::event::EventType;
+use std::{
+    collections::VecDeque,
+    mem,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+};
+use tokio::sync::Notify;
+
+/// What the socket reader does when the consumer falls behind and the channel is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackpressurePolicy {
+    /// Wait for the consumer. Nothing is lost, but the socket stops being read in the meantime.
+    Block,
+    /// Discard the oldest queued event to make room.
+    DropOldest,
+    /// Replace a queued event of the same kind for the same symbol so only the latest one is delivered. Only
+    /// events that carry full state are conflated: quotes, bars and order book snapshots, the last superseding
+    /// every queued book update for its symbol. Trades and incremental book updates are never replaced, since
+    /// dropping one would undercount volume or corrupt the book. Falls back to dropping the oldest event when
+    /// there is nothing to replace.
+    ConflatePerSymbol,
+}
+
+struct State {
+    queue: VecDeque<EventType>,
+    senders: usize,
+    receiver_alive: bool,
+    receiver_waker: Option<Waker>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    writable: Notify,
+    dropped: AtomicU64,
+}
+
+/// Creates the bounded channel that sits between socket readers and the consumer.
+pub(crate) fn channel(capacity: usize, policy: BackpressurePolicy) -> (EventSender, EventReceiver) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            queue: VecDeque::with_capacity(capacity),
+            senders: 1,
+            receiver_alive: true,
+            receiver_waker: None,
+        }),
+        writable: Notify::new(),
+        dropped: AtomicU64::new(0),
+    });
+
+    (
+        EventSender {
+            shared: shared.clone(),
+            capacity,
+            policy,
+        },
+        EventReceiver { shared },
+    )
+}
+
+pub(crate) struct EventSender {
+    shared: Arc<Shared>,
+    capacity: usize,
+    policy: BackpressurePolicy,
+}
+
+impl EventSender {
+    /// Queues an event according to the backpressure policy. Fails if the receiver is gone.
+    pub(crate) async fn send(&self, event: EventType) -> Result<(), ()> {
+        self.send_all(std::iter::once(event)).await
+    }
+
+    /// Queues events in order, taking the lock once for as many of them as fit rather than once per event.
+    pub(crate) async fn send_all(
+        &self,
+        events: impl IntoIterator<Item = EventType>,
+    ) -> Result<(), ()> {
+        let mut events = events.into_iter().peekable();
+        loop {
+            let notified = self.shared.writable.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            let done = {
+                let mut state = self.shared.state.lock().unwrap();
+                if !state.receiver_alive {
+                    return Err(());
+                }
+
+                let mut pushed = false;
+                while let Some(event) = events.next_if(|_| {
+                    state.queue.len() < self.capacity || self.policy != BackpressurePolicy::Block
+                }) {
+                    self.push(&mut state, event);
+                    pushed = true;
+                }
+
+                let done = events.peek().is_none();
+                if pushed {
+                    wake_receiver(state);
+                }
+                done
+            };
+            if done {
+                return Ok(());
+            }
+
+            notified.await;
+        }
+    }
+
+    /// Adds an event to the queue, making room according to the policy if it is full.
+    /// Never called on a full queue under `Block`.
+    fn push(&self, state: &mut State, event: EventType) {
+        if state.queue.len() < self.capacity {
+            state.queue.push_back(event);
+            return;
+        }
+
+        let conflating = self.policy == BackpressurePolicy::ConflatePerSymbol;
+        let dropped = if conflating && matches!(event, EventType::OrderBook { reset: true, .. }) {
+            // Updates queued before the snapshot must not be applied after it.
+            let queued = state.queue.len();
+            state
+                .queue
+                .retain(|queued| !(is_book(queued) && queued.symbol() == event.symbol()));
+            queued - state.queue.len()
+        } else if let Some(index) = state
+            .queue
+            .iter()
+            .position(|queued| conflating && conflates(queued, &event))
+        {
+            state.queue[index] = event;
+            self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+            return;
+        } else {
+            0
+        };
+        if dropped == 0 {
+            state.queue.pop_front();
+        }
+        state.queue.push_back(event);
+        self.shared
+            .dropped
+            .fetch_add(dropped.max(1) as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        !self.shared.state.lock().unwrap().receiver_alive
+    }
+}
+
+impl Clone for EventSender {
+    fn clone(&self) -> Self {
+        self.shared.state.lock().unwrap().senders += 1;
+        EventSender {
+            shared: self.shared.clone(),
+            capacity: self.capacity,
+            policy: self.policy,
+        }
+    }
+}
+
+impl Drop for EventSender {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.senders -= 1;
+        if state.senders == 0 {
+            wake_receiver(state);
+        }
+    }
+}
+
+pub(crate) struct EventReceiver {
+    shared: Arc<Shared>,
+}
+
+impl EventReceiver {
+    pub(crate) fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<EventType>> {
+        let mut state = self.shared.state.lock().unwrap();
+        match state.queue.pop_front() {
+            Some(event) => {
+                drop(state);
+                self.shared.writable.notify_one();
+                Poll::Ready(Some(event))
+            }
+            None if state.senders == 0 => Poll::Ready(None),
+            None => {
+                state.receiver_waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+
+    /// Moves up to `limit` queued events into `buffer` at once. Resolves to 0 only once every sender is gone.
+    pub(crate) fn poll_recv_many(
+        &mut self,
+        cx: &mut Context<'_>,
+        buffer: &mut Vec<EventType>,
+        limit: usize,
+    ) -> Poll<usize> {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.queue.is_empty() {
+            if state.senders == 0 {
+                return Poll::Ready(0);
+            }
+            state.receiver_waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+
+        let count = state.queue.len().min(limit.max(1));
+        buffer.extend(state.queue.drain(..count));
+        drop(state);
+        self.shared.writable.notify_waiters();
+        Poll::Ready(count)
+    }
+
+    /// Events discarded or conflated away because the consumer was too slow.
+    pub(crate) fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for EventReceiver {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.receiver_alive = false;
+        state.queue.clear();
+        drop(state);
+        self.shared.writable.notify_waiters();
+    }
+}
+
+fn wake_receiver(mut state: std::sync::MutexGuard<'_, State>) {
+    let waker = state.receiver_waker.take();
+    drop(state);
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
+
+/// Whether `incoming` carries everything `queued` does, so `queued` can be dropped in its favour.
+fn conflates(queued: &EventType, incoming: &EventType) -> bool {
+    let full_state = matches!(
+        incoming,
+        EventType::Quote { .. }
+            | EventType::Bar { .. }
+            | EventType::UpdatedBar { .. }
+            | EventType::DailyBar { .. }
+    );
+    full_state
+        && mem::discriminant(queued) == mem::discriminant(incoming)
+        && queued.symbol() == incoming.symbol()
+}
+
+fn is_book(event: &EventType) -> bool {
+    matches!(event, EventType::OrderBook { .. })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book(symbol: &str, reset: bool, bid: f64) -> EventType {
+        EventType::OrderBook {
+            symbol: symbol.to_string(),
+            bids: vec![(bid, 1.0)],
+            asks: vec![],
+            reset,
+            timestamp: String::new(),
+        }
+    }
+
+    fn quote(symbol: &str, bid_price: f64) -> EventType {
+        EventType::Quote {
+            symbol: symbol.to_string(),
+            bid_price,
+            ask_price: bid_price + 0.01,
+            bid_size: 1.0,
+            ask_size: 1.0,
+            timestamp: String::new(),
+        }
+    }
+
+    fn bids(receiver: &mut EventReceiver) -> Vec<(String, f64)> {
+        let mut state = receiver.shared.state.lock().unwrap();
+        state
+            .queue
+            .drain(..)
+            .map(|event| match event {
+                EventType::OrderBook { symbol, bids, .. } => (symbol, bids[0].0),
+                EventType::Quote {
+                    symbol, bid_price, ..
+                } => (symbol, bid_price),
+                event => panic!("unexpected event: {:?}", event),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn keeps_every_book_update_for_a_symbol() {
+        let (sender, mut receiver) = channel(2, BackpressurePolicy::ConflatePerSymbol);
+        sender.send(quote("AAPL", 190.0)).await.unwrap();
+        sender.send(book("BTC/USD", false, 100.0)).await.unwrap();
+        sender.send(book("BTC/USD", false, 101.0)).await.unwrap();
+        assert_eq!(
+            bids(&mut receiver),
+            [
+                ("BTC/USD".to_string(), 100.0),
+                ("BTC/USD".to_string(), 101.0)
+            ]
+        );
+        assert_eq!(receiver.dropped(), 1);
+    }
+
+    #[tokio::test]
+    async fn conflates_quotes_and_book_snapshots() {
+        let (sender, mut receiver) = channel(3, BackpressurePolicy::ConflatePerSymbol);
+        sender.send(quote("AAPL", 190.0)).await.unwrap();
+        sender.send(book("BTC/USD", true, 100.0)).await.unwrap();
+        sender.send(book("BTC/USD", false, 101.0)).await.unwrap();
+        sender.send(quote("AAPL", 190.5)).await.unwrap();
+        sender.send(book("BTC/USD", true, 102.0)).await.unwrap();
+        assert_eq!(
+            bids(&mut receiver),
+            [("AAPL".to_string(), 190.5), ("BTC/USD".to_string(), 102.0)]
+        );
+        assert_eq!(receiver.dropped(), 3);
+    }
+}
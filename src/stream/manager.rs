@@ -0,0 +1,327 @@
+use super::{BusSubscriber, EventBus, EventStream, StreamConfig};
+use crate::{
+    datastructures::{
+        client::{FeedType, MarketDataClient, SubscriptionParams},
+        event::EventType,
+    },
+    time,
+};
+use futures_util::{Stream, StreamExt};
+use std::{
+    collections::HashMap,
+    error::Error,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+use tokio::{sync::mpsc, task::JoinHandle};
+
+/// An event along with the feed it arrived on.
+#[derive(Debug, Clone)]
+pub struct FeedEvent {
+    pub feed: FeedType,
+    pub event: EventType,
+}
+
+/// How one feed has been doing since it was added.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FeedHealth {
+    /// False while the feed's stream has ended and is being reopened.
+    pub connected: bool,
+    /// The last message on the feed reported the connection stale.
+    pub stale: bool,
+    pub events: u64,
+    /// RFC 3339 time the last event was received, not its own timestamp.
+    pub last_event_at: Option<String>,
+    pub reconnects: u32,
+    /// Error from the latest failed attempt to reopen the feed, cleared once it reconnects.
+    pub last_error: Option<String>,
+}
+
+type Health = Arc<Mutex<HashMap<FeedType, FeedHealth>>>;
+
+fn update_health(health: &Health, feed: FeedType, update: impl FnOnce(&mut FeedHealth)) {
+    update(health.lock().unwrap().entry(feed).or_default());
+}
+
+/// Owns one `EventStream` per feed and merges them into a single stream of `FeedEvent`s.
+/// A feed whose stream ends is reopened with the configured reconnect backoff.
+pub struct FeedManager<C> {
+    client: C,
+    config: StreamConfig,
+    sender: mpsc::Sender<FeedEvent>,
+    receiver: mpsc::Receiver<FeedEvent>,
+    feeds: HashMap<FeedType, JoinHandle<()>>,
+    health: Health,
+}
+
+impl<C> FeedManager<C>
+where
+    C: MarketDataClient + Clone + Send + Sync + 'static,
+{
+    pub fn new(client: C, config: StreamConfig) -> Self {
+        let (sender, receiver) = mpsc::channel(config.channel_capacity);
+        FeedManager {
+            client,
+            config,
+            sender,
+            receiver,
+            feeds: HashMap::new(),
+            health: Health::default(),
+        }
+    }
+
+    /// Connects to the feed in `params`, replacing any existing subscription to the same feed.
+    pub async fn add_feed(&mut self, params: SubscriptionParams) -> Result<(), Box<dyn Error>> {
+        add_feed(
+            &self.client,
+            &self.config,
+            &self.sender,
+            &mut self.feeds,
+            &self.health,
+            params,
+        )
+        .await
+    }
+
+    /// Closes the connection to a feed. Returns false if it was not subscribed.
+    pub fn remove_feed(&mut self, feed: FeedType) -> bool {
+        remove_feed(&mut self.feeds, &self.health, feed)
+    }
+
+    pub fn feeds(&self) -> Vec<FeedType> {
+        self.feeds.keys().copied().collect()
+    }
+
+    /// Health of every subscribed feed.
+    pub fn health(&self) -> HashMap<FeedType, FeedHealth> {
+        self.health.lock().unwrap().clone()
+    }
+
+    /// Next event from any feed. Waits indefinitely while no feeds are subscribed.
+    pub async fn next_event(&mut self) -> Option<FeedEvent> {
+        self.receiver.recv().await
+    }
+}
+
+impl<C> Stream for FeedManager<C>
+where
+    C: Unpin,
+{
+    type Item = FeedEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<FeedEvent>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+impl<C> Drop for FeedManager<C> {
+    fn drop(&mut self) {
+        for supervisor in self.feeds.values() {
+            supervisor.abort();
+        }
+    }
+}
+
+/// Connections to several feeds at once, e.g. stocks, crypto and news, with every event published on one
+/// `EventBus` and the health of each feed tracked. Feeds can be added and removed while subscribers are
+/// reading; a feed whose stream ends is reopened with the configured reconnect backoff. Must be created inside
+/// a Tokio runtime. Closes every connection when dropped.
+pub struct StreamManager<C> {
+    client: C,
+    config: StreamConfig,
+    sender: mpsc::Sender<FeedEvent>,
+    bus: EventBus,
+    feeds: HashMap<FeedType, JoinHandle<()>>,
+    health: Health,
+}
+
+impl<C> StreamManager<C>
+where
+    C: MarketDataClient + Clone + Send + Sync + 'static,
+{
+    pub fn new(client: C, config: StreamConfig) -> Self {
+        let (sender, mut receiver) = mpsc::channel::<FeedEvent>(config.channel_capacity);
+        let merged = EventStream::from_task(config.channel_capacity, |events| async move {
+            while let Some(feed_event) = receiver.recv().await {
+                if events.send(feed_event.event).await.is_err() {
+                    return;
+                }
+            }
+        });
+        StreamManager {
+            bus: EventBus::new(merged, config.channel_capacity),
+            client,
+            config,
+            sender,
+            feeds: HashMap::new(),
+            health: Health::default(),
+        }
+    }
+
+    /// Connects to the feed in `params`, replacing any existing subscription to the same feed.
+    pub async fn add_feed(&mut self, params: SubscriptionParams) -> Result<(), Box<dyn Error>> {
+        add_feed(
+            &self.client,
+            &self.config,
+            &self.sender,
+            &mut self.feeds,
+            &self.health,
+            params,
+        )
+        .await
+    }
+
+    /// Closes the connection to a feed. Returns false if it was not subscribed.
+    pub fn remove_feed(&mut self, feed: FeedType) -> bool {
+        remove_feed(&mut self.feeds, &self.health, feed)
+    }
+
+    pub fn feeds(&self) -> Vec<FeedType> {
+        self.feeds.keys().copied().collect()
+    }
+
+    /// Subscribes to events from every feed published from now on.
+    pub fn subscribe(&self) -> BusSubscriber {
+        self.bus.subscribe()
+    }
+
+    pub fn bus(&self) -> &EventBus {
+        &self.bus
+    }
+
+    /// Health of every subscribed feed.
+    pub fn health(&self) -> HashMap<FeedType, FeedHealth> {
+        self.health.lock().unwrap().clone()
+    }
+
+    /// Feeds that are reconnecting or whose last message reported the connection stale.
+    pub fn unhealthy(&self) -> Vec<FeedType> {
+        self.health
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, health)| !health.connected || health.stale)
+            .map(|(feed, _)| *feed)
+            .collect()
+    }
+}
+
+impl<C> Drop for StreamManager<C> {
+    fn drop(&mut self) {
+        for supervisor in self.feeds.values() {
+            supervisor.abort();
+        }
+    }
+}
+
+async fn add_feed<C>(
+    client: &C,
+    config: &StreamConfig,
+    sender: &mpsc::Sender<FeedEvent>,
+    feeds: &mut HashMap<FeedType, JoinHandle<()>>,
+    health: &Health,
+    params: SubscriptionParams,
+) -> Result<(), Box<dyn Error>>
+where
+    C: MarketDataClient + Clone + Send + Sync + 'static,
+{
+    let feed = params.feed_type;
+    let stream = EventStream::connect(client.clone(), params.clone(), config.clone()).await?;
+
+    if let Some(previous) = feeds.remove(&feed) {
+        previous.abort();
+    }
+    health.lock().unwrap().insert(
+        feed,
+        FeedHealth {
+            connected: true,
+            ..FeedHealth::default()
+        },
+    );
+    let supervisor = tokio::spawn(supervise(
+        client.clone(),
+        params,
+        config.clone(),
+        stream,
+        sender.clone(),
+        health.clone(),
+    ));
+    feeds.insert(feed, supervisor);
+    Ok(())
+}
+
+fn remove_feed(
+    feeds: &mut HashMap<FeedType, JoinHandle<()>>,
+    health: &Health,
+    feed: FeedType,
+) -> bool {
+    health.lock().unwrap().remove(&feed);
+    match feeds.remove(&feed) {
+        Some(supervisor) => {
+            supervisor.abort();
+            true
+        }
+        None => false,
+    }
+}
+
+async fn supervise<C>(
+    client: C,
+    params: SubscriptionParams,
+    config: StreamConfig,
+    mut stream: EventStream,
+    sender: mpsc::Sender<FeedEvent>,
+    health: Health,
+) where
+    C: MarketDataClient + Clone + Send + Sync + 'static,
+{
+    let feed = params.feed_type;
+    loop {
+        while let Some(event) = stream.next().await {
+            let stale = matches!(event, EventType::StaleConnection { .. });
+            update_health(&health, feed, |health| {
+                health.stale = stale;
+                if !stale {
+                    health.events += 1;
+                    health.last_event_at = Some(time::format_rfc3339(time::now_nanos()));
+                }
+            });
+            if sender.send(FeedEvent { feed, event }).await.is_err() {
+                return;
+            }
+        }
+
+        if config.cancellation.is_cancelled() {
+            return;
+        }
+        update_health(&health, feed, |health| health.connected = false);
+
+        let mut delay = config.reconnect_delay;
+        stream = loop {
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = config.cancellation.cancelled() => return,
+            }
+            let result = EventStream::connect(client.clone(), params.clone(), config.clone())
+                .await
+                .map_err(|e| e.to_string());
+            match result {
+                Ok(stream) => {
+                    update_health(&health, feed, |health| {
+                        health.connected = true;
+                        health.stale = false;
+                        health.reconnects += 1;
+                        health.last_error = None;
+                    });
+                    break stream;
+                }
+                Err(e) => {
+                    tracing::warn!(?feed, error = %e, "Failed to reopen feed");
+                    update_health(&health, feed, |health| health.last_error = Some(e));
+                    delay = (delay * 2).min(config.max_reconnect_delay);
+                }
+            }
+        };
+    }
+}
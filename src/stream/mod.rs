@@ -0,0 +1,490 @@
+mod bus;
+mod channel;
+mod manager;
+
+pub use bus::{BusSubscriber, EventBus};
+pub use channel::BackpressurePolicy;
+pub use manager::{FeedEvent, FeedHealth, FeedManager, StreamManager};
+
+use crate::{
+    datastructures::{
+        client::{BookDepth, MarketDataClient, SubscriptionParams},
+        event::{EventType, ParseMode},
+    },
+    liveness::Heartbeat,
+};
+pub(crate) use channel::EventSender;
+
+use channel::EventReceiver;
+use futures_util::{future::try_join_all, SinkExt, Stream, StreamExt};
+use std::{
+    error::Error,
+    future::{poll_fn, Future},
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::{net::TcpStream, task::JoinHandle, time::Instant};
+use tokio_tungstenite::{tungstenite::protocol::Message, MaybeTlsStream, WebSocketStream};
+use tokio_util::sync::CancellationToken;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Timer period used in place of a disabled heartbeat setting. Far enough out to never fire.
+const DISABLED_TIMER: Duration = Duration::from_secs(60 * 60 * 24 * 365);
+
+/// Settings for a managed event stream.
+#[derive(Clone)]
+pub struct StreamConfig {
+    /// Maximum number of distinct symbols subscribed over one connection. `None` uses a single connection.
+    pub max_symbols_per_connection: Option<usize>,
+    pub reconnect_delay: Duration,
+    pub max_reconnect_delay: Duration,
+    /// How often to send a websocket ping so half-open connections are noticed. `None` disables pings.
+    pub ping_interval: Option<Duration>,
+    /// Emit `EventType::StaleConnection` when nothing arrives for this long. `None` disables the check.
+    pub stale_timeout: Option<Duration>,
+    /// Drop and re-establish a connection once it is considered stale.
+    pub reconnect_on_stale: bool,
+    /// Number of parsed events buffered between the socket readers and the consumer.
+    pub channel_capacity: usize,
+    pub backpressure: BackpressurePolicy,
+    pub parse_mode: ParseMode,
+    /// Cancelling it closes every connection opened with this config and ends the streams cleanly.
+    pub cancellation: CancellationToken,
+    /// Beaten on every message received, pongs included. Its silence limit should exceed the ping interval.
+    pub heartbeat: Option<Heartbeat>,
+}
+
+impl StreamConfig {
+    pub fn builder() -> StreamConfigBuilder {
+        StreamConfigBuilder::default()
+    }
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        StreamConfig {
+            max_symbols_per_connection: None,
+            reconnect_delay: Duration::from_secs(1),
+            max_reconnect_delay: Duration::from_secs(60),
+            ping_interval: Some(Duration::from_secs(30)),
+            stale_timeout: None,
+            reconnect_on_stale: false,
+            channel_capacity: 10_000,
+            backpressure: BackpressurePolicy::Block,
+            parse_mode: ParseMode::Lenient,
+            cancellation: CancellationToken::new(),
+            heartbeat: None,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct StreamConfigBuilder {
+    max_symbols_per_connection: Option<usize>,
+    reconnect_delay: Option<Duration>,
+    max_reconnect_delay: Option<Duration>,
+    ping_interval: Option<Option<Duration>>,
+    stale_timeout: Option<Duration>,
+    reconnect_on_stale: bool,
+    channel_capacity: Option<usize>,
+    backpressure: Option<BackpressurePolicy>,
+    parse_mode: ParseMode,
+    cancellation: Option<CancellationToken>,
+    heartbeat: Option<Heartbeat>,
+}
+
+impl StreamConfigBuilder {
+    /// Splits the symbol set across several connections once it exceeds this size.
+    pub fn max_symbols_per_connection(mut self, max_symbols_per_connection: usize) -> Self {
+        self.max_symbols_per_connection = Some(max_symbols_per_connection);
+        self
+    }
+
+    /// Delay before the first reconnection attempt. Doubles on every failed attempt.
+    pub fn reconnect_delay(mut self, reconnect_delay: Duration) -> Self {
+        self.reconnect_delay = Some(reconnect_delay);
+        self
+    }
+
+    pub fn max_reconnect_delay(mut self, max_reconnect_delay: Duration) -> Self {
+        self.max_reconnect_delay = Some(max_reconnect_delay);
+        self
+    }
+
+    /// Pass `None` to disable client-initiated pings.
+    pub fn ping_interval(mut self, ping_interval: Option<Duration>) -> Self {
+        self.ping_interval = Some(ping_interval);
+        self
+    }
+
+    /// Maximum silence tolerated on a connection before it is reported as stale.
+    pub fn stale_timeout(mut self, stale_timeout: Duration) -> Self {
+        self.stale_timeout = Some(stale_timeout);
+        self
+    }
+
+    /// If true, a stale connection is reconnected after the `StaleConnection` event is emitted.
+    pub fn reconnect_on_stale(mut self, reconnect_on_stale: bool) -> Self {
+        self.reconnect_on_stale = reconnect_on_stale;
+        self
+    }
+
+    pub fn channel_capacity(mut self, channel_capacity: usize) -> Self {
+        self.channel_capacity = Some(channel_capacity);
+        self
+    }
+
+    /// What to do when the consumer is slower than the feed and the channel fills up.
+    pub fn backpressure(mut self, backpressure: BackpressurePolicy) -> Self {
+        self.backpressure = Some(backpressure);
+        self
+    }
+
+    /// `ParseMode::Strict` warns about stream fields and message types the crate does not know. Lenient by default.
+    pub fn parse_mode(mut self, parse_mode: ParseMode) -> Self {
+        self.parse_mode = parse_mode;
+        self
+    }
+
+    /// Ties the stream's lifetime to an application-wide token.
+    pub fn cancellation_token(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    /// Reports the stream's readers as alive while messages keep arriving, e.g. from `Liveness::register`.
+    pub fn heartbeat(mut self, heartbeat: Heartbeat) -> Self {
+        self.heartbeat = Some(heartbeat);
+        self
+    }
+
+    pub fn build(self) -> Result<StreamConfig, &'static str> {
+        let default = StreamConfig::default();
+        let config = StreamConfig {
+            max_symbols_per_connection: self.max_symbols_per_connection,
+            reconnect_delay: self.reconnect_delay.unwrap_or(default.reconnect_delay),
+            max_reconnect_delay: self
+                .max_reconnect_delay
+                .unwrap_or(default.max_reconnect_delay),
+            ping_interval: self.ping_interval.unwrap_or(default.ping_interval),
+            stale_timeout: self.stale_timeout,
+            reconnect_on_stale: self.reconnect_on_stale,
+            channel_capacity: self.channel_capacity.unwrap_or(default.channel_capacity),
+            backpressure: self.backpressure.unwrap_or(default.backpressure),
+            parse_mode: self.parse_mode,
+            cancellation: self.cancellation.unwrap_or(default.cancellation),
+            heartbeat: self.heartbeat,
+        };
+
+        if config.max_symbols_per_connection == Some(0) {
+            return Err("Max symbols per connection must be greater than zero");
+        }
+        if config.reconnect_delay > config.max_reconnect_delay {
+            return Err("Reconnect delay must not exceed the max reconnect delay");
+        }
+        if config.ping_interval == Some(Duration::ZERO)
+            || config.stale_timeout == Some(Duration::ZERO)
+        {
+            return Err("Heartbeat intervals must be greater than zero");
+        }
+        if config.channel_capacity == 0 {
+            return Err("Channel capacity must be greater than zero");
+        }
+        if config.reconnect_on_stale && config.stale_timeout.is_none() {
+            return Err("Reconnecting on stale connections requires a stale timeout");
+        }
+
+        Ok(config)
+    }
+}
+
+/// One logical subscription, possibly spread over several websocket connections, yielding parsed events.
+/// Every connection reconnects and resubscribes on its own; dropping the stream closes all of them.
+pub struct EventStream {
+    receiver: EventReceiver,
+    readers: Vec<JoinHandle<()>>,
+}
+
+impl EventStream {
+    /// Opens every connection needed for `params` and fails if any of the initial handshakes fail.
+    pub async fn connect<C>(
+        client: C,
+        params: SubscriptionParams,
+        config: StreamConfig,
+    ) -> Result<EventStream, Box<dyn Error>>
+    where
+        C: MarketDataClient + Clone + Send + Sync + 'static,
+    {
+        let shards = match config.max_symbols_per_connection {
+            Some(max_symbols) => params.shard(max_symbols),
+            None => vec![params],
+        };
+
+        // Errors are stringified inside the joined futures so `connect` can run on a spawned task.
+        let handshakes = try_join_all(shards.iter().map(|shard| async {
+            client
+                .subscribe(shard.clone())
+                .await
+                .map_err(|e| e.to_string())
+        }));
+        let sockets = tokio::select! {
+            sockets = handshakes => sockets?,
+            _ = config.cancellation.cancelled() => return Err("Subscription cancelled".into()),
+        };
+
+        let (sender, receiver) = channel::channel(config.channel_capacity, config.backpressure);
+        let readers = shards
+            .into_iter()
+            .zip(sockets)
+            .map(|(shard, socket)| {
+                tokio::spawn(run_reader(
+                    client.clone(),
+                    shard,
+                    config.clone(),
+                    socket,
+                    sender.clone(),
+                ))
+            })
+            .collect();
+
+        Ok(EventStream { receiver, readers })
+    }
+
+    /// Stream fed by a single task instead of websocket connections, e.g. a replay. The task should stop once
+    /// sending fails, which means the stream was dropped.
+    pub(crate) fn from_task<F, Fut>(capacity: usize, task: F) -> EventStream
+    where
+        F: FnOnce(EventSender) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let (sender, receiver) = channel::channel(capacity, BackpressurePolicy::Block);
+        EventStream {
+            receiver,
+            readers: vec![tokio::spawn(task(sender))],
+        }
+    }
+
+    /// Number of websocket connections backing this stream.
+    pub fn connections(&self) -> usize {
+        self.readers.len()
+    }
+
+    /// Waits for at least one event, then takes up to `limit` queued events in one go.
+    /// `None` once every connection has stopped.
+    pub async fn next_batch(&mut self, limit: usize) -> Option<Vec<EventType>> {
+        let mut batch = Vec::new();
+        match poll_fn(|cx| self.receiver.poll_recv_many(cx, &mut batch, limit)).await {
+            0 => None,
+            _ => Some(batch),
+        }
+    }
+
+    /// Events discarded by the backpressure policy since the stream was opened.
+    pub fn dropped(&self) -> u64 {
+        self.receiver.dropped()
+    }
+}
+
+impl Stream for EventStream {
+    type Item = EventType;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<EventType>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+impl Drop for EventStream {
+    fn drop(&mut self) {
+        for reader in &self.readers {
+            reader.abort();
+        }
+    }
+}
+
+enum ReadOutcome {
+    Disconnected,
+    /// The consumer dropped the stream or the cancellation token fired.
+    Stopped,
+}
+
+async fn run_reader<C>(
+    client: C,
+    params: SubscriptionParams,
+    config: StreamConfig,
+    mut socket: Socket,
+    sender: EventSender,
+) where
+    C: MarketDataClient,
+{
+    loop {
+        let depth = params.subscription_request.orderbook_depth;
+        if let ReadOutcome::Stopped =
+            forward_events(&client, &mut socket, &sender, &config, depth).await
+        {
+            return;
+        }
+
+        let mut delay = config.reconnect_delay;
+        socket = loop {
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = config.cancellation.cancelled() => return,
+            }
+            if sender.is_closed() {
+                return;
+            }
+
+            match client
+                .subscribe(params.clone())
+                .await
+                .map_err(|e| e.to_string())
+            {
+                Ok(socket) => {
+                    tracing::info!(feed = ?params.feed_type, "Reconnected");
+                    #[cfg(feature = "metrics")]
+                    crate::metrics::registry().ws_reconnects.inc();
+                    break socket;
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, "Reconnect failed");
+                    delay = (delay * 2).min(config.max_reconnect_delay);
+                }
+            }
+        };
+    }
+}
+
+/// Trims order book snapshots to `depth`. Updates pass whole: a change beyond the top levels may still matter
+/// once better levels are removed, and a removal must never be dropped.
+fn trim_snapshots(depth: BookDepth, events: &mut [EventType]) {
+    if depth == BookDepth::Full {
+        return;
+    }
+    for event in events {
+        if let EventType::OrderBook {
+            bids,
+            asks,
+            reset: true,
+            ..
+        } = event
+        {
+            depth.truncate(bids, true);
+            depth.truncate(asks, false);
+        }
+    }
+}
+
+async fn forward_events<C: MarketDataClient>(
+    client: &C,
+    socket: &mut Socket,
+    sender: &EventSender,
+    config: &StreamConfig,
+    depth: BookDepth,
+) -> ReadOutcome {
+    let mut ping = tokio::time::interval(config.ping_interval.unwrap_or(DISABLED_TIMER));
+    ping.reset();
+    let stale_timeout = config.stale_timeout.unwrap_or(DISABLED_TIMER);
+    let mut last_message = Instant::now();
+
+    loop {
+        tokio::select! {
+            message = socket.next() => {
+                last_message = Instant::now();
+                if let Some(heartbeat) = &config.heartbeat {
+                    heartbeat.beat();
+                }
+                #[cfg(feature = "metrics")]
+                crate::metrics::registry().ws_messages.inc();
+                match message {
+                    Some(Ok(Message::Text(text))) => match client.parse_frame(&text, config.parse_mode) {
+                        Ok(mut events) => {
+                            trim_snapshots(depth, &mut events);
+                            if sender.send_all(events).await.is_err() {
+                                return ReadOutcome::Stopped;
+                            }
+                        }
+                        Err(e) => {
+                            tracing::warn!(error = %e, "Failed to parse stream message");
+                            #[cfg(feature = "metrics")]
+                            crate::metrics::registry().parse_errors.inc();
+                        }
+                    },
+                    Some(Ok(Message::Close(_))) | None => return ReadOutcome::Disconnected,
+                    // Pings are answered by tungstenite itself; pongs only count as activity.
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => {
+                        tracing::warn!(error = %e, "Stream connection error");
+                        return ReadOutcome::Disconnected;
+                    }
+                }
+            }
+            _ = ping.tick(), if config.ping_interval.is_some() => {
+                if let Err(e) = socket.send(Message::Ping(vec![])).await {
+                    tracing::warn!(error = %e, "Failed to send ping");
+                    return ReadOutcome::Disconnected;
+                }
+            }
+            _ = config.cancellation.cancelled() => {
+                let _ = socket.close(None).await;
+                return ReadOutcome::Stopped;
+            }
+            _ = tokio::time::sleep_until(last_message + stale_timeout), if config.stale_timeout.is_some() => {
+                let event = EventType::StaleConnection { silent_for: last_message.elapsed() };
+                if sender.send(event).await.is_err() {
+                    return ReadOutcome::Stopped;
+                }
+                if config.reconnect_on_stale {
+                    return ReadOutcome::Disconnected;
+                }
+                // Report again only after another full timeout of silence.
+                last_message = Instant::now();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Levels = Vec<(f64, f64)>;
+
+    fn book(reset: bool, bids: &[(f64, f64)], asks: &[(f64, f64)]) -> EventType {
+        EventType::OrderBook {
+            symbol: "BTC/USD".to_string(),
+            bids: bids.to_vec(),
+            asks: asks.to_vec(),
+            reset,
+            timestamp: String::new(),
+        }
+    }
+
+    fn levels(event: &EventType) -> (Levels, Levels) {
+        match event {
+            EventType::OrderBook { bids, asks, .. } => (bids.clone(), asks.clone()),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn trims_only_snapshots() {
+        let bids = [(99.0, 1.0), (100.0, 1.0), (98.0, 1.0)];
+        let asks = [(103.0, 1.0), (101.0, 1.0), (102.0, 0.0)];
+        let mut events = [book(true, &bids, &asks), book(false, &bids, &asks)];
+        trim_snapshots(BookDepth::Top(2), &mut events);
+        assert_eq!(
+            levels(&events[0]),
+            (
+                vec![(100.0, 1.0), (99.0, 1.0)],
+                vec![(101.0, 1.0), (102.0, 0.0)]
+            )
+        );
+        assert_eq!(levels(&events[1]), (bids.to_vec(), asks.to_vec()));
+
+        let mut full = [book(true, &bids, &asks)];
+        trim_snapshots(BookDepth::Full, &mut full);
+        assert_eq!(levels(&full[0]), (bids.to_vec(), asks.to_vec()));
+    }
+}
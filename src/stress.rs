@@ -0,0 +1,332 @@
+use crate::datastructures::{account::Position, client::TradingClient};
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt,
+    time::Duration,
+};
+use tokio::{sync::watch, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
+
+/// Shares of underlying per option contract.
+const OPTION_MULTIPLIER: f64 = 100.0;
+
+/// Sensitivities of one option contract, per share of underlying as quoted.
+#[derive(Debug, Clone)]
+pub struct Greeks {
+    pub underlying: String,
+    pub underlying_price: f64,
+    pub delta: f64,
+    pub gamma: f64,
+    /// Price change for one percentage point of implied volatility.
+    pub vega: f64,
+    /// As a fraction, e.g. 0.25 for 25%.
+    pub implied_volatility: f64,
+}
+
+/// What the stress test knows about each position beyond its market value. Neither Alpaca nor the positions
+/// endpoint provide these, so they come from the caller.
+#[derive(Debug, Clone, Default)]
+pub struct RiskFactors {
+    /// Beta to the market per underlying. Underlyings not listed get 1.0.
+    pub betas: HashMap<String, f64>,
+    /// Greeks per option symbol. Options without greeks are shocked linearly at their market value.
+    pub greeks: HashMap<String, Greeks>,
+}
+
+impl RiskFactors {
+    fn beta(&self, underlying: &str) -> f64 {
+        self.betas.get(underlying).copied().unwrap_or(1.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shock {
+    /// Relative market move, scaled by each underlying's beta. -0.05 is a 5% drop.
+    Market(f64),
+    /// Relative move of one underlying on top of any market move.
+    Symbol { symbol: String, change: f64 },
+    /// Relative move applied to each held underlying in turn. The scenario reports the worst one.
+    EachSymbol(f64),
+    /// Relative change in implied volatility; 0.5 raises every option's vol by half. Only options with greeks
+    /// are affected.
+    Volatility(f64),
+}
+
+/// Named set of shocks applied together.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scenario {
+    pub name: String,
+    pub shocks: Vec<Shock>,
+}
+
+impl Scenario {
+    pub fn new(name: impl Into<String>, shocks: Vec<Shock>) -> Self {
+        Scenario {
+            name: name.into(),
+            shocks,
+        }
+    }
+
+    /// Market -5%, implied volatility +50% and the worst single name -20%.
+    pub fn standard() -> Vec<Scenario> {
+        vec![
+            Scenario::new("Market -5%", vec![Shock::Market(-0.05)]),
+            Scenario::new("Volatility +50%", vec![Shock::Volatility(0.5)]),
+            Scenario::new("Single name -20%", vec![Shock::EachSymbol(-0.2)]),
+        ]
+    }
+}
+
+/// Hypothetical P&L of one scenario.
+#[derive(Debug, Clone)]
+pub struct ScenarioResult {
+    pub scenario: String,
+    pub pnl: f64,
+    /// `pnl` as a fraction of equity.
+    pub pnl_fraction: f64,
+    /// Underlying that produced the result for `Shock::EachSymbol` scenarios.
+    pub worst_symbol: Option<String>,
+    /// P&L per position, worst first.
+    pub positions: Vec<(String, f64)>,
+}
+
+/// Hypothetical P&L of the current portfolio under each scenario. Options with greeks are repriced with a
+/// delta-gamma-vega approximation; everything else moves linearly with its underlying.
+#[derive(Debug, Clone)]
+pub struct StressReport {
+    pub equity: f64,
+    pub scenarios: Vec<ScenarioResult>,
+    /// Option positions without greeks, whose results are less reliable.
+    pub missing_greeks: Vec<String>,
+}
+
+impl StressReport {
+    pub fn run(
+        equity: f64,
+        positions: &[Position],
+        factors: &RiskFactors,
+        scenarios: &[Scenario],
+    ) -> StressReport {
+        let missing_greeks = positions
+            .iter()
+            .filter(|position| {
+                position.asset_class == "us_option"
+                    && !factors.greeks.contains_key(&position.symbol)
+            })
+            .map(|position| position.symbol.clone())
+            .collect();
+
+        StressReport {
+            equity,
+            scenarios: scenarios
+                .iter()
+                .map(|scenario| evaluate(equity, positions, factors, scenario))
+                .collect(),
+            missing_greeks,
+        }
+    }
+
+    /// Fetches the account and positions and stresses them.
+    pub async fn fetch<C: TradingClient>(
+        client: &C,
+        factors: &RiskFactors,
+        scenarios: &[Scenario],
+    ) -> Result<StressReport, Box<dyn Error>> {
+        let account = client.get_account().await?;
+        let positions = client.get_positions().await?;
+        Ok(StressReport::run(
+            account.equity,
+            &positions,
+            factors,
+            scenarios,
+        ))
+    }
+
+    /// Result with the largest loss.
+    pub fn worst(&self) -> Option<&ScenarioResult> {
+        self.scenarios.iter().min_by(|a, b| a.pnl.total_cmp(&b.pnl))
+    }
+}
+
+impl fmt::Display for StressReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Equity: {:.2}", self.equity)?;
+        writeln!(f, "Scenarios:")?;
+        for result in &self.scenarios {
+            write!(
+                f,
+                "  {:<24} {:>12.2} {:>6.1}%",
+                result.scenario,
+                result.pnl,
+                result.pnl_fraction * 100.0
+            )?;
+            match &result.worst_symbol {
+                Some(symbol) => writeln!(f, "  ({})", symbol)?,
+                None => writeln!(f)?,
+            }
+        }
+        if !self.missing_greeks.is_empty() {
+            writeln!(
+                f,
+                "Options without greeks: {}",
+                self.missing_greeks.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn underlying<'a>(position: &'a Position, factors: &'a RiskFactors) -> &'a str {
+    factors
+        .greeks
+        .get(&position.symbol)
+        .map(|greeks| greeks.underlying.as_str())
+        .unwrap_or(&position.symbol)
+}
+
+fn evaluate(
+    equity: f64,
+    positions: &[Position],
+    factors: &RiskFactors,
+    scenario: &Scenario,
+) -> ScenarioResult {
+    let mut market = 0.0;
+    let mut volatility = 0.0;
+    let mut each_symbol = None;
+    let mut symbol_moves: HashMap<&str, f64> = HashMap::new();
+    for shock in &scenario.shocks {
+        match shock {
+            Shock::Market(change) => market += change,
+            Shock::Volatility(change) => volatility += change,
+            Shock::EachSymbol(change) => *each_symbol.get_or_insert(0.0) += change,
+            Shock::Symbol { symbol, change } => {
+                *symbol_moves.entry(symbol.as_str()).or_insert(0.0) += change
+            }
+        }
+    }
+
+    let shocked = |target: Option<&str>| -> Vec<(String, f64)> {
+        positions
+            .iter()
+            .map(|position| {
+                let underlying = underlying(position, factors);
+                let mut change = factors.beta(underlying) * market
+                    + symbol_moves.get(underlying).copied().unwrap_or(0.0);
+                if target == Some(underlying) {
+                    change += each_symbol.unwrap_or(0.0);
+                }
+                (
+                    position.symbol.clone(),
+                    position_pnl(position, factors, change, volatility),
+                )
+            })
+            .collect()
+    };
+
+    let (worst_symbol, mut pnls) = match each_symbol {
+        None => (None, shocked(None)),
+        Some(_) => {
+            // BTreeMap so ties resolve the same way every run.
+            let underlyings: BTreeMap<&str, ()> = positions
+                .iter()
+                .map(|position| (underlying(position, factors), ()))
+                .collect();
+            underlyings
+                .into_keys()
+                .map(|target| (Some(target.to_string()), shocked(Some(target))))
+                .min_by(|a, b| total(&a.1).total_cmp(&total(&b.1)))
+                .unwrap_or_else(|| (None, shocked(None)))
+        }
+    };
+    pnls.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    let pnl = total(&pnls);
+    ScenarioResult {
+        scenario: scenario.name.clone(),
+        pnl,
+        pnl_fraction: if equity != 0.0 { pnl / equity } else { 0.0 },
+        worst_symbol,
+        positions: pnls,
+    }
+}
+
+/// P&L of one position when its underlying moves by `change` and implied volatility by `volatility`, both relative.
+fn position_pnl(position: &Position, factors: &RiskFactors, change: f64, volatility: f64) -> f64 {
+    match factors.greeks.get(&position.symbol) {
+        Some(greeks) => {
+            let price_move = greeks.underlying_price * change;
+            let vol_points = greeks.implied_volatility * volatility * 100.0;
+            position.qty
+                * OPTION_MULTIPLIER
+                * (greeks.delta * price_move
+                    + 0.5 * greeks.gamma * price_move * price_move
+                    + greeks.vega * vol_points)
+        }
+        None => position.market_value * change,
+    }
+}
+
+fn total(pnls: &[(String, f64)]) -> f64 {
+    pnls.iter().map(|(_, pnl)| pnl).sum()
+}
+
+/// Reruns a `StressReport` on a fixed interval, e.g. daily alongside the concentration report. Stops when dropped.
+pub struct StressMonitor {
+    latest: watch::Receiver<Option<StressReport>>,
+    task: JoinHandle<()>,
+}
+
+impl StressMonitor {
+    pub fn spawn<C>(
+        client: C,
+        factors: RiskFactors,
+        scenarios: Vec<Scenario>,
+        interval: Duration,
+        cancel: CancellationToken,
+    ) -> StressMonitor
+    where
+        C: TradingClient + Send + Sync + 'static,
+    {
+        let (sender, latest) = watch::channel(None);
+        let task = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    _ = cancel.cancelled() => return,
+                }
+                let report = StressReport::fetch(&client, &factors, &scenarios)
+                    .await
+                    .map_err(|e| e.to_string());
+                match report {
+                    Ok(report) => {
+                        if sender.send(Some(report)).is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) => tracing::error!(error = %e, "Stress test failed"),
+                }
+            }
+        });
+
+        StressMonitor { latest, task }
+    }
+
+    /// Most recent report, if one has been generated yet.
+    pub fn latest(&self) -> Option<StressReport> {
+        self.latest.borrow().clone()
+    }
+
+    /// Waits for the next report.
+    pub async fn changed(&mut self) -> Option<StressReport> {
+        self.latest.changed().await.ok()?;
+        self.latest.borrow().clone()
+    }
+}
+
+impl Drop for StressMonitor {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -0,0 +1,281 @@
+use crate::datastructures::{
+    client::TradingClient,
+    order::{CancelOutcome, STRATEGY_TAG},
+};
+use futures_util::FutureExt;
+use std::{
+    any::Any,
+    collections::HashMap,
+    error::Error,
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    sync::{broadcast, Notify},
+    task::JoinHandle,
+};
+
+type CancelHook = Arc<dyn Fn(String) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+/// What happens to a component after it panics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanicAction {
+    /// Stop the component until `Supervisor::resume` is called.
+    Pause,
+    /// Start the component again after `delay`, up to `max_restarts` times, then pause it.
+    Restart { max_restarts: u32, delay: Duration },
+    /// Abort the whole process, for components whose failure makes continuing unsafe.
+    Abort,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PanicPolicy {
+    pub action: PanicAction,
+    /// Cancel the open orders tagged with the component's name as their strategy.
+    pub cancel_orders: bool,
+}
+
+impl Default for PanicPolicy {
+    fn default() -> Self {
+        PanicPolicy {
+            action: PanicAction::Pause,
+            cancel_orders: false,
+        }
+    }
+}
+
+/// Panic policy per component name, with a fallback for components that are not listed.
+#[derive(Debug, Clone, Default)]
+pub struct PanicPolicies {
+    pub default: PanicPolicy,
+    pub overrides: HashMap<String, PanicPolicy>,
+}
+
+impl PanicPolicies {
+    pub fn with(mut self, component: impl Into<String>, policy: PanicPolicy) -> Self {
+        self.overrides.insert(component.into(), policy);
+        self
+    }
+
+    pub fn get(&self, component: &str) -> PanicPolicy {
+        self.overrides
+            .get(component)
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+/// A caught panic.
+#[derive(Debug, Clone)]
+pub struct PanicReport {
+    pub component: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentState {
+    Running,
+    /// Stopped after a panic. Waiting for `Supervisor::resume`.
+    Paused,
+    /// Finished on its own.
+    Finished,
+}
+
+/// Runs a synchronous callback, turning a panic into a `PanicReport` instead of unwinding into the caller.
+pub fn catch<R>(component: &str, callback: impl FnOnce() -> R) -> Result<R, PanicReport> {
+    panic::catch_unwind(AssertUnwindSafe(callback)).map_err(|payload| PanicReport {
+        component: component.to_string(),
+        message: panic_message(payload.as_ref()),
+    })
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Unknown panic".to_string())
+}
+
+/// Cancels every open order whose `strategy` tag matches. Tags are only visible when the client has a journal.
+pub async fn cancel_tagged_orders<C: TradingClient>(
+    client: &C,
+    strategy: &str,
+) -> Result<Vec<CancelOutcome>, Box<dyn Error>> {
+    let orders = client.get_open_orders().await?;
+    let mut outcomes = Vec::new();
+    for order in orders {
+        if order.metadata.get(STRATEGY_TAG).map(String::as_str) == Some(strategy) {
+            outcomes.push(client.cancel_order(&order.id).await?);
+        }
+    }
+    Ok(outcomes)
+}
+
+struct Component {
+    state: ComponentState,
+    resume: Arc<Notify>,
+    task: JoinHandle<()>,
+}
+
+/// Runs components as separate tasks so a panic in one is caught, reported and handled according to its
+/// policy while the rest keep running. Dropping the supervisor stops every component.
+pub struct Supervisor {
+    policies: PanicPolicies,
+    components: Arc<Mutex<HashMap<String, Component>>>,
+    reports: broadcast::Sender<PanicReport>,
+    cancel_hook: Option<CancelHook>,
+}
+
+impl Supervisor {
+    pub fn new(policies: PanicPolicies) -> Self {
+        Supervisor {
+            policies,
+            components: Arc::new(Mutex::new(HashMap::new())),
+            reports: broadcast::channel(64).0,
+            cancel_hook: None,
+        }
+    }
+
+    /// Client used to cancel a panicked component's orders when its policy asks for it.
+    pub fn cancel_orders_with<C>(mut self, client: C) -> Self
+    where
+        C: TradingClient + Clone + Send + Sync + 'static,
+    {
+        self.cancel_hook = Some(Arc::new(move |component: String| {
+            let client = client.clone();
+            Box::pin(async move {
+                let result = cancel_tagged_orders(&client, &component)
+                    .await
+                    .map_err(|e| e.to_string());
+                match result {
+                    Ok(outcomes) => {
+                        tracing::info!(%component, canceled = outcomes.len(), "Canceled orders after panic")
+                    }
+                    Err(e) => {
+                        tracing::error!(%component, error = %e, "Failed to cancel orders after panic")
+                    }
+                }
+            })
+        }));
+        self
+    }
+
+    /// Starts a component. `factory` is called again for every restart or resume.
+    /// Replaces any running component with the same name.
+    pub fn spawn<F, Fut>(&self, component: impl Into<String>, mut factory: F)
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let name = component.into();
+        let policy = self.policies.get(&name);
+        let resume = Arc::new(Notify::new());
+        let components = self.components.clone();
+        let reports = self.reports.clone();
+        let cancel_hook = self.cancel_hook.clone();
+
+        // Held until the component is registered so the task cannot update its state before that.
+        let mut registered = self.components.lock().unwrap();
+        let task = tokio::spawn({
+            let name = name.clone();
+            let resume = resume.clone();
+            async move {
+                let mut restarts = 0;
+                loop {
+                    set_state(&components, &name, ComponentState::Running);
+                    let Err(payload) = AssertUnwindSafe(factory()).catch_unwind().await else {
+                        set_state(&components, &name, ComponentState::Finished);
+                        return;
+                    };
+
+                    let report = PanicReport {
+                        component: name.clone(),
+                        message: panic_message(payload.as_ref()),
+                    };
+                    tracing::error!(component = %report.component, message = %report.message, "Component panicked");
+                    let _ = reports.send(report);
+
+                    if policy.cancel_orders {
+                        if let Some(cancel_hook) = &cancel_hook {
+                            cancel_hook(name.clone()).await;
+                        }
+                    }
+
+                    match policy.action {
+                        PanicAction::Abort => std::process::abort(),
+                        PanicAction::Restart {
+                            max_restarts,
+                            delay,
+                        } if restarts < max_restarts => {
+                            restarts += 1;
+                            tokio::time::sleep(delay).await;
+                        }
+                        _ => {
+                            set_state(&components, &name, ComponentState::Paused);
+                            resume.notified().await;
+                            restarts = 0;
+                        }
+                    }
+                }
+            }
+        });
+
+        let previous = registered.insert(
+            name,
+            Component {
+                state: ComponentState::Running,
+                resume,
+                task,
+            },
+        );
+        if let Some(previous) = previous {
+            previous.task.abort();
+        }
+    }
+
+    /// Restarts a paused component. Returns false if it is not paused.
+    pub fn resume(&self, component: &str) -> bool {
+        match self.components.lock().unwrap().get(component) {
+            Some(entry) if entry.state == ComponentState::Paused => {
+                entry.resume.notify_one();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn state(&self, component: &str) -> Option<ComponentState> {
+        self.components
+            .lock()
+            .unwrap()
+            .get(component)
+            .map(|entry| entry.state)
+    }
+
+    /// Every panic caught from now on.
+    pub fn reports(&self) -> broadcast::Receiver<PanicReport> {
+        self.reports.subscribe()
+    }
+}
+
+impl Drop for Supervisor {
+    fn drop(&mut self) {
+        for component in self.components.lock().unwrap().values() {
+            component.task.abort();
+        }
+    }
+}
+
+fn set_state(
+    components: &Mutex<HashMap<String, Component>>,
+    component: &str,
+    state: ComponentState,
+) {
+    if let Some(entry) = components.lock().unwrap().get_mut(component) {
+        entry.state = state;
+    }
+}
@@ -0,0 +1,165 @@
+use crate::{
+    datastructures::{
+        client::TradingClient,
+        order::{CancelOutcome, OrderResponse},
+    },
+    time,
+};
+use std::{collections::HashSet, error::Error, time::Duration};
+use tokio::{
+    sync::{mpsc, watch},
+    task::JoinHandle,
+};
+use tokio_util::sync::CancellationToken;
+
+/// Which working orders a sweep cancels.
+#[derive(Debug, Clone, Default)]
+pub struct SweepConfig {
+    /// Cancel orders older than this.
+    pub max_age: Option<Duration>,
+    /// Cancel orders for symbols outside this set. `None` keeps every symbol.
+    pub universe: Option<HashSet<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SweepReason {
+    Expired { age: Duration },
+    OutsideUniverse,
+}
+
+#[derive(Debug, Clone)]
+pub struct SweptOrder {
+    pub order: OrderResponse,
+    pub reason: SweepReason,
+    /// How the cancel ended. `None` when it failed.
+    pub outcome: Option<CancelOutcome>,
+}
+
+/// Outcome of a single sweep.
+#[derive(Debug, Clone, Default)]
+pub struct SweepReport {
+    pub swept: Vec<SweptOrder>,
+    /// Orders that matched the criteria but could not be canceled, with the error message.
+    pub failed: Vec<(SweptOrder, String)>,
+}
+
+fn sweep_reason(order: &OrderResponse, config: &SweepConfig) -> Option<SweepReason> {
+    if let Some(universe) = &config.universe {
+        if !universe.contains(&order.symbol) {
+            return Some(SweepReason::OutsideUniverse);
+        }
+    }
+
+    let max_age = config.max_age?;
+    let age = time::age(&order.created_at)?;
+    (age > max_age).then_some(SweepReason::Expired { age })
+}
+
+/// Cancels every open order matching `config` and reports what was swept.
+pub async fn sweep_orders<C: TradingClient>(
+    client: &C,
+    config: &SweepConfig,
+) -> Result<SweepReport, Box<dyn Error>> {
+    let mut report = SweepReport::default();
+
+    let orders = client.get_open_orders().await?;
+    for order in orders {
+        let Some(reason) = sweep_reason(&order, config) else {
+            continue;
+        };
+
+        let result = client
+            .cancel_order(&order.id)
+            .await
+            .map_err(|e| e.to_string());
+        match result {
+            Ok(outcome) => report.swept.push(SweptOrder {
+                order,
+                reason,
+                outcome: Some(outcome),
+            }),
+            Err(e) => report.failed.push((
+                SweptOrder {
+                    order,
+                    reason,
+                    outcome: None,
+                },
+                e,
+            )),
+        }
+    }
+
+    Ok(report)
+}
+
+/// Runs `sweep_orders` on a fixed interval in the background. Stops when dropped.
+pub struct OrderSweeper {
+    config: watch::Sender<SweepConfig>,
+    reports: mpsc::Receiver<SweepReport>,
+    task: JoinHandle<()>,
+}
+
+impl OrderSweeper {
+    /// Cancelling `cancel` stops the sweeper after any sweep already in progress completes.
+    pub fn spawn<C>(
+        client: C,
+        config: SweepConfig,
+        interval: Duration,
+        cancel: CancellationToken,
+    ) -> OrderSweeper
+    where
+        C: TradingClient + Send + Sync + 'static,
+    {
+        let (config, config_receiver) = watch::channel(config);
+        let (report_sender, reports) = mpsc::channel(16);
+
+        let task = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    _ = cancel.cancelled() => return,
+                }
+                let config = config_receiver.borrow().clone();
+                let report = sweep_orders(&client, &config)
+                    .await
+                    .map_err(|e| e.to_string());
+                match report {
+                    Ok(report) if report.swept.is_empty() && report.failed.is_empty() => {}
+                    Ok(report) => {
+                        if report_sender.send(report).await.is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) => tracing::error!(error = %e, "Order sweep failed"),
+                }
+            }
+        });
+
+        OrderSweeper {
+            config,
+            reports,
+            task,
+        }
+    }
+
+    /// Replaces the active universe, e.g. after a strategy restart changed its symbols.
+    pub fn set_universe(&self, universe: Option<HashSet<String>>) {
+        self.config.send_modify(|config| config.universe = universe);
+    }
+
+    pub fn set_max_age(&self, max_age: Option<Duration>) {
+        self.config.send_modify(|config| config.max_age = max_age);
+    }
+
+    /// Next sweep that canceled (or failed to cancel) at least one order.
+    pub async fn next_report(&mut self) -> Option<SweepReport> {
+        self.reports.recv().await
+    }
+}
+
+impl Drop for OrderSweeper {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -0,0 +1,357 @@
+use reqwest::{Client as HttpClient, Request, Response};
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// One REST request and the response it got. Request headers, which carry the credentials, are not kept.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Interaction {
+    method: String,
+    url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+    status: u16,
+    #[serde(default)]
+    headers: Vec<(String, String)>,
+    response: String,
+}
+
+#[derive(Debug)]
+enum CassetteMode {
+    Record,
+    /// Which recorded interactions have been served.
+    Replay(Vec<bool>),
+}
+
+#[derive(Debug)]
+struct CassetteState {
+    mode: CassetteMode,
+    interactions: Vec<Interaction>,
+}
+
+/// REST fixtures for integration tests, set on a client with `AlpacaClient::with_cassette`. Recording sends
+/// requests for real and saves each one with its response to a JSON file; replaying serves the saved
+/// responses instead, so tests run in CI without credentials or network. A replayed request is matched to the
+/// first unserved interaction with the same method and URL, in recorded order, so repeated calls get successive
+/// responses.
+#[derive(Debug, Clone)]
+pub struct Cassette {
+    path: PathBuf,
+    state: Arc<Mutex<CassetteState>>,
+}
+
+impl Cassette {
+    /// Records to `path`, replacing whatever it held. The file is rewritten after every request.
+    pub fn record(path: impl Into<PathBuf>) -> Cassette {
+        Cassette {
+            path: path.into(),
+            state: Arc::new(Mutex::new(CassetteState {
+                mode: CassetteMode::Record,
+                interactions: vec![],
+            })),
+        }
+    }
+
+    /// Replays the interactions recorded to `path`.
+    pub fn replay(path: impl Into<PathBuf>) -> io::Result<Cassette> {
+        let path = path.into();
+        let interactions: Vec<Interaction> = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        Ok(Cassette {
+            path,
+            state: Arc::new(Mutex::new(CassetteState {
+                mode: CassetteMode::Replay(vec![false; interactions.len()]),
+                interactions,
+            })),
+        })
+    }
+
+    /// Replays `path` if it exists and records it otherwise, so a test records its fixture on the first run
+    /// with credentials and replays it from then on.
+    pub fn replay_or_record(path: impl Into<PathBuf>) -> io::Result<Cassette> {
+        let path = path.into();
+        if path.exists() {
+            Cassette::replay(path)
+        } else {
+            Ok(Cassette::record(path))
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Recorded interactions not yet replayed. Zero once a test has made every request it recorded.
+    pub fn unplayed(&self) -> usize {
+        match &self.state.lock().unwrap().mode {
+            CassetteMode::Record => 0,
+            CassetteMode::Replay(played) => played.iter().filter(|played| !**played).count(),
+        }
+    }
+
+    pub(crate) async fn execute(
+        &self,
+        http_client: &HttpClient,
+        request: Request,
+    ) -> Result<Response, Box<dyn Error + Send + Sync>> {
+        let method = request.method().to_string();
+        let url = request.url().to_string();
+        if matches!(self.state.lock().unwrap().mode, CassetteMode::Replay(_)) {
+            return self.play(&method, &url);
+        }
+
+        let body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map(|body| String::from_utf8_lossy(body).into_owned());
+        let response = http_client.execute(request).await?;
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let response = response.text().await?;
+        let interaction = Interaction {
+            method,
+            url,
+            body,
+            status,
+            headers,
+            response,
+        };
+        let replayed = interaction.to_response()?;
+
+        let mut state = self.state.lock().unwrap();
+        state.interactions.push(interaction);
+        fs::write(
+            &self.path,
+            serde_json::to_string_pretty(&state.interactions)?,
+        )?;
+        Ok(replayed)
+    }
+
+    fn play(&self, method: &str, url: &str) -> Result<Response, Box<dyn Error + Send + Sync>> {
+        let mut state = self.state.lock().unwrap();
+        let CassetteState { mode, interactions } = &mut *state;
+        let CassetteMode::Replay(played) = mode else {
+            unreachable!("only replaying cassettes play");
+        };
+        let index = interactions
+            .iter()
+            .zip(played.iter())
+            .position(|(interaction, played)| {
+                !played && interaction.method == method && interaction.url == url
+            })
+            .ok_or_else(|| {
+                format!(
+                    "No recorded response left for {} {} in {}",
+                    method,
+                    url,
+                    self.path.display()
+                )
+            })?;
+        played[index] = true;
+        interactions[index].to_response()
+    }
+}
+
+impl Interaction {
+    fn to_response(&self) -> Result<Response, Box<dyn Error + Send + Sync>> {
+        let mut response = http::Response::builder().status(self.status);
+        for (name, value) in &self.headers {
+            response = response.header(name, value);
+        }
+        Ok(response.body(self.response.clone())?.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        alpaca::{AlpacaClient, AlpacaError},
+        datastructures::{
+            client::TradingClient,
+            config::Config,
+            order::{CancelOutcome, Order, OrderSide, OrderType},
+        },
+    };
+    use serde_json::{json, Value};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    const ORDER: &str = r#"{"id":"61e69015","client_order_id":"eb9e2aaa","symbol":"AAPL","status":"accepted","created_at":"2024-05-01T14:30:00Z","side":"buy","type":"limit","qty":"10","filled_qty":"0","limit_price":"190"}"#;
+
+    fn cassette(name: &str, interactions: Value) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("cassette-{}-{}.json", name, std::process::id()));
+        fs::write(&path, interactions.to_string()).unwrap();
+        path
+    }
+
+    fn alpaca(cassette: &Cassette) -> AlpacaClient {
+        let config = Config::builder()
+            .alpaca_api_key("key".to_string())
+            .alpaca_secret_key("secret".to_string())
+            .build()
+            .unwrap();
+        AlpacaClient::new(&config)
+            .unwrap()
+            .with_cassette(cassette.clone())
+    }
+
+    fn limit_buy() -> Order {
+        Order::builder()
+            .symbol("AAPL")
+            .quantity(10.0)
+            .side(OrderSide::Buy)
+            .order_type(OrderType::Limit)
+            .limit_price(190.0)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn replays_order_placement() {
+        let path = cassette(
+            "placement",
+            json!([
+                {
+                    "method": "GET",
+                    "url": "https://paper-api.alpaca.markets/v2/assets/AAPL",
+                    "status": 200,
+                    "response": r#"{"symbol":"AAPL","exchange":"NASDAQ","class":"us_equity","status":"active","tradable":true,"fractionable":true}"#
+                },
+                {
+                    "method": "POST",
+                    "url": "https://paper-api.alpaca.markets/v2/orders",
+                    "status": 200,
+                    "response": ORDER
+                },
+                {
+                    "method": "POST",
+                    "url": "https://paper-api.alpaca.markets/v2/orders",
+                    "status": 403,
+                    "response": r#"{"code":40310000,"message":"insufficient buying power"}"#
+                }
+            ]),
+        );
+        let cassette = Cassette::replay(&path).unwrap();
+        let client = alpaca(&cassette);
+
+        let asset = client.get_asset("AAPL").await.unwrap();
+        assert!(asset.tradable && asset.fractionable);
+        client.create_order(&limit_buy()).await.unwrap();
+        let error = client.create_order(&limit_buy()).await.unwrap_err();
+        let error = error.downcast_ref::<AlpacaError>().unwrap();
+        assert_eq!((error.status, error.code), (403, 40310000));
+        assert_eq!(cassette.unplayed(), 0);
+
+        let error = client.create_order(&limit_buy()).await.unwrap_err();
+        assert!(error.to_string().starts_with("No recorded response left"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn replays_a_cancellation() {
+        let url = "https://paper-api.alpaca.markets/v2/orders/61e69015";
+        let path = cassette(
+            "cancel",
+            json!([
+                {"method": "DELETE", "url": url, "status": 204, "response": ""},
+                {
+                    "method": "GET",
+                    "url": url,
+                    "status": 200,
+                    "response": ORDER.replace("accepted", "canceled")
+                },
+                {
+                    "method": "DELETE",
+                    "url": url,
+                    "status": 422,
+                    "response": r#"{"code":42210000,"message":"order is not cancelable"}"#
+                },
+                {
+                    "method": "GET",
+                    "url": url,
+                    "status": 200,
+                    "response": ORDER
+                        .replace("accepted", "filled")
+                        .replace(r#""filled_qty":"0""#, r#""filled_qty":"10""#)
+                }
+            ]),
+        );
+        let cassette = Cassette::replay(&path).unwrap();
+        let client = alpaca(&cassette);
+
+        let outcome = client.cancel_order("61e69015").await.unwrap();
+        assert!(matches!(outcome, CancelOutcome::Canceled(_)));
+        let outcome = client.cancel_order("61e69015").await.unwrap();
+        assert!(matches!(outcome, CancelOutcome::Filled(_)));
+        assert_eq!(outcome.order().filled_qty, 10.0);
+        assert_eq!(cassette.unplayed(), 0);
+        fs::remove_file(&path).unwrap();
+    }
+
+    /// Answers each connection with the next of `responses` as a bare HTTP/1.1 response.
+    async fn serve(responses: Vec<&'static str>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            for body in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = vec![0; 4096];
+                let _ = stream.read(&mut request).await.unwrap();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        format!("http://{}", address)
+    }
+
+    #[tokio::test]
+    async fn replays_recorded_responses_in_order() {
+        let base = serve(vec![r#"{"status":"new"}"#, r#"{"status":"filled"}"#]).await;
+        let url = format!("{}/v2/orders/61e69015", base);
+        let path =
+            std::env::temp_dir().join(format!("cassette-record-{}.json", std::process::id()));
+        let http_client = HttpClient::builder().no_proxy().build().unwrap();
+
+        let recording = Cassette::record(&path);
+        for expected in ["new", "filled"] {
+            let request = http_client
+                .get(&url)
+                .header("APCA-API-SECRET-KEY", "secret")
+                .build()
+                .unwrap();
+            let response = recording.execute(&http_client, request).await.unwrap();
+            let body: Value = response.json().await.unwrap();
+            assert_eq!(body["status"], expected);
+        }
+        let saved = fs::read_to_string(&path).unwrap();
+        assert!(!saved.contains("secret"));
+
+        // The server has stopped answering, so these can only come from the file.
+        let replaying = Cassette::replay(&path).unwrap();
+        assert_eq!(replaying.unplayed(), 2);
+        for expected in ["new", "filled"] {
+            let request = http_client.get(&url).build().unwrap();
+            let response = replaying.execute(&http_client, request).await.unwrap();
+            assert_eq!(response.headers()["content-type"], "application/json");
+            let body: Value = response.json().await.unwrap();
+            assert_eq!(body["status"], expected);
+        }
+        assert_eq!(replaying.unplayed(), 0);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,592 @@
+use crate::{
+    datastructures::{
+        account::{Account, AccountActivity, Position},
+        asset::Asset,
+        client::{MarketDataClient, SubscriptionParams, TradingClient},
+        market::{Bar, BarAdjustment, Quote},
+        order::{CancelOutcome, Order, OrderResponse, OrderSide, OrderStatus},
+    },
+    sim::sim_asset,
+    time,
+};
+use async_trait::async_trait;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    error::Error,
+    sync::{Arc, Mutex},
+};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+/// What happens to the next order submitted to a `MockTradingClient`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockOrder {
+    /// Accepted and left working until filled with `MockTradingClient::fill` or cancelled.
+    Rest,
+    /// Accepted and filled in full at `price`.
+    Fill { price: f64 },
+    /// Accepted and filled for `quantity` at `price`, with the rest left working.
+    PartialFill { quantity: f64, price: f64 },
+    /// Rejected: `create_order` returns the message as an error and the order is recorded as rejected.
+    Reject(String),
+    /// Accepted by `create_order`, then recorded as rejected, as a broker does when an order fails checks
+    /// after it was taken.
+    LateReject,
+}
+
+/// A `TradingClient` method, for forcing it to fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MockCall {
+    CreateOrder,
+    GetOpenOrders,
+    GetOrder,
+    CancelOrder,
+    GetAsset,
+    ListAssets,
+    GetLatestQuote,
+    GetDailyBars,
+    GetAccount,
+    GetPositions,
+    GetAccountActivities,
+    Subscribe,
+}
+
+struct MockState {
+    script: VecDeque<MockOrder>,
+    errors: HashMap<MockCall, VecDeque<String>>,
+    submitted: Vec<Order>,
+    cancels: Vec<String>,
+    held: HashSet<String>,
+    orders: Vec<OrderResponse>,
+    positions: BTreeMap<String, Position>,
+    cash: f64,
+    unsettled: f64,
+    quotes: HashMap<String, Quote>,
+    bars: HashMap<String, Vec<Bar>>,
+    assets: BTreeMap<String, Asset>,
+    activities: Vec<AccountActivity>,
+}
+
+impl MockState {
+    fn error(&mut self, call: MockCall) -> Result<(), Box<dyn Error>> {
+        match self.errors.get_mut(&call).and_then(VecDeque::pop_front) {
+            Some(message) => Err(message.into()),
+            None => Ok(()),
+        }
+    }
+
+    fn order(&mut self, order_id: &str) -> Option<&mut OrderResponse> {
+        self.orders.iter_mut().find(|order| order.id == order_id)
+    }
+
+    /// Fills up to `quantity` of a working order and books it into cash and positions.
+    fn fill(&mut self, order_id: &str, quantity: f64, price: f64) -> Option<OrderResponse> {
+        let order = self.order(order_id)?;
+        if order.status.is_terminal() {
+            return None;
+        }
+        let remaining = order.qty.unwrap_or(0.0) - order.filled_qty;
+        let quantity = quantity.min(remaining);
+        if quantity <= 0.0 {
+            return None;
+        }
+        let filled = order.filled_qty + quantity;
+        let average = order.filled_avg_price.unwrap_or(0.0);
+        order.filled_avg_price = Some((average * order.filled_qty + price * quantity) / filled);
+        order.filled_qty = filled;
+        order.status = if filled + 1e-9 >= order.qty.unwrap_or(0.0) {
+            OrderStatus::Filled
+        } else {
+            OrderStatus::PartiallyFilled
+        };
+        let order = order.clone();
+
+        let signed = match order.side {
+            OrderSide::Buy => quantity,
+            OrderSide::Sell => -quantity,
+        };
+        self.cash -= signed * price;
+        let position = self
+            .positions
+            .entry(order.symbol.clone())
+            .or_insert_with(|| mock_position(&order.symbol));
+        let qty = position.qty + signed;
+        if qty == 0.0 || qty.signum() != position.qty.signum() {
+            // Opened, or flipped through flat.
+            position.avg_entry_price = price;
+        } else if qty.abs() > position.qty.abs() {
+            position.avg_entry_price =
+                (position.avg_entry_price * position.qty + price * signed) / qty;
+        }
+        position.qty = qty;
+        position.current_price = Some(price);
+        position.market_value = qty * price;
+        position.unrealized_pl = (price - position.avg_entry_price) * qty;
+        if qty.abs() < 1e-9 {
+            self.positions.remove(&order.symbol);
+        }
+        Some(order)
+    }
+}
+
+/// `TradingClient` with scripted responses for unit-testing strategy code without a network. Orders are
+/// captured for assertions and handled as queued with `push_order`, resting by default; any method can be made
+/// to fail with `fail`. Fills are booked into positions and cash.
+#[derive(Clone)]
+pub struct MockTradingClient {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockTradingClient {
+    /// Starts with `cash`, no positions and no market data.
+    pub fn with_cash(cash: f64) -> Self {
+        MockTradingClient {
+            state: Arc::new(Mutex::new(MockState {
+                script: VecDeque::new(),
+                errors: HashMap::new(),
+                submitted: vec![],
+                cancels: vec![],
+                held: HashSet::new(),
+                orders: vec![],
+                positions: BTreeMap::new(),
+                cash,
+                unsettled: 0.0,
+                quotes: HashMap::new(),
+                bars: HashMap::new(),
+                assets: BTreeMap::new(),
+                activities: vec![],
+            })),
+        }
+    }
+
+    /// Queues how the next submitted order is handled.
+    pub fn push_order(&self, response: MockOrder) {
+        self.state.lock().unwrap().script.push_back(response);
+    }
+
+    /// Makes the next call to `call` fail with `message`. Queue several to fail several calls in a row.
+    pub fn fail(&self, call: MockCall, message: impl Into<String>) {
+        self.state
+            .lock()
+            .unwrap()
+            .errors
+            .entry(call)
+            .or_default()
+            .push_back(message.into());
+    }
+
+    /// Fills up to `quantity` of a working order at `price`. Returns the order as updated, or `None` if there
+    /// is no such working order.
+    pub fn fill(&self, order_id: &str, quantity: f64, price: f64) -> Option<OrderResponse> {
+        self.state.lock().unwrap().fill(order_id, quantity, price)
+    }
+
+    /// Ends a working order as the broker would, e.g. `Expired` or `Canceled`.
+    pub fn close(&self, order_id: &str, status: OrderStatus) -> Option<OrderResponse> {
+        let mut state = self.state.lock().unwrap();
+        let order = state.order(order_id)?;
+        order.status = status;
+        Some(order.clone())
+    }
+
+    /// Leaves a working order working when it is cancelled, as when the cancel has not reached the venue yet,
+    /// so `cancel_order` reports it `Unconfirmed`.
+    pub fn hold(&self, order_id: &str) {
+        self.state.lock().unwrap().held.insert(order_id.to_string());
+    }
+
+    pub fn set_quote(&self, quote: Quote) {
+        let mut state = self.state.lock().unwrap();
+        state.quotes.insert(quote.symbol.clone(), quote);
+    }
+
+    /// Daily bars served for the symbol, oldest first. They are returned as given whatever the adjustment.
+    pub fn set_bars(&self, symbol: &str, bars: Vec<Bar>) {
+        let mut state = self.state.lock().unwrap();
+        state.bars.insert(symbol.to_string(), bars);
+    }
+
+    /// Overrides the tradable, shortable and fractionable asset served for unlisted symbols.
+    pub fn set_asset(&self, asset: Asset) {
+        let mut state = self.state.lock().unwrap();
+        state.assets.insert(asset.symbol.clone(), asset);
+    }
+
+    /// Replaces the position in its symbol.
+    pub fn set_position(&self, position: Position) {
+        let mut state = self.state.lock().unwrap();
+        state.positions.insert(position.symbol.clone(), position);
+    }
+
+    /// Part of the cash that has not settled and cannot be spent yet. Settle it by setting it back to zero.
+    pub fn set_unsettled(&self, amount: f64) {
+        self.state.lock().unwrap().unsettled = amount;
+    }
+
+    /// Adds a non-trading account activity, booking its amount into cash.
+    pub fn push_activity(&self, activity: AccountActivity) {
+        let mut state = self.state.lock().unwrap();
+        state.cash += activity.net_amount;
+        state.activities.push(activity);
+    }
+
+    /// Every order passed to `create_order`, rejected ones included, in order.
+    pub fn submitted(&self) -> Vec<Order> {
+        self.state.lock().unwrap().submitted.clone()
+    }
+
+    /// Ids of every order passed to `cancel_order`, in order.
+    pub fn cancels(&self) -> Vec<String> {
+        self.state.lock().unwrap().cancels.clone()
+    }
+
+    /// Every order as the broker last reported it, oldest first.
+    pub fn orders(&self) -> Vec<OrderResponse> {
+        self.state.lock().unwrap().orders.clone()
+    }
+}
+
+#[async_trait]
+impl TradingClient for MockTradingClient {
+    async fn create_order(&self, order: &Order) -> Result<(), Box<dyn Error>> {
+        let mut state = self.state.lock().unwrap();
+        state.submitted.push(order.clone());
+        state.error(MockCall::CreateOrder)?;
+
+        let id = format!("mock-{}", state.orders.len() + 1);
+        let response = state.script.pop_front().unwrap_or(MockOrder::Rest);
+        state.orders.push(OrderResponse {
+            id: id.clone(),
+            client_order_id: order.client_order_id.clone().unwrap_or_else(|| id.clone()),
+            symbol: order.symbol.clone(),
+            status: match response {
+                MockOrder::Reject(_) | MockOrder::LateReject => OrderStatus::Rejected,
+                _ => OrderStatus::New,
+            },
+            created_at: time::format_rfc3339(time::now_nanos()),
+            side: order.side,
+            order_type: order.order_type,
+            qty: Some(order.quantity),
+            filled_qty: 0.0,
+            filled_avg_price: None,
+            limit_price: order.limit_price,
+            stop_price: order.stop_price,
+            metadata: order.metadata.clone(),
+        });
+        match response {
+            MockOrder::Rest => {}
+            MockOrder::Fill { price } => {
+                state.fill(&id, order.quantity, price);
+            }
+            MockOrder::PartialFill { quantity, price } => {
+                state.fill(&id, quantity, price);
+            }
+            MockOrder::Reject(message) => return Err(message.into()),
+            MockOrder::LateReject => {}
+        }
+        Ok(())
+    }
+
+    async fn get_open_orders(&self) -> Result<Vec<OrderResponse>, Box<dyn Error>> {
+        let mut state = self.state.lock().unwrap();
+        state.error(MockCall::GetOpenOrders)?;
+        Ok(state
+            .orders
+            .iter()
+            .filter(|order| !order.status.is_terminal())
+            .cloned()
+            .collect())
+    }
+
+    async fn get_order(&self, order_id: &str) -> Result<OrderResponse, Box<dyn Error>> {
+        let mut state = self.state.lock().unwrap();
+        state.error(MockCall::GetOrder)?;
+        state
+            .order(order_id)
+            .cloned()
+            .ok_or_else(|| format!("No order {}", order_id).into())
+    }
+
+    async fn get_order_by_client_id(
+        &self,
+        client_order_id: &str,
+    ) -> Result<OrderResponse, Box<dyn Error>> {
+        let mut state = self.state.lock().unwrap();
+        state.error(MockCall::GetOrder)?;
+        state
+            .orders
+            .iter()
+            .find(|order| order.client_order_id == client_order_id)
+            .cloned()
+            .ok_or_else(|| format!("No order {}", client_order_id).into())
+    }
+
+    async fn cancel_order(&self, order_id: &str) -> Result<CancelOutcome, Box<dyn Error>> {
+        let mut state = self.state.lock().unwrap();
+        state.cancels.push(order_id.to_string());
+        state.error(MockCall::CancelOrder)?;
+        let held = state.held.contains(order_id);
+        let order = state
+            .order(order_id)
+            .ok_or_else(|| format!("No order {}", order_id))?;
+        if !order.status.is_terminal() && !held {
+            order.status = OrderStatus::Canceled;
+        }
+        Ok(CancelOutcome::from_order(order.clone()))
+    }
+
+    /// The asset set with `set_asset`, or one that can be traded every way.
+    async fn get_asset(&self, symbol: &str) -> Result<Asset, Box<dyn Error>> {
+        let mut state = self.state.lock().unwrap();
+        state.error(MockCall::GetAsset)?;
+        Ok(state
+            .assets
+            .get(symbol)
+            .cloned()
+            .unwrap_or_else(|| sim_asset(symbol)))
+    }
+
+    /// Assets set with `set_asset`.
+    async fn list_assets(&self) -> Result<Vec<Asset>, Box<dyn Error>> {
+        let mut state = self.state.lock().unwrap();
+        state.error(MockCall::ListAssets)?;
+        Ok(state.assets.values().cloned().collect())
+    }
+
+    async fn get_account(&self) -> Result<Account, Box<dyn Error>> {
+        let mut state = self.state.lock().unwrap();
+        state.error(MockCall::GetAccount)?;
+        let market_value = |long: bool| {
+            state
+                .positions
+                .values()
+                .filter(|position| (position.qty > 0.0) == long)
+                .fold(0.0, |total, position| total + position.market_value)
+        };
+        let (long, short) = (market_value(true), market_value(false));
+        Ok(Account {
+            id: "mock".to_string(),
+            status: "ACTIVE".to_string(),
+            currency: "USD".to_string(),
+            cash: state.cash,
+            equity: state.cash + long + short,
+            last_equity: state.cash + long + short,
+            buying_power: state.cash.max(0.0),
+            long_market_value: long,
+            short_market_value: short,
+            non_marginable_buying_power: Some((state.cash - state.unsettled).max(0.0)),
+        })
+    }
+
+    async fn get_positions(&self) -> Result<Vec<Position>, Box<dyn Error>> {
+        let mut state = self.state.lock().unwrap();
+        state.error(MockCall::GetPositions)?;
+        Ok(state.positions.values().cloned().collect())
+    }
+
+    async fn get_account_activities(
+        &self,
+        since: &str,
+    ) -> Result<Vec<AccountActivity>, Box<dyn Error>> {
+        let mut state = self.state.lock().unwrap();
+        state.error(MockCall::GetAccountActivities)?;
+        let mut activities: Vec<AccountActivity> = state
+            .activities
+            .iter()
+            .filter(|activity| activity.date.as_str() >= since)
+            .cloned()
+            .collect();
+        activities.sort_by(|a, b| a.date.cmp(&b.date));
+        Ok(activities)
+    }
+}
+
+#[async_trait]
+impl MarketDataClient for MockTradingClient {
+    async fn get_latest_quote(&self, symbol: &str) -> Result<Quote, Box<dyn Error>> {
+        let mut state = self.state.lock().unwrap();
+        state.error(MockCall::GetLatestQuote)?;
+        state
+            .quotes
+            .get(symbol)
+            .cloned()
+            .ok_or_else(|| format!("No quote for {}", symbol).into())
+    }
+
+    async fn get_daily_bars(
+        &self,
+        symbol: &str,
+        start: &str,
+        end: &str,
+        _adjustment: BarAdjustment,
+    ) -> Result<Vec<Bar>, Box<dyn Error>> {
+        let mut state = self.state.lock().unwrap();
+        state.error(MockCall::GetDailyBars)?;
+        let bars = state
+            .bars
+            .get(symbol)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        Ok(bars
+            .iter()
+            .filter(|bar| {
+                time::date(&bar.timestamp)
+                    .is_some_and(|date| date.as_str() >= start && date.as_str() <= end)
+            })
+            .cloned()
+            .collect())
+    }
+
+    /// There is no stream to connect to; feed events to the code under test directly.
+    async fn subscribe(
+        &self,
+        _params: SubscriptionParams,
+    ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, Box<dyn Error>> {
+        self.state.lock().unwrap().error(MockCall::Subscribe)?;
+        Err("MockTradingClient does not stream market data".into())
+    }
+}
+
+fn mock_position(symbol: &str) -> Position {
+    let asset = sim_asset(symbol);
+    Position {
+        symbol: symbol.to_string(),
+        exchange: asset.exchange,
+        asset_class: asset.asset_class,
+        qty: 0.0,
+        avg_entry_price: 0.0,
+        market_value: 0.0,
+        current_price: None,
+        unrealized_pl: 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        datastructures::{event::EventType, order::OrderType},
+        replay::{ReplayFeed, ReplaySpeed},
+    };
+    use futures_util::StreamExt;
+
+    fn order(side: OrderSide, quantity: f64) -> Order {
+        Order::builder()
+            .symbol("AAPL")
+            .quantity(quantity)
+            .side(side)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn places_orders_as_scripted() {
+        let client = MockTradingClient::with_cash(10_000.0);
+        client.push_order(MockOrder::Fill { price: 100.0 });
+        client.push_order(MockOrder::Reject("insufficient buying power".to_string()));
+        client.push_order(MockOrder::PartialFill {
+            quantity: 2.0,
+            price: 101.0,
+        });
+
+        client
+            .create_order(&order(OrderSide::Buy, 10.0))
+            .await
+            .unwrap();
+        let error = client
+            .create_order(&order(OrderSide::Buy, 500.0))
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "insufficient buying power");
+        client
+            .create_order(&order(OrderSide::Buy, 5.0))
+            .await
+            .unwrap();
+
+        let statuses: Vec<OrderStatus> = client.orders().iter().map(|o| o.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                OrderStatus::Filled,
+                OrderStatus::Rejected,
+                OrderStatus::PartiallyFilled
+            ]
+        );
+        assert_eq!(client.submitted().len(), 3);
+        let positions = client.get_positions().await.unwrap();
+        assert_eq!(positions[0].qty, 12.0);
+        assert_eq!(client.get_account().await.unwrap().cash, 10_000.0 - 1_202.0);
+        assert_eq!(client.get_open_orders().await.unwrap()[0].id, "mock-3");
+    }
+
+    #[tokio::test]
+    async fn cancels_working_orders_only() {
+        let client = MockTradingClient::with_cash(10_000.0);
+        client.push_order(MockOrder::Rest);
+        client.push_order(MockOrder::Fill { price: 100.0 });
+        client
+            .create_order(&order(OrderSide::Buy, 1.0))
+            .await
+            .unwrap();
+        client
+            .create_order(&order(OrderSide::Buy, 1.0))
+            .await
+            .unwrap();
+
+        let outcome = client.cancel_order("mock-1").await.unwrap();
+        assert!(matches!(outcome, CancelOutcome::Canceled(_)));
+        let outcome = client.cancel_order("mock-2").await.unwrap();
+        assert!(matches!(outcome, CancelOutcome::Filled(_)));
+        assert!(client.cancel_order("mock-9").await.is_err());
+        client.fail(MockCall::CancelOrder, "timed out");
+        assert!(client.cancel_order("mock-1").await.is_err());
+        assert_eq!(client.cancels(), ["mock-1", "mock-2", "mock-9", "mock-1"]);
+        assert!(client.fill("mock-1", 1.0, 99.0).is_none());
+    }
+
+    #[tokio::test]
+    async fn trades_a_replayed_stream() {
+        let quote = |ask_price: f64, second: u32| EventType::Quote {
+            symbol: "AAPL".to_string(),
+            bid_price: ask_price - 0.02,
+            ask_price,
+            bid_size: 1.0,
+            ask_size: 1.0,
+            timestamp: format!("2024-05-01T14:30:0{}Z", second),
+        };
+        let mut stream = ReplayFeed::new(ReplaySpeed::AsFastAsPossible)
+            .events(vec![
+                quote(101.0, 0),
+                quote(99.5, 1),
+                quote(102.0, 2),
+                quote(99.0, 3),
+            ])
+            .start()
+            .await
+            .unwrap();
+
+        // Buys one share whenever the ask dips below 100.
+        let client = MockTradingClient::with_cash(1_000.0);
+        while let Some(event) = stream.next().await {
+            if let EventType::Quote { ask_price, .. } = event {
+                if ask_price < 100.0 {
+                    client.push_order(MockOrder::Fill { price: ask_price });
+                    let buy = Order::builder()
+                        .symbol("AAPL")
+                        .quantity(1.0)
+                        .side(OrderSide::Buy)
+                        .order_type(OrderType::Limit)
+                        .limit_price(ask_price)
+                        .build()
+                        .unwrap();
+                    client.create_order(&buy).await.unwrap();
+                }
+            }
+        }
+
+        let prices: Vec<Option<f64>> = client.submitted().iter().map(|o| o.limit_price).collect();
+        assert_eq!(prices, [Some(99.5), Some(99.0)]);
+        let position = &client.get_positions().await.unwrap()[0];
+        assert_eq!((position.qty, position.avg_entry_price), (2.0, 99.25));
+    }
+}
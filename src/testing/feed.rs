@@ -0,0 +1,332 @@
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::{
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::broadcast,
+    task::JoinHandle,
+};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, Clone, Default)]
+pub struct MockFeedConfig {
+    /// Key and secret the server accepts. `None` accepts any.
+    pub credentials: Option<(String, String)>,
+    /// Frames sent to each connection after it subscribes, as Alpaca sends them: an array of messages such
+    /// as `{"T": "t", "S": "AAPL", "p": 190.1, ...}`. A single message object is sent as a one-element array.
+    pub frames: Vec<Value>,
+    /// Closes each connection once its scripted frames are sent, as when the feed drops.
+    pub close_after_frames: bool,
+}
+
+/// Local WebSocket server speaking the Alpaca market data stream protocol, for testing `subscribe` and event
+/// parsing end to end without a network: it greets each connection, checks the auth message, acknowledges
+/// subscriptions and sends the scripted frames, then any frame passed to `push`. Point a client at it with
+/// `AlpacaClient::with_stream_url`. Stops when dropped.
+pub struct MockFeedServer {
+    address: SocketAddr,
+    live: broadcast::Sender<String>,
+    subscriptions: Arc<Mutex<Vec<Value>>>,
+    cancel: CancellationToken,
+    task: JoinHandle<()>,
+}
+
+impl MockFeedServer {
+    /// Listens on a free local port.
+    pub async fn start(config: MockFeedConfig) -> io::Result<MockFeedServer> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        let live = broadcast::channel(1024).0;
+        let subscriptions = Arc::new(Mutex::new(vec![]));
+        let cancel = CancellationToken::new();
+
+        let task = tokio::spawn({
+            let live = live.clone();
+            let subscriptions = subscriptions.clone();
+            let cancel = cancel.clone();
+            async move {
+                loop {
+                    let stream = tokio::select! {
+                        accepted = listener.accept() => match accepted {
+                            Ok((stream, _)) => stream,
+                            Err(e) => {
+                                tracing::warn!(error = %e, "Mock feed failed to accept a connection");
+                                continue;
+                            }
+                        },
+                        _ = cancel.cancelled() => return,
+                    };
+                    let connection = Connection {
+                        config: config.clone(),
+                        live: live.subscribe(),
+                        subscriptions: subscriptions.clone(),
+                        cancel: cancel.clone(),
+                    };
+                    tokio::spawn(async move {
+                        if let Err(e) = connection.serve(stream).await {
+                            tracing::debug!(error = %e, "Mock feed connection ended");
+                        }
+                    });
+                }
+            }
+        });
+
+        Ok(MockFeedServer {
+            address,
+            live,
+            subscriptions,
+            cancel,
+            task,
+        })
+    }
+
+    /// URL to connect to, e.g. `ws://127.0.0.1:50123`.
+    pub fn url(&self) -> String {
+        format!("ws://{}", self.address)
+    }
+
+    /// Sends a frame to every connection that has subscribed, whatever it subscribed to.
+    pub fn push(&self, frame: Value) {
+        // Only fails when nobody is connected.
+        let _ = self.live.send(frame_text(frame));
+    }
+
+    /// Every subscription message received so far, across connections, as sent by the client.
+    pub fn subscriptions(&self) -> Vec<Value> {
+        self.subscriptions.lock().unwrap().clone()
+    }
+}
+
+impl Drop for MockFeedServer {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+        self.task.abort();
+    }
+}
+
+struct Connection {
+    config: MockFeedConfig,
+    live: broadcast::Receiver<String>,
+    subscriptions: Arc<Mutex<Vec<Value>>>,
+    cancel: CancellationToken,
+}
+
+impl Connection {
+    async fn serve(mut self, stream: TcpStream) -> Result<(), Box<dyn std::error::Error>> {
+        let mut socket = tokio_tungstenite::accept_async(stream).await?;
+        socket
+            .send(text(json!([{"T": "success", "msg": "connected"}])))
+            .await?;
+
+        let mut subscribed = false;
+        loop {
+            let message = tokio::select! {
+                message = socket.next() => match message {
+                    Some(message) => message?,
+                    None => return Ok(()),
+                },
+                frame = self.live.recv(), if subscribed => {
+                    match frame {
+                        Ok(frame) => socket.send(Message::Text(frame)).await?,
+                        Err(broadcast::error::RecvError::Lagged(_)) => {}
+                        Err(broadcast::error::RecvError::Closed) => return Ok(()),
+                    }
+                    continue;
+                }
+                _ = self.cancel.cancelled() => {
+                    let _ = socket.close(None).await;
+                    return Ok(());
+                }
+            };
+            let Message::Text(message) = message else {
+                continue;
+            };
+            let request: Value = serde_json::from_str(&message)?;
+            match request["action"].as_str() {
+                Some("auth") => {
+                    let accepted = self
+                        .config
+                        .credentials
+                        .as_ref()
+                        .is_none_or(|(key, secret)| {
+                            request["key"] == key.as_str() && request["secret"] == secret.as_str()
+                        });
+                    if !accepted {
+                        socket
+                            .send(text(
+                                json!([{"T": "error", "code": 402, "msg": "auth failed"}]),
+                            ))
+                            .await?;
+                        let _ = socket.close(None).await;
+                        return Ok(());
+                    }
+                    socket
+                        .send(text(json!([{"T": "success", "msg": "authenticated"}])))
+                        .await?;
+                }
+                Some("subscribe") => {
+                    self.subscriptions.lock().unwrap().push(request.clone());
+                    let mut acknowledgement = json!({"T": "subscription"});
+                    if let (Some(channels), Some(acknowledgement)) =
+                        (request.as_object(), acknowledgement.as_object_mut())
+                    {
+                        for (channel, symbols) in channels.iter().filter(|(k, _)| *k != "action") {
+                            acknowledgement.insert(channel.clone(), symbols.clone());
+                        }
+                    }
+                    socket.send(text(json!([acknowledgement]))).await?;
+                    if !subscribed {
+                        subscribed = true;
+                        for frame in &self.config.frames {
+                            socket.send(text(frame.clone())).await?;
+                        }
+                        if self.config.close_after_frames {
+                            let _ = socket.close(None).await;
+                            return Ok(());
+                        }
+                    }
+                }
+                _ => {
+                    socket
+                        .send(text(
+                            json!([{"T": "error", "code": 400, "msg": "invalid syntax"}]),
+                        ))
+                        .await?;
+                }
+            }
+        }
+    }
+}
+
+fn frame_text(frame: Value) -> String {
+    match frame {
+        Value::Array(_) => frame.to_string(),
+        message => Value::Array(vec![message]).to_string(),
+    }
+}
+
+fn text(frame: Value) -> Message {
+    Message::Text(frame_text(frame))
+}
+
+#[cfg(all(test, feature = "alpaca"))]
+mod tests {
+    use super::*;
+    use crate::{
+        alpaca::AlpacaClient,
+        datastructures::{
+            client::{FeedType, MarketDataClient, SubscriptionParams, SubscriptionParamsBuilder},
+            config::Config,
+            event::EventType,
+        },
+        stream::{EventStream, StreamConfig},
+    };
+    use std::time::Duration;
+
+    fn alpaca(server: &MockFeedServer, key: &str) -> AlpacaClient {
+        let config = Config::builder()
+            .alpaca_api_key(key.to_string())
+            .alpaca_secret_key("secret".to_string())
+            .build()
+            .unwrap();
+        AlpacaClient::new(&config)
+            .unwrap()
+            .with_stream_url(server.url())
+    }
+
+    fn trades() -> SubscriptionParams {
+        SubscriptionParamsBuilder::new()
+            .feed_type(FeedType::Stocks)
+            .trades(&["AAPL"])
+            .build()
+    }
+
+    async fn next(stream: &mut EventStream) -> Option<EventType> {
+        tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("no event within 5s")
+    }
+
+    #[tokio::test]
+    async fn streams_scripted_then_pushed_frames() {
+        let server = MockFeedServer::start(MockFeedConfig {
+            credentials: Some(("key".to_string(), "secret".to_string())),
+            frames: vec![
+                json!({"T": "t", "S": "AAPL", "p": 190.1, "s": 5, "t": "2024-05-01T14:30:00Z"}),
+            ],
+            close_after_frames: false,
+        })
+        .await
+        .unwrap();
+        let mut stream =
+            EventStream::connect(alpaca(&server, "key"), trades(), StreamConfig::default())
+                .await
+                .unwrap();
+
+        let Some(EventType::Trade { price, volume, .. }) = next(&mut stream).await else {
+            panic!("expected the scripted trade");
+        };
+        assert_eq!((price, volume), (190.1, 5.0));
+        assert_eq!(server.subscriptions()[0]["trades"], json!(["AAPL"]));
+
+        server.push(json!([
+            {"T": "q", "S": "AAPL", "bp": 190.0, "bs": 2, "ap": 190.2, "as": 3, "t": "2024-05-01T14:30:01Z"},
+            {"T": "t", "S": "AAPL", "p": 190.2, "s": 1, "t": "2024-05-01T14:30:02Z"}
+        ]));
+        assert!(matches!(
+            next(&mut stream).await,
+            Some(EventType::Quote { ask_price, .. }) if ask_price == 190.2
+        ));
+        assert!(matches!(
+            next(&mut stream).await,
+            Some(EventType::Trade { price, .. }) if price == 190.2
+        ));
+    }
+
+    #[tokio::test]
+    async fn refuses_unknown_credentials() {
+        let server = MockFeedServer::start(MockFeedConfig {
+            credentials: Some(("key".to_string(), "secret".to_string())),
+            ..MockFeedConfig::default()
+        })
+        .await
+        .unwrap();
+        let error = alpaca(&server, "other")
+            .subscribe(trades())
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Authentication failed");
+        assert!(server.subscriptions().is_empty());
+    }
+
+    #[tokio::test]
+    async fn resubscribes_after_the_feed_drops() {
+        let server = MockFeedServer::start(MockFeedConfig {
+            frames: vec![json!({"T": "t", "S": "AAPL", "p": 190.1, "s": 5})],
+            close_after_frames: true,
+            ..MockFeedConfig::default()
+        })
+        .await
+        .unwrap();
+        let config = StreamConfig::builder()
+            .reconnect_delay(Duration::from_millis(10))
+            .build()
+            .unwrap();
+        let mut stream = EventStream::connect(alpaca(&server, "key"), trades(), config)
+            .await
+            .unwrap();
+
+        for _ in 0..2 {
+            assert!(matches!(
+                next(&mut stream).await,
+                Some(EventType::Trade { .. })
+            ));
+        }
+        assert!(server.subscriptions().len() >= 2);
+    }
+}
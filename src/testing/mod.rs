@@ -0,0 +1,9 @@
+#[cfg(feature = "alpaca")]
+mod cassette;
+mod client;
+mod feed;
+
+#[cfg(feature = "alpaca")]
+pub use cassette::Cassette;
+pub use client::{MockCall, MockOrder, MockTradingClient};
+pub use feed::{MockFeedConfig, MockFeedServer};
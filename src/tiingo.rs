@@ -0,0 +1,412 @@
+use crate::{
+    datastructures::{
+        client::{MarketDataClient, SubscriptionParams},
+        config::Config,
+        corporate_action::CorporateAction,
+        event::{EventBatch, ParseMode},
+        market::{Bar, BarAdjustment, Quote},
+    },
+    http::RestPolicies,
+    time,
+};
+use async_trait::async_trait;
+use reqwest::{header::AUTHORIZATION, Client as HttpClient};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::Value;
+use std::{error::Error, fmt, time::Duration};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+/// Docs: https://www.tiingo.com/documentation/general/overview
+const REST_URL: &str = "https://api.tiingo.com";
+/// Tiingo caps each response, so intraday history is fetched a few days at a time, sized to stay under it.
+const MAX_ROWS_PER_REQUEST: i64 = 9_000;
+const MINUTES_PER_DAY: i64 = 1_440;
+const NANOS_PER_DAY: i64 = 86_400 * 1_000_000_000;
+
+/// Error response of the Tiingo API.
+#[derive(Debug, Clone)]
+pub struct TiingoError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl fmt::Display for TiingoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Tiingo error ({}): {}", self.status, self.message)
+    }
+}
+
+impl Error for TiingoError {}
+
+/// Docs: https://www.tiingo.com/documentation/end-of-day
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawDailyPrice {
+    date: String,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    volume: f64,
+    adj_open: f64,
+    adj_high: f64,
+    adj_low: f64,
+    adj_close: f64,
+    adj_volume: f64,
+    #[serde(default)]
+    div_cash: f64,
+    #[serde(default = "one")]
+    split_factor: f64,
+}
+
+fn one() -> f64 {
+    1.0
+}
+
+#[derive(Deserialize)]
+struct RawPrice {
+    date: String,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    #[serde(default)]
+    volume: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawCryptoPrices {
+    #[serde(default)]
+    price_data: Vec<RawPrice>,
+}
+
+impl RawPrice {
+    fn into_bar(self, symbol: &str) -> Bar {
+        Bar {
+            symbol: symbol.to_string(),
+            open: self.open,
+            high: self.high,
+            low: self.low,
+            close: self.close,
+            volume: self.volume,
+            timestamp: normalize(&self.date),
+        }
+    }
+}
+
+/// Tiingo's timestamps in the crate's UTC form, or as they are if they do not parse.
+fn normalize(timestamp: &str) -> String {
+    time::parse_rfc3339(timestamp)
+        .map(time::format_rfc3339)
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+/// Ticker as Tiingo writes it: "btcusd" for the crypto pair "BTC/USD", and share classes with a dash, e.g.
+/// "BRK-B" for "BRK.B".
+fn ticker(symbol: &str) -> String {
+    if symbol.contains('/') {
+        symbol.replace('/', "").to_lowercase()
+    } else {
+        symbol.replace('.', "-")
+    }
+}
+
+fn day_nanos(date: &str) -> Result<i64, String> {
+    time::parse_rfc3339(&format!("{}T00:00:00Z", date))
+        .ok_or_else(|| format!("Not a YYYY-MM-DD date: {}", date))
+}
+
+fn format_date(nanos: i64) -> String {
+    time::date(&time::format_rfc3339(nanos)).unwrap_or_default()
+}
+
+/// Historical end-of-day and intraday prices from Tiingo, for backtests over longer history than Alpaca serves.
+/// End-of-day prices reach back decades for US stocks; intraday prices come from IEX, and crypto from the
+/// exchanges Tiingo aggregates. Stocks are written as Alpaca writes them, e.g. "BRK.B", and crypto as pairs,
+/// e.g. "BTC/USD". Tiingo does not trade and is not streamed here, so pair it with a broker in a `SplitClient`.
+/// Applies the config's REST policies like `AlpacaClient`.
+#[derive(Clone)]
+pub struct TiingoClient {
+    http_client: HttpClient,
+    rest: RestPolicies,
+    api_key: String,
+}
+
+impl TiingoClient {
+    /// Uses the config's Tiingo key.
+    pub fn new(config: &Config) -> Result<Self, reqwest::Error> {
+        Ok(TiingoClient {
+            http_client: config.http.build_client()?,
+            rest: RestPolicies::new(config),
+            api_key: config.tiingo_api_key.clone().unwrap_or_default(),
+        })
+    }
+
+    /// Every call is a read, so all are retried.
+    async fn get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<T, Box<dyn Error>> {
+        let url = format!("{}{}", REST_URL, path);
+        let build = || {
+            Ok(self
+                .http_client
+                .get(&url)
+                .query(query)
+                .header(AUTHORIZATION, format!("Token {}", self.api_key))
+                .build()?)
+        };
+        let response = self.rest.send(&self.http_client, true, build).await?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            let message = serde_json::from_str::<Value>(&body)
+                .ok()
+                .and_then(|value| Some(value.get("detail")?.as_str()?.to_string()))
+                .unwrap_or(body);
+            return Err(TiingoError {
+                status: status.as_u16(),
+                message,
+            }
+            .into());
+        }
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    /// End-of-day prices from `start` to `end` inclusive, or to the latest if `None`.
+    /// Docs: https://www.tiingo.com/documentation/end-of-day
+    async fn daily_prices(
+        &self,
+        symbol: &str,
+        start: &str,
+        end: Option<&str>,
+    ) -> Result<Vec<RawDailyPrice>, Box<dyn Error>> {
+        let path = format!("/tiingo/daily/{}/prices", ticker(symbol));
+        let mut query = vec![("startDate", start.to_string())];
+        if let Some(end) = end {
+            query.push(("endDate", end.to_string()));
+        }
+        self.get(&path, &query).await
+    }
+
+    /// Prices resampled to `frequency`, e.g. "1min" or "1day", from `start` to `end` inclusive, oldest first.
+    /// Docs: https://www.tiingo.com/documentation/iex and https://www.tiingo.com/documentation/crypto
+    async fn prices(
+        &self,
+        symbol: &str,
+        start: &str,
+        end: &str,
+        frequency: &str,
+    ) -> Result<Vec<Bar>, Box<dyn Error>> {
+        let mut query = vec![
+            ("startDate", start.to_string()),
+            ("endDate", end.to_string()),
+            ("resampleFreq", frequency.to_string()),
+        ];
+        if symbol.contains('/') {
+            query.push(("tickers", ticker(symbol)));
+            let prices: Vec<RawCryptoPrices> = self.get("/tiingo/crypto/prices", &query).await?;
+            return Ok(prices
+                .into_iter()
+                .flat_map(|prices| prices.price_data)
+                .map(|price| price.into_bar(symbol))
+                .collect());
+        }
+        query.push(("columns", "open,high,low,close,volume".to_string()));
+        let path = format!("/iex/{}/prices", ticker(symbol));
+        let prices: Vec<RawPrice> = self.get(&path, &query).await?;
+        Ok(prices
+            .into_iter()
+            .map(|price| price.into_bar(symbol))
+            .collect())
+    }
+
+    /// Intraday bars of `interval`, a whole number of minutes, from `start` to `end` inclusive, as YYYY-MM-DD
+    /// dates, oldest first and stamped with their start. Long ranges are fetched a few days at a time. Stock bars
+    /// come from IEX alone, so their volumes are IEX's share of the market.
+    pub async fn get_intraday_bars(
+        &self,
+        symbol: &str,
+        start: &str,
+        end: &str,
+        interval: Duration,
+    ) -> Result<Vec<Bar>, Box<dyn Error>> {
+        let minutes = (interval.as_secs() / 60) as i64;
+        if minutes == 0 || !interval.as_secs().is_multiple_of(60) {
+            return Err("Interval must be a whole number of minutes".into());
+        }
+        let frequency = if minutes % 60 == 0 {
+            format!("{}hour", minutes / 60)
+        } else {
+            format!("{}min", minutes)
+        };
+        let days_per_request = (MAX_ROWS_PER_REQUEST * minutes / MINUTES_PER_DAY).max(1);
+
+        let last = day_nanos(end)?;
+        let mut from = day_nanos(start)?;
+        let mut bars: Vec<Bar> = Vec::new();
+        while from <= last {
+            let to = (from + (days_per_request - 1) * NANOS_PER_DAY).min(last);
+            let page = self
+                .prices(symbol, &format_date(from), &format_date(to), &frequency)
+                .await?;
+            // Pages meet at a day boundary, which either may include.
+            let newest = bars.last().map(|bar| bar.timestamp.clone());
+            bars.extend(
+                page.into_iter()
+                    .filter(|bar| newest.as_ref().is_none_or(|newest| bar.timestamp > *newest)),
+            );
+            from = to + NANOS_PER_DAY;
+        }
+        Ok(bars)
+    }
+
+    /// Splits and cash dividends with an ex-date from `start` to `end` inclusive, as YYYY-MM-DD dates, sorted by
+    /// ex-date, as recorded in Tiingo's end-of-day prices. Meant for `BacktestConfig::corporate_actions`.
+    pub async fn get_corporate_actions(
+        &self,
+        symbols: &[&str],
+        start: &str,
+        end: &str,
+    ) -> Result<Vec<CorporateAction>, Box<dyn Error>> {
+        let mut actions = Vec::new();
+        for symbol in symbols {
+            let prices = self.daily_prices(symbol, start, Some(end)).await?;
+            actions.extend(corporate_actions(symbol, &prices));
+        }
+        actions.sort_by(|a, b| a.ex_date().cmp(b.ex_date()));
+        Ok(actions)
+    }
+}
+
+/// Each end-of-day price records the split and dividend going ex that day.
+fn corporate_actions(symbol: &str, prices: &[RawDailyPrice]) -> Vec<CorporateAction> {
+    let mut actions = Vec::new();
+    for price in prices {
+        let ex_date = time::date(&price.date).unwrap_or_default();
+        if price.split_factor > 0.0 && price.split_factor != 1.0 {
+            actions.push(CorporateAction::Split {
+                symbol: symbol.to_string(),
+                ex_date: ex_date.clone(),
+                ratio: price.split_factor,
+            });
+        }
+        if price.div_cash > 0.0 {
+            actions.push(CorporateAction::CashDividend {
+                symbol: symbol.to_string(),
+                ex_date,
+                amount: price.div_cash,
+            });
+        }
+    }
+    actions
+}
+
+#[async_trait]
+impl MarketDataClient for TiingoClient {
+    /// Docs: https://www.tiingo.com/documentation/iex
+    /// IEX's top of book, not the national best bid and offer. Stocks only.
+    async fn get_latest_quote(&self, symbol: &str) -> Result<Quote, Box<dyn Error>> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct TopOfBook {
+            #[serde(default)]
+            bid_price: Option<f64>,
+            #[serde(default)]
+            bid_size: Option<f64>,
+            #[serde(default)]
+            ask_price: Option<f64>,
+            #[serde(default)]
+            ask_size: Option<f64>,
+            timestamp: String,
+        }
+        if symbol.contains('/') {
+            return Err("Tiingo quotes are for stocks only".into());
+        }
+        let path = format!("/iex/{}", ticker(symbol));
+        let top: Vec<TopOfBook> = self.get(&path, &[]).await?;
+        let top = top
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("No quote for {}", symbol))?;
+        Ok(Quote {
+            symbol: symbol.to_string(),
+            bid_price: top.bid_price.unwrap_or_default(),
+            ask_price: top.ask_price.unwrap_or_default(),
+            bid_size: top.bid_size.unwrap_or_default(),
+            ask_size: top.ask_size.unwrap_or_default(),
+            timestamp: normalize(&top.timestamp),
+        })
+    }
+
+    /// Tiingo adjusts end-of-day prices for splits and dividends together, so split-only bars are adjusted
+    /// locally from the splits it records. Crypto has no corporate actions, so the adjustment is ignored for it.
+    async fn get_daily_bars(
+        &self,
+        symbol: &str,
+        start: &str,
+        end: &str,
+        adjustment: BarAdjustment,
+    ) -> Result<Vec<Bar>, Box<dyn Error>> {
+        if symbol.contains('/') {
+            return self.prices(symbol, start, end, "1day").await;
+        }
+        // Splits after `end` restate the bars too, so split-adjusted bars need every price since `start`.
+        let through = match adjustment {
+            BarAdjustment::Split => None,
+            BarAdjustment::Raw | BarAdjustment::TotalReturn => Some(end),
+        };
+        let prices = self.daily_prices(symbol, start, through).await?;
+        let splits: Vec<CorporateAction> = corporate_actions(symbol, &prices)
+            .into_iter()
+            .filter(|action| matches!(action, CorporateAction::Split { .. }))
+            .collect();
+        let mut bars: Vec<Bar> = prices
+            .into_iter()
+            .filter(|price| time::date(&price.date).is_some_and(|date| date.as_str() <= end))
+            .map(|price| {
+                let timestamp = normalize(&price.date);
+                let symbol = symbol.to_string();
+                match adjustment {
+                    BarAdjustment::TotalReturn => Bar {
+                        symbol,
+                        open: price.adj_open,
+                        high: price.adj_high,
+                        low: price.adj_low,
+                        close: price.adj_close,
+                        volume: price.adj_volume,
+                        timestamp,
+                    },
+                    BarAdjustment::Raw | BarAdjustment::Split => Bar {
+                        symbol,
+                        open: price.open,
+                        high: price.high,
+                        low: price.low,
+                        close: price.close,
+                        volume: price.volume,
+                        timestamp,
+                    },
+                }
+            })
+            .collect();
+        if adjustment == BarAdjustment::Split {
+            adjustment.apply(&mut bars, &splits);
+        }
+        Ok(bars)
+    }
+
+    /// Tiingo is served here for history only; stream from the broker's client instead.
+    async fn subscribe(
+        &self,
+        _params: SubscriptionParams,
+    ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, Box<dyn Error>> {
+        Err("Tiingo is a historical data source and cannot be streamed".into())
+    }
+
+    fn parse_frame(&self, _frame: &str, _mode: ParseMode) -> Result<EventBatch, serde_json::Error> {
+        Ok(EventBatch::new())
+    }
+}
@@ -0,0 +1,206 @@
+// Minimal RFC 3339 handling for the timestamps Alpaca sends, e.g. "2021-03-16T18:38:01.942282Z".
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Parses an RFC 3339 timestamp into nanoseconds since the Unix epoch.
+pub fn parse_rfc3339(s: &str) -> Option<i64> {
+    let bytes = s.as_bytes();
+    if bytes.len() < 20
+        || bytes[4] != b'-'
+        || bytes[7] != b'-'
+        || bytes[13] != b':'
+        || bytes[16] != b':'
+    {
+        return None;
+    }
+    if !matches!(bytes[10], b'T' | b't' | b' ') {
+        return None;
+    }
+
+    let number = |range: std::ops::Range<usize>| s.get(range)?.parse::<i64>().ok();
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+
+    let mut rest = &s[19..];
+    let mut nanos = 0;
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let digits = fraction.bytes().take_while(u8::is_ascii_digit).count();
+        if digits == 0 {
+            return None;
+        }
+        for (i, digit) in fraction[..digits.min(9)].bytes().enumerate() {
+            nanos += (digit - b'0') as i64 * 10_i64.pow(8 - i as u32);
+        }
+        rest = &fraction[digits..];
+    }
+
+    let offset_seconds = match rest {
+        "Z" | "z" => 0,
+        _ if rest.len() == 6
+            && (rest.starts_with('+') || rest.starts_with('-'))
+            && &rest[3..4] == ":" =>
+        {
+            let magnitude =
+                rest[1..3].parse::<i64>().ok()? * 3600 + rest[4..6].parse::<i64>().ok()? * 60;
+            if rest.starts_with('-') {
+                -magnitude
+            } else {
+                magnitude
+            }
+        }
+        _ => return None,
+    };
+
+    let seconds =
+        days_from_civil(year, month, day) * SECONDS_PER_DAY + hour * 3600 + minute * 60 + second
+            - offset_seconds;
+    Some(seconds * NANOS_PER_SECOND + nanos)
+}
+
+/// Formats nanoseconds since the Unix epoch as an RFC 3339 UTC timestamp.
+pub fn format_rfc3339(nanos: i64) -> String {
+    let seconds = nanos.div_euclid(NANOS_PER_SECOND);
+    let fraction = nanos.rem_euclid(NANOS_PER_SECOND);
+    let (year, month, day) = civil_from_days(seconds.div_euclid(SECONDS_PER_DAY));
+    let time = seconds.rem_euclid(SECONDS_PER_DAY);
+
+    let mut formatted = format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    );
+    if fraction != 0 {
+        formatted.push_str(format!(".{:09}", fraction).trim_end_matches('0'));
+    }
+    formatted.push('Z');
+    formatted
+}
+
+/// Current time in nanoseconds since the Unix epoch.
+pub fn now_nanos() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as i64)
+        .unwrap_or_default()
+}
+
+/// Time elapsed since an RFC 3339 timestamp. Timestamps in the future count as zero.
+pub fn age(timestamp: &str) -> Option<Duration> {
+    let elapsed = now_nanos() - parse_rfc3339(timestamp)?;
+    Some(Duration::from_nanos(elapsed.max(0) as u64))
+}
+
+/// UTC calendar date of an RFC 3339 timestamp, as YYYY-MM-DD.
+pub fn date(timestamp: &str) -> Option<String> {
+    let (year, month, day) =
+        civil_from_days(parse_rfc3339(timestamp)?.div_euclid(NANOS_PER_SECOND * SECONDS_PER_DAY));
+    Some(format!("{:04}-{:02}-{:02}", year, month, day))
+}
+
+/// UTC offset of US Eastern time (New York) at an instant, in seconds: -4h during daylight saving time, -5h otherwise.
+/// Uses the rules in force since 2007.
+pub fn us_eastern_offset(nanos: i64) -> i64 {
+    let seconds = nanos.div_euclid(NANOS_PER_SECOND);
+    let (year, _, _) = civil_from_days(seconds.div_euclid(SECONDS_PER_DAY));
+    // 1970-01-01 was a Thursday; 0 is Sunday.
+    let first_sunday = |month| {
+        let first = days_from_civil(year, month, 1);
+        first + (7 - (first + 4).rem_euclid(7)) % 7
+    };
+    // Clocks change at 2am local: 07:00 UTC in March, 06:00 UTC in November.
+    let dst_start = (first_sunday(3) + 7) * SECONDS_PER_DAY + 7 * 3600;
+    let dst_end = first_sunday(11) * SECONDS_PER_DAY + 6 * 3600;
+    if (dst_start..dst_end).contains(&seconds) {
+        -4 * 3600
+    } else {
+        -5 * 3600
+    }
+}
+
+/// Instant of a US Eastern wall-clock time on a YYYY-MM-DD date, in nanoseconds since the Unix epoch.
+pub fn us_eastern(date: &str, hour: u32, minute: u32) -> Option<i64> {
+    let number = |range: std::ops::Range<usize>| date.get(range)?.parse::<i64>().ok();
+    if date.len() != 10 || hour > 23 || minute > 59 {
+        return None;
+    }
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let local = days_from_civil(year, month, day) * SECONDS_PER_DAY
+        + hour as i64 * 3600
+        + minute as i64 * 60;
+    // The offset at standard time is close enough to find which offset applies.
+    let offset = us_eastern_offset((local + 5 * 3600) * NANOS_PER_SECOND);
+    Some((local - offset) * NANOS_PER_SECOND)
+}
+
+/// Day of the week of an instant in US Eastern time, 1 for Monday to 7 for Sunday.
+pub(crate) fn us_eastern_weekday(nanos: i64) -> i64 {
+    let local = nanos.div_euclid(NANOS_PER_SECOND) + us_eastern_offset(nanos);
+    // 1970-01-01 was a Thursday.
+    (local.div_euclid(SECONDS_PER_DAY) + 3).rem_euclid(7) + 1
+}
+
+/// Time until the next `hour:minute` US Eastern.
+pub(crate) fn until_us_eastern(hour: u32, minute: u32) -> Duration {
+    let now = now_nanos();
+    let date = |nanos: i64| date(&format_rfc3339(nanos)).unwrap_or_default();
+    // Today's and tomorrow's Eastern dates; one of them holds the next occurrence.
+    let local = now + us_eastern_offset(now) * NANOS_PER_SECOND;
+    [0, 1]
+        .into_iter()
+        .filter_map(|days| {
+            us_eastern(
+                &date(local + days * SECONDS_PER_DAY * NANOS_PER_SECOND),
+                hour,
+                minute,
+            )
+        })
+        .find(|at| *at > now)
+        .map(|at| Duration::from_nanos((at - now) as u64))
+        .unwrap_or(Duration::from_secs(86_400))
+}
+
+// Howard Hinnant's days_from_civil / civil_from_days algorithms, proleptic Gregorian calendar.
+pub(crate) fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+pub(crate) fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
@@ -0,0 +1,319 @@
+use crate::{
+    datastructures::{
+        asset::Asset,
+        client::BrokerClient,
+        market::{Bar, BarAdjustment},
+    },
+    time,
+};
+use futures_util::{stream, StreamExt};
+use serde::Serialize;
+use std::{
+    collections::{BTreeSet, HashSet},
+    error::Error,
+    sync::{Arc, Mutex, OnceLock, RwLock},
+    time::Duration,
+};
+use tokio::{sync::broadcast, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
+
+/// Bar requests in flight at once while screening.
+const SCREEN_CONCURRENCY: usize = 8;
+/// Wait before retrying a scheduled refresh that failed.
+const RETRY_DELAY: Duration = Duration::from_secs(15 * 60);
+
+/// Which assets belong in the trading universe. Price and volume are judged on daily bars, so screening with
+/// either costs one bars request per candidate; narrow the candidates with the asset filters where possible.
+#[derive(Debug, Clone)]
+pub struct ScreenCriteria {
+    /// "us_equity" or "crypto". `None` allows both.
+    pub asset_class: Option<String>,
+    /// Listing exchanges to keep, e.g. "NASDAQ". Empty allows any.
+    pub exchanges: Vec<String>,
+    /// Only assets with listed options.
+    pub optionable: bool,
+    pub shortable: bool,
+    /// Latest daily close.
+    pub min_price: Option<f64>,
+    pub max_price: Option<f64>,
+    /// Average daily share volume over `volume_lookback_days`.
+    pub min_average_volume: Option<f64>,
+    /// Trading days averaged for `min_average_volume` and ranked on for `max_symbols`.
+    pub volume_lookback_days: u32,
+    /// Keep only the most traded symbols that pass.
+    pub max_symbols: Option<usize>,
+    /// Always in the universe, if tradable.
+    pub include: Vec<String>,
+    /// Never in the universe.
+    pub exclude: Vec<String>,
+}
+
+impl Default for ScreenCriteria {
+    fn default() -> Self {
+        ScreenCriteria {
+            asset_class: Some("us_equity".to_string()),
+            exchanges: vec![],
+            optionable: false,
+            shortable: false,
+            min_price: None,
+            max_price: None,
+            min_average_volume: None,
+            volume_lookback_days: 20,
+            max_symbols: None,
+            include: vec![],
+            exclude: vec![],
+        }
+    }
+}
+
+impl ScreenCriteria {
+    fn admits(&self, asset: &Asset) -> bool {
+        asset.tradable
+            && asset.status != "inactive"
+            && !self.exclude.contains(&asset.symbol)
+            && self
+                .asset_class
+                .as_ref()
+                .is_none_or(|class| &asset.asset_class == class)
+            && (self.exchanges.is_empty() || self.exchanges.contains(&asset.exchange))
+            && (!self.optionable || asset.is_optionable())
+            && (!self.shortable || asset.shortable)
+    }
+
+    fn needs_bars(&self) -> bool {
+        self.min_price.is_some()
+            || self.max_price.is_some()
+            || self.min_average_volume.is_some()
+            || self.max_symbols.is_some()
+    }
+}
+
+/// Symbols that joined and left the universe in one refresh.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UniverseChange {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// The whole universe after the refresh, sorted.
+    pub symbols: Vec<String>,
+    pub refreshed_at: String,
+}
+
+impl UniverseChange {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Trading universe defined by screening criteria and refreshed from the broker's asset list and daily bars.
+/// Changes are published to subscribers, such as `StrategyRunner` via `RunnerConfig::universe`, and the
+/// symbols can be turned into a new subscription with `static_symbols`.
+#[derive(Debug, Clone)]
+pub struct Universe {
+    criteria: Arc<ScreenCriteria>,
+    symbols: Arc<RwLock<BTreeSet<String>>>,
+    changes: broadcast::Sender<UniverseChange>,
+}
+
+impl Universe {
+    pub fn new(criteria: ScreenCriteria) -> Self {
+        Universe {
+            criteria: Arc::new(criteria),
+            symbols: Arc::new(RwLock::new(BTreeSet::new())),
+            changes: broadcast::channel(16).0,
+        }
+    }
+
+    pub fn criteria(&self) -> &ScreenCriteria {
+        &self.criteria
+    }
+
+    /// Sorted.
+    pub fn symbols(&self) -> Vec<String> {
+        self.symbols.read().unwrap().iter().cloned().collect()
+    }
+
+    pub fn contains(&self, symbol: &str) -> bool {
+        self.symbols.read().unwrap().contains(symbol)
+    }
+
+    /// The symbols as `&'static str`, for `SubscriptionParamsBuilder`. Each distinct symbol is allocated once
+    /// for the life of the process, so repeated refreshes do not grow memory beyond the symbols ever seen.
+    pub fn static_symbols(&self) -> Vec<&'static str> {
+        self.symbols
+            .read()
+            .unwrap()
+            .iter()
+            .map(|symbol| intern(symbol))
+            .collect()
+    }
+
+    /// Changes from now on. Refreshes that change nothing are not published.
+    pub fn subscribe(&self) -> broadcast::Receiver<UniverseChange> {
+        self.changes.subscribe()
+    }
+
+    /// Screens the client's assets and replaces the universe with the result.
+    pub async fn refresh<C: BrokerClient + Sync>(
+        &self,
+        client: &C,
+    ) -> Result<UniverseChange, Box<dyn Error>> {
+        let screened: BTreeSet<String> = self.screen(client).await?.into_iter().collect();
+        let mut symbols = self.symbols.write().unwrap();
+        let change = UniverseChange {
+            added: screened.difference(&symbols).cloned().collect(),
+            removed: symbols.difference(&screened).cloned().collect(),
+            symbols: screened.iter().cloned().collect(),
+            refreshed_at: time::format_rfc3339(time::now_nanos()),
+        };
+        *symbols = screened;
+        drop(symbols);
+
+        tracing::info!(
+            symbols = change.symbols.len(),
+            added = change.added.len(),
+            removed = change.removed.len(),
+            "Universe refreshed"
+        );
+        if !change.is_empty() {
+            // Only fails when nobody is subscribed.
+            let _ = self.changes.send(change.clone());
+        }
+        Ok(change)
+    }
+
+    /// Symbols that currently pass the criteria, without changing the universe.
+    pub async fn screen<C: BrokerClient + Sync>(
+        &self,
+        client: &C,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        let criteria = &self.criteria;
+        let assets = client.list_assets().await?;
+        let mut candidates: Vec<&Asset> = assets
+            .iter()
+            .filter(|asset| criteria.admits(asset))
+            .collect();
+        let included: HashSet<&str> = criteria.include.iter().map(String::as_str).collect();
+        if !criteria.needs_bars() {
+            return Ok(candidates.iter().map(|a| a.symbol.clone()).collect());
+        }
+        candidates.retain(|asset| !included.contains(asset.symbol.as_str()));
+
+        let today = time::now_nanos();
+        let lookback = criteria.volume_lookback_days.max(1) as i64;
+        // Calendar days enough to cover the trading days plus weekends and holidays.
+        let start = time::format_rfc3339(today - (lookback * 7 / 5 + 10) * 86_400 * 1_000_000_000);
+        let (start, end) = (
+            time::date(&start).unwrap_or_default(),
+            time::date(&time::format_rfc3339(today)).unwrap_or_default(),
+        );
+
+        let symbols: Vec<String> = candidates.iter().map(|a| a.symbol.clone()).collect();
+        let fetched: Vec<(String, Result<Vec<Bar>, String>)> = stream::iter(symbols)
+            .map(|symbol| {
+                let (start, end) = (&start, &end);
+                async move {
+                    let bars = client
+                        .get_daily_bars(&symbol, start, end, BarAdjustment::Split)
+                        .await
+                        .map_err(|e| e.to_string());
+                    (symbol, bars)
+                }
+            })
+            .buffer_unordered(SCREEN_CONCURRENCY)
+            .collect()
+            .await;
+
+        let mut screened: Vec<(String, f64)> = vec![];
+        for (symbol, bars) in fetched {
+            let bars = match bars {
+                Ok(bars) => bars,
+                Err(e) => {
+                    tracing::warn!(%symbol, error = %e, "Failed to fetch bars for screening");
+                    continue;
+                }
+            };
+            let recent = &bars[bars.len().saturating_sub(lookback as usize)..];
+            let Some(close) = recent.last().map(|bar| bar.close) else {
+                continue;
+            };
+            let volume = recent.iter().map(|bar| bar.volume).sum::<f64>() / recent.len() as f64;
+            if criteria.min_price.is_none_or(|min| close >= min)
+                && criteria.max_price.is_none_or(|max| close <= max)
+                && criteria.min_average_volume.is_none_or(|min| volume >= min)
+            {
+                screened.push((symbol, volume));
+            }
+        }
+
+        screened.sort_by(|a, b| b.1.total_cmp(&a.1));
+        if let Some(max_symbols) = criteria.max_symbols {
+            screened.truncate(max_symbols);
+        }
+        let mut symbols: Vec<String> = screened.into_iter().map(|(symbol, _)| symbol).collect();
+        symbols.extend(
+            assets
+                .iter()
+                .filter(|asset| included.contains(asset.symbol.as_str()) && asset.tradable)
+                .map(|asset| asset.symbol.clone()),
+        );
+        Ok(symbols)
+    }
+
+    /// Refreshes now and then every day at `hour:minute` US Eastern time, e.g. 20:00 after the close, until
+    /// cancelled or the returned handle is dropped. Failed refreshes are retried after 15 minutes.
+    pub fn schedule<C>(
+        &self,
+        client: C,
+        hour: u32,
+        minute: u32,
+        cancel: CancellationToken,
+    ) -> UniverseSchedule
+    where
+        C: BrokerClient + Send + Sync + 'static,
+    {
+        let universe = self.clone();
+        let task = tokio::spawn(async move {
+            let mut wait = Duration::ZERO;
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep(wait) => {}
+                    _ = cancel.cancelled() => return,
+                }
+                let result = universe.refresh(&client).await.map_err(|e| e.to_string());
+                wait = match result {
+                    Ok(_) => time::until_us_eastern(hour, minute),
+                    Err(e) => {
+                        tracing::error!(error = %e, "Universe refresh failed");
+                        RETRY_DELAY
+                    }
+                };
+            }
+        });
+        UniverseSchedule { task }
+    }
+}
+
+/// Leaks each distinct symbol once so it can be used where a `&'static str` is required.
+fn intern(symbol: &str) -> &'static str {
+    static INTERNED: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+    let mut interned = INTERNED.get_or_init(Default::default).lock().unwrap();
+    match interned.get(symbol) {
+        Some(symbol) => symbol,
+        None => {
+            let symbol: &'static str = Box::leak(symbol.to_string().into_boxed_str());
+            interned.insert(symbol);
+            symbol
+        }
+    }
+}
+
+/// Background task started by `Universe::schedule`. Stops when dropped.
+pub struct UniverseSchedule {
+    task: JoinHandle<()>,
+}
+
+impl Drop for UniverseSchedule {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -0,0 +1,247 @@
+use crate::{
+    datastructures::order::OrderStatus,
+    http::{HttpClientConfig, RetryPolicy},
+    journal::{JournalEvent, OrderJournal},
+    time,
+};
+use reqwest::Client;
+use serde_json::Value;
+use std::{sync::Arc, time::Duration};
+use tokio::{
+    sync::{broadcast::error::RecvError, mpsc},
+    task::JoinHandle,
+};
+
+/// Which events a rule fires on.
+#[derive(Clone)]
+pub enum Trigger {
+    Any,
+    OrderRejected,
+    /// Fills worth more than this, as quantity times price.
+    FillNotionalAbove(f64),
+    /// Drawdown events at or past this fraction of peak equity.
+    DrawdownAbove(f64),
+    /// Orders queued during a broker outage.
+    OrderQueued,
+    /// Deposits, withdrawals, dividends and other non-trading account activity.
+    AccountActivity,
+    Custom(Arc<dyn Fn(&JournalEvent) -> bool + Send + Sync>),
+}
+
+impl Trigger {
+    fn matches(&self, event: &JournalEvent) -> bool {
+        match (self, event) {
+            (Trigger::Any, _) => true,
+            (Trigger::OrderRejected, JournalEvent::OrderUpdate(order)) => {
+                order.status == OrderStatus::Rejected
+            }
+            (Trigger::FillNotionalAbove(threshold), JournalEvent::Fill(fill)) => {
+                (fill.quantity * fill.price).abs() > *threshold
+            }
+            (Trigger::DrawdownAbove(threshold), JournalEvent::Drawdown { drawdown, .. }) => {
+                drawdown >= threshold
+            }
+            (Trigger::OrderQueued, JournalEvent::IntentQueued(_)) => true,
+            (Trigger::AccountActivity, JournalEvent::AccountActivity(_)) => true,
+            (Trigger::Custom(matches), event) => matches(event),
+            _ => false,
+        }
+    }
+}
+
+/// Posts matching events to a URL.
+#[derive(Clone)]
+pub struct WebhookRule {
+    /// Used in logs and available to templates as `{{rule}}`.
+    pub name: String,
+    pub trigger: Trigger,
+    pub url: String,
+    /// Request body with `{{path}}` placeholders filled from the event's JSON, e.g. `{{order.symbol}}` or
+    /// `{{fill.price}}`, plus `{{rule}}`, `{{event}}` and `{{sent_at}}`. Strings are JSON-escaped without quotes so
+    /// they can go inside a JSON string; missing fields render empty. `None` posts the event as JSON.
+    pub template: Option<String>,
+    pub content_type: String,
+    /// Extra headers, e.g. for authentication.
+    pub headers: Vec<(String, String)>,
+}
+
+impl WebhookRule {
+    pub fn new(name: impl Into<String>, trigger: Trigger, url: impl Into<String>) -> Self {
+        WebhookRule {
+            name: name.into(),
+            trigger,
+            url: url.into(),
+            template: None,
+            content_type: "application/json".to_string(),
+            headers: vec![],
+        }
+    }
+
+    pub fn template(mut self, template: impl Into<String>) -> Self {
+        self.template = Some(template.into());
+        self
+    }
+
+    pub fn content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = content_type.into();
+        self
+    }
+
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    fn render(&self, event: &JournalEvent) -> String {
+        let value = serde_json::to_value(event).unwrap_or_default();
+        let Some(template) = &self.template else {
+            return value.to_string();
+        };
+
+        let mut rendered = String::with_capacity(template.len());
+        let mut rest = template.as_str();
+        while let Some(open) = rest.find("{{") {
+            let Some(close) = rest[open..].find("}}") else {
+                break;
+            };
+            rendered.push_str(&rest[..open]);
+            let path = rest[open + 2..open + close].trim();
+            let field = match path {
+                "rule" => Some(Value::String(self.name.clone())),
+                "sent_at" => Some(Value::String(time::format_rfc3339(time::now_nanos()))),
+                path => path
+                    .split('.')
+                    .try_fold(&value, |value, key| value.get(key))
+                    .cloned(),
+            };
+            match field {
+                Some(Value::String(text)) => {
+                    let quoted = Value::String(text).to_string();
+                    rendered.push_str(&quoted[1..quoted.len() - 1]);
+                }
+                Some(Value::Null) | None => {}
+                Some(other) => rendered.push_str(&other.to_string()),
+            }
+            rest = &rest[open + close + 2..];
+        }
+        rendered.push_str(rest);
+        rendered
+    }
+}
+
+#[derive(Clone)]
+pub struct WebhookConfig {
+    pub rules: Vec<WebhookRule>,
+    /// Retries after 429s, 5xx responses and transport errors.
+    pub retry: RetryPolicy,
+    pub http: HttpClientConfig,
+    /// Events waiting to be matched. Events published while it is full are dropped with a warning.
+    pub queue_capacity: usize,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        WebhookConfig {
+            rules: vec![],
+            retry: RetryPolicy::default(),
+            http: HttpClientConfig {
+                request_timeout: Some(Duration::from_secs(10)),
+                ..HttpClientConfig::default()
+            },
+            queue_capacity: 1024,
+        }
+    }
+}
+
+/// Posts events matching its rules to webhooks, so systems the crate knows nothing about can react to orders, fills
+/// and alerts. Deliveries run in the background and are not ordered relative to each other. Stops when dropped.
+pub struct WebhookEngine {
+    sender: mpsc::Sender<JournalEvent>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl WebhookEngine {
+    pub fn spawn(config: WebhookConfig) -> Result<Self, reqwest::Error> {
+        let (sender, mut receiver) = mpsc::channel(config.queue_capacity.max(1));
+        let client = config.http.build_client()?;
+        let rules = Arc::new(config.rules);
+        let retry = config.retry;
+
+        let dispatcher = tokio::spawn(async move {
+            while let Some(event) = receiver.recv().await {
+                for rule in rules.iter().filter(|rule| rule.trigger.matches(&event)) {
+                    let body = rule.render(&event);
+                    tokio::spawn(deliver(client.clone(), rule.clone(), body, retry));
+                }
+            }
+        });
+
+        Ok(WebhookEngine {
+            sender,
+            tasks: vec![dispatcher],
+        })
+    }
+
+    /// Queues an event for matching without waiting.
+    pub fn publish(&self, event: JournalEvent) {
+        if self.sender.try_send(event).is_err() {
+            tracing::warn!("Webhook queue full; dropping event");
+        }
+    }
+
+    /// Matches every event the journal publishes from now on.
+    pub fn watch(&mut self, journal: &OrderJournal) {
+        let mut events = journal.subscribe();
+        let sender = self.sender.clone();
+        self.tasks.push(tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => {
+                        if sender.send(event).await.is_err() {
+                            return;
+                        }
+                    }
+                    Err(RecvError::Lagged(missed)) => {
+                        tracing::warn!(missed, "Webhook engine fell behind the journal")
+                    }
+                    Err(RecvError::Closed) => return,
+                }
+            }
+        }));
+    }
+}
+
+impl Drop for WebhookEngine {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+async fn deliver(client: Client, rule: WebhookRule, body: String, retry: RetryPolicy) {
+    for attempt in 1..=retry.max_attempts.max(1) {
+        let mut request = client
+            .post(&rule.url)
+            .header("Content-Type", &rule.content_type)
+            .body(body.clone());
+        for (name, value) in &rule.headers {
+            request = request.header(name, value);
+        }
+
+        let error = match request.send().await {
+            Ok(response) if response.status().is_success() => return,
+            Ok(response) if !RetryPolicy::is_retryable(response.status()) => {
+                tracing::warn!(rule = %rule.name, status = %response.status(), "Webhook rejected");
+                return;
+            }
+            Ok(response) => response.status().to_string(),
+            Err(e) => e.to_string(),
+        };
+        if attempt == retry.max_attempts.max(1) {
+            tracing::error!(rule = %rule.name, %error, attempts = attempt, "Webhook delivery failed");
+            return;
+        }
+        tokio::time::sleep(retry.backoff(attempt)).await;
+    }
+}
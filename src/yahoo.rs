@@ -0,0 +1,419 @@
+use crate::{
+    datastructures::{
+        config::Config,
+        corporate_action::CorporateAction,
+        market::{Bar, BarAdjustment},
+    },
+    http::{HttpClientConfig, RestPolicies},
+    time,
+};
+use reqwest::Client as HttpClient;
+use serde::Deserialize;
+use std::{collections::HashMap, error::Error, fmt, time::Duration};
+
+/// Unofficial and undocumented; the endpoint the finance.yahoo.com charts use.
+const CHART_URL: &str = "https://query1.finance.yahoo.com/v8/finance/chart";
+/// Yahoo rejects requests without a browser-like user agent.
+const USER_AGENT: &str = "Mozilla/5.0 (compatible; trading-client)";
+/// Intraday intervals Yahoo serves, in minutes.
+const INTRADAY_MINUTES: [u64; 7] = [1, 2, 5, 15, 30, 60, 90];
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Error response of the Yahoo Finance chart endpoint.
+#[derive(Debug, Clone)]
+pub struct YahooError {
+    pub status: u16,
+    pub code: String,
+    pub description: String,
+}
+
+impl fmt::Display for YahooError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Yahoo error {} ({}): {}",
+            self.code, self.status, self.description
+        )
+    }
+}
+
+impl Error for YahooError {}
+
+#[derive(Deserialize)]
+struct RawResponse {
+    chart: RawChart,
+}
+
+#[derive(Deserialize)]
+struct RawChart {
+    #[serde(default)]
+    result: Option<Vec<RawResult>>,
+    #[serde(default)]
+    error: Option<RawError>,
+}
+
+#[derive(Deserialize)]
+struct RawError {
+    #[serde(default)]
+    code: String,
+    #[serde(default)]
+    description: String,
+}
+
+#[derive(Deserialize)]
+struct RawResult {
+    meta: RawMeta,
+    #[serde(default)]
+    timestamp: Vec<i64>,
+    indicators: RawIndicators,
+    #[serde(default)]
+    events: RawEvents,
+}
+
+#[derive(Deserialize)]
+struct RawMeta {
+    /// Offset of the exchange's time zone from UTC, in seconds.
+    #[serde(default)]
+    gmtoffset: i64,
+}
+
+/// Prices as parallel arrays, with nulls where the symbol did not trade.
+#[derive(Deserialize)]
+struct RawIndicators {
+    quote: Vec<RawQuote>,
+    #[serde(default)]
+    adjclose: Vec<RawAdjClose>,
+}
+
+#[derive(Deserialize)]
+struct RawQuote {
+    #[serde(default)]
+    open: Vec<Option<f64>>,
+    #[serde(default)]
+    high: Vec<Option<f64>>,
+    #[serde(default)]
+    low: Vec<Option<f64>>,
+    #[serde(default)]
+    close: Vec<Option<f64>>,
+    #[serde(default)]
+    volume: Vec<Option<f64>>,
+}
+
+#[derive(Deserialize)]
+struct RawAdjClose {
+    #[serde(default)]
+    adjclose: Vec<Option<f64>>,
+}
+
+/// Keyed by the event's time in seconds.
+#[derive(Default, Deserialize)]
+struct RawEvents {
+    #[serde(default)]
+    dividends: HashMap<String, RawDividend>,
+    #[serde(default)]
+    splits: HashMap<String, RawSplit>,
+}
+
+#[derive(Deserialize)]
+struct RawDividend {
+    amount: f64,
+    date: i64,
+}
+
+#[derive(Deserialize)]
+struct RawSplit {
+    numerator: f64,
+    denominator: f64,
+    date: i64,
+}
+
+/// Symbol as Yahoo writes it: "BTC-USD" for the crypto pair "BTC/USD", and share classes with a dash, e.g.
+/// "BRK-B" for "BRK.B".
+fn yahoo_symbol(symbol: &str) -> String {
+    symbol.replace(['/', '.'], "-")
+}
+
+fn day_seconds(date: &str) -> Result<i64, String> {
+    time::parse_rfc3339(&format!("{}T00:00:00Z", date))
+        .map(|nanos| nanos / NANOS_PER_SECOND)
+        .ok_or_else(|| format!("Not a YYYY-MM-DD date: {}", date))
+}
+
+/// Date of an instant on the exchange's calendar, which is what Yahoo's event and daily times mean.
+fn exchange_date(seconds: i64, gmtoffset: i64) -> String {
+    time::date(&time::format_rfc3339(
+        (seconds + gmtoffset) * NANOS_PER_SECOND,
+    ))
+    .unwrap_or_default()
+}
+
+impl RawResult {
+    /// Splits and dividends in the chart, unsorted.
+    fn corporate_actions(&self, symbol: &str) -> Vec<CorporateAction> {
+        let offset = self.meta.gmtoffset;
+        let splits = self
+            .events
+            .splits
+            .values()
+            .filter(|split| split.denominator > 0.0);
+        let dividends = self.events.dividends.values();
+        splits
+            .map(|split| CorporateAction::Split {
+                symbol: symbol.to_string(),
+                ex_date: exchange_date(split.date, offset),
+                ratio: split.numerator / split.denominator,
+            })
+            .chain(dividends.map(|dividend| CorporateAction::CashDividend {
+                symbol: symbol.to_string(),
+                ex_date: exchange_date(dividend.date, offset),
+                amount: dividend.amount,
+            }))
+            .collect()
+    }
+
+    /// Bars with every price present, oldest first, each with its adjusted close if the chart has them. Daily
+    /// bars are stamped with midnight UTC of their trading day, intraday bars with their start.
+    fn bars(&self, symbol: &str, daily: bool) -> Vec<(Bar, Option<f64>)> {
+        let Some(quote) = self.indicators.quote.first() else {
+            return vec![];
+        };
+        let adjclose = self.indicators.adjclose.first();
+        let field = |values: &[Option<f64>], i: usize| values.get(i).copied().flatten();
+        self.timestamp
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &seconds)| {
+                let timestamp = if daily {
+                    format!("{}T00:00:00Z", exchange_date(seconds, self.meta.gmtoffset))
+                } else {
+                    time::format_rfc3339(seconds * NANOS_PER_SECOND)
+                };
+                let bar = Bar {
+                    symbol: symbol.to_string(),
+                    open: field(&quote.open, i)?,
+                    high: field(&quote.high, i)?,
+                    low: field(&quote.low, i)?,
+                    close: field(&quote.close, i)?,
+                    volume: field(&quote.volume, i).unwrap_or_default(),
+                    timestamp,
+                };
+                Some((bar, adjclose.and_then(|adj| field(&adj.adjclose, i))))
+            })
+            .collect()
+    }
+}
+
+/// Historical bars from Yahoo Finance's public chart endpoint. Needs no account or keys, so backtests can run
+/// before signing up with a broker. The endpoint is unofficial: it may change or throttle without notice, and
+/// its data is for personal use. Stocks are written as Alpaca writes them, e.g. "BRK.B", and crypto as pairs,
+/// e.g. "BTC/USD".
+#[derive(Clone)]
+pub struct YahooClient {
+    http_client: HttpClient,
+    rest: RestPolicies,
+}
+
+impl YahooClient {
+    /// Client with reqwest's default HTTP settings and no rate limiting, retries or circuit breaker.
+    pub fn new() -> Result<Self, reqwest::Error> {
+        Ok(YahooClient {
+            http_client: HttpClientConfig {
+                user_agent: Some(USER_AGENT.to_string()),
+                ..Default::default()
+            }
+            .build_client()?,
+            rest: RestPolicies::default(),
+        })
+    }
+
+    /// Applies the config's HTTP settings and REST policies. Its keys are not used.
+    pub fn with_config(config: &Config) -> Result<Self, reqwest::Error> {
+        let mut http = config.http.clone();
+        http.user_agent
+            .get_or_insert_with(|| USER_AGENT.to_string());
+        Ok(YahooClient {
+            http_client: http.build_client()?,
+            rest: RestPolicies::new(config),
+        })
+    }
+
+    /// The chart of `symbol` at `interval`, e.g. "1d" or "5m", from `start` to `end` in seconds since the epoch,
+    /// with its splits and dividends. Every call is a read, so all are retried.
+    async fn chart(
+        &self,
+        symbol: &str,
+        start: i64,
+        end: i64,
+        interval: &str,
+    ) -> Result<RawResult, Box<dyn Error>> {
+        let url = format!("{}/{}", CHART_URL, yahoo_symbol(symbol));
+        let query = [
+            ("period1", start.to_string()),
+            ("period2", end.to_string()),
+            ("interval", interval.to_string()),
+            ("events", "div,split".to_string()),
+            ("includeAdjustedClose", "true".to_string()),
+        ];
+        let build = || Ok(self.http_client.get(&url).query(&query).build()?);
+        let response = self.rest.send(&self.http_client, true, build).await?;
+        let status = response.status();
+        let body = response.text().await?;
+        let chart = match serde_json::from_str::<RawResponse>(&body) {
+            Ok(response) => response.chart,
+            Err(_) if !status.is_success() => {
+                return Err(YahooError {
+                    status: status.as_u16(),
+                    code: String::new(),
+                    description: body,
+                }
+                .into())
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if let Some(error) = chart.error {
+            return Err(YahooError {
+                status: status.as_u16(),
+                code: error.code,
+                description: error.description,
+            }
+            .into());
+        }
+        chart
+            .result
+            .and_then(|results| results.into_iter().next())
+            .ok_or_else(|| format!("No chart for {}", symbol).into())
+    }
+
+    /// Daily bars for the trading days from `start` to `end` inclusive, as YYYY-MM-DD dates, oldest first.
+    /// Yahoo serves prices adjusted for splits, with a close also adjusted for dividends; raw bars are unadjusted
+    /// locally from its splits, and total return bars scaled by its adjusted close.
+    pub async fn get_daily_bars(
+        &self,
+        symbol: &str,
+        start: &str,
+        end: &str,
+        adjustment: BarAdjustment,
+    ) -> Result<Vec<Bar>, Box<dyn Error>> {
+        let start_seconds = day_seconds(start)?;
+        let end_seconds = day_seconds(end)? + SECONDS_PER_DAY;
+        // Splits after `end` restate the bars too.
+        let through = match adjustment {
+            BarAdjustment::Raw => (time::now_nanos() / NANOS_PER_SECOND).max(end_seconds),
+            BarAdjustment::Split | BarAdjustment::TotalReturn => end_seconds,
+        };
+        let chart = self.chart(symbol, start_seconds, through, "1d").await?;
+        let in_range =
+            |bar: &Bar| time::date(&bar.timestamp).is_some_and(|date| date.as_str() <= end);
+        let mut bars: Vec<Bar> = match adjustment {
+            BarAdjustment::Raw | BarAdjustment::Split => chart
+                .bars(symbol, true)
+                .into_iter()
+                .map(|(bar, _)| bar)
+                .collect(),
+            BarAdjustment::TotalReturn => chart
+                .bars(symbol, true)
+                .into_iter()
+                .map(|(mut bar, adjclose)| {
+                    let factor = adjclose
+                        .filter(|_| bar.close > 0.0)
+                        .map_or(1.0, |adj| adj / bar.close);
+                    bar.open *= factor;
+                    bar.high *= factor;
+                    bar.low *= factor;
+                    bar.close *= factor;
+                    bar
+                })
+                .collect(),
+        };
+        if adjustment == BarAdjustment::Raw {
+            // Adjusting for the inverse of every split restores the prices as traded.
+            let reversed: Vec<CorporateAction> = chart
+                .corporate_actions(symbol)
+                .into_iter()
+                .filter_map(|action| match action {
+                    CorporateAction::Split {
+                        symbol,
+                        ex_date,
+                        ratio,
+                    } => Some(CorporateAction::Split {
+                        symbol,
+                        ex_date,
+                        ratio: 1.0 / ratio,
+                    }),
+                    CorporateAction::CashDividend { .. } => None,
+                })
+                .collect();
+            BarAdjustment::Split.apply(&mut bars, &reversed);
+        }
+        bars.retain(in_range);
+        Ok(bars)
+    }
+
+    /// Intraday bars of `interval`, one of 1, 2, 5, 15, 30, 60 or 90 minutes, from `start` to `end` inclusive,
+    /// as YYYY-MM-DD dates, oldest first and stamped with their start. Yahoo serves minute bars for the last 30
+    /// days, a week per request, and longer intervals for the last 60, adjusted for splits.
+    pub async fn get_intraday_bars(
+        &self,
+        symbol: &str,
+        start: &str,
+        end: &str,
+        interval: Duration,
+    ) -> Result<Vec<Bar>, Box<dyn Error>> {
+        let minutes = interval.as_secs() / 60;
+        if !interval.as_secs().is_multiple_of(60) || !INTRADAY_MINUTES.contains(&minutes) {
+            return Err("Yahoo serves intraday bars of 1, 2, 5, 15, 30, 60 or 90 minutes".into());
+        }
+        // Minute bars come at most a week at a time.
+        let days_per_request = if minutes == 1 { 7 } else { 60 };
+
+        let last = day_seconds(end)? + SECONDS_PER_DAY;
+        let mut from = day_seconds(start)?;
+        let mut bars: Vec<Bar> = Vec::new();
+        while from < last {
+            let to = (from + days_per_request * SECONDS_PER_DAY).min(last);
+            let chart = self
+                .chart(symbol, from, to, &format!("{}m", minutes))
+                .await?;
+            let newest = bars.last().map(|bar| bar.timestamp.clone());
+            bars.extend(
+                chart
+                    .bars(symbol, false)
+                    .into_iter()
+                    .map(|(bar, _)| bar)
+                    .filter(|bar| newest.as_ref().is_none_or(|newest| bar.timestamp > *newest)),
+            );
+            from = to;
+        }
+        Ok(bars)
+    }
+
+    /// Splits and cash dividends with an ex-date from `start` to `end` inclusive, as YYYY-MM-DD dates, sorted by
+    /// ex-date. Meant for `BacktestConfig::corporate_actions`.
+    pub async fn get_corporate_actions(
+        &self,
+        symbols: &[&str],
+        start: &str,
+        end: &str,
+    ) -> Result<Vec<CorporateAction>, Box<dyn Error>> {
+        let mut actions = Vec::new();
+        for symbol in symbols {
+            let chart = self
+                .chart(
+                    symbol,
+                    day_seconds(start)?,
+                    day_seconds(end)? + SECONDS_PER_DAY,
+                    "1d",
+                )
+                .await?;
+            actions.extend(
+                chart
+                    .corporate_actions(symbol)
+                    .into_iter()
+                    .filter(|action| action.ex_date() >= start && action.ex_date() <= end),
+            );
+        }
+        actions.sort_by(|a, b| a.ex_date().cmp(b.ex_date()));
+        Ok(actions)
+    }
+}